@@ -1,6 +1,9 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use speicherwald::db;
-use speicherwald::scanner::run_scan;
+use speicherwald::metrics::Metrics;
+use speicherwald::scanner::{run_scan, Reuse};
 use speicherwald::types::ScanOptions;
 use sqlx::sqlite::SqlitePoolOptions;
 use std::fs;
@@ -59,6 +62,9 @@ fn benchmark_small_tree(c: &mut Criterion) {
                     excludes: vec![],
                     max_depth: None,
                     concurrency: Some(4),
+                    measure_hardlinks: false,
+                    measure_ads: false,
+                    ..Default::default()
                 };
 
                 let pool =
@@ -68,8 +74,27 @@ fn benchmark_small_tree(c: &mut Criterion) {
                 let (tx, _rx) = broadcast::channel(32);
                 let cancel = CancellationToken::new();
                 black_box(
-                    run_scan(pool, id, vec![path.clone()], options, tx, cancel, 256, 512, 100, None, Some(4))
-                        .await,
+                    run_scan(
+                        pool,
+                        id,
+                        vec![path.clone()],
+                        options,
+                        tx,
+                        cancel,
+                        Default::default(),
+                        256,
+                        512,
+                        64 << 20,
+                        100,
+                        None,
+                        Some(4),
+                        None,
+                        Default::default(),
+                        Reuse::Nothing,
+                        &Metrics::default(),
+                        &Default::default(),
+                    )
+                    .await,
                 )
             })
         })
@@ -92,6 +117,9 @@ fn benchmark_large_tree(c: &mut Criterion) {
                     excludes: vec![],
                     max_depth: None,
                     concurrency: Some(8),
+                    measure_hardlinks: false,
+                    measure_ads: false,
+                    ..Default::default()
                 };
 
                 let pool =
@@ -101,8 +129,27 @@ fn benchmark_large_tree(c: &mut Criterion) {
                 let (tx, _rx) = broadcast::channel(32);
                 let cancel = CancellationToken::new();
                 black_box(
-                    run_scan(pool, id, vec![path.clone()], options, tx, cancel, 256, 512, 100, None, Some(8))
-                        .await,
+                    run_scan(
+                        pool,
+                        id,
+                        vec![path.clone()],
+                        options,
+                        tx,
+                        cancel,
+                        Default::default(),
+                        256,
+                        512,
+                        64 << 20,
+                        100,
+                        None,
+                        Some(8),
+                        None,
+                        Default::default(),
+                        Reuse::Nothing,
+                        &Metrics::default(),
+                        &Default::default(),
+                    )
+                    .await,
                 )
             })
         })
@@ -128,6 +175,9 @@ fn benchmark_concurrency(c: &mut Criterion) {
                         excludes: vec![],
                         max_depth: None,
                         concurrency: Some(concurrency),
+                        measure_hardlinks: false,
+                        measure_ads: false,
+                        ..Default::default()
                     };
                     let pool =
                         SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
@@ -143,11 +193,18 @@ fn benchmark_concurrency(c: &mut Criterion) {
                             options,
                             tx,
                             cancel,
+                            Default::default(),
                             256,
                             512,
+                            64 << 20,
                             100,
                             None,
                             Some(concurrency),
+                            None,
+                            Default::default(),
+                            Reuse::Nothing,
+                            &Metrics::default(),
+                            &Default::default(),
                         )
                         .await,
                     )
@@ -176,6 +233,9 @@ fn benchmark_exclude_patterns(c: &mut Criterion) {
                     excludes: vec![],
                     max_depth: None,
                     concurrency: Some(4),
+                    measure_hardlinks: false,
+                    measure_ads: false,
+                    ..Default::default()
                 };
                 let pool =
                     SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
@@ -184,8 +244,27 @@ fn benchmark_exclude_patterns(c: &mut Criterion) {
                 let (tx, _rx) = broadcast::channel(32);
                 let cancel = CancellationToken::new();
                 black_box(
-                    run_scan(pool, id, vec![path.clone()], options, tx, cancel, 256, 512, 100, None, Some(4))
-                        .await,
+                    run_scan(
+                        pool,
+                        id,
+                        vec![path.clone()],
+                        options,
+                        tx,
+                        cancel,
+                        Default::default(),
+                        256,
+                        512,
+                        64 << 20,
+                        100,
+                        None,
+                        Some(4),
+                        None,
+                        Default::default(),
+                        Reuse::Nothing,
+                        &Metrics::default(),
+                        &Default::default(),
+                    )
+                    .await,
                 )
             })
         })
@@ -202,6 +281,9 @@ fn benchmark_exclude_patterns(c: &mut Criterion) {
                     excludes: vec!["**/dir_1/**".to_string(), "**/file_5.txt".to_string()],
                     max_depth: None,
                     concurrency: Some(4),
+                    measure_hardlinks: false,
+                    measure_ads: false,
+                    ..Default::default()
                 };
                 let pool =
                     SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
@@ -210,8 +292,27 @@ fn benchmark_exclude_patterns(c: &mut Criterion) {
                 let (tx, _rx) = broadcast::channel(32);
                 let cancel = CancellationToken::new();
                 black_box(
-                    run_scan(pool, id, vec![path.clone()], options, tx, cancel, 256, 512, 100, None, Some(4))
-                        .await,
+                    run_scan(
+                        pool,
+                        id,
+                        vec![path.clone()],
+                        options,
+                        tx,
+                        cancel,
+                        Default::default(),
+                        256,
+                        512,
+                        64 << 20,
+                        100,
+                        None,
+                        Some(4),
+                        None,
+                        Default::default(),
+                        Reuse::Nothing,
+                        &Metrics::default(),
+                        &Default::default(),
+                    )
+                    .await,
                 )
             })
         })
@@ -0,0 +1,128 @@
+//! Errors returned by the client.
+
+use serde::Deserialize;
+
+/// A convenient type alias for `Result<T, Error>`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error talking to the SpeicherWald API.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The server answered with an error status.
+    ///
+    /// `code` and `message` come from the structured error body
+    /// (`{"error": {"code", "message", "details"}}`); bodies in any other shape
+    /// are reported with the code `HTTP_<status>` and the raw body as message.
+    #[error("API error {status} {code}: {message}")]
+    Api {
+        /// The HTTP status code.
+        status: u16,
+        /// The machine-readable error code, e.g. `NOT_FOUND` or `RATE_LIMITED`.
+        code: String,
+        /// The human-readable error message.
+        message: String,
+        /// Additional details, e.g. `retry_after_seconds` for rate limits.
+        details: Option<serde_json::Value>,
+    },
+    /// The base URL or a path could not be turned into a request URL.
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    /// The request failed before a response arrived, or the response body broke off.
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// A response or event body did not match the expected type.
+    #[error("Invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// Writing an export to the destination failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// Returns the HTTP status for `Api` errors.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the server reported the resource as missing.
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// Returns the number of seconds to wait before retrying a rate-limited request.
+    pub fn retry_after_seconds(&self) -> Option<u64> {
+        match self {
+            Error::Api { details: Some(details), .. } => details.get("retry_after_seconds")?.as_u64(),
+            _ => None,
+        }
+    }
+
+    /// Builds an `Api` error from an error response.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status code of the response.
+    /// * `body` - The response body.
+    pub(crate) fn from_response_body(status: u16, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct Body {
+            error: Detail,
+            /// The rate limiter puts this next to `error` instead of into `details`
+            retry_after_seconds: Option<u64>,
+        }
+        #[derive(Deserialize)]
+        struct Detail {
+            code: String,
+            message: String,
+            details: Option<serde_json::Value>,
+        }
+
+        match serde_json::from_str::<Body>(body) {
+            Ok(b) => {
+                let details = match (b.error.details, b.retry_after_seconds) {
+                    (None, Some(secs)) => Some(serde_json::json!({ "retry_after_seconds": secs })),
+                    (details, _) => details,
+                };
+                Error::Api { status, code: b.error.code, message: b.error.message, details }
+            }
+            Err(_) => Error::Api {
+                status,
+                code: format!("HTTP_{}", status),
+                message: body.trim().to_string(),
+                details: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_structured_and_rate_limit_bodies() {
+        let err = Error::from_response_body(
+            404,
+            r#"{"error":{"code":"NOT_FOUND","message":"scan not found"},"status":404,"timestamp":"x"}"#,
+        );
+        assert!(err.is_not_found());
+        assert!(
+            matches!(&err, Error::Api { code, message, .. } if code == "NOT_FOUND" && message == "scan not found")
+        );
+
+        let err = Error::from_response_body(
+            429,
+            r#"{"error":{"code":"RATE_LIMITED","message":"slow down"},"retry_after_seconds":7,"status":429}"#,
+        );
+        assert_eq!(err.retry_after_seconds(), Some(7));
+
+        let err = Error::from_response_body(502, "Bad Gateway");
+        assert!(
+            matches!(&err, Error::Api { code, message, .. } if code == "HTTP_502" && message == "Bad Gateway")
+        );
+    }
+}
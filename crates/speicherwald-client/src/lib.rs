@@ -0,0 +1,538 @@
+//! # SpeicherWald API Client
+//!
+//! An async client for the SpeicherWald HTTP API. Requests and responses use
+//! the DTOs of `speicherwald-types`, the same types the backend serializes, so
+//! the client cannot drift from the server.
+//!
+//! ```no_run
+//! # async fn run() -> speicherwald_client::Result<()> {
+//! use futures::StreamExt;
+//! use speicherwald_client::{Client, CreateScanRequest};
+//!
+//! let client = Client::builder("http://127.0.0.1:8080").token("secret").build()?;
+//! let scan = client
+//!     .create_scan(&CreateScanRequest { root_paths: vec!["D:\\".into()], ..Default::default() })
+//!     .await?;
+//! let mut events = client.events(scan.id).await?;
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+mod sse;
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use reqwest::{Method, RequestBuilder, Response, Url};
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+pub use error::{Error, Result};
+pub use speicherwald_types::*;
+pub use sse::EventStream;
+
+/// The request header that selects the namespace.
+const NAMESPACE_HEADER: &str = "x-speicherwald-namespace";
+
+/// The default timeout of a non-streaming request.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Builder for a [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    /// The base URL of the backend, e.g. `http://127.0.0.1:8080`
+    base_url: String,
+    /// The bearer token sent with every request
+    token: Option<String>,
+    /// The namespace sent with every request
+    namespace: Option<String>,
+    /// The timeout of non-streaming requests
+    timeout: Duration,
+    /// The timeout for establishing connections
+    connect_timeout: Option<Duration>,
+}
+
+impl ClientBuilder {
+    /// Sets the bearer token for servers started with `SPEICHERWALD_AUTH_TOKEN`.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Sets the namespace requests operate in (`X-Speicherwald-Namespace`).
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Sets the timeout of non-streaming requests (default 30 s).
+    ///
+    /// Event streams and exports are not limited, they last as long as the
+    /// server keeps sending.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the timeout for establishing connections, for all requests.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Client>` - The client, or `InvalidUrl` if the base URL is not an
+    ///   absolute `http`/`https` URL.
+    pub fn build(self) -> Result<Client> {
+        let mut base = Url::parse(self.base_url.trim())
+            .map_err(|e| Error::InvalidUrl(format!("{}: {}", self.base_url, e)))?;
+        if !matches!(base.scheme(), "http" | "https") || base.cannot_be_a_base() {
+            return Err(Error::InvalidUrl(format!("{}: not an http(s) base URL", self.base_url)));
+        }
+        // Keep a path prefix such as `/speicherwald` when joining endpoint paths
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let mut http = reqwest::Client::builder();
+        if let Some(t) = self.connect_timeout {
+            http = http.connect_timeout(t);
+        }
+        Ok(Client {
+            http: http.build()?,
+            base,
+            token: self.token,
+            namespace: self.namespace,
+            timeout: self.timeout,
+        })
+    }
+}
+
+/// An async client for the SpeicherWald API.
+///
+/// Cloning is cheap and shares the connection pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// The underlying HTTP client
+    http: reqwest::Client,
+    /// The base URL, always ending with `/`
+    base: Url,
+    /// The bearer token sent with every request
+    token: Option<String>,
+    /// The namespace sent with every request
+    namespace: Option<String>,
+    /// The timeout of non-streaming requests
+    timeout: Duration,
+}
+
+/// Options of [`Client::cancel_scan`].
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CancelOptions {
+    /// Delete the scan and all its data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purge: Option<bool>,
+    /// Keep the records persisted so far as a `partial` scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalize: Option<bool>,
+}
+
+/// Parameters of [`Client::tree`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TreeParams {
+    /// The directory to start from; the scan roots if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// How many levels below `path` to return.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<i64>,
+    /// `size` or `name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// The maximum number of nodes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+/// Parameters of [`Client::list`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListParams {
+    /// The directory to list; the scan roots if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// `allocated`, `logical`, `name`, `name_natural` (numbers by value) or `type`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// `asc` or `desc`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    /// The maximum number of items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The number of items to skip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    /// The minimum allocated size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<i64>,
+    /// The maximum allocated size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<i64>,
+    /// Only items modified at or after this Unix timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_after: Option<i64>,
+    /// Only items modified at or before this Unix timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_before: Option<i64>,
+    /// `dirs`, `files` or `all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// Parameters of [`Client::node`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NodeParams {
+    /// The directory to get.
+    pub path: String,
+    /// Whether to return the other directories in the same parent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub with_siblings: Option<bool>,
+    /// The maximum number of siblings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+/// Parameters of [`Client::top`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TopParams {
+    /// `dirs` or `files`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The maximum number of items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+/// Parameters of [`Client::warnings`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WarningsParams {
+    /// Only return warnings with this code, e.g. `read_dir_failed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The maximum number of warnings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The number of warnings to skip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// Parameters of [`Client::search`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchParams {
+    /// A substring of the path; left out if empty.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub query: String,
+    /// A substring of the name, or a glob the whole name must match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// The maximum number of results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The number of results to skip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    /// The minimum allocated size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<i64>,
+    /// The maximum allocated size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<i64>,
+    /// Only items modified at or after this time (seconds since the Unix epoch).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_after: Option<i64>,
+    /// Only items modified at or before this time (seconds since the Unix epoch).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_before: Option<i64>,
+    /// `file`, `dir` or `all` (default).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Comma-separated file extensions such as `.log,.tmp`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ext: Option<String>,
+    /// A file extension such as `pdf`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    /// Whether to include files (default true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_files: Option<bool>,
+    /// Whether to include directories (default true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_dirs: Option<bool>,
+    /// `allocated` (default), `logical`, `mtime`, `name` or `path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// `desc` (default) or `asc`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+}
+
+/// Parameters of [`Client::search_all`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GlobalSearchParams {
+    /// The search term.
+    pub q: String,
+    /// The scans to search; the latest scan per set of roots if empty.
+    #[serde(serialize_with = "join_ids", skip_serializing_if = "Vec::is_empty")]
+    pub scans: Vec<Uuid>,
+    /// The maximum number of results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The number of results to skip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    /// Whether to include files (default true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_files: Option<bool>,
+    /// Whether to include directories (default true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_dirs: Option<bool>,
+}
+
+/// Serializes scan IDs as the comma-separated list the server expects.
+fn join_ids<S: Serializer>(ids: &[Uuid], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(","))
+}
+
+/// Parameters of [`Client::export`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportParams {
+    /// `csv`, `json` or `ndjson`.
+    pub format: String,
+    /// `nodes`, `files` or `all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The maximum number of records.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+impl Default for ExportParams {
+    fn default() -> Self {
+        Self { format: "csv".to_string(), scope: None, limit: None }
+    }
+}
+
+impl Client {
+    /// Starts building a client for the backend at `base_url`.
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            token: None,
+            namespace: None,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: None,
+        }
+    }
+
+    /// Creates a client with default settings.
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::builder(base_url).build()
+    }
+
+    /// Starts a scan (`POST /scans`).
+    pub async fn create_scan(&self, req: &CreateScanRequest) -> Result<CreateScanResponse> {
+        self.json(self.request(Method::POST, "scans")?.json(req)).await
+    }
+
+    /// Estimates how many directories and files a scan would find without
+    /// running it (`POST /scans/estimate`).
+    pub async fn estimate_scan(&self, req: &CreateScanRequest) -> Result<ScanEstimate> {
+        self.json(self.request(Method::POST, "scans/estimate")?.json(req)).await
+    }
+
+    /// Lists the scans of the namespace, newest first (`GET /scans`).
+    pub async fn list_scans(&self) -> Result<Vec<ScanSummary>> {
+        self.json(self.request(Method::GET, "scans")?).await
+    }
+
+    /// Gets a scan (`GET /scans/{id}`).
+    pub async fn get_scan(&self, id: Uuid) -> Result<ScanSummary> {
+        self.json(self.request(Method::GET, &format!("scans/{}", id))?).await
+    }
+
+    /// Gets the manifest of a scan: summary, options, roots with their drives
+    /// and the largest directories (`GET /scans/{id}/manifest`).
+    pub async fn manifest(&self, id: Uuid) -> Result<ScanManifest> {
+        self.json(self.request(Method::GET, &format!("scans/{}/manifest", id))?).await
+    }
+
+    /// Cancels a running scan, optionally purging or finalizing it (`DELETE /scans/{id}`).
+    pub async fn cancel_scan(&self, id: Uuid, options: CancelOptions) -> Result<()> {
+        self.send(self.request(Method::DELETE, &format!("scans/{}", id))?.query(&options)).await?;
+        Ok(())
+    }
+
+    /// Gets the directory tree of a scan (`GET /scans/{id}/tree`).
+    pub async fn tree(&self, id: Uuid, params: &TreeParams) -> Result<Vec<NodeDto>> {
+        self.json(self.request(Method::GET, &format!("scans/{}/tree", id))?.query(params)).await
+    }
+
+    /// Lists the children of a directory (`GET /scans/{id}/list`).
+    pub async fn list(&self, id: Uuid, params: &ListParams) -> Result<Vec<ListItem>> {
+        self.json(self.request(Method::GET, &format!("scans/{}/list", id))?.query(params)).await
+    }
+
+    /// Lists a page like [`Client::list`] together with the count and sizes of
+    /// all matching items (`GET /scans/{id}/list?with_totals=true`).
+    pub async fn list_with_totals(&self, id: Uuid, params: &ListParams) -> Result<ListResponse> {
+        let req = self.request(Method::GET, &format!("scans/{}/list", id))?.query(params);
+        self.json(req.query(&[("with_totals", "true")])).await
+    }
+
+    /// Gets a directory with its ancestors and optionally its siblings (`GET /scans/{id}/node`).
+    ///
+    /// A missing directory is answered with a `NOT_FOUND` error whose details
+    /// name the `nearest_ancestor` that was scanned.
+    pub async fn node(&self, id: Uuid, params: &NodeParams) -> Result<NodeContextDto> {
+        self.json(self.request(Method::GET, &format!("scans/{}/node", id))?.query(params)).await
+    }
+
+    /// Gets the largest directories or files (`GET /scans/{id}/top`).
+    pub async fn top(&self, id: Uuid, params: &TopParams) -> Result<Vec<TopItem>> {
+        self.json(self.request(Method::GET, &format!("scans/{}/top", id))?.query(params)).await
+    }
+
+    /// Searches one scan (`GET /scans/{id}/search`).
+    pub async fn search(&self, id: Uuid, params: &SearchParams) -> Result<SearchResult> {
+        self.json(self.request(Method::GET, &format!("scans/{}/search", id))?.query(params)).await
+    }
+
+    /// Searches several scans at once (`GET /search`).
+    pub async fn search_all(&self, params: &GlobalSearchParams) -> Result<GlobalSearchResult> {
+        self.json(self.request(Method::GET, "search")?.query(params)).await
+    }
+
+    /// Gets the stored warnings of a scan (`GET /scans/{id}/warnings`).
+    pub async fn warnings(&self, id: Uuid, params: &WarningsParams) -> Result<ScanWarningsResponse> {
+        self.json(self.request(Method::GET, &format!("scans/{}/warnings", id))?.query(params)).await
+    }
+
+    /// Starts keeping a finished scan up to date (`POST /scans/{id}/watch`).
+    pub async fn start_watch(&self, id: Uuid) -> Result<WatchResponse> {
+        self.json(self.request(Method::POST, &format!("scans/{}/watch", id))?).await
+    }
+
+    /// Stops watching a scan (`DELETE /scans/{id}/watch`).
+    pub async fn stop_watch(&self, id: Uuid) -> Result<()> {
+        self.send(self.request(Method::DELETE, &format!("scans/{}/watch", id))?).await?;
+        Ok(())
+    }
+
+    /// Streams an export of a scan into `writer` (`GET /scans/{id}/export`).
+    ///
+    /// The export is written chunk by chunk as it arrives and never held in
+    /// memory as a whole.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64>` - The number of bytes written.
+    pub async fn export<W>(&self, id: Uuid, params: &ExportParams, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let resp = self
+            .send_streaming(self.request(Method::GET, &format!("scans/{}/export", id))?.query(params))
+            .await?;
+        let mut body = resp.bytes_stream();
+        let mut written = 0u64;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        writer.flush().await?;
+        Ok(written)
+    }
+
+    /// Subscribes to the events of a running, watched or interrupted scan
+    /// (`GET /scans/{id}/events`).
+    ///
+    /// The stream ends when the server closes it, e.g. after the final event.
+    /// Open streams also yield a [`ScanEvent::Heartbeat`] every `sse.heartbeat_secs`;
+    /// missing several in a row means the connection stalled and should be reopened.
+    pub async fn events(&self, id: Uuid) -> Result<EventStream> {
+        let req = self
+            .request(Method::GET, &format!("scans/{}/events", id))?
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+        let resp = self.send_streaming(req).await?;
+        Ok(sse::event_stream(resp))
+    }
+
+    /// Builds a request to an endpoint path relative to the base URL.
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let url = self.base.join(path).map_err(|e| Error::InvalidUrl(format!("{}: {}", path, e)))?;
+        let mut req = self.http.request(method, url);
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        if let Some(ns) = &self.namespace {
+            req = req.header(NAMESPACE_HEADER, ns);
+        }
+        Ok(req)
+    }
+
+    /// Sends a request with the request timeout and maps error responses.
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        check(req.timeout(self.timeout).send().await?).await
+    }
+
+    /// Sends a request without a total timeout, for responses that stream.
+    async fn send_streaming(&self, req: RequestBuilder) -> Result<Response> {
+        check(req.send().await?).await
+    }
+
+    /// Sends a request and decodes the JSON response body.
+    async fn json<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T> {
+        let bytes = self.send(req).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+/// Turns error statuses into [`Error::Api`].
+async fn check(resp: Response) -> Result<Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    Err(Error::from_response_body(status.as_u16(), &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url_keeps_path_prefix() {
+        let client = Client::new("http://127.0.0.1:8080/speicherwald").unwrap();
+        let req = client.request(Method::GET, "scans").unwrap().build().unwrap();
+        assert_eq!(req.url().as_str(), "http://127.0.0.1:8080/speicherwald/scans");
+        assert!(matches!(Client::new("file:///tmp"), Err(Error::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn global_search_joins_scan_ids() {
+        let a = Uuid::nil();
+        let params = GlobalSearchParams { q: "x".into(), scans: vec![a, a], ..Default::default() };
+        let client = Client::new("http://localhost").unwrap();
+        let req = client.request(Method::GET, "search").unwrap().query(&params).build().unwrap();
+        assert_eq!(req.url().query(), Some(format!("q=x&scans={}%2C{}", a, a).as_str()));
+    }
+}
@@ -0,0 +1,84 @@
+//! Decoding of the `GET /scans/{id}/events` Server-Sent Events stream.
+
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+use speicherwald_types::ScanEvent;
+
+use crate::error::{Error, Result};
+
+/// A stream of scan events, ending when the server closes the connection.
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<ScanEvent>> + Send>>;
+
+/// Splits an SSE byte stream into the `data` payloads of its events.
+///
+/// Comments such as the server's `: keep-alive` lines and events without data
+/// are skipped. Multi-line data is joined with `\n` as the SSE spec requires.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    /// Bytes received but not yet part of a complete event, without `\r`
+    buf: Vec<u8>,
+}
+
+impl SseParser {
+    /// Appends a received chunk.
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend(chunk.iter().copied().filter(|b| *b != b'\r'));
+    }
+
+    /// Returns the data of the next complete event, if one was received.
+    pub(crate) fn next_data(&mut self) -> Option<String> {
+        loop {
+            let end = self.buf.windows(2).position(|w| w == b"\n\n")?;
+            let block: Vec<u8> = self.buf.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block[..end]);
+            let data: Vec<&str> = block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|d| d.strip_prefix(' ').unwrap_or(d))
+                .collect();
+            if !data.is_empty() {
+                return Some(data.join("\n"));
+            }
+        }
+    }
+}
+
+/// Turns an SSE response into a stream of decoded scan events.
+///
+/// # Arguments
+///
+/// * `response` - A successful response of the events endpoint.
+pub(crate) fn event_stream(response: reqwest::Response) -> EventStream {
+    let bytes = response.bytes_stream().boxed();
+    futures::stream::unfold((bytes, SseParser::default()), |(mut bytes, mut parser)| async move {
+        loop {
+            if let Some(data) = parser.next_data() {
+                let event = serde_json::from_str::<ScanEvent>(&data).map_err(Error::from);
+                return Some((event, (bytes, parser)));
+            }
+            match bytes.next().await {
+                Some(Ok(chunk)) => parser.push(&chunk),
+                Some(Err(e)) => return Some((Err(Error::from(e)), (bytes, parser))),
+                None => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let mut p = SseParser::default();
+        p.push(b": keep-alive\n\nda");
+        assert_eq!(p.next_data(), None);
+        p.push(b"ta: {\"type\":\"cancelled\"}\r\n\r\ndata: a\ndata: b\n\n");
+        assert_eq!(p.next_data().as_deref(), Some(r#"{"type":"cancelled"}"#));
+        assert_eq!(p.next_data().as_deref(), Some("a\nb"));
+        assert_eq!(p.next_data(), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Drives the real backend router through the client over HTTP, so the client
+//! and the server cannot drift apart unnoticed.
+
+use std::{net::SocketAddr, time::Duration};
+
+use axum::routing::{get, post};
+use futures::StreamExt;
+use speicherwald::{config::AppConfig, routes, state::AppState};
+use speicherwald_client::{
+    CancelOptions, Client, CreateScanRequest, ExportParams, GlobalSearchParams, ListItem, ListParams,
+    ScanEvent, SearchItem, SearchParams, TopParams, TreeParams, WarningsParams,
+};
+use uuid::Uuid;
+
+struct TestServer {
+    _dir: tempfile::TempDir,
+    state: AppState,
+    base_url: String,
+    root: String,
+}
+
+async fn spawn_server() -> TestServer {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    std::fs::create_dir_all(root.join("projects").join("node_modules")).unwrap();
+    std::fs::write(root.join("projects").join("node_modules").join("lib.js"), vec![b'x'; 4096]).unwrap();
+    std::fs::write(root.join("projects").join("readme.txt"), b"hello").unwrap();
+
+    let db_url = format!("sqlite://{}?mode=rwc", dir.path().join("client.db").display());
+    let pool = speicherwald::db::connect_write_pool(&db_url, 4).await.unwrap();
+    speicherwald::db::init_db(&pool).await.unwrap();
+    let state = AppState::new(pool, AppConfig::default());
+
+    let app = axum::Router::new()
+        .route("/scans", post(routes::scans::create_scan).get(routes::scans::list_scans))
+        .route("/scans/{id}", get(routes::scans::get_scan).delete(routes::scans::cancel_scan))
+        .route("/scans/{id}/events", get(routes::scans::scan_events))
+        .route("/scans/{id}/tree", get(routes::scans::get_tree))
+        .route("/scans/{id}/top", get(routes::scans::get_top))
+        .route("/scans/{id}/list", get(routes::scans::get_list))
+        .route("/scans/{id}/search", get(routes::search::search_scan))
+        .route("/scans/{id}/export", get(routes::export::export_scan))
+        .route("/scans/{id}/warnings", get(routes::warnings::get_warnings))
+        .route("/scans/{id}/watch", post(routes::watch::start_watch).delete(routes::watch::stop_watch))
+        .route("/search", get(routes::search::search_all))
+        .with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+
+    TestServer {
+        _dir: dir,
+        state,
+        base_url: format!("http://{}", addr),
+        root: root.to_string_lossy().to_string(),
+    }
+}
+
+async fn finished_scan(client: &Client, root: &str) -> Uuid {
+    let req = CreateScanRequest { root_paths: vec![root.to_string()], ..Default::default() };
+    let created = client.create_scan(&req).await.unwrap();
+    for _ in 0..200 {
+        if client.get_scan(created.id).await.unwrap().status == "done" {
+            return created.id;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("scan {} did not finish", created.id);
+}
+
+#[tokio::test]
+async fn client_covers_scan_lifecycle_and_queries() {
+    let server = spawn_server().await;
+    let client = Client::builder(&server.base_url).timeout(Duration::from_secs(10)).build().unwrap();
+    let id = finished_scan(&client, &server.root).await;
+
+    let scans = client.list_scans().await.unwrap();
+    assert!(scans.iter().any(|s| s.id == id));
+    let summary = client.get_scan(id).await.unwrap();
+    assert_eq!(summary.file_count, 2);
+    assert!(!summary.resumable);
+
+    let tree = client.tree(id, &TreeParams::default()).await.unwrap();
+    assert!(tree.iter().any(|n| n.path.ends_with("node_modules")));
+    let top = client.top(id, &TopParams { scope: Some("files".into()), limit: Some(1) }).await.unwrap();
+    assert_eq!(top.len(), 1);
+    let listed = client
+        .list(id, &ListParams { path: Some(format!("{}/projects", server.root)), ..Default::default() })
+        .await
+        .unwrap();
+    assert!(listed.iter().any(|i| matches!(i, ListItem::File { name, .. } if name == "readme.txt")));
+
+    let found = client
+        .search(id, &SearchParams { query: "node_modules".into(), ..Default::default() })
+        .await
+        .unwrap();
+    assert!(found.items.iter().any(|i| matches!(i, SearchItem::Dir { .. })));
+    let across =
+        client.search_all(&GlobalSearchParams { q: "readme".into(), ..Default::default() }).await.unwrap();
+    assert_eq!(across.total_count, 1);
+    assert_eq!(across.items[0].scan_id, id);
+
+    let warnings = client.warnings(id, &WarningsParams::default()).await.unwrap();
+    assert_eq!(warnings.total, 0);
+
+    let mut csv: Vec<u8> = Vec::new();
+    let written = client.export(id, &ExportParams::default(), &mut csv).await.unwrap();
+    assert_eq!(written, csv.len() as u64);
+    assert!(String::from_utf8_lossy(&csv).contains("readme.txt"));
+
+    client.cancel_scan(id, CancelOptions { purge: Some(true), ..Default::default() }).await.unwrap();
+    assert!(client.get_scan(id).await.unwrap_err().is_not_found());
+}
+
+#[tokio::test]
+async fn client_maps_errors_and_streams_events() {
+    let server = spawn_server().await;
+    let client = Client::new(&server.base_url).unwrap();
+
+    let err = client.get_scan(Uuid::new_v4()).await.unwrap_err();
+    match err {
+        speicherwald_client::Error::Api { status, code, .. } => {
+            assert_eq!(status, 404);
+            assert_eq!(code, "NOT_FOUND");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // An interrupted scan replays its end as a single event and closes the stream
+    let id = finished_scan(&client, &server.root).await;
+    sqlx::query("UPDATE scans SET status='interrupted' WHERE id=?1")
+        .bind(id.to_string())
+        .execute(&server.state.db)
+        .await
+        .unwrap();
+    // As after a restart, which forgets the kept events of finished scans
+    server.state.finished_events.write().await.clear();
+    let events: Vec<ScanEvent> = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.events(id).await.unwrap().map(|e| e.unwrap()).collect(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(events, vec![ScanEvent::Interrupted { resumable: true }]);
+    assert!(client.get_scan(id).await.unwrap().resumable);
+}
+
+#[tokio::test]
+async fn client_sends_bearer_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_url = format!("sqlite://{}?mode=rwc", dir.path().join("auth.db").display());
+    let pool = speicherwald::db::connect_write_pool(&db_url, 2).await.unwrap();
+    speicherwald::db::init_db(&pool).await.unwrap();
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.tokens = vec!["lan-secret".into()];
+    let tokens = std::sync::Arc::new(speicherwald::middleware::auth::AuthTokens::from_config(&config.auth));
+    let state = AppState::new(pool, config);
+
+    let app = axum::Router::new()
+        .route("/scans", get(routes::scans::list_scans))
+        .route("/healthz", get(routes::health::healthz))
+        .with_state(state)
+        .layer(axum::middleware::from_fn_with_state(tokens, speicherwald::middleware::auth::auth_middleware));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+    let base_url = format!("http://{}", addr);
+
+    match Client::new(&base_url).unwrap().list_scans().await.unwrap_err() {
+        speicherwald_client::Error::Api { status, .. } => assert_eq!(status, 401),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    let client = Client::builder(&base_url).token("lan-secret").build().unwrap();
+    assert!(client.list_scans().await.unwrap().is_empty());
+    let health = reqwest::get(format!("{}/healthz", base_url)).await.unwrap();
+    assert_eq!(health.status(), 200);
+}
@@ -0,0 +1,2053 @@
+//! Data transfer objects of the SpeicherWald HTTP API.
+//!
+//! These types are shared by the backend, the web UI and
+//! `speicherwald-client`, so all of them serialize the same JSON. They only
+//! depend on `serde` and `uuid` to stay usable from WebAssembly.
+
+use std::fmt;
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use uuid::Uuid;
+
+/// Options for configuring a scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Whether to follow symbolic links.
+    pub follow_symlinks: bool,
+    /// Whether to include hidden files and directories.
+    pub include_hidden: bool,
+    /// Whether to measure logical file size.
+    pub measure_logical: bool,
+    /// Whether to measure allocated disk space.
+    pub measure_allocated: bool,
+    /// A list of glob patterns to exclude from the scan.
+    pub excludes: Vec<String>,
+    /// A list of glob patterns files must match to be recorded; empty records all
+    /// files. Directories are always traversed, and excludes win over includes.
+    #[serde(default)]
+    pub includes: Vec<String>,
+    /// The maximum depth of the scan.
+    pub max_depth: Option<u32>,
+    /// The number of concurrent scanner threads.
+    pub concurrency: Option<usize>,
+    /// Whether to detect hardlinks and count the allocated size of each linked file only once.
+    #[serde(default)]
+    pub measure_hardlinks: bool,
+    /// Whether to add the NTFS alternate data streams of files to their sizes (Windows only).
+    #[serde(default)]
+    pub measure_ads: bool,
+    /// The OS-level I/O priority of the scanner threads.
+    #[serde(default)]
+    pub io_priority: IoPriority,
+    /// Whether to record the owner of every file (Windows: account name of the
+    /// owner SID, elsewhere the numeric user ID).
+    #[serde(default)]
+    pub capture_owner: bool,
+    /// How the scanner opens directories and files.
+    #[serde(default)]
+    pub access_mode: AccessMode,
+    /// Files of at least this many bytes get a BLAKE3 hash of their content,
+    /// to find files that changed without changing size between scans.
+    /// `None` hashes nothing.
+    #[serde(default)]
+    pub hash_min_size: Option<u64>,
+}
+
+/// The I/O priority scanner threads run with, so scans of live file servers
+/// do not starve other workloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoPriority {
+    /// The priority of the server process.
+    #[default]
+    Normal,
+    /// Lowered priority: below-normal thread priority on Windows, the lowest
+    /// best-effort I/O level on Linux.
+    Low,
+    /// Background mode on Windows (`THREAD_MODE_BACKGROUND_BEGIN`), the idle
+    /// I/O class on Linux; disk access only happens when nothing else needs it.
+    Background,
+}
+
+/// How the scanner opens directories and files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessMode {
+    /// With the permissions of the server's account.
+    #[default]
+    Normal,
+    /// With `SeBackupPrivilege` enabled (Windows only), which reads directories
+    /// restricted to administrators. Falls back to `normal` with a warning if
+    /// the account does not hold the privilege.
+    Backup,
+}
+
+/// A data transfer object for a node (directory) in the scanned tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeDto {
+    /// The path of the node.
+    pub path: String,
+    /// The parent path of the node.
+    pub parent_path: Option<String>,
+    /// The depth of the node in the directory tree.
+    pub depth: i64,
+    /// Whether the node is a directory.
+    pub is_dir: bool,
+    /// The logical size of the node in bytes.
+    pub logical_size: i64,
+    /// The allocated size of the node in bytes.
+    pub allocated_size: i64,
+    /// The number of files in the node.
+    pub file_count: i64,
+    /// The number of subdirectories in the node.
+    pub dir_count: i64,
+    /// The modification time of the node.
+    pub mtime: Option<i64>,
+    /// The access time of the node.
+    pub atime: Option<i64>,
+    /// The fingerprint of the node's direct children as 16 hex digits, for
+    /// debugging change detection. `None` for incomplete listings and older scans.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+/// A directory together with its breadcrumbs, answered by `GET /scans/{id}/node`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeContextDto {
+    /// The requested directory.
+    pub node: NodeDto,
+    /// The directories from the scan root down to the parent of `node`.
+    pub ancestors: Vec<NodeDto>,
+    /// The other directories in the same parent, largest first; only with `with_siblings=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub siblings: Option<Vec<NodeDto>>,
+}
+
+/// A data transfer object for a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDto {
+    /// The path of the file.
+    pub path: String,
+    /// The parent path of the file.
+    pub parent_path: Option<String>,
+    /// The logical size of the file in bytes.
+    pub logical_size: i64,
+    /// The allocated size of the file in bytes.
+    pub allocated_size: i64,
+}
+
+/// A path completion candidate taken from the scanned directory tree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompletionDto {
+    /// The full path of the candidate directory.
+    pub path: String,
+    /// The last path segment, suitable for display.
+    pub name: String,
+    /// The logical size of the directory in bytes.
+    pub logical_size: i64,
+    /// The allocated size of the directory in bytes.
+    pub allocated_size: i64,
+    /// The number of direct children (files and directories) recorded in the scan.
+    pub child_count: i64,
+}
+
+/// An item in the "top" list, which can be either a file or a directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TopItem {
+    /// A directory item.
+    Dir {
+        /// The path of the directory.
+        path: String,
+        /// The parent path of the directory.
+        parent_path: Option<String>,
+        /// The depth of the directory in the directory tree.
+        depth: i64,
+        /// The logical size of the directory in bytes.
+        logical_size: i64,
+        /// The allocated size of the directory in bytes.
+        allocated_size: i64,
+        /// The number of files in the directory.
+        file_count: i64,
+        /// The number of subdirectories in the directory.
+        dir_count: i64,
+        /// The modification time of the directory.
+        mtime: Option<i64>,
+        /// The access time of the directory.
+        atime: Option<i64>,
+    },
+    /// A file item.
+    File {
+        /// The path of the file.
+        path: String,
+        /// The parent path of the file.
+        parent_path: Option<String>,
+        /// The logical size of the file in bytes.
+        logical_size: i64,
+        /// The allocated size of the file in bytes.
+        allocated_size: i64,
+        /// The modification time of the file.
+        mtime: Option<i64>,
+        /// The access time of the file.
+        atime: Option<i64>,
+    },
+}
+
+/// An item of `GET /scans/{id}/recent`: a [`TopItem`] plus the outcome of the
+/// optional re-check against the live filesystem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentItem {
+    /// The file or directory as recorded by the scan.
+    #[serde(flatten)]
+    pub item: TopItem,
+    /// With `verify=true`: whether the path was modified or removed since the scan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+    /// With `verify=true`: the current modification time, absent if the path is gone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_mtime: Option<i64>,
+}
+
+/// An item in a directory listing, which can be either a file or a directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ListItem {
+    /// A directory item.
+    Dir {
+        /// The name of the directory.
+        name: String,
+        /// The path of the directory.
+        path: String,
+        /// The parent path of the directory.
+        parent_path: Option<String>,
+        /// The depth of the directory in the directory tree.
+        depth: i64,
+        /// The logical size of the directory in bytes.
+        logical_size: i64,
+        /// The allocated size of the directory in bytes.
+        allocated_size: i64,
+        /// The number of files in the directory.
+        file_count: i64,
+        /// The number of subdirectories in the directory.
+        dir_count: i64,
+        /// The modification time of the directory.
+        mtime: Option<i64>,
+        /// The access time of the directory.
+        atime: Option<i64>,
+    },
+    /// A file item.
+    File {
+        /// The name of the file.
+        name: String,
+        /// The path of the file.
+        path: String,
+        /// The parent path of the file.
+        parent_path: Option<String>,
+        /// The logical size of the file in bytes.
+        logical_size: i64,
+        /// The allocated size of the file in bytes.
+        allocated_size: i64,
+        /// The modification time of the file.
+        mtime: Option<i64>,
+        /// The access time of the file.
+        atime: Option<i64>,
+    },
+}
+
+/// A page of a directory listing with totals over all matching items,
+/// returned by the list endpoint with `with_totals=true`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResponse {
+    /// The items of the requested page.
+    pub items: Vec<ListItem>,
+    /// The number of items matching the filters, across all pages.
+    pub total_count: i64,
+    /// The allocated bytes of all matching items.
+    pub total_allocated_size: i64,
+    /// The logical bytes of all matching items.
+    pub total_logical_size: i64,
+}
+
+/// Information about a drive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriveInfo {
+    /// The path of the drive (e.g., "C:\\").
+    pub path: String,
+    /// The type of the drive (e.g., "fixed", "network").
+    pub drive_type: String,
+    /// The total size of the drive in bytes.
+    pub total_bytes: u64,
+    /// The amount of free space on the drive in bytes.
+    pub free_bytes: u64,
+    /// The volume label; only with `?detailed=true`.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The filesystem, e.g. "NTFS", "ReFS" or "exFAT"; only with `?detailed=true`.
+    #[serde(default)]
+    pub filesystem: Option<String>,
+    /// The volume serial number as shown by `vol` (e.g. "1A2B-3C4D"); only with `?detailed=true`.
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    /// Whether the volume is protected by BitLocker; only with `?detailed=true`
+    /// and `None` if the state could not be determined.
+    #[serde(default)]
+    pub bitlocker: Option<bool>,
+}
+
+/// One free space sample of a drive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriveHistorySample {
+    /// When the sample was taken, in seconds since the Unix epoch. For
+    /// downsampled points this is the newest sample of the bucket.
+    pub sampled_at: i64,
+    /// The total size of the drive in bytes.
+    pub total_bytes: u64,
+    /// The free space of the drive in bytes; averaged for downsampled points.
+    pub free_bytes: u64,
+}
+
+/// The free space history of a drive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriveHistoryResponse {
+    /// The drive path the samples belong to.
+    pub path: String,
+    /// The number of hours covered.
+    pub hours: u32,
+    /// The samples, oldest first.
+    pub samples: Vec<DriveHistorySample>,
+}
+
+/// A request to move or copy a file or directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MovePathRequest {
+    /// The source paths.
+    pub sources: Vec<String>,
+    /// The destination paths.
+    pub destinations: Vec<String>,
+    /// Whether to remove the source after the operation.
+    #[serde(default)]
+    pub remove_source: bool,
+    /// Whether to overwrite the destination if it already exists.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// The response from a move path operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovePathResponse {
+    /// The status of the operation.
+    pub status: String,
+    /// The source paths.
+    pub sources: Vec<String>,
+    /// The destination paths.
+    pub destinations: Vec<String>,
+    /// The total number of bytes to transfer.
+    pub bytes_to_transfer: u64,
+    /// The number of bytes that were successfully moved or copied.
+    pub bytes_moved: u64,
+    /// The number of bytes freed by the operation.
+    pub freed_bytes: u64,
+    /// The duration of the operation in milliseconds.
+    pub duration_ms: u128,
+    /// The start time of the operation.
+    pub started_at: String,
+    /// The end time of the operation.
+    pub finished_at: String,
+    /// Any warnings that occurred during the operation.
+    pub warnings: Vec<String>,
+}
+
+/// One source and its destination in a [`MoveBatchRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MoveBatchItem {
+    /// The file or directory to move.
+    pub source: String,
+    /// The path it is moved to.
+    pub destination: String,
+}
+
+/// A request to move or copy several files or directories in one operation.
+///
+/// The items are given either as `items` or as `sources` that all go into
+/// `destination_dir`, keeping their names; both forms can be combined.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MoveBatchRequest {
+    /// Sources with their own destinations.
+    #[serde(default)]
+    pub items: Vec<MoveBatchItem>,
+    /// Sources moved into `destination_dir`.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// The directory `sources` are moved into.
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    /// Whether to remove the sources after copying.
+    #[serde(default)]
+    pub remove_source: bool,
+    /// Whether to overwrite destinations that already exist.
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Whether to skip the remaining items after the first failed one.
+    #[serde(default)]
+    pub stop_on_error: bool,
+}
+
+/// The state of one item of a move operation.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveItemStatus {
+    /// Not processed yet.
+    Pending,
+    /// Moved or copied.
+    Succeeded,
+    /// Left alone, see the message.
+    Skipped,
+    /// Moving or copying failed, see the message.
+    Failed,
+}
+
+/// The outcome of one item of a move operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveItemResult {
+    /// The source path.
+    pub source: String,
+    /// The destination path.
+    pub destination: String,
+    /// The state of the item.
+    pub status: MoveItemStatus,
+    /// Why the item was skipped or failed.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// The format of an archive written by `POST /paths/archive`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A zip archive with deflate compression.
+    #[serde(rename = "zip")]
+    Zip,
+    /// A tar archive compressed with zstd.
+    #[serde(rename = "tar.zst")]
+    TarZst,
+}
+
+/// A request to pack a file or directory into an archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArchivePathRequest {
+    /// The file or directory to archive.
+    pub source: String,
+    /// The path of the archive to write.
+    pub destination: String,
+    /// The archive format.
+    pub format: ArchiveFormat,
+    /// Whether to delete the source once the archive is complete.
+    #[serde(default)]
+    pub remove_source: bool,
+    /// Whether to replace an existing archive at the destination.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// The state of an archive operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveOperation {
+    /// The ID of the operation, used with `GET /paths/operations/{op_id}`.
+    pub op_id: Uuid,
+    /// `running`, `completed` or `failed`.
+    pub status: String,
+    /// The archived file or directory.
+    pub source: String,
+    /// The archive path.
+    pub destination: String,
+    /// The archive format.
+    pub format: ArchiveFormat,
+    /// The total size of the files to archive.
+    pub bytes_to_transfer: u64,
+    /// The bytes of source files read so far.
+    pub bytes_processed: u64,
+    /// The file currently being archived, `None` when not running.
+    pub current_file: Option<String>,
+    /// The size of the finished archive.
+    pub bytes_written: u64,
+    /// The bytes freed by removing the source.
+    pub freed_bytes: u64,
+    /// The duration of the operation so far in milliseconds.
+    pub duration_ms: u64,
+    /// The start time of the operation.
+    pub started_at: String,
+    /// The end time of the operation, `None` while it is running.
+    pub finished_at: Option<String>,
+    /// Any warnings that occurred during the operation.
+    pub warnings: Vec<String>,
+    /// Why the operation failed.
+    pub error: Option<String>,
+}
+
+/// The state of a background move or copy started by `POST /paths/move` or
+/// `POST /paths/move-batch`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveOperation {
+    /// The ID of the operation, used with `GET /paths/operations/{op_id}`.
+    pub op_id: Uuid,
+    /// `running`, `cancelling`, `completed`, `cancelled` or `failed`.
+    pub status: String,
+    /// The source paths.
+    pub sources: Vec<String>,
+    /// The destination paths.
+    pub destinations: Vec<String>,
+    /// Whether the sources are removed after copying.
+    pub remove_source: bool,
+    /// The size of the items started so far; grows as each item is sized.
+    pub bytes_to_transfer: u64,
+    /// The bytes moved or copied so far.
+    pub bytes_moved: u64,
+    /// The bytes freed by removing sources.
+    pub freed_bytes: u64,
+    /// The number of items in the request.
+    pub items_total: usize,
+    /// The number of items already processed.
+    pub items_done: usize,
+    /// The source currently being processed, `None` when not running.
+    pub current_source: Option<String>,
+    /// The state of every item, in request order.
+    #[serde(default)]
+    pub items: Vec<MoveItemResult>,
+    /// The duration of the operation so far in milliseconds.
+    pub duration_ms: u64,
+    /// The start time of the operation.
+    pub started_at: String,
+    /// The end time of the operation, `None` while it is running.
+    pub finished_at: Option<String>,
+    /// Any warnings that occurred during the operation.
+    pub warnings: Vec<String>,
+    /// Why the operation failed.
+    pub error: Option<String>,
+}
+
+/// A background path operation as reported by `GET /paths/operations`.
+///
+/// The variants keep `duration_ms` as `u64`: serde cannot read a `u128` through
+/// an internally tagged enum.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PathOperation {
+    /// A move or copy.
+    Move(MoveOperation),
+    /// An archive, optionally followed by deleting the source.
+    Archive(ArchiveOperation),
+}
+
+/// How a path is deleted.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteMode {
+    /// Move the item to the recycle bin so it can be restored.
+    #[default]
+    Recycle,
+    /// Delete the item permanently.
+    Permanent,
+}
+
+/// A request to delete files or directories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeletePathRequest {
+    /// The paths to delete.
+    pub paths: Vec<String>,
+    /// Whether to recycle (default) or delete permanently.
+    #[serde(default)]
+    pub mode: DeleteMode,
+    /// A scan whose rows for the deleted paths are removed and whose directory totals are reduced.
+    pub scan_id: Option<Uuid>,
+    /// Only report what would be removed, without deleting anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What happened to a single path of a delete request.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteStatus {
+    /// The item was moved to the recycle bin.
+    Recycled,
+    /// The item was deleted permanently, either as requested or because the
+    /// system bypassed the recycle bin (see `DeletedPath::message`).
+    Deleted,
+    /// Dry run only: the item would be deleted.
+    WouldDelete,
+    /// The item was not deleted.
+    Failed,
+}
+
+/// The outcome for a single path of a delete request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedPath {
+    /// The path.
+    pub path: String,
+    /// What happened to the item.
+    pub status: DeleteStatus,
+    /// The size of the item in bytes (the sum of all files for directories).
+    pub bytes: u64,
+    /// The number of files in the item (1 for a file).
+    #[serde(default)]
+    pub files: u64,
+    /// How many of those files are hidden or system files.
+    #[serde(default)]
+    pub hidden_files: u64,
+    /// Why the item failed, or a warning such as a bypassed recycle bin.
+    pub message: Option<String>,
+}
+
+/// The response from a delete path operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletePathResponse {
+    /// The requested mode.
+    pub mode: DeleteMode,
+    /// The outcome per path, in request order.
+    pub items: Vec<DeletedPath>,
+    /// Whether this was a dry run; nothing was deleted.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The total size of all recycled or deleted items, or of the items a dry run would remove.
+    pub removed_bytes: u64,
+    /// The total size of permanently deleted items; recycled items still occupy space.
+    #[serde(default)]
+    pub freed_bytes: u64,
+    /// The total number of removed files, or of the files a dry run would remove.
+    #[serde(default)]
+    pub files: u64,
+    /// Whether the rows of `scan_id` were updated.
+    pub scan_updated: bool,
+    /// The duration of the operation in milliseconds.
+    pub duration_ms: u128,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        // Calculate concurrency: use half the CPU cores, minimum 2, maximum 16
+        let cpu_count = num_cpus::get();
+        let default_concurrency = (cpu_count / 2).clamp(2, 16);
+
+        Self {
+            follow_symlinks: false,
+            include_hidden: true,
+            measure_logical: true,
+            measure_allocated: true,
+            excludes: vec![],
+            includes: vec![],
+            max_depth: None,
+            concurrency: Some(default_concurrency),
+            measure_hardlinks: false,
+            measure_ads: false,
+            io_priority: IoPriority::Normal,
+            capture_owner: false,
+            access_mode: AccessMode::Normal,
+            hash_min_size: None,
+        }
+    }
+}
+
+/// A request to create a new scan.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateScanRequest {
+    /// The root paths to scan.
+    pub root_paths: Vec<String>,
+    /// Whether to follow symbolic links.
+    pub follow_symlinks: Option<bool>,
+    /// Whether to include hidden files and directories.
+    pub include_hidden: Option<bool>,
+    /// Whether to measure logical file size.
+    pub measure_logical: Option<bool>,
+    /// Whether to measure allocated disk space.
+    pub measure_allocated: Option<bool>,
+    /// A list of glob patterns to exclude from the scan.
+    pub excludes: Option<Vec<String>>,
+    /// A list of glob patterns files must match to be recorded.
+    pub includes: Option<Vec<String>>,
+    /// The maximum depth of the scan.
+    pub max_depth: Option<u32>,
+    /// The number of concurrent scanner threads.
+    pub concurrency: Option<usize>,
+    /// Whether to detect hardlinks so shared data is only counted once.
+    pub measure_hardlinks: Option<bool>,
+    /// Whether to count NTFS alternate data streams (Windows only).
+    pub measure_ads: Option<bool>,
+    /// The I/O priority of the scanner threads.
+    #[serde(default)]
+    pub io_priority: Option<IoPriority>,
+    /// Whether to record the owner of every file.
+    #[serde(default)]
+    pub capture_owner: Option<bool>,
+    /// How the scanner opens directories and files.
+    #[serde(default)]
+    pub access_mode: Option<AccessMode>,
+    /// The size from which files get a content hash.
+    #[serde(default)]
+    pub hash_min_size: Option<u64>,
+    /// Whether the scanned roots must never be modified, e.g. snapshots or
+    /// backup targets. Path operations refuse to touch anything below them
+    /// until the scan is deleted.
+    #[serde(default)]
+    pub read_only: Option<bool>,
+    /// Whether to leave out the `[system_excludes]` patterns the server adds
+    /// to scans of a system drive.
+    #[serde(default)]
+    pub no_system_excludes: Option<bool>,
+    /// A finished scan to repeat incrementally: the files of directories whose
+    /// modification time did not change since are copied from it instead of
+    /// being listed again.
+    #[serde(default)]
+    pub rescan_of: Option<Uuid>,
+}
+
+/// The response from a create scan request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateScanResponse {
+    /// The ID of the new scan.
+    pub id: Uuid,
+    /// The status of the new scan.
+    pub status: String,
+    /// The start time of the new scan.
+    pub started_at: String,
+    /// Adjustments made to the request, e.g. nested root paths that were dropped.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// A sampled guess of how large a scan would get, as returned by `POST /scans/estimate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanEstimate {
+    /// The estimated number of directories, roots included.
+    pub dirs: EstimateRange,
+    /// The estimated number of files.
+    pub files: EstimateRange,
+    /// How many directories were actually listed.
+    pub sampled_dirs: u64,
+    /// Whether sampling reached every level before the time budget ran out;
+    /// if not, the deepest levels are extrapolated and the band is wider.
+    pub complete: bool,
+    /// How long sampling took in milliseconds.
+    pub elapsed_ms: u64,
+    /// Each root in request order.
+    pub roots: Vec<RootEstimate>,
+    /// Adjustments made to the request, e.g. nested root paths that were dropped.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// An estimated count with its confidence band.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EstimateRange {
+    /// The most likely value.
+    pub estimate: u64,
+    /// The lower end of the band; never below what was actually counted.
+    pub low: u64,
+    /// The upper end of the band.
+    pub high: u64,
+}
+
+/// One root of a [`ScanEstimate`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootEstimate {
+    /// The root path.
+    pub path: String,
+    /// Why the root cannot be scanned (`missing_root`, `access_denied`,
+    /// `not_a_directory`, `excluded`, `metadata_failed`, `read_dir_failed`);
+    /// `None` if it can.
+    pub problem: Option<String>,
+    /// The estimated number of directories below and including the root.
+    pub dirs: u64,
+    /// The estimated number of files below the root.
+    pub files: u64,
+    /// How many sampled directories could not be listed.
+    pub unreadable_dirs: u64,
+}
+
+/// The direct children of a directory, as returned by `GET /preview`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirPreview {
+    /// The directory.
+    pub path: String,
+    /// The children, directories first by name, then files largest first.
+    pub entries: Vec<PreviewEntry>,
+    /// How many children a scan with the default options would leave out,
+    /// such as hidden entries or links that are not followed.
+    pub skipped: u64,
+    /// Whether the entry limit or the time budget cut the listing short.
+    pub truncated: bool,
+    /// How long the listing took in milliseconds.
+    pub elapsed_ms: u64,
+}
+
+/// One child of a [`DirPreview`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewEntry {
+    /// The file name of the child.
+    pub name: String,
+    /// Whether the child is a directory.
+    pub is_dir: bool,
+    /// The logical size of a file; `None` for directories, which are not descended into.
+    pub logical_size: Option<u64>,
+    /// The modification time as Unix seconds.
+    pub mtime: Option<i64>,
+}
+
+/// A request to create a recurring scan schedule.
+///
+/// Accepts the same fields as [`CreateScanRequest`] plus exactly one of
+/// `interval_minutes` or `cron`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateScheduleRequest {
+    /// The scan to run each time the schedule fires.
+    #[serde(flatten)]
+    pub scan: CreateScanRequest,
+    /// Run the scan every N minutes.
+    pub interval_minutes: Option<u32>,
+    /// A five-field cron expression (minute hour day-of-month month day-of-week), evaluated in UTC.
+    pub cron: Option<String>,
+}
+
+// `#[serde(flatten)]` would ignore `deny_unknown_fields` of the scan request, so
+// the schedule's own fields are taken out of the map and the rest is read as a
+// `CreateScanRequest`, which still rejects unknown fields.
+impl<'de> Deserialize<'de> for CreateScheduleRequest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScheduleVisitor;
+
+        impl<'de> Visitor<'de> for ScheduleVisitor {
+            type Value = CreateScheduleRequest;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a scan request with interval_minutes or cron")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let mut fields = ScheduleFields { map, interval_minutes: None, cron: None };
+                let scan = CreateScanRequest::deserialize(MapAccessDeserializer::new(&mut fields))?;
+                Ok(CreateScheduleRequest {
+                    scan,
+                    interval_minutes: fields.interval_minutes.flatten(),
+                    cron: fields.cron.flatten(),
+                })
+            }
+        }
+
+        deserializer.deserialize_map(ScheduleVisitor)
+    }
+}
+
+/// The entries of a schedule body without `interval_minutes` and `cron`, which it keeps.
+struct ScheduleFields<A> {
+    map: A,
+    interval_minutes: Option<Option<u32>>,
+    cron: Option<Option<String>>,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for ScheduleFields<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        while let Some(key) = self.map.next_key::<String>()? {
+            match key.as_str() {
+                "interval_minutes" if self.interval_minutes.is_some() => {
+                    return Err(de::Error::duplicate_field("interval_minutes"))
+                }
+                "interval_minutes" => self.interval_minutes = Some(self.map.next_value()?),
+                "cron" if self.cron.is_some() => return Err(de::Error::duplicate_field("cron")),
+                "cron" => self.cron = Some(self.map.next_value()?),
+                _ => return seed.deserialize(IntoDeserializer::<A::Error>::into_deserializer(key)).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
+
+/// A recurring scan schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleDto {
+    /// The ID of the schedule.
+    pub id: Uuid,
+    /// The scan request that is executed when the schedule fires.
+    pub scan: CreateScanRequest,
+    /// The interval in minutes, if this is an interval schedule.
+    pub interval_minutes: Option<u32>,
+    /// The cron expression, if this is a cron schedule.
+    pub cron: Option<String>,
+    /// The creation time of the schedule.
+    pub created_at: String,
+    /// The time the schedule last started a scan.
+    pub last_run_at: Option<String>,
+    /// The next time the schedule is due.
+    pub next_run_at: String,
+    /// The ID of the scan most recently started by this schedule.
+    pub last_scan_id: Option<Uuid>,
+    /// The namespace the schedule and its scans belong to.
+    pub namespace: String,
+}
+
+/// The sizes and counts of a directory in one scan, or their difference.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct DiffValues {
+    /// The logical size in bytes.
+    pub logical_size: i64,
+    /// The allocated size in bytes.
+    pub allocated_size: i64,
+    /// The number of files in the subtree.
+    pub file_count: i64,
+    /// The number of subdirectories in the subtree.
+    pub dir_count: i64,
+}
+
+/// How a directory changed between two scans.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// The directory exists only in the newer scan.
+    Added,
+    /// The directory exists only in the older scan.
+    Removed,
+    /// The directory exists in both scans with different values.
+    Changed,
+    /// The directory exists in both scans with identical values.
+    Unchanged,
+}
+
+/// A per-directory comparison between two scans.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffItem {
+    /// The path of the directory.
+    pub path: String,
+    /// Whether the directory was added, removed or changed.
+    pub status: DiffStatus,
+    /// The values in the older scan, if the directory existed there.
+    pub old: Option<DiffValues>,
+    /// The values in the newer scan, if the directory exists there.
+    pub new: Option<DiffValues>,
+    /// `new - old`, treating a missing side as zero.
+    pub delta: DiffValues,
+    /// The growth of the compared size (see `DiffResponse::size_basis`) relative
+    /// to the old value, e.g. `0.5` for +50%. `None` if the old size was zero.
+    pub relative_growth: Option<f64>,
+}
+
+/// The result of comparing two scans.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffResponse {
+    /// The newer scan.
+    pub scan_id: Uuid,
+    /// The older scan the newer one is compared against.
+    pub other_id: Uuid,
+    /// The size used for sorting and `relative_growth`: `"allocated"` or `"logical"`.
+    pub size_basis: String,
+    /// `false` if at least one scan did not measure allocated sizes, in which
+    /// case `size_basis` falls back to `"logical"` and allocated deltas are not meaningful.
+    pub allocated_comparable: bool,
+    /// The compared directories.
+    pub items: Vec<DiffItem>,
+}
+
+/// A directory that grew between the oldest and newest scan of a root.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GrowthItem {
+    /// The path of the directory.
+    pub path: String,
+    /// `true` if the directory did not exist in the older scan; its growth is its full size.
+    pub is_new: bool,
+    /// The size in the older scan (see `GrowthReportResponse::size_basis`), 0 for new directories.
+    pub old_size: i64,
+    /// The size in the newer scan.
+    pub new_size: i64,
+    /// `new_size - old_size` in bytes.
+    pub growth: i64,
+    /// The growth in percent of the old size, e.g. `50.0` for +50%. `None` for new directories.
+    pub growth_percent: Option<f64>,
+}
+
+/// The directories of a root that grew the most within a time window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrowthReportResponse {
+    /// The root as given in the query.
+    pub root: String,
+    /// The earliest finished scan of the root in the window.
+    pub older_scan_id: Option<Uuid>,
+    /// The latest finished scan of the root in the window.
+    pub newer_scan_id: Option<Uuid>,
+    /// When the older scan started.
+    pub older_started_at: Option<String>,
+    /// When the newer scan started.
+    pub newer_started_at: Option<String>,
+    /// The compared size: `"allocated"`, or `"logical"` if a scan did not measure allocated sizes.
+    pub size_basis: String,
+    /// The directories that grew, largest growth first; empty if fewer than two scans were found.
+    pub items: Vec<GrowthItem>,
+}
+
+/// The state of the watcher of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// Whether the scan is being watched.
+    pub watching: bool,
+    /// The watched root paths.
+    pub root_paths: Vec<String>,
+}
+
+/// A request to remap the root of a scan to a new location.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemapRootRequest {
+    /// The old root, e.g. `X:\`. Every scan root at or below it is remapped.
+    pub from: String,
+    /// The new root, e.g. `Y:\`.
+    pub to: String,
+}
+
+/// A remap applied to the stored paths of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRemapDto {
+    /// The ID of the remap record.
+    pub id: i64,
+    /// The old root.
+    pub from: String,
+    /// The new root.
+    pub to: String,
+    /// The namespace the remap was requested from.
+    pub namespace: String,
+    /// The number of directory rows rewritten.
+    pub nodes_updated: i64,
+    /// The number of file rows rewritten.
+    pub files_updated: i64,
+    /// The time of the remap.
+    pub remapped_at: String,
+}
+
+/// The result of re-checking a sample of a scan's paths against the filesystem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanVerificationDto {
+    /// The ID of the verification record.
+    pub id: i64,
+    /// The number of directories checked.
+    pub sampled_dirs: i64,
+    /// The number of files checked.
+    pub sampled_files: i64,
+    /// The number of checked paths that no longer exist.
+    pub missing: i64,
+    /// The number of checked paths that still exist but changed: files whose
+    /// size or modification time differs, directories whose modification time does.
+    pub changed: i64,
+    /// The stored size of the missing files plus the size change of the changed
+    /// ones, in bytes.
+    pub drift_bytes: i64,
+    /// The time of the verification.
+    pub verified_at: String,
+}
+
+/// A directory whose stored totals differ from the sum of its subdirectories and own files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanInconsistency {
+    /// The directory.
+    pub path: String,
+    /// The depth of the directory below its root.
+    pub depth: i64,
+    /// The stored logical size.
+    pub logical_size: i64,
+    /// The logical size of the subdirectories and own files.
+    pub expected_logical_size: i64,
+    /// The stored allocated size.
+    pub allocated_size: i64,
+    /// The allocated size of the subdirectories and own files.
+    pub expected_allocated_size: i64,
+    /// The stored number of files below the directory.
+    pub file_count: i64,
+    /// The number of files of the subdirectories and own files.
+    pub expected_file_count: i64,
+    /// The stored number of directories below the directory.
+    pub dir_count: i64,
+    /// The number of subdirectories and the directories below them.
+    pub expected_dir_count: i64,
+    /// When the mismatch was found.
+    pub detected_at: String,
+    /// When the totals were recomputed; `None` while they are still wrong.
+    pub repaired_at: Option<String>,
+}
+
+/// The result of `POST /scans/{id}/recompute`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecomputeResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The number of directories whose totals did not add up.
+    pub inconsistencies: u64,
+    /// The number of directories whose totals were rewritten; 0 without `repair`.
+    pub repaired: u64,
+    /// The first of the inconsistent directories, deepest first.
+    pub items: Vec<ScanInconsistency>,
+}
+
+/// A file whose size is unchanged between two scans but whose content hash differs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityMismatch {
+    /// The path of the file.
+    pub path: String,
+    /// The logical size of the file in both scans.
+    pub logical_size: i64,
+    /// The modification time in the scan.
+    pub mtime: Option<i64>,
+    /// The modification time in the other scan.
+    pub other_mtime: Option<i64>,
+    /// The hex BLAKE3 hash in the scan.
+    pub hash: String,
+    /// The hex BLAKE3 hash in the other scan.
+    pub other_hash: String,
+}
+
+/// The result of `GET /scans/{id}/integrity`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentIntegrityResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The ID of the scan it was compared with.
+    pub other: Uuid,
+    /// The number of files of the same size hashed in both scans.
+    pub compared: u64,
+    /// The compared files whose hashes differ.
+    pub mismatches: Vec<IntegrityMismatch>,
+}
+
+/// A directory contributing to what an exclude simulation would remove.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExcludeSimulationDir {
+    /// The directory; excluded itself, or holding excluded files directly.
+    pub path: String,
+    /// Whether the directory itself matches a pattern, removing its whole subtree.
+    pub excluded: bool,
+    /// The number of files removed from the directory.
+    pub file_count: u64,
+    /// The number of directories removed, the directory itself included.
+    pub dir_count: u64,
+    /// The logical size removed.
+    pub logical_size: u64,
+    /// The allocated size removed.
+    pub allocated_size: u64,
+}
+
+/// The result of `GET /scans/{id}/simulate-exclude`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludeSimulation {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The simulated exclude patterns.
+    pub patterns: Vec<String>,
+    /// The number of files a scan with the patterns would not have recorded.
+    pub excluded_files: u64,
+    /// The number of directories a scan with the patterns would not have recorded.
+    pub excluded_dirs: u64,
+    /// The logical size removed from the totals.
+    pub logical_size: u64,
+    /// The allocated size removed from the totals.
+    pub allocated_size: u64,
+    /// The directories contributing most, by allocated size.
+    pub top_dirs: Vec<ExcludeSimulationDir>,
+}
+
+/// The request body of `POST /scans/{id}/share`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateShareRequest {
+    /// How many seconds the link stays valid; without it the link lasts until it is revoked.
+    #[serde(default)]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// A read-only share link of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    /// The ID of the shared scan.
+    pub scan_id: Uuid,
+    /// The token, passed as `?share=` to the shared endpoints.
+    pub token: String,
+    /// When the link was created.
+    pub created_at: String,
+    /// When the link expires, `None` if it lasts until it is revoked.
+    pub expires_at: Option<String>,
+}
+
+/// An upload of an exported scan that has not been committed yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSession {
+    /// The ID of the session.
+    pub id: Uuid,
+    /// The ID of the scan the uploaded rows are stored in.
+    pub scan_id: Uuid,
+    /// The format of the upload: `ndjson` or `csv`.
+    pub format: String,
+    /// The server the export came from.
+    pub origin: String,
+    /// The number of bytes accepted so far, where the next chunk continues.
+    pub offset: u64,
+    /// The number of records accepted so far, including CSV header lines.
+    pub records: u64,
+    /// When the session was created.
+    pub created_at: String,
+    /// When the session last accepted a chunk.
+    pub updated_at: String,
+}
+
+/// The configured scan retention policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Whether at least one limit is set; without one nothing is ever pruned.
+    pub enabled: bool,
+    /// How many finished scans are kept per set of root paths.
+    pub max_scans_per_root: Option<usize>,
+    /// Finished scans started more than this many days ago are pruned.
+    pub max_age_days: Option<u64>,
+    /// The interval of the background pruning, in seconds.
+    pub interval_secs: u64,
+    /// Whether `PRAGMA incremental_vacuum` runs after a prune.
+    pub incremental_vacuum: bool,
+}
+
+/// A scan the retention policy selects for pruning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionCandidate {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The status of the scan.
+    pub status: String,
+    /// The root paths of the scan.
+    pub root_paths: Vec<String>,
+    /// The start time of the scan.
+    pub started_at: String,
+    /// The allocated size of the scan's files, if recorded.
+    pub total_allocated_size: Option<i64>,
+    /// Why the scan is pruned: `max_scans_per_root` or `max_age_days`.
+    pub reason: String,
+    /// Why the scan is kept for now despite the policy: `running`, `watched` or `in_use`
+    /// (read by a running diff or export). `None` if it will be pruned.
+    pub protected: Option<String>,
+}
+
+/// The retention policy and the scans the next prune would remove.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// The configured policy.
+    pub policy: RetentionPolicy,
+    /// The scans selected by the policy, oldest first.
+    pub candidates: Vec<RetentionCandidate>,
+}
+
+/// The result of a prune.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRunResponse {
+    /// The pruned scans.
+    pub deleted: Vec<RetentionCandidate>,
+    /// The selected scans that were kept because they are running, watched or in use.
+    pub skipped: Vec<RetentionCandidate>,
+    /// Whether `PRAGMA incremental_vacuum` ran afterwards.
+    pub vacuumed: bool,
+    /// The duration of the prune in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// The result of restoring a database backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreResponse {
+    /// The size of the restored database file in bytes (uncompressed).
+    pub size_bytes: u64,
+    /// The number of scans in the restored database.
+    pub scan_count: u64,
+    /// The duration of the restore in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// The result of `POST /admin/db/vacuum` or `POST /admin/db/analyze`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbMaintenanceResponse {
+    /// `vacuum`, `incremental_vacuum` or `analyze`.
+    pub operation: String,
+    /// The size of the database in bytes before the operation.
+    pub size_before_bytes: u64,
+    /// The size of the database in bytes afterwards.
+    pub size_after_bytes: u64,
+    /// The bytes returned to the operating system.
+    pub reclaimed_bytes: u64,
+    /// The duration of the operation in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// The result of `GET /admin/db/integrity`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityResponse {
+    /// Whether `PRAGMA integrity_check` found no problems.
+    pub ok: bool,
+    /// The problems found, as SQLite reports them.
+    pub problems: Vec<String>,
+    /// Whether the check stopped at the requested limit, so there may be more problems.
+    pub truncated: bool,
+    /// The duration of the check in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// The result of reloading the configuration with `POST /admin/reload`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadResponse {
+    /// The settings that changed but keep their old value until the server
+    /// is restarted, e.g. `server.port` or `database.url`.
+    pub requires_restart: Vec<String>,
+}
+
+/// The response to starting a duplicate search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateJobResponse {
+    /// The ID of the scan being searched.
+    pub scan_id: Uuid,
+    /// The status of the search (`"running"`).
+    pub status: String,
+    /// The minimum file size considered, in bytes.
+    pub min_size: u64,
+}
+
+/// A group of files with identical content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroupDto {
+    /// The BLAKE3 hash of the content, hex encoded.
+    pub hash: String,
+    /// The size of each file in bytes.
+    pub file_size: i64,
+    /// The number of files in the group.
+    pub file_count: i64,
+    /// The bytes that would be freed by keeping only one of the files.
+    pub wasted_bytes: i64,
+    /// The paths of the files, sorted.
+    pub paths: Vec<String>,
+}
+
+/// The duplicate groups of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatesResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// Whether a duplicate search for the scan is still running.
+    pub running: bool,
+    /// The total number of groups stored for the scan.
+    pub total_groups: i64,
+    /// The wasted bytes of all groups stored for the scan.
+    pub total_wasted_bytes: i64,
+    /// The requested page of groups, largest waste first.
+    pub groups: Vec<DuplicateGroupDto>,
+}
+
+/// The files of one age band.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgeBand {
+    /// The smallest age in whole days that falls into the band.
+    pub min_days: u32,
+    /// The largest age in whole days that falls into the band, `None` for the oldest band.
+    pub max_days: Option<u32>,
+    /// The number of files in the band.
+    pub file_count: i64,
+    /// The allocated bytes of the files in the band.
+    pub allocated_size: i64,
+}
+
+/// The age bands of a subtree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgeBandSet {
+    /// The subtree the bands cover, `None` for the whole scan.
+    pub path: Option<String>,
+    /// The bands, youngest first.
+    pub bands: Vec<AgeBand>,
+    /// The number of files without a timestamp for the chosen basis.
+    pub undated_file_count: i64,
+    /// The allocated bytes of the files without a timestamp.
+    pub undated_allocated_size: i64,
+}
+
+/// The age distribution of the files of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgeBandsResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The timestamp the ages are based on: `"mtime"` or `"atime"`.
+    pub basis: String,
+    /// The reference time ages are measured from, in seconds since the Unix epoch.
+    pub reference_time: i64,
+    /// The upper band boundaries in days; the last band has no upper boundary.
+    pub bounds_days: Vec<u32>,
+    /// The bands of the requested subtree.
+    pub total: AgeBandSet,
+    /// The bands of each immediate child of the subtree, largest first, if
+    /// `group_depth=1` was requested.
+    pub children: Option<Vec<AgeBandSet>>,
+}
+
+/// One bucket of an age report.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgeReportBucket {
+    /// A short label for charts, e.g. `"<30d"`, `"90d-1y"`, `">2y"` or `"unknown"`.
+    pub label: String,
+    /// The smallest age in whole days that falls into the bucket, `None` for the unknown bucket.
+    pub min_days: Option<u32>,
+    /// The largest age in whole days that falls into the bucket, `None` for the
+    /// oldest and the unknown bucket.
+    pub max_days: Option<u32>,
+    /// The number of files in the bucket.
+    pub file_count: i64,
+    /// The allocated bytes of the files in the bucket.
+    pub allocated_size: i64,
+}
+
+/// A directory ranked by the bytes of the old files directly inside it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OldDirectory {
+    /// The directory.
+    pub path: String,
+    /// The number of old files in the directory.
+    pub file_count: i64,
+    /// The allocated bytes of the old files in the directory.
+    pub allocated_size: i64,
+}
+
+/// How much data of a scan has not been modified (or accessed) for how long.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgeReportResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The timestamp the ages are based on: `"mtime"` or `"atime"`.
+    pub field: String,
+    /// The reference time ages are measured from, in seconds since the Unix epoch.
+    pub reference_time: i64,
+    /// The subtree the report covers, `None` for the whole scan.
+    pub path: Option<String>,
+    /// The bucket edges in days, youngest first.
+    pub edges_days: Vec<u32>,
+    /// The buckets, youngest first, followed by the bucket of files without a timestamp.
+    pub buckets: Vec<AgeReportBucket>,
+    /// Files older than this many days (the oldest bucket) count as old.
+    pub old_after_days: u32,
+    /// The directories with the most old bytes, if `top_dirs` was requested.
+    pub top_dirs: Option<Vec<OldDirectory>>,
+}
+
+/// The kind of a treemap node.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TreemapNodeKind {
+    /// A scanned directory.
+    Dir,
+    /// The files directly inside the parent directory.
+    Files,
+    /// The children too small to draw, plus bytes of the parent no child accounts for.
+    Other,
+}
+
+/// A rectangle of a treemap.
+///
+/// The sizes of the children of a node always add up to the node's size.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TreemapNode {
+    /// What the node stands for.
+    pub kind: TreemapNodeKind,
+    /// The directory, or for `files` and `other` the parent directory.
+    pub path: String,
+    /// The display name: the last path component, `<files>` or `<other>`.
+    pub name: String,
+    /// The allocated bytes of the node.
+    pub allocated_size: i64,
+    /// The number of files in the node; for `other` only those of the coalesced directories.
+    pub file_count: i64,
+    /// The number of directories coalesced into an `other` node, `0` otherwise.
+    pub coalesced: i64,
+    /// The children, largest first; empty below the requested depth and for synthetic nodes.
+    pub children: Vec<TreemapNode>,
+}
+
+/// A nested treemap of a directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreemapResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The number of directory levels below the root that were expanded.
+    pub depth: u32,
+    /// Children smaller than this share of their parent were coalesced into `<other>`.
+    pub min_fraction: f64,
+    /// Whether expansion stopped early because the node limit was reached.
+    pub truncated: bool,
+    /// The requested directory.
+    pub root: TreemapNode,
+}
+
+/// The contribution of one file extension to a directory's compressibility.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtensionContribution {
+    /// The lowercase extension without the dot, empty for files without one.
+    pub extension: String,
+    /// The configured compression class of the extension, `None` if unclassified.
+    pub class: Option<String>,
+    /// The number of files with the extension.
+    pub file_count: i64,
+    /// The allocated bytes of the files with the extension.
+    pub allocated_size: i64,
+    /// The estimated bytes compression would free for these files.
+    pub estimated_reclaimable: i64,
+}
+
+/// The compressibility estimate of the files directly inside one directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompressibilityDirectory {
+    /// The directory.
+    pub path: String,
+    /// The number of files directly inside the directory.
+    pub file_count: i64,
+    /// The logical bytes of those files.
+    pub logical_size: i64,
+    /// The allocated bytes of those files.
+    pub allocated_size: i64,
+    /// The estimated bytes compression would free.
+    pub estimated_reclaimable: i64,
+    /// The estimated reclaimable share of the allocated bytes, between 0 and 1.
+    pub score: f64,
+    /// How much the estimate can be trusted, between 0 and 1. Low for directories
+    /// with few files or mostly unclassified extensions.
+    pub confidence: f64,
+    /// The extensions contributing most to the estimate, largest first.
+    pub top_extensions: Vec<ExtensionContribution>,
+}
+
+/// Directories of a scan ranked by how much compressing them would free.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressibilityResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The subtree the analysis covers, `None` for the whole scan.
+    pub path: Option<String>,
+    /// The estimated reclaimable bytes of all directories, not just the listed ones.
+    pub total_estimated_reclaimable: i64,
+    /// The directories, most reclaimable first.
+    pub directories: Vec<CompressibilityDirectory>,
+}
+
+/// A directory ranked by the zero-byte files directly inside it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmptyFilesDirectory {
+    /// The directory.
+    pub path: String,
+    /// The number of zero-byte files directly inside the directory.
+    pub empty_file_count: i64,
+}
+
+/// The zero-byte files of a scan, per directory or as a page of file paths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmptyFilesResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The subtree the analysis covers, `None` for the whole scan.
+    pub path: Option<String>,
+    /// Whether the counts were recorded by the scanner. `false` for scans made before
+    /// empty files were counted, whose directory counts are derived from the stored files.
+    pub counted_during_scan: bool,
+    /// The number of zero-byte files in the subtree.
+    pub total_empty_files: i64,
+    /// The directories with zero-byte files, most first; `None` when listing files.
+    pub directories: Option<Vec<EmptyFilesDirectory>>,
+    /// The requested page of zero-byte file paths, ordered by path; `None` when ranking directories.
+    pub files: Option<Vec<String>>,
+}
+
+/// A stored warning of a scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanWarningDto {
+    /// The path the warning is about.
+    pub path: String,
+    /// The warning code, e.g. `metadata_failed` or `read_dir_failed`.
+    pub code: String,
+    /// The warning message.
+    pub message: String,
+    /// When the warning was stored.
+    pub created_at: String,
+}
+
+/// A page of the stored warnings of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanWarningsResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The number of stored warnings matching the filter.
+    pub total: i64,
+    /// The warnings of the requested page, in the order they occurred.
+    pub items: Vec<ScanWarningDto>,
+}
+
+/// A symbolic link, junction or other reparse point recorded by a scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanLinkDto {
+    /// The path of the link.
+    pub path: String,
+    /// `symlink`, `junction` or `reparse_point`.
+    pub kind: String,
+    /// Where the link points, as stored in the link.
+    pub target: Option<String>,
+    /// Whether the scan descended into (or measured) the target.
+    pub followed: bool,
+    /// Whether the target lies outside the scanned root; `None` if it could not be resolved.
+    pub outside_root: Option<bool>,
+}
+
+/// A page of the links recorded by a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanLinksResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The number of links matching the filter.
+    pub total: i64,
+    /// The links of the requested page, ordered by path.
+    pub items: Vec<ScanLinkDto>,
+}
+
+/// One scanner check applied to a path by `GET /scans/{id}/explain`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathCheck {
+    /// `within_root`, `exists`, `excluded`, `symlink`, `reparse_point`, `hidden`,
+    /// `max_depth` or `included`.
+    pub check: String,
+    /// Whether the check lets the path into the scan.
+    pub passed: bool,
+    /// The exclude pattern that matched, for the `excluded` check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// What the outcome is based on, e.g. the depth compared to `max_depth`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Whether a scan's options take in a path, checked against the live filesystem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathExplanation {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The explained path.
+    pub path: String,
+    /// The root of the scan the path lies in.
+    pub root: Option<String>,
+    /// The depth of the path below its root; 0 for the root itself.
+    pub depth: Option<u32>,
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+    /// Whether the scanner takes in the path: every check and every directory above it passes.
+    pub included: bool,
+    /// The nearest directory above the path that the scanner leaves out.
+    pub skipped_ancestor: Option<String>,
+    /// The checks applied to the path, in the order the scanner applies them.
+    pub checks: Vec<PathCheck>,
+}
+
+/// A file with alternate data streams.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamFile {
+    /// The path of the file.
+    pub path: String,
+    /// The number of alternate data streams.
+    pub ads_count: i64,
+    /// The bytes in the alternate data streams.
+    pub ads_size: i64,
+    /// The logical size of the file including its streams.
+    pub logical_size: i64,
+    /// The allocated size of the file including its streams.
+    pub allocated_size: i64,
+}
+
+/// The files of a scan whose alternate data streams hold the most bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamsResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// Whether the scan measured alternate data streams at all.
+    pub measured: bool,
+    /// The threshold in bytes the stream bytes of a listed file exceed.
+    pub min_size: i64,
+    /// The number of files with more than `min_size` stream bytes.
+    pub total: i64,
+    /// The stream bytes of all those files.
+    pub total_ads_size: i64,
+    /// The files of the requested page, most stream bytes first.
+    pub items: Vec<StreamFile>,
+}
+
+/// The files of one owner below a directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OwnerUsage {
+    /// The owner: `DOMAIN\name` or a SID string on Windows, the user ID elsewhere,
+    /// "unknown" if it could not be read.
+    pub owner: String,
+    /// The number of files owned.
+    pub file_count: i64,
+    /// The logical size of those files.
+    pub logical_size: i64,
+    /// The allocated size of those files.
+    pub allocated_size: i64,
+}
+
+/// The owners of the files below a directory, ranked by allocated bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnersResponse {
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The subtree the totals cover; `None` for the whole scan.
+    pub path: Option<String>,
+    /// Whether the scan captured file owners at all.
+    pub captured: bool,
+    /// The owners, most allocated bytes first.
+    pub items: Vec<OwnerUsage>,
+}
+
+/// The share of one root in a scan with several roots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootSummary {
+    /// The root path as given in the scan request.
+    pub root: String,
+    /// The number of directories below and including the root.
+    pub dir_count: u64,
+    /// The number of files below the root.
+    pub file_count: u64,
+    /// The logical size of the files below the root.
+    pub logical_size: u64,
+    /// The allocated size of the files below the root.
+    pub allocated_size: u64,
+    /// The number of warnings raised while scanning the root.
+    pub warning_count: u64,
+}
+
+/// The version of the [`ScanManifest`] format; raised on incompatible changes.
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+/// A scan with everything external tools usually stitch together from
+/// several endpoints, as returned by `GET /scans/{id}/manifest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanManifest {
+    /// The format version, see [`MANIFEST_SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// When the manifest was generated (RFC 3339).
+    pub generated_at: String,
+    /// The summary of the scan, as returned by `GET /scans/{id}`.
+    pub scan: ScanSummary,
+    /// The options the scan ran with; `None` if the stored options cannot be read.
+    pub options: Option<ScanOptions>,
+    /// The roots of the scan in request order.
+    pub roots: Vec<ManifestRoot>,
+    /// The directories with the most allocated bytes, largest first.
+    pub top_dirs: Vec<TopItem>,
+}
+
+/// One root of a [`ScanManifest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestRoot {
+    /// The root path.
+    pub path: String,
+    /// The subtotals of the root; `None` if they were not recorded.
+    pub totals: Option<RootSummary>,
+    /// The drive holding the root at the time of the request; `None` if it is
+    /// not among the enumerated drives.
+    pub drive: Option<DriveInfo>,
+}
+
+/// A summary of a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    /// The ID of the scan.
+    pub id: Uuid,
+    /// The status of the scan: `running`, `done`, `partial`, `canceled`, `failed` or `interrupted`.
+    ///
+    /// `partial` scans were cancelled with `finalize=true` and keep the records
+    /// persisted up to that point; their totals cover only those records.
+    /// `interrupted` scans were still running when the server stopped.
+    /// `importing` scans are uploads that have not been committed yet.
+    pub status: String,
+    /// The start time of the scan.
+    pub started_at: Option<String>,
+    /// The end time of the scan.
+    pub finished_at: Option<String>,
+    /// The total logical size of all files scanned.
+    pub total_logical_size: i64,
+    /// The total allocated size of all files scanned.
+    pub total_allocated_size: i64,
+    /// The total number of directories scanned.
+    pub dir_count: i64,
+    /// The total number of files scanned.
+    pub file_count: i64,
+    /// The number of warnings generated during the scan.
+    pub warning_count: i64,
+    /// The allocated bytes not counted twice because they belong to an already counted hardlink.
+    pub dedup_saved_bytes: i64,
+    /// The namespace the scan belongs to.
+    pub namespace: String,
+    /// The server an imported scan came from (see `POST /import/sessions`),
+    /// `None` for scans made by this server.
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// The root paths of the scan in request order.
+    #[serde(default)]
+    pub root_paths: Vec<String>,
+    /// The options the scan ran with, defaults applied. Only filled by
+    /// `GET /scans/{id}`; `None` if the stored options cannot be read.
+    #[serde(default)]
+    pub options: Option<ScanOptions>,
+    /// Whether path operations refuse to modify anything below the scan's roots.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Whether the scan ran with backup privileges; `false` if `access_mode`
+    /// was `backup` but the privilege could not be enabled.
+    #[serde(default)]
+    pub backup_mode: bool,
+    /// Whether the scan was cut off, by a server restart (`interrupted`) or a
+    /// finalizing cancel (`partial`), and can be continued with `POST /scans/{id}/resume`.
+    pub resumable: bool,
+    /// How long the scan ran until it ended, also for cancelled and failed
+    /// scans; `None` while it runs. A resumed scan reports its last run.
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    /// The directories stored per second of `duration_ms`.
+    #[serde(default)]
+    pub dirs_per_sec: Option<f64>,
+    /// The files stored per second of `duration_ms`.
+    #[serde(default)]
+    pub files_per_sec: Option<f64>,
+    /// The allocated bytes stored per second of `duration_ms`.
+    #[serde(default)]
+    pub bytes_per_sec: Option<f64>,
+    /// The subtotals per root, in the order of the request, as recorded when
+    /// the scan ended. Only filled by `GET /scans/{id}`; empty for scans that
+    /// have not ended or were made before subtotals were recorded.
+    #[serde(default)]
+    pub roots: Vec<RootSummary>,
+}
+
+/// The totals of an earlier scan of the same roots, the denominator of a scan's estimated progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedTotals {
+    /// The ID of the earlier scan.
+    pub scan_id: Uuid,
+    /// The number of directories it found.
+    pub dirs: u64,
+    /// The number of files it found.
+    pub files: u64,
+}
+
+/// An event that occurs during a scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanEvent {
+    /// The scan has started.
+    Started {
+        /// The root paths of the scan.
+        root_paths: Vec<String>,
+        /// The totals of the last finished scan of the same roots, `null` if there is none.
+        #[serde(default)]
+        expected_totals: Option<ExpectedTotals>,
+    },
+    /// A progress update.
+    Progress {
+        /// The path currently being scanned.
+        current_path: String,
+        /// The number of directories scanned so far.
+        dirs_scanned: u64,
+        /// The number of files scanned so far.
+        files_scanned: u64,
+        /// The logical size of the scanned files so far.
+        logical_size: u64,
+        /// The allocated size of the scanned files so far.
+        allocated_size: u64,
+        /// The share of the expected totals scanned so far, at most 99 until the scan is done;
+        /// `null` without `expected_totals`.
+        #[serde(default)]
+        estimated_percent: Option<u8>,
+    },
+    /// A progress update of a duplicate search.
+    HashProgress {
+        /// The file hashed most recently.
+        current_path: String,
+        /// The number of candidate files hashed so far.
+        files_hashed: u64,
+        /// The number of candidate files.
+        files_total: u64,
+        /// The number of bytes hashed so far.
+        bytes_hashed: u64,
+        /// The total size of all candidate files.
+        bytes_total: u64,
+    },
+    /// The duplicate search has completed.
+    DuplicatesDone {
+        /// The number of duplicate groups found.
+        groups: u64,
+        /// The bytes that would be freed by keeping one file of every group.
+        wasted_bytes: u64,
+    },
+    /// A watched scan has applied a batch of filesystem changes.
+    Updated {
+        /// The number of changed paths applied in the batch.
+        changed_paths: u64,
+        /// The total number of directories after the batch.
+        total_dirs: u64,
+        /// The total number of files after the batch.
+        total_files: u64,
+        /// The total logical size after the batch.
+        total_logical_size: u64,
+        /// The total allocated size after the batch.
+        total_allocated_size: u64,
+    },
+    /// A warning has occurred.
+    Warning {
+        /// The path associated with the warning.
+        path: String,
+        /// The warning code.
+        code: String,
+        /// The warning message.
+        message: String,
+    },
+    /// The scan has been paused; workers wait before starting their next directory.
+    Paused,
+    /// The paused scan has been resumed.
+    Resumed,
+    /// The scan has completed.
+    Done {
+        /// The total number of directories scanned.
+        total_dirs: u64,
+        /// The total number of files scanned.
+        total_files: u64,
+        /// The total logical size of all files scanned.
+        total_logical_size: u64,
+        /// The total allocated size of all files scanned.
+        total_allocated_size: u64,
+        /// The subtotals per root, in the order of the request.
+        #[serde(default)]
+        roots: Vec<RootSummary>,
+        /// How long the scan ran.
+        #[serde(default)]
+        duration_ms: u64,
+        /// The directories scanned per second.
+        #[serde(default)]
+        dirs_per_sec: f64,
+        /// The files scanned per second.
+        #[serde(default)]
+        files_per_sec: f64,
+        /// The allocated bytes scanned per second.
+        #[serde(default)]
+        bytes_per_sec: f64,
+        /// The most bytes the scanner's buffers held at the same time.
+        #[serde(default)]
+        buffer_peak_bytes: u64,
+    },
+    /// The scan has been cancelled.
+    Cancelled,
+    /// The scan was cut off by a server restart.
+    ///
+    /// Sent instead of a live stream to clients that subscribe to an interrupted scan.
+    Interrupted {
+        /// Whether the scan can be started again from its stored roots and options.
+        resumable: bool,
+    },
+    /// The scan has failed.
+    Failed {
+        /// The error message.
+        message: String,
+    },
+    /// Sent periodically as the SSE event `heartbeat` while a stream is open.
+    ///
+    /// Unlike the keep-alive comments it reaches the client's event handling,
+    /// so a client that misses several heartbeats knows the stream stalled.
+    Heartbeat {
+        /// The server time in seconds since the Unix epoch.
+        timestamp: i64,
+    },
+}
+
+/// The response from the search endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// The search results.
+    pub items: Vec<SearchItem>,
+    /// The total number of matching items.
+    pub total_count: i64,
+    /// The original search query.
+    pub query: String,
+    /// Whether a name glob too complex for SQL stopped at its row cap, so
+    /// `total_count` and the pages only cover the rows read until then.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// An item in the search results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SearchItem {
+    /// A directory search result.
+    Dir {
+        /// The path of the directory.
+        path: String,
+        /// The name of the directory.
+        name: String,
+        /// The allocated size of the directory in bytes.
+        allocated_size: i64,
+        /// The logical size of the directory in bytes.
+        logical_size: i64,
+        /// The number of files in the directory.
+        file_count: i64,
+        /// The number of subdirectories in the directory.
+        dir_count: i64,
+        /// The depth of the directory in the directory tree.
+        depth: i64,
+    },
+    /// A file search result.
+    File {
+        /// The path of the file.
+        path: String,
+        /// The name of the file.
+        name: String,
+        /// The allocated size of the file in bytes.
+        allocated_size: i64,
+        /// The logical size of the file in bytes.
+        logical_size: i64,
+        /// The file extension.
+        extension: Option<String>,
+    },
+}
+
+/// The response from the cross-scan search endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchResult {
+    /// The search results.
+    pub items: Vec<GlobalSearchHit>,
+    /// The total number of matching items after deduplication.
+    pub total_count: i64,
+    /// The original search query.
+    pub query: String,
+    /// The scans that were searched.
+    pub scans: Vec<Uuid>,
+}
+
+/// An item in the cross-scan search results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchHit {
+    /// The scan the item was found in.
+    pub scan_id: Uuid,
+    /// When that scan was started.
+    pub started_at: String,
+    /// The root path of that scan which contains the item.
+    pub root_path: Option<String>,
+    /// The matched file or directory.
+    #[serde(flatten)]
+    pub item: SearchItem,
+}
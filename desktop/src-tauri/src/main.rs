@@ -15,13 +15,18 @@
 //! ## Features
 //!
 //! - Automatic backend discovery in multiple locations
+//! - Reuse of an already running backend via its discovery file
 //! - Dynamic port allocation for avoiding conflicts
 //! - Health check verification before opening main window
+//! - Automatic restart of a crashed backend with bounded backoff
+//! - The backend's stderr tail on the error page when it keeps crashing
 //! - Proper cleanup on application exit
 //! - User-friendly error messages in German
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod supervisor;
+
 use std::{
   env,
   collections::HashSet,
@@ -29,11 +34,15 @@ use std::{
   net::{TcpListener, TcpStream},
   path::PathBuf,
   process::{Child, Command, Stdio},
-  sync::Mutex,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
   thread,
-  time::Duration,
+  time::{Duration, Instant},
 };
-use tauri::{Manager, WindowUrl};
+use supervisor::{RestartDecision, RestartPolicy, StderrTail};
+use tauri::{AppHandle, Manager, WindowUrl};
 
 /// Application state for managing the backend process.
 ///
@@ -44,8 +53,26 @@ struct BackendState {
   child: Mutex<Option<Child>>,
   /// The port number the backend is running on
   port: u16,
+  /// Set when the window closes so the supervisor stops restarting the backend
+  shutting_down: AtomicBool,
+  /// The last output of the backend on stderr (release builds only)
+  stderr_tail: Arc<StderrTail>,
+  /// What the supervisor observed, e.g. for an "About" dialog
+  status: Mutex<BackendStatus>,
+}
+
+/// Restarts and exits of the backend seen by the supervisor.
+#[derive(Debug, Clone, Default)]
+struct BackendStatus {
+  /// How often the backend was restarted after it crashed
+  restart_count: u32,
+  /// The exit status of the last backend process that ended, e.g. `exit code: 3`
+  last_exit_status: Option<String>,
 }
 
+/// The Tauri event emitted after the backend was restarted.
+const BACKEND_RESTARTED_EVENT: &str = "backend-restarted";
+
 /// Finds an available TCP port on the localhost interface.
 ///
 /// Binds to port 0 which automatically selects an available port,
@@ -116,6 +143,7 @@ fn candidate_backend_paths() -> Vec<PathBuf> {
 /// # Arguments
 ///
 /// * `port` - The port number on which the backend should listen
+/// * `stderr_tail` - Where the backend's stderr is collected in release builds
 ///
 /// # Returns
 ///
@@ -126,8 +154,9 @@ fn candidate_backend_paths() -> Vec<PathBuf> {
 /// - Avoids spawning the desktop executable itself (recursion prevention)
 /// - Sets up environment for user-writable database location
 /// - In debug mode, inherits stdout/stderr for development visibility
-/// - In release mode, suppresses output for cleaner user experience
-fn spawn_backend(port: u16) -> anyhow::Result<Child> {
+/// - In release mode, suppresses stdout and keeps the tail of stderr for the error page
+#[cfg_attr(debug_assertions, allow(unused_variables))]
+fn spawn_backend(port: u16, stderr_tail: &Arc<StderrTail>) -> anyhow::Result<Child> {
   let mut last_err: Option<anyhow::Error> = None;
   let self_path = env::current_exe().ok().and_then(|p| p.canonicalize().ok());
   for cand in candidate_backend_paths() {
@@ -145,13 +174,20 @@ fn spawn_backend(port: u16) -> anyhow::Result<Child> {
       if let Some(dir) = cand.parent() { cmd.current_dir(dir); }
       cmd.env("SPEICHERWALD__SERVER__PORT", format!("{}", port))
         .env("SPEICHERWALD__SERVER__HOST", "127.0.0.1")
+        // The WebView talks plain HTTP to the loopback backend
+        .env("SPEICHERWALD__SERVER__TLS__ENABLED", "false")
         .envs(user_writable_envs());
       #[cfg(debug_assertions)]
       { cmd.stdout(Stdio::inherit()).stderr(Stdio::inherit()); }
       #[cfg(not(debug_assertions))]
-      { cmd.stdout(Stdio::null()).stderr(Stdio::null()); }
+      { cmd.stdout(Stdio::null()).stderr(Stdio::piped()); }
       match cmd.spawn() {
-        Ok(child) => return Ok(child),
+        #[allow(unused_mut)]
+        Ok(mut child) => {
+          #[cfg(not(debug_assertions))]
+          if let Some(stderr) = child.stderr.take() { stderr_tail.capture(stderr); }
+          return Ok(child);
+        }
         Err(e) => { eprintln!("[desktop] failed to spawn {:?}: {}", cand, e); last_err = Some(anyhow::anyhow!(e)); }
       }
     }
@@ -194,6 +230,89 @@ fn wait_until_ready(port: u16, timeout_ms: u64) -> bool {
   false
 }
 
+/// Returns the location of the backend discovery file.
+///
+/// Mirrors `discovery::default_path` in the backend: `SPEICHERWALD_DISCOVERY_FILE`,
+/// then `%LOCALAPPDATA%\SpeicherWald\backend.json` on Windows or
+/// `$XDG_DATA_HOME/speicherwald/backend.json` (default `~/.local/share`) elsewhere.
+fn discovery_file_path() -> Option<PathBuf> {
+  if let Ok(p) = env::var("SPEICHERWALD_DISCOVERY_FILE") {
+    if !p.trim().is_empty() { return Some(PathBuf::from(p)); }
+  }
+  #[cfg(windows)]
+  {
+    env::var("LOCALAPPDATA").ok().map(|d| PathBuf::from(d).join("SpeicherWald").join("backend.json"))
+  }
+  #[cfg(not(windows))]
+  {
+    let base = env::var("XDG_DATA_HOME").ok().filter(|d| !d.is_empty()).map(PathBuf::from)
+      .or_else(|| env::var("HOME").ok().map(|h| PathBuf::from(h).join(".local").join("share")))?;
+    Some(base.join("speicherwald").join("backend.json"))
+  }
+}
+
+/// Checks whether a process with the given PID is still running.
+///
+/// # Notes
+///
+/// - Uses `tasklist` on Windows and `kill -0` elsewhere
+/// - If the check itself fails the process is assumed to be alive; the
+///   subsequent health check decides in that case
+fn pid_alive(pid: u32) -> bool {
+  if pid == 0 { return false; }
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    match Command::new("tasklist")
+      .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+      .creation_flags(CREATE_NO_WINDOW)
+      .output()
+    {
+      Ok(out) => String::from_utf8_lossy(&out.stdout).contains(&format!("\"{}\"", pid)),
+      Err(_) => true,
+    }
+  }
+  #[cfg(not(windows))]
+  {
+    match Command::new("kill").arg("-0").arg(pid.to_string()).output() {
+      Ok(out) => out.status.success() || String::from_utf8_lossy(&out.stderr).contains("not permitted"),
+      Err(_) => true,
+    }
+  }
+}
+
+/// Looks for a backend that is already running for this user.
+///
+/// Reads the discovery file written by the backend and accepts it only if the
+/// process is alive, it does not require a bearer token (the webview cannot
+/// send one) and it answers the health check.
+///
+/// # Returns
+///
+/// The port of the running backend, or `None` if a new one must be spawned
+fn find_running_backend() -> Option<u16> {
+  let path = discovery_file_path()?;
+  let data = std::fs::read(&path).ok()?;
+  let info: serde_json::Value = serde_json::from_slice(&data).ok()?;
+  let port = u16::try_from(info.get("port")?.as_u64()?).ok()?;
+  let pid = u32::try_from(info.get("pid")?.as_u64()?).ok()?;
+  if info.get("auth").and_then(|a| a.as_str()).unwrap_or("none") != "none" {
+    eprintln!("[desktop] running backend requires auth, not reusing it");
+    return None;
+  }
+  if !pid_alive(pid) {
+    eprintln!("[desktop] stale discovery file {} (pid {} not running)", path.display(), pid);
+    return None;
+  }
+  if wait_until_ready(port, 1_000) {
+    eprintln!("[desktop] reusing running backend on port {} (pid {})", port, pid);
+    Some(port)
+  } else {
+    None
+  }
+}
+
 /// Terminates the backend server process gracefully.
 ///
 /// Sends a termination signal to the backend process and waits for it to exit.
@@ -224,6 +343,123 @@ fn kill_backend(child: &mut Option<Child>) {
   *child = None;
 }
 
+/// How often the supervisor checks whether the backend child is still running.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Restarts the backend child whenever it exits unexpectedly.
+///
+/// The backend is started again on the same port, so the web UI reconnects by
+/// itself and sees scans cut off by the crash as interrupted. A backend that
+/// exits with a non-zero code or by a signal is restarted following
+/// [`RestartPolicy`]; every restart is announced in the window. When the
+/// policy gives up, a native notification tells the user and the window shows
+/// the error page with the backend's last stderr output.
+///
+/// # Arguments
+///
+/// * `app` - The application handle holding the `BackendState`
+///
+/// # Notes
+///
+/// - Polls the child with `try_wait`, as the window's close handler needs the
+///   handle to kill it
+/// - Runs until the window closes, the backend exits normally or the policy gives up
+/// - Only used for a backend this app spawned itself, not for a reused one
+fn supervise_backend(app: AppHandle) {
+  let mut policy = RestartPolicy::default();
+  loop {
+    thread::sleep(SUPERVISE_INTERVAL);
+    let state = app.state::<BackendState>();
+    if state.shutting_down.load(Ordering::SeqCst) { return; }
+    let exit = {
+      let mut guard = state.child.lock().unwrap();
+      let exit = match guard.as_mut().map(|ch| ch.try_wait()) {
+        Some(Ok(None)) => None,
+        Some(Ok(Some(status))) => Some(Some(status)),
+        Some(Err(e)) => {
+          eprintln!("[desktop] backend status unavailable: {}", e);
+          Some(None)
+        }
+        // the last restart failed to spawn
+        None => Some(None),
+      };
+      if exit.is_some() { *guard = None; }
+      exit
+    };
+    let Some(status) = exit else { continue };
+    let reason = match status {
+      Some(status) => {
+        state.status.lock().unwrap().last_exit_status = Some(status.to_string());
+        if status.success() {
+          eprintln!("[desktop] backend exited normally, not restarting");
+          return;
+        }
+        format!("exited with {}", status)
+      }
+      None => "not running".to_string(),
+    };
+
+    match policy.on_exit(Instant::now()) {
+      RestartDecision::Restart { after } => {
+        eprintln!("[desktop] backend {}, restarting in {:?}", reason, after);
+        thread::sleep(after);
+        if state.shutting_down.load(Ordering::SeqCst) { return; }
+        match spawn_backend(state.port, &state.stderr_tail) {
+          Ok(child) => {
+            {
+              let mut guard = state.child.lock().unwrap();
+              *guard = Some(child);
+              // the window may have closed while we were spawning
+              if state.shutting_down.load(Ordering::SeqCst) { kill_backend(&mut *guard); return; }
+            }
+            let restart_count = {
+              let mut status = state.status.lock().unwrap();
+              status.restart_count += 1;
+              status.restart_count
+            };
+            if wait_until_ready(state.port, 10_000) {
+              announce_restart(&app, restart_count);
+            }
+          }
+          Err(e) => eprintln!("[desktop] backend restart failed: {}", e),
+        }
+      }
+      RestartDecision::GiveUp => {
+        eprintln!("[desktop] backend {}, giving up after repeated crashes", reason);
+        let _ = tauri::api::notification::Notification::new(&app.config().tauri.bundle.identifier)
+          .title("SpeicherWald – Backend abgestürzt")
+          .body("Das Backend ist wiederholt abgestürzt und wird nicht mehr neu gestartet. Bitte starten Sie SpeicherWald neu.")
+          .show();
+        let status = state.status.lock().unwrap().clone();
+        let error = format!(
+          "Das Backend ist wiederholt abgestürzt ({}-mal neu gestartet, zuletzt: {}).",
+          status.restart_count,
+          status.last_exit_status.as_deref().unwrap_or(&reason)
+        );
+        show_error_page(&app, &error_page_html(&error, &state.stderr_tail.text()));
+        return;
+      }
+    }
+  }
+}
+
+/// Tells the web UI that the backend was restarted.
+///
+/// Emits [`BACKEND_RESTARTED_EVENT`] and shows a toast in the page. The UI is
+/// served by the backend itself and has no Tauri IPC, so the toast is added
+/// to its `#toasts` container by script, styled like the UI's own toasts.
+fn announce_restart(app: &AppHandle, restart_count: u32) {
+  let _ = app.emit_all(BACKEND_RESTARTED_EVENT, serde_json::json!({ "restart_count": restart_count }));
+  if let Some(window) = app.get_window("main") {
+    let _ = window.eval(
+      "(function(){var c=document.getElementById('toasts');if(!c)return;\
+       var t=document.createElement('div');t.className='toast fade-in';\
+       t.textContent='Backend neu gestartet';c.appendChild(t);\
+       setTimeout(function(){t.remove();},4000);})();",
+    );
+  }
+}
+
 /// Generates environment variables for user-writable locations.
 ///
 /// Sets up environment variables to ensure the SQLite database is stored
@@ -294,6 +530,59 @@ fn percent_encode_for_data_url(input: &str) -> String {
   s
 }
 
+/// Escapes text for use in HTML element content and attributes.
+fn escape_html(input: &str) -> String {
+  input.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\'', "&#39;").replace('"', "&quot;")
+}
+
+/// Builds the error page shown when the backend cannot be started or keeps crashing.
+///
+/// # Arguments
+///
+/// * `error` - The error message, plain text
+/// * `stderr_tail` - The backend's last stderr output; left out if empty
+fn error_page_html(error: &str, stderr_tail: &str) -> String {
+  let stderr_block = if stderr_tail.trim().is_empty() {
+    String::new()
+  } else {
+    format!(
+      "<p>Letzte Ausgabe des Backends:</p>\n  <pre style='background:#f4f4f4; padding:10px; max-height:300px; overflow:auto; white-space:pre-wrap;'>{}</pre>\n",
+      escape_html(stderr_tail)
+    )
+  };
+  format!(r#"<html><head><meta charset='utf-8'><title>SpeicherWald – Fehler</title></head>
+<body style='font-family:Segoe UI, sans-serif; padding:20px;'>
+  <h2>SpeicherWald – Backend konnte nicht gestartet werden</h2>
+  <p style='color:#b00020;'>{}</p>
+  {}<p>Bitte prüfen Sie:</p>
+  <ul>
+    <li>Liegt <code>speicherwald.exe</code> im selben Ordner wie <code>SpeicherWald.exe</code>?</li>
+    <li>Wurde die Datei ggf. von SmartScreen blockiert? Rechtsklick → Eigenschaften → Zulassen.</li>
+    <li>Test: Starten Sie <code>speicherwald.exe</code> in PowerShell und öffnen Sie dann <a href='http://127.0.0.1:8080/'>http://127.0.0.1:8080/</a>.</li>
+  </ul>
+</body></html>"#, escape_html(error), stderr_block)
+}
+
+/// Shows the error page in the main window, creating the window if needed.
+///
+/// An open window gets the page written into its document, as webviews refuse
+/// to navigate from the UI's origin to a `data:` URL.
+fn show_error_page(app: &AppHandle, html: &str) {
+  if let Some(window) = app.get_window("main") {
+    let html = serde_json::to_string(html).unwrap_or_default();
+    let _ = window.eval(&format!("document.open();document.write({});document.close();", html));
+    let _ = window.set_title("SpeicherWald – Fehler");
+    return;
+  }
+  let url = WindowUrl::External(
+    format!("data:text/html,{}", percent_encode_for_data_url(html)).parse().unwrap()
+  );
+  let _ = tauri::WindowBuilder::new(app, "main", url)
+    .title("SpeicherWald – Fehler")
+    .inner_size(900.0, 600.0)
+    .build();
+}
+
 /// Main entry point for the SpeicherWald desktop application.
 ///
 /// Sets up and runs the Tauri application, handling backend process management,
@@ -302,24 +591,41 @@ fn percent_encode_for_data_url(input: &str) -> String {
 ///
 /// # Application Flow
 ///
-/// 1. Find an available port for the backend
-/// 2. Attempt to spawn the backend server process
+/// 1. Reuse a running backend from the discovery file, or find an available port
+/// 2. Otherwise attempt to spawn the backend server process
 /// 3. If successful: wait for backend to be ready, then open main window
 /// 4. If failed: show error window with troubleshooting information
-/// 5. Handle window close events by properly cleaning up the backend
+/// 5. Supervise a spawned backend and restart it after crashes
+/// 6. Handle window close events by properly cleaning up the backend
 fn main() {
-  let port = find_free_port();
+  let existing_port = find_running_backend();
+  let port = existing_port.unwrap_or_else(find_free_port);
 
   tauri::Builder::default()
     .setup(move |app| {
-      // launch backend
-      let child_res = spawn_backend(port);
+      // launch backend unless one is already running (then there is no child to manage)
+      let stderr_tail = Arc::new(StderrTail::default());
+      let child_res =
+        if existing_port.is_some() { Ok(None) } else { spawn_backend(port, &stderr_tail).map(Some) };
 
       match child_res {
         Ok(child) => {
-          let state = BackendState { child: Mutex::new(Some(child)), port };
+          let supervised = child.is_some();
+          let state = BackendState {
+            child: Mutex::new(child),
+            port,
+            shutting_down: AtomicBool::new(false),
+            stderr_tail,
+            status: Mutex::new(BackendStatus::default()),
+          };
           app.manage(state);
 
+          // restart our own backend if it crashes; a reused backend is not ours to manage
+          if supervised {
+            let app_handle = app.handle();
+            thread::spawn(move || supervise_backend(app_handle));
+          }
+
           // wait until ready and then open window
           {
             let app_handle = app.handle();
@@ -351,25 +657,7 @@ fn main() {
         }
         Err(e) => {
           // Show an informative window instead of exiting silently
-          let app_handle = app.handle();
-          let html = format!(r#"<html><head><meta charset='utf-8'><title>SpeicherWald – Fehler</title></head>
-<body style='font-family:Segoe UI, sans-serif; padding:20px;'>
-  <h2>SpeicherWald – Backend konnte nicht gestartet werden</h2>
-  <p style='color:#b00020;'>Fehler: {}</p>
-  <p>Bitte prüfen Sie:</p>
-  <ul>
-    <li>Liegt <code>speicherwald.exe</code> im selben Ordner wie <code>SpeicherWald.exe</code>?</li>
-    <li>Wurde die Datei ggf. von SmartScreen blockiert? Rechtsklick → Eigenschaften → Zulassen.</li>
-    <li>Test: Starten Sie <code>speicherwald.exe</code> in PowerShell und öffnen Sie dann <a href='http://127.0.0.1:8080/'>http://127.0.0.1:8080/</a>.</li>
-  </ul>
-</body></html>"#, e);
-          let url = WindowUrl::External(
-            format!("data:text/html,{}", percent_encode_for_data_url(&html)).parse().unwrap()
-          );
-          let _ = tauri::WindowBuilder::new(&app_handle, "main", url)
-            .title("SpeicherWald – Fehler")
-            .inner_size(900.0, 600.0)
-            .build();
+          show_error_page(&app.handle(), &error_page_html(&format!("Fehler: {}", e), &stderr_tail.text()));
           Ok(())
         }
       }
@@ -377,6 +665,7 @@ fn main() {
     .on_window_event(|event| {
       if let tauri::WindowEvent::CloseRequested { .. } = event.event() {
         if let Some(state) = event.window().try_state::<BackendState>() {
+          state.shutting_down.store(true, Ordering::SeqCst);
           let mut guard = state.child.lock().unwrap();
           kill_backend(&mut *guard);
         }
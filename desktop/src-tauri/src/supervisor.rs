@@ -0,0 +1,194 @@
+//! Restart policy for the backend child process.
+//!
+//! The desktop shell restarts a crashed backend on the same port so the web UI
+//! can reconnect. Restarts back off exponentially, and once too many happen
+//! within a time window the shell gives up instead of looping forever. The
+//! last output the backend wrote to stderr is kept in a [`StderrTail`] so the
+//! error page can show why it crashed.
+
+use std::{
+  collections::VecDeque,
+  io::Read,
+  sync::{Arc, Mutex},
+  thread,
+  time::{Duration, Instant},
+};
+
+/// What to do after the backend process exited unexpectedly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartDecision {
+  /// Start the backend again after waiting the given delay.
+  Restart { after: Duration },
+  /// Stop restarting; the backend crashed too often.
+  GiveUp,
+}
+
+/// Bounded restarts with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+  /// The maximum number of restarts within `window`
+  max_restarts: usize,
+  /// The time window restarts are counted in
+  window: Duration,
+  /// The delay before the first restart in a window
+  base_backoff: Duration,
+  /// The upper bound of the delay
+  max_backoff: Duration,
+  /// When the restarts within the current window happened
+  restarts: VecDeque<Instant>,
+}
+
+impl Default for RestartPolicy {
+  /// 3 restarts per 10 minutes, starting at 500 ms and doubling up to 30 s.
+  fn default() -> Self {
+    Self::new(3, Duration::from_secs(600), Duration::from_millis(500), Duration::from_secs(30))
+  }
+}
+
+impl RestartPolicy {
+  /// Creates a restart policy.
+  ///
+  /// # Arguments
+  ///
+  /// * `max_restarts` - The maximum number of restarts within `window`
+  /// * `window` - The time window restarts are counted in
+  /// * `base_backoff` - The delay before the first restart
+  /// * `max_backoff` - The upper bound of the delay
+  pub fn new(max_restarts: usize, window: Duration, base_backoff: Duration, max_backoff: Duration) -> Self {
+    Self { max_restarts, window, base_backoff, max_backoff, restarts: VecDeque::new() }
+  }
+
+  /// Decides how to react to a backend exit and records the restart.
+  ///
+  /// # Arguments
+  ///
+  /// * `now` - The time the exit was noticed
+  ///
+  /// # Returns
+  ///
+  /// `Restart` with the delay to wait, or `GiveUp` once `max_restarts`
+  /// restarts happened within `window`
+  pub fn on_exit(&mut self, now: Instant) -> RestartDecision {
+    while let Some(first) = self.restarts.front() {
+      if now.saturating_duration_since(*first) >= self.window {
+        self.restarts.pop_front();
+      } else {
+        break;
+      }
+    }
+    if self.restarts.len() >= self.max_restarts {
+      return RestartDecision::GiveUp;
+    }
+    let factor = 1u32.checked_shl(self.restarts.len() as u32).unwrap_or(u32::MAX);
+    let after = self.base_backoff.saturating_mul(factor).min(self.max_backoff);
+    self.restarts.push_back(now);
+    RestartDecision::Restart { after }
+  }
+}
+
+/// The last bytes the backend wrote to stderr, shared by all its restarts.
+#[derive(Debug, Default)]
+pub struct StderrTail {
+  buf: Mutex<VecDeque<u8>>,
+}
+
+impl StderrTail {
+  /// The number of bytes kept; older output is dropped.
+  pub const CAPACITY: usize = 16 * 1024;
+
+  /// Appends output, dropping the oldest bytes beyond [`Self::CAPACITY`].
+  pub fn push(&self, bytes: &[u8]) {
+    let mut buf = self.buf.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let bytes = &bytes[bytes.len().saturating_sub(Self::CAPACITY)..];
+    let overflow = (buf.len() + bytes.len()).saturating_sub(Self::CAPACITY);
+    buf.drain(..overflow);
+    buf.extend(bytes);
+  }
+
+  /// Returns the kept output as text; invalid UTF-8 is replaced.
+  pub fn text(&self) -> String {
+    let buf = self.buf.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    String::from_utf8_lossy(&buf.iter().copied().collect::<Vec<u8>>()).into_owned()
+  }
+
+  /// Copies everything `reader` yields into the tail on a background thread
+  /// until the stream ends, i.e. the backend exited.
+  pub fn capture(self: &Arc<Self>, mut reader: impl Read + Send + 'static) {
+    let tail = self.clone();
+    thread::spawn(move || {
+      let mut chunk = [0u8; 4096];
+      while let Ok(n) = reader.read(&mut chunk) {
+        if n == 0 { break; }
+        tail.push(&chunk[..n]);
+      }
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy() -> RestartPolicy {
+    RestartPolicy::new(3, Duration::from_secs(60), Duration::from_secs(1), Duration::from_secs(3))
+  }
+
+  #[test]
+  fn backoff_doubles_up_to_the_cap() {
+    let mut p = RestartPolicy::new(5, Duration::from_secs(60), Duration::from_secs(1), Duration::from_secs(3));
+    let now = Instant::now();
+    let delays: Vec<_> = (0..4).map(|_| p.on_exit(now)).collect();
+    assert_eq!(
+      delays,
+      vec![
+        RestartDecision::Restart { after: Duration::from_secs(1) },
+        RestartDecision::Restart { after: Duration::from_secs(2) },
+        RestartDecision::Restart { after: Duration::from_secs(3) },
+        RestartDecision::Restart { after: Duration::from_secs(3) },
+      ]
+    );
+  }
+
+  #[test]
+  fn gives_up_after_too_many_restarts_in_window() {
+    let mut p = policy();
+    let start = Instant::now();
+    for i in 0..3 {
+      assert!(matches!(p.on_exit(start + Duration::from_secs(i)), RestartDecision::Restart { .. }));
+    }
+    assert_eq!(p.on_exit(start + Duration::from_secs(10)), RestartDecision::GiveUp);
+  }
+
+  #[test]
+  fn old_restarts_leave_the_window() {
+    let mut p = policy();
+    let start = Instant::now();
+    for _ in 0..3 {
+      p.on_exit(start);
+    }
+    // A minute later the earlier crashes no longer count, backoff starts over
+    assert_eq!(
+      p.on_exit(start + Duration::from_secs(60)),
+      RestartDecision::Restart { after: Duration::from_secs(1) }
+    );
+  }
+
+  #[test]
+  fn stderr_tail_keeps_the_latest_bytes() {
+    let tail = Arc::new(StderrTail::default());
+    tail.push(b"started\n");
+    tail.push(&vec![b'x'; StderrTail::CAPACITY]);
+    tail.push(b"panicked at db.rs");
+    let text = tail.text();
+    assert_eq!(text.len(), StderrTail::CAPACITY);
+    assert!(text.ends_with("xpanicked at db.rs"));
+
+    let captured = Arc::new(StderrTail::default());
+    captured.capture(std::io::Cursor::new(b"error: database is locked".to_vec()));
+    let start = Instant::now();
+    while captured.text().is_empty() && start.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(captured.text(), "error: database is locked");
+  }
+}
@@ -0,0 +1,462 @@
+//! Online backups and restores of the database.
+//!
+//! A backup is a consistent copy of the live database written with
+//! `VACUUM INTO` and sent gzip-compressed. A restore takes such a file (gzip or
+//! plain), checks that it is an intact SQLite database with the schema version
+//! of [`crate::db::SCHEMA_VERSION`] and the tables of the live database, and
+//! then replaces the contents of every table in one transaction. The pools stay
+//! open, so handlers holding a clone of the state see the restored data right
+//! away.
+//!
+//! Neither runs while a scan job is active. A restore also waits for watchers
+//! to be stopped, since they write to scans the backup may not contain.
+
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use axum::body::{Body, Bytes};
+use futures::StreamExt;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+    Connection, SqlitePool,
+};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, SCHEMA_VERSION},
+    error::{AppError, AppResult},
+    state::AppState,
+};
+
+/// The path of the restore endpoint, which is exempt from the global body limit.
+pub const RESTORE_PATH: &str = "/admin/backup/restore";
+
+/// The largest database a restore accepts, compressed or uncompressed (16 GiB).
+pub const MAX_RESTORE_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+/// The size of the chunks a backup is sent in.
+const BACKUP_CHUNK_BYTES: usize = 256 * 1024;
+
+/// The number of chunks that may wait for the client.
+const BACKUP_QUEUED_CHUNKS: usize = 8;
+
+/// A file in the temp directory that is removed when dropped.
+#[derive(Debug)]
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(label: &str) -> Self {
+        TempFile(std::env::temp_dir().join(format!("speicherwald-{}-{}.db", label, Uuid::new_v4())))
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to remove temporary file {}: {}", self.0.display(), e);
+            }
+        }
+    }
+}
+
+/// A consistent copy of the database.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// The database file, removed once the snapshot is dropped.
+    pub file: TempFile,
+    /// The size of the file in bytes.
+    pub size_bytes: u64,
+    /// The number of scans in the copy.
+    pub scan_count: u64,
+}
+
+/// The outcome of a restore.
+#[derive(Debug)]
+pub struct RestoreOutcome {
+    /// The size of the restored database file in bytes.
+    pub size_bytes: u64,
+    /// The number of scans in the restored database.
+    pub scan_count: u64,
+}
+
+/// Fails with `Conflict` while a scan job is running.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `include_watchers` - Whether watched scans count as running as well.
+///
+/// # Returns
+///
+/// * `AppResult<()>` - `Ok(())` if nothing is running.
+pub async fn ensure_idle(state: &AppState, include_watchers: bool) -> AppResult<()> {
+    let jobs = state.jobs.read().await.len();
+    if jobs > 0 {
+        return Err(AppError::Conflict(format!(
+            "{} scan job(s) running; wait for them or cancel them first",
+            jobs
+        )));
+    }
+    if include_watchers {
+        let watchers = state.watchers.read().await.len();
+        if watchers > 0 {
+            return Err(AppError::Conflict(format!(
+                "{} scan(s) watched; stop watching them first",
+                watchers
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Writes a consistent copy of the database to a temporary file.
+///
+/// # Arguments
+///
+/// * `pool` - The pool of the live database.
+///
+/// # Returns
+///
+/// * `AppResult<Snapshot>` - The copy with its size and scan count.
+pub async fn snapshot(pool: &SqlitePool) -> AppResult<Snapshot> {
+    let file = TempFile::new("backup");
+    sqlx::query("VACUUM INTO ?1").bind(file.path().to_string_lossy().into_owned()).execute(pool).await?;
+    let size_bytes = tokio::fs::metadata(file.path()).await?.len();
+    let mut conn = open_file(file.path()).await?;
+    let scan_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&mut conn).await?;
+    conn.close().await?;
+    Ok(Snapshot { file, size_bytes, scan_count: scan_count as u64 })
+}
+
+/// Sends a snapshot gzip-compressed through a channel, in chunks.
+///
+/// Compression runs on a blocking thread; the snapshot file is removed when it
+/// is done or the receiver has gone away. A read error is sent as the last item
+/// so the response is aborted instead of ending like a complete file.
+///
+/// # Arguments
+///
+/// * `snapshot` - The snapshot to send.
+///
+/// # Returns
+///
+/// * The receiving end of the channel, for `Body::from_stream`.
+pub fn stream_gzip(snapshot: Snapshot) -> tokio_stream::wrappers::ReceiverStream<std::io::Result<Bytes>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(BACKUP_QUEUED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let file = snapshot.file;
+        let result = (|| -> std::io::Result<()> {
+            let source = std::io::BufReader::new(std::fs::File::open(file.path())?);
+            let mut encoder = flate2::read::GzEncoder::new(source, flate2::Compression::default());
+            let mut buf = vec![0u8; BACKUP_CHUNK_BYTES];
+            loop {
+                let n = encoder.read(&mut buf)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                if tx.blocking_send(Ok(Bytes::copy_from_slice(&buf[..n]))).is_err() {
+                    // Client disconnected
+                    return Ok(());
+                }
+            }
+        })();
+        if let Err(e) = result {
+            tracing::error!("Sending backup {} failed: {}", file.path().display(), e);
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    tokio_stream::wrappers::ReceiverStream::new(rx)
+}
+
+/// Replaces the database contents with an uploaded backup.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `body` - The uploaded database file, gzip-compressed or plain.
+///
+/// # Returns
+///
+/// * `AppResult<RestoreOutcome>` - The size and scan count of the restored database,
+///   `BadRequest` if the upload is not a usable backup, or `Conflict` if a scan is
+///   running or watched.
+pub async fn restore(state: &AppState, body: Body) -> AppResult<RestoreOutcome> {
+    ensure_idle(state, true).await?;
+
+    let upload = TempFile::new("upload");
+    receive(body, upload.path()).await?;
+    let database = if is_gzip(upload.path()).await? {
+        let plain = TempFile::new("restore");
+        let (from, to) = (upload.path().to_path_buf(), plain.path().to_path_buf());
+        tokio::task::spawn_blocking(move || gunzip(&from, &to))
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("decompression task failed: {}", e)))??;
+        drop(upload);
+        plain
+    } else {
+        upload
+    };
+
+    let size_bytes = tokio::fs::metadata(database.path()).await?.len();
+    let mut live = state.db.acquire().await?;
+    let tables = table_columns(&mut live).await?;
+    drop(live);
+    let scan_count = verify(database.path(), &tables).await?;
+
+    // A job started while the upload was received would write into tables that are about to be replaced
+    ensure_idle(state, true).await?;
+    replace_contents(&state.db, database.path(), &tables).await?;
+
+    // Event logs of finished scans refer to the old contents
+    state.finished_events.write().await.clear();
+    state.read_only_roots.invalidate().await;
+    match db::mark_interrupted_scans(&state.db).await {
+        Ok(ids) if !ids.is_empty() => {
+            tracing::info!("Marked {} scan(s) of the restored backup as interrupted", ids.len())
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to mark running scans of the restored backup: {}", e),
+    }
+    tracing::info!(
+        target: "audit",
+        "Restored database backup ({} bytes, {} scans)",
+        size_bytes,
+        scan_count
+    );
+    Ok(RestoreOutcome { size_bytes, scan_count })
+}
+
+/// Streams a request body into a file, up to [`MAX_RESTORE_BYTES`].
+async fn receive(body: Body, path: &Path) -> AppResult<u64> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut stream = body.into_data_stream();
+    let mut written = 0u64;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(format!("failed to read upload: {}", e)))?;
+        written += chunk.len() as u64;
+        if written > MAX_RESTORE_BYTES {
+            return Err(too_large());
+        }
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    if written == 0 {
+        return Err(AppError::BadRequest("the upload is empty".into()));
+    }
+    Ok(written)
+}
+
+fn too_large() -> AppError {
+    AppError::BadRequest(format!("backups larger than {} bytes cannot be restored", MAX_RESTORE_BYTES))
+}
+
+async fn is_gzip(path: &Path) -> AppResult<bool> {
+    use tokio::io::AsyncReadExt;
+    let mut magic = [0u8; 2];
+    let mut file = tokio::fs::File::open(path).await?;
+    let n = file.read(&mut magic).await?;
+    Ok(n == 2 && magic == [0x1f, 0x8b])
+}
+
+fn gunzip(from: &Path, to: &Path) -> AppResult<()> {
+    let decoder = flate2::read::MultiGzDecoder::new(std::io::BufReader::new(std::fs::File::open(from)?));
+    let mut output = std::io::BufWriter::new(std::fs::File::create(to)?);
+    let copied = std::io::copy(&mut decoder.take(MAX_RESTORE_BYTES + 1), &mut output)
+        .map_err(|e| AppError::BadRequest(format!("invalid gzip data: {}", e)))?;
+    if copied > MAX_RESTORE_BYTES {
+        return Err(too_large());
+    }
+    std::io::Write::flush(&mut output)?;
+    Ok(())
+}
+
+/// Opens one of the temporary database files.
+async fn open_file(path: &Path) -> AppResult<SqliteConnection> {
+    let options = SqliteConnectOptions::new().filename(path);
+    Ok(SqliteConnection::connect_with(&options).await?)
+}
+
+/// Lists the columns of every table, by table name.
+async fn table_columns(conn: &mut SqliteConnection) -> AppResult<BTreeMap<String, Vec<String>>> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut tables = BTreeMap::new();
+    for name in names {
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?1) ORDER BY cid")
+            .bind(&name)
+            .fetch_all(&mut *conn)
+            .await?;
+        tables.insert(name, columns);
+    }
+    Ok(tables)
+}
+
+/// Checks that a file is an intact database with the live schema.
+///
+/// # Returns
+///
+/// * `AppResult<u64>` - The number of scans in the file, or `BadRequest`.
+async fn verify(path: &Path, expected: &BTreeMap<String, Vec<String>>) -> AppResult<u64> {
+    let invalid =
+        |e: AppError| AppError::BadRequest(format!("the upload is not a valid SQLite database: {}", e));
+    let mut conn = open_file(path).await.map_err(invalid)?;
+    let check: String =
+        sqlx::query_scalar("PRAGMA quick_check").fetch_one(&mut conn).await.map_err(|e| invalid(e.into()))?;
+    if check != "ok" {
+        return Err(AppError::BadRequest(format!("the backup failed the integrity check: {}", check)));
+    }
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&mut conn).await?;
+    if version != SCHEMA_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "the backup has schema version {}, this server expects {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    let tables = table_columns(&mut conn).await?;
+    if let Some(table) = expected.keys().find(|t| !tables.contains_key(*t)) {
+        return Err(AppError::BadRequest(format!("the backup has no table {}", table)));
+    }
+    for (table, columns) in expected {
+        if let Some(column) = columns.iter().find(|c| !tables[table].contains(c)) {
+            return Err(AppError::BadRequest(format!("the backup has no column {}.{}", table, column)));
+        }
+    }
+    let scan_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&mut conn).await?;
+    conn.close().await?;
+    Ok(scan_count as u64)
+}
+
+/// Replaces the rows of every table with those of the backup, in one transaction.
+///
+/// Runs on a connection taken out of the pool, which is closed afterwards, so the
+/// attached backup and the disabled foreign keys never leak into other requests.
+async fn replace_contents(
+    pool: &SqlitePool,
+    path: &Path,
+    tables: &BTreeMap<String, Vec<String>>,
+) -> AppResult<()> {
+    let mut conn = pool.acquire().await?.detach();
+    // Rows are copied table by table, in no particular order
+    sqlx::query("PRAGMA foreign_keys=OFF").execute(&mut conn).await?;
+    sqlx::query("ATTACH DATABASE ?1 AS backup")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut conn).await?;
+    let copied = copy_tables(&mut conn, tables).await;
+    match copied {
+        Ok(()) => {
+            sqlx::query("COMMIT").execute(&mut conn).await?;
+        }
+        Err(_) => {
+            let _ = sqlx::query("ROLLBACK").execute(&mut conn).await;
+        }
+    }
+    let _ = conn.close().await;
+    copied
+}
+
+async fn copy_tables(conn: &mut SqliteConnection, tables: &BTreeMap<String, Vec<String>>) -> AppResult<()> {
+    for (table, columns) in tables {
+        let columns = columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ");
+        sqlx::query(&format!("DELETE FROM main.\"{}\"", table)).execute(&mut *conn).await?;
+        sqlx::query(&format!(
+            "INSERT INTO main.\"{t}\" ({c}) SELECT {c} FROM backup.\"{t}\"",
+            t = table,
+            c = columns
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan, test_db};
+
+    async fn state_with_scan() -> (tempfile::TempDir, AppState) {
+        let (dir, pool) = test_db().await;
+        insert_scan(&pool, "done", &["/data"], "default").await;
+        (dir, AppState::new(pool, AppConfig::default()))
+    }
+
+    fn gzip(path: &Path) -> Vec<u8> {
+        let source = std::fs::File::open(path).unwrap();
+        let mut out = Vec::new();
+        flate2::read::GzEncoder::new(source, flate2::Compression::fast()).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn backup_round_trips_through_restore() {
+        let (_source_dir, source) = state_with_scan().await;
+        let (_target_dir, target) = state_with_scan().await;
+        insert_scan(&target.db, "done", &["/x"], "default").await;
+        let expected: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM scans").fetch_all(&source.db).await.unwrap();
+
+        let snap = snapshot(&source.db).await.unwrap();
+        assert_eq!(snap.scan_count, 1);
+        assert!(snap.size_bytes > 0);
+        let compressed = gzip(snap.file.path());
+
+        let outcome = restore(&target, Body::from(compressed)).await.unwrap();
+        assert_eq!(outcome.scan_count, 1);
+        assert_eq!(outcome.size_bytes, snap.size_bytes);
+        let restored: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM scans").fetch_all(&target.db).await.unwrap();
+        assert_eq!(restored, expected);
+        // The pool keeps working with foreign keys on
+        let fk: i64 = sqlx::query_scalar("PRAGMA foreign_keys").fetch_one(&target.db).await.unwrap();
+        assert_eq!(fk, 1);
+    }
+
+    #[tokio::test]
+    async fn restore_rejects_foreign_files_and_versions() {
+        let (_dir, state) = state_with_scan().await;
+
+        let res = restore(&state, Body::from("definitely not sqlite".repeat(100))).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+
+        let snap = snapshot(&state.db).await.unwrap();
+        {
+            let options = SqliteConnectOptions::new().filename(snap.file.path());
+            let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+            sqlx::query(&format!("PRAGMA user_version={}", SCHEMA_VERSION + 1))
+                .execute(&mut conn)
+                .await
+                .unwrap();
+            conn.close().await.unwrap();
+        }
+        let res = restore(&state, Body::from(std::fs::read(snap.file.path()).unwrap())).await;
+        assert!(matches!(res, Err(AppError::BadRequest(msg)) if msg.contains("schema version")));
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&state.db).await.unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn restore_refuses_while_a_scan_runs() {
+        let (_dir, state) = state_with_scan().await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(1);
+        let job = crate::state::JobHandle::new(tokio_util::sync::CancellationToken::new(), tx);
+        state.jobs.write().await.insert(Uuid::new_v4(), job);
+        assert!(matches!(restore(&state, Body::from("x")).await, Err(AppError::Conflict(_))));
+        assert!(matches!(ensure_idle(&state, false).await, Err(AppError::Conflict(_))));
+    }
+}
@@ -0,0 +1,410 @@
+//! Command line interface of the backend binary.
+//!
+//! Without a subcommand (or with `serve`) the binary runs the HTTP server.
+//! `scan` and `export` work headless against a SQLite database: they print
+//! progress to stderr and write the scan results to stdout or a file, in the
+//! same CSV and NDJSON layouts as `GET /scans/{id}/export`.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use sqlx::{migrate::MigrateDatabase, Row, Sqlite};
+use tokio::io::AsyncWrite;
+use uuid::Uuid;
+
+use crate::{
+    config::{self, AppConfig},
+    db,
+    middleware::namespace::Namespace,
+    routes::{
+        export::{write_export, CsvOptions, ExportFormat},
+        scans::start_scan,
+    },
+    state::AppState,
+    types::{AccessMode, CreateScanRequest, IoPriority, ScanEvent},
+};
+
+/// The exit code of a scan that finished with status `failed`.
+pub const EXIT_FAILED: i32 = 1;
+/// The exit code of a scan that was interrupted with Ctrl+C.
+pub const EXIT_CANCELED: i32 = 130;
+
+/// How often the `scan` subcommand reports progress on stderr.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The command line of the backend binary.
+#[derive(Debug, Parser)]
+#[command(name = "speicherwald", version, about = "Directory size analysis with a web UI")]
+pub struct Cli {
+    /// The subcommand; the HTTP server runs if none is given.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The subcommands of the backend binary.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Runs the HTTP server (default).
+    Serve,
+    /// Scans directories without starting the HTTP server.
+    Scan(ScanArgs),
+    /// Writes the results of a stored scan.
+    Export(ExportArgs),
+    /// Prints the discovery file of a running backend.
+    Discover,
+}
+
+/// The output formats of the `scan` and `export` subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Comma-separated values with one header line per section.
+    Csv,
+    /// One JSON object per line, tagged with `"type": "node"` or `"file"`.
+    Ndjson,
+}
+
+/// The records written by the `scan` and `export` subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputScope {
+    /// Directories only.
+    Nodes,
+    /// Files only.
+    Files,
+    /// Directories, then files.
+    All,
+}
+
+/// Where and how scan results are written.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Ndjson)]
+    pub format: OutputFormat,
+    /// The records to write.
+    #[arg(long, value_enum, default_value_t = OutputScope::All)]
+    pub scope: OutputScope,
+    /// The file to write to instead of stdout.
+    #[arg(short, long, value_name = "FILE", alias = "json-output")]
+    pub output: Option<PathBuf>,
+    /// The CSV columns, e.g. `path,allocated_size,mtime`.
+    #[arg(long, value_name = "LIST")]
+    pub columns: Option<String>,
+    /// The CSV field separator.
+    #[arg(long, value_parser = ["comma", "semicolon"], default_value = "comma")]
+    pub sep: String,
+}
+
+/// Arguments of the `scan` subcommand.
+///
+/// The scan options mirror the fields of `POST /scans`; omitted ones fall
+/// back to `[scan_defaults]`.
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    /// The root directories to scan.
+    #[arg(required = true, value_name = "PATH")]
+    pub paths: Vec<String>,
+    /// A glob pattern to exclude; may be repeated. Replaces the configured excludes.
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub excludes: Vec<String>,
+    /// A glob pattern files must match to be recorded; may be repeated.
+    #[arg(long = "include", value_name = "GLOB")]
+    pub includes: Vec<String>,
+    /// The maximum depth below the roots.
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<u32>,
+    /// Skips hidden files and directories.
+    #[arg(long)]
+    pub no_hidden: bool,
+    /// Follows symbolic links.
+    #[arg(long)]
+    pub follow_symlinks: bool,
+    /// Leaves out the `[system_excludes]` patterns added to scans of a system drive.
+    #[arg(long)]
+    pub no_system_excludes: bool,
+    /// The number of concurrent directory workers.
+    #[arg(long, value_name = "N")]
+    pub concurrency: Option<usize>,
+    /// The I/O priority of the scanner threads.
+    #[arg(long, value_parser = ["normal", "low", "background"])]
+    pub io_priority: Option<String>,
+    /// How directories and files are opened; `backup` needs `SeBackupPrivilege` (Windows only).
+    #[arg(long, value_parser = ["normal", "backup"])]
+    pub access_mode: Option<String>,
+    /// Hashes the content of files of at least this many bytes with BLAKE3.
+    #[arg(long, value_name = "BYTES")]
+    pub hash_min_size: Option<u64>,
+    /// The SQLite database to keep the scan in, e.g. `sqlite://data/speicherwald.db`.
+    /// Without it a temporary database is used and removed afterwards.
+    #[arg(long, value_name = "URL")]
+    pub db: Option<String>,
+    /// Where and how the results are written.
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+impl ScanArgs {
+    /// Builds the request `POST /scans` would receive for these arguments.
+    pub fn to_request(&self) -> CreateScanRequest {
+        let non_empty = |patterns: &Vec<String>| (!patterns.is_empty()).then(|| patterns.clone());
+        CreateScanRequest {
+            root_paths: self.paths.clone(),
+            follow_symlinks: self.follow_symlinks.then_some(true),
+            include_hidden: self.no_hidden.then_some(false),
+            measure_logical: None,
+            measure_allocated: None,
+            excludes: non_empty(&self.excludes),
+            includes: non_empty(&self.includes),
+            max_depth: self.max_depth,
+            concurrency: self.concurrency,
+            measure_hardlinks: None,
+            measure_ads: None,
+            io_priority: self.io_priority.as_deref().map(|p| match p {
+                "low" => IoPriority::Low,
+                "background" => IoPriority::Background,
+                _ => IoPriority::Normal,
+            }),
+            capture_owner: None,
+            access_mode: self.access_mode.as_deref().map(|m| match m {
+                "backup" => AccessMode::Backup,
+                _ => AccessMode::Normal,
+            }),
+            hash_min_size: self.hash_min_size,
+            read_only: None,
+            no_system_excludes: self.no_system_excludes.then_some(true),
+            rescan_of: None,
+        }
+    }
+}
+
+/// Arguments of the `export` subcommand.
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// The ID of the scan to export.
+    pub scan_id: Uuid,
+    /// The SQLite database holding the scan; defaults to the configured one.
+    #[arg(long, value_name = "URL")]
+    pub db: Option<String>,
+    /// Where and how the results are written.
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+/// Opens (and if needed creates) a database and builds the state the routes expect.
+async fn open_state(cfg: AppConfig, db_url: &str) -> anyhow::Result<AppState> {
+    config::ensure_sqlite_parent_dir(db_url)?;
+    if !Sqlite::database_exists(db_url).await.unwrap_or(false) {
+        Sqlite::create_database(db_url).await?;
+    }
+    let pool = db::connect_write_pool(db_url, 4).await?;
+    db::init_db(&pool).await?;
+    Ok(AppState::new(pool, cfg))
+}
+
+/// Writes the export of a scan to the file of `args`, or to stdout.
+async fn write_output(state: &AppState, scan_id: Uuid, args: &OutputArgs) -> anyhow::Result<()> {
+    let format = match args.format {
+        OutputFormat::Csv => ExportFormat::Csv(CsvOptions::from_query(args.columns.as_deref(), Some(&args.sep))?),
+        OutputFormat::Ndjson => ExportFormat::Ndjson,
+    };
+    let scope = match args.scope {
+        OutputScope::Nodes => "nodes",
+        OutputScope::Files => "files",
+        OutputScope::All => "all",
+    };
+    let mut out: Box<dyn AsyncWrite + Unpin + Send> = match &args.output {
+        Some(path) => Box::new(tokio::io::BufWriter::new(tokio::fs::File::create(path).await?)),
+        None => Box::new(tokio::io::BufWriter::new(tokio::io::stdout())),
+    };
+    write_export(state, scan_id, format, scope, &mut out).await
+}
+
+/// Runs the `scan` subcommand.
+///
+/// # Returns
+///
+/// * `anyhow::Result<i32>` - The process exit code: 0 if the scan finished,
+///   [`EXIT_FAILED`] if it failed and [`EXIT_CANCELED`] if it was interrupted.
+pub async fn scan(cfg: AppConfig, args: ScanArgs) -> anyhow::Result<i32> {
+    let temp_db = args.db.is_none().then(|| std::env::temp_dir().join(format!("speicherwald-{}.db", Uuid::new_v4())));
+    let db_url = match (&args.db, &temp_db) {
+        (Some(url), _) => url.clone(),
+        (None, Some(path)) => format!("sqlite://{}", path.to_string_lossy().replace('\\', "/")),
+        (None, None) => unreachable!("a temporary database is chosen when --db is missing"),
+    };
+
+    let state = open_state(cfg, &db_url).await?;
+    let result = scan_with_state(&state, &args).await;
+
+    state.db.close().await;
+    if let Some(path) = temp_db {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+    result
+}
+
+/// Runs a scan to completion and writes its results if it succeeded.
+async fn scan_with_state(state: &AppState, args: &ScanArgs) -> anyhow::Result<i32> {
+    let scan = start_scan(state, args.to_request(), &Namespace::default()).await?;
+    eprintln!("Scan {} started", scan.id);
+
+    let job = {
+        let jobs = state.jobs.read().await;
+        jobs.get(&scan.id).map(|h| (h.sender.subscribe(), h.finished.clone(), h.cancel.clone()))
+    };
+    // A missing job means the scan has already finished
+    if let Some((mut events, finished, cancel)) = job {
+        let mut last_report = Instant::now();
+        let mut ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+        loop {
+            tokio::select! {
+                _ = finished.cancelled() => break,
+                _ = &mut ctrl_c, if !cancel.is_cancelled() => {
+                    eprintln!("Interrupted, cancelling scan...");
+                    cancel.cancel();
+                }
+                event = events.recv() => match event {
+                    Ok(ScanEvent::Progress { dirs_scanned, files_scanned, allocated_size, .. })
+                        if last_report.elapsed() >= PROGRESS_INTERVAL =>
+                    {
+                        last_report = Instant::now();
+                        eprintln!("{} dirs, {} files, {} bytes", dirs_scanned, files_scanned, allocated_size);
+                    }
+                    Ok(ScanEvent::Warning { path, message, .. }) => eprintln!("warning: {}: {}", path, message),
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => finished.cancelled().await,
+                },
+            }
+        }
+    }
+
+    let row = sqlx::query("SELECT status, dir_count, file_count, total_allocated_size FROM scans WHERE id=?1")
+        .bind(scan.id.to_string())
+        .fetch_one(&state.db)
+        .await?;
+    let status: String = row.try_get("status")?;
+    eprintln!(
+        "Scan {} {}: {} dirs, {} files, {} bytes",
+        scan.id,
+        status,
+        row.try_get::<Option<i64>, _>("dir_count")?.unwrap_or(0),
+        row.try_get::<Option<i64>, _>("file_count")?.unwrap_or(0),
+        row.try_get::<Option<i64>, _>("total_allocated_size")?.unwrap_or(0),
+    );
+    match status.as_str() {
+        "failed" => Ok(EXIT_FAILED),
+        "canceled" => Ok(EXIT_CANCELED),
+        _ => {
+            write_output(state, scan.id, &args.output).await?;
+            Ok(0)
+        }
+    }
+}
+
+/// Runs the `export` subcommand.
+///
+/// # Returns
+///
+/// * `anyhow::Result<i32>` - The process exit code; an unknown scan ID is an error.
+pub async fn export(cfg: AppConfig, args: ExportArgs) -> anyhow::Result<i32> {
+    let db_url = args.db.clone().unwrap_or_else(|| cfg.database.url.clone());
+    if !Sqlite::database_exists(&db_url).await.unwrap_or(false) {
+        anyhow::bail!("database {} does not exist", db_url);
+    }
+    let state = open_state(cfg, &db_url).await?;
+    let exists = sqlx::query("SELECT 1 FROM scans WHERE id=?1")
+        .bind(args.scan_id.to_string())
+        .fetch_optional(&state.db)
+        .await?
+        .is_some();
+    if !exists {
+        anyhow::bail!("scan {} not found in {}", args.scan_id, db_url);
+    }
+    write_output(&state, args.scan_id, &args.output).await?;
+    state.db.close().await;
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("speicherwald").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn scan_flags_map_to_the_create_scan_request() {
+        let cli = parse(&[
+            "scan", "D:\\", "--exclude", "**/node_modules", "--exclude", "*.tmp", "--max-depth", "3",
+            "--no-hidden", "--concurrency", "4", "--json-output", "results.ndjson",
+        ]);
+        let Some(Command::Scan(args)) = cli.command else { panic!("expected scan") };
+        let req = args.to_request();
+        assert_eq!(req.root_paths, vec!["D:\\"]);
+        assert_eq!(req.excludes, Some(vec!["**/node_modules".to_string(), "*.tmp".to_string()]));
+        assert_eq!(req.includes, None);
+        assert_eq!(req.max_depth, Some(3));
+        assert_eq!(req.include_hidden, Some(false));
+        assert_eq!(req.follow_symlinks, None);
+        assert_eq!(req.concurrency, Some(4));
+        assert_eq!(args.output.output, Some(PathBuf::from("results.ndjson")));
+        assert_eq!(args.output.format, OutputFormat::Ndjson);
+    }
+
+    #[test]
+    fn serve_is_the_default() {
+        assert!(parse(&[]).command.is_none());
+        assert!(matches!(parse(&["serve"]).command, Some(Command::Serve)));
+        assert!(Cli::try_parse_from(["speicherwald", "scan"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn scan_writes_results_and_keeps_them_in_the_given_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.txt"), b"hello").unwrap();
+        std::fs::write(root.join("sub").join("b.bin"), vec![0u8; 2048]).unwrap();
+        let out = dir.path().join("out.ndjson");
+        let db_url = format!("sqlite://{}", dir.path().join("cli.db").to_string_lossy().replace('\\', "/"));
+
+        let root_arg = root.to_string_lossy().to_string();
+        let out_arg = out.to_string_lossy().to_string();
+        let cli = parse(&["scan", &root_arg, "--db", &db_url, "--scope", "files", "-o", &out_arg]);
+        let Some(Command::Scan(args)) = cli.command else { panic!("expected scan") };
+        assert_eq!(scan(AppConfig::default(), args).await.unwrap(), 0);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l["type"] == "file"));
+
+        // The scan stays in the database and can be exported again as CSV
+        let state = open_state(AppConfig::default(), &db_url).await.unwrap();
+        let id: String = sqlx::query_scalar("SELECT id FROM scans").fetch_one(&state.db).await.unwrap();
+        state.db.close().await;
+        let csv = dir.path().join("out.csv");
+        let csv_arg = csv.to_string_lossy().to_string();
+        let cli = parse(&["export", &id, "--db", &db_url, "--format", "csv", "--output", &csv_arg]);
+        let Some(Command::Export(args)) = cli.command else { panic!("expected export") };
+        assert_eq!(export(AppConfig::default(), args).await.unwrap(), 0);
+        let csv = std::fs::read_to_string(&csv).unwrap();
+        assert!(csv.starts_with("Type,Path,Parent Path,Depth"));
+        assert!(csv.contains("b.bin"));
+    }
+
+    #[tokio::test]
+    async fn scan_of_a_missing_root_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+        let cli = parse(&["scan", &missing]);
+        let Some(Command::Scan(args)) = cli.command else { panic!("expected scan") };
+        assert!(scan(AppConfig::default(), args).await.is_err());
+    }
+}
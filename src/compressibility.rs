@@ -0,0 +1,278 @@
+//! Heuristic estimate of how much compression would free in a directory.
+//!
+//! The scoring works on per-extension aggregates of the files directly inside
+//! a directory and never touches the filesystem. Each extension belongs to a
+//! configured class with an expected saving ratio; the estimate for a group of
+//! files is the part of their allocated bytes (up to their logical size) above
+//! the expected compressed size. Files the filesystem already compresses (or stores sparsely) have
+//! fewer allocated than logical bytes and therefore contribute little or nothing.
+//!
+//! NTFS compresses in 64 KiB units, so small files gain less than their ratio
+//! suggests; the estimate is damped for directories with a small average file size.
+
+use std::collections::HashMap;
+
+use crate::{
+    config::AnalysisConfig,
+    types::{CompressibilityDirectory, ExtensionContribution},
+};
+
+/// The unit NTFS compresses in; directories with larger average files get the full estimate.
+const COMPRESSION_UNIT: f64 = 65_536.0;
+/// The smallest damping factor applied for tiny average file sizes.
+const MIN_SIZE_FACTOR: f64 = 0.25;
+/// The number of files from which a directory's estimate is considered representative.
+const CONFIDENT_FILE_COUNT: f64 = 10.0;
+
+/// Maps file extensions to their compression class and expected saving ratio.
+#[derive(Debug, Clone)]
+pub struct CompressionTable {
+    /// Class names and their expected ratios
+    classes: Vec<(String, f64)>,
+    /// Extension -> index into `classes`
+    by_extension: HashMap<String, usize>,
+    unclassified_ratio: f64,
+}
+
+impl CompressionTable {
+    /// Builds the table from the analysis configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The analysis configuration with the extension classes.
+    pub fn new(config: &AnalysisConfig) -> Self {
+        let mut classes = Vec::with_capacity(config.compression_classes.len());
+        let mut by_extension = HashMap::new();
+        for (idx, class) in config.compression_classes.iter().enumerate() {
+            classes.push((class.name.clone(), class.expected_ratio.clamp(0.0, 1.0)));
+            for ext in &class.extensions {
+                by_extension.entry(ext.to_lowercase()).or_insert(idx);
+            }
+        }
+        Self { classes, by_extension, unclassified_ratio: config.unclassified_ratio.clamp(0.0, 1.0) }
+    }
+
+    /// Returns the class name and expected ratio of an extension, `None` if it is unclassified.
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - The lowercase extension without the dot.
+    pub fn classify(&self, extension: &str) -> Option<(&str, f64)> {
+        self.by_extension.get(extension).map(|&idx| (self.classes[idx].0.as_str(), self.classes[idx].1))
+    }
+}
+
+/// The aggregated sizes of the files with one extension in one directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionStats {
+    /// The lowercase extension without the dot, empty for files without one.
+    pub extension: String,
+    /// The number of files.
+    pub file_count: i64,
+    /// The logical bytes of the files.
+    pub logical_size: i64,
+    /// The allocated bytes of the files.
+    pub allocated_size: i64,
+}
+
+fn round3(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+/// Estimates the compressibility of the files directly inside a directory.
+///
+/// # Arguments
+///
+/// * `table` - The extension classification.
+/// * `path` - The directory.
+/// * `stats` - The per-extension aggregates of the directory's files.
+/// * `top_n` - The maximum number of extensions reported as contributors.
+///
+/// # Returns
+///
+/// * `CompressibilityDirectory` - The estimate with score, confidence and top contributors.
+pub fn score_directory(
+    table: &CompressionTable,
+    path: String,
+    stats: &[ExtensionStats],
+    top_n: usize,
+) -> CompressibilityDirectory {
+    let file_count: i64 = stats.iter().map(|s| s.file_count).sum();
+    let logical_size: i64 = stats.iter().map(|s| s.logical_size).sum();
+    let allocated_size: i64 = stats.iter().map(|s| s.allocated_size).sum();
+    let avg_size = if file_count > 0 { allocated_size as f64 / file_count as f64 } else { 0.0 };
+    let size_factor = (avg_size / COMPRESSION_UNIT).clamp(MIN_SIZE_FACTOR, 1.0);
+
+    let mut classified_size = 0i64;
+    let mut contributions: Vec<ExtensionContribution> = stats
+        .iter()
+        .map(|s| {
+            let class = table.classify(&s.extension);
+            let ratio = match class {
+                Some((_, ratio)) => {
+                    classified_size += s.allocated_size;
+                    ratio
+                }
+                None => table.unclassified_ratio,
+            };
+            // Cluster slack above the logical size is not data compression can shrink
+            let stored = s.allocated_size.min(s.logical_size).max(0) as f64;
+            let expected = s.logical_size.max(0) as f64 * (1.0 - ratio);
+            let reclaimable = ((stored - expected).max(0.0) * size_factor).round() as i64;
+            ExtensionContribution {
+                extension: s.extension.clone(),
+                class: class.map(|(name, _)| name.to_string()),
+                file_count: s.file_count,
+                allocated_size: s.allocated_size,
+                estimated_reclaimable: reclaimable,
+            }
+        })
+        .collect();
+    let estimated_reclaimable: i64 = contributions.iter().map(|c| c.estimated_reclaimable).sum();
+
+    let (score, confidence) = if allocated_size > 0 {
+        let classified_share = classified_size as f64 / allocated_size as f64;
+        let sample_factor = (file_count as f64 / CONFIDENT_FILE_COUNT).min(1.0);
+        (estimated_reclaimable as f64 / allocated_size as f64, classified_share * sample_factor)
+    } else {
+        (0.0, 0.0)
+    };
+
+    contributions.sort_by(|a, b| {
+        b.estimated_reclaimable
+            .cmp(&a.estimated_reclaimable)
+            .then_with(|| b.allocated_size.cmp(&a.allocated_size))
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    contributions.truncate(top_n);
+
+    CompressibilityDirectory {
+        path,
+        file_count,
+        logical_size,
+        allocated_size,
+        estimated_reclaimable,
+        score: round3(score.clamp(0.0, 1.0)),
+        confidence: round3(confidence.clamp(0.0, 1.0)),
+        top_extensions: contributions,
+    }
+}
+
+/// Sorts directories by estimated reclaimable bytes, then score, then path.
+pub fn rank_directories(dirs: &mut [CompressibilityDirectory]) {
+    dirs.sort_by(|a, b| {
+        b.estimated_reclaimable
+            .cmp(&a.estimated_reclaimable)
+            .then_with(|| b.score.total_cmp(&a.score))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompressionClassConfig;
+
+    const MIB: i64 = 1024 * 1024;
+
+    fn stats(extension: &str, file_count: i64, logical: i64, allocated: i64) -> ExtensionStats {
+        ExtensionStats {
+            extension: extension.into(),
+            file_count,
+            logical_size: logical,
+            allocated_size: allocated,
+        }
+    }
+
+    fn table() -> CompressionTable {
+        CompressionTable::new(&AnalysisConfig::default())
+    }
+
+    #[test]
+    fn classifies_by_configured_table() {
+        let t = table();
+        assert_eq!(t.classify("log"), Some(("text", 0.7)));
+        assert_eq!(t.classify("jpg"), Some(("compressed", 0.0)));
+        assert_eq!(t.classify("unknownext"), None);
+
+        let custom = CompressionTable::new(&AnalysisConfig {
+            compression_classes: vec![CompressionClassConfig {
+                name: "photos".into(),
+                extensions: vec!["jpg".into()],
+                expected_ratio: 0.2,
+            }],
+            unclassified_ratio: 0.0,
+        });
+        assert_eq!(custom.classify("jpg"), Some(("photos", 0.2)));
+        assert_eq!(custom.classify("log"), None);
+    }
+
+    #[test]
+    fn estimates_reclaimable_bytes_per_class() {
+        let dir = score_directory(
+            &table(),
+            "/data/mixed".into(),
+            &[stats("csv", 10, 10 * MIB, 10 * MIB), stats("zip", 10, 10 * MIB, 10 * MIB)],
+            5,
+        );
+        assert_eq!(dir.file_count, 20);
+        assert_eq!(dir.estimated_reclaimable, 7 * MIB);
+        assert_eq!(dir.score, 0.35);
+        assert_eq!(dir.confidence, 1.0);
+        assert_eq!(dir.top_extensions[0].extension, "csv");
+        assert_eq!(dir.top_extensions[0].class.as_deref(), Some("text"));
+        assert_eq!(dir.top_extensions[1].estimated_reclaimable, 0);
+
+        let top_one = score_directory(
+            &table(),
+            "/data/mixed".into(),
+            &[stats("csv", 10, 10 * MIB, 10 * MIB), stats("zip", 10, 10 * MIB, 10 * MIB)],
+            1,
+        );
+        assert_eq!(top_one.top_extensions.len(), 1);
+    }
+
+    #[test]
+    fn already_compressed_files_free_nothing() {
+        // NTFS-compressed logs: 30% of the logical size is allocated
+        let dir = score_directory(&table(), "/data/c".into(), &[stats("log", 10, 10 * MIB, 3 * MIB)], 5);
+        assert_eq!(dir.estimated_reclaimable, 0);
+        assert_eq!(dir.score, 0.0);
+
+        // Photos whose clusters hold 10% slack
+        let dir = score_directory(&table(), "/data/p".into(), &[stats("jpg", 10, 10 * MIB, 11 * MIB)], 5);
+        assert_eq!(dir.estimated_reclaimable, 0);
+    }
+
+    #[test]
+    fn small_files_and_unclassified_types_lower_the_estimate() {
+        // 100 files of 4 KiB: damped to a quarter of the plain estimate
+        let dir = score_directory(&table(), "/data/t".into(), &[stats("txt", 100, 409_600, 409_600)], 5);
+        assert_eq!(dir.estimated_reclaimable, 71_680);
+
+        let dir = score_directory(&table(), "/data/u".into(), &[stats("xyz", 2, 10 * MIB, 10 * MIB)], 5);
+        assert_eq!(dir.estimated_reclaimable, MIB);
+        assert_eq!(dir.confidence, 0.0);
+        assert_eq!(dir.top_extensions[0].class, None);
+
+        let dir = score_directory(&table(), "/data/few".into(), &[stats("log", 2, 2 * MIB, 2 * MIB)], 5);
+        assert_eq!(dir.confidence, 0.2);
+
+        let empty = score_directory(&table(), "/data/e".into(), &[], 5);
+        assert_eq!((empty.estimated_reclaimable, empty.score, empty.confidence), (0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ranks_by_reclaimable_then_score_then_path() {
+        let t = table();
+        let mut dirs = vec![
+            score_directory(&t, "/b".into(), &[stats("log", 10, 10 * MIB, 10 * MIB)], 5),
+            score_directory(&t, "/c".into(), &[stats("zip", 10, 50 * MIB, 50 * MIB)], 5),
+            score_directory(&t, "/a".into(), &[stats("log", 10, 10 * MIB, 10 * MIB)], 5),
+            score_directory(&t, "/d".into(), &[stats("log", 10, 20 * MIB, 20 * MIB)], 5),
+        ];
+        rank_directories(&mut dirs);
+        let order: Vec<&str> = dirs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(order, vec!["/d", "/a", "/b", "/c"]);
+    }
+}
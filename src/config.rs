@@ -1,6 +1,10 @@
+use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::{Arc, RwLock};
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+
+use crate::types::{AccessMode, IoPriority};
 
 /// Configuration for the HTTP server.
 #[derive(Debug, Clone, Deserialize)]
@@ -9,6 +13,24 @@ pub struct ServerConfig {
     pub host: String,
     /// The port to listen on.
     pub port: u16,
+    /// HTTPS for the listener.
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+/// Configuration for serving HTTPS instead of plain HTTP.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Whether the server only accepts TLS connections.
+    pub enabled: bool,
+    /// The PEM file with the certificate chain, server certificate first.
+    pub cert_path: String,
+    /// The PEM file with the private key of the server certificate.
+    pub key_path: String,
+    /// How often the files are checked for a renewed certificate, in seconds;
+    /// 0 only reloads them on SIGHUP.
+    pub reload_interval_secs: u64,
 }
 
 /// Configuration for the database connection.
@@ -16,6 +38,9 @@ pub struct ServerConfig {
 pub struct DatabaseConfig {
     /// The database connection URL.
     pub url: String,
+    /// Whether to run `ANALYZE` after every finished scan so query plans account for its rows.
+    #[serde(default)]
+    pub analyze_after_scan: bool,
 }
 
 /// Default settings for new scans.
@@ -31,10 +56,30 @@ pub struct ScanDefaultsConfig {
     pub measure_allocated: bool,
     /// A list of glob patterns to exclude from scans.
     pub excludes: Vec<String>,
+    /// A list of glob patterns files must match to be recorded; empty records all files.
+    #[serde(default)]
+    pub includes: Vec<String>,
     /// The maximum scan depth.
     pub max_depth: Option<u32>,
     /// The number of concurrent scanner threads.
     pub concurrency: Option<usize>,
+    /// Whether to detect hardlinks so shared data is only counted once.
+    pub measure_hardlinks: bool,
+    /// Whether to count NTFS alternate data streams (Windows only).
+    #[serde(default)]
+    pub measure_ads: bool,
+    /// The I/O priority of the scanner threads.
+    #[serde(default)]
+    pub io_priority: IoPriority,
+    /// Whether to record the owner of every file; costs one security lookup per file.
+    #[serde(default)]
+    pub capture_owner: bool,
+    /// How the scanner opens directories and files.
+    #[serde(default)]
+    pub access_mode: AccessMode,
+    /// Files of at least this many bytes get a BLAKE3 content hash; unset hashes nothing.
+    #[serde(default)]
+    pub hash_min_size: Option<u64>,
 }
 
 /// Configuration for the file scanner.
@@ -44,12 +89,286 @@ pub struct ScannerConfig {
     pub batch_size: usize,
     /// The number of pending records that triggers a flush to the database.
     pub flush_threshold: usize,
+    /// The estimated bytes of pending records that trigger a flush, whichever
+    /// limit is reached first. Bounds the buffers of directories with long paths.
+    pub max_buffer_bytes: usize,
     /// The interval in milliseconds at which to flush pending records to the database.
     pub flush_interval_ms: u64,
     /// The maximum number of open file handles.
     pub handle_limit: Option<usize>,
     /// The number of concurrent directory traversers.
     pub dir_concurrency: Option<usize>,
+    /// The maximum number of entries processed per directory; further entries are
+    /// skipped with a warning. `None` means unlimited.
+    pub max_entries_per_dir: Option<u64>,
+    /// Files smaller than this many bytes are ignored by the duplicate search.
+    pub duplicate_min_size: u64,
+    /// How long a watched scan collects filesystem notifications before applying them.
+    pub watch_debounce_ms: u64,
+    /// How often a batch write that fails because the database is locked is retried.
+    pub persist_max_retries: u32,
+    /// The total time in milliseconds a batch write may spend waiting between retries.
+    pub persist_retry_budget_ms: u64,
+}
+
+/// A group of file extensions that compress about equally well.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompressionClassConfig {
+    /// The name reported for files of the class, e.g. `text`.
+    pub name: String,
+    /// The lowercase extensions without the dot.
+    pub extensions: Vec<String>,
+    /// The expected share of bytes compression saves, between 0 and 1.
+    pub expected_ratio: f64,
+}
+
+/// Configuration for the analysis endpoints.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalysisConfig {
+    /// The extension classes used to estimate compressibility. Setting them
+    /// replaces the built-in table as a whole.
+    pub compression_classes: Vec<CompressionClassConfig>,
+    /// The expected share of bytes compression saves for unclassified extensions.
+    pub unclassified_ratio: f64,
+}
+
+/// A category files are grouped in by their extension, e.g. `video`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CategoryConfig {
+    /// The name stored for files of the category.
+    pub name: String,
+    /// The lowercase extensions without the dot.
+    pub extensions: Vec<String>,
+}
+
+/// Configuration for the classification of files into categories.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CategoriesConfig {
+    /// Whether the scanner stores a category for every file.
+    pub enabled: bool,
+    /// The categories. Setting them replaces the built-in list as a whole; an
+    /// extension listed in several categories belongs to the last of them.
+    pub groups: Vec<CategoryConfig>,
+}
+
+/// Configuration for the Server-Sent Events streams.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SseConfig {
+    /// The interval of the protocol keep-alive comments, in seconds.
+    pub keep_alive_secs: u64,
+    /// The interval of the `heartbeat` events clients use to detect a stalled
+    /// stream, in seconds; 0 disables them.
+    pub heartbeat_secs: u64,
+    /// The size of the comment sent at the start of a stream so buffering
+    /// proxies start flushing; 0 disables it.
+    pub padding_bytes: usize,
+    /// How long the recent events of a finished scan stay available for
+    /// replay to reloading clients, in seconds; 0 drops them right away.
+    pub replay_grace_secs: u64,
+    /// The maximum number of event streams (SSE and WebSocket) one client IP may keep open at a
+    /// time; further streams are refused with 429. 0 disables the limit.
+    pub max_connections_per_client: usize,
+}
+
+/// Configuration for the log output.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// The format of the log records on stdout and in the log files.
+    pub format: LogFormat,
+}
+
+/// The format of the log records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One human-readable line per record.
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the enclosing spans such
+    /// as `scan_id` and `request_id`, for log aggregators.
+    Json,
+}
+
+/// Configuration for the automatic pruning of old scans.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// How many finished scans to keep per set of root paths; `None` keeps all.
+    pub max_scans_per_root: Option<usize>,
+    /// Finished scans older than this many days are pruned; `None` keeps them.
+    /// The newest scan of a set of root paths is always kept.
+    pub max_age_days: Option<u64>,
+    /// The interval of the background pruning, in seconds.
+    pub interval_secs: u64,
+    /// Whether to run `PRAGMA incremental_vacuum` after scans were pruned.
+    /// Only has an effect on databases created with `auto_vacuum = INCREMENTAL`.
+    pub incremental_vacuum: bool,
+}
+
+/// Configuration for the free space history of the drives.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DriveHistoryConfig {
+    /// Whether the background sampler runs.
+    pub enabled: bool,
+    /// The interval between two samples, in seconds.
+    pub interval_secs: u64,
+    /// Samples older than this many days are deleted.
+    pub retention_days: u64,
+    /// The history of a drive that no longer shows up is deleted once its
+    /// newest sample is older than this many hours.
+    pub purge_missing_hours: u64,
+}
+
+/// A receiver of the notifications about finished scans.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookEndpointConfig {
+    /// The `http://` or `https://` URL the payload is posted to.
+    pub url: String,
+    /// The events sent to this receiver (`done`, `failed`, `canceled`); all of them if empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// The key of the HMAC-SHA256 signature in the `X-Speicherwald-Signature`
+    /// header; requests are not signed without one.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl WebhookEndpointConfig {
+    /// Whether the receiver wants the given event.
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+/// Configuration for the notifications about finished scans.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhooksConfig {
+    /// The receivers, configured as `[[webhooks.endpoints]]` entries.
+    pub endpoints: Vec<WebhookEndpointConfig>,
+    /// The timeout of one delivery attempt, in seconds.
+    pub timeout_secs: u64,
+    /// How often a failed delivery is retried.
+    pub max_retries: u32,
+    /// The wait before the first retry, in milliseconds; it doubles with every further one.
+    pub retry_backoff_ms: u64,
+}
+
+/// Exclude patterns added to every scan of a system drive.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SystemExcludesConfig {
+    /// The patterns for Windows system drives, e.g. `pagefile.sys`.
+    pub windows: Vec<String>,
+    /// The patterns for the root file system on other platforms, e.g. `/proc`.
+    pub unix: Vec<String>,
+    /// The roots that count as system drives; empty detects the drive Windows
+    /// is installed on (`%SystemDrive%`), or `/` on other platforms.
+    pub drives: Vec<String>,
+}
+
+impl SystemExcludesConfig {
+    /// Returns the patterns of the platform the server runs on.
+    pub fn patterns(&self) -> &[String] {
+        if cfg!(windows) {
+            &self.windows
+        } else {
+            &self.unix
+        }
+    }
+
+    /// Returns the roots that count as system drives.
+    pub fn system_drives(&self) -> Vec<String> {
+        if !self.drives.is_empty() {
+            return self.drives.clone();
+        }
+        if cfg!(windows) {
+            let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+            vec![format!("{}\\", drive.trim_end_matches(['\\', '/']))]
+        } else {
+            vec!["/".to_string()]
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// Returns whether any limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.max_scans_per_root.is_some() || self.max_age_days.is_some()
+    }
+}
+
+/// Configuration for API token authentication.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Whether requests must carry one of `tokens` as a bearer token.
+    pub enabled: bool,
+    /// The accepted tokens. A comma-separated string is accepted as well, so
+    /// `SPEICHERWALD__AUTH__TOKENS=a,b` can set several.
+    #[serde(deserialize_with = "list_or_comma_separated")]
+    pub tokens: Vec<String>,
+}
+
+/// Deserializes a list of strings from a list or a comma-separated string.
+fn list_or_comma_separated<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrString {
+        List(Vec<String>),
+        Joined(String),
+    }
+    let items = match ListOrString::deserialize(deserializer)? {
+        ListOrString::List(items) => items,
+        ListOrString::Joined(joined) => joined.split(',').map(str::to_string).collect(),
+    };
+    Ok(items.into_iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+}
+
+/// A per-endpoint rate limit, configured as one `[[rate_limits]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    /// The route pattern, e.g. `/scans` or `/scans/:id/events`. Placeholders
+    /// may be written as `:name` or `{name}`.
+    pub pattern: String,
+    /// The maximum number of requests per client IP within the window.
+    pub max_requests: usize,
+    /// The length of the window, in seconds.
+    pub window_secs: u64,
+}
+
+/// Returns the built-in per-endpoint limits, mirroring `config/default.toml`.
+pub fn default_rate_limits() -> Vec<RateLimitConfig> {
+    [
+        ("/scans", 30, 60),
+        ("/scans/:id/events", 30, 60),
+        ("/scans/:id/search", 600, 60),
+        ("/search", 120, 60),
+        ("/drives", 120, 60),
+        ("/paths/move", 10, 60),
+        ("/paths/move-batch", 10, 60),
+        ("/paths/delete", 10, 60),
+        ("/paths/archive", 10, 60),
+        ("/scans/retention/run", 5, 60),
+        ("/admin/backup", 5, 60),
+        ("/admin/backup/restore", 2, 60),
+        ("/admin/reload", 5, 60),
+        ("/admin/db/vacuum", 2, 60),
+        ("/admin/db/analyze", 5, 60),
+        ("/admin/db/integrity", 5, 60),
+    ]
+    .into_iter()
+    .map(|(pattern, max_requests, window_secs)| RateLimitConfig {
+        pattern: pattern.to_string(),
+        max_requests,
+        window_secs,
+    })
+    .collect()
 }
 
 /// Configuration for security-related HTTP headers.
@@ -78,6 +397,36 @@ pub struct AppConfig {
     pub scanner: ScannerConfig,
     /// Security headers configuration.
     pub security: Option<SecurityConfig>,
+    /// Analysis configuration.
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
+    /// File categories.
+    #[serde(default)]
+    pub categories: CategoriesConfig,
+    /// Server-Sent Events configuration.
+    #[serde(default)]
+    pub sse: SseConfig,
+    /// Log output configuration.
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// API token authentication.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Scan retention policy.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Free space history of the drives.
+    #[serde(default)]
+    pub drive_history: DriveHistoryConfig,
+    /// Exclude patterns for scans of system drives.
+    #[serde(default)]
+    pub system_excludes: SystemExcludesConfig,
+    /// Notifications about finished scans.
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+    /// Per-endpoint rate limits. Setting them replaces the built-in list as a whole.
+    #[serde(default = "default_rate_limits")]
+    pub rate_limits: Vec<RateLimitConfig>,
 }
 
 impl Default for AppConfig {
@@ -109,13 +458,228 @@ impl Default for ScannerConfig {
         Self {
             batch_size: 4000,
             flush_threshold: 8000,
+            max_buffer_bytes: 64 * 1024 * 1024,
             flush_interval_ms: 750,
             handle_limit: None,
             dir_concurrency: Some(12),
+            max_entries_per_dir: None,
+            duplicate_min_size: 1024 * 1024,
+            watch_debounce_ms: 1000,
+            persist_max_retries: 8,
+            persist_retry_budget_ms: 120_000,
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self { enabled: false, cert_path: String::new(), key_path: String::new(), reload_interval_secs: 60 }
+    }
+}
+
+impl Default for SseConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_secs: 10,
+            heartbeat_secs: 15,
+            padding_bytes: 2048,
+            replay_grace_secs: 300,
+            max_connections_per_client: 5,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { max_scans_per_root: None, max_age_days: None, interval_secs: 3600, incremental_vacuum: true }
+    }
+}
+
+impl Default for DriveHistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, interval_secs: 900, retention_days: 90, purge_missing_hours: 24 }
+    }
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self { endpoints: Vec::new(), timeout_secs: 5, max_retries: 3, retry_backoff_ms: 1000 }
+    }
+}
+
+impl Default for SystemExcludesConfig {
+    fn default() -> Self {
+        let list = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        Self {
+            windows: list(&[
+                "pagefile.sys",
+                "hiberfil.sys",
+                "swapfile.sys",
+                "DumpStack.log.tmp",
+                "$Recycle.Bin",
+                "System Volume Information",
+            ]),
+            unix: list(&["/proc", "/sys", "/dev", "/run", "/swapfile"]),
+            drives: Vec::new(),
+        }
+    }
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        let class = |name: &str, extensions: &[&str], expected_ratio: f64| CompressionClassConfig {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            expected_ratio,
+        };
+        Self {
+            compression_classes: vec![
+                class(
+                    "text",
+                    &[
+                        "txt", "log", "csv", "tsv", "json", "xml", "html", "htm", "md", "sql", "yaml", "yml",
+                        "ini", "cfg", "conf", "svg", "rtf",
+                    ],
+                    0.7,
+                ),
+                class("source", &["c", "h", "cpp", "hpp", "cs", "java", "js", "ts", "py", "rs", "go"], 0.65),
+                class("uncompressed_image", &["bmp", "tif", "tiff", "psd", "raw", "dng", "tga"], 0.5),
+                class("database", &["db", "sqlite", "mdb", "accdb", "mdf", "ldf", "dbf"], 0.4),
+                class("binary", &["exe", "dll", "so", "o", "obj", "lib", "pdb", "sys", "bin"], 0.35),
+                class("uncompressed_audio", &["wav", "aif", "aiff"], 0.1),
+                class(
+                    "compressed",
+                    &[
+                        "zip", "7z", "rar", "gz", "tgz", "bz2", "xz", "zst", "cab", "jpg", "jpeg", "png",
+                        "gif", "webp", "heic", "mp3", "aac", "ogg", "flac", "m4a", "mp4", "mkv", "avi",
+                        "mov", "wmv", "webm", "docx", "xlsx", "pptx", "pdf", "jar", "apk", "msi",
+                    ],
+                    0.0,
+                ),
+            ],
+            unclassified_ratio: 0.1,
+        }
+    }
+}
+
+impl Default for CategoriesConfig {
+    fn default() -> Self {
+        let group = |name: &str, extensions: &[&str]| CategoryConfig {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        };
+        Self {
+            enabled: true,
+            groups: vec![
+                group("video", &["mp4", "mkv", "avi", "mov", "wmv", "webm", "m4v", "mpg", "mpeg", "flv"]),
+                group(
+                    "images",
+                    &[
+                        "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "raw", "dng",
+                        "cr2", "nef", "psd", "svg", "ico",
+                    ],
+                ),
+                group("audio", &["mp3", "wav", "flac", "aac", "ogg", "m4a", "wma", "aif", "aiff", "opus"]),
+                group(
+                    "archives",
+                    &[
+                        "zip", "7z", "rar", "gz", "tgz", "bz2", "xz", "zst", "tar", "cab", "iso", "img",
+                        "vhd", "vhdx",
+                    ],
+                ),
+                group(
+                    "documents",
+                    &[
+                        "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "rtf", "txt",
+                        "md",
+                    ],
+                ),
+                group("databases", &["db", "sqlite", "sqlite3", "mdb", "accdb", "mdf", "ldf", "ndf", "dbf"]),
+                group("logs", &["log", "etl", "evtx", "dmp"]),
+            ],
         }
     }
 }
 
+/// The configuration of the running server, shared by handlers and middleware.
+///
+/// A reload swaps the whole configuration at once. Readers keep the snapshot
+/// they took, so a request never mixes settings of the old and the new one.
+#[derive(Debug, Clone)]
+pub struct SharedConfig(Arc<RwLock<Arc<AppConfig>>>);
+
+impl SharedConfig {
+    /// Wraps the configuration the server starts with.
+    pub fn new(cfg: AppConfig) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(cfg))))
+    }
+
+    /// Returns the current configuration.
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replaces the configuration for all later readers.
+    pub fn replace(&self, cfg: AppConfig) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(cfg);
+    }
+}
+
+/// Prepares a freshly loaded configuration for replacing the running one.
+///
+/// Settings that are only read at startup keep their current value in the
+/// returned configuration, so it always describes what the server really uses.
+///
+/// # Arguments
+///
+/// * `current` - The configuration the server runs with.
+/// * `new` - The newly loaded and validated configuration.
+///
+/// # Returns
+///
+/// * `(AppConfig, Vec<String>)` - The configuration to apply and the names of
+///   the settings that changed but only take effect after a restart.
+pub fn merge_reload(current: &AppConfig, mut new: AppConfig) -> (AppConfig, Vec<String>) {
+    let mut restart = Vec::new();
+    if new.server.host != current.server.host {
+        restart.push("server.host".to_string());
+    }
+    if new.server.port != current.server.port {
+        restart.push("server.port".to_string());
+    }
+    // A renewed certificate in the same files is picked up by the listener itself
+    if new.server.tls != current.server.tls {
+        restart.push("server.tls".to_string());
+    }
+    new.server = current.server.clone();
+    if new.database.url != current.database.url {
+        restart.push("database.url".to_string());
+    }
+    new.database.url = current.database.url.clone();
+    // The tokens are handed to the auth middleware once
+    if new.auth.enabled != current.auth.enabled || new.auth.tokens != current.auth.tokens {
+        restart.push("auth".to_string());
+    }
+    new.auth = current.auth.clone();
+    if new.retention.interval_secs != current.retention.interval_secs {
+        restart.push("retention.interval_secs".to_string());
+    }
+    new.retention.interval_secs = current.retention.interval_secs;
+    let (a, b) = (&new.drive_history, &current.drive_history);
+    if (a.enabled, a.interval_secs, a.retention_days, a.purge_missing_hours)
+        != (b.enabled, b.interval_secs, b.retention_days, b.purge_missing_hours)
+    {
+        restart.push("drive_history".to_string());
+    }
+    new.drive_history = current.drive_history.clone();
+    // The log layers are set up once at startup
+    if new.logging.format != current.logging.format {
+        restart.push("logging.format".to_string());
+    }
+    new.logging = current.logging.clone();
+    (new, restart)
+}
+
 /// Loads the application configuration from various sources.
 ///
 /// This function loads configuration in the following order of precedence (highest to lowest):
@@ -147,53 +711,269 @@ pub fn load() -> anyhow::Result<AppConfig> {
 
     let cfg = builder.build()?;
     let app_cfg: AppConfig = cfg.try_deserialize()?;
-    validate(&app_cfg)?;
+    app_cfg.validate()?;
     Ok(app_cfg)
 }
 
-fn validate(cfg: &AppConfig) -> anyhow::Result<()> {
+/// The largest `scanner.batch_size`; SQLite limits the bound parameters of one statement.
+pub const MAX_BATCH_SIZE: usize = 10_000;
+
+/// All problems found in a configuration.
+///
+/// Displayed as a block with one line per problem, so a broken configuration
+/// can be fixed in one go instead of one restart per mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl std::fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid configuration ({} error(s)):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+impl AppConfig {
+    /// Checks the configuration after files and environment overrides were merged.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ConfigErrors>` - Every violated rule, not only the first.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
+        validate(self, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+
+    /// Settings that are valid but may not work as intended.
+    ///
+    /// [`load`] runs before logging is set up, so the caller logs these once it is.
+    pub fn warnings(&self) -> Vec<String> {
+        // Privileged ports on Unix-like systems
+        let privileged = cfg!(unix) && self.server.port != 0 && self.server.port < 1024;
+        privileged
+            .then(|| format!("Using privileged port {} - may require elevated permissions", self.server.port))
+            .into_iter()
+            .collect()
+    }
+}
+
+fn validate(cfg: &AppConfig, errors: &mut Vec<String>) {
     // Server
-    if cfg.server.port == 0 {
-        return Err(anyhow::anyhow!("invalid server.port: {}", cfg.server.port));
+    require(errors, cfg.server.port != 0, "server.port must not be 0");
+    if cfg.server.port != 0
+        && format!("{}:{}", cfg.server.host, cfg.server.port).parse::<SocketAddr>().is_err()
+    {
+        errors.push(format!(
+            "server.host '{}' must be an IP address (IPv6 in brackets, e.g. [::1])",
+            cfg.server.host
+        ));
+    }
+    if cfg.server.tls.enabled && (cfg.server.tls.cert_path.is_empty() || cfg.server.tls.key_path.is_empty()) {
+        errors.push("server.tls.enabled requires server.tls.cert_path and server.tls.key_path".into());
     }
-    // Warn for privileged ports on Unix-like systems
-    #[cfg(unix)]
-    if cfg.server.port < 1024 {
-        tracing::warn!("Using privileged port {} - may require elevated permissions", cfg.server.port);
+
+    // Database
+    if !cfg.database.url.starts_with("sqlite:") {
+        errors.push(format!(
+            "database.url '{}' must be a SQLite URL (sqlite://path/to/file.db)",
+            cfg.database.url
+        ));
     }
 
     // Scanner
-    if cfg.scanner.batch_size == 0 {
-        return Err(anyhow::anyhow!("scanner.batch_size must be > 0"));
+    let scanner = &cfg.scanner;
+    if !(1..=MAX_BATCH_SIZE).contains(&scanner.batch_size) {
+        errors.push(format!("scanner.batch_size must be in 1..={}", MAX_BATCH_SIZE));
     }
-    if cfg.scanner.flush_threshold == 0 {
-        return Err(anyhow::anyhow!("scanner.flush_threshold must be > 0"));
+    if scanner.flush_threshold < scanner.batch_size {
+        errors.push(format!(
+            "scanner.flush_threshold ({}) must be >= batch_size ({})",
+            scanner.flush_threshold, scanner.batch_size
+        ));
     }
-    if cfg.scanner.flush_threshold <= cfg.scanner.batch_size {
-        return Err(anyhow::anyhow!("scanner.flush_threshold must be > batch_size"));
+    require(errors, scanner.flush_threshold > 0, "scanner.flush_threshold must be > 0");
+    require(errors, scanner.max_buffer_bytes > 0, "scanner.max_buffer_bytes must be > 0");
+    require(errors, scanner.flush_interval_ms > 0, "scanner.flush_interval_ms must be > 0");
+    require(errors, scanner.watch_debounce_ms > 0, "scanner.watch_debounce_ms must be > 0");
+    require(
+        errors,
+        scanner.dir_concurrency.is_none_or(|dc| (1..=256).contains(&dc)),
+        "scanner.dir_concurrency must be in 1..=256",
+    );
+    require(errors, scanner.handle_limit != Some(0), "scanner.handle_limit must be > 0 when set");
+    require(
+        errors,
+        scanner.max_entries_per_dir != Some(0),
+        "scanner.max_entries_per_dir must be > 0 when set",
+    );
+
+    // Scan defaults
+    require(
+        errors,
+        cfg.scan_defaults.concurrency.is_none_or(|c| (1..=256).contains(&c)),
+        "scan_defaults.concurrency must be in 1..=256",
+    );
+    // Every worker holds a handle, so a lower limit silently shrinks the worker pool
+    if let Some(h) = scanner.handle_limit.filter(|h| *h > 0) {
+        for (name, concurrency) in [
+            ("scanner.dir_concurrency", scanner.dir_concurrency),
+            ("scan_defaults.concurrency", cfg.scan_defaults.concurrency),
+        ] {
+            if let Some(c) = concurrency.filter(|c| *c > h) {
+                errors.push(format!("scanner.handle_limit ({}) must be >= {} ({})", h, name, c));
+            }
+        }
     }
-    if cfg.scanner.flush_interval_ms == 0 {
-        return Err(anyhow::anyhow!("scanner.flush_interval_ms must be > 0"));
+
+    // System excludes
+    for pat in cfg.system_excludes.windows.iter().chain(&cfg.system_excludes.unix) {
+        if let Err(e) = globset::Glob::new(&pat.trim().replace('\\', "/")) {
+            errors.push(format!("system_excludes: invalid pattern '{}': {}", pat, e));
+        }
     }
-    if let Some(dc) = cfg.scanner.dir_concurrency {
-        if dc == 0 || dc > 256 {
-            return Err(anyhow::anyhow!("scanner.dir_concurrency must be in 1..=256"));
+
+    // Analysis
+    validate_analysis(&cfg.analysis, errors);
+
+    // Categories
+    validate_categories(&cfg.categories, errors);
+
+    // Auth
+    require(
+        errors,
+        !cfg.auth.enabled || !cfg.auth.tokens.is_empty(),
+        "auth.enabled requires at least one token in auth.tokens",
+    );
+    require(
+        errors,
+        !cfg.auth.tokens.iter().any(|t| t.chars().any(char::is_whitespace)),
+        "auth.tokens must not contain whitespace",
+    );
+
+    // SSE
+    require(errors, cfg.sse.keep_alive_secs > 0, "sse.keep_alive_secs must be > 0");
+    require(errors, cfg.sse.padding_bytes <= 65_536, "sse.padding_bytes must be <= 65536");
+
+    // Retention
+    require(
+        errors,
+        cfg.retention.max_scans_per_root != Some(0),
+        "retention.max_scans_per_root must be > 0 when set",
+    );
+    require(errors, cfg.retention.max_age_days != Some(0), "retention.max_age_days must be > 0 when set");
+    require(errors, cfg.retention.interval_secs > 0, "retention.interval_secs must be > 0");
+
+    // Drive history
+    require(errors, cfg.drive_history.interval_secs >= 60, "drive_history.interval_secs must be >= 60");
+    require(errors, cfg.drive_history.retention_days > 0, "drive_history.retention_days must be > 0");
+
+    // Webhooks
+    validate_webhooks(&cfg.webhooks, errors);
+
+    // Rate limits
+    validate_rate_limits(&cfg.rate_limits, errors);
+}
+
+/// Records `message` unless the rule holds.
+fn require(errors: &mut Vec<String>, ok: bool, message: &str) {
+    if !ok {
+        errors.push(message.to_string());
+    }
+}
+
+fn validate_webhooks(cfg: &WebhooksConfig, errors: &mut Vec<String>) {
+    if cfg.timeout_secs == 0 {
+        errors.push("webhooks.timeout_secs must be > 0".into());
+    }
+    if cfg.max_retries > 10 {
+        errors.push("webhooks.max_retries must be <= 10".into());
+    }
+    for endpoint in &cfg.endpoints {
+        if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+            errors.push(format!(
+                "webhooks.endpoints: url '{}' must start with http:// or https://",
+                endpoint.url
+            ));
+        }
+        let known = |e: &&String| ["done", "failed", "canceled"].contains(&e.as_str());
+        for event in endpoint.events.iter().filter(|e| !known(e)) {
+            errors.push(format!(
+                "webhooks.endpoints: unknown event '{}' of '{}', expected done, failed or canceled",
+                event, endpoint.url
+            ));
+        }
+        if endpoint.secret.as_deref() == Some("") {
+            errors.push(format!("webhooks.endpoints: secret of '{}' must not be empty", endpoint.url));
         }
     }
-    if let Some(h) = cfg.scanner.handle_limit {
-        if h == 0 {
-            return Err(anyhow::anyhow!("scanner.handle_limit must be > 0 when set"));
+}
+
+fn validate_rate_limits(limits: &[RateLimitConfig], errors: &mut Vec<String>) {
+    for limit in limits {
+        if !limit.pattern.starts_with('/') {
+            errors.push(format!("rate_limits: pattern '{}' must start with '/'", limit.pattern));
+        }
+        if limit.max_requests == 0 {
+            errors.push(format!("rate_limits: max_requests of '{}' must be > 0", limit.pattern));
+        }
+        if limit.window_secs == 0 {
+            errors.push(format!("rate_limits: window_secs of '{}' must be > 0", limit.pattern));
         }
     }
+}
 
-    // Scan defaults
-    if let Some(c) = cfg.scan_defaults.concurrency {
-        if c == 0 || c > 256 {
-            return Err(anyhow::anyhow!("scan_defaults.concurrency must be in 1..=256"));
+fn validate_categories(cfg: &CategoriesConfig, errors: &mut Vec<String>) {
+    for group in &cfg.groups {
+        if group.name.trim().is_empty() {
+            errors.push("categories.groups: name must not be empty".into());
+        }
+        for ext in &group.extensions {
+            if ext.is_empty() || ext.contains('.') || ext.to_lowercase() != *ext {
+                errors.push(format!(
+                    "categories.groups: extension '{}' must be lowercase and without a dot",
+                    ext
+                ));
+            }
         }
     }
+}
 
-    Ok(())
+fn validate_analysis(cfg: &AnalysisConfig, errors: &mut Vec<String>) {
+    if !(0.0..=1.0).contains(&cfg.unclassified_ratio) {
+        errors.push("analysis.unclassified_ratio must be in 0..=1".into());
+    }
+    let mut seen = std::collections::HashSet::new();
+    for class in &cfg.compression_classes {
+        if class.name.trim().is_empty() {
+            errors.push("analysis.compression_classes: name must not be empty".into());
+        }
+        if !(0.0..=1.0).contains(&class.expected_ratio) {
+            errors.push(format!(
+                "analysis.compression_classes: expected_ratio of '{}' must be in 0..=1",
+                class.name
+            ));
+        }
+        for ext in &class.extensions {
+            if ext.is_empty() || ext.contains('.') || ext.to_lowercase() != *ext {
+                errors.push(format!(
+                    "analysis.compression_classes: extension '{}' must be lowercase and without a dot",
+                    ext
+                ));
+            }
+            if !seen.insert(ext.as_str()) {
+                errors.push(format!("analysis.compression_classes: extension '{}' is listed twice", ext));
+            }
+        }
+    }
 }
 
 /// Ensures that the parent directory for a SQLite database file exists.
@@ -239,3 +1019,85 @@ pub fn ensure_sqlite_parent_dir(url: &str) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The violations of a default configuration changed by `change`.
+    fn violations(change: impl FnOnce(&mut AppConfig)) -> Vec<String> {
+        let mut cfg = AppConfig::default();
+        change(&mut cfg);
+        cfg.validate().err().map(|e| e.0).unwrap_or_default()
+    }
+
+    #[test]
+    fn validate_applies_every_rule() {
+        assert!(AppConfig::default().validate().is_ok());
+        assert_eq!(violations(|c| c.server.port = 0), ["server.port must not be 0"]);
+        assert!(violations(|c| c.server.host = "0.0.0.0.0".into())[0].contains("server.host '0.0.0.0.0'"));
+        assert!(violations(|c| c.server.host = "[::1]".into()).is_empty());
+        assert!(violations(|c| c.database.url = "postgres://db".into())[0].contains("SQLite URL"));
+        assert!(violations(|c| c.database.url = "sqlite::memory:".into()).is_empty());
+        assert_eq!(
+            violations(|c| c.scanner.batch_size = 10_001),
+            [
+                "scanner.batch_size must be in 1..=10000",
+                "scanner.flush_threshold (8000) must be >= batch_size (10001)",
+            ]
+        );
+        // A threshold equal to the batch size flushes every full batch
+        assert!(violations(|c| c.scanner.flush_threshold = 4000).is_empty());
+        assert_eq!(
+            violations(|c| {
+                c.scanner.handle_limit = Some(8);
+                c.scan_defaults.concurrency = Some(8);
+            }),
+            ["scanner.handle_limit (8) must be >= scanner.dir_concurrency (12)"]
+        );
+        assert_eq!(
+            violations(|c| {
+                c.scanner.handle_limit = Some(16);
+                c.scan_defaults.concurrency = Some(32);
+            }),
+            ["scanner.handle_limit (16) must be >= scan_defaults.concurrency (32)"]
+        );
+        assert!(violations(|c| c.scanner.handle_limit = Some(12)).is_empty());
+    }
+
+    #[test]
+    fn privileged_port_is_a_warning() {
+        let mut cfg = AppConfig::default();
+        cfg.server.port = 80;
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.warnings().len(), usize::from(cfg!(unix)));
+        cfg.server.port = 8080;
+        assert!(cfg.warnings().is_empty());
+    }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        let errors = violations(|c| {
+            c.server.port = 0;
+            c.scanner.batch_size = 0;
+            c.scanner.flush_interval_ms = 0;
+            c.database.url = "mysql://db".into();
+        });
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        let text = ConfigErrors(errors).to_string();
+        let head = "invalid configuration (4 error(s)):\n  - server.port must not be 0\n";
+        assert!(text.starts_with(head), "{}", text);
+    }
+
+    #[test]
+    fn env_overrides_are_validated() {
+        std::env::set_var("SPEICHERWALD__SCANNER__BATCH_SIZE", "20000");
+        std::env::set_var("SPEICHERWALD__DATABASE__URL", "postgres://db");
+        let err = load().unwrap_err();
+        let errors = &err.downcast_ref::<ConfigErrors>().unwrap().0;
+        assert!(errors.iter().any(|e| e.starts_with("scanner.batch_size")), "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("database.url")), "{:?}", errors);
+        std::env::remove_var("SPEICHERWALD__SCANNER__BATCH_SIZE");
+        std::env::remove_var("SPEICHERWALD__DATABASE__URL");
+    }
+}
@@ -1,4 +1,99 @@
-use sqlx::SqlitePool;
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+/// The schema version [`init_db`] stamps into `PRAGMA user_version`.
+///
+/// Bump it whenever `init_db` adds a table or column, so that backups taken with
+/// an older schema are rejected on restore.
+///
+/// - 2: `files.ads_count` and `files.ads_size`
+/// - 3: unique `(scan_id, path)` indexes on `nodes` and `files`
+/// - 4: `drive_history`
+/// - 5: `files.owner` and `owner_usage`
+/// - 6: `scans.duration_ms` and the throughput columns
+/// - 7: `scans.read_only`
+/// - 8: `files.category`
+/// - 9: `scan_verifications`
+/// - 10: `nodes.path_raw` and `files.path_raw`
+/// - 11: `scans.backup_mode`
+/// - 12: `(scan_id, parent_path, allocated_size DESC, path)` indexes on `nodes` and `files`
+/// - 13: `scan_inconsistencies`
+/// - 14: `files.hash`
+/// - 15: `share_links`
+/// - 16: `scans.origin` and `import_sessions`
+/// - 17: `scan_links`
+pub const SCHEMA_VERSION: i64 = 17;
+
+/// Opens the read/write connection pool.
+///
+/// This pool is used by the scanner's batch writer and every other write path
+/// (scan creation, purge, schedules). It uses a long busy timeout so writers
+/// wait for each other instead of failing.
+///
+/// # Arguments
+///
+/// * `db_url` - The SQLite connection URL.
+/// * `max_connections` - The maximum number of pooled connections.
+///
+/// # Returns
+///
+/// * `anyhow::Result<SqlitePool>` - The connected pool.
+pub async fn connect_write_pool(db_url: &str, max_connections: u32) -> anyhow::Result<SqlitePool> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .after_connect(|conn, _meta| {
+            Box::pin(async move {
+                let _ = sqlx::query("PRAGMA foreign_keys=ON;").execute(&mut *conn).await;
+                let _ = sqlx::query("PRAGMA busy_timeout=10000;").execute(&mut *conn).await;
+                let _ = sqlx::query("PRAGMA cache_size=-65536;").execute(&mut *conn).await; // ~64MB page cache
+                let _ = sqlx::query("PRAGMA temp_store=MEMORY;").execute(&mut *conn).await;
+                let _ = sqlx::query("PRAGMA mmap_size=268435456;").execute(&mut *conn).await; // 256MB mmap
+                Ok(())
+            })
+        })
+        .connect(db_url)
+        .await?;
+    Ok(pool)
+}
+
+/// Opens the read-only connection pool used by query endpoints.
+///
+/// Connections are opened with `PRAGMA query_only=ON` and a short busy
+/// timeout. In WAL mode readers never wait for the writer, so a reader that
+/// does hit `SQLITE_BUSY` (e.g. during a checkpoint) fails fast instead of
+/// stalling the UI for seconds.
+///
+/// # Arguments
+///
+/// * `db_url` - The SQLite connection URL.
+/// * `max_connections` - The maximum number of pooled connections.
+/// * `busy_timeout_ms` - The busy timeout for read connections in milliseconds.
+///
+/// # Returns
+///
+/// * `anyhow::Result<SqlitePool>` - The connected pool.
+pub async fn connect_read_pool(
+    db_url: &str,
+    max_connections: u32,
+    busy_timeout_ms: u64,
+) -> anyhow::Result<SqlitePool> {
+    let busy_pragma = format!("PRAGMA busy_timeout={};", busy_timeout_ms);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .after_connect(move |conn, _meta| {
+            let busy_pragma = busy_pragma.clone();
+            Box::pin(async move {
+                sqlx::query("PRAGMA query_only=ON;").execute(&mut *conn).await?;
+                let _ = sqlx::query(&busy_pragma).execute(&mut *conn).await;
+                let _ = sqlx::query("PRAGMA cache_size=-16384;").execute(&mut *conn).await; // ~16MB page cache
+                let _ = sqlx::query("PRAGMA temp_store=MEMORY;").execute(&mut *conn).await;
+                let _ = sqlx::query("PRAGMA mmap_size=268435456;").execute(&mut *conn).await; // 256MB mmap
+                Ok(())
+            })
+        })
+        .connect(db_url)
+        .await?;
+    Ok(pool)
+}
 
 /// Initializes the database by creating tables and indexes if they don't exist.
 ///
@@ -16,6 +111,11 @@ use sqlx::SqlitePool;
 pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
     // FIX Bug #57 - Log PRAGMA failures
     // Pragmas for better durability/performance
+    // Incremental auto-vacuum lets the retention task return pages of pruned scans to the OS.
+    // Only takes effect on a new, empty database; existing ones keep their mode.
+    if let Err(e) = sqlx::query("PRAGMA auto_vacuum=INCREMENTAL;").execute(pool).await {
+        tracing::warn!("Failed to set auto_vacuum: {}", e);
+    }
     if let Err(e) = sqlx::query("PRAGMA journal_mode=WAL;").execute(pool).await {
         tracing::warn!("Failed to set WAL journal mode: {}", e);
     }
@@ -52,13 +152,19 @@ pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
             total_allocated_size INTEGER NULL,
             dir_count INTEGER NULL,
             file_count INTEGER NULL,
-            warning_count INTEGER NULL
+            warning_count INTEGER NULL,
+            dedup_saved_bytes INTEGER NULL,
+            namespace TEXT NOT NULL DEFAULT 'default',
+            duration_ms INTEGER NULL,
+            dirs_per_sec REAL NULL,
+            files_per_sec REAL NULL,
+            bytes_per_sec REAL NULL
         )"#,
     )
     .execute(pool)
     .await?;
 
-    // warnings table (optional for future use)
+    // warnings table (details of the warnings counted in scans.warning_count)
     sqlx::query(
         r#"CREATE TABLE IF NOT EXISTS warnings (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -88,6 +194,9 @@ pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
             dir_count INTEGER NOT NULL,
             mtime INTEGER NULL,
             atime INTEGER NULL,
+            fingerprint INTEGER NULL,
+            empty_file_count INTEGER NULL,
+            path_raw BLOB NULL,
             FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
         )"#,
     )
@@ -105,16 +214,233 @@ pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
             allocated_size INTEGER NOT NULL,
             mtime INTEGER NULL,
             atime INTEGER NULL,
+            ads_count INTEGER NULL,
+            ads_size INTEGER NULL,
+            owner TEXT NULL,
+            category TEXT NULL,
+            path_raw BLOB NULL,
+            hash TEXT NULL,
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // schedules table (recurring scans, survive restarts)
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS schedules (
+            id TEXT PRIMARY KEY,
+            request TEXT NOT NULL,
+            interval_minutes INTEGER NULL,
+            cron TEXT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            last_run_at TEXT NULL,
+            next_run_at TEXT NOT NULL,
+            last_scan_id TEXT NULL,
+            namespace TEXT NOT NULL DEFAULT 'default'
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // duplicates table (groups of identical files found by a duplicate search)
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS duplicates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            file_count INTEGER NOT NULL,
+            wasted_bytes INTEGER NOT NULL,
+            paths TEXT NOT NULL,
             FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
         )"#,
     )
     .execute(pool)
     .await?;
 
+    // scan_remaps table (audit trail of root remaps applied to a scan's paths)
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS scan_remaps (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            from_root TEXT NOT NULL,
+            to_root TEXT NOT NULL,
+            namespace TEXT NOT NULL,
+            nodes_updated INTEGER NOT NULL,
+            files_updated INTEGER NOT NULL,
+            remapped_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Subtotals of each root of a scan, written when the scan ends
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS scan_roots (
+            scan_id TEXT NOT NULL,
+            root_index INTEGER NOT NULL,
+            root_path TEXT NOT NULL,
+            dir_count INTEGER NOT NULL,
+            file_count INTEGER NOT NULL,
+            logical_size INTEGER NOT NULL,
+            allocated_size INTEGER NOT NULL,
+            warning_count INTEGER NOT NULL,
+            PRIMARY KEY(scan_id, root_index),
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Symbolic links, junctions and other reparse points met by a scan
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS scan_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            parent_path TEXT NULL,
+            kind TEXT NOT NULL,
+            target TEXT NULL,
+            followed INTEGER NOT NULL,
+            outside_root INTEGER NULL,
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Bytes per owner of the files directly inside each directory (scans with capture_owner)
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS owner_usage (
+            scan_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            owner TEXT NOT NULL,
+            file_count INTEGER NOT NULL,
+            logical_size INTEGER NOT NULL,
+            allocated_size INTEGER NOT NULL,
+            PRIMARY KEY (scan_id, path, owner),
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Re-checks of a sample of a scan's paths against the filesystem
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS scan_verifications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            sampled_dirs INTEGER NOT NULL,
+            sampled_files INTEGER NOT NULL,
+            missing INTEGER NOT NULL,
+            changed INTEGER NOT NULL,
+            drift_bytes INTEGER NOT NULL,
+            verified_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Directories whose stored totals do not add up to their children
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS scan_inconsistencies (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            depth INTEGER NOT NULL,
+            logical_size INTEGER NOT NULL,
+            expected_logical_size INTEGER NOT NULL,
+            allocated_size INTEGER NOT NULL,
+            expected_allocated_size INTEGER NOT NULL,
+            file_count INTEGER NOT NULL,
+            expected_file_count INTEGER NOT NULL,
+            dir_count INTEGER NOT NULL,
+            expected_dir_count INTEGER NOT NULL,
+            detected_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            repaired_at TEXT NULL,
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Read-only share links of finished scans
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS share_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scan_id TEXT NOT NULL,
+            token TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            expires_at TEXT NULL,
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Uploads of exported scans that are not committed yet, see crate::import
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS import_sessions (
+            id TEXT PRIMARY KEY,
+            scan_id TEXT NOT NULL,
+            format TEXT NOT NULL,
+            received_bytes INTEGER NOT NULL DEFAULT 0,
+            pending BLOB NOT NULL DEFAULT x'',
+            csv_header TEXT NULL,
+            record_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+            FOREIGN KEY(scan_id) REFERENCES scans(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Free space samples of the drives, independent of any scan
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS drive_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            sampled_at INTEGER NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            free_bytes INTEGER NOT NULL
+        )"#,
+    )
+    .execute(pool)
+    .await?;
+
     // FIX Bug #56 - Better error detection for migrations
-    // Add timestamp columns if they don't exist (migrations)
-    for (table, column) in [("nodes", "mtime"), ("nodes", "atime"), ("files", "mtime"), ("files", "atime")] {
-        let query = format!("ALTER TABLE {} ADD COLUMN {} INTEGER NULL", table, column);
+    // Add columns introduced after the initial schema if they don't exist (migrations)
+    let added_columns = [
+        ("nodes", "mtime", "INTEGER NULL"),
+        ("nodes", "atime", "INTEGER NULL"),
+        ("nodes", "fingerprint", "INTEGER NULL"),
+        ("nodes", "empty_file_count", "INTEGER NULL"),
+        ("nodes", "path_raw", "BLOB NULL"),
+        ("files", "mtime", "INTEGER NULL"),
+        ("files", "atime", "INTEGER NULL"),
+        ("files", "ads_count", "INTEGER NULL"),
+        ("files", "ads_size", "INTEGER NULL"),
+        ("files", "owner", "TEXT NULL"),
+        ("files", "category", "TEXT NULL"),
+        ("files", "path_raw", "BLOB NULL"),
+        ("files", "hash", "TEXT NULL"),
+        ("scans", "dedup_saved_bytes", "INTEGER NULL"),
+        ("scans", "namespace", "TEXT NOT NULL DEFAULT 'default'"),
+        ("scans", "duration_ms", "INTEGER NULL"),
+        ("scans", "dirs_per_sec", "REAL NULL"),
+        ("scans", "files_per_sec", "REAL NULL"),
+        ("scans", "bytes_per_sec", "REAL NULL"),
+        ("scans", "read_only", "INTEGER NOT NULL DEFAULT 0"),
+        ("scans", "backup_mode", "INTEGER NOT NULL DEFAULT 0"),
+        ("scans", "origin", "TEXT NULL"),
+        ("schedules", "namespace", "TEXT NOT NULL DEFAULT 'default'"),
+    ];
+    for (table, column, definition) in added_columns {
+        let query = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition);
         if let Err(e) = sqlx::query(&query).execute(pool).await {
             // Check if it's a benign "column already exists" error
             match &e {
@@ -133,17 +459,30 @@ pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
         }
     }
 
+    unique_scan_paths(pool).await?;
+    size_ordered_children(pool).await?;
+
     // FIX Bug #62 - Log index creation failures
     let indexes = [
         ("idx_scans_status_started", "CREATE INDEX IF NOT EXISTS idx_scans_status_started ON scans(status, started_at DESC)"),
+        ("idx_scans_namespace_started", "CREATE INDEX IF NOT EXISTS idx_scans_namespace_started ON scans(namespace, started_at DESC)"),
         ("idx_warnings_scan", "CREATE INDEX IF NOT EXISTS idx_warnings_scan ON warnings(scan_id)"),
-        ("idx_nodes_scan_path", "CREATE INDEX IF NOT EXISTS idx_nodes_scan_path ON nodes(scan_id, path)"),
+        ("idx_warnings_scan_code", "CREATE INDEX IF NOT EXISTS idx_warnings_scan_code ON warnings(scan_id, code)"),
         ("idx_nodes_scan_isdir", "CREATE INDEX IF NOT EXISTS idx_nodes_scan_isdir ON nodes(scan_id, is_dir)"),
-        ("idx_nodes_scan_parent", "CREATE INDEX IF NOT EXISTS idx_nodes_scan_parent ON nodes(scan_id, parent_path)"),
         ("idx_nodes_scan_isdir_alloc_desc", "CREATE INDEX IF NOT EXISTS idx_nodes_scan_isdir_alloc_desc ON nodes(scan_id, is_dir, allocated_size DESC)"),
-        ("idx_files_scan_parent", "CREATE INDEX IF NOT EXISTS idx_files_scan_parent ON files(scan_id, parent_path)"),
         ("idx_files_scan_size", "CREATE INDEX IF NOT EXISTS idx_files_scan_size ON files(scan_id, allocated_size DESC)"),
-        ("idx_files_scan_path", "CREATE INDEX IF NOT EXISTS idx_files_scan_path ON files(scan_id, path)"),
+        ("idx_files_scan_logical", "CREATE INDEX IF NOT EXISTS idx_files_scan_logical ON files(scan_id, logical_size)"),
+        ("idx_files_scan_mtime", "CREATE INDEX IF NOT EXISTS idx_files_scan_mtime ON files(scan_id, mtime)"),
+        ("idx_duplicates_scan_wasted", "CREATE INDEX IF NOT EXISTS idx_duplicates_scan_wasted ON duplicates(scan_id, wasted_bytes DESC)"),
+        ("idx_scan_remaps_scan", "CREATE INDEX IF NOT EXISTS idx_scan_remaps_scan ON scan_remaps(scan_id)"),
+        ("idx_scan_links_scan", "CREATE INDEX IF NOT EXISTS idx_scan_links_scan ON scan_links(scan_id, path)"),
+        ("idx_scan_verifications_scan", "CREATE INDEX IF NOT EXISTS idx_scan_verifications_scan ON scan_verifications(scan_id)"),
+        ("idx_scan_inconsistencies_scan", "CREATE INDEX IF NOT EXISTS idx_scan_inconsistencies_scan ON scan_inconsistencies(scan_id, depth DESC)"),
+        ("idx_share_links_scan", "CREATE INDEX IF NOT EXISTS idx_share_links_scan ON share_links(scan_id)"),
+        ("idx_import_sessions_scan", "CREATE INDEX IF NOT EXISTS idx_import_sessions_scan ON import_sessions(scan_id)"),
+        ("idx_drive_history_path_time", "CREATE INDEX IF NOT EXISTS idx_drive_history_path_time ON drive_history(path, sampled_at)"),
+        ("idx_schedules_next_run", "CREATE INDEX IF NOT EXISTS idx_schedules_next_run ON schedules(next_run_at)"),
+        ("idx_schedules_namespace", "CREATE INDEX IF NOT EXISTS idx_schedules_namespace ON schedules(namespace, created_at)"),
     ];
 
     // FIX Bug #39: Better handling of duplicate index creation
@@ -166,5 +505,246 @@ pub async fn init_db(pool: &SqlitePool) -> anyhow::Result<()> {
         }
     }
 
+    sqlx::query(&format!("PRAGMA user_version={};", SCHEMA_VERSION)).execute(pool).await?;
+
+    Ok(())
+}
+
+/// Makes `(scan_id, path)` unique in `nodes` and `files`.
+///
+/// The scanner writes records with `ON CONFLICT(scan_id, path) DO UPDATE`, so
+/// writing a directory again refreshes its rows instead of adding a second set
+/// that would double the sizes of its ancestors. Databases created before the
+/// index may already hold duplicates; of each group the row with the largest
+/// allocated size (the newest of equal ones) is kept. The unique index replaces
+/// the plain `(scan_id, path)` index of older schemas.
+async fn unique_scan_paths(pool: &SqlitePool) -> anyhow::Result<()> {
+    for table in ["nodes", "files"] {
+        let index = format!("ux_{}_scan_path", table);
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM sqlite_master WHERE type='index' AND name=?1")
+            .bind(&index)
+            .fetch_optional(pool)
+            .await?;
+        if exists.is_some() {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        let removed = sqlx::query(&format!(
+            r#"DELETE FROM {table} WHERE id IN (
+                   SELECT id FROM (
+                       SELECT id, ROW_NUMBER() OVER (
+                           PARTITION BY scan_id, path ORDER BY allocated_size DESC, id DESC
+                       ) AS rn FROM {table}
+                   ) WHERE rn > 1
+               )"#,
+            table = table
+        ))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if removed > 0 {
+            tracing::warn!("Removed {} duplicate rows from {} before adding {}", removed, table, index);
+        }
+        sqlx::query(&format!("CREATE UNIQUE INDEX {} ON {}(scan_id, path)", index, table)).execute(&mut *tx).await?;
+        sqlx::query(&format!("DROP INDEX IF EXISTS idx_{}_scan_path", table)).execute(&mut *tx).await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Indexes the children of each directory by allocated size.
+///
+/// Listing a directory asks for its largest subdirectories and files; with
+/// `(scan_id, parent_path, allocated_size DESC, path)` SQLite reads them in
+/// that order straight from the index instead of sorting every child. The
+/// indexes replace the plain `(scan_id, parent_path)` ones, which they cover.
+/// Databases older than schema version 12 get them once on startup; on large
+/// databases building them takes a while.
+async fn size_ordered_children(pool: &SqlitePool) -> anyhow::Result<()> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+    if version >= 12 {
+        return Ok(());
+    }
+    for table in ["nodes", "files"] {
+        let started = std::time::Instant::now();
+        let mut tx = pool.begin().await?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_scan_parent_alloc \
+             ON {table}(scan_id, parent_path, allocated_size DESC, path)",
+            table = table
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!("DROP INDEX IF EXISTS idx_{}_scan_parent", table)).execute(&mut *tx).await?;
+        tx.commit().await?;
+        if version > 0 {
+            tracing::info!("Indexed the {} of each directory by size in {:?}", table, started.elapsed());
+        }
+    }
     Ok(())
 }
+
+/// Marks scans that were still running when the server stopped as interrupted.
+///
+/// No scan task survives a restart, so every `running` or `paused` scan found
+/// at startup was cut off. Its records up to the last persisted batch, its roots and its
+/// options are kept, which lets clients start it again.
+///
+/// # Arguments
+///
+/// * `pool` - A `SqlitePool` connection pool to the database.
+///
+/// # Returns
+///
+/// * `anyhow::Result<Vec<String>>` - The IDs of the scans that were marked.
+pub async fn mark_interrupted_scans(pool: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    let ids = sqlx::query_scalar(
+        r#"UPDATE scans SET status='interrupted', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now')
+           WHERE status IN ('running','paused') RETURNING id"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::AppConfig,
+        middleware::namespace::Namespace,
+        routes::{
+            negotiate::ResponseFormat,
+            scans::{get_list, ListQuery},
+        },
+        state::AppState,
+        test_support::{insert_file, insert_node, insert_scan, test_db_in, FileRow, NodeRow},
+    };
+    use axum::extract::{Path, Query, State};
+    use std::time::{Duration, Instant};
+
+    async fn open_pools(dir: &tempfile::TempDir) -> (SqlitePool, SqlitePool) {
+        let write = test_db_in(dir.path()).await;
+        let db_url = format!("sqlite://{}?mode=rwc", dir.path().join("test.db").display());
+        let read = connect_read_pool(&db_url, 4, 2000).await.unwrap();
+        (write, read)
+    }
+
+    #[tokio::test]
+    async fn read_pool_rejects_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_write, read) = open_pools(&dir).await;
+        let res = sqlx::query("INSERT INTO scans (id, status, root_paths, options) VALUES ('x', 'done', '[]', '{}')")
+            .execute(&read)
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn list_stays_responsive_during_bulk_inserts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (write, read) = open_pools(&dir).await;
+        let state = AppState::new(write.clone(), AppConfig::default()).with_read_pool(read);
+
+        let id = insert_scan(&write, "running", &["/data"], "default").await;
+
+        let writer_pool = write.clone();
+        let writer = tokio::spawn(async move {
+            for batch in 0..50 {
+                let mut tx = writer_pool.begin().await.unwrap();
+                for i in 0..500 {
+                    sqlx::query(
+                        r#"INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count)
+                           VALUES (?1, ?2, '/data', 1, 1, 1, 1, 0, 0)"#,
+                    )
+                    .bind(id.to_string())
+                    .bind(format!("/data/d{}_{}", batch, i))
+                    .execute(&mut *tx)
+                    .await
+                    .unwrap();
+                }
+                tx.commit().await.unwrap();
+            }
+        });
+
+        let mut worst = Duration::ZERO;
+        while !writer.is_finished() {
+            let started = Instant::now();
+            let q = ListQuery { path: Some("/data".into()), limit: Some(100), ..Default::default() };
+            let ns = Namespace::default();
+            let res = get_list(State(state.clone()), ns, Path(id), Query(q), ResponseFormat::Json).await;
+            assert!(res.is_ok(), "list failed while writer was active");
+            worst = worst.max(started.elapsed());
+        }
+        writer.await.unwrap();
+
+        assert!(worst < Duration::from_secs(2), "slowest list took {:?}", worst);
+    }
+
+    #[tokio::test]
+    async fn duplicate_paths_are_merged_before_the_unique_index_is_added() {
+        let dir = tempfile::tempdir().unwrap();
+        let (write, _read) = open_pools(&dir).await;
+        let scan = insert_scan(&write, "done", &[], "default").await;
+        let id = scan.to_string();
+        // Databases from before the unique index could hold the same path twice
+        sqlx::query("DROP INDEX ux_nodes_scan_path").execute(&write).await.unwrap();
+        sqlx::query("DROP INDEX ux_files_scan_path").execute(&write).await.unwrap();
+        for (path, size) in [("/data", 100), ("/data", 300), ("/data", 200), ("/data/a", 50)] {
+            insert_node(&write, scan, NodeRow { path, size, ..Default::default() }).await;
+            insert_file(&write, scan, FileRow { path: &format!("{}/f", path), size, ..Default::default() })
+                .await;
+        }
+
+        init_db(&write).await.unwrap();
+        for table in ["nodes", "files"] {
+            let rows: Vec<(String, i64)> =
+                sqlx::query_as(&format!("SELECT path, allocated_size FROM {} WHERE scan_id=?1 ORDER BY allocated_size DESC", table))
+                    .bind(&id)
+                    .fetch_all(&write)
+                    .await
+                    .unwrap();
+            let sizes: Vec<i64> = rows.iter().map(|(_, size)| *size).collect();
+            assert_eq!(sizes, vec![300, 50], "{}", table);
+        }
+        let dup = sqlx::query(
+            r#"INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count)
+               VALUES (?1, '/data', NULL, 0, 1, 1, 1, 0, 0)"#,
+        )
+        .bind(&id)
+        .execute(&write)
+        .await;
+        assert!(dup.is_err());
+    }
+
+    #[tokio::test]
+    async fn older_databases_get_the_size_ordered_child_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (write, _read) = open_pools(&dir).await;
+        let index_names = || async {
+            sqlx::query_scalar::<_, String>(
+                "SELECT name FROM sqlite_master WHERE type='index' AND name LIKE 'idx_%_scan_parent%' \
+                 ORDER BY name",
+            )
+            .fetch_all(&write)
+            .await
+            .unwrap()
+        };
+        assert_eq!(index_names().await, ["idx_files_scan_parent_alloc", "idx_nodes_scan_parent_alloc"]);
+
+        // A database of schema version 11 only has the plain parent indexes
+        for table in ["nodes", "files"] {
+            let drop = format!("DROP INDEX idx_{}_scan_parent_alloc", table);
+            sqlx::query(&drop).execute(&write).await.unwrap();
+            sqlx::query(&format!("CREATE INDEX idx_{0}_scan_parent ON {0}(scan_id, parent_path)", table))
+                .execute(&write)
+                .await
+                .unwrap();
+        }
+        sqlx::query("PRAGMA user_version=11").execute(&write).await.unwrap();
+        init_db(&write).await.unwrap();
+        assert_eq!(index_names().await, ["idx_files_scan_parent_alloc", "idx_nodes_scan_parent_alloc"]);
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&write).await.unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,372 @@
+//! Backend discovery file for local tools.
+//!
+//! When the server has bound its listener it writes a small JSON file to a
+//! well-known per-user location so that editor extensions, scripts and the
+//! desktop shell can find the (randomly chosen) port. The file is refreshed
+//! periodically so staleness can be detected and removed on graceful shutdown.
+//!
+//! Location (first match wins):
+//!
+//! - `SPEICHERWALD_DISCOVERY_FILE`
+//! - Windows: `%LOCALAPPDATA%\SpeicherWald\backend.json`
+//! - Unix: `$XDG_DATA_HOME/speicherwald/backend.json`, falling back to
+//!   `~/.local/share/speicherwald/backend.json`
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// File name of the discovery file inside the data directory.
+pub const DISCOVERY_FILE_NAME: &str = "backend.json";
+
+/// Contents of the discovery file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiscoveryInfo {
+    /// Base URL of the running backend, e.g. `http://127.0.0.1:49152`.
+    pub url: String,
+    /// The TCP port the backend listens on.
+    pub port: u16,
+    /// Process ID of the backend.
+    pub pid: u32,
+    /// When the backend started (RFC 3339).
+    pub started_at: String,
+    /// When the file was last refreshed (RFC 3339).
+    pub updated_at: String,
+    /// The backend version.
+    pub version: String,
+    /// How to authenticate: `"none"` or `"bearer"` (a token from `[auth]` or `SPEICHERWALD_AUTH_TOKEN`).
+    pub auth: String,
+}
+
+impl DiscoveryInfo {
+    /// Builds the discovery info for the current process.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address the listener is bound to. Unspecified addresses
+    ///   (`0.0.0.0`, `::`) are advertised as loopback.
+    /// * `auth_required` - Whether requests need a bearer token.
+    /// * `tls` - Whether the listener serves HTTPS.
+    pub fn for_current_process(addr: std::net::SocketAddr, auth_required: bool, tls: bool) -> Self {
+        let ip = if addr.ip().is_unspecified() {
+            match addr {
+                std::net::SocketAddr::V4(_) => std::net::IpAddr::from([127, 0, 0, 1]),
+                std::net::SocketAddr::V6(_) => std::net::IpAddr::from(std::net::Ipv6Addr::LOCALHOST),
+            }
+        } else {
+            addr.ip()
+        };
+        let scheme = if tls { "https" } else { "http" };
+        let url = format!("{}://{}", scheme, std::net::SocketAddr::new(ip, addr.port()));
+        let auth = if auth_required { "bearer" } else { "none" };
+        let now = timestamp(Utc::now());
+        Self {
+            url,
+            port: addr.port(),
+            pid: std::process::id(),
+            started_at: now.clone(),
+            updated_at: now,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            auth: auth.to_string(),
+        }
+    }
+
+    /// Returns `true` if the file no longer describes a live backend.
+    ///
+    /// A file is stale if its process is gone or it has not been refreshed
+    /// within `max_age`.
+    pub fn is_stale(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        if !pid_alive(self.pid) {
+            return true;
+        }
+        match DateTime::parse_from_rfc3339(&self.updated_at) {
+            Ok(updated) => {
+                let age = now.signed_duration_since(updated.with_timezone(&Utc));
+                age.to_std().map(|a| a > max_age).unwrap_or(false)
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// How often the running backend rewrites the discovery file.
+///
+/// Configurable via `SPEICHERWALD_DISCOVERY_REFRESH_SECS` (default 10, 2–300).
+pub fn refresh_interval() -> Duration {
+    let secs = std::env::var("SPEICHERWALD_DISCOVERY_REFRESH_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(10)
+        .clamp(2, 300);
+    Duration::from_secs(secs)
+}
+
+/// Age after which a discovery file is considered stale (three missed refreshes).
+pub fn stale_after() -> Duration {
+    refresh_interval() * 3
+}
+
+fn timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Returns the well-known discovery file location for the current user.
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - `None` if no per-user directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    if let Ok(p) = std::env::var("SPEICHERWALD_DISCOVERY_FILE") {
+        if !p.trim().is_empty() {
+            return Some(PathBuf::from(p));
+        }
+    }
+    #[cfg(windows)]
+    {
+        std::env::var("LOCALAPPDATA")
+            .ok()
+            .map(|d| Path::new(&d).join("SpeicherWald").join(DISCOVERY_FILE_NAME))
+    }
+    #[cfg(not(windows))]
+    {
+        let base = std::env::var("XDG_DATA_HOME")
+            .ok()
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var("HOME").ok().map(|h| Path::new(&h).join(".local").join("share")))?;
+        Some(base.join("speicherwald").join(DISCOVERY_FILE_NAME))
+    }
+}
+
+/// Reads and parses a discovery file.
+///
+/// # Returns
+///
+/// * `io::Result<DiscoveryInfo>` - An `InvalidData` error if the file is not valid JSON.
+pub fn read(path: &Path) -> io::Result<DiscoveryInfo> {
+    let data = std::fs::read(path)?;
+    serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes the file atomically (temp file + rename) so readers never see a
+/// partially written file.
+fn write_atomic(path: &Path, info: &DiscoveryInfo) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let data = serde_json::to_vec_pretty(info).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    std::fs::write(&tmp, data)?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// The discovery file owned by this process.
+#[derive(Debug)]
+pub struct DiscoveryFile {
+    path: PathBuf,
+    info: DiscoveryInfo,
+}
+
+impl DiscoveryFile {
+    /// Writes the discovery file, replacing a stale one left by a crashed backend.
+    ///
+    /// A file that still belongs to another live backend is overwritten as
+    /// well (the newest backend wins), but a warning is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Where to write the file.
+    /// * `info` - The contents to write.
+    /// * `max_age` - Age after which an existing file is considered stale.
+    pub fn create(path: PathBuf, info: DiscoveryInfo, max_age: Duration) -> io::Result<Self> {
+        if let Ok(existing) = read(&path) {
+            if existing.pid != info.pid && !existing.is_stale(Utc::now(), max_age) {
+                tracing::warn!(
+                    "Discovery file {} belongs to another running backend (pid {}, {}); replacing it",
+                    path.display(),
+                    existing.pid,
+                    existing.url
+                );
+            }
+        }
+        write_atomic(&path, &info)?;
+        Ok(Self { path, info })
+    }
+
+    /// Returns the location of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Updates `updated_at` and rewrites the file.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.info.updated_at = timestamp(Utc::now());
+        write_atomic(&self.path, &self.info)
+    }
+
+    /// Deletes the file if it still belongs to this process.
+    pub fn remove(self) -> io::Result<()> {
+        match read(&self.path) {
+            Ok(existing) if existing.pid != self.info.pid => Ok(()),
+            Ok(_) | Err(_) => match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// Checks whether a process with the given PID is running.
+///
+/// Processes owned by other users count as running.
+pub fn pid_alive(pid: u32) -> bool {
+    if pid == 0 {
+        return false;
+    }
+    if pid == std::process::id() {
+        return true;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Path::new("/proc").join(pid.to_string()).exists()
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        // `kill -0` only probes; EPERM means the process exists but belongs to someone else
+        match std::process::Command::new("kill").arg("-0").arg(pid.to_string()).output() {
+            Ok(out) => out.status.success() || String::from_utf8_lossy(&out.stderr).contains("not permitted"),
+            Err(_) => true,
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        match std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+        {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).contains(&format!("\"{}\"", pid)),
+            // If we cannot tell, don't declare a possibly live backend stale
+            Err(_) => true,
+        }
+    }
+}
+
+/// Implements `speicherwald discover`: prints the discovery file as JSON.
+///
+/// The printed object carries an extra `stale` flag and `path`. The exit code
+/// is 0 for a live backend, 1 if the file is stale and 2 if there is none.
+pub fn print_discover() -> i32 {
+    let max_age = stale_after();
+    let Some(path) = default_path() else {
+        eprintln!("no per-user data directory found");
+        return 2;
+    };
+    match read(&path) {
+        Ok(info) => {
+            let stale = info.is_stale(Utc::now(), max_age);
+            let mut out = serde_json::to_value(&info).unwrap_or_default();
+            if let Some(obj) = out.as_object_mut() {
+                obj.insert("path".into(), path.display().to_string().into());
+                obj.insert("stale".into(), stale.into());
+            }
+            println!("{}", serde_json::to_string_pretty(&out).unwrap_or_default());
+            if stale {
+                1
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            eprintln!("no backend discovery file at {}: {}", path.display(), e);
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Above any real pid_max on Linux/macOS and never handed out on Windows (PIDs are multiples of 4)
+    const FAKE_PID: u32 = 999_999_997;
+
+    fn sample() -> DiscoveryInfo {
+        DiscoveryInfo::for_current_process("0.0.0.0:49152".parse().unwrap(), false, false)
+    }
+
+    #[test]
+    fn info_advertises_loopback_for_unspecified_addr() {
+        let info = sample();
+        assert_eq!(info.url, "http://127.0.0.1:49152");
+        assert_eq!(info.port, 49152);
+        assert_eq!(info.pid, std::process::id());
+        let tls = DiscoveryInfo::for_current_process("[::]:8443".parse().unwrap(), false, true);
+        assert_eq!(tls.url, "https://[::1]:8443");
+    }
+
+    #[test]
+    fn create_refresh_remove_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(DISCOVERY_FILE_NAME);
+        let mut info = sample();
+        info.updated_at = "2020-01-01T00:00:00Z".into();
+
+        let mut file = DiscoveryFile::create(path.clone(), info.clone(), Duration::from_secs(60)).unwrap();
+        assert_eq!(read(&path).unwrap(), info);
+
+        file.refresh().unwrap();
+        let refreshed = read(&path).unwrap();
+        assert_eq!(refreshed.started_at, info.started_at);
+        assert_ne!(refreshed.updated_at, info.updated_at);
+        assert!(!refreshed.is_stale(Utc::now(), Duration::from_secs(60)));
+
+        file.remove().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn remove_keeps_file_taken_over_by_another_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DISCOVERY_FILE_NAME);
+        let file = DiscoveryFile::create(path.clone(), sample(), Duration::from_secs(60)).unwrap();
+
+        let mut other = sample();
+        other.pid = FAKE_PID;
+        write_atomic(&path, &other).unwrap();
+
+        file.remove().unwrap();
+        assert_eq!(read(&path).unwrap().pid, FAKE_PID);
+    }
+
+    #[test]
+    fn dead_pid_is_stale() {
+        assert!(!pid_alive(FAKE_PID));
+        assert!(!pid_alive(0));
+        let mut info = sample();
+        info.pid = FAKE_PID;
+        assert!(info.is_stale(Utc::now(), Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn old_refresh_is_stale() {
+        let mut info = sample();
+        let now = Utc::now();
+        info.updated_at = timestamp(now - chrono::Duration::seconds(120));
+        assert!(info.is_stale(now, Duration::from_secs(60)));
+        assert!(!info.is_stale(now, Duration::from_secs(300)));
+        info.updated_at = "garbage".into();
+        assert!(info.is_stale(now, Duration::from_secs(300)));
+    }
+}
@@ -0,0 +1,265 @@
+//! Free space history of the drives.
+//!
+//! A background task (spawned in `main.rs` when `[drive_history]` is enabled)
+//! enumerates the drives every `interval_secs` with the same code as
+//! `GET /drives` and appends one sample per drive to the `drive_history` table.
+//! `GET /drives/history` reads the samples back, downsampled to a fixed number
+//! of points.
+//!
+//! Samples older than `retention_days` are deleted with every run. A drive that
+//! no longer shows up keeps its history for `purge_missing_hours`, so a USB disk
+//! unplugged for the night does not lose it, and is purged after that. While
+//! the enumeration fails, or every drive reports a size of zero, the sampler
+//! records nothing and waits exponentially longer between attempts.
+
+use std::time::Duration;
+
+use sqlx::{Row, SqlitePool};
+
+use crate::{
+    config::DriveHistoryConfig,
+    types::{DriveHistorySample, DriveInfo},
+};
+
+/// The maximum number of points `GET /drives/history` returns.
+pub const MAX_POINTS: i64 = 300;
+
+/// Failed attempts beyond this count do not lengthen the wait any further.
+const MAX_BACKOFF_EXPONENT: u32 = 4;
+
+/// Stores one sample per drive and applies the retention limits.
+///
+/// Drives without a size, i.e. whose space could not be read, are skipped but
+/// still count as present.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `drives` - The drives as returned by the enumeration.
+/// * `cfg` - The history configuration.
+/// * `now` - The current time in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// * `anyhow::Result<u64>` - The number of samples stored.
+pub async fn record(
+    pool: &SqlitePool,
+    drives: &[DriveInfo],
+    cfg: &DriveHistoryConfig,
+    now: i64,
+) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut stored = 0;
+    for drive in drives.iter().filter(|d| d.total_bytes > 0) {
+        sqlx::query(
+            "INSERT INTO drive_history (path, sampled_at, total_bytes, free_bytes) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(&drive.path)
+        .bind(now)
+        .bind(drive.total_bytes as i64)
+        .bind(drive.free_bytes as i64)
+        .execute(&mut *tx)
+        .await?;
+        stored += 1;
+    }
+
+    let expired = now - (cfg.retention_days as i64).saturating_mul(86_400);
+    sqlx::query("DELETE FROM drive_history WHERE sampled_at < ?1").bind(expired).execute(&mut *tx).await?;
+
+    let present = serde_json::to_string(&drives.iter().map(|d| d.path.as_str()).collect::<Vec<_>>())?;
+    let missing_since = now - (cfg.purge_missing_hours as i64).saturating_mul(3_600);
+    let purged = sqlx::query(
+        r#"DELETE FROM drive_history WHERE path IN (
+               SELECT path FROM drive_history
+               WHERE path NOT IN (SELECT value FROM json_each(?1))
+               GROUP BY path HAVING MAX(sampled_at) < ?2
+           )"#,
+    )
+    .bind(present)
+    .bind(missing_since)
+    .execute(&mut *tx)
+    .await?;
+    if purged.rows_affected() > 0 {
+        tracing::info!(samples = purged.rows_affected(), "Purged the history of drives that disappeared");
+    }
+
+    tx.commit().await?;
+    Ok(stored)
+}
+
+/// Reads the history of a drive, downsampled to at most `max_points` points.
+///
+/// The covered time range is split into `max_points` equally long buckets;
+/// each bucket with samples becomes one point with the newest timestamp, the
+/// largest total size and the average free space of its samples.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `path` - The drive path, compared case-insensitively.
+/// * `since` - The start of the range in seconds since the Unix epoch.
+/// * `now` - The end of the range in seconds since the Unix epoch.
+/// * `max_points` - The maximum number of points to return.
+///
+/// # Returns
+///
+/// * `anyhow::Result<Vec<DriveHistorySample>>` - The points, oldest first.
+pub async fn history(
+    pool: &SqlitePool,
+    path: &str,
+    since: i64,
+    now: i64,
+    max_points: i64,
+) -> anyhow::Result<Vec<DriveHistorySample>> {
+    let bucket_secs = (now - since).max(0) / max_points.max(1) + 1;
+    let rows = sqlx::query(
+        r#"SELECT MAX(sampled_at) AS sampled_at, MAX(total_bytes) AS total_bytes,
+                  CAST(AVG(free_bytes) AS INTEGER) AS free_bytes
+           FROM drive_history
+           WHERE path = ?1 COLLATE NOCASE AND sampled_at >= ?2 AND sampled_at <= ?3
+           GROUP BY (sampled_at - ?2) / ?4
+           ORDER BY sampled_at ASC"#,
+    )
+    .bind(path)
+    .bind(since)
+    .bind(now)
+    .bind(bucket_secs)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| DriveHistorySample {
+            sampled_at: r.get("sampled_at"),
+            total_bytes: r.get::<i64, _>("total_bytes").max(0) as u64,
+            free_bytes: r.get::<i64, _>("free_bytes").max(0) as u64,
+        })
+        .collect())
+}
+
+/// Returns how long to wait before the next attempt after `failures` failed ones.
+fn next_delay(interval: Duration, failures: u32) -> Duration {
+    interval * 2u32.pow(failures.min(MAX_BACKOFF_EXPONENT))
+}
+
+/// Samples the drives until the process exits.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `cfg` - The history configuration.
+pub async fn run(pool: SqlitePool, cfg: DriveHistoryConfig) {
+    let interval = Duration::from_secs(cfg.interval_secs);
+    let mut failures = 0u32;
+    loop {
+        match crate::routes::drives::enumerate_drives(false).await {
+            Ok(drives) if drives.iter().any(|d| d.total_bytes > 0) => {
+                if failures > 0 {
+                    tracing::info!(failures, "Drive enumeration recovered, sampling resumes");
+                }
+                failures = 0;
+                if let Err(e) = record(&pool, &drives, &cfg, chrono::Utc::now().timestamp()).await {
+                    tracing::error!("Storing drive history failed: {}", e);
+                }
+            }
+            result => {
+                failures = failures.saturating_add(1);
+                let delay = next_delay(interval, failures);
+                match result {
+                    Err(e) => tracing::warn!(failures, ?delay, "Drive enumeration failed: {}", e),
+                    Ok(_) => tracing::warn!(failures, ?delay, "No drive reported its size"),
+                }
+            }
+        }
+        tokio::time::sleep(next_delay(interval, failures)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_db_in;
+
+    async fn pool(dir: &tempfile::TempDir) -> SqlitePool {
+        let pool = test_db_in(dir.path()).await;
+        pool
+    }
+
+    fn drive(path: &str, total: u64, free: u64) -> DriveInfo {
+        DriveInfo {
+            path: path.into(),
+            drive_type: "fixed".into(),
+            total_bytes: total,
+            free_bytes: free,
+            label: None,
+            filesystem: None,
+            serial_number: None,
+            bitlocker: None,
+        }
+    }
+
+    async fn paths(pool: &SqlitePool) -> Vec<(String, i64)> {
+        sqlx::query("SELECT path, COUNT(*) AS n FROM drive_history GROUP BY path ORDER BY path")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| (r.get("path"), r.get("n")))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn record_applies_retention_and_purges_vanished_drives() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = pool(&dir).await;
+        let cfg = DriveHistoryConfig { retention_days: 2, purge_missing_hours: 12, ..Default::default() };
+        let hour = 3_600;
+        let start = 1_700_000_000;
+
+        let both = [drive("C:\\", 100, 40), drive("E:\\", 50, 10)];
+        assert_eq!(record(&pool, &both, &cfg, start).await.unwrap(), 2);
+        // E: is unplugged; its history survives the grace period
+        let only_c = [drive("C:\\", 100, 30)];
+        record(&pool, &only_c, &cfg, start + 6 * hour).await.unwrap();
+        assert_eq!(paths(&pool).await, vec![("C:\\".into(), 2), ("E:\\".into(), 1)]);
+        // A listed drive without a size is kept even though it gets no sample
+        let unreadable = [drive("C:\\", 100, 30), drive("E:\\", 0, 0)];
+        record(&pool, &unreadable, &cfg, start + 24 * hour).await.unwrap();
+        assert_eq!(paths(&pool).await, vec![("C:\\".into(), 3), ("E:\\".into(), 1)]);
+        // Gone for longer than purge_missing_hours, and the first C: sample expired
+        record(&pool, &only_c, &cfg, start + 49 * hour).await.unwrap();
+        assert_eq!(paths(&pool).await, vec![("C:\\".into(), 3)]);
+    }
+
+    #[tokio::test]
+    async fn history_is_downsampled_and_case_insensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = pool(&dir).await;
+        let cfg = DriveHistoryConfig::default();
+        let start = 1_700_000_000;
+        // One week of samples every 15 minutes
+        let samples = 7 * 24 * 4;
+        for i in 0..samples {
+            record(&pool, &[drive("C:\\", 1000, 500 - i as u64 / 2)], &cfg, start + i * 900).await.unwrap();
+        }
+        let now = start + (samples - 1) * 900;
+
+        let points = history(&pool, "c:\\", now - 7 * 24 * 3_600, now, MAX_POINTS).await.unwrap();
+        assert!(points.len() <= MAX_POINTS as usize && points.len() >= 200, "{}", points.len());
+        assert!(points.windows(2).all(|w| w[0].sampled_at < w[1].sampled_at));
+        assert_eq!(points.last().unwrap().sampled_at, now);
+        assert!(points.iter().all(|p| p.total_bytes == 1000));
+
+        let all = history(&pool, "C:\\", now - 3_600, now, MAX_POINTS).await.unwrap();
+        assert_eq!(all.len(), 5);
+        assert!(history(&pool, "D:\\", start, now, MAX_POINTS).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_limit() {
+        let interval = Duration::from_secs(900);
+        assert_eq!(next_delay(interval, 0), interval);
+        assert_eq!(next_delay(interval, 1), interval * 2);
+        assert_eq!(next_delay(interval, 3), interval * 8);
+        assert_eq!(next_delay(interval, 50), interval * 16);
+    }
+}
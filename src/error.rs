@@ -21,6 +21,14 @@ pub enum AppError {
     BadRequest(String),
     /// For when a requested resource is not found.
     NotFound(String),
+    /// For when a requested resource is not found but the client can recover,
+    /// e.g. from the nearest existing ancestor of a path.
+    NotFoundWithDetails {
+        /// A message describing what was not found.
+        message: String,
+        /// What the client needs to recover, returned as `error.details`.
+        details: serde_json::Value,
+    },
     /// For when a request conflicts with the current state of the server.
     Conflict(String),
     /// For when a service is temporarily unavailable.
@@ -29,10 +37,9 @@ pub enum AppError {
     Database(String),
     /// For when user input is invalid.
     InvalidInput(String),
-    /// For errors that occur during the scanning process.
-    Scanner(String),
-    /// For when a request is not authorized.
-    Unauthorized(String),
+    /// For when a request is understood but must not be carried out, e.g. a
+    /// path operation on the roots of a read-only scan.
+    Forbidden(String),
     /// For when a client has sent too many requests in a given amount of time.
     RateLimited {
         /// The number of seconds to wait before retrying the request.
@@ -55,12 +62,12 @@ impl fmt::Display for AppError {
             AppError::Internal(e) => write!(f, "Internal error: {}", e),
             AppError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AppError::NotFoundWithDetails { message, .. } => write!(f, "Not found: {}", message),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::ServiceUnavailable(msg) => write!(f, "Service unavailable: {}", msg),
             AppError::Database(msg) => write!(f, "Database error: {}", msg),
             AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
-            AppError::Scanner(msg) => write!(f, "Scanner error: {}", msg),
-            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::RateLimited { retry_after_seconds } => {
                 write!(f, "Rate limited. Retry after {} seconds", retry_after_seconds)
             }
@@ -97,6 +104,9 @@ impl IntoResponse for AppError {
             }
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg, None),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg, None),
+            AppError::NotFoundWithDetails { message, details } => {
+                (StatusCode::NOT_FOUND, "NOT_FOUND", message, Some(details))
+            }
             AppError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg, None),
             AppError::ServiceUnavailable(msg) => {
                 (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE", msg, None)
@@ -111,11 +121,7 @@ impl IntoResponse for AppError {
                 )
             }
             AppError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, "INVALID_INPUT", msg, None),
-            AppError::Scanner(msg) => {
-                tracing::warn!("Scanner error: {}", msg);
-                (StatusCode::INTERNAL_SERVER_ERROR, "SCANNER_ERROR", msg, None)
-            }
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg, None),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg, None),
             AppError::RateLimited { retry_after_seconds } => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "RATE_LIMITED",
@@ -151,6 +157,9 @@ impl IntoResponse for AppError {
         if let Some(details) = details {
             body["error"]["details"] = details;
         }
+        if let Some(request_id) = crate::middleware::request_id::current() {
+            body["request_id"] = json!(request_id);
+        }
 
         (status, Json(body)).into_response()
     }
@@ -191,101 +200,3 @@ impl From<globset::Error> for AppError {
 
 /// A type alias for `Result<T, AppError>`, used throughout the application.
 pub type AppResult<T> = Result<T, AppError>;
-
-/// An extension trait for `Option` that provides a convenient way to convert
-/// an `Option` to a `Result` with a `NotFound` error.
-pub trait OptionExt<T> {
-    /// Converts an `Option<T>` to a `Result<T, AppError>`.
-    ///
-    /// # Arguments
-    ///
-    /// * `entity` - A string describing the entity that was not found.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(T)` if the `Option` is `Some(T)`.
-    /// * `Err(AppError::NotFound)` if the `Option` is `None`.
-    fn ok_or_not_found(self, entity: &str) -> AppResult<T>;
-}
-
-impl<T> OptionExt<T> for Option<T> {
-    fn ok_or_not_found(self, entity: &str) -> AppResult<T> {
-        self.ok_or_else(|| AppError::NotFound(format!("{} not found", entity)))
-    }
-}
-
-/// A module containing helper functions for request validation.
-pub mod validation {
-    use super::*;
-    use std::path::Path;
-
-    /// Validates a file path.
-    ///
-    /// This function checks if a path is empty or contains null characters.
-    ///
-    /// # Arguments
-    ///
-    /// * `path` - The path to validate.
-    ///
-    /// # Returns
-    ///
-    /// * `AppResult<()>` - `Ok(())` if the path is valid, or an `AppError` if it's not.
-    pub fn validate_path(path: &str) -> AppResult<()> {
-        if path.is_empty() {
-            return Err(AppError::ValidationError {
-                field: "path".to_string(),
-                message: "Path cannot be empty".to_string(),
-            });
-        }
-
-        if path.contains('\0') {
-            return Err(AppError::ValidationError {
-                field: "path".to_string(),
-                message: "Path contains null characters".to_string(),
-            });
-        }
-
-        Ok(())
-    }
-
-    /// Validates that a number is positive.
-    ///
-    /// # Arguments
-    ///
-    /// * `value` - The number to validate.
-    /// * `field` - The name of the field being validated.
-    ///
-    /// # Returns
-    ///
-    /// * `AppResult<()>` - `Ok(())` if the number is positive, or an `AppError` if it's not.
-    pub fn validate_positive_number(value: Option<i64>, field: &str) -> AppResult<()> {
-        if let Some(v) = value {
-            if v <= 0 {
-                return Err(AppError::ValidationError {
-                    field: field.to_string(),
-                    message: format!("Value must be positive, got {}", v),
-                });
-            }
-        }
-        Ok(())
-    }
-
-    /// Validates that a list of paths exist on the filesystem.
-    ///
-    // # Arguments
-    ///
-    /// * `paths` - A slice of paths to validate.
-    ///
-    /// # Returns
-    ///
-    /// * `AppResult<()>` - `Ok(())` if all paths exist, or an `AppError` if any of them don't.
-    pub fn validate_paths_exist(paths: &[String]) -> AppResult<()> {
-        for path_str in paths {
-            let path = Path::new(path_str);
-            if !path.exists() {
-                return Err(AppError::NotFound(format!("Path does not exist: {}", path_str)));
-            }
-        }
-        Ok(())
-    }
-}
@@ -0,0 +1,819 @@
+//! Imports of scans exported by other servers.
+//!
+//! Branch offices run their own server and send their scans to a central one:
+//! the NDJSON or CSV export of `GET /scans/{id}/export?scope=all` is uploaded
+//! to an import session in one or more chunks. The rows are checked and
+//! stored in a new scan of status `importing` as they arrive, in batches like
+//! the rows of a scan. Committing the session checks that every file belongs
+//! to an uploaded directory, takes the directories without an uploaded parent
+//! as roots, computes the totals and marks the scan `done`. The scan carries
+//! the `origin` the upload named, which tells imported scans apart in
+//! `GET /scans`.
+//!
+//! Uploads are resumable. A session remembers how many bytes it accepted,
+//! including an incomplete last record, and the next chunk continues at that
+//! offset; an invalid record is not accepted, so the offset stays where it
+//! starts. Rows are stored with the scanner's upserts, so rows sent again
+//! after a server crash replace themselves instead of being counted twice.
+//! Sessions that accept nothing for [`STALE_AFTER_DAYS`] are dropped with
+//! their scan.
+
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use axum::body::Body;
+use futures::StreamExt;
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::export::{CsvColumn, FileExport, NdjsonRecord, NodeExport},
+    scanner::{category::CategoryMap, FileRecord, NodeRecord, PersistRetry},
+    types::{ImportSession, ScanOptions},
+};
+
+/// The path prefix of the import endpoints, which are exempt from the global body limit.
+pub const IMPORT_PATH_PREFIX: &str = "/import/";
+
+/// The request header naming the server an export came from.
+pub const ORIGIN_HEADER: &str = "x-speicherwald-origin";
+
+/// The longest record an upload may contain, so a body without line breaks cannot fill the memory.
+pub const MAX_RECORD_BYTES: usize = 1024 * 1024;
+
+/// Sessions that accepted no chunk for this many days are dropped with their scan.
+pub const STALE_AFTER_DAYS: u32 = 7;
+
+/// The longest origin, that of a fully qualified host name.
+const MAX_ORIGIN_LEN: usize = 253;
+
+/// The format of an uploaded export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `GET /scans/{id}/export?format=ndjson`
+    Ndjson,
+    /// `GET /scans/{id}/export?format=csv`, with sizes in bytes
+    Csv,
+}
+
+impl ImportFormat {
+    /// Parses the `format` query parameter; NDJSON if it is absent.
+    pub fn parse(raw: Option<&str>) -> AppResult<Self> {
+        match raw.unwrap_or("ndjson") {
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            other => Err(AppError::BadRequest(format!("Invalid format '{}'. Use 'ndjson' or 'csv'", other))),
+        }
+    }
+
+    /// The name of the format as stored and reported.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Validates the name of the server an export came from.
+///
+/// Origins are host names: 1 to 253 characters of ASCII letters, digits, `-`,
+/// `_` and `.`.
+///
+/// # Arguments
+///
+/// * `raw` - The origin, surrounding whitespace is ignored.
+///
+/// # Returns
+///
+/// * `AppResult<String>` - The origin, or `BadRequest` if it is invalid.
+pub fn parse_origin(raw: &str) -> AppResult<String> {
+    let origin = raw.trim();
+    let valid = !origin.is_empty()
+        && origin.len() <= MAX_ORIGIN_LEN
+        && origin.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "invalid origin (1-{} characters of A-Z, a-z, 0-9, '-', '_', '.')",
+            MAX_ORIGIN_LEN
+        )));
+    }
+    Ok(origin.to_string())
+}
+
+/// How the rows of an upload are stored; the batching is that of a scan.
+#[derive(Debug, Clone)]
+pub struct ImportSettings {
+    batch_size: usize,
+    flush_threshold: usize,
+    retry: PersistRetry,
+    categories: Option<Arc<CategoryMap>>,
+}
+
+impl ImportSettings {
+    /// Reads the batching and the file categories from the configuration.
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            batch_size: cfg.scanner.batch_size,
+            flush_threshold: cfg.scanner.flush_threshold.max(1),
+            retry: PersistRetry::from_config(&cfg.scanner),
+            categories: CategoryMap::for_scan(&cfg.categories),
+        }
+    }
+}
+
+/// The sessions a request is currently appending to or committing.
+#[derive(Default)]
+pub struct ImportUploads {
+    active: Mutex<HashSet<Uuid>>,
+}
+
+impl ImportUploads {
+    /// Marks a session busy until the returned guard is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the session.
+    ///
+    /// # Returns
+    ///
+    /// * `AppResult<UploadGuard>` - The guard, or `Conflict` if another request works on the session.
+    pub fn begin(self: &Arc<Self>, id: Uuid) -> AppResult<UploadGuard> {
+        if !self.active.lock().unwrap_or_else(|e| e.into_inner()).insert(id) {
+            return Err(AppError::Conflict("another upload to this import session is in progress".into()));
+        }
+        Ok(UploadGuard { uploads: self.clone(), id })
+    }
+}
+
+/// Keeps a session busy while it is alive.
+pub struct UploadGuard {
+    uploads: Arc<ImportUploads>,
+    id: Uuid,
+}
+
+impl Drop for UploadGuard {
+    fn drop(&mut self) {
+        self.uploads.active.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
+
+/// The header line of the CSV section being read.
+#[derive(Debug, Clone)]
+struct CsvHeader {
+    line: String,
+    separator: char,
+    columns: Vec<CsvColumn>,
+}
+
+impl CsvHeader {
+    fn parse(line: &str) -> Result<Self, String> {
+        // No label contains a separator, so the one that occurs is the separator
+        let separator = if line.contains(';') { ';' } else { ',' };
+        let mut columns = Vec::new();
+        for label in line.split(separator).map(str::trim) {
+            let column = CsvColumn::from_header(label).ok_or_else(|| {
+                if label.starts_with("Logical Size (") || label.starts_with("Allocated Size (") {
+                    format!("column '{}' is not in bytes; export with units=bytes", label)
+                } else {
+                    format!("unknown column '{}'", label)
+                }
+            })?;
+            columns.push(column);
+        }
+        for required in [CsvColumn::Type, CsvColumn::Path] {
+            if !columns.contains(&required) {
+                return Err(format!("the header has no '{}' column", required.header()));
+            }
+        }
+        Ok(Self { line: line.to_string(), separator, columns })
+    }
+}
+
+/// A decoded record.
+enum Decoded {
+    Node(NodeRecord),
+    File(FileRecord),
+}
+
+/// Turns the records of an upload into rows.
+struct Decoder {
+    format: ImportFormat,
+    /// The header of the current CSV section, `None` while the next line is a header.
+    csv: Option<CsvHeader>,
+    /// The number of records decoded so far, headers and empty lines included.
+    records: u64,
+}
+
+impl Decoder {
+    /// Decodes one record, including its line break.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Decoded>, String>` - The row, `None` for headers and empty
+    ///   lines, or what is wrong with the record.
+    fn decode(&mut self, record: &[u8], categories: Option<&CategoryMap>) -> Result<Option<Decoded>, String> {
+        let text = std::str::from_utf8(record).map_err(|_| "the record is not valid UTF-8".to_string())?;
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let row = match self.format {
+            ImportFormat::Ndjson => decode_json(text, categories)?,
+            ImportFormat::Csv => self.decode_csv(text, categories)?,
+        };
+        self.records += 1;
+        Ok(row)
+    }
+
+    fn decode_csv(
+        &mut self,
+        text: &str,
+        categories: Option<&CategoryMap>,
+    ) -> Result<Option<Decoded>, String> {
+        if text.is_empty() {
+            // An empty line ends a section; the next one starts with its own header
+            self.csv = None;
+            return Ok(None);
+        }
+        let Some(header) = &self.csv else {
+            self.csv = Some(CsvHeader::parse(text)?);
+            return Ok(None);
+        };
+        let fields = split_csv(text, header.separator)?;
+        if fields.len() != header.columns.len() {
+            return Err(format!("expected {} fields, found {}", header.columns.len(), fields.len()));
+        }
+        let field = |column: CsvColumn| {
+            header
+                .columns
+                .iter()
+                .position(|c| *c == column)
+                .map(|i| fields[i].as_str())
+                .filter(|v| !v.is_empty())
+        };
+        let number = |columns: &[CsvColumn]| -> Result<Option<i64>, String> {
+            match columns.iter().find_map(|c| field(*c)) {
+                Some(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("{}: invalid number '{}'", columns[0].header(), value)),
+                None => Ok(None),
+            }
+        };
+        let required = |columns: &[CsvColumn]| -> Result<i64, String> {
+            number(columns)?.ok_or_else(|| format!("no value for '{}'", columns[0].header()))
+        };
+        let path = field(CsvColumn::Path).unwrap_or_default().to_string();
+        let parent_path = field(CsvColumn::ParentPath).map(str::to_string);
+        let logical_size = required(&[CsvColumn::LogicalBytes, CsvColumn::LogicalSize])?;
+        let allocated_size = required(&[CsvColumn::AllocatedBytes, CsvColumn::AllocatedSize])?;
+        let mtime = number(&[CsvColumn::Mtime])?;
+        let path_raw = field(CsvColumn::PathRaw).map(str::to_string);
+        match field(CsvColumn::Type) {
+            Some("Dir") => node_row(NodeExport {
+                path,
+                parent_path,
+                depth: required(&[CsvColumn::Depth])?,
+                is_dir: true,
+                logical_size,
+                allocated_size,
+                file_count: required(&[CsvColumn::FileCount])?,
+                dir_count: required(&[CsvColumn::DirCount])?,
+                mtime,
+                path_raw,
+            }),
+            Some("File") => {
+                let file = FileExport { path, parent_path, logical_size, allocated_size, mtime, path_raw };
+                file_row(file, categories)
+            }
+            other => Err(format!("unknown type '{}'; expected 'Dir' or 'File'", other.unwrap_or_default())),
+        }
+        .map(Some)
+    }
+}
+
+/// Decodes an NDJSON line as written by the export; unknown fields of newer servers are ignored.
+fn decode_json(text: &str, categories: Option<&CategoryMap>) -> Result<Option<Decoded>, String> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut de = serde_json::Deserializer::from_str(text);
+    let record: NdjsonRecord = serde_path_to_error::deserialize(&mut de).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.into_inner();
+        if inner.is_data() && path != "." {
+            format!("{}: {}", path, inner)
+        } else {
+            inner.to_string()
+        }
+    })?;
+    de.end().map_err(|e| e.to_string())?;
+    match record {
+        NdjsonRecord::Node(node) => node_row(node),
+        NdjsonRecord::File(file) => file_row(file, categories),
+    }
+    .map(Some)
+}
+
+/// Splits a CSV record into its fields, undoing the quoting of [`crate::routes::export::push_csv_field`].
+fn split_csv(record: &str, separator: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let (mut quoted, mut at_start) = (false, true);
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c != '"' {
+                field.push(c);
+            } else if chars.next_if_eq(&'"').is_some() {
+                field.push('"');
+            } else {
+                quoted = false;
+            }
+        } else if c == '"' && at_start {
+            quoted = true;
+        } else if c == separator {
+            fields.push(std::mem::take(&mut field));
+            at_start = true;
+            continue;
+        } else {
+            field.push(c);
+        }
+        at_start = false;
+    }
+    if quoted {
+        return Err("a quoted field is not closed".into());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Checks a directory record and converts it into a row.
+fn node_row(node: NodeExport) -> Result<Decoded, String> {
+    check_path(&node.path, node.parent_path.as_deref())?;
+    if !node.is_dir {
+        return Err("is_dir: node records are directories".into());
+    }
+    Ok(Decoded::Node(NodeRecord {
+        depth: u32::try_from(node.depth).map_err(|_| format!("depth: {} is out of range", node.depth))?,
+        is_dir: true,
+        logical_size: non_negative("logical_size", node.logical_size)?,
+        allocated_size: non_negative("allocated_size", node.allocated_size)?,
+        file_count: non_negative("file_count", node.file_count)?,
+        dir_count: non_negative("dir_count", node.dir_count)?,
+        mtime: node.mtime,
+        atime: None,
+        // Without a fingerprint a later rescan never takes over the imported rows
+        fingerprint: None,
+        empty_file_count: 0,
+        path_raw: decode_raw_path(node.path_raw)?,
+        path: node.path,
+        parent_path: node.parent_path,
+    }))
+}
+
+/// Checks a file record and converts it into a row.
+fn file_row(file: FileExport, categories: Option<&CategoryMap>) -> Result<Decoded, String> {
+    check_path(&file.path, file.parent_path.as_deref())?;
+    Ok(Decoded::File(FileRecord::imported(
+        file.path.clone(),
+        file.parent_path,
+        non_negative("logical_size", file.logical_size)?,
+        non_negative("allocated_size", file.allocated_size)?,
+        file.mtime,
+        categories.map(|c| c.classify(Path::new(&file.path))),
+        decode_raw_path(file.path_raw)?,
+    )))
+}
+
+/// Checks that a path is set and lies below its parent.
+fn check_path(path: &str, parent_path: Option<&str>) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("path: must not be empty".into());
+    }
+    if let Some(parent) = parent_path {
+        if path.len() <= parent.len() || !path.starts_with(parent) {
+            return Err(format!("path: '{}' is not below its parent_path '{}'", path, parent));
+        }
+    }
+    Ok(())
+}
+
+fn non_negative(field: &str, value: i64) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| format!("{}: {} is negative", field, value))
+}
+
+fn decode_raw_path(raw: Option<String>) -> Result<Option<Vec<u8>>, String> {
+    use base64::Engine;
+    raw.map(|raw| base64::engine::general_purpose::STANDARD.decode(raw))
+        .transpose()
+        .map_err(|e| format!("path_raw: invalid base64: {}", e))
+}
+
+/// The length of the first complete record in `buf`, including its line break.
+///
+/// CSV records end at the first line break outside quotes, since paths may
+/// contain line breaks.
+fn record_len(buf: &[u8], format: ImportFormat) -> Option<usize> {
+    match format {
+        ImportFormat::Ndjson => buf.iter().position(|&b| b == b'\n').map(|i| i + 1),
+        ImportFormat::Csv => {
+            let mut quoted = false;
+            for (i, &b) in buf.iter().enumerate() {
+                match b {
+                    b'"' => quoted = !quoted,
+                    b'\n' if !quoted => return Some(i + 1),
+                    _ => {}
+                }
+            }
+            None
+        }
+    }
+}
+
+/// An import session as stored in `import_sessions`.
+pub struct Session {
+    /// The ID of the session.
+    pub id: Uuid,
+    /// The ID of the scan the rows are stored in.
+    pub scan_id: Uuid,
+    origin: String,
+    /// The number of bytes accepted, `pending` included.
+    received: u64,
+    /// The start of a record whose end has not been uploaded yet.
+    pending: Vec<u8>,
+    decoder: Decoder,
+    created_at: String,
+    updated_at: String,
+}
+
+impl Session {
+    /// The number of bytes accepted so far, where the next chunk continues.
+    pub fn offset(&self) -> u64 {
+        self.received
+    }
+
+    /// Converts the session into its API representation.
+    pub fn to_dto(&self) -> ImportSession {
+        ImportSession {
+            id: self.id,
+            scan_id: self.scan_id,
+            format: self.decoder.format.as_str().to_string(),
+            origin: self.origin.clone(),
+            offset: self.received,
+            records: self.decoder.records,
+            created_at: self.created_at.clone(),
+            updated_at: self.updated_at.clone(),
+        }
+    }
+
+    /// Decodes the complete records at the start of `pending` and keeps the incomplete rest.
+    ///
+    /// On an invalid record everything from it on is dropped, so the session
+    /// continues at the offset where the record starts.
+    fn take_records(
+        &mut self,
+        settings: &ImportSettings,
+        nodes: &mut Vec<NodeRecord>,
+        files: &mut Vec<FileRecord>,
+    ) -> AppResult<()> {
+        let mut start = 0;
+        let mut result = Ok(());
+        while let Some(len) = record_len(&self.pending[start..], self.decoder.format) {
+            match self.decoder.decode(&self.pending[start..start + len], settings.categories.as_deref()) {
+                Ok(Some(Decoded::Node(node))) => nodes.push(node),
+                Ok(Some(Decoded::File(file))) => files.push(file),
+                Ok(None) => {}
+                Err(message) => {
+                    self.received -= (self.pending.len() - start) as u64;
+                    self.pending.truncate(start);
+                    result = Err(self.rejected(&message));
+                    break;
+                }
+            }
+            start += len;
+        }
+        self.pending.drain(..start);
+        if result.is_ok() && self.pending.len() > MAX_RECORD_BYTES {
+            self.received -= self.pending.len() as u64;
+            self.pending.clear();
+            result = Err(self.rejected(&format!("the record is longer than {} bytes", MAX_RECORD_BYTES)));
+        }
+        result
+    }
+
+    /// The error of a record that was not accepted; the offset is where it starts.
+    fn rejected(&self, message: &str) -> AppError {
+        let offset = self.received - self.pending.len() as u64;
+        AppError::InvalidInput(format!(
+            "record {} at offset {}: {}",
+            self.decoder.records + 1,
+            offset,
+            message
+        ))
+    }
+}
+
+/// Starts an import session with a new scan of status `importing`.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `ns` - The namespace the scan is created in.
+/// * `format` - The format of the upload.
+/// * `origin` - The server the export comes from, see [`parse_origin`].
+///
+/// # Returns
+///
+/// * `AppResult<Session>` - The new session.
+pub async fn create(
+    pool: &SqlitePool,
+    ns: &Namespace,
+    format: ImportFormat,
+    origin: &str,
+) -> AppResult<Session> {
+    let (id, scan_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let options = serde_json::to_string(&ScanOptions::default()).map_err(anyhow::Error::from)?;
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO scans (id, status, root_paths, options, namespace, origin) \
+         VALUES (?1, 'importing', '[]', ?2, ?3, ?4)",
+    )
+    .bind(scan_id.to_string())
+    .bind(options)
+    .bind(ns.as_str())
+    .bind(origin)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("INSERT INTO import_sessions (id, scan_id, format) VALUES (?1, ?2, ?3)")
+        .bind(id.to_string())
+        .bind(scan_id.to_string())
+        .bind(format.as_str())
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(session_id = %id, scan_id = %scan_id, origin, "import session started");
+    load(pool, ns, id).await
+}
+
+/// Loads a session whose scan is visible from `ns`.
+///
+/// # Returns
+///
+/// * `AppResult<Session>` - The session, or `NotFound`.
+pub async fn load(pool: &SqlitePool, ns: &Namespace, id: Uuid) -> AppResult<Session> {
+    let r = sqlx::query(
+        "SELECT i.scan_id, i.format, i.received_bytes, i.pending, i.csv_header, i.record_count, \
+                i.created_at, i.updated_at, s.origin \
+         FROM import_sessions i JOIN scans s ON s.id = i.scan_id \
+         WHERE i.id=?1 AND (?2 OR s.namespace=?3)",
+    )
+    .bind(id.to_string())
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("import session not found".into()))?;
+    let corrupt = |what: &str| AppError::Database(format!("import session {} has an invalid {}", id, what));
+    let csv = match r.get::<Option<String>, _>("csv_header") {
+        Some(line) => Some(CsvHeader::parse(&line).map_err(|_| corrupt("CSV header"))?),
+        None => None,
+    };
+    Ok(Session {
+        id,
+        scan_id: Uuid::parse_str(&r.get::<String, _>("scan_id")).map_err(|_| corrupt("scan ID"))?,
+        origin: r.get::<Option<String>, _>("origin").unwrap_or_default(),
+        received: r.get::<i64, _>("received_bytes") as u64,
+        pending: r.get("pending"),
+        decoder: Decoder {
+            format: ImportFormat::parse(Some(r.get("format"))).map_err(|_| corrupt("format"))?,
+            csv,
+            records: r.get::<i64, _>("record_count") as u64,
+        },
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    })
+}
+
+/// Appends a chunk of the export to a session.
+///
+/// The rows are stored in batches while the body streams in. When the body
+/// breaks off, what arrived stays accepted; when a record is invalid, the
+/// records before it stay accepted. Either way the session is saved and the
+/// upload continues at [`Session::offset`].
+///
+/// # Arguments
+///
+/// * `pool` - The database pool to write to.
+/// * `session` - The session, updated in place.
+/// * `body` - The chunk.
+/// * `settings` - How the rows are stored.
+///
+/// # Returns
+///
+/// * `AppResult<()>` - `InvalidInput` naming the first invalid record, or
+///   `BadRequest` if the body broke off.
+pub async fn append(
+    pool: &SqlitePool,
+    session: &mut Session,
+    body: Body,
+    settings: &ImportSettings,
+) -> AppResult<()> {
+    let (mut nodes, mut files) = (Vec::new(), Vec::new());
+    let mut stream = body.into_data_stream();
+    let mut outcome = Ok(());
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                outcome = Err(AppError::BadRequest(format!("failed to read upload: {}", e)));
+                break;
+            }
+        };
+        session.pending.extend_from_slice(&chunk);
+        session.received += chunk.len() as u64;
+        if let Err(e) = session.take_records(settings, &mut nodes, &mut files) {
+            outcome = Err(e);
+            break;
+        }
+        if nodes.len() + files.len() >= settings.flush_threshold {
+            write(pool, session.scan_id, &mut nodes, &mut files, settings).await?;
+        }
+    }
+    write(pool, session.scan_id, &mut nodes, &mut files, settings).await?;
+    save(pool, session).await?;
+    outcome
+}
+
+/// Stores buffered rows and clears the buffers.
+async fn write(
+    pool: &SqlitePool,
+    scan_id: Uuid,
+    nodes: &mut Vec<NodeRecord>,
+    files: &mut Vec<FileRecord>,
+    settings: &ImportSettings,
+) -> AppResult<()> {
+    let warnings =
+        crate::scanner::persist_imported(pool, scan_id, nodes, files, settings.batch_size, &settings.retry)
+            .await?;
+    if warnings > 0 {
+        sqlx::query("UPDATE scans SET warning_count=COALESCE(warning_count,0)+?2 WHERE id=?1")
+            .bind(scan_id.to_string())
+            .bind(warnings as i64)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Saves where the upload of a session continues.
+async fn save(pool: &SqlitePool, session: &Session) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE import_sessions SET received_bytes=?2, pending=?3, csv_header=?4, record_count=?5, \
+         updated_at=strftime('%Y-%m-%dT%H:%M:%SZ','now') WHERE id=?1",
+    )
+    .bind(session.id.to_string())
+    .bind(session.received as i64)
+    .bind(&session.pending)
+    .bind(session.decoder.csv.as_ref().map(|h| h.line.as_str()))
+    .bind(session.decoder.records as i64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Ends a session and turns its scan into a finished scan.
+///
+/// An incomplete last record, as an export without a final line break leaves,
+/// is read first. Every file must belong to an uploaded directory; the
+/// directories without an uploaded parent become the roots of the scan. If
+/// the checks fail the session stays open, so missing rows can still be
+/// appended.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool to write to.
+/// * `session` - The session.
+/// * `settings` - How the rows are stored.
+///
+/// # Returns
+///
+/// * `AppResult<()>` - `InvalidInput` if the upload is incomplete.
+pub async fn commit(pool: &SqlitePool, mut session: Session, settings: &ImportSettings) -> AppResult<()> {
+    let (mut nodes, mut files) = (Vec::new(), Vec::new());
+    if !session.pending.is_empty() {
+        session.pending.push(b'\n');
+        session.received += 1;
+        session.take_records(settings, &mut nodes, &mut files)?;
+        if !session.pending.is_empty() {
+            return Err(session.rejected("the upload ends inside the record"));
+        }
+        write(pool, session.scan_id, &mut nodes, &mut files, settings).await?;
+    }
+
+    let sid = session.scan_id.to_string();
+    let (orphans, example): (i64, Option<String>) = sqlx::query_as(
+        "SELECT COUNT(*), MIN(f.path) FROM files f WHERE f.scan_id=?1 AND NOT EXISTS \
+         (SELECT 1 FROM nodes n WHERE n.scan_id=?1 AND n.path=f.parent_path)",
+    )
+    .bind(&sid)
+    .fetch_one(pool)
+    .await?;
+    if orphans > 0 {
+        return Err(AppError::InvalidInput(format!(
+            "{} file(s) belong to no uploaded directory, e.g. {}",
+            orphans,
+            example.unwrap_or_default()
+        )));
+    }
+    let roots: Vec<String> = sqlx::query_scalar(
+        "SELECT n.path FROM nodes n WHERE n.scan_id=?1 AND NOT EXISTS \
+         (SELECT 1 FROM nodes p WHERE p.scan_id=?1 AND p.path=n.parent_path) ORDER BY n.path",
+    )
+    .bind(&sid)
+    .fetch_all(pool)
+    .await?;
+    if roots.is_empty() {
+        return Err(AppError::InvalidInput("the upload contains no directory".into()));
+    }
+
+    // One transaction, so a scan is never done while its session is still open
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"UPDATE scans SET status='done', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now'), root_paths=?2,
+            total_logical_size=(SELECT COALESCE(SUM(logical_size),0) FROM files WHERE scan_id=?1),
+            total_allocated_size=(SELECT COALESCE(SUM(allocated_size),0) FROM files WHERE scan_id=?1),
+            file_count=(SELECT COUNT(*) FROM files WHERE scan_id=?1),
+            dir_count=(SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND is_dir=1),
+            warning_count=COALESCE(warning_count,0)
+           WHERE id=?1"#,
+    )
+    .bind(&sid)
+    .bind(serde_json::to_string(&roots).map_err(anyhow::Error::from)?)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM import_sessions WHERE id=?1")
+        .bind(session.id.to_string())
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(
+        target: "audit",
+        "Imported scan {} from {} ({} root(s))",
+        session.scan_id,
+        session.origin,
+        roots.len()
+    );
+    Ok(())
+}
+
+/// Deletes the sessions that accepted nothing for [`STALE_AFTER_DAYS`], with their scans.
+///
+/// # Returns
+///
+/// * `AppResult<u64>` - The number of deleted sessions.
+pub async fn purge_stale(pool: &SqlitePool) -> AppResult<u64> {
+    let res = sqlx::query(
+        "DELETE FROM scans WHERE status='importing' AND id IN (SELECT scan_id FROM import_sessions \
+         WHERE updated_at <= strftime('%Y-%m-%dT%H:%M:%SZ','now', ?1))",
+    )
+    .bind(format!("-{} days", STALE_AFTER_DAYS))
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_records_end_outside_quotes_and_split_like_the_export_writes_them() {
+        let buf = b"File,\"/data/a\nb\",x\r\nFile";
+        let len = record_len(buf, ImportFormat::Csv).unwrap();
+        assert_eq!(&buf[..len], b"File,\"/data/a\nb\",x\r\n");
+        assert_eq!(record_len(b"File,\"/data/open", ImportFormat::Csv), None);
+        assert_eq!(record_len(b"{\"a\":\"\\\"\"}\n", ImportFormat::Ndjson), Some(11));
+
+        assert_eq!(split_csv("File,\"a,\"\"b\"\"\",,x", ',').unwrap(), ["File", "a,\"b\"", "", "x"]);
+        assert_eq!(split_csv("Dir;\"a;b\";1", ';').unwrap(), ["Dir", "a;b", "1"]);
+        assert!(split_csv("File,\"open", ',').is_err());
+    }
+
+    #[test]
+    fn headers_must_carry_sizes_in_bytes() {
+        let header = CsvHeader::parse("Type;Path;Parent Path;Logical Size;Allocated Size").unwrap();
+        assert_eq!(header.separator, ';');
+        let err = CsvHeader::parse("Type,Path,Logical Size (MiB)").unwrap_err();
+        assert!(err.contains("units=bytes"), "{}", err);
+        assert!(CsvHeader::parse("Path,Logical Size").unwrap_err().contains("'Type'"));
+        assert_eq!(parse_origin(" branch-01.example.com ").unwrap(), "branch-01.example.com");
+        assert!(parse_origin("branch 01").is_err());
+        assert!(parse_origin("").is_err());
+    }
+}
@@ -14,15 +14,26 @@
 //!
 //! ## Core Components
 //!
+//! - [`backup`]: Online backups and restores of the database
+//! - [`cli`]: Command line parsing and the headless `scan` and `export` commands
+//! - [`compressibility`]: Heuristic scoring of compression candidates
 //! - [`config`]: Application configuration management
 //! - [`db`]: Database schema initialization and migrations
+//! - [`discovery`]: Discovery file that lets local tools find the running backend
+//! - [`drive_history`]: Free space history of the drives
 //! - [`error`]: Centralized error handling and HTTP error responses
+//! - [`import`]: Imports of scans exported by other servers
+//! - [`logging`]: Log output as text or JSON
 //! - [`metrics`]: Application performance and usage metrics
 //! - [`middleware`]: HTTP middleware for security, rate limiting, and validation
+//! - [`retention`]: Automatic pruning of old scans
 //! - [`routes`]: HTTP API endpoint handlers
 //! - [`scanner`]: File system scanning and analysis engine
+//! - [`scheduler`]: Background scheduler for recurring scans
 //! - [`state`]: Shared application state and resource management
+//! - [`tls`]: HTTPS listener with certificate reload
 //! - [`types`]: Data transfer objects and shared type definitions
+//! - [`webhooks`]: Notifications about finished scans
 //!
 //! ## Features
 //!
@@ -35,12 +46,28 @@
 //! - Rate limiting and security headers
 //! - Comprehensive error handling and logging
 
+pub mod backup;
+pub mod cli;
+pub mod compressibility;
 pub mod config;
 pub mod db;
+pub mod discovery;
+pub mod drive_history;
 pub mod error;
+pub mod import;
+pub mod logging;
 pub mod metrics;
 pub mod middleware;
+pub mod retention;
 pub mod routes;
 pub mod scanner;
+pub mod scheduler;
 pub mod state;
+pub mod tls;
 pub mod types;
+pub mod webhooks;
+
+/// Fixtures shared by the unit tests.
+#[cfg(test)]
+#[path = "tests/support.rs"]
+mod test_support;
@@ -0,0 +1,93 @@
+//! Log output in the configured format.
+//!
+//! Text output is meant for people reading a console, JSON output for log
+//! aggregators: one object per line with the event fields, the current span
+//! and the list of enclosing spans. Scans run in a `scan` span with a `scan_id`
+//! field and requests in a `request` span with a `request_id` field, so the
+//! records of one scan or request can be queried by these fields.
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::config::LogFormat;
+
+/// Returns a layer that writes log records in `format` to `writer`.
+///
+/// # Arguments
+///
+/// * `format` - The format of the records.
+/// * `writer` - Where the records are written.
+/// * `ansi` - Whether text records may be colored; JSON records never are.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).boxed(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Collects the records written by a test subscriber.
+    #[derive(Clone, Default)]
+    pub struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        /// Returns the JSON records written so far.
+        pub fn records(&self) -> Vec<serde_json::Value> {
+            let bytes = self.0.lock().unwrap().clone();
+            String::from_utf8(bytes)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns a JSON subscriber for `tracing::subscriber::set_default` and its output.
+    pub fn json_subscriber() -> (impl Subscriber + Send + Sync, Captured) {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let layer = fmt_layer(LogFormat::Json, move || writer.clone(), false);
+        (tracing_subscriber::registry().with(layer), captured)
+    }
+
+    #[test]
+    fn json_records_carry_the_fields_of_their_spans() {
+        let (subscriber, captured) = json_subscriber();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("scan", scan_id = "abc");
+            let _entered = span.enter();
+            tracing::info!(files = 3, "scan finished");
+        });
+        let records = captured.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["fields"]["message"], "scan finished");
+        assert_eq!(records[0]["fields"]["files"], 3);
+        assert_eq!(records[0]["span"]["scan_id"], "abc");
+        assert_eq!(records[0]["spans"][0]["scan_id"], "abc");
+    }
+}
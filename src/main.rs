@@ -3,11 +3,12 @@ use std::net::SocketAddr;
 use axum::extract::DefaultBodyLimit;
 use axum::http::header::CONTENT_TYPE;
 use axum::middleware::{from_fn, from_fn_with_state};
+use axum::serve::ListenerExt;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Sqlite};
+use sqlx::{migrate::MigrateDatabase, Sqlite};
 use tokio::time::{self, Duration as TokioDuration};
 use tower_http::compression::predicate::{DefaultPredicate, Predicate};
 use tower_http::{
@@ -19,15 +20,31 @@ use tower_http::{
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod backup;
+mod cli;
+mod compressibility;
 mod config;
 mod db;
+mod discovery;
+mod drive_history;
 mod error;
+mod import;
+mod logging;
 mod metrics;
 mod middleware;
+mod retention;
 mod routes;
 mod scanner;
+mod scheduler;
 mod state;
+mod tls;
 mod types;
+mod webhooks;
+
+/// Fixtures shared by the unit tests.
+#[cfg(test)]
+#[path = "tests/support.rs"]
+mod test_support;
 
 use state::AppState;
 
@@ -46,6 +63,28 @@ const UI_INDEX: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/ui/index.html");
 /// * `anyhow::Result<()>` - `Ok(())` on successful execution, or an error if
 ///   something goes wrong during setup or server execution.
 async fn main() -> anyhow::Result<()> {
+    use clap::Parser;
+
+    match cli::Cli::parse().command {
+        None | Some(cli::Command::Serve) => {}
+        // `speicherwald discover` prints the discovery file of a running backend and exits
+        Some(cli::Command::Discover) => std::process::exit(discovery::print_discover()),
+        // Headless commands keep stdout free for their results and log to stderr only
+        Some(cli::Command::Scan(args)) => {
+            init_stderr_logging();
+            let code = cli::scan(load_config(), args).await?;
+            std::process::exit(code);
+        }
+        Some(cli::Command::Export(args)) => {
+            init_stderr_logging();
+            let code = cli::export(load_config(), args).await?;
+            std::process::exit(code);
+        }
+    }
+
+    // Load configuration (embedded defaults -> speicherwald.toml -> env/.env)
+    let app_cfg = load_config();
+
     // Logging (stdout + tägliche Datei-Rotation unter ./logs)
     std::fs::create_dir_all("logs").ok();
     let (stdout_nb, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
@@ -55,14 +94,14 @@ async fn main() -> anyhow::Result<()> {
         .unwrap_or_else(|_| "info,tower_http=info".into());
     tracing_subscriber::registry()
         .with(env_filter)
-        .with(tracing_subscriber::fmt::layer().with_writer(stdout_nb))
-        .with(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(file_nb))
+        .with(logging::fmt_layer(app_cfg.logging.format, stdout_nb, true))
+        .with(logging::fmt_layer(app_cfg.logging.format, file_nb, false))
         .init();
     // Guards am Leben halten (nicht fallen lassen), damit Non-Blocking Writer korrekt flushen
     let _log_guards = (stdout_guard, file_guard);
-
-    // Load configuration (embedded defaults -> speicherwald.toml -> env/.env)
-    let app_cfg = config::load()?;
+    for warning in app_cfg.warnings() {
+        tracing::warn!("{}", warning);
+    }
 
     // Prepare data dir (if sqlite)
     let db_url = &app_cfg.database.url;
@@ -84,30 +123,41 @@ async fn main() -> anyhow::Result<()> {
         })
         .unwrap_or(16)
         .clamp(1, 64);
-    let pool = SqlitePoolOptions::new()
-        .max_connections(max_conns)
-        .after_connect(|conn, _meta| {
-            Box::pin(async move {
-                let _ = sqlx::query("PRAGMA foreign_keys=ON;").execute(&mut *conn).await;
-                let _ = sqlx::query("PRAGMA busy_timeout=10000;").execute(&mut *conn).await;
-                let _ = sqlx::query("PRAGMA cache_size=-65536;").execute(&mut *conn).await; // ~64MB page cache
-                let _ = sqlx::query("PRAGMA temp_store=MEMORY;").execute(&mut *conn).await;
-                let _ = sqlx::query("PRAGMA mmap_size=268435456;").execute(&mut *conn).await; // 256MB mmap
-                Ok(())
-            })
-        })
-        .connect(db_url)
-        .await?;
+    let pool = db::connect_write_pool(db_url, max_conns).await?;
 
     // Initialize DB schema
     db::init_db(&pool).await?;
 
+    // Scans cut off by a crash or restart stay visible as interrupted and resumable
+    let interrupted = db::mark_interrupted_scans(&pool).await?;
+    if !interrupted.is_empty() {
+        tracing::warn!("Marked {} scan(s) as interrupted: {}", interrupted.len(), interrupted.join(", "));
+    }
+
+    // Separate read-only pool so UI reads don't queue behind scan inserts
+    let read_max_conns = std::env::var("SPEICHERWALD_DB_READ_MAX_CONNECTIONS")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(8)
+        .clamp(1, 64);
+    let read_busy_timeout_ms = std::env::var("SPEICHERWALD_DB_READ_BUSY_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(2000)
+        .clamp(100, 10_000);
+    let read_pool = db::connect_read_pool(db_url, read_max_conns, read_busy_timeout_ms).await?;
+
     // App state (includes rate limiting)
-    let state = AppState::new(pool.clone(), app_cfg.clone());
+    let discovery_path = discovery::default_path();
+    let state = AppState::new(pool.clone(), app_cfg.clone())
+        .with_read_pool(read_pool)
+        .with_discovery_path(discovery_path.clone());
 
     // Spawn periodic cleanup for per-endpoint rate limiters to avoid memory growth
+    // and for expired share links and abandoned imports
     {
         let rl = state.rate_limiter.clone();
+        let share_db = state.db.clone();
         // Configurable cleanup interval
         let cleanup_secs = std::env::var("SPEICHERWALD_RATE_LIMIT_CLEANUP_INTERVAL")
             .ok()
@@ -119,6 +169,70 @@ async fn main() -> anyhow::Result<()> {
             loop {
                 ticker.tick().await;
                 rl.cleanup_all().await;
+                match middleware::share::purge_expired(&share_db).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Purged {} expired share link(s)", n),
+                    Err(e) => tracing::warn!("Failed to purge expired share links: {}", e),
+                }
+                match import::purge_stale(&share_db).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Dropped {} abandoned import session(s)", n),
+                    Err(e) => tracing::warn!("Failed to drop abandoned import sessions: {}", e),
+                }
+            }
+        });
+    }
+
+    // Spawn the retention task; without a configured limit nothing is pruned, and
+    // it keeps running so a config reload can add limits
+    {
+        let retention_state = state.clone();
+        let interval_secs = app_cfg.retention.interval_secs;
+        tokio::spawn(async move {
+            let mut ticker = time::interval(TokioDuration::from_secs(interval_secs));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = retention::prune(&retention_state).await {
+                    tracing::error!("Retention prune failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // Spawn the free space sampler; drives are only enumerated on Windows
+    if cfg!(windows) && app_cfg.drive_history.enabled {
+        tokio::spawn(drive_history::run(state.db.clone(), app_cfg.drive_history.clone()));
+    }
+
+    // SIGHUP reloads the configuration; Windows uses POST /admin/reload instead
+    #[cfg(unix)]
+    {
+        let reload_state = state.clone();
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if let Err(e) = routes::reload::reload(&reload_state).await {
+                    tracing::error!("Configuration reload failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // Spawn the recurring scan scheduler; schedules live in the DB and survive restarts
+    {
+        let sched_state = state.clone();
+        let tick_secs = std::env::var("SPEICHERWALD_SCHEDULER_INTERVAL")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(30)
+            .clamp(5, 300);
+        tokio::spawn(async move {
+            let mut ticker = time::interval(TokioDuration::from_secs(tick_secs));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = scheduler::run_due_schedules(&sched_state).await {
+                    tracing::error!("Scheduler tick failed: {}", e);
+                }
             }
         });
     }
@@ -150,7 +264,11 @@ async fn main() -> anyhow::Result<()> {
             if let Some(ct) = res.headers().get(CONTENT_TYPE) {
                 if let Ok(s) = ct.to_str() {
                     // Also exclude chunked responses for better compatibility
-                    if s.starts_with("text/event-stream") || s.starts_with("multipart/") {
+                    // Backups are gzip-compressed already
+                    if s.starts_with("text/event-stream")
+                        || s.starts_with("multipart/")
+                        || s.starts_with("application/gzip")
+                    {
                         return false;
                     }
                 }
@@ -160,22 +278,19 @@ async fn main() -> anyhow::Result<()> {
     }
     let compression = CompressionLayer::new().compress_when(NoSseDefault(DefaultPredicate::new()));
 
-    // Clone config Arc for stateful middleware
+    // The middleware reads the shared config per request, so reloads apply to it
     let cfg_arc = state.config.clone();
+    let auth_tokens = std::sync::Arc::new(middleware::auth::AuthTokens::from_config(&app_cfg.auth));
 
-    // FIX Bug #32: Configure per-endpoint rate limits
-    // Note: Only static routes work with string-based endpoint matching.
-    // Dynamic routes like /scans/{id}/events cannot be matched this way.
-    // TODO: Implement pattern-based route matching for per-endpoint rate limiting
-    let state_with_limits = {
-        let mut s = state.clone();
-        s.rate_limiter = s.rate_limiter.with_limits(vec![
-            ("/scans", 30, 60),           // 30 requests per minute for scan creation
-            ("/paths/move", 10, 60),      // 10 move operations per minute
-            // Removed: ("/scans/{id}/events", ...) - doesn't work with parametrized routes
-        ]);
-        s
-    };
+    // Per-endpoint limits come from [[rate_limits]]; handlers of static routes check
+    // them directly, parametrized routes use the route-level layer below
+    let endpoint_limit = from_fn_with_state(
+        state.rate_limiter.clone(),
+        middleware::rate_limit::endpoint_rate_limit_middleware,
+    );
+
+    // ETags for the endpoints the web UI polls while live updates are on
+    let scan_etag = from_fn_with_state(state.clone(), routes::scans::scan_etag);
 
     let app = Router::new()
         .route("/healthz", get(routes::health::healthz))
@@ -183,20 +298,77 @@ async fn main() -> anyhow::Result<()> {
         .route("/metrics", get(routes::health::metrics))
         .route("/metrics/prometheus", get(routes::health::metrics_prometheus))
         .route("/version", get(routes::health::version))
+        .route("/admin/backup", get(routes::backup::get_backup))
+        .route("/admin/backup/restore", post(routes::backup::restore_backup))
+        .route("/admin/reload", post(routes::reload::reload_config))
+        .route("/admin/db/vacuum", post(routes::maintenance::vacuum_db))
+        .route("/admin/db/analyze", post(routes::maintenance::analyze_db))
+        .route("/admin/db/integrity", get(routes::maintenance::check_integrity))
         .route("/scans", post(routes::scans::create_scan).get(routes::scans::list_scans))
+        .route("/scans/estimate", post(routes::scans::estimate_scan))
+        .route("/scans/retention", get(routes::retention::get_retention))
+        .route("/scans/retention/run", post(routes::retention::run_retention))
         .route("/scans/{id}", get(routes::scans::get_scan).delete(routes::scans::cancel_scan))
-        .route("/scans/{id}/events", get(routes::scans::scan_events))
-        .route("/scans/{id}/tree", get(routes::scans::get_tree))
-        .route("/scans/{id}/top", get(routes::scans::get_top))
-        .route("/scans/{id}/list", get(routes::scans::get_list))
+        .route("/scans/{id}/pause", post(routes::scans::pause_scan))
+        .route("/scans/{id}/resume", post(routes::scans::resume_scan))
+        .route("/scans/{id}/events", get(routes::scans::scan_events).layer(endpoint_limit.clone()))
+        .route("/scans/{id}/ws", get(routes::ws::scan_ws).layer(endpoint_limit.clone()))
+        .route("/scans/{id}/tree", get(routes::scans::get_tree).layer(scan_etag.clone()))
+        .route("/scans/{id}/top", get(routes::scans::get_top).layer(scan_etag.clone()))
+        .route("/scans/{id}/list", get(routes::scans::get_list).layer(scan_etag.clone()))
         .route("/scans/{id}/recent", get(routes::scans::get_recent))
+        .route("/scans/{id}/complete", get(routes::scans::get_complete))
+        .route("/scans/{id}/node", get(routes::node::get_node))
+        .route("/scans/{id}/diff", get(routes::diff::diff_scan))
+        .route("/reports/growth", get(routes::reports::get_growth))
+        .route("/scans/{id}/analysis/age-bands", get(routes::analysis::get_age_bands))
+        .route("/scans/{id}/analysis/compressibility", get(routes::analysis::get_compressibility))
+        .route("/scans/{id}/analysis/empty-files", get(routes::analysis::get_empty_files))
+        .route("/scans/{id}/age-report", get(routes::analysis::get_age_report))
+        .route("/scans/{id}/treemap", get(routes::analysis::get_treemap))
+        .route("/scans/{id}/streams", get(routes::streams::get_streams))
+        .route("/scans/{id}/owners", get(routes::owners::get_owners))
+        .route("/scans/{id}/warnings", get(routes::warnings::get_warnings))
+        .route("/scans/{id}/verify", post(routes::verify::verify_scan).get(routes::verify::list_verifications))
+        .route("/scans/{id}/recompute", post(routes::consistency::recompute_scan))
+        .route("/scans/{id}/inconsistencies", get(routes::consistency::list_inconsistencies))
+        .route("/scans/{id}/integrity", get(routes::integrity::integrity_scan))
+        .route("/scans/{id}/simulate-exclude", get(routes::simulate::simulate_exclude))
+        .route("/scans/{id}/share", post(routes::share::create_share))
+        .route("/scans/{id}/share/{token}", delete(routes::share::revoke_share))
+        .route("/scans/{id}/links", get(routes::links::get_links))
+        .route("/scans/{id}/explain", get(routes::explain::explain_scan_path))
+        .route("/scans/{id}/watch", post(routes::watch::start_watch).delete(routes::watch::stop_watch))
+        .route("/scans/{id}/remap-root", post(routes::remap::remap_root).get(routes::remap::list_remaps))
+        .route(
+            "/scans/{id}/duplicates",
+            post(routes::duplicates::start_duplicates).get(routes::duplicates::get_duplicates),
+        )
         .route("/scans/{id}/search", get(routes::search::search_scan))
+        .route("/search", get(routes::search::search_all))
         .route("/scans/{id}/export", get(routes::export::export_scan))
         .route("/scans/{id}/statistics", get(routes::export::export_statistics))
+        .route("/scans/{id}/manifest", get(routes::manifest::get_manifest))
+        .route("/import/sessions", post(routes::import::create_session))
+        .route("/import/sessions/{id}", get(routes::import::get_session).put(routes::import::append_chunk))
+        .route("/import/sessions/{id}/commit", post(routes::import::commit_session))
+        .route("/import/scan", post(routes::import::import_scan))
+        .route("/schedules", post(routes::schedules::create_schedule).get(routes::schedules::list_schedules))
+        .route("/schedules/{id}", delete(routes::schedules::delete_schedule))
         .route("/drives", get(routes::drives::list_drives))
+        .route("/drives/history", get(routes::drives::get_drive_history))
+        .route("/preview", get(routes::preview::preview_path))
         .route("/paths/move", post(routes::paths::move_path))
+        .route("/paths/move-batch", post(routes::paths::move_batch))
+        .route("/paths/delete", post(routes::paths::delete_path))
+        .route("/paths/archive", post(routes::paths::archive_path))
+        .route("/paths/operations", get(routes::paths::list_operations))
+        .route(
+            "/paths/operations/{op_id}",
+            get(routes::paths::get_operation).delete(routes::paths::cancel_operation),
+        )
         .fallback_service(static_ui_service)
-        .with_state(state_with_limits)
+        .with_state(state.clone())
         // Globales Body-Limit – schützt vor übergroßen Requests (configurable via env)
         .layer(DefaultBodyLimit::max(
             std::env::var("SPEICHERWALD_MAX_BODY_SIZE")
@@ -206,10 +378,16 @@ async fn main() -> anyhow::Result<()> {
                 .clamp(1024 * 1024, 50 * 1024 * 1024), // 1MB to 50MB
         ))
         .layer(from_fn(middleware::validation::validate_request_middleware))
-        .layer(from_fn(middleware::auth::auth_middleware)) // FIX Bug #5: Apply authentication
+        // FIX Bug #5: Apply authentication
+        .layer(from_fn_with_state(auth_tokens.clone(), middleware::auth::auth_middleware))
+        // Runs before the auth check, which lets requests admitted by a share link pass
+        .layer(from_fn_with_state(state.clone(), middleware::share::share_middleware))
         .layer(from_fn(middleware::rate_limit::rate_limit_middleware))
         .layer(compression)
+        .layer(from_fn_with_state(state.metrics.clone(), middleware::http_metrics::http_metrics_middleware))
         .layer(TraceLayer::new_for_http())
+        // Outside the trace layer, so its records carry the request ID too
+        .layer(from_fn(middleware::request_id::request_id_middleware))
         .layer(from_fn_with_state(cfg_arc, middleware::security_headers::security_headers_middleware));
 
     // CORS: in Debug permissiv (für lokale Entwicklung mit separater UI), in Release nicht nötig (same-origin)
@@ -221,15 +399,103 @@ async fn main() -> anyhow::Result<()> {
     let addr: SocketAddr = format!("{}:{}", host, port)
         .parse()
         .map_err(|e| anyhow::anyhow!("invalid listen addr {}:{} - {}", host, port, e))?;
+    if !auth_tokens.is_enabled() && !addr.ip().is_loopback() {
+        tracing::warn!("Listening on {} without authentication; enable [auth] to require API tokens", addr);
+    }
+    // A certificate that cannot be loaded stops the server before it listens
+    let certs = if app_cfg.server.tls.enabled {
+        Some(std::sync::Arc::new(tls::CertStore::load(&app_cfg.server.tls)?))
+    } else {
+        None
+    };
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
-    info!("SpeicherWald listening on http://{}", listener.local_addr()?);
+    let local_addr = listener.local_addr()?;
+    let scheme = if certs.is_some() { "https" } else { "http" };
+    info!("SpeicherWald listening on {}://{}", scheme, local_addr);
+
+    // Discovery file so local tools can find this backend; refreshed so staleness is detectable
+    let discovery_file = discovery_path.and_then(|path| {
+        let info = discovery::DiscoveryInfo::for_current_process(
+            local_addr,
+            auth_tokens.is_enabled(),
+            certs.is_some(),
+        );
+        match discovery::DiscoveryFile::create(path.clone(), info, discovery::stale_after()) {
+            Ok(f) => {
+                info!("Discovery file written to {}", f.path().display());
+                Some(f)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to write discovery file {}: {}", path.display(), e);
+                None
+            }
+        }
+    });
+    let discovery_file = std::sync::Arc::new(std::sync::Mutex::new(discovery_file));
+    {
+        let discovery_file = discovery_file.clone();
+        tokio::spawn(async move {
+            let mut ticker = time::interval(discovery::refresh_interval());
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Some(f) = discovery_file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                    if let Err(e) = f.refresh() {
+                        tracing::warn!("Failed to refresh discovery file {}: {}", f.path().display(), e);
+                    }
+                }
+            }
+        });
+    }
+
     let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
-    axum::serve(listener, make_service).with_graceful_shutdown(shutdown_signal()).await?;
+    let served = match certs {
+        Some(certs) => {
+            let secs = app_cfg.server.tls.reload_interval_secs;
+            certs.spawn_reloader((secs > 0).then(|| TokioDuration::from_secs(secs)));
+            // `tap_io` gives the TLS listener axum's `ConnectInfo<SocketAddr>` support
+            let listener = tls::TlsListener::new(listener, certs)?.tap_io(|_| {});
+            axum::serve(listener, make_service).with_graceful_shutdown(shutdown_signal()).await
+        }
+        None => axum::serve(listener, make_service).with_graceful_shutdown(shutdown_signal()).await,
+    };
+
+    if let Some(f) = discovery_file.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        if let Err(e) = f.remove() {
+            tracing::warn!("Failed to remove discovery file: {}", e);
+        }
+    }
+    served?;
 
     Ok(())
 }
 
+/// Loads the configuration, or prints why it is invalid and exits.
+///
+/// Runs before logging is set up, so the problems go to stderr as a block
+/// listing every violated rule at once.
+fn load_config() -> config::AppConfig {
+    match config::load() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Installs a logger for the headless subcommands that writes warnings to stderr.
+///
+/// `RUST_LOG` overrides the level as for the server.
+fn init_stderr_logging() {
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into());
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+}
+
 /// Listens for shutdown signals (Ctrl+C, SIGTERM) and gracefully shuts down the server.
 ///
 /// This function waits for either a Ctrl+C signal or, on Unix systems, a SIGTERM
@@ -1,7 +1,8 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// A collection of atomic counters for tracking application performance metrics.
 ///
@@ -22,6 +23,22 @@ pub struct Metrics {
     pub bytes_scanned: Arc<AtomicU64>,
     /// The total number of warnings generated across all scans.
     pub warnings_count: Arc<AtomicUsize>,
+    /// The number of scans currently in `run_scan`.
+    pub scans_running: Arc<AtomicUsize>,
+    /// The number of scanner workers currently scanning a directory.
+    pub scanner_active_workers: Arc<AtomicUsize>,
+    /// The number of batches waiting in the scanners' aggregator channels.
+    pub scanner_queue_depth: Arc<AtomicUsize>,
+    /// The number of node and file records waiting to be persisted.
+    pub scanner_buffered_records: Arc<AtomicUsize>,
+    /// The estimated bytes of the records waiting to be persisted.
+    pub scanner_buffered_bytes: Arc<AtomicUsize>,
+    /// The most bytes the buffers of one scan held at the same time, over the scans since startup.
+    pub scanner_buffer_peak_bytes: Arc<AtomicUsize>,
+    /// How long the scans that ended since startup ran, whatever their outcome.
+    pub scan_durations: Arc<DurationHistogram>,
+    /// How long HTTP requests took per route and status class.
+    pub http_requests: Arc<HttpLatencies>,
     /// The time at which the application was started.
     pub start_time: Instant,
 }
@@ -37,6 +54,14 @@ impl Metrics {
             dirs_processed: Arc::new(AtomicU64::new(0)),
             bytes_scanned: Arc::new(AtomicU64::new(0)),
             warnings_count: Arc::new(AtomicUsize::new(0)),
+            scans_running: Arc::new(AtomicUsize::new(0)),
+            scanner_active_workers: Arc::new(AtomicUsize::new(0)),
+            scanner_queue_depth: Arc::new(AtomicUsize::new(0)),
+            scanner_buffered_records: Arc::new(AtomicUsize::new(0)),
+            scanner_buffered_bytes: Arc::new(AtomicUsize::new(0)),
+            scanner_buffer_peak_bytes: Arc::new(AtomicUsize::new(0)),
+            scan_durations: Arc::new(DurationHistogram::default()),
+            http_requests: Arc::new(HttpLatencies::default()),
             start_time: Instant::now(),
         }
     }
@@ -76,6 +101,11 @@ impl Metrics {
         self.warnings_count.fetch_add(count, Ordering::Relaxed);
     }
 
+    /// Records how long a scan ran in the `scan_durations` histogram.
+    pub fn observe_scan_duration(&self, duration: Duration) {
+        self.scan_durations.observe(duration);
+    }
+
     /// Returns a snapshot of the current metrics.
     pub fn get_snapshot(&self) -> MetricsSnapshot {
         MetricsSnapshot {
@@ -86,6 +116,12 @@ impl Metrics {
             dirs_processed: self.dirs_processed.load(Ordering::Relaxed),
             bytes_scanned: self.bytes_scanned.load(Ordering::Relaxed),
             warnings_count: self.warnings_count.load(Ordering::Relaxed),
+            scans_running: self.scans_running.load(Ordering::Relaxed),
+            scanner_active_workers: self.scanner_active_workers.load(Ordering::Relaxed),
+            scanner_queue_depth: self.scanner_queue_depth.load(Ordering::Relaxed),
+            scanner_buffered_records: self.scanner_buffered_records.load(Ordering::Relaxed),
+            scanner_buffered_bytes: self.scanner_buffered_bytes.load(Ordering::Relaxed),
+            scanner_buffer_peak_bytes: self.scanner_buffer_peak_bytes.load(Ordering::Relaxed),
             uptime_seconds: self.start_time.elapsed().as_secs(),
         }
     }
@@ -97,6 +133,224 @@ impl Default for Metrics {
     }
 }
 
+/// One scan's contribution to a gauge that is summed over all running scans.
+///
+/// Dropping the share takes its contribution back, so the gauge returns to zero
+/// even when a scan ends early with an error.
+pub struct GaugeShare {
+    gauge: Arc<AtomicUsize>,
+    value: usize,
+}
+
+impl GaugeShare {
+    /// Creates a share that contributes nothing yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `gauge` - The gauge to contribute to.
+    pub fn new(gauge: &Arc<AtomicUsize>) -> Self {
+        Self { gauge: gauge.clone(), value: 0 }
+    }
+
+    /// Creates a share that contributes one, e.g. for a running scan or worker.
+    pub fn one(gauge: &Arc<AtomicUsize>) -> Self {
+        let mut share = Self::new(gauge);
+        share.set(1);
+        share
+    }
+
+    /// Sets this share's contribution.
+    pub fn set(&mut self, value: usize) {
+        if value > self.value {
+            self.gauge.fetch_add(value - self.value, Ordering::Relaxed);
+        } else if value < self.value {
+            self.gauge.fetch_sub(self.value - value, Ordering::Relaxed);
+        }
+        self.value = value;
+    }
+}
+
+impl Drop for GaugeShare {
+    fn drop(&mut self) {
+        self.set(0);
+    }
+}
+
+/// The upper bounds of the scan duration buckets in seconds, from one second to a day.
+pub const SCAN_DURATION_BUCKETS: [f64; 10] =
+    [1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 1_800.0, 3_600.0, 14_400.0, 86_400.0];
+
+/// The upper bounds of the HTTP request latency buckets in seconds, from 1 ms to 10 s.
+pub const HTTP_LATENCY_BUCKETS: [f64; 13] =
+    [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// A Prometheus-style histogram of durations.
+///
+/// Observations are counted per bucket and summed up when rendered, since
+/// Prometheus expects cumulative buckets. The default uses the
+/// [`SCAN_DURATION_BUCKETS`].
+pub struct DurationHistogram {
+    /// The upper bounds of the buckets in seconds.
+    bounds: &'static [f64],
+    /// Observations per bucket, not cumulative; the last one is `+Inf`.
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl Default for DurationHistogram {
+    fn default() -> Self {
+        Self::new(&SCAN_DURATION_BUCKETS)
+    }
+}
+
+impl DurationHistogram {
+    /// Creates an empty histogram with the given bucket bounds in seconds, ascending.
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds one observation.
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let bucket = self.bounds.iter().position(|le| secs <= *le).unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(duration.as_micros().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+
+    /// Renders the histogram in the Prometheus text format.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The metric name, without the `_bucket`, `_sum` and `_count` suffixes.
+    /// * `help` - The help text.
+    pub fn to_prometheus(&self, name: &str, help: &str) -> String {
+        let mut out = format!("# HELP {name} {help}\n# TYPE {name} histogram\n");
+        self.write_samples(&mut out, name, "");
+        out
+    }
+
+    /// Appends the bucket, sum and count samples, each carrying `labels`
+    /// (`key="value",` pairs, each followed by a comma).
+    fn write_samples(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = self.bounds.get(i).map_or("+Inf".to_string(), |le| le.to_string());
+            out.push_str(&format!("{name}_bucket{{{labels}le=\"{le}\"}} {cumulative}\n"));
+        }
+        let labels = labels.trim_end_matches(',');
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{labels}}}") };
+        let sum = self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let count = self.count.load(Ordering::Relaxed);
+        out.push_str(&format!("{name}_sum{labels} {sum}\n{name}_count{labels} {count}\n"));
+    }
+
+    /// Returns the upper bound of the bucket that holds the `q` quantile, in
+    /// seconds; `None` without observations or if it lies above the last bound.
+    fn quantile_bound(&self, q: f64) -> Option<f64> {
+        let count = self.count.load(Ordering::Relaxed);
+        let rank = (count as f64 * q).ceil().max(1.0) as u64;
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            if count > 0 && cumulative >= rank {
+                return self.bounds.get(i).copied();
+            }
+        }
+        None
+    }
+}
+
+/// The route label of requests that matched no route, such as files of the web UI.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Latency histograms of HTTP requests per route template and status class.
+///
+/// Routes are labeled by their template (`/scans/{id}/tree`), never by the
+/// requested path, so the number of series stays bounded.
+#[derive(Default)]
+pub struct HttpLatencies {
+    series: Mutex<BTreeMap<(String, &'static str), DurationHistogram>>,
+}
+
+impl HttpLatencies {
+    /// Records one request.
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - The matched route template or [`UNMATCHED_ROUTE`].
+    /// * `status` - The HTTP status code of the response.
+    /// * `duration` - How long the request took until the response head.
+    pub fn observe(&self, route: &str, status: u16, duration: Duration) {
+        let class = match status {
+            100..=199 => "1xx",
+            200..=299 => "2xx",
+            300..=399 => "3xx",
+            400..=499 => "4xx",
+            _ => "5xx",
+        };
+        let mut series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        series
+            .entry((route.to_string(), class))
+            .or_insert_with(|| DurationHistogram::new(&HTTP_LATENCY_BUCKETS))
+            .observe(duration);
+    }
+
+    /// Renders one histogram per route and status class with `route` and
+    /// `status` labels in the Prometheus text format.
+    pub fn to_prometheus(&self, name: &str, help: &str) -> String {
+        let mut out = format!("# HELP {name} {help}\n# TYPE {name} histogram\n");
+        let series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for ((route, class), histogram) in series.iter() {
+            let route = route.replace('\\', "\\\\").replace('"', "\\\"");
+            histogram.write_samples(&mut out, name, &format!("route=\"{route}\",status=\"{class}\","));
+        }
+        out
+    }
+
+    /// Returns the request count and latency of every route and status class.
+    pub fn summary(&self) -> Vec<HttpRouteLatency> {
+        let series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        series
+            .iter()
+            .map(|((route, class), histogram)| {
+                let count = histogram.count.load(Ordering::Relaxed);
+                let sum_ms = histogram.sum_us.load(Ordering::Relaxed) as f64 / 1000.0;
+                HttpRouteLatency {
+                    route: route.clone(),
+                    status: class,
+                    count,
+                    avg_ms: if count == 0 { 0.0 } else { sum_ms / count as f64 },
+                    p95_ms: histogram.quantile_bound(0.95).map(|secs| secs * 1000.0),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The requests of one route and status class in the JSON metrics.
+#[derive(Debug, Serialize)]
+pub struct HttpRouteLatency {
+    /// The route template, e.g. `/scans/{id}/tree`.
+    pub route: String,
+    /// The status class, e.g. `2xx`.
+    pub status: &'static str,
+    /// The number of requests.
+    pub count: u64,
+    /// The mean latency in milliseconds.
+    pub avg_ms: f64,
+    /// The upper bound of the bucket holding the 95th percentile latency, in
+    /// milliseconds; `None` if it exceeds the largest bucket.
+    pub p95_ms: Option<f64>,
+}
+
 /// A snapshot of the application metrics at a specific point in time.
 #[derive(Serialize)]
 pub struct MetricsSnapshot {
@@ -114,6 +368,59 @@ pub struct MetricsSnapshot {
     pub bytes_scanned: u64,
     /// The total number of warnings generated across all scans.
     pub warnings_count: usize,
+    /// The number of scans currently running.
+    pub scans_running: usize,
+    /// The number of scanner workers currently scanning a directory.
+    pub scanner_active_workers: usize,
+    /// The number of batches waiting in the scanners' aggregator channels.
+    pub scanner_queue_depth: usize,
+    /// The number of node and file records waiting to be persisted.
+    pub scanner_buffered_records: usize,
+    /// The estimated bytes of the records waiting to be persisted.
+    pub scanner_buffered_bytes: usize,
+    /// The most bytes the buffers of one scan held at the same time, over the scans since startup.
+    pub scanner_buffer_peak_bytes: usize,
     /// The uptime of the application in seconds.
     pub uptime_seconds: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_histogram_renders_cumulative_buckets() {
+        let metrics = Metrics::new();
+        for secs in [0, 3, 4, 100_000] {
+            metrics.observe_scan_duration(Duration::from_secs(secs));
+        }
+        metrics.observe_scan_duration(Duration::from_millis(1_500));
+        let text = metrics.scan_durations.to_prometheus("scan_duration_seconds", "Scan durations");
+        assert!(text.starts_with("# HELP scan_duration_seconds Scan durations\n# TYPE scan_duration_seconds histogram\n"));
+        assert!(text.contains("scan_duration_seconds_bucket{le=\"1\"} 1\n"));
+        assert!(text.contains("scan_duration_seconds_bucket{le=\"5\"} 4\n"));
+        assert!(text.contains("scan_duration_seconds_bucket{le=\"86400\"} 4\n"));
+        assert!(text.contains("scan_duration_seconds_bucket{le=\"+Inf\"} 5\n"));
+        assert!(text.contains("scan_duration_seconds_sum 100008.5\n"));
+        assert!(text.ends_with("scan_duration_seconds_count 5\n"));
+    }
+    #[test]
+    fn http_latencies_are_labeled_by_route_and_status_class() {
+        let latencies = HttpLatencies::default();
+        latencies.observe("/scans/{id}/tree", 200, Duration::from_millis(3));
+        latencies.observe("/scans/{id}/tree", 204, Duration::from_millis(30));
+        latencies.observe("/scans/{id}/tree", 404, Duration::from_micros(500));
+        let text = latencies.to_prometheus("http_seconds", "Latency");
+        let ok = "route=\"/scans/{id}/tree\",status=\"2xx\"";
+        assert!(text.contains(&format!("http_seconds_bucket{{{ok},le=\"0.005\"}} 1\n")));
+        assert!(text.contains(&format!("http_seconds_bucket{{{ok},le=\"0.05\"}} 2\n")));
+        assert!(text.contains(&format!("http_seconds_sum{{{ok}}} 0.033\n")));
+        assert!(text.contains("http_seconds_count{route=\"/scans/{id}/tree\",status=\"4xx\"} 1\n"));
+
+        let summary = latencies.summary();
+        assert_eq!(summary.len(), 2);
+        assert_eq!((summary[0].status, summary[0].count), ("2xx", 2));
+        assert_eq!(summary[0].avg_ms, 16.5);
+        assert_eq!(summary[0].p95_ms, Some(50.0));
+    }
+}
@@ -1,54 +1,264 @@
+use std::sync::Arc;
+
 use axum::{
-    extract::Request,
-    http::{header, StatusCode},
+    extract::{Query, Request, State},
+    http::{header, Method, StatusCode},
     middleware::Next,
     response::Response,
 };
 
-/// Middleware that checks for a Bearer token in the Authorization header.
+use crate::{
+    config::AuthConfig,
+    middleware::{namespace::Namespace, share::SharedScan},
+};
+
+/// Paths that stay reachable without a token, for load balancers and orchestrators.
+const OPEN_PATHS: &[&str] = &["/healthz", "/readyz"];
+
+/// The tokens accepted by [`auth_middleware`].
+#[derive(Debug, Clone, Default)]
+pub struct AuthTokens {
+    /// Tokens that grant access to every namespace.
+    global: Vec<String>,
+    /// Tokens bound to a single namespace.
+    namespaced: Vec<(Namespace, String)>,
+}
+
+impl AuthTokens {
+    /// Collects the accepted tokens from the `[auth]` section and the environment.
+    ///
+    /// The tokens of `[auth]` count only if it is enabled. `SPEICHERWALD_AUTH_TOKEN`
+    /// adds a global token and `SPEICHERWALD_NAMESPACE_TOKENS`
+    /// (`namespace=token,other=token2`) adds tokens bound to one namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The authentication configuration.
+    pub fn from_config(cfg: &AuthConfig) -> Self {
+        let mut global = if cfg.enabled { cfg.tokens.clone() } else { Vec::new() };
+        if let Some(token) = std::env::var("SPEICHERWALD_AUTH_TOKEN").ok().filter(|t| !t.is_empty()) {
+            global.push(token);
+        }
+        let namespaced = std::env::var("SPEICHERWALD_NAMESPACE_TOKENS")
+            .map(|v| parse_namespace_tokens(&v))
+            .unwrap_or_default();
+        Self { global, namespaced }
+    }
+
+    /// Returns whether requests need a token at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.global.is_empty() || !self.namespaced.is_empty()
+    }
+
+    /// Checks the token of a request.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(ns))` - The token is bound to the namespace `ns`.
+    /// * `Ok(None)` - The request may pass unrestricted.
+    /// * `Err(StatusCode::UNAUTHORIZED)` - The token is missing or unknown.
+    fn authorize(&self, req: &Request) -> Result<Option<Namespace>, StatusCode> {
+        if !self.is_enabled() || OPEN_PATHS.contains(&req.uri().path()) {
+            return Ok(None);
+        }
+        let provided = bearer_token(req)
+            .or_else(|| is_event_stream(req).then(|| query_token(req)).flatten())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        // Compare against every token so the time taken does not tell which one matched
+        let global = self.global.iter().fold(false, |hit, t| tokens_match(&provided, t) | hit);
+        let namespaced = self.namespaced.iter().fold(None, |hit, (ns, t)| {
+            if tokens_match(&provided, t) && hit.is_none() {
+                Some(ns.clone())
+            } else {
+                hit
+            }
+        });
+        match (global, namespaced) {
+            (true, _) => Ok(None),
+            (false, Some(ns)) => Ok(Some(ns)),
+            (false, None) => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+/// Middleware that requires a known bearer token once any token is configured.
 ///
-/// If `SPEICHERWALD_AUTH_TOKEN` is set in the environment, this middleware enforces
-/// that all requests must have a matching `Authorization: Bearer <token>` header.
-/// If the environment variable is not set, the middleware is a no-op (authentication disabled).
-pub async fn auth_middleware(req: Request, next: Next) -> Result<Response, StatusCode> {
-    // Check if auth is enabled via env var
-    // FIX Bug #6: Cache the token lookup to avoid env var overhead on every request
-    static AUTH_TOKEN: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
-    let expected_token_opt = AUTH_TOKEN.get_or_init(|| {
-        std::env::var("SPEICHERWALD_AUTH_TOKEN").ok().filter(|t| !t.is_empty())
-    });
-
-    let expected_token = match expected_token_opt {
-        Some(t) => t,
-        None => return Ok(next.run(req).await),
-    };
-
-    // Check header
-    let auth_header = req
-        .headers()
+/// Tokens come from `[auth]` and the environment, see [`AuthTokens::from_config`].
+/// Without any token the middleware is a no-op. `/healthz` and `/readyz` are
+/// always open. `GET /scans/{id}/events` and `GET /scans/{id}/ws` also accept
+/// `?token=`, because browsers cannot set headers on an `EventSource` or a
+/// WebSocket upgrade.
+///
+/// Requests authenticated with a namespace token are pinned to that namespace
+/// regardless of the `X-Speicherwald-Namespace` header. Requests a share link
+/// admitted (see [`crate::middleware::share`]) need no token.
+pub async fn auth_middleware(
+    State(tokens): State<Arc<AuthTokens>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if req.extensions().get::<SharedScan>().is_some() {
+        return Ok(next.run(req).await);
+    }
+    if let Some(ns) = tokens.authorize(&req)? {
+        req.extensions_mut().insert(ns);
+    }
+    Ok(next.run(req).await)
+}
+
+fn bearer_token(req: &Request) -> Option<String> {
+    req.headers()
         .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
-
-    match auth_header {
-        Some(auth_val) if auth_val.starts_with("Bearer ") => {
-            let provided_token = &auth_val[7..];
-            // FIX Bug #6: Use constant-time comparison to prevent timing attacks
-            // Simple constant-time comparison implementation
-            let provided_bytes = provided_token.as_bytes();
-            let expected_bytes = expected_token.as_bytes();
-            if provided_bytes.len() != expected_bytes.len() {
-                return Err(StatusCode::UNAUTHORIZED);
-            }
-            let mut diff = 0u8;
-            for (i, &b) in provided_bytes.iter().enumerate() {
-                diff |= b ^ expected_bytes[i];
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string())
+}
+
+/// Returns whether the request opens a scan's event stream, over SSE or a WebSocket.
+fn is_event_stream(req: &Request) -> bool {
+    let path = req.uri().path();
+    req.method() == Method::GET
+        && path.starts_with("/scans/")
+        && (path.ends_with("/events") || path.ends_with("/ws"))
+}
+
+fn query_token(req: &Request) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct TokenQuery {
+        token: Option<String>,
+    }
+    Query::<TokenQuery>::try_from_uri(req.uri()).ok()?.0.token.filter(|t| !t.is_empty())
+}
+
+/// Compares two tokens without leaking the position of the first mismatch.
+pub(crate) fn tokens_match(provided: &str, expected: &str) -> bool {
+    // FIX Bug #6: Use constant-time comparison to prevent timing attacks
+    // Simple constant-time comparison implementation
+    let provided_bytes = provided.as_bytes();
+    let expected_bytes = expected.as_bytes();
+    if provided_bytes.len() != expected_bytes.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (i, &b) in provided_bytes.iter().enumerate() {
+        diff |= b ^ expected_bytes[i];
+    }
+    diff == 0
+}
+
+/// Parses `namespace=token` pairs separated by commas; invalid entries are skipped.
+fn parse_namespace_tokens(raw: &str) -> Vec<(Namespace, String)> {
+    raw.split(',')
+        .filter_map(|pair| {
+            let (ns, token) = pair.split_once('=')?;
+            let token = token.trim();
+            if token.is_empty() {
+                return None;
             }
-            if diff == 0 {
-                Ok(next.run(req).await)
-            } else {
-                Err(StatusCode::UNAUTHORIZED)
+            match Namespace::parse(ns) {
+                Ok(ns) => Some((ns, token.to_string())),
+                Err(_) => {
+                    tracing::warn!("Ignoring namespace token with invalid namespace {:?}", ns.trim());
+                    None
+                }
             }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespace_tokens_are_parsed() {
+        let tokens = parse_namespace_tokens("hr=abc, finance = def ,bad name=x,empty=,noeq");
+        let names: Vec<(&str, &str)> = tokens.iter().map(|(n, t)| (n.as_str(), t.as_str())).collect();
+        assert_eq!(names, vec![("hr", "abc"), ("finance", "def")]);
+    }
+
+    #[test]
+    fn tokens_match_requires_exact_token() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+    }
+
+    fn tokens() -> AuthTokens {
+        AuthTokens {
+            global: vec!["admin-token".into(), "second-token".into()],
+            namespaced: vec![(Namespace::parse("hr").unwrap(), "hr-token".into())],
+        }
+    }
+
+    fn request(method: Method, uri: &str, bearer: Option<&str>) -> Request {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = bearer {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
         }
-        _ => Err(StatusCode::UNAUTHORIZED),
+        builder.body(axum::body::Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn allows_known_bearer_tokens() {
+        let t = tokens();
+        assert_eq!(t.authorize(&request(Method::GET, "/scans", Some("admin-token"))), Ok(None));
+        assert_eq!(t.authorize(&request(Method::POST, "/paths/delete", Some("second-token"))), Ok(None));
+        let pinned = t.authorize(&request(Method::GET, "/scans", Some("hr-token"))).unwrap();
+        assert_eq!(pinned.as_ref().map(Namespace::as_str), Some("hr"));
+        // Open paths and disabled auth need no token
+        assert_eq!(t.authorize(&request(Method::GET, "/healthz", None)), Ok(None));
+        assert_eq!(t.authorize(&request(Method::GET, "/readyz", None)), Ok(None));
+        assert_eq!(AuthTokens::default().authorize(&request(Method::GET, "/scans", None)), Ok(None));
+    }
+
+    #[test]
+    fn denies_missing_or_unknown_tokens() {
+        let t = tokens();
+        let denied = Err(StatusCode::UNAUTHORIZED);
+        assert_eq!(t.authorize(&request(Method::GET, "/scans", None)), denied);
+        assert_eq!(t.authorize(&request(Method::GET, "/scans", Some("admin-tokeN"))), denied);
+        assert_eq!(t.authorize(&request(Method::GET, "/metrics", Some(""))), denied);
+        assert_eq!(t.authorize(&request(Method::GET, "/healthz/../scans", None)), denied);
+        // The query token only counts for event streams
+        assert_eq!(t.authorize(&request(Method::GET, "/scans?token=admin-token", None)), denied);
+        assert_eq!(t.authorize(&request(Method::DELETE, "/scans/x/events?token=admin-token", None)), denied);
+    }
+
+    #[test]
+    fn event_streams_accept_query_token() {
+        let t = tokens();
+        let id = uuid::Uuid::new_v4();
+        let uri = |token: &str| format!("/scans/{}/events?token={}", id, token);
+        assert_eq!(t.authorize(&request(Method::GET, &uri("admin-token"), None)), Ok(None));
+        let pinned = t.authorize(&request(Method::GET, &uri("hr-token"), None)).unwrap();
+        assert_eq!(pinned.as_ref().map(Namespace::as_str), Some("hr"));
+        assert_eq!(t.authorize(&request(Method::GET, &uri("wrong"), None)), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            t.authorize(&request(Method::GET, &format!("/scans/{}/events", id), None)),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        // The header still works for clients that can set it
+        assert_eq!(t.authorize(&request(Method::GET, &uri("wrong"), Some("admin-token"))), Ok(None));
+    }
+
+    #[test]
+    fn websocket_upgrades_accept_query_token() {
+        let t = tokens();
+        let uri = |token: &str| format!("/scans/{}/ws?token={}", uuid::Uuid::new_v4(), token);
+        assert_eq!(t.authorize(&request(Method::GET, &uri("admin-token"), None)), Ok(None));
+        let pinned = t.authorize(&request(Method::GET, &uri("hr-token"), None)).unwrap();
+        assert_eq!(pinned.as_ref().map(Namespace::as_str), Some("hr"));
+        assert_eq!(t.authorize(&request(Method::GET, &uri("wrong"), None)), Err(StatusCode::UNAUTHORIZED));
+        let denied = Err(StatusCode::UNAUTHORIZED);
+        assert_eq!(t.authorize(&request(Method::GET, "/ws?token=admin-token", None)), denied);
+    }
+
+    #[test]
+    fn config_tokens_count_only_when_enabled() {
+        let cfg = AuthConfig { enabled: false, tokens: vec!["cfg-token".into()] };
+        assert!(!AuthTokens::from_config(&cfg).global.contains(&"cfg-token".to_string()));
+        let cfg = AuthConfig { enabled: true, ..cfg };
+        assert!(AuthTokens::from_config(&cfg).global.contains(&"cfg-token".to_string()));
     }
 }
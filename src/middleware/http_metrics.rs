@@ -0,0 +1,76 @@
+//! Request latency metrics per route.
+//!
+//! Every request is timed from the moment it was routed until the response
+//! head is ready and recorded under its route template (`/scans/{id}/tree`,
+//! taken from [`MatchedPath`]) and status class. Requests that matched no route,
+//! such as the files of the web UI, share one label. Event streams and
+//! downloads count until their first byte, not until the body is complete.
+
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::metrics::{Metrics, UNMATCHED_ROUTE};
+
+/// Times the request and records it in the HTTP latency histograms.
+///
+/// # Arguments
+///
+/// * `metrics` - The application metrics.
+/// * `req` - The incoming HTTP request.
+/// * `next` - The next middleware in the chain.
+///
+/// # Returns
+///
+/// The response of the next middleware, unchanged.
+pub async fn http_metrics_middleware(State(metrics): State<Metrics>, req: Request, next: Next) -> Response {
+    let route =
+        req.extensions().get::<MatchedPath>().map_or(UNMATCHED_ROUTE, MatchedPath::as_str).to_string();
+    let started = Instant::now();
+    let res = next.run(req).await;
+    metrics.http_requests.observe(&route, res.status().as_u16(), started.elapsed());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::StatusCode, middleware::from_fn_with_state, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_are_recorded_under_their_route_template() {
+        let metrics = Metrics::new();
+        let app = Router::new()
+            .route("/scans/{id}/tree", get(|| async { "[]" }))
+            .route("/healthz", get(|| async { StatusCode::SERVICE_UNAVAILABLE }))
+            .layer(from_fn_with_state(metrics.clone(), http_metrics_middleware));
+        for uri in [
+            "/scans/6f0c5a4e-0000-4000-8000-000000000001/tree",
+            "/scans/6f0c5a4e-0000-4000-8000-000000000002/tree",
+            "/healthz",
+            "/index.html",
+        ] {
+            app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        }
+
+        let text =
+            metrics.http_requests.to_prometheus("speicherwald_http_request_duration_seconds", "Latency");
+        assert!(text.contains(
+            "speicherwald_http_request_duration_seconds_count{route=\"/scans/{id}/tree\",status=\"2xx\"} 2\n"
+        ));
+        assert!(text.contains(
+            "speicherwald_http_request_duration_seconds_count{route=\"/healthz\",status=\"5xx\"} 1\n"
+        ));
+        assert!(text.contains(
+            "speicherwald_http_request_duration_seconds_count{route=\"unmatched\",status=\"4xx\"} 1\n"
+        ));
+        assert!(!text.contains("6f0c5a4e"));
+        assert_eq!(metrics.http_requests.summary().len(), 3);
+    }
+}
@@ -35,14 +35,16 @@ use std::net::{IpAddr, SocketAddr};
 /// # Examples
 ///
 /// ```rust
-/// use axum::http::HeaderMap;
-/// use std::net::IpAddr;
-/// 
+/// use axum::http::{HeaderMap, HeaderValue};
+/// use speicherwald::middleware::ip::extract_ip_from_headers;
+/// use std::net::{IpAddr, Ipv4Addr};
+///
 /// let mut headers = HeaderMap::new();
-/// headers.insert("x-forwarded-for", "203.0.113.1, 10.0.0.1");
-/// 
+/// headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.1, 10.0.0.1"));
+///
+/// // The rightmost address is the one the last proxy added
 /// let client_ip = extract_ip_from_headers(&headers, None);
-/// assert_eq!(client_ip, IpAddr::V4(203, 0, 113, 1));
+/// assert_eq!(client_ip, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
 /// ```
 pub fn extract_ip_from_headers(headers: &HeaderMap, fallback: Option<IpAddr>) -> IpAddr {
     // FIX Bug #1: Prioritize X-Real-IP as it's often set by the immediate proxy
@@ -57,7 +59,7 @@ pub fn extract_ip_from_headers(headers: &HeaderMap, fallback: Option<IpAddr>) ->
     // in a single reverse-proxy setup than the leftmost (which can be spoofed by the client).
     // Note: In complex multi-proxy chains, this requires the last proxy to be trusted.
     if let Some(h) = headers.get("x-forwarded-for").and_then(|hv| hv.to_str().ok()) {
-        if let Some(last) = h.split(',').last() {
+        if let Some(last) = h.split(',').next_back() {
             if let Ok(ip) = last.trim().parse::<IpAddr>() {
                 return ip;
             }
@@ -86,8 +88,8 @@ pub fn extract_ip_from_headers(headers: &HeaderMap, fallback: Option<IpAddr>) ->
 /// # Examples
 ///
 /// ```rust
-/// use axum::{extract::Request, middleware::Next, response::Response};
-/// 
+/// use speicherwald::middleware::ip::MaybeRemoteAddr;
+///
 /// async fn handler(addr: MaybeRemoteAddr) -> String {
 ///     match addr.0 {
 ///         Some(socket_addr) => format!("Connected from {}", socket_addr),
@@ -118,15 +120,10 @@ where
     ///
     /// `Ok(Self)` containing `Some(SocketAddr)` if extraction succeeds,
     /// or `None` if connection information is not available
-    fn from_request_parts(
-        parts: &mut Parts,
-        state: &S,
-    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
-        async move {
-            match ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await {
-                Ok(ConnectInfo(addr)) => Ok(MaybeRemoteAddr(Some(addr))),
-                Err(_) => Ok(MaybeRemoteAddr(None)),
-            }
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await {
+            Ok(ConnectInfo(addr)) => Ok(MaybeRemoteAddr(Some(addr))),
+            Err(_) => Ok(MaybeRemoteAddr(None)),
         }
     }
 }
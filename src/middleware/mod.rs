@@ -6,10 +6,13 @@
 //! comprehensive request processing pipeline.
 
 pub mod auth;
+pub mod http_metrics;
 pub mod ip;
+pub mod namespace;
 pub mod rate_limit;
+pub mod request_id;
 pub mod security_headers;
+pub mod share;
 pub mod validation;
-pub mod csrf; // FIX Bug #30: CSRF protection
 
 pub use rate_limit::EndpointRateLimiter;
@@ -0,0 +1,184 @@
+//! Namespace separation of scans within a single database.
+//!
+//! Every request belongs to exactly one namespace. A namespace-bound auth token
+//! (see [`crate::middleware::auth`]) pins the namespace; otherwise it is taken
+//! from the `X-Speicherwald-Namespace` header, and requests without either use
+//! `"default"`, which preserves the single-tenant behavior.
+//!
+//! Scans and schedules are stamped with the namespace they were created in and
+//! are invisible from all other namespaces. The admin namespace (configured via
+//! `SPEICHERWALD_ADMIN_NAMESPACE`, default `"admin"`) sees everything.
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+
+/// The request header that selects the namespace.
+pub const NAMESPACE_HEADER: &str = "x-speicherwald-namespace";
+
+/// The namespace used when a request does not name one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// The maximum length of a namespace name.
+const MAX_NAMESPACE_LEN: usize = 64;
+
+/// The namespace a request operates in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace(String);
+
+impl Default for Namespace {
+    fn default() -> Self {
+        Namespace(DEFAULT_NAMESPACE.to_string())
+    }
+}
+
+impl Namespace {
+    /// Parses and validates a namespace name.
+    ///
+    /// Names are 1 to 64 characters of ASCII letters, digits, `-`, `_` and `.`.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The namespace name, surrounding whitespace is ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `AppResult<Self>` - The namespace, or `BadRequest` if the name is invalid.
+    pub fn parse(raw: &str) -> AppResult<Self> {
+        let name = raw.trim();
+        let valid = !name.is_empty()
+            && name.len() <= MAX_NAMESPACE_LEN
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(AppError::BadRequest(format!(
+                "invalid namespace (1-{} characters of A-Z, a-z, 0-9, '-', '_', '.')",
+                MAX_NAMESPACE_LEN
+            )));
+        }
+        Ok(Namespace(name.to_string()))
+    }
+
+    /// Returns the namespace name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns whether this is the admin namespace, which can see all scans.
+    pub fn is_admin(&self) -> bool {
+        self.0 == admin_namespace()
+    }
+
+    /// Checks that a scan exists and is visible from this namespace.
+    ///
+    /// Every scan-scoped handler calls this before touching scan data, so scans
+    /// of other namespaces behave exactly like scans that do not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database pool to query.
+    /// * `id` - The ID of the scan.
+    ///
+    /// # Returns
+    ///
+    /// * `AppResult<()>` - `NotFound` if the scan is missing or belongs to another namespace.
+    pub async fn ensure_scan(&self, pool: &sqlx::SqlitePool, id: Uuid) -> AppResult<()> {
+        let visible: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM scans WHERE id = ?1 AND (?2 OR namespace = ?3)")
+                .bind(id.to_string())
+                .bind(self.is_admin())
+                .bind(self.as_str())
+                .fetch_optional(pool)
+                .await?;
+        match visible {
+            Some(_) => Ok(()),
+            None => Err(AppError::NotFound("scan not found".into())),
+        }
+    }
+}
+
+/// Returns the name of the admin namespace.
+fn admin_namespace() -> &'static str {
+    static ADMIN: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    ADMIN.get_or_init(|| {
+        std::env::var("SPEICHERWALD_ADMIN_NAMESPACE")
+            .ok()
+            .and_then(|v| Namespace::parse(&v).ok())
+            .map(|ns| ns.0)
+            .unwrap_or_else(|| "admin".to_string())
+    })
+}
+
+impl<S> FromRequestParts<S> for Namespace
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Resolves the namespace of a request.
+    ///
+    /// A namespace inserted by the auth middleware for a namespace-bound token
+    /// takes precedence over the header.
+    ///
+    /// # Arguments
+    ///
+    /// * `parts` - The request parts containing headers and extensions
+    /// * `state` - The request state
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Self)` with the resolved namespace, or `BadRequest` if the header is invalid
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        let resolved = match parts.extensions.get::<Namespace>() {
+            Some(ns) => Ok(ns.clone()),
+            None => match parts.headers.get(NAMESPACE_HEADER) {
+                Some(v) => v
+                    .to_str()
+                    .map_err(|_| AppError::BadRequest("invalid namespace header".into()))
+                    .and_then(Namespace::parse),
+                None => Ok(Namespace::default()),
+            },
+        };
+        async move { resolved }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn resolve(req: Request<()>) -> AppResult<Namespace> {
+        let (mut parts, _) = req.into_parts();
+        Namespace::from_request_parts(&mut parts, &()).await
+    }
+
+    #[test]
+    fn parse_validates_names() {
+        assert_eq!(Namespace::parse(" finance ").unwrap().as_str(), "finance");
+        assert!(Namespace::parse("team-a_1.x").is_ok());
+        assert!(Namespace::parse("").is_err());
+        assert!(Namespace::parse("a b").is_err());
+        assert!(Namespace::parse("../etc").is_err());
+        assert!(Namespace::parse(&"x".repeat(65)).is_err());
+    }
+
+    #[tokio::test]
+    async fn header_selects_namespace_and_defaults_apply() {
+        let ns = resolve(Request::builder().header(NAMESPACE_HEADER, "hr").body(()).unwrap()).await.unwrap();
+        assert_eq!(ns.as_str(), "hr");
+        let ns = resolve(Request::builder().body(()).unwrap()).await.unwrap();
+        assert_eq!(ns, Namespace::default());
+        assert!(resolve(Request::builder().header(NAMESPACE_HEADER, "a/b").body(()).unwrap()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn token_bound_namespace_wins_over_header() {
+        let mut req = Request::builder().header(NAMESPACE_HEADER, "admin").body(()).unwrap();
+        req.extensions_mut().insert(Namespace::parse("hr").unwrap());
+        assert_eq!(resolve(req).await.unwrap().as_str(), "hr");
+    }
+}
@@ -2,13 +2,15 @@
 //!
 //! This module provides thread-safe rate limiting functionality using a sliding window
 //! algorithm. It supports both global rate limiting and per-endpoint rate limiting
-//! with configurable windows and request thresholds.
+//! with configurable windows and request thresholds. Per-endpoint limits are keyed
+//! by route patterns, so parametrized routes like `/scans/:id/events` can be limited.
 
 // FIX Bug #20: Removed dead_code annotation
 use super::ip::extract_ip_from_headers;
+use crate::config::RateLimitConfig;
 use axum::{
-    extract::{connect_info::ConnectInfo, Request},
-    http::StatusCode,
+    extract::{connect_info::ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
@@ -22,6 +24,42 @@ use std::{
 };
 use tokio::sync::RwLock;
 
+/// The rejection returned when a client exceeded a rate limit.
+///
+/// Converts into a `429 Too Many Requests` response with a `Retry-After`
+/// header and the same delay in the JSON body.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimited {
+    /// How long the client has to wait before the next request is allowed.
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// Returns the delay in whole seconds, rounded up and at least 1 so clients
+    /// never retry before the window has moved on.
+    pub fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+        secs.max(1)
+    }
+}
+
+impl IntoResponse for RateLimited {
+    fn into_response(self) -> Response {
+        let secs = self.retry_after_secs();
+        let body = Json(json!({
+            "error": {
+                "code": "RATE_LIMITED",
+                "message": format!("Too many requests. Please retry after {} seconds", secs),
+            },
+            "retry_after_seconds": secs,
+            "status": 429,
+        }));
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, body).into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        response
+    }
+}
+
 /// A thread-safe rate limiter based on the sliding window algorithm.
 ///
 /// This implementation tracks request timestamps per IP address and enforces
@@ -70,8 +108,8 @@ impl RateLimiter {
     /// # Returns
     ///
     /// * `Ok(())` if the request is allowed and has been recorded
-    /// * `Err((StatusCode, Json))` with HTTP 429 status and retry information if rate limited
-    pub async fn check_rate_limit(&self, ip: IpAddr) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    /// * `Err(RateLimited)` with the time until the next request is allowed if rate limited
+    pub async fn check_rate_limit(&self, ip: IpAddr) -> Result<(), RateLimited> {
         let now = Instant::now();
         let mut requests = self.requests.write().await;
 
@@ -96,17 +134,7 @@ impl RateLimiter {
                 Duration::from_secs(1)
             };
 
-            return Err((
-                StatusCode::TOO_MANY_REQUESTS,
-                Json(json!({
-                    "error": {
-                        "code": "RATE_LIMITED",
-                        "message": format!("Too many requests. Please retry after {} seconds", retry_after.as_secs()),
-                    },
-                    "retry_after_seconds": retry_after.as_secs(),
-                    "status": 429,
-                })),
-            ));
+            return Err(RateLimited { retry_after });
         }
 
         // Add current timestamp
@@ -200,18 +228,61 @@ pub async fn rate_limit_middleware(req: Request, next: Next) -> Response {
 
     match limiter.check_rate_limit(ip).await {
         Ok(()) => next.run(req).await,
-        Err((status, body)) => (status, body).into_response(),
+        Err(limited) => limited.into_response(),
+    }
+}
+
+/// Normalizes a route pattern so `{id}` and `:id` placeholders compare equal.
+///
+/// Trailing slashes are dropped, so `/scans/` and `/scans` share a limit.
+fn normalize_pattern(pattern: &str) -> String {
+    let segments: Vec<String> = pattern
+        .trim()
+        .trim_end_matches('/')
+        .split('/')
+        .map(|seg| match seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => format!(":{}", name.trim_start_matches('*')),
+            None => seg.to_string(),
+        })
+        .collect();
+    let joined = segments.join("/");
+    if joined.is_empty() {
+        "/".to_string()
+    } else {
+        joined
+    }
+}
+
+/// Returns whether a normalized route pattern matches a path.
+///
+/// A `:name` segment matches any single non-empty segment; all other segments
+/// must be equal. The path may be a concrete request path or a route template.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some(p), Some(s)) if p.starts_with(':') => {
+                if s.is_empty() {
+                    return false;
+                }
+            }
+            (Some(p), Some(s)) if p == s => {}
+            _ => return false,
+        }
     }
 }
 
 /// A manager for per-endpoint rate limiters.
 ///
 /// This struct maintains a collection of `RateLimiter` instances, each associated
-/// with a specific API endpoint. This allows different endpoints to have different
-/// rate limiting policies based on their resource requirements and usage patterns.
+/// with a route pattern such as `/scans` or `/scans/:id/events`. This allows
+/// different endpoints to have different rate limiting policies based on their
+/// resource requirements and usage patterns.
 #[derive(Clone)]
 pub struct EndpointRateLimiter {
-    /// Map of endpoint paths to their respective rate limiters
+    /// Map of normalized route patterns to their respective rate limiters
     limiters: Arc<RwLock<HashMap<String, RateLimiter>>>,
 }
 
@@ -231,6 +302,21 @@ impl EndpointRateLimiter {
         Self { limiters: Arc::new(RwLock::new(HashMap::new())) }
     }
 
+    /// Creates an `EndpointRateLimiter` from the `[[rate_limits]]` configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The configured route patterns and their limits
+    ///
+    /// # Returns
+    ///
+    /// A new `EndpointRateLimiter` with one limiter per configured pattern
+    pub fn from_config(limits: &[RateLimitConfig]) -> Self {
+        Self::new().with_limits(
+            limits.iter().map(|l| (l.pattern.as_str(), l.max_requests, l.window_secs)).collect(),
+        )
+    }
+
     /// Configures the rate limiter with a set of endpoint-specific limits.
     ///
     /// This method extends the existing limits rather than replacing them. If an endpoint
@@ -239,7 +325,7 @@ impl EndpointRateLimiter {
     /// # Arguments
     ///
     /// * `limits` - A vector of tuples, where each tuple contains:
-    ///   - The endpoint path (e.g., "/scans", "/search")
+    ///   - The route pattern (e.g., "/scans", "/scans/:id/events" or "/scans/{id}/events")
     ///   - The maximum number of requests allowed per time window
     ///   - The time window duration in seconds
     ///
@@ -255,40 +341,73 @@ impl EndpointRateLimiter {
                 .map(|guard| guard.clone())
                 .unwrap_or_else(|_| HashMap::new()),
         };
-        
+
         // Add/update new limits
         for (endpoint, max_requests, window_seconds) in limits {
-            limiters_map.insert(endpoint.to_string(), RateLimiter::new(max_requests, window_seconds));
+            limiters_map.insert(normalize_pattern(endpoint), RateLimiter::new(max_requests, window_seconds));
         }
-        
+
         Self {
             limiters: Arc::new(RwLock::new(limiters_map))
         }
     }
 
+    /// Replaces the configured limits, e.g. after a configuration reload.
+    ///
+    /// Patterns whose limit is unchanged keep their request history, so a
+    /// reload does not hand out a fresh quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The new route patterns and their limits
+    pub async fn reload(&self, limits: &[RateLimitConfig]) {
+        let mut limiters = self.limiters.write().await;
+        let mut next = HashMap::with_capacity(limits.len());
+        for l in limits {
+            let pattern = normalize_pattern(&l.pattern);
+            let window = Duration::from_secs(l.window_secs);
+            let limiter = match limiters.remove(&pattern) {
+                Some(old) if old.max_requests == l.max_requests && old.window == window => old,
+                _ => RateLimiter::new(l.max_requests, l.window_secs),
+            };
+            next.insert(pattern, limiter);
+        }
+        *limiters = next;
+    }
+
     /// Checks if a request to a specific endpoint from a given IP address is allowed.
     ///
+    /// The endpoint is looked up as a pattern first; otherwise the pattern
+    /// matching it with the fewest placeholders applies, so a static route
+    /// wins over a parametrized one.
+    ///
     /// # Arguments
     ///
-    /// * `endpoint` - The path of the endpoint being accessed (e.g., "/scans")
+    /// * `endpoint` - The route pattern or concrete path being accessed
+    ///   (e.g., "/scans", "/scans/:id/events" or "/scans/42/events")
     /// * `ip` - The IP address of the client making the request
     ///
     /// # Returns
     ///
     /// * `Ok(())` if the request is allowed
-    /// * `Err((StatusCode, Json))` with HTTP 429 status if rate limited
-    pub async fn check_endpoint_limit(
-        &self,
-        endpoint: &str,
-        ip: IpAddr,
-    ) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
-        let limiters = self.limiters.read().await;
-
-        if let Some(limiter) = limiters.get(endpoint) {
-            limiter.check_rate_limit(ip).await
-        } else {
+    /// * `Err(RateLimited)` with HTTP 429 retry information if rate limited
+    pub async fn check_endpoint_limit(&self, endpoint: &str, ip: IpAddr) -> Result<(), RateLimited> {
+        let endpoint = normalize_pattern(endpoint);
+        let limiter = {
+            let limiters = self.limiters.read().await;
+            limiters.get(&endpoint).cloned().or_else(|| {
+                limiters
+                    .iter()
+                    .filter(|(pattern, _)| pattern_matches(pattern, &endpoint))
+                    .min_by_key(|(pattern, _)| (pattern.matches("/:").count(), pattern.as_str()))
+                    .map(|(_, limiter)| limiter.clone())
+            })
+        };
+
+        match limiter {
+            Some(limiter) => limiter.check_rate_limit(ip).await,
             // No specific limit for this endpoint
-            Ok(())
+            None => Ok(()),
         }
     }
 
@@ -309,20 +428,37 @@ impl EndpointRateLimiter {
     }
 }
 
-/// A background task that periodically cleans up old entries from a `RateLimiter`.
+/// An Axum middleware enforcing the per-endpoint limit of the matched route.
 ///
-/// This function runs in a loop, triggering cleanup at regular intervals to
-/// maintain reasonable memory usage by removing expired request timestamps.
+/// Layered onto individual routes, it looks up the route template from
+/// [`MatchedPath`] (falling back to the request path) so limits configured for
+/// parametrized patterns like `/scans/:id/events` apply to every scan ID.
 ///
 /// # Arguments
 ///
-/// * `limiter` - The `RateLimiter` to clean up
-pub async fn cleanup_task(limiter: RateLimiter) {
-    let mut interval = tokio::time::interval(Duration::from_secs(300)); // Clean every 5 minutes
+/// * `limiter` - The per-endpoint limiters
+/// * `req` - The incoming HTTP request
+/// * `next` - The next middleware in the chain
+///
+/// # Returns
+///
+/// * `Response` - The response from the next middleware, or a `429 Too Many Requests`
+///   response with a `Retry-After` header if the client is rate-limited
+pub async fn endpoint_rate_limit_middleware(
+    State(limiter): State<EndpointRateLimiter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let remote_ip = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+    let ip = extract_ip_from_headers(req.headers(), remote_ip);
+    let endpoint = match req.extensions().get::<MatchedPath>() {
+        Some(matched) => matched.as_str().to_string(),
+        None => req.uri().path().to_string(),
+    };
 
-    loop {
-        interval.tick().await;
-        limiter.cleanup_old_entries().await;
+    match limiter.check_endpoint_limit(&endpoint, ip).await {
+        Ok(()) => next.run(req).await,
+        Err(limited) => limited.into_response(),
     }
 }
 
@@ -364,4 +500,82 @@ mod tests {
         assert!(limiter.check_rate_limit(ip1).await.is_err());
         assert!(limiter.check_rate_limit(ip2).await.is_err());
     }
+
+    #[test]
+    fn patterns_match_placeholders_in_either_syntax() {
+        assert_eq!(normalize_pattern("/scans/{id}/events"), "/scans/:id/events");
+        assert_eq!(normalize_pattern("/scans/"), "/scans");
+        assert!(pattern_matches("/scans/:id/events", "/scans/6f1c/events"));
+        assert!(pattern_matches("/scans/:id/events", "/scans/:id/events"));
+        assert!(!pattern_matches("/scans/:id/events", "/scans//events"));
+        assert!(!pattern_matches("/scans/:id/events", "/scans/6f1c/tree"));
+        assert!(!pattern_matches("/scans/:id", "/scans/6f1c/events"));
+    }
+
+    #[tokio::test]
+    async fn static_patterns_win_over_placeholders() {
+        let limiter = EndpointRateLimiter::new().with_limits(vec![("/scans/:id", 1, 60), ("/scans/retention", 5, 60)]);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        for _ in 0..5 {
+            assert!(limiter.check_endpoint_limit("/scans/retention", ip).await.is_ok());
+        }
+        assert!(limiter.check_endpoint_limit("/scans/retention", ip).await.is_err());
+        assert!(limiter.check_endpoint_limit("/scans/abc", ip).await.is_ok());
+        assert!(limiter.check_endpoint_limit("/scans/def", ip).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn parametrized_routes_are_limited_with_retry_after() {
+        use axum::{body::Body, routing::get, Router};
+        use tower::ServiceExt;
+
+        let limiter = EndpointRateLimiter::from_config(&[RateLimitConfig {
+            pattern: "/scans/:id/events".into(),
+            max_requests: 3,
+            window_secs: 30,
+        }]);
+        let app = Router::new()
+            .route("/scans/{id}/events", get(|| async { "ok" }))
+            .route("/scans/{id}/tree", get(|| async { "ok" }))
+            .route_layer(axum::middleware::from_fn_with_state(limiter, endpoint_rate_limit_middleware));
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        // Different scan IDs share the limit of the pattern
+        for id in ["a", "b", "c"] {
+            let res = app.clone().oneshot(get(&format!("/scans/{}/events", id))).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        for id in ["a", "d"] {
+            let res = app.clone().oneshot(get(&format!("/scans/{}/events", id))).await.unwrap();
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            let retry_after: u64 =
+                res.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+            assert!((29..=30).contains(&retry_after), "retry_after = {}", retry_after);
+        }
+
+        // Other routes have no limit configured
+        for _ in 0..5 {
+            let res = app.clone().oneshot(get("/scans/a/tree")).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        // Clients behind other addresses are counted separately
+        let other = Request::builder()
+            .uri("/scans/a/events")
+            .header("x-forwarded-for", "10.0.0.7")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(app.clone().oneshot(other).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        let limited = RateLimited { retry_after: Duration::from_millis(1500) };
+        assert_eq!(limited.retry_after_secs(), 2);
+        let limited = RateLimited { retry_after: Duration::ZERO };
+        assert_eq!(limited.retry_after_secs(), 1);
+        let res = limited.into_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[header::RETRY_AFTER], "1");
+    }
 }
@@ -0,0 +1,102 @@
+//! Request IDs for correlating log records and error responses.
+//!
+//! Every request gets an ID, taken from a well-formed `X-Request-Id` header
+//! (e.g. set by a reverse proxy) or generated. The request is handled inside a
+//! `request` span with the ID as its `request_id` field, so every log record
+//! written while handling it carries the ID. Error responses include it as
+//! `request_id` and every response returns it in `X-Request-Id`.
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+/// The header the ID is read from and returned in.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The longest incoming ID that is taken over.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the ID of the request being handled, `None` outside of requests.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Returns the incoming ID if it is short and only made of safe characters.
+fn incoming(headers: &HeaderMap) -> Option<String> {
+    let id = headers.get(&REQUEST_ID_HEADER)?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'));
+    valid.then(|| id.to_string())
+}
+
+/// Assigns an ID to the request and handles it in a span carrying that ID.
+///
+/// # Arguments
+///
+/// * `req` - The incoming HTTP request.
+/// * `next` - The next middleware in the chain.
+///
+/// # Returns
+///
+/// The response with the ID in the `X-Request-Id` header.
+pub async fn request_id_middleware(req: Request, next: Next) -> Response {
+    let id = incoming(req.headers()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span =
+        tracing::info_span!("request", request_id = %id, method = %req.method(), path = %req.uri().path());
+    let mut res = REQUEST_ID.scope(id.clone(), next.run(req)).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware::from_fn, routing::get, Router};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::error::AppError;
+    use crate::logging::tests::json_subscriber;
+
+    async fn failing() -> Result<(), AppError> {
+        tracing::warn!("about to fail");
+        Err(AppError::NotFound("nothing here".into()))
+    }
+
+    #[tokio::test]
+    async fn ids_reach_logs_error_bodies_and_headers() {
+        let (subscriber, captured) = json_subscriber();
+        let _default = tracing::subscriber::set_default(subscriber);
+        let app = Router::new().route("/fail", get(failing)).layer(from_fn(request_id_middleware));
+
+        let res = app.clone().oneshot(Request::get("/fail").body(Body::empty()).unwrap()).await.unwrap();
+        let id = res.headers()[&REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok(), "{}", id);
+        let body: serde_json::Value =
+            serde_json::from_slice(&res.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        assert_eq!(body["request_id"], id.as_str());
+        let records = captured.records();
+        let record = records.iter().find(|r| r["fields"]["message"] == "about to fail").unwrap();
+        assert_eq!(record["span"]["request_id"], id.as_str());
+
+        // Well-formed IDs of a proxy are kept, others replaced
+        let req = Request::get("/fail").header("x-request-id", "proxy-42").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.headers()[&REQUEST_ID_HEADER], "proxy-42");
+        let req = Request::get("/fail").header("x-request-id", "a b").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_ne!(res.headers()[&REQUEST_ID_HEADER], "a b");
+        assert_eq!(current(), None);
+    }
+}
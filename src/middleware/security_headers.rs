@@ -5,16 +5,14 @@
 //! data injection attacks. It also handles appropriate caching policies for different
 //! content types.
 
-use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, PRAGMA};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, PRAGMA};
 use axum::{
     extract::{Request, State},
     http::{HeaderName, HeaderValue},
     middleware::Next,
     response::Response,
 };
-use std::sync::Arc;
-
-use crate::config::AppConfig;
+use crate::config::SharedConfig;
 
 /// Adds standard security-related HTTP headers to all responses.
 ///
@@ -41,7 +39,8 @@ use crate::config::AppConfig;
 ///
 /// # Arguments
 ///
-/// * `State(cfg)` - The application configuration containing security settings
+/// * `State(cfg)` - The application configuration containing security settings, read per request
+///   so a reload applies to the next response
 /// * `req` - The incoming HTTP request
 /// * `next` - The next middleware in the chain
 ///
@@ -49,10 +48,11 @@ use crate::config::AppConfig;
 ///
 /// The response with security headers and appropriate caching policies applied
 pub async fn security_headers_middleware(
-    State(cfg): State<Arc<AppConfig>>,
+    State(cfg): State<SharedConfig>,
     req: Request,
     next: Next,
 ) -> Response {
+    let cfg = cfg.get();
     let mut res = next.run(req).await;
     let headers = res.headers_mut();
 
@@ -113,7 +113,10 @@ pub async fn security_headers_middleware(
     if let Some(s) = ct_val.as_deref() {
         let is_json = s.starts_with("application/json");
         let is_sse = s.starts_with("text/event-stream");
-        if is_json || is_sse {
+        if is_json && headers.contains_key(ETAG) {
+            // Responses with an ETag may be stored, but must be revalidated on every use
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        } else if is_json || is_sse {
             headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
             headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
             // Hint for reverse proxies not to buffer SSE
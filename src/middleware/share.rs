@@ -0,0 +1,159 @@
+//! Read-only access to a single scan through a share link.
+//!
+//! `POST /scans/{id}/share` stores a random token in `share_links`. A request
+//! that presents it as `?share=` may read the scan through the endpoints in
+//! [`SHARED_ENDPOINTS`] without an auth token; the scan's namespace is pinned
+//! for it, so the scan is visible whatever namespace the link was opened in.
+//! Every other request, and a token presented for another scan, gets no
+//! access from the link and is left to [`crate::middleware::auth`].
+
+use axum::{
+    extract::{Query, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    error::AppResult,
+    middleware::{auth::tokens_match, namespace::Namespace},
+    state::AppState,
+};
+
+/// The endpoints below `/scans/{id}/` a share link opens, for `GET` requests only.
+pub const SHARED_ENDPOINTS: &[&str] = &["tree", "top", "list", "export", "statistics"];
+
+/// Marks a request that a share link authorized; the auth middleware lets it pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedScan(pub Uuid);
+
+/// A stored share link that matched a token.
+#[derive(Debug)]
+pub struct ShareMatch {
+    /// The row ID of the link.
+    pub link_id: i64,
+    /// The namespace of the shared scan.
+    pub namespace: String,
+}
+
+/// Middleware that admits `GET` requests to the shared endpoints of a scan with a valid `?share=` token.
+///
+/// The request is marked with [`SharedScan`] and pinned to the scan's
+/// namespace. Requests without a token, with an unknown or expired one or to
+/// any other endpoint pass through unchanged.
+pub async fn share_middleware(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    if let Some((id, token)) = shared_request(&req) {
+        match find_link(state.read_pool(), id, &token, true).await {
+            Ok(Some(link)) => match Namespace::parse(&link.namespace) {
+                Ok(ns) => {
+                    req.extensions_mut().insert(SharedScan(id));
+                    req.extensions_mut().insert(ns);
+                }
+                Err(_) => tracing::warn!("Scan {} has an invalid namespace {:?}", id, link.namespace),
+            },
+            Ok(None) => tracing::debug!("Share token rejected for scan {}", id),
+            Err(e) => tracing::warn!("Failed to check a share token for scan {}: {}", id, e),
+        }
+    }
+    next.run(req).await
+}
+
+/// The scan and token of a `GET` request to a shared endpoint that carries `?share=`.
+fn shared_request(req: &Request) -> Option<(Uuid, String)> {
+    if req.method() != Method::GET {
+        return None;
+    }
+    let rest = req.uri().path().strip_prefix("/scans/")?;
+    let (id, endpoint) = rest.split_once('/')?;
+    if !SHARED_ENDPOINTS.contains(&endpoint) {
+        return None;
+    }
+    #[derive(serde::Deserialize)]
+    struct ShareQuery {
+        share: Option<String>,
+    }
+    let token = Query::<ShareQuery>::try_from_uri(req.uri()).ok()?.0.share.filter(|t| !t.is_empty())?;
+    Some((Uuid::parse_str(id).ok()?, token))
+}
+
+/// Finds the share link of a scan with the given token.
+///
+/// Every link of the scan is compared in constant time, so the time taken does
+/// not tell how much of the token matched.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `id` - The ID of the scan.
+/// * `token` - The presented token.
+/// * `unexpired` - Whether only links that have not expired count.
+///
+/// # Returns
+///
+/// * `AppResult<Option<ShareMatch>>` - The matching link, if any.
+pub async fn find_link(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    token: &str,
+    unexpired: bool,
+) -> AppResult<Option<ShareMatch>> {
+    let rows = sqlx::query(
+        "SELECT l.id, l.token, s.namespace FROM share_links l JOIN scans s ON s.id = l.scan_id \
+         WHERE l.scan_id=?1 AND (NOT ?2 OR l.expires_at IS NULL \
+           OR l.expires_at > strftime('%Y-%m-%dT%H:%M:%SZ','now'))",
+    )
+    .bind(id.to_string())
+    .bind(unexpired)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().fold(None, |hit, r| {
+        if tokens_match(token, r.get::<&str, _>("token")) && hit.is_none() {
+            Some(ShareMatch { link_id: r.get("id"), namespace: r.get("namespace") })
+        } else {
+            hit
+        }
+    }))
+}
+
+/// Deletes the share links that have expired.
+///
+/// # Returns
+///
+/// * `AppResult<u64>` - The number of deleted links.
+pub async fn purge_expired(pool: &sqlx::SqlitePool) -> AppResult<u64> {
+    let res = sqlx::query(
+        "DELETE FROM share_links WHERE expires_at IS NOT NULL \
+         AND expires_at <= strftime('%Y-%m-%dT%H:%M:%SZ','now')",
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, uri: &str) -> Request {
+        Request::builder().method(method).uri(uri).body(axum::body::Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn only_get_requests_to_shared_endpoints_carry_a_share() {
+        let id = Uuid::new_v4();
+        let shared = |method: Method, uri: String| shared_request(&request(method, &uri));
+        assert_eq!(shared(Method::GET, format!("/scans/{}/tree?share=abc", id)), Some((id, "abc".into())));
+        assert_eq!(
+            shared(Method::GET, format!("/scans/{}/statistics?limit=5&share=abc", id)),
+            Some((id, "abc".into()))
+        );
+        assert_eq!(shared(Method::DELETE, format!("/scans/{}/tree?share=abc", id)), None);
+        assert_eq!(shared(Method::GET, format!("/scans/{}?share=abc", id)), None);
+        assert_eq!(shared(Method::GET, format!("/scans/{}/move?share=abc", id)), None);
+        assert_eq!(shared(Method::GET, format!("/scans/{}/tree/x?share=abc", id)), None);
+        assert_eq!(shared(Method::GET, format!("/scans/{}/tree?share=", id)), None);
+        assert_eq!(shared(Method::GET, "/scans/not-a-uuid/tree?share=abc".into()), None);
+    }
+}
@@ -3,19 +3,25 @@
 //! This module provides comprehensive validation middleware and utility functions to
 //! protect against common security vulnerabilities including path traversal attacks,
 //! injection attempts, and malformed requests. It includes validation for UUIDs,
-//! file paths, scan options, and user input sanitization.
+//! file paths, scan options, and user input sanitization. Request bodies are
+//! read with [`ValidJson`], which names the offending field when a body does
+//! not match its DTO.
 
 #![allow(dead_code)]
 use axum::{
-    extract::Request,
-    http::StatusCode,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use serde::de::DeserializeOwned;
 use serde_json::json;
 use uuid::Uuid;
 
+use crate::error::AppError;
+
 /// An Axum middleware that validates incoming requests for common security issues.
 ///
 /// This middleware provides early validation of HTTP requests to detect and block
@@ -27,7 +33,8 @@ use uuid::Uuid;
 ///   intended directories using patterns like `../`, encoded variants, and null bytes
 /// - **Suspicious User Agent Detection**: Flags known security scanners and malicious bots
 /// - **Content Length Validation**: Enforces maximum request body size to prevent
-///   denial of service attacks via large payloads
+///   denial of service attacks via large payloads (except for backup restores,
+///   see [`crate::backup::MAX_RESTORE_BYTES`])
 ///
 /// # Arguments
 ///
@@ -67,7 +74,12 @@ pub async fn validate_request_middleware(req: Request, next: Next) -> Response {
 
     // Check content length for POST/PUT requests
     // This is redundant with DefaultBodyLimit but provides early rejection
-    if matches!(req.method(), &axum::http::Method::POST | &axum::http::Method::PUT) {
+    // Backup uploads are streamed to disk and checked against their own limit,
+    // scan imports are streamed into the database
+    if matches!(req.method(), &axum::http::Method::POST | &axum::http::Method::PUT)
+        && uri_path != crate::backup::RESTORE_PATH
+        && !uri_path.starts_with(crate::import::IMPORT_PATH_PREFIX)
+    {
         if let Some(content_length) = req.headers().get("content-length") {
             if let Ok(length_str) = content_length.to_str() {
                 if let Ok(length) = length_str.parse::<usize>() {
@@ -398,6 +410,65 @@ pub fn validate_scan_options(
     Ok(())
 }
 
+/// A JSON request body extractor whose rejections are [`AppError::InvalidInput`].
+///
+/// Unlike [`axum::Json`], a body that does not match the DTO is answered with
+/// the usual JSON error shape, and the message names the path of the
+/// offending field and what was expected there, e.g.
+/// `max_depth: invalid type: string "three", expected u32` and its position in the body.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(req.headers()) {
+            let message = "expected a JSON body with Content-Type: application/json";
+            return Err(AppError::InvalidInput(message.into()));
+        }
+        let bytes = Bytes::from_request(req, state).await.map_err(|e| AppError::BadRequest(e.body_text()))?;
+        parse_json_body(&bytes).map(ValidJson)
+    }
+}
+
+/// Whether the request declares a JSON body, as `application/json` or an `application/*+json` type.
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(value) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let essence = value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence.strip_prefix("application/").is_some_and(|sub| sub == "json" || sub.ends_with("+json"))
+}
+
+/// Deserializes a JSON request body, reporting the path of the field that failed.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw request body.
+///
+/// # Returns
+///
+/// * `Result<T, AppError>` - The DTO, or `InvalidInput` describing the first mismatch.
+pub fn parse_json_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, AppError> {
+    let mut de = serde_json::Deserializer::from_slice(bytes);
+    let value = serde_path_to_error::deserialize(&mut de).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.into_inner();
+        AppError::InvalidInput(if inner.is_data() && path != "." {
+            format!("invalid request body: {}: {}", path, inner)
+        } else {
+            format!("invalid request body: {}", inner)
+        })
+    })?;
+    de.end().map_err(|e| AppError::InvalidInput(format!("invalid request body: {}", e)))?;
+    Ok(value)
+}
+
 /// Sanitizes user input for logging purposes.
 ///
 /// This function processes user input to make it safe for logging by:
@@ -427,6 +498,70 @@ pub fn sanitize_for_logging(input: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{CreateScanRequest, CreateScheduleRequest, MoveBatchRequest};
+
+    /// The status and error message `ValidJson` answers a body with.
+    async fn reject<T: DeserializeOwned>(content_type: &str, body: &str) -> (StatusCode, String) {
+        let req = Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, content_type)
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let err = ValidJson::<T>::from_request(req, &()).await.err().expect("the body was accepted");
+        let res = err.into_response();
+        let status = res.status();
+        let bytes = http_body_util::BodyExt::collect(res.into_body()).await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["code"], "INVALID_INPUT");
+        (status, body["error"]["message"].as_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn request_bodies_name_the_offending_field() {
+        let json = "application/json";
+        let (status, message) =
+            reject::<CreateScanRequest>(json, r#"{"root_paths":["/data"],"folow_symlinks":true}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("unknown field `folow_symlinks`"), "{}", message);
+        let (_, message) =
+            reject::<CreateScanRequest>(json, r#"{"root_paths":["/data"],"max_depth":"three"}"#).await;
+        assert!(message.contains("max_depth: invalid type: string \"three\", expected u32"), "{}", message);
+        let (_, message) = reject::<CreateScanRequest>(json, r#"{"include_hidden":true}"#).await;
+        assert!(message.contains("missing field `root_paths`"), "{}", message);
+        // Nested fields are named with their full path
+        let (_, message) =
+            reject::<MoveBatchRequest>(json, r#"{"items":[{"source":"/a","destination":7}]}"#).await;
+        assert!(message.contains("items[0].destination: invalid type: integer `7`"), "{}", message);
+        let (_, message) = reject::<CreateScanRequest>(json, r#"{"root_paths":[]} x"#).await;
+        assert!(message.contains("trailing characters"), "{}", message);
+        let (_, message) = reject::<CreateScanRequest>("text/plain", r#"{"root_paths":[]}"#).await;
+        assert!(message.contains("Content-Type: application/json"), "{}", message);
+
+        let ok = |body: &str| parse_json_body::<CreateScheduleRequest>(body.as_bytes());
+        // The scan request flattened into a schedule still takes the schedule's own fields
+        let schedule = ok(r#"{"root_paths":["/data"],"interval_minutes":60}"#).unwrap();
+        assert_eq!((schedule.scan.root_paths.len(), schedule.interval_minutes), (1, Some(60)));
+        // and still rejects unknown fields
+        let (status, message) = reject::<CreateScheduleRequest>(
+            json,
+            r#"{"root_paths":["/d"],"interval_minutes":60,"folow_symlinks":true}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("unknown field `folow_symlinks`"), "{}", message);
+        let (_, message) = reject::<CreateScheduleRequest>(
+            json,
+            r#"{"root_paths":["/d"],"cron":"* * * * *","max_depth":"x"}"#,
+        )
+        .await;
+        assert!(message.contains("max_depth: invalid type: string \"x\""), "{}", message);
+        let (_, message) = reject::<CreateScheduleRequest>(json, r#"{"root_paths":["/d"],"cron":7}"#).await;
+        assert!(message.contains("cron: invalid type: integer `7`"), "{}", message);
+        assert!(is_json_content_type(&HeaderMap::from_iter([(
+            CONTENT_TYPE,
+            "Application/merge-patch+JSON; charset=utf-8".parse().unwrap()
+        )])));
+    }
 
     #[test]
     fn test_path_traversal_detection() {
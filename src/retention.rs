@@ -0,0 +1,360 @@
+//! Automatic pruning of old scans.
+//!
+//! The policy lives in the `[retention]` config section. For every set of root
+//! paths (per namespace and origin) it keeps the newest `max_scans_per_root` finished scans
+//! and drops finished scans started more than `max_age_days` ago; the newest scan
+//! of a set of root paths is always kept. A background task (spawned in
+//! `main.rs`) calls [`prune`] every `interval_secs`, and `POST /scans/retention/run`
+//! calls it on demand.
+//!
+//! Running scans and unfinished imports are never considered. Scans that are watched or read by a
+//! running diff or export are skipped and picked up by a later run. Pruning
+//! deletes the `scans` row, which cascades to the nodes, files and warnings.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    config::RetentionConfig,
+    error::AppResult,
+    scheduler::format_timestamp,
+    state::AppState,
+    types::{RetentionCandidate, RetentionPolicy},
+};
+
+/// A finished scan as seen by the retention policy.
+#[derive(Debug, Clone)]
+pub struct ScanRecord {
+    /// The ID of the scan.
+    pub id: Uuid,
+    /// The namespace the scan belongs to.
+    pub namespace: String,
+    /// The status of the scan.
+    pub status: String,
+    /// The root paths of the scan.
+    pub root_paths: Vec<String>,
+    /// The server an imported scan came from, `None` for scans of this server.
+    pub origin: Option<String>,
+    /// The start time, formatted like SQLite's `strftime('%Y-%m-%dT%H:%M:%SZ')`.
+    pub started_at: String,
+    /// The allocated size of the scan's files, if recorded.
+    pub total_allocated_size: Option<i64>,
+}
+
+/// A scan selected by the policy.
+#[derive(Debug, Clone)]
+pub struct Selected {
+    /// The scan.
+    pub record: ScanRecord,
+    /// The limit the scan exceeds: `max_scans_per_root` or `max_age_days`.
+    pub reason: &'static str,
+    /// Why the scan is kept for now, if it is.
+    pub protected: Option<&'static str>,
+}
+
+impl Selected {
+    /// Converts the selection into its API representation.
+    pub fn to_dto(&self) -> RetentionCandidate {
+        RetentionCandidate {
+            scan_id: self.record.id,
+            status: self.record.status.clone(),
+            root_paths: self.record.root_paths.clone(),
+            started_at: self.record.started_at.clone(),
+            total_allocated_size: self.record.total_allocated_size,
+            reason: self.reason.to_string(),
+            protected: self.protected.map(str::to_string),
+        }
+    }
+}
+
+/// The outcome of a prune.
+#[derive(Debug, Default)]
+pub struct PruneOutcome {
+    /// The pruned scans.
+    pub deleted: Vec<Selected>,
+    /// The selected scans kept because they are running, watched or in use.
+    pub skipped: Vec<Selected>,
+    /// Whether `PRAGMA incremental_vacuum` ran afterwards.
+    pub vacuumed: bool,
+}
+
+/// Returns the API representation of a retention policy.
+pub fn policy_dto(cfg: &RetentionConfig) -> RetentionPolicy {
+    RetentionPolicy {
+        enabled: cfg.is_enabled(),
+        max_scans_per_root: cfg.max_scans_per_root,
+        max_age_days: cfg.max_age_days,
+        interval_secs: cfg.interval_secs,
+        incremental_vacuum: cfg.incremental_vacuum,
+    }
+}
+
+/// Selects the scans a policy prunes.
+///
+/// # Arguments
+///
+/// * `scans` - The finished scans.
+/// * `cfg` - The retention policy.
+/// * `now` - The reference time for `max_age_days`.
+///
+/// # Returns
+///
+/// The selected scans with the limit each one exceeds, oldest first. Nothing
+/// is selected when the policy sets no limit.
+pub fn select(
+    scans: Vec<ScanRecord>,
+    cfg: &RetentionConfig,
+    now: DateTime<Utc>,
+) -> Vec<(ScanRecord, &'static str)> {
+    let cutoff = cfg.max_age_days.map(|days| format_timestamp(now - Duration::days(days.min(36_500) as i64)));
+    // The drives of another server may have the same paths as the local ones
+    type Group = (String, Option<String>, Vec<String>);
+    let mut groups: HashMap<Group, Vec<ScanRecord>> = HashMap::new();
+    for scan in scans {
+        let key = (scan.namespace.clone(), scan.origin.clone(), scan.root_paths.clone());
+        groups.entry(key).or_default().push(scan);
+    }
+
+    let mut selected = Vec::new();
+    for (_, mut group) in groups {
+        // Newest first; the ID breaks ties so the selection is stable
+        group.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+        for (rank, scan) in group.into_iter().enumerate().skip(1) {
+            let reason = if cfg.max_scans_per_root.is_some_and(|max| rank >= max) {
+                "max_scans_per_root"
+            } else if cutoff.as_deref().is_some_and(|cutoff| scan.started_at.as_str() < cutoff) {
+                "max_age_days"
+            } else {
+                continue;
+            };
+            selected.push((scan, reason));
+        }
+    }
+    selected.sort_by(|(a, _), (b, _)| a.started_at.cmp(&b.started_at).then_with(|| a.id.cmp(&b.id)));
+    selected
+}
+
+/// Returns why a scan must not be pruned right now, if it must not.
+async fn protection(state: &AppState, id: Uuid) -> Option<&'static str> {
+    if state.jobs.read().await.contains_key(&id) {
+        Some("running")
+    } else if state.watchers.read().await.contains_key(&id) {
+        Some("watched")
+    } else if state.scan_leases.is_leased(id) {
+        Some("in_use")
+    } else {
+        None
+    }
+}
+
+/// Loads the finished scans and selects the ones the configured policy prunes.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `AppResult<Vec<Selected>>` - The selected scans, oldest first, each marked
+///   with why it is kept for now if it is protected.
+pub async fn plan(state: &AppState) -> AppResult<Vec<Selected>> {
+    let config = state.config.get();
+    let cfg = &config.retention;
+    if !cfg.is_enabled() {
+        return Ok(Vec::new());
+    }
+    let rows = sqlx::query(
+        r#"SELECT id, namespace, status, root_paths, origin, started_at, total_allocated_size
+           FROM scans WHERE status NOT IN ('running','paused','importing')"#,
+    )
+    .fetch_all(state.read_pool())
+    .await?;
+    let scans = rows
+        .into_iter()
+        .filter_map(|r| {
+            let id = Uuid::parse_str(&r.get::<String, _>("id")).ok()?;
+            Some(ScanRecord {
+                id,
+                namespace: r.get("namespace"),
+                status: r.get("status"),
+                root_paths: serde_json::from_str(&r.get::<String, _>("root_paths")).unwrap_or_default(),
+                origin: r.get("origin"),
+                started_at: r.get("started_at"),
+                total_allocated_size: r.get("total_allocated_size"),
+            })
+        })
+        .collect();
+
+    let mut selected = Vec::new();
+    for (record, reason) in select(scans, cfg, Utc::now()) {
+        let protected = protection(state, record.id).await;
+        selected.push(Selected { record, reason, protected });
+    }
+    Ok(selected)
+}
+
+/// Deletes the scans the configured policy selects.
+///
+/// Each scan is checked again right before it is deleted, so a scan that was
+/// resumed, watched or opened by a diff or export in the meantime is kept.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `AppResult<PruneOutcome>` - The pruned and the skipped scans.
+pub async fn prune(state: &AppState) -> AppResult<PruneOutcome> {
+    let mut outcome = PruneOutcome::default();
+    for mut selected in plan(state).await? {
+        selected.protected = protection(state, selected.record.id).await;
+        if selected.protected.is_some() {
+            outcome.skipped.push(selected);
+            continue;
+        }
+        let id = selected.record.id;
+        let res =
+            sqlx::query("DELETE FROM scans WHERE id=?1 AND status NOT IN ('running','paused','importing')")
+                .bind(id.to_string())
+                .execute(&state.db)
+                .await?;
+        if res.rows_affected() > 0 {
+            state.finished_events.write().await.remove(&id);
+            outcome.deleted.push(selected);
+        }
+    }
+
+    if !outcome.deleted.is_empty() {
+        tracing::info!("Retention pruned {} scan(s)", outcome.deleted.len());
+        state.read_only_roots.invalidate().await;
+        if state.config.get().retention.incremental_vacuum {
+            outcome.vacuumed = incremental_vacuum(&state.db).await;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Returns freed pages to the OS if the database uses incremental auto-vacuum.
+async fn incremental_vacuum(pool: &sqlx::SqlitePool) -> bool {
+    // 2 = INCREMENTAL; databases created before it was enabled need a one-time VACUUM
+    let mode: i64 = sqlx::query_scalar("PRAGMA auto_vacuum").fetch_one(pool).await.unwrap_or(0);
+    if mode != 2 {
+        return false;
+    }
+    match sqlx::query("PRAGMA incremental_vacuum").execute(pool).await {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::warn!("Incremental vacuum failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::test_db;
+
+    fn scan(namespace: &str, root: &str, started_at: &str) -> ScanRecord {
+        ScanRecord {
+            id: Uuid::new_v4(),
+            namespace: namespace.to_string(),
+            status: "done".to_string(),
+            root_paths: vec![root.to_string()],
+            origin: None,
+            started_at: started_at.to_string(),
+            total_allocated_size: None,
+        }
+    }
+
+    fn policy(max_scans_per_root: Option<usize>, max_age_days: Option<u64>) -> RetentionConfig {
+        RetentionConfig { max_scans_per_root, max_age_days, ..RetentionConfig::default() }
+    }
+
+    #[test]
+    fn select_keeps_newest_per_root_and_namespace() {
+        let now = Utc::now();
+        let scans = vec![
+            scan("default", "C:\\", "2024-01-01T00:00:00Z"),
+            scan("default", "C:\\", "2024-01-03T00:00:00Z"),
+            scan("default", "C:\\", "2024-01-02T00:00:00Z"),
+            scan("default", "D:\\", "2024-01-01T00:00:00Z"),
+            scan("hr", "C:\\", "2023-01-01T00:00:00Z"),
+            // An imported scan of another server's C:\ is no newer scan of this one's
+            ScanRecord { origin: Some("branch-1".into()), ..scan("default", "C:\\", "2024-01-04T00:00:00Z") },
+        ];
+        let selected = select(scans, &policy(Some(2), None), now);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].0.started_at, "2024-01-01T00:00:00Z");
+        assert_eq!(selected[0].0.root_paths, vec!["C:\\".to_string()]);
+        assert_eq!(selected[0].1, "max_scans_per_root");
+
+        // Age alone never removes the newest scan of a root
+        let selected = select(
+            vec![
+                scan("default", "C:\\", "2000-01-01T00:00:00Z"),
+                scan("default", "C:\\", "2000-01-02T00:00:00Z"),
+            ],
+            &policy(None, Some(30)),
+            now,
+        );
+        assert_eq!(selected.len(), 1);
+        assert_eq!(
+            (selected[0].0.started_at.as_str(), selected[0].1),
+            ("2000-01-01T00:00:00Z", "max_age_days")
+        );
+
+        assert!(select(vec![scan("default", "C:\\", "2000-01-01T00:00:00Z")], &policy(None, None), now)
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_skips_running_and_leased_scans() {
+        let (_dir, pool) = test_db().await;
+        let insert = |id: Uuid, status: &'static str, started_at: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query(
+                    "INSERT INTO scans (id, status, root_paths, options, started_at) VALUES (?1, ?2, ?3, '{}', ?4)",
+                )
+                .bind(id.to_string())
+                .bind(status)
+                .bind(r#"["C:\\data"]"#)
+                .bind(started_at)
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+        };
+        let (oldest, leased, running, newest) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        insert(oldest, "done", "2024-01-01T00:00:00Z").await;
+        insert(leased, "done", "2024-01-02T00:00:00Z").await;
+        insert(running, "running", "2023-01-01T00:00:00Z").await;
+        insert(newest, "done", "2024-01-03T00:00:00Z").await;
+
+        let mut cfg = AppConfig::default();
+        cfg.retention.max_scans_per_root = Some(1);
+        let state = AppState::new(pool.clone(), cfg);
+        let lease = state.scan_leases.acquire(leased);
+
+        let outcome = prune(&state).await.unwrap();
+        let deleted: Vec<Uuid> = outcome.deleted.iter().map(|s| s.record.id).collect();
+        let skipped: Vec<Uuid> = outcome.skipped.iter().map(|s| s.record.id).collect();
+        assert_eq!(deleted, vec![oldest]);
+        assert_eq!(skipped, vec![leased]);
+        assert_eq!(outcome.skipped[0].protected, Some("in_use"));
+
+        drop(lease);
+        let outcome = prune(&state).await.unwrap();
+        assert_eq!(outcome.deleted.iter().map(|s| s.record.id).collect::<Vec<_>>(), vec![leased]);
+        let left: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM scans ORDER BY started_at").fetch_all(&pool).await.unwrap();
+        assert_eq!(left, vec![running.to_string(), newest.to_string()]);
+    }
+}
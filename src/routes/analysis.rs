@@ -0,0 +1,1308 @@
+//! Scan analysis API endpoints.
+//!
+//! Aggregations over the stored files of a scan that help with storage
+//! decisions, such as how much data has not been touched for a long time.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/analysis/age-bands?path=&basis=mtime|atime&bounds=30,90,365,1095&group_depth=0|1`
+//!   - Allocated bytes and file counts per file age band
+//! - `GET /scans/{id}/age-report?path=&field=mtime|atime&bounds=30,90,365,730&top_dirs=N`
+//!   - Age buckets with chart labels, plus the directories holding the most old bytes
+//! - `GET /scans/{id}/analysis/compressibility?path=&limit=50`
+//!   - Directories ranked by the bytes compression would likely free
+//! - `GET /scans/{id}/analysis/empty-files?path=&limit=50&offset=0&files=false`
+//!   - Directories ranked by their zero-byte files, or a page of the files themselves
+//! - `GET /scans/{id}/treemap?path=&depth=2&min_fraction=0.01`
+//!   - Nested directory sizes for a treemap, small children coalesced into `<other>`
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    compressibility::{rank_directories, score_directory, CompressionTable, ExtensionStats},
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::{resolve_query_path, subtree_like_pattern},
+    state::AppState,
+    types::{
+        AgeBand, AgeBandSet, AgeBandsResponse, AgeReportBucket, AgeReportResponse, CompressibilityResponse,
+        EmptyFilesDirectory, EmptyFilesResponse, OldDirectory, TreemapNode, TreemapNodeKind, TreemapResponse,
+    },
+};
+
+/// Upper band boundaries in days used when `bounds` is omitted: 30 days, 90 days, 1 year, 3 years.
+const DEFAULT_AGE_BOUNDS: [u32; 4] = [30, 90, 365, 1095];
+/// Bucket edges in days used by the age report when `bounds` is omitted: 30 days, 90 days, 1 year, 2 years.
+const AGE_REPORT_BOUNDS: &str = "30,90,365,730";
+const AGE_BOUNDS_MAX: usize = 16;
+const TOP_DIRS_MAX: usize = 1_000;
+/// A century; anything older lands in the last band anyway.
+const AGE_BOUND_MAX_DAYS: u32 = 36_500;
+const CHILDREN_LIMIT_DEFAULT: usize = 100;
+const CHILDREN_LIMIT_MAX: usize = 1_000;
+const SECS_PER_DAY: i64 = 86_400;
+const COMPRESSIBILITY_LIMIT_DEFAULT: usize = 50;
+const COMPRESSIBILITY_LIMIT_MAX: usize = 500;
+const TOP_EXTENSIONS: usize = 5;
+const EMPTY_FILES_LIMIT_DEFAULT: usize = 50;
+const EMPTY_FILES_LIMIT_MAX: usize = 1_000;
+/// Longer suffixes are treated as part of the file name rather than an extension.
+const EXTENSION_MAX_LEN: usize = 16;
+const TREEMAP_DEPTH_DEFAULT: u32 = 2;
+const TREEMAP_DEPTH_MAX: u32 = 6;
+const TREEMAP_MIN_FRACTION_DEFAULT: f64 = 0.01;
+/// Directories are no longer expanded once the treemap holds this many nodes.
+const TREEMAP_NODES_MAX: usize = 10_000;
+/// Parent directories per `IN (...)` list, well below SQLite's bind limit.
+const TREEMAP_CHUNK: usize = 500;
+
+/// Query parameters for the age bands endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct AgeBandsQuery {
+    /// An optional path to restrict the analysis to a subtree.
+    pub path: Option<String>,
+    /// The timestamp to measure the age from: "mtime" (default) or "atime".
+    pub basis: Option<String>,
+    /// Comma-separated, strictly increasing upper band boundaries in days.
+    pub bounds: Option<String>,
+    /// `1` to also return the bands of each immediate child of `path`.
+    pub group_depth: Option<u32>,
+    /// The maximum number of children returned with `group_depth=1`.
+    pub limit: Option<usize>,
+}
+
+/// Returns how much data of a scan falls into each file age band.
+///
+/// The age of a file is the number of whole days between its modification
+/// (or access) time and now. Files without the chosen timestamp are counted
+/// separately as undated. Access times are only as reliable as the filesystem
+/// keeps them; many systems update them lazily or not at all.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The age band query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing an `AgeBandsResponse`.
+pub async fn get_age_bands(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<AgeBandsQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let now = chrono::Utc::now().timestamp();
+    Ok(Json(age_bands(state.read_pool(), id, &q, now).await?))
+}
+
+/// Parses and validates the `bounds` query parameter.
+fn parse_bounds(raw: Option<&str>) -> AppResult<Vec<u32>> {
+    let Some(raw) = raw else { return Ok(DEFAULT_AGE_BOUNDS.to_vec()) };
+    let mut bounds = Vec::new();
+    for part in raw.split(',') {
+        let days: u32 = part
+            .trim()
+            .parse()
+            .map_err(|_| AppError::BadRequest(format!("invalid band boundary: {}", part.trim())))?;
+        if days == 0 || days > AGE_BOUND_MAX_DAYS {
+            return Err(AppError::BadRequest(format!(
+                "band boundaries must be between 1 and {} days",
+                AGE_BOUND_MAX_DAYS
+            )));
+        }
+        if bounds.last().is_some_and(|&prev| days <= prev) {
+            return Err(AppError::BadRequest("band boundaries must be strictly increasing".into()));
+        }
+        bounds.push(days);
+    }
+    if bounds.len() > AGE_BOUNDS_MAX {
+        return Err(AppError::BadRequest(format!("at most {} band boundaries are allowed", AGE_BOUNDS_MAX)));
+    }
+    Ok(bounds)
+}
+
+fn empty_set(path: Option<String>, bounds: &[u32]) -> AgeBandSet {
+    let mut bands = Vec::with_capacity(bounds.len() + 1);
+    let mut min_days = 0;
+    for &b in bounds {
+        bands.push(AgeBand { min_days, max_days: Some(b), file_count: 0, allocated_size: 0 });
+        min_days = b + 1;
+    }
+    bands.push(AgeBand { min_days, max_days: None, file_count: 0, allocated_size: 0 });
+    AgeBandSet { path, bands, undated_file_count: 0, undated_allocated_size: 0 }
+}
+
+fn add_to_set(set: &mut AgeBandSet, band: i64, files: i64, allocated: i64) {
+    if band < 0 {
+        set.undated_file_count += files;
+        set.undated_allocated_size += allocated;
+    } else if let Some(b) = set.bands.get_mut(band as usize) {
+        b.file_count += files;
+        b.allocated_size += allocated;
+    }
+}
+
+fn set_allocated(set: &AgeBandSet) -> i64 {
+    set.bands.iter().map(|b| b.allocated_size).sum::<i64>() + set.undated_allocated_size
+}
+
+/// Computes the age bands behind `GET /scans/{id}/analysis/age-bands`.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the scan.
+/// * `q` - The age band query parameters.
+/// * `now` - The reference time in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// * `AppResult<AgeBandsResponse>` - The bands of the subtree and, if requested, of its children.
+pub async fn age_bands(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    q: &AgeBandsQuery,
+    now: i64,
+) -> AppResult<AgeBandsResponse> {
+    // Column names come from this fixed set, never from user input
+    let (basis, col) = match q.basis.as_deref() {
+        None | Some("mtime") => ("mtime", "mtime"),
+        Some("atime") => ("atime", "atime"),
+        Some(_) => return Err(AppError::BadRequest("basis must be 'mtime' or 'atime'".into())),
+    };
+    let grouped = match q.group_depth.unwrap_or(0) {
+        0 => false,
+        1 => true,
+        _ => return Err(AppError::BadRequest("group_depth must be 0 or 1".into())),
+    };
+    let bounds = parse_bounds(q.bounds.as_deref())?;
+    let subtree = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(pool, id, p).await?)
+        }
+        None if grouped => return Err(AppError::BadRequest("group_depth=1 requires a path".into())),
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(CHILDREN_LIMIT_DEFAULT).clamp(1, CHILDREN_LIMIT_MAX);
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
+    match subtree.as_deref() {
+        Some(root) if grouped => {
+            // The first path component below the subtree root names the child
+            let sep = if root.contains('\\') { '\\' } else { '/' };
+            let prefix_len =
+                if root.ends_with(['/', '\\']) { root.chars().count() } else { root.chars().count() + 1 };
+            qb.push(format!(
+                "CASE WHEN instr(substr(path, {start}), '{sep}') > 0 \
+                 THEN substr(path, 1, {len} + instr(substr(path, {start}), '{sep}') - 1) ELSE path END AS grp, ",
+                start = prefix_len + 1,
+                len = prefix_len,
+                sep = sep
+            ));
+        }
+        _ => {
+            qb.push("'' AS grp, ");
+        }
+    }
+    qb.push(format!("CASE WHEN {} IS NULL THEN -1", col));
+    for (i, &days) in bounds.iter().enumerate() {
+        // Whole days of age <= days  <=>  age in seconds < (days + 1) * 86400
+        qb.push(" WHEN ");
+        qb.push_bind(now).push(format!(" - {} < ", col)).push_bind((days as i64 + 1) * SECS_PER_DAY);
+        qb.push(format!(" THEN {}", i));
+    }
+    qb.push(format!(" ELSE {} END AS band, ", bounds.len()));
+    qb.push("COUNT(*) AS files, COALESCE(SUM(allocated_size), 0) AS allocated FROM files WHERE scan_id=");
+    qb.push_bind(id.to_string());
+    if let Some(root) = subtree.as_deref() {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+    qb.push(" GROUP BY grp, band");
+
+    let rows = qb.build().fetch_all(pool).await?;
+    let mut total = empty_set(subtree.clone(), &bounds);
+    let mut children: HashMap<String, AgeBandSet> = HashMap::new();
+    for r in rows {
+        let band: i64 = r.get("band");
+        let files: i64 = r.get("files");
+        let allocated: i64 = r.get("allocated");
+        add_to_set(&mut total, band, files, allocated);
+        if grouped {
+            let grp: String = r.get("grp");
+            let set = children.entry(grp.clone()).or_insert_with(|| empty_set(Some(grp), &bounds));
+            add_to_set(set, band, files, allocated);
+        }
+    }
+
+    let children = grouped.then(|| {
+        let mut list: Vec<AgeBandSet> = children.into_values().collect();
+        list.sort_by(|a, b| set_allocated(b).cmp(&set_allocated(a)).then_with(|| a.path.cmp(&b.path)));
+        list.truncate(limit);
+        list
+    });
+
+    Ok(AgeBandsResponse {
+        scan_id: id,
+        basis: basis.to_string(),
+        reference_time: now,
+        bounds_days: bounds,
+        total,
+        children,
+    })
+}
+
+/// Query parameters for the age report endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct AgeReportQuery {
+    /// An optional path to restrict the report to a subtree.
+    pub path: Option<String>,
+    /// The timestamp to measure the age from: "mtime" (default) or "atime".
+    pub field: Option<String>,
+    /// Comma-separated, strictly increasing bucket edges in days.
+    pub bounds: Option<String>,
+    /// The number of directories with the most old bytes to return.
+    pub top_dirs: Option<usize>,
+}
+
+/// Returns an age report of a scan for archiving decisions.
+///
+/// Like the age bands, but with chart labels per bucket, files without the
+/// chosen timestamp as an `unknown` bucket, and optionally the directories
+/// whose files directly inside them hold the most bytes of the oldest bucket.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The age report query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing an `AgeReportResponse`.
+pub async fn get_age_report(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<AgeReportQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let now = chrono::Utc::now().timestamp();
+    Ok(Json(age_report(state.read_pool(), id, &q, now).await?))
+}
+
+/// Formats an age in days for a chart label, whole years as years.
+fn days_label(days: u32) -> String {
+    if days >= 365 && days.is_multiple_of(365) {
+        format!("{}y", days / 365)
+    } else {
+        format!("{}d", days)
+    }
+}
+
+fn bucket_label(band: &AgeBand) -> String {
+    match (band.min_days, band.max_days) {
+        (0, Some(max)) => format!("<{}", days_label(max)),
+        (min, Some(max)) => format!("{}-{}", days_label(min - 1), days_label(max)),
+        (min, None) => format!(">{}", days_label(min.saturating_sub(1))),
+    }
+}
+
+/// Computes the report behind `GET /scans/{id}/age-report`.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the scan.
+/// * `q` - The age report query parameters.
+/// * `now` - The reference time in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// * `AppResult<AgeReportResponse>` - The buckets and, if requested, the top directories.
+pub async fn age_report(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    q: &AgeReportQuery,
+    now: i64,
+) -> AppResult<AgeReportResponse> {
+    // Column names come from this fixed set, never from user input
+    let col = match q.field.as_deref() {
+        None | Some("mtime") => "mtime",
+        Some("atime") => "atime",
+        Some(_) => return Err(AppError::BadRequest("field must be 'mtime' or 'atime'".into())),
+    };
+    let bands_query = AgeBandsQuery {
+        path: q.path.clone(),
+        basis: Some(col.to_string()),
+        bounds: Some(q.bounds.clone().unwrap_or_else(|| AGE_REPORT_BOUNDS.to_string())),
+        ..Default::default()
+    };
+    let bands = age_bands(pool, id, &bands_query, now).await?;
+    let old_after_days = bands.bounds_days.last().copied().unwrap_or(0);
+
+    let mut buckets: Vec<AgeReportBucket> = bands
+        .total
+        .bands
+        .iter()
+        .map(|b| AgeReportBucket {
+            label: bucket_label(b),
+            min_days: Some(b.min_days),
+            max_days: b.max_days,
+            file_count: b.file_count,
+            allocated_size: b.allocated_size,
+        })
+        .collect();
+    buckets.push(AgeReportBucket {
+        label: "unknown".into(),
+        min_days: None,
+        max_days: None,
+        file_count: bands.total.undated_file_count,
+        allocated_size: bands.total.undated_allocated_size,
+    });
+
+    let top_dirs = match q.top_dirs {
+        Some(n) => {
+            let mut qb = QueryBuilder::<Sqlite>::new(
+                "SELECT COALESCE(parent_path, '') AS dir, COUNT(*) AS files, \
+                 COALESCE(SUM(allocated_size), 0) AS allocated FROM files WHERE scan_id=",
+            );
+            qb.push_bind(id.to_string());
+            push_subtree_filter(&mut qb, bands.total.path.as_deref());
+            // Same day arithmetic as the bands: older than `old_after_days` whole days
+            qb.push(format!(" AND {col} IS NOT NULL AND "));
+            qb.push_bind(now)
+                .push(format!(" - {col} >= "))
+                .push_bind((old_after_days as i64 + 1) * SECS_PER_DAY);
+            qb.push(" GROUP BY dir ORDER BY allocated DESC, dir LIMIT ");
+            qb.push_bind(n.clamp(1, TOP_DIRS_MAX) as i64);
+            let rows = qb.build().fetch_all(pool).await?;
+            Some(
+                rows.iter()
+                    .map(|r| OldDirectory {
+                        path: r.get("dir"),
+                        file_count: r.get("files"),
+                        allocated_size: r.get("allocated"),
+                    })
+                    .collect(),
+            )
+        }
+        None => None,
+    };
+
+    Ok(AgeReportResponse {
+        scan_id: id,
+        field: col.to_string(),
+        reference_time: now,
+        path: bands.total.path,
+        edges_days: bands.bounds_days,
+        buckets,
+        old_after_days,
+        top_dirs,
+    })
+}
+
+/// Query parameters for the compressibility endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct CompressibilityQuery {
+    /// An optional path to restrict the analysis to a subtree.
+    pub path: Option<String>,
+    /// The maximum number of directories to return.
+    pub limit: Option<usize>,
+}
+
+/// Ranks the directories of a scan by how much compressing them would likely free.
+///
+/// The estimate is computed from the stored file sizes and extensions only, using
+/// the extension classes of the `analysis` configuration. Directories are scored
+/// by the files directly inside them.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The compressibility query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `CompressibilityResponse`.
+pub async fn get_compressibility(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<CompressibilityQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let table = CompressionTable::new(&state.config.get().analysis);
+    Ok(Json(compressibility(state.read_pool(), id, &q, &table).await?))
+}
+
+/// Computes the ranking behind `GET /scans/{id}/analysis/compressibility`.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the scan.
+/// * `q` - The compressibility query parameters.
+/// * `table` - The extension classification.
+///
+/// # Returns
+///
+/// * `AppResult<CompressibilityResponse>` - The directories with reclaimable bytes, most first.
+pub async fn compressibility(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    q: &CompressibilityQuery,
+    table: &CompressionTable,
+) -> AppResult<CompressibilityResponse> {
+    let subtree = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(pool, id, p).await?)
+        }
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(COMPRESSIBILITY_LIMIT_DEFAULT).clamp(1, COMPRESSIBILITY_LIMIT_MAX);
+
+    // rtrim() with every character but '.' strips the path back to its last dot; a
+    // suffix containing a separator means the dot was in a directory name
+    let mut qb = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT dir, CASE WHEN instr(suffix, '/') > 0 OR instr(suffix, '\\') > 0 OR length(suffix) > {} \
+         THEN '' ELSE lower(suffix) END AS ext, COUNT(*) AS files, \
+         COALESCE(SUM(logical_size), 0) AS logical, COALESCE(SUM(allocated_size), 0) AS allocated \
+         FROM (SELECT COALESCE(parent_path, '') AS dir, logical_size, allocated_size, \
+         substr(path, length(rtrim(path, replace(path, '.', ''))) + 1) AS suffix FROM files WHERE scan_id=",
+        EXTENSION_MAX_LEN
+    ));
+    qb.push_bind(id.to_string());
+    if let Some(root) = subtree.as_deref() {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+    qb.push(") GROUP BY dir, ext");
+
+    let rows = qb.build().fetch_all(pool).await?;
+    let mut by_dir: HashMap<String, Vec<ExtensionStats>> = HashMap::new();
+    for r in rows {
+        by_dir.entry(r.get("dir")).or_default().push(ExtensionStats {
+            extension: r.get("ext"),
+            file_count: r.get("files"),
+            logical_size: r.get("logical"),
+            allocated_size: r.get("allocated"),
+        });
+    }
+
+    let mut directories: Vec<_> = by_dir
+        .into_iter()
+        .map(|(dir, stats)| score_directory(table, dir, &stats, TOP_EXTENSIONS))
+        .filter(|d| d.estimated_reclaimable > 0)
+        .collect();
+    let total_estimated_reclaimable = directories.iter().map(|d| d.estimated_reclaimable).sum();
+    rank_directories(&mut directories);
+    directories.truncate(limit);
+
+    Ok(CompressibilityResponse { scan_id: id, path: subtree, total_estimated_reclaimable, directories })
+}
+
+/// Query parameters for the empty files endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct EmptyFilesQuery {
+    /// An optional path to restrict the analysis to a subtree.
+    pub path: Option<String>,
+    /// The maximum number of directories or files to return.
+    pub limit: Option<usize>,
+    /// The number of directories or files to skip.
+    pub offset: Option<usize>,
+    /// `true` to list the zero-byte files instead of ranking directories.
+    pub files: Option<bool>,
+}
+
+/// Returns where the zero-byte files of a scan are.
+///
+/// By default directories are ranked by the number of zero-byte files directly
+/// inside them. With `files=true` the file paths themselves are listed page by
+/// page. Scans made before the scanner counted empty files are answered from
+/// the stored file sizes instead.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The empty files query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing an `EmptyFilesResponse`.
+pub async fn get_empty_files(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<EmptyFilesQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    Ok(Json(empty_files(state.read_pool(), id, &q).await?))
+}
+
+/// Restricts a query on `nodes` or `files` to the subtree rooted at `root`.
+fn push_subtree_filter(qb: &mut QueryBuilder<'_, Sqlite>, root: Option<&str>) {
+    if let Some(root) = root {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+}
+
+/// Computes the result behind `GET /scans/{id}/analysis/empty-files`.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the scan.
+/// * `q` - The empty files query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<EmptyFilesResponse>` - The ranked directories or the requested page of files.
+pub async fn empty_files(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    q: &EmptyFilesQuery,
+) -> AppResult<EmptyFilesResponse> {
+    let subtree = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(pool, id, p).await?)
+        }
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(EMPTY_FILES_LIMIT_DEFAULT).clamp(1, EMPTY_FILES_LIMIT_MAX);
+    let offset = q.offset.unwrap_or(0);
+
+    // Directories stored before the column existed (or added by an older watcher) have no count
+    let uncounted: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND is_dir=1 AND empty_file_count IS NULL",
+    )
+    .bind(id.to_string())
+    .fetch_one(pool)
+    .await?;
+    let counted_during_scan = uncounted == 0;
+
+    // (directory, count) pairs of every directory with zero-byte files in the subtree
+    let mut qb = if counted_during_scan {
+        QueryBuilder::<Sqlite>::new(
+            "SELECT path AS dir, empty_file_count AS cnt FROM nodes \
+             WHERE is_dir=1 AND empty_file_count > 0 AND scan_id=",
+        )
+    } else {
+        QueryBuilder::<Sqlite>::new(
+            "SELECT COALESCE(parent_path, '') AS dir, COUNT(*) AS cnt FROM files \
+             WHERE logical_size=0 AND scan_id=",
+        )
+    };
+    qb.push_bind(id.to_string());
+    push_subtree_filter(&mut qb, subtree.as_deref());
+    if !counted_during_scan {
+        qb.push(" GROUP BY dir");
+    }
+    let mut directories: Vec<EmptyFilesDirectory> = qb
+        .build()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| EmptyFilesDirectory { path: r.get("dir"), empty_file_count: r.get("cnt") })
+        .collect();
+    let total_empty_files = directories.iter().map(|d| d.empty_file_count).sum();
+
+    if q.files.unwrap_or(false) {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT path FROM files WHERE logical_size=0 AND scan_id=");
+        qb.push_bind(id.to_string());
+        push_subtree_filter(&mut qb, subtree.as_deref());
+        qb.push(" ORDER BY path LIMIT ").push_bind(limit as i64);
+        qb.push(" OFFSET ").push_bind(offset.min(i64::MAX as usize) as i64);
+        let files = qb.build().fetch_all(pool).await?.into_iter().map(|r| r.get("path")).collect();
+        return Ok(EmptyFilesResponse {
+            scan_id: id,
+            path: subtree,
+            counted_during_scan,
+            total_empty_files,
+            directories: None,
+            files: Some(files),
+        });
+    }
+
+    directories.sort_by(|a, b| b.empty_file_count.cmp(&a.empty_file_count).then_with(|| a.path.cmp(&b.path)));
+    let directories = directories.into_iter().skip(offset).take(limit).collect();
+    Ok(EmptyFilesResponse {
+        scan_id: id,
+        path: subtree,
+        counted_during_scan,
+        total_empty_files,
+        directories: Some(directories),
+        files: None,
+    })
+}
+
+/// Query parameters for the treemap endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct TreemapQuery {
+    /// The directory to map; may be omitted for scans with a single root.
+    pub path: Option<String>,
+    /// The number of directory levels to expand below `path`.
+    pub depth: Option<u32>,
+    /// Children smaller than this share of their parent are coalesced into `<other>`.
+    pub min_fraction: Option<f64>,
+}
+
+/// Returns the nested directory sizes below a path for drawing a treemap.
+///
+/// Every directory lists its subdirectories, a synthetic `<files>` child for the
+/// files directly inside it and an `<other>` child for the children below
+/// `min_fraction` of its size, so the children always add up to their parent.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The treemap query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `TreemapResponse`.
+pub async fn get_treemap(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<TreemapQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    Ok(Json(treemap(state.read_pool(), id, &q).await?))
+}
+
+/// The last component of a stored path, or the path itself for roots like `C:\`.
+fn path_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(path).to_string()
+}
+
+fn dir_node(path: String, allocated_size: i64, file_count: i64) -> TreemapNode {
+    TreemapNode {
+        kind: TreemapNodeKind::Dir,
+        name: path_name(&path),
+        path,
+        allocated_size,
+        file_count,
+        coalesced: 0,
+        children: Vec::new(),
+    }
+}
+
+/// Lays out the children of one directory.
+///
+/// # Arguments
+///
+/// * `parent` - The directory.
+/// * `total` - The allocated bytes of the directory.
+/// * `dirs` - The subdirectories.
+/// * `files` - The number and allocated bytes of the files directly inside the directory.
+/// * `min_fraction` - Children below this share of `total` are coalesced.
+///
+/// # Returns
+///
+/// * `Vec<TreemapNode>` - The kept children largest first, then `<other>` if needed.
+fn layout_children(
+    parent: &str,
+    total: i64,
+    dirs: Vec<TreemapNode>,
+    files: (i64, i64),
+    min_fraction: f64,
+) -> Vec<TreemapNode> {
+    let mut candidates = dirs;
+    if files.0 > 0 {
+        candidates.push(TreemapNode {
+            kind: TreemapNodeKind::Files,
+            path: parent.to_string(),
+            name: "<files>".into(),
+            allocated_size: files.1,
+            file_count: files.0,
+            coalesced: 0,
+            children: Vec::new(),
+        });
+    }
+    candidates.sort_by(|a, b| b.allocated_size.cmp(&a.allocated_size).then_with(|| a.path.cmp(&b.path)));
+
+    let threshold = total as f64 * min_fraction;
+    let (mut kept, small): (Vec<TreemapNode>, Vec<TreemapNode>) =
+        candidates.into_iter().partition(|c| c.allocated_size > 0 && c.allocated_size as f64 >= threshold);
+    // The rest also covers bytes no stored child accounts for, e.g. after a depth-limited scan
+    let rest = total - kept.iter().map(|c| c.allocated_size).sum::<i64>();
+    if rest > 0 || !small.is_empty() {
+        kept.push(TreemapNode {
+            kind: TreemapNodeKind::Other,
+            path: parent.to_string(),
+            name: "<other>".into(),
+            allocated_size: rest.max(0),
+            file_count: small.iter().map(|c| c.file_count).sum(),
+            coalesced: small.iter().filter(|c| c.kind == TreemapNodeKind::Dir).count() as i64,
+            children: Vec::new(),
+        });
+    }
+    kept
+}
+
+/// Computes the children of one level of directories with two queries per chunk.
+async fn treemap_level(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    parents: &[(String, i64)],
+    min_fraction: f64,
+) -> AppResult<HashMap<String, Vec<TreemapNode>>> {
+    let mut dirs: HashMap<String, Vec<TreemapNode>> = HashMap::new();
+    let mut files: HashMap<String, (i64, i64)> = HashMap::new();
+    for chunk in parents.chunks(TREEMAP_CHUNK) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT parent_path, path, allocated_size, file_count FROM nodes WHERE is_dir=1 AND scan_id=",
+        );
+        qb.push_bind(id.to_string());
+        qb.push(" AND parent_path IN (");
+        let mut list = qb.separated(", ");
+        for (path, _) in chunk {
+            list.push_bind(path.clone());
+        }
+        qb.push(")");
+        for r in qb.build().fetch_all(pool).await? {
+            let node = dir_node(r.get("path"), r.get("allocated_size"), r.get("file_count"));
+            dirs.entry(r.get("parent_path")).or_default().push(node);
+        }
+
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT parent_path, COUNT(*) AS files, COALESCE(SUM(allocated_size), 0) AS allocated \
+             FROM files WHERE scan_id=",
+        );
+        qb.push_bind(id.to_string());
+        qb.push(" AND parent_path IN (");
+        let mut list = qb.separated(", ");
+        for (path, _) in chunk {
+            list.push_bind(path.clone());
+        }
+        qb.push(") GROUP BY parent_path");
+        for r in qb.build().fetch_all(pool).await? {
+            files.insert(r.get("parent_path"), (r.get("files"), r.get("allocated")));
+        }
+    }
+
+    Ok(parents
+        .iter()
+        .map(|(path, total)| {
+            let children = layout_children(
+                path,
+                *total,
+                dirs.remove(path).unwrap_or_default(),
+                files.remove(path).unwrap_or((0, 0)),
+                min_fraction,
+            );
+            (path.clone(), children)
+        })
+        .collect())
+}
+
+/// Hangs the computed children below their directories, depth first.
+fn attach_children(
+    mut node: TreemapNode,
+    children_of: &mut HashMap<String, Vec<TreemapNode>>,
+) -> TreemapNode {
+    if node.kind != TreemapNodeKind::Dir {
+        return node;
+    }
+    if let Some(children) = children_of.remove(&node.path) {
+        node.children = children.into_iter().map(|c| attach_children(c, children_of)).collect();
+        // Stored totals can lag behind their children (e.g. while watched); keep the sum exact
+        let sum: i64 = node.children.iter().map(|c| c.allocated_size).sum();
+        node.allocated_size = node.allocated_size.max(sum);
+    }
+    node
+}
+
+/// Computes the treemap behind `GET /scans/{id}/treemap`.
+///
+/// Each level of directories is resolved with one query on `nodes` and one on
+/// `files` (per chunk of parents), so only the directories that end up in the
+/// map are ever loaded.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the scan.
+/// * `q` - The treemap query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<TreemapResponse>` - The treemap rooted at the requested directory.
+pub async fn treemap(pool: &sqlx::SqlitePool, id: Uuid, q: &TreemapQuery) -> AppResult<TreemapResponse> {
+    let depth = q.depth.unwrap_or(TREEMAP_DEPTH_DEFAULT);
+    if depth == 0 || depth > TREEMAP_DEPTH_MAX {
+        return Err(AppError::BadRequest(format!("depth must be between 1 and {}", TREEMAP_DEPTH_MAX)));
+    }
+    let min_fraction = q.min_fraction.unwrap_or(TREEMAP_MIN_FRACTION_DEFAULT);
+    if !(0.0..1.0).contains(&min_fraction) {
+        return Err(AppError::BadRequest("min_fraction must be at least 0 and below 1".into()));
+    }
+    let root_path = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            resolve_query_path(pool, id, p).await?
+        }
+        None => {
+            let roots: String = sqlx::query_scalar("SELECT root_paths FROM scans WHERE id=?1")
+                .bind(id.to_string())
+                .fetch_one(pool)
+                .await?;
+            let roots: Vec<String> = serde_json::from_str(&roots).unwrap_or_default();
+            let [root] = roots.as_slice() else {
+                return Err(AppError::BadRequest("path is required for scans with several roots".into()));
+            };
+            resolve_query_path(pool, id, root).await?
+        }
+    };
+
+    let row =
+        sqlx::query("SELECT allocated_size, file_count FROM nodes WHERE scan_id=?1 AND path=?2 AND is_dir=1")
+            .bind(id.to_string())
+            .bind(&root_path)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("directory not found in scan: {}", root_path)))?;
+    let root = dir_node(root_path.clone(), row.get("allocated_size"), row.get("file_count"));
+
+    let mut children_of: HashMap<String, Vec<TreemapNode>> = HashMap::new();
+    let mut frontier = vec![(root_path, root.allocated_size)];
+    let mut node_count = 1;
+    let mut truncated = false;
+    for _ in 0..depth {
+        if frontier.is_empty() {
+            break;
+        }
+        if node_count >= TREEMAP_NODES_MAX {
+            truncated = true;
+            break;
+        }
+        let level = treemap_level(pool, id, &frontier, min_fraction).await?;
+        let mut next = Vec::new();
+        for (parent, children) in level {
+            node_count += children.len();
+            for child in children.iter().filter(|c| c.kind == TreemapNodeKind::Dir) {
+                next.push((child.path.clone(), child.allocated_size));
+            }
+            children_of.insert(parent, children);
+        }
+        frontier = next;
+    }
+
+    Ok(TreemapResponse {
+        scan_id: id,
+        depth,
+        min_fraction,
+        truncated,
+        root: attach_children(root, &mut children_of),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_node, insert_scan, test_db, FileRow, NodeRow};
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn days_ago(days: i64) -> i64 {
+        NOW - days * SECS_PER_DAY
+    }
+
+    async fn fixture() -> (tempfile::TempDir, sqlx::SqlitePool, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &["/data"], "default").await;
+
+        // (path, allocated, mtime, atime)
+        let files: &[(&str, i64, Option<i64>, Option<i64>)] = &[
+            ("/data/a/new.txt", 10, Some(days_ago(0)), Some(days_ago(400))),
+            ("/data/a/month.txt", 20, Some(days_ago(30)), Some(days_ago(1))),
+            ("/data/a/deep/quarter.txt", 40, Some(days_ago(31)), None),
+            ("/data/b/year.txt", 80, Some(days_ago(365)), None),
+            ("/data/b/old.txt", 160, Some(days_ago(366)), None),
+            ("/data/b/ancient.txt", 320, Some(days_ago(5000)), None),
+            ("/data/top.txt", 640, None, None),
+            ("/database/other.txt", 1280, Some(days_ago(0)), None),
+        ];
+        for &(path, size, mtime, atime) in files {
+            let parent = path.rsplit_once('/').map(|(p, _)| p);
+            insert_file(&pool, id, FileRow { path, parent, size, mtime, atime }).await;
+        }
+        (dir, pool, id)
+    }
+
+    fn counts(set: &AgeBandSet) -> Vec<(i64, i64)> {
+        set.bands.iter().map(|b| (b.file_count, b.allocated_size)).collect()
+    }
+
+    fn query(path: Option<&str>) -> AgeBandsQuery {
+        AgeBandsQuery { path: path.map(String::from), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn bands_split_at_day_boundaries() {
+        let (_dir, pool, id) = fixture().await;
+        let resp = age_bands(&pool, id, &query(Some("/data")), NOW).await.unwrap();
+        assert_eq!(resp.basis, "mtime");
+        assert_eq!(resp.reference_time, NOW);
+        assert_eq!(resp.bounds_days, vec![30, 90, 365, 1095]);
+        let limits: Vec<(u32, Option<u32>)> =
+            resp.total.bands.iter().map(|b| (b.min_days, b.max_days)).collect();
+        assert_eq!(
+            limits,
+            vec![(0, Some(30)), (31, Some(90)), (91, Some(365)), (366, Some(1095)), (1096, None)]
+        );
+        // "/database" shares the prefix but is not part of the subtree
+        assert_eq!(counts(&resp.total), vec![(2, 30), (1, 40), (1, 80), (1, 160), (1, 320)]);
+        assert_eq!((resp.total.undated_file_count, resp.total.undated_allocated_size), (1, 640));
+        assert!(resp.children.is_none());
+
+        let all = age_bands(&pool, id, &query(None), NOW).await.unwrap();
+        assert_eq!(all.total.path, None);
+        assert_eq!(all.total.bands[0].file_count, 3);
+    }
+
+    #[tokio::test]
+    async fn atime_basis_and_custom_bounds() {
+        let (_dir, pool, id) = fixture().await;
+        let q = AgeBandsQuery {
+            path: Some("/data".into()),
+            basis: Some("atime".into()),
+            bounds: Some("7, 365".into()),
+            ..Default::default()
+        };
+        let resp = age_bands(&pool, id, &q, NOW).await.unwrap();
+        assert_eq!(resp.basis, "atime");
+        assert_eq!(counts(&resp.total), vec![(1, 20), (0, 0), (1, 10)]);
+        assert_eq!(resp.total.undated_file_count, 5);
+    }
+
+    #[tokio::test]
+    async fn group_depth_bands_each_child() {
+        let (_dir, pool, id) = fixture().await;
+        let q = AgeBandsQuery { path: Some("/data/".into()), group_depth: Some(1), ..Default::default() };
+        let resp = age_bands(&pool, id, &q, NOW).await.unwrap();
+        let children = resp.children.unwrap();
+        let paths: Vec<&str> = children.iter().map(|c| c.path.as_deref().unwrap()).collect();
+        assert_eq!(paths, vec!["/data/top.txt", "/data/b", "/data/a"]);
+        assert_eq!(counts(&children[1]), vec![(0, 0), (0, 0), (1, 80), (1, 160), (1, 320)]);
+        assert_eq!(counts(&children[2]), vec![(2, 30), (1, 40), (0, 0), (0, 0), (0, 0)]);
+        assert_eq!(children[0].undated_file_count, 1);
+
+        let q = AgeBandsQuery {
+            path: Some("/data".into()),
+            group_depth: Some(1),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(age_bands(&pool, id, &q, NOW).await.unwrap().children.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_parameters() {
+        let (_dir, pool, id) = fixture().await;
+        for bounds in ["", "30,30", "90,30", "0", "abc", "40000"] {
+            let q = AgeBandsQuery { bounds: Some(bounds.into()), ..Default::default() };
+            assert!(
+                matches!(age_bands(&pool, id, &q, NOW).await, Err(AppError::BadRequest(_))),
+                "{}",
+                bounds
+            );
+        }
+        let q = AgeBandsQuery { basis: Some("ctime".into()), ..Default::default() };
+        assert!(matches!(age_bands(&pool, id, &q, NOW).await, Err(AppError::BadRequest(_))));
+        let q = AgeBandsQuery { group_depth: Some(1), ..Default::default() };
+        assert!(matches!(age_bands(&pool, id, &q, NOW).await, Err(AppError::BadRequest(_))));
+        let q = AgeBandsQuery { path: Some("/data".into()), group_depth: Some(2), ..Default::default() };
+        assert!(matches!(age_bands(&pool, id, &q, NOW).await, Err(AppError::BadRequest(_))));
+    }
+
+    /// Inserts `count` files named `f<i>.<ext>` into `dir`.
+    async fn insert_files(
+        pool: &sqlx::SqlitePool,
+        id: Uuid,
+        dir: &str,
+        ext: &str,
+        count: usize,
+        logical: i64,
+        allocated: i64,
+    ) {
+        for i in 0..count {
+            sqlx::query(
+                r#"INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size)
+                   VALUES (?1, ?2, ?3, ?4, ?5)"#,
+            )
+            .bind(id.to_string())
+            .bind(format!("{}/f{}.{}", dir, i, ext))
+            .bind(dir)
+            .bind(logical)
+            .bind(allocated)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    async fn compress_fixture() -> (tempfile::TempDir, sqlx::SqlitePool, Uuid) {
+        const MIB: i64 = 1024 * 1024;
+        let (dir, pool, id) = fixture().await;
+        insert_files(&pool, id, "/data/logs", "log", 20, MIB, MIB).await;
+        insert_files(&pool, id, "/data/photos", "JPG", 20, MIB, MIB).await;
+        insert_files(&pool, id, "/data/mixed", "csv", 10, MIB, MIB).await;
+        insert_files(&pool, id, "/data/mixed", "zip", 10, MIB, MIB).await;
+        // Already NTFS-compressed
+        insert_files(&pool, id, "/data/packed.logs", "log", 20, MIB, MIB / 4).await;
+        insert_files(&pool, id, "/data/tiny", "txt", 100, 100, 4096).await;
+        (dir, pool, id)
+    }
+
+    fn default_table() -> CompressionTable {
+        CompressionTable::new(&crate::config::AnalysisConfig::default())
+    }
+
+    #[tokio::test]
+    async fn compressibility_ranks_directories_by_extension_mix() {
+        let (_dir, pool, id) = compress_fixture().await;
+        let q = CompressibilityQuery { path: Some("/data".into()), ..Default::default() };
+        let resp = compressibility(&pool, id, &q, &default_table()).await.unwrap();
+        let order: Vec<&str> = resp.directories.iter().map(|d| d.path.as_str()).collect();
+        // The age fixture's ".txt" files in /data/a, /data/b and /data all rank below
+        assert_eq!(&order[..3], &["/data/logs", "/data/mixed", "/data/tiny"]);
+        assert!(!order.contains(&"/data/photos"));
+        assert!(!order.contains(&"/data/packed.logs"));
+        assert!(!order.contains(&"/database"));
+
+        let mixed = &resp.directories[1];
+        assert_eq!(mixed.file_count, 20);
+        assert_eq!(mixed.score, 0.35);
+        let exts: Vec<&str> = mixed.top_extensions.iter().map(|e| e.extension.as_str()).collect();
+        assert_eq!(exts, vec!["csv", "zip"]);
+        assert_eq!(
+            resp.total_estimated_reclaimable,
+            resp.directories.iter().map(|d| d.estimated_reclaimable).sum::<i64>()
+        );
+
+        let q = CompressibilityQuery { path: Some("/data".into()), limit: Some(1) };
+        assert_eq!(compressibility(&pool, id, &q, &default_table()).await.unwrap().directories.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn compressibility_follows_configured_classes() {
+        let (_dir, pool, id) = compress_fixture().await;
+        let config = crate::config::AnalysisConfig {
+            compression_classes: vec![crate::config::CompressionClassConfig {
+                name: "photos".into(),
+                extensions: vec!["jpg".into()],
+                expected_ratio: 0.9,
+            }],
+            unclassified_ratio: 0.0,
+        };
+        let q = CompressibilityQuery { path: Some("/data".into()), ..Default::default() };
+        let resp = compressibility(&pool, id, &q, &CompressionTable::new(&config)).await.unwrap();
+        let order: Vec<&str> = resp.directories.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(order, vec!["/data/photos"]);
+        assert_eq!(resp.directories[0].top_extensions[0].class.as_deref(), Some("photos"));
+        assert_eq!(resp.directories[0].confidence, 1.0);
+    }
+
+    /// Adds zero-byte files and directory nodes, with counts only if `counted`.
+    async fn empty_fixture(counted: bool) -> (tempfile::TempDir, sqlx::SqlitePool, Uuid) {
+        let (dir, pool, id) = fixture().await;
+        let dirs: &[(&str, i64)] =
+            &[("/data", 0), ("/data/a", 2), ("/data/a/deep", 1), ("/data/b", 3), ("/database", 1)];
+        for &(path, empty) in dirs {
+            insert_files(&pool, id, path, "empty", empty as usize, 0, 0).await;
+            sqlx::query(
+                r#"INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size,
+                   file_count, dir_count, empty_file_count) VALUES (?1, ?2, NULL, 0, 1, 0, 0, 0, 0, ?3)"#,
+            )
+            .bind(id.to_string())
+            .bind(path)
+            .bind(counted.then_some(empty))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        (dir, pool, id)
+    }
+
+    #[tokio::test]
+    async fn empty_files_rank_directories_and_list_files() {
+        for counted in [true, false] {
+            let (_dir, pool, id) = empty_fixture(counted).await;
+            let q = EmptyFilesQuery { path: Some("/data".into()), ..Default::default() };
+            let resp = empty_files(&pool, id, &q).await.unwrap();
+            assert_eq!(resp.counted_during_scan, counted);
+            assert_eq!(resp.total_empty_files, 6);
+            assert!(resp.files.is_none());
+            let ranked: Vec<(&str, i64)> = resp
+                .directories
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|d| (d.path.as_str(), d.empty_file_count))
+                .collect();
+            assert_eq!(ranked, vec![("/data/b", 3), ("/data/a", 2), ("/data/a/deep", 1)]);
+
+            let q = EmptyFilesQuery { limit: Some(1), offset: Some(1), ..Default::default() };
+            let resp = empty_files(&pool, id, &q).await.unwrap();
+            assert_eq!(resp.total_empty_files, 7);
+            assert_eq!(resp.directories.unwrap()[0].path, "/data/a");
+
+            let q = EmptyFilesQuery {
+                path: Some("/data/a".into()),
+                limit: Some(2),
+                offset: Some(1),
+                files: Some(true),
+            };
+            let resp = empty_files(&pool, id, &q).await.unwrap();
+            assert_eq!(resp.total_empty_files, 3);
+            assert!(resp.directories.is_none());
+            assert_eq!(resp.files.unwrap(), vec!["/data/a/f0.empty", "/data/a/f1.empty"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn age_report_labels_buckets_and_ranks_old_directories() {
+        let (_dir, pool, id) = fixture().await;
+        let q = AgeReportQuery { path: Some("/data".into()), top_dirs: Some(5), ..Default::default() };
+        let report = age_report(&pool, id, &q, NOW).await.unwrap();
+        assert_eq!(report.field, "mtime");
+        assert_eq!(report.edges_days, vec![30, 90, 365, 730]);
+        assert_eq!(report.old_after_days, 730);
+        let buckets: Vec<(&str, i64, i64)> =
+            report.buckets.iter().map(|b| (b.label.as_str(), b.file_count, b.allocated_size)).collect();
+        assert_eq!(
+            buckets,
+            vec![
+                ("<30d", 2, 30),
+                ("30d-90d", 1, 40),
+                ("90d-1y", 1, 80),
+                ("1y-2y", 1, 160),
+                (">2y", 1, 320),
+                ("unknown", 1, 640),
+            ]
+        );
+        assert_eq!(report.buckets.last().unwrap().min_days, None);
+        let top = report.top_dirs.unwrap();
+        assert_eq!(top, vec![OldDirectory { path: "/data/b".into(), file_count: 1, allocated_size: 320 }]);
+
+        // Access times with custom edges; files without atime stay unknown
+        let q = AgeReportQuery {
+            path: Some("/data".into()),
+            field: Some("atime".into()),
+            bounds: Some("30,365".into()),
+            top_dirs: Some(5),
+        };
+        let report = age_report(&pool, id, &q, NOW).await.unwrap();
+        let labels: Vec<&str> = report.buckets.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, vec!["<30d", "30d-1y", ">1y", "unknown"]);
+        assert_eq!(report.buckets[3].file_count, 5);
+        assert_eq!(report.top_dirs.unwrap()[0].path, "/data/a");
+
+        let q = AgeReportQuery { field: Some("ctime".into()), ..Default::default() };
+        assert!(matches!(age_report(&pool, id, &q, NOW).await, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn treemap_children_add_up_to_their_parent() {
+        let (_dir, pool, id) = fixture().await;
+        // (path, parent, allocated, file_count)
+        let dirs: &[(&str, &str, i64, i64)] = &[
+            ("/t", "/", 1000, 10),
+            ("/t/big", "/t", 600, 4),
+            ("/t/big/sub", "/t/big", 500, 3),
+            ("/t/mid", "/t", 200, 2),
+            ("/t/small", "/t", 5, 1),
+        ];
+        for &(path, parent, size, files) in dirs {
+            insert_node(&pool, id, NodeRow { path, parent: Some(parent), size, files, ..Default::default() })
+                .await;
+        }
+        for (path, size) in [("/t/a.bin", 100), ("/t/b.bin", 50), ("/t/big/c.bin", 100)] {
+            let parent = path.rsplit_once('/').map(|(p, _)| p);
+            insert_file(&pool, id, FileRow { path, parent, size, ..Default::default() }).await;
+        }
+
+        let q = TreemapQuery { path: Some("/t".into()), ..Default::default() };
+        let map = treemap(&pool, id, &q).await.unwrap();
+        assert_eq!((map.depth, map.truncated), (2, false));
+        let root = &map.root;
+        assert_eq!((root.name.as_str(), root.allocated_size), ("t", 1000));
+        let summary = |node: &TreemapNode| -> Vec<(TreemapNodeKind, String, i64)> {
+            node.children.iter().map(|c| (c.kind, c.name.clone(), c.allocated_size)).collect()
+        };
+        // "/t/small" is below 1% and lands in <other> together with 45 unaccounted bytes
+        assert_eq!(
+            summary(root),
+            vec![
+                (TreemapNodeKind::Dir, "big".into(), 600),
+                (TreemapNodeKind::Dir, "mid".into(), 200),
+                (TreemapNodeKind::Files, "<files>".into(), 150),
+                (TreemapNodeKind::Other, "<other>".into(), 50),
+            ]
+        );
+        assert_eq!((root.children[2].file_count, root.children[2].path.as_str()), (2, "/t"));
+        assert_eq!((root.children[3].coalesced, root.children[3].file_count), (1, 1));
+        assert_eq!(
+            summary(&root.children[0]),
+            vec![(TreemapNodeKind::Dir, "sub".into(), 500), (TreemapNodeKind::Files, "<files>".into(), 100)]
+        );
+        assert!(root.children[0].children[0].children.is_empty());
+        assert_eq!(summary(&root.children[1]), vec![(TreemapNodeKind::Other, "<other>".into(), 200)]);
+
+        let q = TreemapQuery { path: Some("/t".into()), depth: Some(1), min_fraction: Some(0.5) };
+        let map = treemap(&pool, id, &q).await.unwrap();
+        assert_eq!(
+            summary(&map.root),
+            vec![(TreemapNodeKind::Dir, "big".into(), 600), (TreemapNodeKind::Other, "<other>".into(), 400)]
+        );
+        assert!(map.root.children[0].children.is_empty());
+
+        for q in [
+            TreemapQuery { path: Some("/t".into()), depth: Some(0), ..Default::default() },
+            TreemapQuery { path: Some("/t".into()), min_fraction: Some(1.0), ..Default::default() },
+        ] {
+            assert!(matches!(treemap(&pool, id, &q).await, Err(AppError::BadRequest(_))));
+        }
+        let q = TreemapQuery { path: Some("/nowhere".into()), ..Default::default() };
+        assert!(matches!(treemap(&pool, id, &q).await, Err(AppError::NotFound(_))));
+        // The fixture scan has a single root, which is mapped when the path is omitted
+        let q = TreemapQuery { path: None, ..Default::default() };
+        assert!(matches!(treemap(&pool, id, &q).await, Err(AppError::NotFound(_))));
+    }
+}
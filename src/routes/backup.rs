@@ -0,0 +1,173 @@
+//! Database backup API endpoints.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /admin/backup` - Download a gzip-compressed copy of the database
+//! - `POST /admin/backup/restore` - Replace the database with an uploaded backup
+//!
+//! A backup holds the scans of every namespace, so both endpoints refuse
+//! requests authenticated with a token bound to any namespace but the admin
+//! one. See [`crate::backup`] for how backups are taken and restored.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{
+    backup,
+    error::{AppError, AppResult},
+    middleware::{
+        ip::{extract_ip_from_headers, MaybeRemoteAddr},
+        namespace::Namespace,
+    },
+    state::AppState,
+    types::RestoreResponse,
+};
+
+/// The response header with the uncompressed size of a backup in bytes.
+pub const BACKUP_SIZE_HEADER: &str = "x-backup-size";
+
+/// The response header with the number of scans in a backup.
+pub const BACKUP_SCANS_HEADER: &str = "x-backup-scan-count";
+
+/// Fails unless the request may use the endpoints that act on every namespace.
+///
+/// Global tokens, tokens bound to the admin namespace and requests while auth
+/// is disabled pass; tokens bound to any other namespace get `Forbidden`.
+pub(crate) fn require_admin(req: &Request) -> AppResult<()> {
+    // The auth middleware only inserts a namespace for namespace-bound tokens
+    match req.extensions().get::<Namespace>() {
+        Some(ns) if !ns.is_admin() => Err(AppError::Forbidden(format!(
+            "the admin endpoints are not available to tokens of the namespace '{}'",
+            ns.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the 429 response if the client exceeded the limit of `endpoint`.
+pub(crate) async fn check_rate_limit(
+    state: &AppState,
+    endpoint: &str,
+    maybe_remote: &MaybeRemoteAddr,
+    headers: &HeaderMap,
+) -> Option<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(headers, fallback_ip);
+    state.rate_limiter.check_endpoint_limit(endpoint, ip).await.err().map(IntoResponse::into_response)
+}
+
+/// Streams a gzip-compressed copy of the database.
+///
+/// The uncompressed size and the number of scans are sent in the
+/// `X-Backup-Size` and `X-Backup-Scan-Count` headers.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `req` - The request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - The backup as `application/gzip` attachment, or
+///   `Conflict` while a scan job is running.
+pub async fn get_backup(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, "/admin/backup", &maybe_remote, req.headers()).await {
+        return Ok(limited);
+    }
+    backup::ensure_idle(&state, false).await?;
+
+    let snapshot = backup::snapshot(&state.db).await?;
+    let (size_bytes, scan_count) = (snapshot.size_bytes, snapshot.scan_count);
+    tracing::info!(target: "audit", "Database backup taken ({} bytes, {} scans)", size_bytes, scan_count);
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, HeaderValue::from_static("application/gzip"))
+        .header(BACKUP_SIZE_HEADER, size_bytes)
+        .header(BACKUP_SCANS_HEADER, scan_count)
+        .body(Body::from_stream(backup::stream_gzip(snapshot)))
+        .unwrap();
+    let filename =
+        format!("attachment; filename=\"speicherwald_{}.db.gz\"", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+    if let Ok(header_val) = HeaderValue::from_str(&filename) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, header_val);
+    }
+    Ok(response)
+}
+
+/// Replaces the database with an uploaded backup.
+///
+/// The request body is the database file, gzip-compressed (as sent by
+/// `GET /admin/backup`) or plain.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `req` - The request with the backup as body.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `RestoreResponse`.
+pub async fn restore_backup(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, backup::RESTORE_PATH, &maybe_remote, req.headers()).await
+    {
+        return Ok(limited);
+    }
+
+    let started = std::time::Instant::now();
+    let outcome = backup::restore(&state, req.into_body()).await?;
+    Ok(Json(RestoreResponse {
+        size_bytes: outcome.size_bytes,
+        scan_count: outcome.scan_count,
+        duration_ms: started.elapsed().as_millis(),
+    })
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::AppConfig, test_support::test_db};
+    use axum::http::StatusCode;
+
+    fn request(token_namespace: Option<&str>) -> Request {
+        let mut req = Request::builder().uri("/admin/backup").body(Body::empty()).unwrap();
+        if let Some(ns) = token_namespace {
+            req.extensions_mut().insert(Namespace::parse(ns).unwrap());
+        }
+        req
+    }
+
+    #[tokio::test]
+    async fn backups_are_forbidden_for_namespace_bound_tokens() {
+        let (_dir, pool) = test_db().await;
+        let state = AppState::new(pool, AppConfig::default());
+
+        let denied = get_backup(State(state.clone()), MaybeRemoteAddr(None), request(Some("hr"))).await;
+        assert_eq!(denied.unwrap_err().into_response().status(), StatusCode::FORBIDDEN);
+        let denied = restore_backup(State(state.clone()), MaybeRemoteAddr(None), request(Some("hr"))).await;
+        assert_eq!(denied.unwrap_err().into_response().status(), StatusCode::FORBIDDEN);
+
+        // Global tokens and tokens of the admin namespace get the backup
+        for ns in [None, Some("admin")] {
+            let res = get_backup(State(state.clone()), MaybeRemoteAddr(None), request(ns)).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+    }
+}
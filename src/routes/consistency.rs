@@ -0,0 +1,239 @@
+//! Directory total consistency API endpoints.
+//!
+//! Every completed scan checks that each directory's totals equal the sum of
+//! its subdirectories and own files and records the directories that do not
+//! add up (see [`crate::scanner::consistency`]). These endpoints run the check
+//! again, optionally repair the totals and list what was recorded.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/recompute?repair=` - Check the directory totals, and rewrite them with `repair=true`
+//! - `GET /scans/{id}/inconsistencies` - List the recorded directories, deepest first
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::parse_options,
+    scanner::consistency,
+    state::AppState,
+    types::{RecomputeResponse, ScanInconsistency},
+};
+
+/// The most inconsistent directories returned by the recompute endpoint.
+const LISTED_MAX: i64 = 100;
+
+/// Query parameters for the recompute endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct RecomputeQuery {
+    /// Whether to rewrite the totals that do not add up.
+    pub repair: Option<bool>,
+}
+
+/// Checks the directory totals of a scan and optionally repairs them.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - Whether to repair.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `RecomputeResponse`
+///   with the first [`LISTED_MAX`] inconsistent directories. `Conflict` while a
+///   job for the scan is running.
+pub async fn recompute_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<RecomputeQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    if state.jobs.read().await.contains_key(&id) {
+        return Err(AppError::Conflict("cannot recompute a scan while a job for it is running".into()));
+    }
+    let options: String = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scan not found".into()))?;
+    let measure_logical = parse_options(id, &options).is_none_or(|o| o.measure_logical);
+
+    let inconsistencies = consistency::record(&state.db, id, measure_logical).await?;
+    let repaired = if q.repair.unwrap_or(false) && inconsistencies > 0 {
+        consistency::repair(&state.db, id, measure_logical).await?
+    } else {
+        0
+    };
+    tracing::info!(scan_id = %id, inconsistencies, repaired, "directory totals recomputed");
+    let items = load_inconsistencies(&state.db, id, LISTED_MAX).await?;
+    Ok(Json(RecomputeResponse { scan_id: id, inconsistencies, repaired, items }))
+}
+
+/// Lists the directories of a scan whose totals did not add up at the last check.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON array of `ScanInconsistency`, deepest first.
+pub async fn list_inconsistencies(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    Ok(Json(load_inconsistencies(state.read_pool(), id, -1).await?))
+}
+
+/// Loads up to `limit` recorded directories of a scan; a negative limit loads all.
+async fn load_inconsistencies(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    limit: i64,
+) -> AppResult<Vec<ScanInconsistency>> {
+    let rows = sqlx::query(
+        "SELECT path, depth, logical_size, expected_logical_size, allocated_size, expected_allocated_size, \
+         file_count, expected_file_count, dir_count, expected_dir_count, detected_at, repaired_at \
+         FROM scan_inconsistencies WHERE scan_id=?1 ORDER BY depth DESC, path LIMIT ?2",
+    )
+    .bind(id.to_string())
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| ScanInconsistency {
+            path: r.get("path"),
+            depth: r.get("depth"),
+            logical_size: r.get("logical_size"),
+            expected_logical_size: r.get("expected_logical_size"),
+            allocated_size: r.get("allocated_size"),
+            expected_allocated_size: r.get("expected_allocated_size"),
+            file_count: r.get("file_count"),
+            expected_file_count: r.get("expected_file_count"),
+            dir_count: r.get("dir_count"),
+            expected_dir_count: r.get("expected_dir_count"),
+            detected_at: r.get("detected_at"),
+            repaired_at: r.get("repaired_at"),
+        })
+        .collect())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use http_body_util::BodyExt;
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan, test_db_in};
+    use crate::types::ScanOptions;
+
+    async fn recompute(state: &AppState, id: Uuid, repair: bool) -> RecomputeResponse {
+        let q = RecomputeQuery { repair: Some(repair) };
+        let res = recompute_scan(State(state.clone()), Namespace::default(), Path(id), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scanned_totals_add_up_and_corrupted_ones_are_repaired() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::write(root.join("top.bin"), vec![1u8; 3000]).unwrap();
+        std::fs::write(root.join("a/mid.bin"), vec![1u8; 5000]).unwrap();
+        std::fs::write(root.join("a/b/c/deep.bin"), vec![1u8; 7000]).unwrap();
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let (tx, _rx) = broadcast::channel(1024);
+        crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            ScanOptions::default(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            100,
+            200,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE scans SET status='done' WHERE id=?1")
+            .bind(id.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let state = AppState::new(pool.clone(), AppConfig::default());
+
+        // The scan's own check found nothing
+        let r = recompute(&state, id, false).await;
+        assert_eq!((r.inconsistencies, r.repaired), (0, 0));
+
+        let a = root.join("a").to_string_lossy().to_string();
+        let c = root.join("a/b/c").to_string_lossy().to_string();
+        let totals = |path: String| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_as::<_, (i64, i64, i64)>(
+                    "SELECT logical_size, file_count, dir_count FROM nodes WHERE scan_id=?1 AND path=?2",
+                )
+                .bind(id.to_string())
+                .bind(path)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+        let before = (totals(a.clone()).await, totals(c.clone()).await);
+        sqlx::query(
+            "UPDATE nodes SET logical_size=1, allocated_size=1, file_count=0 WHERE scan_id=?1 AND path=?2",
+        )
+        .bind(id.to_string())
+        .bind(&c)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let r = recompute(&state, id, false).await;
+        assert_eq!((r.inconsistencies, r.repaired), (2, 0));
+        assert_eq!(r.items[0].path, c);
+        assert_eq!(r.items[0].expected_logical_size, 7000);
+        assert_eq!(r.items[0].repaired_at, None);
+
+        let r = recompute(&state, id, true).await;
+        assert_eq!(r.inconsistencies, 2);
+        assert!(r.repaired >= 1);
+        assert!(r.items.iter().all(|i| i.repaired_at.is_some()));
+        assert_eq!((totals(a).await, totals(c).await), before);
+        assert_eq!(recompute(&state, id, false).await.inconsistencies, 0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Scan comparison API endpoint.
+//!
+//! Compares the directory trees of two scans (typically the same root scanned
+//! at different times) and reports per-directory growth.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/diff?other={other_id}` - Compare scan `id` (newer) against `other` (older)
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::{resolve_query_path, subtree_like_pattern},
+    state::AppState,
+    types::{DiffItem, DiffResponse, DiffStatus, DiffValues},
+};
+
+const DIFF_LIMIT_MAX: i64 = 5_000;
+
+/// Query parameters for the diff endpoint.
+#[derive(Debug, serde::Deserialize)]
+pub struct DiffQuery {
+    /// The older scan to compare against.
+    pub other: Uuid,
+    /// The sort key: "absolute" (growth in bytes, default) or "relative" (growth in percent).
+    pub sort: Option<String>,
+    /// The sort direction: "desc" (largest growth first, default) or "asc" (largest shrinkage first).
+    pub order: Option<String>,
+    /// The maximum number of directories to return.
+    pub limit: Option<i64>,
+    /// An optional path to restrict the comparison to a subtree.
+    pub path: Option<String>,
+}
+
+/// Compares two scans directory by directory.
+///
+/// Directories are matched by path. Directories that exist in only one of the
+/// scans are reported as `added` or `removed`. If either scan was run without
+/// measuring allocated sizes, sorting and relative growth fall back to the
+/// logical size and the response is flagged with `allocated_comparable: false`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request; both scans must be visible from it.
+/// * `id` - The ID of the newer scan.
+/// * `q` - The diff query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `DiffResponse`.
+pub async fn diff_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<DiffQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    ns.ensure_scan(state.read_pool(), q.other).await?;
+    // Keep both scans from being pruned while they are compared
+    let _leases = (state.scan_leases.acquire(id), state.scan_leases.acquire(q.other));
+    Ok(Json(diff_scans(state.read_pool(), id, &q).await?))
+}
+
+/// Returns whether a scan measured allocated sizes, based on its stored options.
+///
+/// Scans whose options can't be read are assumed to have used the defaults.
+fn measured_allocated(options_json: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(options_json)
+        .ok()
+        .and_then(|v| v.get("measure_allocated").and_then(|m| m.as_bool()))
+        .unwrap_or(true)
+}
+
+async fn scan_options(pool: &sqlx::SqlitePool, id: Uuid, what: &str) -> AppResult<String> {
+    let row = sqlx::query("SELECT options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("{} not found", what)))?;
+    Ok(row.get("options"))
+}
+
+fn push_subtree_filter(qb: &mut QueryBuilder<'_, Sqlite>, alias: &str, subtree: Option<&str>) {
+    if let Some(root) = subtree {
+        qb.push(format!(" AND ({}.path = ", alias)).push_bind(root.to_string());
+        qb.push(format!(" OR {}.path LIKE ", alias)).push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+}
+
+fn values(r: &SqliteRow, prefix: &str) -> Option<DiffValues> {
+    Some(DiffValues {
+        logical_size: r.get::<Option<i64>, _>(format!("{}_logical", prefix).as_str())?,
+        allocated_size: r.get::<Option<i64>, _>(format!("{}_alloc", prefix).as_str()).unwrap_or(0),
+        file_count: r.get::<Option<i64>, _>(format!("{}_files", prefix).as_str()).unwrap_or(0),
+        dir_count: r.get::<Option<i64>, _>(format!("{}_dirs", prefix).as_str()).unwrap_or(0),
+    })
+}
+
+fn diff_item(path: String, old: Option<DiffValues>, new: Option<DiffValues>, use_allocated: bool) -> DiffItem {
+    let o = old.unwrap_or_default();
+    let n = new.unwrap_or_default();
+    let delta = DiffValues {
+        logical_size: n.logical_size - o.logical_size,
+        allocated_size: n.allocated_size - o.allocated_size,
+        file_count: n.file_count - o.file_count,
+        dir_count: n.dir_count - o.dir_count,
+    };
+    let status = match (old, new) {
+        (None, _) => DiffStatus::Added,
+        (_, None) => DiffStatus::Removed,
+        (Some(a), Some(b)) if a == b => DiffStatus::Unchanged,
+        _ => DiffStatus::Changed,
+    };
+    let (old_size, delta_size) =
+        if use_allocated { (o.allocated_size, delta.allocated_size) } else { (o.logical_size, delta.logical_size) };
+    let relative_growth = (old.is_some() && old_size > 0).then(|| delta_size as f64 / old_size as f64);
+    DiffItem { path, status, old, new, delta, relative_growth }
+}
+
+/// Computes the comparison behind `GET /scans/{id}/diff`.
+///
+/// # Arguments
+///
+/// * `pool` - The pool to query.
+/// * `id` - The ID of the newer scan.
+/// * `q` - The diff query parameters; `q.other` is the older scan.
+///
+/// # Returns
+///
+/// * `AppResult<DiffResponse>` - The compared directories, sorted and limited.
+pub async fn diff_scans(pool: &sqlx::SqlitePool, id: Uuid, q: &DiffQuery) -> AppResult<DiffResponse> {
+    if q.other == id {
+        return Err(AppError::BadRequest("other must be a different scan".into()));
+    }
+    let relative = match q.sort.as_deref() {
+        None | Some("absolute") => false,
+        Some("relative") => true,
+        Some(_) => return Err(AppError::BadRequest("sort must be 'absolute' or 'relative'".into())),
+    };
+    let dir = match q.order.as_deref() {
+        None | Some("desc") => "DESC",
+        Some("asc") => "ASC",
+        Some(_) => return Err(AppError::BadRequest("order must be 'asc' or 'desc'".into())),
+    };
+    let limit = q.limit.unwrap_or(200).clamp(1, DIFF_LIMIT_MAX);
+    let subtree = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(pool, id, p).await?)
+        }
+        None => None,
+    };
+
+    let new_options = scan_options(pool, id, "scan").await?;
+    let old_options = scan_options(pool, q.other, "other scan").await?;
+    let allocated_comparable = measured_allocated(&new_options) && measured_allocated(&old_options);
+    let (size_basis, col) = if allocated_comparable { ("allocated", "alloc") } else { ("logical", "logical") };
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT path, o_logical, o_alloc, o_files, o_dirs, n_logical, n_alloc, n_files, n_dirs FROM (\
+         SELECT n.path AS path, o.logical_size AS o_logical, o.allocated_size AS o_alloc, \
+         o.file_count AS o_files, o.dir_count AS o_dirs, n.logical_size AS n_logical, \
+         n.allocated_size AS n_alloc, n.file_count AS n_files, n.dir_count AS n_dirs \
+         FROM nodes n LEFT JOIN nodes o ON o.is_dir=1 AND o.path=n.path AND o.scan_id=",
+    );
+    qb.push_bind(q.other.to_string());
+    qb.push(" WHERE n.is_dir=1 AND n.scan_id=").push_bind(id.to_string());
+    push_subtree_filter(&mut qb, "n", subtree.as_deref());
+    qb.push(
+        " UNION ALL SELECT o.path, o.logical_size, o.allocated_size, o.file_count, o.dir_count, \
+         NULL, NULL, NULL, NULL FROM nodes o WHERE o.is_dir=1 AND o.scan_id=",
+    );
+    qb.push_bind(q.other.to_string());
+    push_subtree_filter(&mut qb, "o", subtree.as_deref());
+    qb.push(" AND NOT EXISTS (SELECT 1 FROM nodes n WHERE n.is_dir=1 AND n.path=o.path AND n.scan_id=");
+    qb.push_bind(id.to_string()).push("))");
+
+    // Column names come from the fixed set above, never from user input
+    let new_size = format!("COALESCE(n_{}, 0)", col);
+    let old_size = format!("COALESCE(o_{}, 0)", col);
+    if relative {
+        // Directories that grew from nothing sort as infinite growth
+        qb.push(format!(
+            " ORDER BY CASE WHEN {old} = 0 THEN (CASE WHEN {new} > 0 THEN 1e308 ELSE 0 END) \
+             ELSE ({new} - {old}) * 1.0 / {old} END {dir}, path ASC",
+            old = old_size,
+            new = new_size,
+            dir = dir
+        ));
+    } else {
+        qb.push(format!(" ORDER BY ({} - {}) {}, path ASC", new_size, old_size, dir));
+    }
+    qb.push(" LIMIT ").push_bind(limit);
+
+    let rows = qb.build().fetch_all(pool).await?;
+    let items = rows
+        .iter()
+        .map(|r| diff_item(r.get("path"), values(r, "o"), values(r, "n"), allocated_comparable))
+        .collect();
+
+    Ok(DiffResponse { scan_id: id, other_id: q.other, size_basis: size_basis.to_string(), allocated_comparable, items })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_scan_with_options, test_db};
+
+    async fn insert_scan(pool: &sqlx::SqlitePool, options: &str, dirs: &[(&str, i64, i64)]) -> Uuid {
+        let options: serde_json::Value = serde_json::from_str(options).unwrap();
+        let id = insert_scan_with_options(pool, "done", &["/data"], "default", &options).await;
+        for &(path, logical, allocated) in dirs {
+            sqlx::query(
+                r#"INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count)
+                   VALUES (?1, ?2, NULL, 0, 1, ?3, ?4, 1, 0)"#,
+            )
+            .bind(id.to_string())
+            .bind(path)
+            .bind(logical)
+            .bind(allocated)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        id
+    }
+
+    fn query(other: Uuid) -> DiffQuery {
+        DiffQuery { other, sort: None, order: None, limit: None, path: None }
+    }
+
+    fn find<'a>(resp: &'a DiffResponse, path: &str) -> &'a DiffItem {
+        resp.items.iter().find(|i| i.path == path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn diff_marks_added_removed_and_sorts_by_growth() {
+        let (_dir, pool) = test_db().await;
+        let old = insert_scan(
+            &pool,
+            r#"{"measure_allocated":true}"#,
+            &[("/data", 300, 300), ("/data/a", 100, 100), ("/data/b", 200, 200), ("/data/gone", 50, 50)],
+        )
+        .await;
+        let new = insert_scan(
+            &pool,
+            r#"{"measure_allocated":true}"#,
+            &[("/data", 800, 800), ("/data/a", 400, 400), ("/data/b", 100, 100), ("/data/new", 300, 300)],
+        )
+        .await;
+
+        let resp = diff_scans(&pool, new, &query(old)).await.unwrap();
+        assert_eq!(resp.size_basis, "allocated");
+        assert!(resp.allocated_comparable);
+        let paths: Vec<&str> = resp.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["/data", "/data/a", "/data/new", "/data/gone", "/data/b"]);
+
+        let added = find(&resp, "/data/new");
+        assert_eq!(added.status, DiffStatus::Added);
+        assert!(added.old.is_none());
+        assert_eq!(added.delta.allocated_size, 300);
+        assert_eq!(added.relative_growth, None);
+
+        let removed = find(&resp, "/data/gone");
+        assert_eq!(removed.status, DiffStatus::Removed);
+        assert!(removed.new.is_none());
+        assert_eq!(removed.delta.allocated_size, -50);
+        assert_eq!(removed.relative_growth, Some(-1.0));
+
+        assert_eq!(find(&resp, "/data/a").relative_growth, Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn diff_relative_sort_path_filter_and_limit() {
+        let (_dir, pool) = test_db().await;
+        let old = insert_scan(&pool, "{}", &[("/data/a", 100, 100), ("/data/b", 10, 10), ("/other", 1, 1)]).await;
+        let new = insert_scan(
+            &pool,
+            "{}",
+            &[("/data/a", 200, 200), ("/data/b", 50, 50), ("/data/c", 5, 5), ("/other", 1000, 1000)],
+        )
+        .await;
+
+        let mut q = query(old);
+        q.sort = Some("relative".into());
+        q.path = Some("/data".into());
+        let resp = diff_scans(&pool, new, &q).await.unwrap();
+        let paths: Vec<&str> = resp.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["/data/c", "/data/b", "/data/a"]);
+
+        q.limit = Some(1);
+        q.order = Some("asc".into());
+        let resp = diff_scans(&pool, new, &q).await.unwrap();
+        assert_eq!(resp.items.len(), 1);
+        assert_eq!(resp.items[0].path, "/data/a");
+    }
+
+    #[tokio::test]
+    async fn diff_falls_back_to_logical_size() {
+        let (_dir, pool) = test_db().await;
+        let old = insert_scan(&pool, r#"{"measure_allocated":true}"#, &[("/data/a", 100, 4096), ("/data/b", 100, 4096)])
+            .await;
+        let new =
+            insert_scan(&pool, r#"{"measure_allocated":false}"#, &[("/data/a", 150, 0), ("/data/b", 500, 0)]).await;
+
+        let resp = diff_scans(&pool, new, &query(old)).await.unwrap();
+        assert_eq!(resp.size_basis, "logical");
+        assert!(!resp.allocated_comparable);
+        assert_eq!(resp.items[0].path, "/data/b");
+        assert_eq!(resp.items[0].relative_growth, Some(4.0));
+    }
+
+    #[tokio::test]
+    async fn diff_rejects_unknown_or_identical_scans() {
+        let (_dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "{}", &[]).await;
+        assert!(matches!(diff_scans(&pool, id, &query(id)).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(diff_scans(&pool, id, &query(Uuid::new_v4())).await, Err(AppError::NotFound(_))));
+    }
+}
@@ -7,20 +7,32 @@
 //! ## Features
 //!
 //! - **Windows**: Full drive enumeration with type detection and space information
+//! - **Volume Details**: Label, filesystem, serial number and BitLocker state with
+//!   `?detailed=true`, since these need extra system calls per volume
 //! - **Network Drives**: Timeout-protected network drive queries
 //! - **Cross-platform**: Graceful fallback on non-Windows systems
 //! - **Rate Limiting**: Per-endpoint rate limiting to prevent abuse
+//! - **History**: Free space samples recorded by [`crate::drive_history`],
+//!   served downsampled by `GET /drives/history?path=C:\&hours=168`
 
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::HeaderMap,
     response::{IntoResponse, Response},
     Json,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::error::{AppError, AppResult};
 use crate::state::AppState;
-use crate::{middleware::ip::{extract_ip_from_headers, MaybeRemoteAddr}, types::DriveInfo};
+use crate::{
+    drive_history,
+    middleware::ip::{extract_ip_from_headers, MaybeRemoteAddr},
+    types::{DriveHistoryResponse, DriveInfo},
+};
+
+const HISTORY_HOURS_DEFAULT: u32 = 168;
+const HISTORY_HOURS_MAX: u32 = 24 * 366;
 
 /// Response structure for the drives listing endpoint.
 ///
@@ -32,36 +44,139 @@ struct DrivesResponse {
     items: Vec<DriveInfo>,
 }
 
-/// (Windows specific) Lists the available drives and their storage information.
+/// Query parameters for the drives listing endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct DrivesQuery {
+    /// Also read label, filesystem, serial number and BitLocker state of each volume.
+    #[serde(default)]
+    pub detailed: bool,
+}
+
+/// Query parameters for the drive history endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct DriveHistoryQuery {
+    /// The drive, e.g. `C:\` or `c:`.
+    pub path: String,
+    /// How many hours back to look; defaults to a week.
+    pub hours: Option<u32>,
+}
+
+/// Volume metadata returned with `?detailed=true`.
+#[cfg(windows)]
+#[derive(Debug, Default)]
+struct VolumeDetails {
+    label: Option<String>,
+    filesystem: Option<String>,
+    serial_number: Option<String>,
+    bitlocker: Option<bool>,
+}
+
+/// Lists the available drives and their storage information.
 ///
-/// This function uses the Windows API to enumerate logical drives and retrieve
-/// their type, total size, and free space.
+/// With `?detailed=true` the volume label, filesystem, serial number and
+/// BitLocker state are read as well. If the drives cannot be enumerated the
+/// list is empty.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
 /// * `maybe_remote` - The optional remote address of the client.
 /// * `headers` - The request headers.
+/// * `query` - Whether to include volume details.
 ///
 /// # Returns
 ///
 /// * `Response` - A JSON response containing a list of `DriveInfo` objects.
-#[cfg(windows)]
 pub async fn list_drives(
     State(state): State<AppState>,
     maybe_remote: MaybeRemoteAddr,
     headers: HeaderMap,
+    Query(query): Query<DrivesQuery>,
 ) -> Response {
-    use std::time::Duration;
-    use windows::core::PCWSTR;
-    use windows::Win32::Storage::FileSystem::{GetDriveTypeW, GetLogicalDrives};
-
     // Per-endpoint rate limit: "/drives"
     let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
     let ip = extract_ip_from_headers(&headers, fallback_ip);
-    if let Err((status, body)) = state.rate_limiter.check_endpoint_limit("/drives", ip).await {
-        return (status, body).into_response();
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/drives", ip).await {
+        return limited.into_response();
+    }
+    let items = enumerate_drives(query.detailed).await.unwrap_or_else(|e| {
+        tracing::error!("Drive enumeration failed: {}", e);
+        Vec::new()
+    });
+    Json(DrivesResponse { items }).into_response()
+}
+
+/// Retrieves the free space history of a drive.
+///
+/// The samples are recorded by the background sampler of
+/// [`crate::drive_history`] and downsampled to at most
+/// [`drive_history::MAX_POINTS`] points. A drive without samples yields an
+/// empty list.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+/// * `query` - The drive and the number of hours to cover.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `DriveHistoryResponse`.
+pub async fn get_drive_history(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+    Query(query): Query<DriveHistoryQuery>,
+) -> AppResult<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/drives/history", ip).await {
+        return Ok(limited.into_response());
     }
+    let path = normalize_drive_path(&query.path)?;
+    let hours = query.hours.unwrap_or(HISTORY_HOURS_DEFAULT).clamp(1, HISTORY_HOURS_MAX);
+    let now = chrono::Utc::now().timestamp();
+    let since = now - i64::from(hours) * 3_600;
+    let samples = drive_history::history(state.read_pool(), &path, since, now, drive_history::MAX_POINTS)
+        .await?;
+    Ok(Json(DriveHistoryResponse { path, hours, samples }).into_response())
+}
+
+/// Normalizes a drive path to the form the enumeration reports, e.g. `c:` to `C:\`.
+fn normalize_drive_path(raw: &str) -> AppResult<String> {
+    let mut path = raw.trim().replace('/', "\\");
+    if path.is_empty() || path.len() > 260 {
+        return Err(AppError::BadRequest("path must name a drive, e.g. C:\\".into()));
+    }
+    if path.len() == 2 && path.ends_with(':') {
+        path.push('\\');
+    }
+    if path.len() == 3 && path.as_bytes()[0].is_ascii_alphabetic() && path.ends_with(":\\") {
+        path = path.to_ascii_uppercase();
+    }
+    Ok(path)
+}
+
+/// (Windows specific) Enumerates the drives and their storage information.
+///
+/// This function uses the Windows API to enumerate logical drives and retrieve
+/// their type, total size, and free space. It is shared by `GET /drives` and
+/// the free space sampler of [`crate::drive_history`]. Drives whose space
+/// cannot be read in time are reported with zero sizes.
+///
+/// # Arguments
+///
+/// * `detailed` - Whether to read label, filesystem, serial number and BitLocker state.
+///
+/// # Returns
+///
+/// * `anyhow::Result<Vec<DriveInfo>>` - The drives, or an error if they cannot be enumerated.
+#[cfg(windows)]
+pub async fn enumerate_drives(detailed: bool) -> anyhow::Result<Vec<DriveInfo>> {
+    use std::time::Duration;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{GetDriveTypeW, GetLogicalDrives};
 
     // 1. Enumerate drives and types (fast, blocking)
     let drive_candidates = tokio::task::spawn_blocking(move || {
@@ -69,8 +184,7 @@ pub async fn list_drives(
         unsafe {
             let mask = GetLogicalDrives();
             if mask == 0 {
-                tracing::error!("GetLogicalDrives failed");
-                return candidates;
+                anyhow::bail!("GetLogicalDrives failed: {}", std::io::Error::last_os_error());
             }
             for i in 0..26u32 {
                 if (mask & (1u32 << i)) == 0 { continue; }
@@ -88,13 +202,9 @@ pub async fn list_drives(
                 candidates.push((path, type_str.to_string(), dtype == 4)); // dtype 4 is network
             }
         }
-        candidates
+        Ok(candidates)
     })
-    .await
-    .unwrap_or_else(|e| {
-        tracing::error!("Drive enumeration failed: {}", e);
-        Vec::new()
-    });
+    .await??;
 
     // 2. Query space info with bounded concurrency (async)
     // FIX Bug #5: Use buffer_unordered to limit concurrent threads
@@ -108,7 +218,7 @@ pub async fn list_drives(
         .map(|(path, drive_type, is_network)| async move {
             let _permit = sem.acquire().await; // Global limit
             let path_clone = path.clone();
-            let space_info = if is_network {
+            let (space_info, details) = if is_network {
                 // Network drive: with timeout
                 let timeout_ms = std::env::var("SPEICHERWALD_NETWORK_DRIVE_TIMEOUT_MS")
                     .ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(1000).clamp(100, 5000);
@@ -116,7 +226,7 @@ pub async fn list_drives(
                 let result = tokio::time::timeout(
                     Duration::from_millis(timeout_ms),
                     tokio::task::spawn_blocking(move || {
-                        get_drive_space(&path_clone)
+                        (get_drive_space(&path_clone), detailed.then(|| volume_details(&path_clone)))
                     })
                 ).await;
 
@@ -124,35 +234,40 @@ pub async fn list_drives(
                     Ok(Ok(info)) => info, // Success
                     Ok(Err(e)) => { // Panic in task
                         tracing::error!("Drive space task panicked: {}", e);
-                        (0, 0, 0)
+                        ((0, 0, 0), None)
                     },
                     Err(_) => { // Timeout
                         // tracing::warn!("Timeout getting space for {}", path); 
-                        (0, 0, 0) 
+                        ((0, 0, 0), None)
                     }
                 }
             } else {
                 // Local drive: plain blocking
                  tokio::task::spawn_blocking(move || {
-                    get_drive_space(&path_clone)
-                }).await.unwrap_or((0, 0, 0))
+                    (get_drive_space(&path_clone), detailed.then(|| volume_details(&path_clone)))
+                }).await.unwrap_or(((0, 0, 0), None))
             };
+            let details = details.unwrap_or_default();
             
             DriveInfo {
                 path,
                 drive_type,
                 total_bytes: space_info.1,
                 free_bytes: space_info.2,
+                label: details.label,
+                filesystem: details.filesystem,
+                serial_number: details.serial_number,
+                bitlocker: details.bitlocker,
             }
         })
         .buffer_unordered(8) // process at most 8 drives concurrently
         .collect::<Vec<_>>()
         .await;
 
-
-    Json(DrivesResponse { items }).into_response()
+    Ok(items)
 }
 
+
 #[cfg(windows)]
 fn get_drive_space(path: &str) -> (u64, u64, u64) {
     use windows::core::PCWSTR;
@@ -173,32 +288,85 @@ fn get_drive_space(path: &str) -> (u64, u64, u64) {
     (free, total, total_free)
 }
 
-/// (Non-Windows) Fallback implementation for listing drives.
+/// Reads label, filesystem and serial number of a volume via `GetVolumeInformationW`
+/// and its BitLocker state from the shell.
+#[cfg(windows)]
+fn volume_details(path: &str) -> VolumeDetails {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    fn wide_to_string(buf: &[u16]) -> Option<String> {
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        (len > 0).then(|| String::from_utf16_lossy(&buf[..len]))
+    }
+
+    let w: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut label = [0u16; 261];
+    let mut fs_name = [0u16; 261];
+    let mut serial = 0u32;
+    let ok = unsafe {
+        GetVolumeInformationW(
+            PCWSTR(w.as_ptr()),
+            Some(&mut label),
+            Some(&mut serial),
+            None,
+            None,
+            Some(&mut fs_name),
+        )
+        .is_ok()
+    };
+    if !ok {
+        // e.g. an empty card reader or a disconnected network drive
+        return VolumeDetails { bitlocker: bitlocker_state(&w), ..Default::default() };
+    }
+    VolumeDetails {
+        label: wide_to_string(&label),
+        filesystem: wide_to_string(&fs_name),
+        serial_number: Some(format!("{:04X}-{:04X}", serial >> 16, serial & 0xFFFF)),
+        bitlocker: bitlocker_state(&w),
+    }
+}
+
+/// Reads the shell property `System.Volume.BitLockerProtection` of a volume root.
+///
+/// This is what Explorer uses for its lock icon and, unlike WMI, works without
+/// administrator rights. `None` if the property is unavailable.
+#[cfg(windows)]
+fn bitlocker_state(root: &[u16]) -> Option<bool> {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::PROPERTYKEY;
+    use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+    use windows::Win32::UI::Shell::PropertiesSystem::{IPropertyStore, PSGetPropertyKeyFromName, GPS_DEFAULT};
+    use windows::Win32::UI::Shell::SHGetPropertyStoreFromParsingName;
+
+    unsafe {
+        let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let state = (|| -> windows::core::Result<i32> {
+            let mut key = PROPERTYKEY::default();
+            PSGetPropertyKeyFromName(w!("System.Volume.BitLockerProtection"), &mut key)?;
+            let store: IPropertyStore =
+                SHGetPropertyStoreFromParsingName(PCWSTR(root.as_ptr()), None, GPS_DEFAULT)?;
+            let value = store.GetValue(&key)?;
+            i32::try_from(&value)
+        })();
+        if initialized {
+            CoUninitialize();
+        }
+        // 2 is "off"; "on" (1) and the encrypting, suspended and locked states all mean BitLocker is in use
+        match state {
+            Ok(0) | Err(_) => None,
+            Ok(2) => Some(false),
+            Ok(_) => Some(true),
+        }
+    }
+}
+
+/// (Non-Windows) Fallback implementation for enumerating drives.
 ///
 /// This function returns an empty list of drives, as the drive enumeration
 /// logic is specific to the Windows API.
-///
-/// # Arguments
-///
-/// * `state` - The application state.
-/// * `maybe_remote` - The optional remote address of the client.
-/// * `headers` - The request headers.
-///
-/// # Returns
-///
-/// * `Response` - A JSON response containing an empty list.
 #[cfg(not(windows))]
-pub async fn list_drives(
-    State(state): State<AppState>,
-    maybe_remote: MaybeRemoteAddr,
-    headers: HeaderMap,
-) -> Response {
-    // Per-endpoint rate limit: "/drives"
-    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
-    let ip = extract_ip_from_headers(&headers, fallback_ip);
-    if let Err((status, body)) = state.rate_limiter.check_endpoint_limit("/drives", ip).await {
-        return (status, body).into_response();
-    }
-    // Fallback für Nicht-Windows: leere Liste zurückgeben.
-    Json(DrivesResponse { items: Vec::new() }).into_response()
+pub async fn enumerate_drives(_detailed: bool) -> anyhow::Result<Vec<DriveInfo>> {
+    // Fallback für Nicht-Windows: leere Liste zurückgeben, daher auch keine Volume-Details.
+    Ok(Vec::new())
 }
@@ -0,0 +1,261 @@
+//! Duplicate file API endpoints.
+//!
+//! A duplicate search hashes the candidate files of a finished scan in the
+//! background (see [`crate::scanner::duplicates`]). While it runs it is
+//! registered like a scan job, so its progress is streamed by
+//! `GET /scans/{id}/events` and `DELETE /scans/{id}` cancels it.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/duplicates?min_size={bytes}` - Start a duplicate search
+//! - `GET /scans/{id}/duplicates?limit={n}&offset={n}` - List duplicate groups, largest waste first
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    scanner::duplicates::find_duplicates,
+    state::{AppState, JobHandle},
+    types::{DuplicateGroupDto, DuplicateJobResponse, DuplicatesResponse, ScanEvent},
+};
+
+const DUPLICATES_LIMIT_DEFAULT: i64 = 100;
+const DUPLICATES_LIMIT_MAX: i64 = 1_000;
+
+/// Query parameters for starting a duplicate search.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct StartDuplicatesQuery {
+    /// Files smaller than this many bytes are ignored. Defaults to `scanner.duplicate_min_size`.
+    pub min_size: Option<u64>,
+}
+
+/// Query parameters for listing duplicate groups.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct DuplicatesQuery {
+    /// The maximum number of groups to return.
+    pub limit: Option<i64>,
+    /// The number of groups to skip.
+    pub offset: Option<i64>,
+}
+
+/// Starts a duplicate search for a finished scan.
+///
+/// Previous results of the scan are replaced once the search starts. Only one
+/// job per scan can run at a time.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The search parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - `202 Accepted` with a `DuplicateJobResponse`,
+///   or `Conflict` if the scan is not finished or a job for it is already running.
+pub async fn start_duplicates(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<StartDuplicatesQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    let status: String = sqlx::query_scalar("SELECT status FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await?;
+    if status != "done" {
+        return Err(AppError::Conflict(format!("duplicate search requires a finished scan (status: {})", status)));
+    }
+    let min_size = q.min_size.unwrap_or(state.config.get().scanner.duplicate_min_size);
+
+    let (tx, _rx) = broadcast::channel::<ScanEvent>(4096);
+    let cancel = CancellationToken::new();
+    let handle = JobHandle::new(cancel.clone(), tx.clone());
+    let finished = handle.finished.clone();
+    {
+        let mut jobs = state.jobs.write().await;
+        if jobs.contains_key(&id) {
+            return Err(AppError::Conflict("a job for this scan is already running".into()));
+        }
+        jobs.insert(id, handle);
+    }
+
+    // Same worker heuristic as the scanner, clamped by the handle limit
+    let mut concurrency = num_cpus::get().max(1);
+    if let Some(h) = state.config.get().scanner.handle_limit {
+        concurrency = concurrency.min(h.max(1));
+    }
+    let db = state.db.clone();
+    let jobs_map = state.jobs.clone();
+    tokio::spawn(async move {
+        let res = find_duplicates(db, id, min_size, concurrency, tx.clone(), cancel.clone()).await;
+        match res {
+            Ok(_) if cancel.is_cancelled() => {
+                let _ = tx.send(ScanEvent::Cancelled);
+            }
+            Ok(summary) => {
+                let _ = tx.send(ScanEvent::DuplicatesDone {
+                    groups: summary.groups,
+                    wasted_bytes: summary.wasted_bytes,
+                });
+            }
+            Err(e) => {
+                tracing::error!("Duplicate search for scan {} failed: {}", id, e);
+                let _ = tx.send(ScanEvent::Failed { message: format!("{}", e) });
+            }
+        }
+        jobs_map.write().await.remove(&id);
+        finished.cancel();
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(DuplicateJobResponse { scan_id: id, status: "running".into(), min_size })))
+}
+
+/// Lists the duplicate groups of a scan, sorted by wasted bytes.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The paging parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `DuplicatesResponse`.
+pub async fn get_duplicates(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<DuplicatesQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let limit = q.limit.unwrap_or(DUPLICATES_LIMIT_DEFAULT).clamp(1, DUPLICATES_LIMIT_MAX);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let totals = sqlx::query(
+        "SELECT COUNT(*) AS group_count, COALESCE(SUM(wasted_bytes), 0) AS wasted FROM duplicates WHERE scan_id=?1",
+    )
+    .bind(id.to_string())
+    .fetch_one(state.read_pool())
+    .await?;
+    let rows = sqlx::query(
+        r#"SELECT hash, file_size, file_count, wasted_bytes, paths FROM duplicates
+           WHERE scan_id=?1
+           ORDER BY wasted_bytes DESC, id
+           LIMIT ?2 OFFSET ?3"#,
+    )
+    .bind(id.to_string())
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(state.read_pool())
+    .await?;
+
+    let mut groups = Vec::with_capacity(rows.len());
+    for r in rows {
+        let paths: String = r.get("paths");
+        groups.push(DuplicateGroupDto {
+            hash: r.get("hash"),
+            file_size: r.get("file_size"),
+            file_count: r.get("file_count"),
+            wasted_bytes: r.get("wasted_bytes"),
+            paths: serde_json::from_str(&paths)
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid duplicate paths: {}", e)))?,
+        });
+    }
+    let running = state.jobs.read().await.contains_key(&id);
+
+    Ok(Json(DuplicatesResponse {
+        scan_id: id,
+        running,
+        total_groups: totals.get("group_count"),
+        total_wasted_bytes: totals.get("wasted"),
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_file, insert_scan, test_db, FileRow};
+
+    async fn fixture() -> (tempfile::TempDir, AppState, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &[], "hr").await;
+
+        // (name, size, content byte): two groups of different waste and one unique file
+        let files: &[(&str, usize, u8)] =
+            &[("a1", 300, 1), ("a2", 300, 1), ("b1", 200, 2), ("b2", 200, 2), ("b3", 200, 2), ("c", 300, 3)];
+        for &(name, size, byte) in files {
+            let path = dir.path().join(name);
+            std::fs::write(&path, vec![byte; size]).unwrap();
+            let path = path.to_string_lossy();
+            insert_file(&pool, id, FileRow { path: &path, size: size as i64, ..Default::default() }).await;
+        }
+        (dir, AppState::new(pool, AppConfig::default()), id)
+    }
+
+    async fn list(state: &AppState, ns: &Namespace, id: Uuid, q: DuplicatesQuery) -> DuplicatesResponse {
+        use http_body_util::BodyExt;
+        let resp = get_duplicates(State(state.clone()), ns.clone(), Path(id), Query(q)).await.unwrap();
+        let bytes = resp.into_response().into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn search_runs_in_background_and_lists_groups_by_waste() {
+        let (_dir, state, id) = fixture().await;
+        let hr = Namespace::parse("hr").unwrap();
+        let q = StartDuplicatesQuery { min_size: Some(100) };
+        let resp = start_duplicates(State(state.clone()), hr.clone(), Path(id), Query(q)).await.unwrap().into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        for _ in 0..200 {
+            if !state.jobs.read().await.contains_key(&id) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let all = list(&state, &hr, id, DuplicatesQuery::default()).await;
+        assert!(!all.running);
+        assert_eq!(all.total_groups, 2);
+        assert_eq!(all.total_wasted_bytes, 400 + 300);
+        let wasted: Vec<i64> = all.groups.iter().map(|g| g.wasted_bytes).collect();
+        assert_eq!(wasted, vec![400, 300]);
+        assert_eq!(all.groups[0].file_count, 3);
+
+        let page = list(&state, &hr, id, DuplicatesQuery { limit: Some(1), offset: Some(1) }).await;
+        assert_eq!(page.groups.len(), 1);
+        assert_eq!(page.groups[0].wasted_bytes, 300);
+    }
+
+    #[tokio::test]
+    async fn search_requires_finished_scan_in_own_namespace() {
+        let (_dir, state, id) = fixture().await;
+        let other = Namespace::parse("finance").unwrap();
+        let res = start_duplicates(State(state.clone()), other.clone(), Path(id), Query(Default::default())).await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+        let res = get_duplicates(State(state.clone()), other, Path(id), Query(Default::default())).await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+
+        sqlx::query("UPDATE scans SET status='running' WHERE id=?1").bind(id.to_string()).execute(&state.db).await.unwrap();
+        let hr = Namespace::parse("hr").unwrap();
+        let res = start_duplicates(State(state.clone()), hr, Path(id), Query(Default::default())).await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+    }
+}
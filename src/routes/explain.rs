@@ -0,0 +1,132 @@
+//! Scan explanation API endpoints.
+//!
+//! When a directory is missing from a scan it is often unclear which option
+//! left it out. Explaining a path re-runs the scanner's checks with the
+//! stored options of the scan against the live filesystem and lists each
+//! check with its outcome, including the exclude pattern that matched.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/explain?path=` - Why a scan takes in a path or leaves it out
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::{is_within_root, normalize_query_path},
+    scanner::explain::explain_path,
+    state::AppState,
+    types::{PathCheck, PathExplanation, ScanOptions},
+};
+
+/// Query parameters for the explain endpoint.
+#[derive(Debug, serde::Deserialize)]
+pub struct ExplainQuery {
+    /// The path to explain.
+    pub path: String,
+}
+
+/// Explains whether a scan takes in a path or leaves it out.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The path to explain.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `PathExplanation`.
+///   A path outside the scan's roots only gets a failed `within_root` check.
+pub async fn explain_scan_path(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<ExplainQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let path = normalize_query_path(&q.path)?;
+    let row = sqlx::query("SELECT root_paths, options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(state.read_pool())
+        .await?;
+    let root_paths: Vec<String> = serde_json::from_str(&row.get::<String, _>("root_paths"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid root_paths of scan {}: {}", id, e)))?;
+    let options: ScanOptions = serde_json::from_str(&row.get::<String, _>("options"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid options of scan {}: {}", id, e)))?;
+
+    let Some(root) = root_paths.into_iter().find(|root| is_within_root(root, &path)) else {
+        return Ok(Json(PathExplanation {
+            scan_id: id,
+            path,
+            root: None,
+            depth: None,
+            is_dir: false,
+            included: false,
+            skipped_ancestor: None,
+            checks: vec![PathCheck {
+                check: "within_root".into(),
+                passed: false,
+                pattern: None,
+                detail: Some("the path is not below a root of the scan".into()),
+            }],
+        }));
+    };
+    let explanation = spawn_blocking(move || explain_path(id, &root, &path, &options))
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("explaining the path failed: {}", e)))??;
+    Ok(Json(explanation))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan_with_options, test_db_in};
+
+    #[tokio::test]
+    async fn explains_paths_with_the_stored_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("build/out")).unwrap();
+        let pool = test_db_in(dir.path()).await;
+        let options = ScanOptions { excludes: vec!["build".into()], ..Default::default() };
+        let id =
+            insert_scan_with_options(&pool, "done", &[&root.to_string_lossy()], "default", &options).await;
+        let state = AppState::new(pool, AppConfig::default());
+        let explain = |path: String| {
+            let state = state.clone();
+            async move {
+                let q = ExplainQuery { path };
+                let resp = explain_scan_path(State(state), Namespace::default(), Path(id), Query(q))
+                    .await
+                    .unwrap()
+                    .into_response();
+                let body = http_body_util::BodyExt::collect(resp.into_body()).await.unwrap().to_bytes();
+                serde_json::from_slice::<PathExplanation>(&body).unwrap()
+            }
+        };
+
+        let e = explain(root.join("build/out").to_string_lossy().to_string()).await;
+        assert!(!e.included);
+        assert_eq!(e.root.as_deref(), Some(root.to_string_lossy().as_ref()));
+        assert_eq!(e.skipped_ancestor, Some(root.join("build").to_string_lossy().to_string()));
+        let e = explain(root.join("build").to_string_lossy().to_string()).await;
+        assert_eq!(e.checks[0].check, "excluded");
+        assert_eq!(e.checks[0].pattern.as_deref(), Some("build"));
+
+        let e = explain(dir.path().join("elsewhere").to_string_lossy().to_string()).await;
+        assert!(!e.included);
+        assert_eq!(e.root, None);
+        assert_eq!(e.checks[0].check, "within_root");
+    }
+}
@@ -6,13 +6,22 @@
 //!
 //! ## Features
 //!
-//! - **Multiple Formats**: Export data as CSV or JSON
+//! - **Multiple Formats**: Export data as CSV, JSON or NDJSON
+//! - **Streaming**: NDJSON exports stream rows straight from SQLite, so even
+//!   scans with tens of millions of files export in flat memory
+//! - **Tree Files**: `format=wds` streams the tree as a WinDirStat-style CSV
+//!   listing (see [`export_wds`]) for tree viewers
 //! - **Flexible Scopes**: Export nodes (directories), files, or both
 //! - **Configurable Limits**: Control the number of records exported
 //! - **Statistics**: Export summary statistics for scans
-//! - **CSV Escaping**: Proper CSV escaping for special characters
+//! - **CSV Escaping**: RFC 4180 quoting with selectable columns and separator
+//! - **Size Units**: CSV sizes in bytes, KiB, MiB, GiB or per-row units with a
+//!   German or English decimal separator (see [`SizeFormat`]), shared with the
+//!   statistics endpoint
 //! - **Batch Processing**: Efficient chunked database queries
 
+use std::borrow::Cow;
+
 use axum::{
     extract::{Path, Query, State},
     http::header,
@@ -20,49 +29,456 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
+use sqlx::{sqlite::SqliteRow, Row};
 use uuid::Uuid;
 
 use crate::{
     error::{AppError, AppResult},
-    state::AppState,
+    middleware::namespace::Namespace,
+    routes::scans::{resolve_query_path, subtree_like_pattern},
+    state::{AppState, ScanLease},
 };
 
 /// Query parameters for the export endpoint.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct ExportQuery {
-    /// The export format (e.g., "csv", "json").
-    pub format: String,        // csv or json
+    /// The export format (e.g., "csv", "json", "ndjson").
+    pub format: String,        // csv, json or ndjson
     /// The scope of the export (e.g., "nodes", "files", "all").
     pub scope: Option<String>, // nodes, files, or all
-    /// The maximum number of records to export.
+    /// The maximum number of records to export. For NDJSON, 0 or absent means no limit.
     pub limit: Option<i64>,
+    /// Comma-separated CSV columns, e.g. `path,allocated_size,mtime`.
+    pub columns: Option<String>,
+    /// The CSV field separator: "comma" (default) or "semicolon".
+    pub sep: Option<String>,
+    /// Restricts the export to the subtree below this path.
+    pub path: Option<String>,
+    /// Only exports records with at least this allocated size in bytes.
+    pub min_size: Option<i64>,
+    /// Order of the directory records: "path" (default) or "size".
+    pub sort: Option<String>,
+    /// The size reported by `format=wds`: "allocated" (default) or "logical".
+    pub size: Option<String>,
+    /// The unit of the CSV size columns: "bytes" (default), "kib", "mib", "gib" or "auto".
+    pub units: Option<String>,
+    /// The decimal separator of the CSV size columns: "en" (`.`, default) or "de" (`,`).
+    pub locale: Option<String>,
 }
 
-/// Formats a node record as a CSV line.
-///
-/// This function converts a directory node into a properly escaped CSV format
-/// with all relevant metadata fields.
-///
-/// # Arguments
-///
-/// * `node` - The node to format
+/// Query parameters for the statistics endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct StatisticsQuery {
+    /// Restricts the extension and category breakdowns to the subtree below this path.
+    pub path: Option<String>,
+    /// The number of extensions to list before rolling the rest into "other".
+    pub top: Option<usize>,
+    /// Adds a `formatted` object with the sizes in this unit, see [`ExportQuery::units`].
+    pub units: Option<String>,
+    /// The decimal separator of the `formatted` sizes, see [`ExportQuery::locale`].
+    pub locale: Option<String>,
+}
+
+/// The unit sizes are formatted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeUnit {
+    /// Plain byte counts.
+    #[default]
+    Bytes,
+    /// Kibibytes (1024 bytes).
+    Kib,
+    /// Mebibytes (1024² bytes).
+    Mib,
+    /// Gibibytes (1024³ bytes).
+    Gib,
+    /// The largest unit up to PiB the value reaches, written after the number.
+    Auto,
+}
+
+/// The decimal separator of formatted sizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberLocale {
+    /// `1234.57`
+    #[default]
+    En,
+    /// `1234,57`
+    De,
+}
+
+/// How sizes are written in CSV exports and the statistics' `formatted` object.
 ///
-/// # Returns
+/// Values in KiB and larger units are rounded to two decimals, half up, using
+/// integer arithmetic so the output never depends on floating point. No
+/// thousands separator is written, so spreadsheets read the numbers as such.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeFormat {
+    /// The unit of the values.
+    pub unit: SizeUnit,
+    /// The decimal separator.
+    pub locale: NumberLocale,
+}
+
+/// Returns `magnitude / divisor` in hundredths, rounded half up.
+fn round_hundredths(magnitude: u128, divisor: u128) -> u128 {
+    (magnitude * 200 + divisor) / (2 * divisor)
+}
+
+/// The units [`SizeUnit::Auto`] picks from, with their size in bytes.
+const AUTO_UNITS: &[(&str, u128)] =
+    &[("KiB", 1 << 10), ("MiB", 1 << 20), ("GiB", 1 << 30), ("TiB", 1 << 40), ("PiB", 1 << 50)];
+
+impl SizeFormat {
+    /// Builds the format from the `units` and `locale` query parameters.
+    pub fn from_query(units: Option<&str>, locale: Option<&str>) -> AppResult<Self> {
+        let unit = match units.unwrap_or("bytes") {
+            "bytes" => SizeUnit::Bytes,
+            "kib" => SizeUnit::Kib,
+            "mib" => SizeUnit::Mib,
+            "gib" => SizeUnit::Gib,
+            "auto" => SizeUnit::Auto,
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid units '{}'. Use 'bytes', 'kib', 'mib', 'gib' or 'auto'",
+                    other
+                )))
+            }
+        };
+        let locale = match locale.unwrap_or("en") {
+            "en" => NumberLocale::En,
+            "de" => NumberLocale::De,
+            other => return Err(AppError::BadRequest(format!("Invalid locale '{}'. Use 'de' or 'en'", other))),
+        };
+        Ok(Self { unit, locale })
+    }
+
+    /// The unit named in column headers, `None` for bytes and per-row units.
+    pub fn header_unit(&self) -> Option<&'static str> {
+        match self.unit {
+            SizeUnit::Kib => Some("KiB"),
+            SizeUnit::Mib => Some("MiB"),
+            SizeUnit::Gib => Some("GiB"),
+            SizeUnit::Bytes | SizeUnit::Auto => None,
+        }
+    }
+
+    /// Formats a size in bytes.
+    ///
+    /// Fixed units give the bare number (`1,50`); `auto` appends the unit
+    /// (`1,50 GiB`, or `512 B` below one KiB).
+    pub fn format(&self, bytes: i64) -> String {
+        let divisor: u128 = match self.unit {
+            SizeUnit::Bytes => return bytes.to_string(),
+            SizeUnit::Kib => 1 << 10,
+            SizeUnit::Mib => 1 << 20,
+            SizeUnit::Gib => 1 << 30,
+            SizeUnit::Auto => {
+                // Picked after rounding, so 1023.999 KiB is written as 1.00 MiB
+                let magnitude = u128::from(bytes.unsigned_abs());
+                let Some((unit, divisor)) =
+                    AUTO_UNITS.iter().rev().find(|(_, d)| round_hundredths(magnitude, *d) >= 100)
+                else {
+                    return format!("{} B", bytes);
+                };
+                return format!("{} {}", self.scaled(bytes, *divisor), unit);
+            }
+        };
+        self.scaled(bytes, divisor)
+    }
+
+    /// Writes `bytes / divisor` with two decimals, rounded half up (away from zero).
+    fn scaled(&self, bytes: i64, divisor: u128) -> String {
+        let hundredths = round_hundredths(u128::from(bytes.unsigned_abs()), divisor);
+        let sign = if bytes < 0 && hundredths > 0 { "-" } else { "" };
+        let decimal = match self.locale {
+            NumberLocale::En => '.',
+            NumberLocale::De => ',',
+        };
+        format!("{}{}{}{:02}", sign, hundredths / 100, decimal, hundredths % 100)
+    }
+}
+
+/// Aggregated size information for a single file extension.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ExtensionStat {
+    /// The lowercased extension including the leading dot (e.g. ".mp4"),
+    /// `"(none)"` for files without an extension, or `"other"` for the rollup.
+    pub extension: String,
+    /// The number of files with this extension.
+    pub count: i64,
+    /// The total logical size of these files in bytes.
+    pub logical_size: i64,
+    /// The total allocated size of these files in bytes.
+    pub allocated_size: i64,
+}
+
+/// Aggregated size information for a file category such as `video`.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct CategoryStat {
+    /// The category, `"other"` for files whose extension is in no category.
+    pub category: String,
+    /// The number of files in this category.
+    pub count: i64,
+    /// The total logical size of these files in bytes.
+    pub logical_size: i64,
+    /// The total allocated size of these files in bytes.
+    pub allocated_size: i64,
+}
+
+/// A column of the CSV export, selected with `?columns=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// "Dir" or "File".
+    Type,
+    /// The full path.
+    Path,
+    /// The path of the parent directory.
+    ParentPath,
+    /// The depth in the directory tree (directories only).
+    Depth,
+    /// 1 for directories, 0 for files.
+    IsDir,
+    /// The logical size, in bytes unless `units` says otherwise.
+    LogicalSize,
+    /// The allocated size, in bytes unless `units` says otherwise.
+    AllocatedSize,
+    /// The logical size in bytes, whatever `units` is.
+    LogicalBytes,
+    /// The allocated size in bytes, whatever `units` is.
+    AllocatedBytes,
+    /// The number of files below the directory (directories only).
+    FileCount,
+    /// The number of subdirectories (directories only).
+    DirCount,
+    /// The modification time as Unix seconds.
+    Mtime,
+    /// The exact path in base64 if `path` is a lossy rendering of it, empty otherwise.
+    PathRaw,
+}
+
+impl CsvColumn {
+    /// Every column.
+    const ALL: [CsvColumn; 13] = [
+        Self::Type,
+        Self::Path,
+        Self::ParentPath,
+        Self::Depth,
+        Self::IsDir,
+        Self::LogicalSize,
+        Self::AllocatedSize,
+        Self::LogicalBytes,
+        Self::AllocatedBytes,
+        Self::FileCount,
+        Self::DirCount,
+        Self::Mtime,
+        Self::PathRaw,
+    ];
+
+    /// Parses the plain label of a column in a header line, e.g. `Allocated Size`.
+    pub fn from_header(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.header() == label)
+    }
+
+    /// Parses a column name as used in the `columns` query parameter.
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "type" => Self::Type,
+            "path" => Self::Path,
+            "parent_path" => Self::ParentPath,
+            "depth" => Self::Depth,
+            "is_dir" => Self::IsDir,
+            "logical_size" => Self::LogicalSize,
+            "allocated_size" => Self::AllocatedSize,
+            "logical_bytes" => Self::LogicalBytes,
+            "allocated_bytes" => Self::AllocatedBytes,
+            "file_count" => Self::FileCount,
+            "dir_count" => Self::DirCount,
+            "mtime" => Self::Mtime,
+            "path_raw" => Self::PathRaw,
+            _ => return None,
+        })
+    }
+
+    /// The label of the column in the header line; size columns in a fixed
+    /// unit name it, e.g. `Allocated Size (MiB)`.
+    fn label(self, sizes: &SizeFormat) -> Cow<'static, str> {
+        match (self, sizes.header_unit()) {
+            (Self::LogicalSize | Self::AllocatedSize, Some(unit)) => {
+                Cow::Owned(format!("{} ({})", self.header(), unit))
+            }
+            _ => Cow::Borrowed(self.header()),
+        }
+    }
+
+    /// The plain label of the column.
+    pub fn header(self) -> &'static str {
+        match self {
+            Self::Type => "Type",
+            Self::Path => "Path",
+            Self::ParentPath => "Parent Path",
+            Self::Depth => "Depth",
+            Self::IsDir => "Is Directory",
+            Self::LogicalSize => "Logical Size",
+            Self::AllocatedSize => "Allocated Size",
+            Self::LogicalBytes => "Logical Bytes",
+            Self::AllocatedBytes => "Allocated Bytes",
+            Self::FileCount => "File Count",
+            Self::DirCount => "Dir Count",
+            Self::Mtime => "Modified",
+            Self::PathRaw => "Raw Path (Base64)",
+        }
+    }
+}
+
+/// Columns of the directory section when no `columns` are requested.
+const NODE_CSV_COLUMNS: &[CsvColumn] = &[
+    CsvColumn::Type,
+    CsvColumn::Path,
+    CsvColumn::ParentPath,
+    CsvColumn::Depth,
+    CsvColumn::IsDir,
+    CsvColumn::LogicalSize,
+    CsvColumn::AllocatedSize,
+    CsvColumn::FileCount,
+    CsvColumn::DirCount,
+];
+
+/// Columns of the file section when no `columns` are requested.
+const FILE_CSV_COLUMNS: &[CsvColumn] =
+    &[CsvColumn::Type, CsvColumn::Path, CsvColumn::ParentPath, CsvColumn::LogicalSize, CsvColumn::AllocatedSize];
+
+/// Layout of a CSV export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The columns of both sections, or `None` for the per-section defaults.
+    pub columns: Option<Vec<CsvColumn>>,
+    /// The field separator, `,` or `;` (for Excel in locales using a decimal comma).
+    pub separator: char,
+    /// How the `logical_size` and `allocated_size` columns are written.
+    pub sizes: SizeFormat,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self { columns: None, separator: ',', sizes: SizeFormat::default() }
+    }
+}
+
+impl CsvOptions {
+    /// Builds the options from the `columns` and `sep` query parameters.
+    pub fn from_query(columns: Option<&str>, sep: Option<&str>) -> AppResult<Self> {
+        let separator = match sep.unwrap_or("comma") {
+            "comma" => ',',
+            "semicolon" => ';',
+            other => {
+                return Err(AppError::BadRequest(format!("Invalid sep '{}'. Use 'comma' or 'semicolon'", other)))
+            }
+        };
+        let columns = match columns.map(str::trim).filter(|c| !c.is_empty()) {
+            Some(spec) => {
+                let mut cols = Vec::new();
+                for name in spec.split(',').map(str::trim) {
+                    let col = CsvColumn::parse(name)
+                        .ok_or_else(|| AppError::BadRequest(format!("Unknown CSV column '{}'", name)))?;
+                    if !cols.contains(&col) {
+                        cols.push(col);
+                    }
+                }
+                Some(cols)
+            }
+            None => None,
+        };
+        Ok(Self { columns, separator, sizes: SizeFormat::default() })
+    }
+
+    /// Sets how the size columns are written.
+    pub fn with_sizes(mut self, sizes: SizeFormat) -> Self {
+        self.sizes = sizes;
+        self
+    }
+
+    fn node_columns(&self) -> &[CsvColumn] {
+        self.columns.as_deref().unwrap_or(NODE_CSV_COLUMNS)
+    }
+
+    fn file_columns(&self) -> &[CsvColumn] {
+        self.columns.as_deref().unwrap_or(FILE_CSV_COLUMNS)
+    }
+}
+
+/// Appends one field to a CSV record following RFC 4180.
 ///
-/// A string containing the CSV-formatted node record
-fn format_node_csv(node: &NodeExport) -> String {
-    format!(
-        "Dir,\"{}\",\"{}\",{},{},{},{},{},{}\n",
-        escape_csv(&node.path),
-        escape_csv(node.parent_path.as_deref().unwrap_or("")),
-        node.depth,
-        if node.is_dir { 1 } else { 0 },
-        node.logical_size,
-        node.allocated_size,
-        node.file_count,
-        node.dir_count
-    )
+/// Fields containing the separator, a double quote or a line break are
+/// enclosed in double quotes and embedded quotes are doubled. Line breaks are
+/// kept as they are, so paths survive a round trip unchanged.
+pub fn push_csv_field(out: &mut String, field: &str, separator: char) {
+    if field.contains([separator, '"', '\n', '\r']) {
+        out.push('"');
+        for c in field.chars() {
+            if c == '"' {
+                out.push('"');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+/// Appends a record of `fields` terminated by CRLF.
+pub fn push_csv_record<I, S>(out: &mut String, fields: I, separator: char)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(separator);
+        }
+        push_csv_field(out, field.as_ref(), separator);
+    }
+    out.push_str("\r\n");
+}
+
+/// Formats the value of `column` for a directory record.
+fn node_csv_value(node: &NodeExport, column: CsvColumn, sizes: &SizeFormat) -> String {
+    match column {
+        CsvColumn::Type => "Dir".to_string(),
+        CsvColumn::Path => node.path.clone(),
+        CsvColumn::ParentPath => node.parent_path.clone().unwrap_or_default(),
+        CsvColumn::Depth => node.depth.to_string(),
+        CsvColumn::IsDir => if node.is_dir { "1" } else { "0" }.to_string(),
+        CsvColumn::LogicalSize => sizes.format(node.logical_size),
+        CsvColumn::AllocatedSize => sizes.format(node.allocated_size),
+        CsvColumn::LogicalBytes => node.logical_size.to_string(),
+        CsvColumn::AllocatedBytes => node.allocated_size.to_string(),
+        CsvColumn::FileCount => node.file_count.to_string(),
+        CsvColumn::DirCount => node.dir_count.to_string(),
+        CsvColumn::Mtime => node.mtime.map(|t| t.to_string()).unwrap_or_default(),
+        CsvColumn::PathRaw => node.path_raw.clone().unwrap_or_default(),
+    }
+}
+
+/// Formats the value of `column` for a file record; directory-only columns stay empty.
+fn file_csv_value(file: &FileExport, column: CsvColumn, sizes: &SizeFormat) -> String {
+    match column {
+        CsvColumn::Type => "File".to_string(),
+        CsvColumn::Path => file.path.clone(),
+        CsvColumn::ParentPath => file.parent_path.clone().unwrap_or_default(),
+        CsvColumn::IsDir => "0".to_string(),
+        CsvColumn::LogicalSize => sizes.format(file.logical_size),
+        CsvColumn::AllocatedSize => sizes.format(file.allocated_size),
+        CsvColumn::LogicalBytes => file.logical_size.to_string(),
+        CsvColumn::AllocatedBytes => file.allocated_size.to_string(),
+        CsvColumn::Mtime => file.mtime.map(|t| t.to_string()).unwrap_or_default(),
+        CsvColumn::PathRaw => file.path_raw.clone().unwrap_or_default(),
+        CsvColumn::Depth | CsvColumn::FileCount | CsvColumn::DirCount => String::new(),
+    }
+}
+
+/// Appends the header line for `columns`.
+fn push_csv_header(out: &mut String, columns: &[CsvColumn], csv: &CsvOptions) {
+    push_csv_record(out, columns.iter().map(|c| c.label(&csv.sizes)), csv.separator);
 }
 
 /// The structure of the JSON export.
@@ -81,7 +497,7 @@ pub struct ExportData {
 }
 
 /// A node (directory) record for export.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NodeExport {
     /// The path of the node.
     pub path: String,
@@ -99,10 +515,15 @@ pub struct NodeExport {
     pub file_count: i64,
     /// The number of subdirectories in the node.
     pub dir_count: i64,
+    /// The modification time as Unix seconds, if known.
+    pub mtime: Option<i64>,
+    /// The exact path in base64, present only if `path` is not valid Unicode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_raw: Option<String>,
 }
 
 /// A file record for export.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileExport {
     /// The path of the file.
     pub path: String,
@@ -112,13 +533,35 @@ pub struct FileExport {
     pub logical_size: i64,
     /// The allocated size of the file in bytes.
     pub allocated_size: i64,
+    /// The modification time as Unix seconds, if known.
+    pub mtime: Option<i64>,
+    /// The exact path in base64, present only if `path` is not valid Unicode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_raw: Option<String>,
 }
 
-/// Exports the data of a scan in either CSV or JSON format.
+/// Row filters shared by all export formats.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportFilter {
+    /// Normalized path restricting the export to this subtree.
+    pub root: Option<String>,
+    /// Minimum allocated size in bytes.
+    pub min_size: Option<i64>,
+    /// Orders directories by allocated size (largest first) instead of by path.
+    pub dirs_by_size: bool,
+}
+
+/// Exports the data of a scan in CSV, JSON or NDJSON format.
+///
+/// NDJSON exports are streamed and are not subject to the record limit cap
+/// that applies to CSV and JSON. `path`, `min_size` and `sort` filter and
+/// order the records of every format; `columns`, `sep`, `units` and `locale`
+/// shape the CSV.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan to export.
 /// * `query` - The export query parameters.
 ///
@@ -127,17 +570,38 @@ pub struct FileExport {
 /// * `AppResult<Response>` - The exported data as a file download.
 pub async fn export_scan(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
     Query(query): Query<ExportQuery>,
 ) -> AppResult<Response> {
-    // Validate scan exists
-    let scan = sqlx::query("SELECT id FROM scans WHERE id = ?1")
-        .bind(id.to_string())
-        .fetch_optional(&state.db)
-        .await?;
+    // Validate scan exists and is visible
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let filter = export_filter(&state, id, &query).await?;
+    // Keeps the scan from being pruned until the export (including a streamed body) is done
+    let lease = state.scan_leases.acquire(id);
 
-    if scan.is_none() {
-        return Err(AppError::NotFound("Scan not found".to_string()));
+    let scope = query.scope.as_deref().unwrap_or("all");
+    let sizes = SizeFormat::from_query(query.units.as_deref(), query.locale.as_deref())?;
+    if query.format != "csv" && sizes != SizeFormat::default() {
+        return Err(AppError::BadRequest("units and locale only apply to format=csv".into()));
+    }
+    if query.format == "wds" {
+        if scope != "all" || filter.min_size.is_some() || filter.dirs_by_size {
+            return Err(AppError::BadRequest("format=wds exports the whole tree; only 'path' and 'size' apply".into()));
+        }
+        let logical = match query.size.as_deref() {
+            None | Some("allocated") => false,
+            Some("logical") => true,
+            Some(other) => {
+                return Err(AppError::BadRequest(format!("Invalid size '{}'. Use 'allocated' or 'logical'", other)))
+            }
+        };
+        return Ok(export_wds(&state, id, filter.root, logical, lease));
+    }
+    if query.format == "ndjson" {
+        // Streaming keeps memory flat, so 0 or no limit exports everything
+        let limit = query.limit.filter(|l| *l > 0);
+        return Ok(export_ndjson(&state, id, scope, filter, limit, lease));
     }
 
     let requested_limit = query.limit.unwrap_or(10_000);
@@ -146,15 +610,38 @@ pub async fn export_scan(
         tracing::warn!("Export limit clamped from {} to 25000 for scan {}", requested_limit, id);
     }
     let limit = requested_limit.clamp(1, 25_000); // Reduced to prevent server overload and memory issues
-    let scope = query.scope.as_deref().unwrap_or("all");
 
     match query.format.as_str() {
-        "csv" => export_csv(state, id, scope, limit).await.map(|r| r.into_response()),
-        "json" => export_json(state, id, scope, limit).await.map(|r| r.into_response()),
-        _ => Err(AppError::BadRequest("Invalid format. Use 'csv' or 'json'".to_string())),
+        "csv" => {
+            let csv = CsvOptions::from_query(query.columns.as_deref(), query.sep.as_deref())?.with_sizes(sizes);
+            Ok(export_csv(state, id, scope, filter, csv, limit, lease).into_response())
+        }
+        "json" => export_json(state, id, scope, &filter, limit).await.map(|r| r.into_response()),
+        _ => Err(AppError::BadRequest("Invalid format. Use 'csv', 'json', 'ndjson' or 'wds'".to_string())),
     }
 }
 
+/// Builds the row filter from the `path`, `min_size` and `sort` parameters.
+async fn export_filter(state: &AppState, scan_id: Uuid, query: &ExportQuery) -> AppResult<ExportFilter> {
+    let root = match query.path.as_deref().filter(|p| !p.is_empty()) {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(state.read_pool(), scan_id, p).await?)
+        }
+        None => None,
+    };
+    let dirs_by_size = match query.sort.as_deref() {
+        None | Some("path") => false,
+        Some("size") => true,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!("Invalid sort '{}'. Use 'path' or 'size'", other)))
+        }
+    };
+    Ok(ExportFilter { root, min_size: query.min_size.filter(|s| *s > 0), dirs_by_size })
+}
+
 /// Exports scan data in CSV format.
 ///
 /// This function generates a CSV file containing scan results based on the specified scope.
@@ -165,143 +652,130 @@ pub async fn export_scan(
 /// * `state` - The application state containing database connection
 /// * `scan_id` - The UUID of the scan to export
 /// * `scope` - The export scope: "nodes", "files", or "all"
+/// * `filter` - Restricts and orders the exported records
+/// * `csv` - The columns and separator of the CSV
 /// * `limit` - Maximum number of records to export
+/// * `lease` - Keeps the scan from being pruned until the body is dropped
 ///
 /// # Returns
 ///
 /// An HTTP response with CSV content and appropriate headers for file download
-/// Exports scan data in CSV format.
+fn export_csv(
+    state: AppState,
+    scan_id: Uuid,
+    scope: &str,
+    filter: ExportFilter,
+    csv: CsvOptions,
+    limit: i64,
+    lease: ScanLease,
+) -> Response {
+    use axum::body::Body;
+    use axum::http::HeaderValue;
+
+    let stream = csv_chunks(state, scan_id, scope, filter, csv, limit);
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8"))
+        .body(Body::from_stream(hold_lease(stream, lease)))
+        .unwrap();
+
+    let filename = format!("attachment; filename=\"scan_{}.csv\"", scan_id);
+    if let Ok(header_val) = HeaderValue::from_str(&filename) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, header_val);
+    }
+    response
+}
+
+/// Produces the CSV export of a scan as a stream of text chunks.
 ///
-/// This function generates a CSV file containing scan results based on the specified scope.
-/// It includes proper headers and handles both nodes (directories) and files.
+/// Directories come first, then files ordered by allocated size; each section
+/// starts with its own header line and the sections are separated by an empty
+/// line. Records follow RFC 4180 (see [`push_csv_field`]).
 ///
 /// # Arguments
 ///
 /// * `state` - The application state containing database connection
 /// * `scan_id` - The UUID of the scan to export
 /// * `scope` - The export scope: "nodes", "files", or "all"
+/// * `filter` - Restricts and orders the exported records
+/// * `csv` - The columns and separator of the CSV
 /// * `limit` - Maximum number of records to export
-///
-/// # Returns
-///
-/// An HTTP response with CSV content and appropriate headers for file download
-async fn export_csv(state: AppState, scan_id: Uuid, scope: &str, limit: i64) -> AppResult<impl IntoResponse> {
-    use axum::body::Body;
-    use axum::http::HeaderValue;
-    use futures::stream::TryStreamExt;
+fn csv_chunks(
+    state: AppState,
+    scan_id: Uuid,
+    scope: &str,
+    filter: ExportFilter,
+    csv: CsvOptions,
+    limit: i64,
+) -> impl futures::Stream<Item = Result<String, AppError>> {
+    // (section being exported, its cursor, whether its header is written, exported count)
+    type CsvProgress = (Option<ExportTable>, Option<ExportCursor>, bool, i64);
 
     let include_nodes = scope == "all" || scope == "nodes";
     let include_files = scope == "all" || scope == "files";
-    let scope_str = scope.to_string();
-
-    // Initial state: (last_node_cursor, last_file_cursor, nodes_done, files_done, header_sent, exported_count)
-    let initial_state = (None::<String>, None::<(i64, String)>, false, false, false, 0i64);
-
-    let stream = futures::stream::try_unfold(
-        initial_state,
-        move |(mut last_node_cursor, mut last_file_cursor, mut nodes_done, mut files_done, mut header_sent, mut count)| {
-            let state = state.clone();
-            let scope = scope_str.clone();
-            async move {
-                if nodes_done && files_done {
-                    // Type annotation needed for the compiler
-                    return Ok::<Option<(String, (Option<String>, Option<(i64, String)>, bool, bool, bool, i64))>, AppError>(None);
-                }
-                
-                let remaining = limit - count;
-                if remaining <= 0 {
-                    return Ok(None); 
-                }
-
-                let mut chunk = String::new();
+    let first = if include_nodes {
+        Some(ExportTable::Nodes)
+    } else if include_files {
+        Some(ExportTable::Files)
+    } else {
+        None
+    };
 
-                // 1. Send Headers if not sent
-                if !header_sent {
-                    header_sent = true;
-                }
+    futures::stream::try_unfold((first, None, false, 0i64), move |(table, cursor, header_sent, count): CsvProgress| {
+        let state = state.clone();
+        let filter = filter.clone();
+        let csv = csv.clone();
+        async move {
+            let Some(table) = table else {
+                return Ok::<Option<(String, CsvProgress)>, AppError>(None);
+            };
+            let remaining = limit - count;
+            if remaining <= 0 {
+                return Ok(None);
+            }
 
-                let batch_size = EXPORT_CHUNK_SIZE.min(remaining);
-                
-                // 2. Fetch Nodes
-                if include_nodes && !nodes_done {
-                    if count == 0 {
-                        chunk.push_str("Type,Path,Parent Path,Depth,Is Directory,Logical Size,Allocated Size,File Count,Dir Count\n");
+            let sep = csv.separator;
+            let mut chunk = String::new();
+            let batch_size = EXPORT_CHUNK_SIZE.min(remaining);
+            let (rows, last) = match table {
+                ExportTable::Nodes => {
+                    let columns = csv.node_columns();
+                    if !header_sent {
+                        push_csv_header(&mut chunk, columns, &csv);
                     }
-                    
-                    if batch_size <= 0 {
-                        nodes_done = true;
-                    } else {
-                        let nodes = fetch_nodes_batch(&state, scan_id, batch_size, last_node_cursor.clone()).await.map_err(AppError::from)?;
-                        if nodes.is_empty() {
-                            nodes_done = true;
-                        } else {
-                            for node in &nodes {
-                                chunk.push_str(&format_node_csv(node));
-                            }
-                            if let Some(last) = nodes.last() {
-                                last_node_cursor = Some(last.path.clone());
-                            }
-                            count += nodes.len() as i64;
-                        }
+                    let nodes = fetch_nodes_batch(&state, scan_id, &filter, batch_size, cursor).await?;
+                    for node in &nodes {
+                        push_csv_record(&mut chunk, columns.iter().map(|c| node_csv_value(node, *c, &csv.sizes)), sep);
                     }
-                    
-                    if nodes_done {
-                        last_node_cursor = None; 
-                        if include_files {
-                            chunk.push('\n');
-                        }
+                    (nodes.len() as i64, nodes.last().map(|n| (n.allocated_size, n.path.clone())))
+                }
+                ExportTable::Files => {
+                    let columns = csv.file_columns();
+                    if !header_sent {
+                        push_csv_header(&mut chunk, columns, &csv);
+                    }
+                    let files = fetch_files_batch(&state, scan_id, &filter, batch_size, cursor).await?;
+                    for file in &files {
+                        push_csv_record(&mut chunk, columns.iter().map(|c| file_csv_value(file, *c, &csv.sizes)), sep);
                     }
-                } 
-                // 3. Fetch Files
-                else if include_files && !files_done {
-                     if last_file_cursor.is_none() { 
-                         chunk.push_str("Type,Path,Parent Path,Logical Size,Allocated Size\n");
-                     }
- 
-                     let remaining = limit - count;
-                     let batch_size = EXPORT_CHUNK_SIZE.min(remaining);
-
-                     if batch_size <= 0 {
-                         files_done = true;
-                     } else {
-                         let files = fetch_files_batch(&state, scan_id, batch_size, last_file_cursor.clone()).await.map_err(AppError::from)?;
-                         if files.is_empty() {
-                             files_done = true;
-                         } else {
-                             for file in &files {
-                                 chunk.push_str(&format!(
-                                     "File,\"{}\",\"{}\",{},{}\n",
-                                     escape_csv(&file.path),
-                                     escape_csv(file.parent_path.as_deref().unwrap_or("")),
-                                     file.logical_size,
-                                     file.allocated_size,
-                                 ));
-                             }
-                             if let Some(last) = files.last() {
-                                 last_file_cursor = Some((last.allocated_size, last.path.clone()));
-                             }
-                             count += files.len() as i64;
-                         }
-                     }
-                } else {
-                    return Ok(None);
+                    (files.len() as i64, files.last().map(|f| (f.allocated_size, f.path.clone())))
                 }
-                
-                Ok(Some((chunk, (last_node_cursor, last_file_cursor, nodes_done, files_done, header_sent, count))))
-            }
-        },
-    );
-
-    let mut response = Response::builder()
-        .header(header::CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8"))
-        .body(Body::from_stream(stream))
-        .unwrap();
+            };
 
-    let filename = format!("attachment; filename=\"scan_{}.csv\"", scan_id);
-    if let Ok(header_val) = HeaderValue::from_str(&filename) {
-        response.headers_mut().insert(header::CONTENT_DISPOSITION, header_val);
-    }
-    Ok(response)
+            let next = match last {
+                Some(last) => (Some(table), Some(last), true, count + rows),
+                None => {
+                    // Section done; the file section follows after an empty line
+                    let next_table = (table == ExportTable::Nodes && include_files).then_some(ExportTable::Files);
+                    if next_table.is_some() {
+                        chunk.push_str("\r\n");
+                    }
+                    (next_table, None, false, count)
+                }
+            };
+            Ok(Some((chunk, next)))
+        }
+    })
 }
 
 /// Exports scan data in JSON format.
@@ -314,6 +788,7 @@ async fn export_csv(state: AppState, scan_id: Uuid, scope: &str, limit: i64) ->
 /// * `state` - The application state containing database connection
 /// * `scan_id` - The UUID of the scan to export
 /// * `scope` - The export scope: "nodes", "files", or "all"
+/// * `filter` - Restricts and orders the exported records
 /// * `limit` - Maximum number of records to export
 ///
 /// # Returns
@@ -323,6 +798,7 @@ async fn export_json(
     state: AppState,
     scan_id: Uuid,
     scope: &str,
+    filter: &ExportFilter,
     limit: i64,
 ) -> AppResult<impl IntoResponse> {
     let mut export_data = ExportData {
@@ -334,11 +810,11 @@ async fn export_json(
     };
 
     if scope == "all" || scope == "nodes" {
-        export_data.nodes = Some(fetch_nodes_all(&state, scan_id, limit).await?);
+        export_data.nodes = Some(fetch_nodes_all(&state, scan_id, filter, limit).await?);
     }
 
     if scope == "all" || scope == "files" {
-        export_data.files = Some(fetch_files_all(&state, scan_id, limit).await?);
+        export_data.files = Some(fetch_files_all(&state, scan_id, filter, limit).await?);
     }
 
     use axum::http::HeaderValue;
@@ -354,32 +830,13 @@ async fn export_json(
     Ok(response)
 }
 
-/// Escapes a string for safe CSV output.
-///
-/// This function handles CSV escaping by replacing dangerous characters:
-/// - Double quotes are escaped as two double quotes
-/// - Newline and carriage return are replaced with spaces
-/// - Other control characters are replaced with spaces
-///
-/// # Arguments
-///
-/// * `s` - The string to escape
-///
-/// # Returns
-///
-/// A CSV-safe version of the input string
-fn escape_csv(s: &str) -> String {
-    // FIX Bug #7 - Optimization: Avoid excessive allocations from flat_map/vec!
-    let mut out = String::with_capacity(s.len() + 10);
-    for c in s.chars() {
-        match c {
-            '"' => { out.push('"'); out.push('"'); },
-            '\n' | '\r' => out.push(' '),
-            c if c.is_control() => out.push(' '),
-            c => out.push(c),
-        }
-    }
-    out
+/// Keeps `lease` alive for as long as the stream is.
+fn hold_lease<S: futures::Stream>(stream: S, lease: ScanLease) -> impl futures::Stream<Item = S::Item> {
+    use futures::StreamExt;
+    stream.map(move |item| {
+        let _ = &lease;
+        item
+    })
 }
 
 /// Chunk size for database export queries.
@@ -388,24 +845,84 @@ fn escape_csv(s: &str) -> String {
 /// to balance memory usage and performance.
 const EXPORT_CHUNK_SIZE: i64 = 800;
 
-// Modified for streaming: just fetch one batch at the specific offset and return it.
-// The caller (stream) manages the offset loop.
+/// The table an export query reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportTable {
+    Nodes,
+    Files,
+}
+
+/// Keyset position after the last exported row: `(allocated_size, path)`.
+type ExportCursor = (i64, String);
+
+/// Builds the query for a batch of export rows.
+///
+/// Files are ordered by allocated size (largest first) with the path as tie
+/// breaker; directories are ordered by path unless `filter.dirs_by_size` is
+/// set. A negative `limit` means no limit.
+fn export_rows_query(
+    table: ExportTable,
+    scan_id: Uuid,
+    filter: &ExportFilter,
+    cursor: Option<ExportCursor>,
+    limit: i64,
+) -> sqlx::QueryBuilder<'static, sqlx::Sqlite> {
+    let mut qb = sqlx::QueryBuilder::new(match table {
+        ExportTable::Nodes => {
+            "SELECT path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count, \
+             mtime, path_raw FROM nodes WHERE is_dir = 1 AND scan_id = "
+        }
+        ExportTable::Files => {
+            "SELECT path, parent_path, logical_size, allocated_size, mtime, path_raw \
+             FROM files WHERE scan_id = "
+        }
+    });
+    qb.push_bind(scan_id.to_string());
+    if let Some(root) = &filter.root {
+        qb.push(" AND (path = ").push_bind(root.clone());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+    if let Some(min_size) = filter.min_size {
+        qb.push(" AND allocated_size >= ").push_bind(min_size);
+    }
+    let by_size = table == ExportTable::Files || filter.dirs_by_size;
+    if let Some((last_alloc, last_path)) = cursor {
+        if by_size {
+            // Keyset: allocated_size DESC, path ASC
+            qb.push(" AND (allocated_size < ").push_bind(last_alloc);
+            qb.push(" OR (allocated_size = ").push_bind(last_alloc);
+            qb.push(" AND path > ").push_bind(last_path).push("))");
+        } else {
+            qb.push(" AND path > ").push_bind(last_path);
+        }
+    }
+    qb.push(if by_size { " ORDER BY allocated_size DESC, path ASC" } else { " ORDER BY path ASC" });
+    qb.push(" LIMIT ").push_bind(limit);
+    qb
+}
+
 /// Fetches all nodes for JSON export (or non-streaming).
-async fn fetch_nodes_all(state: &AppState, scan_id: Uuid, limit: i64) -> Result<Vec<NodeExport>, sqlx::Error> {
+async fn fetch_nodes_all(
+    state: &AppState,
+    scan_id: Uuid,
+    filter: &ExportFilter,
+    limit: i64,
+) -> Result<Vec<NodeExport>, sqlx::Error> {
     let mut results = Vec::new();
-    let mut current_cursor: Option<String> = None;
+    let mut current_cursor: Option<ExportCursor> = None;
     let mut count = 0;
     loop {
         let remaining = limit - count;
         if remaining <= 0 { break; }
         let batch_size = EXPORT_CHUNK_SIZE.min(remaining);
         
-        let batch = fetch_nodes_batch(state, scan_id, batch_size, current_cursor.clone()).await?;
+        let batch = fetch_nodes_batch(state, scan_id, filter, batch_size, current_cursor.clone()).await?;
 
         if batch.is_empty() { break; }
         
         if let Some(last) = batch.last() {
-            current_cursor = Some(last.path.clone());
+            current_cursor = Some((last.allocated_size, last.path.clone()));
         }
 
         count += batch.len() as i64;
@@ -416,61 +933,49 @@ async fn fetch_nodes_all(state: &AppState, scan_id: Uuid, limit: i64) -> Result<
 
 /// Fetches a single batch of nodes for export.
 async fn fetch_nodes_batch(
-    state: &AppState, 
-    scan_id: Uuid, 
-    limit: i64, 
-    cursor_path: Option<String>
+    state: &AppState,
+    scan_id: Uuid,
+    filter: &ExportFilter,
+    limit: i64,
+    cursor: Option<ExportCursor>,
 ) -> Result<Vec<NodeExport>, sqlx::Error> {
-    let sid = scan_id.to_string();
-    let query_str = if cursor_path.is_some() {
-        "SELECT path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count \
-         FROM nodes WHERE scan_id = ?1 AND is_dir = 1 AND path > ?2 ORDER BY path ASC LIMIT ?3"
-    } else {
-        "SELECT path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count \
-         FROM nodes WHERE scan_id = ?1 AND is_dir = 1 ORDER BY path ASC LIMIT ?2"
-    };
+    let mut qb = export_rows_query(ExportTable::Nodes, scan_id, filter, cursor, limit);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    Ok(rows.iter().map(node_export_from_row).collect())
+}
 
-    let query = if let Some(path) = cursor_path.as_ref() {
-         sqlx::query(query_str)
-             .bind(&sid)
-             .bind(path)
-             .bind(limit)
-    } else {
-         sqlx::query(query_str)
-             .bind(&sid)
-             .bind(limit)
-    };
-    
-    let rows = query.fetch_all(&state.db).await?;
-
-
-    let mut results = Vec::with_capacity(rows.len());
-    for row in rows {
-        results.push(NodeExport {
-            path: row.get("path"),
-            parent_path: row.get("parent_path"),
-            depth: row.get("depth"),
-            is_dir: row.get("is_dir"),
-            logical_size: row.get("logical_size"),
-            allocated_size: row.get("allocated_size"),
-            file_count: row.get("file_count"),
-            dir_count: row.get("dir_count"),
-        });
+/// Builds a node export record from a `nodes` row.
+fn node_export_from_row(row: &SqliteRow) -> NodeExport {
+    NodeExport {
+        path: row.get("path"),
+        parent_path: row.get("parent_path"),
+        depth: row.get("depth"),
+        is_dir: row.get("is_dir"),
+        logical_size: row.get("logical_size"),
+        allocated_size: row.get("allocated_size"),
+        file_count: row.get("file_count"),
+        dir_count: row.get("dir_count"),
+        mtime: row.get("mtime"),
+        path_raw: raw_path_base64(row),
     }
-    Ok(results)
 }
 
 /// Fetches all files for JSON export (or non-streaming).
-async fn fetch_files_all(state: &AppState, scan_id: Uuid, limit: i64) -> Result<Vec<FileExport>, sqlx::Error> {
+async fn fetch_files_all(
+    state: &AppState,
+    scan_id: Uuid,
+    filter: &ExportFilter,
+    limit: i64,
+) -> Result<Vec<FileExport>, sqlx::Error> {
     let mut results = Vec::new();
-    let mut current_cursor: Option<(i64, String)> = None;
+    let mut current_cursor: Option<ExportCursor> = None;
     let mut count = 0;
     loop {
         let remaining = limit - count;
         if remaining <= 0 { break; }
         let batch_size = EXPORT_CHUNK_SIZE.min(remaining);
         
-        let batch = fetch_files_batch(state, scan_id, batch_size, current_cursor.clone()).await?;
+        let batch = fetch_files_batch(state, scan_id, filter, batch_size, current_cursor.clone()).await?;
 
         if batch.is_empty() { break; }
         
@@ -486,67 +991,602 @@ async fn fetch_files_all(state: &AppState, scan_id: Uuid, limit: i64) -> Result<
 
 /// Fetches a single batch of files for export.
 async fn fetch_files_batch(
-    state: &AppState, 
-    scan_id: Uuid, 
-    limit: i64, 
-    cursor: Option<(i64, String)>
+    state: &AppState,
+    scan_id: Uuid,
+    filter: &ExportFilter,
+    limit: i64,
+    cursor: Option<ExportCursor>,
 ) -> Result<Vec<FileExport>, sqlx::Error> {
-    let sid = scan_id.to_string();
-    // Keyset: (allocated_size, path) < (last_alloc, last_path)
-    // DESC order for allocated_size, ASC for path (determinism)
-    // WHERE allocated_size < ? OR (allocated_size = ? AND path > ?) 
-    
-    let query_str = if cursor.is_some() {
-        "SELECT path, parent_path, logical_size, allocated_size \
-         FROM files WHERE scan_id = ?1 AND (allocated_size < ?2 OR (allocated_size = ?3 AND path > ?4)) \
-         ORDER BY allocated_size DESC, path ASC LIMIT ?5"
-    } else {
-        "SELECT path, parent_path, logical_size, allocated_size \
-         FROM files WHERE scan_id = ?1 ORDER BY allocated_size DESC, path ASC LIMIT ?2"
-    };
+    let mut qb = export_rows_query(ExportTable::Files, scan_id, filter, cursor, limit);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    Ok(rows.iter().map(file_export_from_row).collect())
+}
 
-    let query = if let Some((last_alloc, last_path)) = cursor {
-         sqlx::query(query_str)
-             .bind(&sid)
-             .bind(last_alloc)
-             .bind(last_alloc)
-             .bind(last_path)
-             .bind(limit)
-    } else {
-         sqlx::query(query_str)
-             .bind(&sid)
-             .bind(limit)
-    };
-    
-    let rows = query.fetch_all(&state.db).await?;
+/// Builds a file export record from a `files` row.
+fn file_export_from_row(row: &SqliteRow) -> FileExport {
+    FileExport {
+        path: row.get("path"),
+        parent_path: row.get("parent_path"),
+        logical_size: row.get("logical_size"),
+        allocated_size: row.get("allocated_size"),
+        mtime: row.get("mtime"),
+        path_raw: raw_path_base64(row),
+    }
+}
+
+/// Encodes the `path_raw` column of a row, which is only set for paths that are not valid Unicode.
+fn raw_path_base64(row: &SqliteRow) -> Option<String> {
+    use base64::Engine;
+    row.get::<Option<Vec<u8>>, _>("path_raw").map(|raw| base64::engine::general_purpose::STANDARD.encode(raw))
+}
 
+/// Target size of one NDJSON response chunk in bytes.
+const NDJSON_CHUNK_BYTES: usize = 64 * 1024;
+/// Number of encoded chunks that may wait for a slow client before the reader pauses.
+const NDJSON_QUEUED_CHUNKS: usize = 4;
 
-    let mut results = Vec::with_capacity(rows.len());
-    for row in rows {
-        results.push(FileExport {
-            path: row.get("path"),
-            parent_path: row.get("parent_path"),
-            logical_size: row.get("logical_size"),
-            allocated_size: row.get("allocated_size"),
-        });
+/// A single line of the NDJSON export, tagged with its record type.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum NdjsonRecord {
+    /// A directory record.
+    Node(NodeExport),
+    /// A file record.
+    File(FileExport),
+}
+
+/// Exports scan data as newline-delimited JSON.
+///
+/// Rows are read with a database cursor in a background task and handed to the
+/// response body in chunks of about [`NDJSON_CHUNK_BYTES`]. The bounded channel
+/// between both sides keeps memory usage flat regardless of the scan size.
+///
+/// # Arguments
+///
+/// * `state` - The application state containing database connection
+/// * `scan_id` - The UUID of the scan to export
+/// * `scope` - The export scope: "nodes", "files", or "all"
+/// * `filter` - Restricts and orders the exported records
+/// * `limit` - Maximum number of records to export, or `None` for all records
+/// * `lease` - Keeps the scan from being pruned until the export task ends
+///
+/// # Returns
+///
+/// A chunked HTTP response with NDJSON content and appropriate headers for file download
+fn export_ndjson(
+    state: &AppState,
+    scan_id: Uuid,
+    scope: &str,
+    filter: ExportFilter,
+    limit: Option<i64>,
+    lease: ScanLease,
+) -> Response {
+    use axum::body::Body;
+    use axum::http::HeaderValue;
+
+    let include_nodes = scope == "all" || scope == "nodes";
+    let include_files = scope == "all" || scope == "files";
+    let pool = state.read_pool().clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(NDJSON_QUEUED_CHUNKS);
+
+    tokio::spawn(async move {
+        let _lease = lease;
+        if let Err(e) = stream_ndjson(&pool, scan_id, include_nodes, include_files, &filter, limit, &tx).await {
+            tracing::error!("NDJSON export of scan {} failed: {}", scan_id, e);
+            // Abort the response so the client does not mistake a truncated file for a complete one
+            let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"))
+        .body(Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+        .unwrap();
+
+    let filename = format!("attachment; filename=\"scan_{}.ndjson\"", scan_id);
+    if let Ok(header_val) = HeaderValue::from_str(&filename) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, header_val);
     }
-    Ok(results)
+    response
+}
+
+/// Reads nodes and then files of a scan with a cursor and sends them as NDJSON chunks.
+///
+/// Stops quietly when the receiving side has gone away (client disconnected).
+async fn stream_ndjson(
+    pool: &sqlx::SqlitePool,
+    scan_id: Uuid,
+    include_nodes: bool,
+    include_files: bool,
+    filter: &ExportFilter,
+    limit: Option<i64>,
+    tx: &tokio::sync::mpsc::Sender<std::io::Result<axum::body::Bytes>>,
+) -> anyhow::Result<()> {
+    use futures::stream::TryStreamExt;
+
+    // SQLite treats a negative LIMIT as "no limit"
+    let mut remaining = limit.unwrap_or(-1);
+    let mut buf: Vec<u8> = Vec::with_capacity(NDJSON_CHUNK_BYTES + 4096);
+
+    if include_nodes {
+        let mut qb = export_rows_query(ExportTable::Nodes, scan_id, filter, None, remaining);
+        let mut rows = qb.build().fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            push_ndjson_line(&mut buf, &NdjsonRecord::Node(node_export_from_row(&row)))?;
+            if remaining > 0 {
+                remaining -= 1;
+            }
+            if buf.len() >= NDJSON_CHUNK_BYTES && !send_ndjson_chunk(tx, &mut buf).await {
+                return Ok(());
+            }
+        }
+    }
+
+    if include_files && remaining != 0 {
+        let mut qb = export_rows_query(ExportTable::Files, scan_id, filter, None, remaining);
+        let mut rows = qb.build().fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            push_ndjson_line(&mut buf, &NdjsonRecord::File(file_export_from_row(&row)))?;
+            if buf.len() >= NDJSON_CHUNK_BYTES && !send_ndjson_chunk(tx, &mut buf).await {
+                return Ok(());
+            }
+        }
+    }
+
+    send_ndjson_chunk(tx, &mut buf).await;
+    Ok(())
+}
+
+/// Appends one record and its terminating newline to the chunk buffer.
+fn push_ndjson_line(buf: &mut Vec<u8>, record: &NdjsonRecord) -> serde_json::Result<()> {
+    serde_json::to_writer(&mut *buf, record)?;
+    buf.push(b'\n');
+    Ok(())
+}
+
+/// Sends the buffered lines as one body chunk. Returns `false` if the client is gone.
+async fn send_ndjson_chunk(
+    tx: &tokio::sync::mpsc::Sender<std::io::Result<axum::body::Bytes>>,
+    buf: &mut Vec<u8>,
+) -> bool {
+    if buf.is_empty() {
+        return true;
+    }
+    let chunk = std::mem::replace(buf, Vec::with_capacity(NDJSON_CHUNK_BYTES + 4096));
+    tx.send(Ok(axum::body::Bytes::from(chunk))).await.is_ok()
+}
+
+/// The header of the `format=wds` tree listing.
+const WDS_HEADER: [&str; 5] = ["Name", "Size", "Files", "Folders", "Last Change"];
+
+/// Exports the directory tree of a scan as a WinDirStat-style CSV listing.
+///
+/// The listing has the columns of [`WDS_HEADER`]: the full path (directories
+/// end with a separator), the allocated or logical size, the number of files
+/// and folders below a directory (empty for files) and the modification time
+/// in UTC. Rows come in depth-first order: each directory is followed by its
+/// own files and then its subdirectories, so every parent precedes its
+/// children. Files whose directory is not stored are left out. Like NDJSON the
+/// rows are streamed from database cursors in a background task.
+///
+/// # Arguments
+///
+/// * `state` - The application state containing database connection
+/// * `scan_id` - The UUID of the scan to export
+/// * `root` - The directory whose subtree is exported, or `None` for the whole scan
+/// * `logical` - Reports logical instead of allocated sizes
+/// * `lease` - Keeps the scan from being pruned until the export task ends
+///
+/// # Returns
+///
+/// A chunked HTTP response with the listing as a file download
+fn export_wds(state: &AppState, scan_id: Uuid, root: Option<String>, logical: bool, lease: ScanLease) -> Response {
+    use axum::body::Body;
+    use axum::http::HeaderValue;
+
+    let pool = state.read_pool().clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(NDJSON_QUEUED_CHUNKS);
+    tokio::spawn(async move {
+        let _lease = lease;
+        if let Err(e) = stream_wds(&pool, scan_id, root.as_deref(), logical, &tx).await {
+            tracing::error!("Tree export of scan {} failed: {}", scan_id, e);
+            let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8"))
+        .body(Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+        .unwrap();
+    let filename = format!("attachment; filename=\"scan_{}_tree.csv\"", scan_id);
+    if let Ok(header_val) = HeaderValue::from_str(&filename) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, header_val);
+    }
+    response
+}
+
+/// The SQL expression ordering paths depth-first, matching [`tree_order_key`].
+fn tree_order_sql(column: &str) -> String {
+    format!("replace(replace({}, '\\', char(1)), '/', char(1))", column)
+}
+
+/// Returns the key that orders paths depth-first.
+///
+/// Separators sort before every other character, so a directory's subtree
+/// follows it without siblings like `a b` or `a.txt` in between. SQLite
+/// compares the keys bytewise, like `str` does.
+fn tree_order_key(path: &str) -> String {
+    path.replace(['\\', '/'], "\u{1}")
+}
+
+/// Appends the subtree filter for `root` to an export query.
+fn push_subtree_filter(qb: &mut sqlx::QueryBuilder<'static, sqlx::Sqlite>, root: Option<&str>) {
+    if let Some(root) = root {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+}
+
+/// Formats Unix seconds for the `Last Change` column.
+fn wds_time(mtime: Option<i64>) -> String {
+    mtime
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the directories and files of a scan in depth-first order and sends them as tree listing chunks.
+///
+/// Both tables are read with their own cursor in the same order and merged,
+/// so memory stays flat. Stops quietly when the client has gone away.
+async fn stream_wds(
+    pool: &sqlx::SqlitePool,
+    scan_id: Uuid,
+    root: Option<&str>,
+    logical: bool,
+    tx: &tokio::sync::mpsc::Sender<std::io::Result<axum::body::Bytes>>,
+) -> anyhow::Result<()> {
+    use futures::stream::TryStreamExt;
+
+    let size_column = if logical { "logical_size" } else { "allocated_size" };
+    let mut dirs_qb = sqlx::QueryBuilder::new(format!(
+        "SELECT path, file_count, dir_count, {} AS size, mtime FROM nodes WHERE is_dir = 1 AND scan_id = ",
+        size_column
+    ));
+    dirs_qb.push_bind(scan_id.to_string());
+    push_subtree_filter(&mut dirs_qb, root);
+    dirs_qb.push(format!(" ORDER BY {}", tree_order_sql("path")));
+    let mut files_qb = sqlx::QueryBuilder::new(format!(
+        "SELECT path, parent_path, {} AS size, mtime FROM files WHERE parent_path IS NOT NULL AND scan_id = ",
+        size_column
+    ));
+    files_qb.push_bind(scan_id.to_string());
+    push_subtree_filter(&mut files_qb, root);
+    files_qb.push(format!(" ORDER BY {}, path", tree_order_sql("parent_path")));
+
+    let mut dirs = dirs_qb.build().fetch(pool);
+    let mut files = files_qb.build().fetch(pool);
+    let mut next_file = files.try_next().await?;
+    let mut chunk = String::with_capacity(NDJSON_CHUNK_BYTES + 4096);
+    push_csv_record(&mut chunk, WDS_HEADER, ',');
+
+    while let Some(dir) = dirs.try_next().await? {
+        let path: String = dir.get("path");
+        let name = if path.ends_with(['\\', '/']) {
+            path.clone()
+        } else {
+            format!("{}{}", path, if path.contains('\\') { '\\' } else { '/' })
+        };
+        let fields = [
+            name,
+            dir.get::<i64, _>("size").to_string(),
+            dir.get::<i64, _>("file_count").to_string(),
+            dir.get::<i64, _>("dir_count").to_string(),
+            wds_time(dir.get("mtime")),
+        ];
+        push_csv_record(&mut chunk, fields, ',');
+
+        let key = tree_order_key(&path);
+        while let Some(file) = &next_file {
+            let parent_key = tree_order_key(file.get::<&str, _>("parent_path"));
+            if parent_key > key {
+                break;
+            }
+            // Files sorting before the current directory belong to none that is stored
+            if parent_key == key {
+                let fields = [
+                    file.get::<String, _>("path"),
+                    file.get::<i64, _>("size").to_string(),
+                    String::new(),
+                    String::new(),
+                    wds_time(file.get("mtime")),
+                ];
+                push_csv_record(&mut chunk, fields, ',');
+            }
+            next_file = files.try_next().await?;
+        }
+
+        if chunk.len() >= NDJSON_CHUNK_BYTES {
+            let bytes = axum::body::Bytes::from(std::mem::take(&mut chunk));
+            if tx.send(Ok(bytes)).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        let _ = tx.send(Ok(axum::body::Bytes::from(chunk))).await;
+    }
+    Ok(())
+}
+
+/// The formats [`write_export`] can produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The CSV layout of `GET /scans/{id}/export?format=csv`.
+    Csv(CsvOptions),
+    /// The NDJSON layout of `GET /scans/{id}/export?format=ndjson`.
+    Ndjson,
+}
+
+/// Writes the nodes and/or files of a scan to `out`.
+///
+/// Used by the command line, which has no HTTP response to stream into.
+/// Unlike the endpoint, no record limit applies.
+///
+/// # Arguments
+///
+/// * `state` - The application state containing database connection
+/// * `scan_id` - The UUID of the scan to export
+/// * `format` - The output format
+/// * `scope` - The export scope: "nodes", "files", or "all"
+/// * `out` - The writer receiving the export
+pub async fn write_export<W: tokio::io::AsyncWrite + Unpin>(
+    state: &AppState,
+    scan_id: Uuid,
+    format: ExportFormat,
+    scope: &str,
+    out: &mut W,
+) -> anyhow::Result<()> {
+    use futures::stream::TryStreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let _lease = state.scan_leases.acquire(scan_id);
+    match format {
+        ExportFormat::Csv(csv) => {
+            let chunks = csv_chunks(state.clone(), scan_id, scope, ExportFilter::default(), csv, i64::MAX);
+            let mut chunks = std::pin::pin!(chunks);
+            while let Some(chunk) = chunks.try_next().await? {
+                out.write_all(chunk.as_bytes()).await?;
+            }
+        }
+        ExportFormat::Ndjson => {
+            let include_nodes = scope == "all" || scope == "nodes";
+            let include_files = scope == "all" || scope == "files";
+            let pool = state.read_pool().clone();
+            let (tx, mut rx) = tokio::sync::mpsc::channel(NDJSON_QUEUED_CHUNKS);
+            let reader = async move {
+                let res = stream_ndjson(&pool, scan_id, include_nodes, include_files, &ExportFilter::default(), None, &tx).await;
+                drop(tx);
+                res
+            };
+            // Owning the receiver lets a failed write stop the reader instead of blocking it
+            let sink = &mut *out;
+            let writer = async move {
+                while let Some(chunk) = rx.recv().await {
+                    sink.write_all(&chunk?).await?;
+                }
+                anyhow::Ok(())
+            };
+            let (read, written) = tokio::join!(reader, writer);
+            read?;
+            written?;
+        }
+    }
+    out.flush().await?;
+    Ok(())
+}
+
+/// Default number of extensions listed in the statistics breakdown.
+const EXTENSIONS_TOP_DEFAULT: usize = 20;
+/// Upper bound for the `top` query parameter of the statistics endpoint.
+const EXTENSIONS_TOP_MAX: usize = 500;
+
+/// Returns the lowercased extension of a file path, including the leading dot.
+///
+/// Both `/` and `\\` are treated as separators. Dotfiles such as `.bashrc`
+/// have no extension.
+fn file_extension(path: &str) -> Option<String> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match name.rfind('.') {
+        Some(idx) if idx > 0 && idx + 1 < name.len() => Some(name[idx..].to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Sorts extension stats by allocated size and folds everything past `top`
+/// into a single "other" entry.
+fn rollup_extensions(mut stats: Vec<ExtensionStat>, top: usize) -> Vec<ExtensionStat> {
+    stats.sort_by(|a, b| b.allocated_size.cmp(&a.allocated_size).then_with(|| a.extension.cmp(&b.extension)));
+    if stats.len() > top {
+        let rest = stats.split_off(top);
+        let mut other = ExtensionStat { extension: "other".to_string(), ..Default::default() };
+        for s in rest {
+            other.count += s.count;
+            other.logical_size += s.logical_size;
+            other.allocated_size += s.allocated_size;
+        }
+        stats.push(other);
+    }
+    stats
+}
+
+/// Computes the per-extension breakdown of all files in a scan.
+///
+/// Rows are streamed and aggregated in memory, so only one entry per distinct
+/// extension is kept regardless of the number of files.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `scan_id` - The ID of the scan.
+/// * `root` - Optional normalized path restricting the breakdown to a subtree.
+/// * `top` - The number of extensions to list before rolling up into "other".
+///
+/// # Returns
+///
+/// * `AppResult<Vec<ExtensionStat>>` - The breakdown, largest allocated size first.
+async fn extension_breakdown(
+    state: &AppState,
+    scan_id: Uuid,
+    root: Option<&str>,
+    top: usize,
+) -> AppResult<Vec<ExtensionStat>> {
+    use futures::stream::TryStreamExt;
+    use std::collections::HashMap;
+
+    let mut qb = sqlx::QueryBuilder::new("SELECT path, logical_size, allocated_size FROM files WHERE scan_id=");
+    qb.push_bind(scan_id.to_string());
+    if let Some(root) = root {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+
+    let mut by_ext: HashMap<String, ExtensionStat> = HashMap::new();
+    let mut rows = qb.build().fetch(state.read_pool());
+    while let Some(row) = rows.try_next().await? {
+        let path: String = row.get("path");
+        let ext = file_extension(&path).unwrap_or_else(|| "(none)".to_string());
+        let entry = by_ext.entry(ext).or_insert_with_key(|k| ExtensionStat { extension: k.clone(), ..Default::default() });
+        entry.count += 1;
+        entry.logical_size += row.get::<i64, _>("logical_size");
+        entry.allocated_size += row.get::<i64, _>("allocated_size");
+    }
+
+    Ok(rollup_extensions(by_ext.into_values().collect(), top))
+}
+
+/// Per-category totals of a scan, optionally restricted to a subtree.
+///
+/// Files stored without a category (scans run with classification disabled)
+/// are left out, so the result is empty for such scans.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `scan_id` - The ID of the scan.
+/// * `root` - Optional normalized path restricting the totals to a subtree.
+///
+/// # Returns
+///
+/// * `AppResult<Vec<CategoryStat>>` - The totals, largest allocated size first.
+async fn category_breakdown(
+    state: &AppState,
+    scan_id: Uuid,
+    root: Option<&str>,
+) -> AppResult<Vec<CategoryStat>> {
+    let mut qb = sqlx::QueryBuilder::new(
+        "SELECT category, COUNT(*) AS count, COALESCE(SUM(logical_size),0) AS logical, \
+         COALESCE(SUM(allocated_size),0) AS allocated FROM files WHERE category IS NOT NULL AND scan_id=",
+    );
+    qb.push_bind(scan_id.to_string());
+    if let Some(root) = root {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+    qb.push(" GROUP BY category ORDER BY allocated DESC, category");
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    Ok(rows
+        .iter()
+        .map(|row| CategoryStat {
+            category: row.get("category"),
+            count: row.get("count"),
+            logical_size: row.get("logical"),
+            allocated_size: row.get("allocated"),
+        })
+        .collect())
+}
+
+/// The sizes of the statistics response written with `sizes`, as in CSV exports.
+fn formatted_statistics(
+    stats: &serde_json::Value,
+    extensions: &[ExtensionStat],
+    categories: &[CategoryStat],
+    sizes: &SizeFormat,
+) -> serde_json::Value {
+    let size = |key: &str| stats[key].as_i64().map(|bytes| sizes.format(bytes));
+    let by_extension: serde_json::Map<String, serde_json::Value> = extensions
+        .iter()
+        .map(|e| {
+            let value = serde_json::json!({
+                "logical_size": sizes.format(e.logical_size),
+                "allocated_size": sizes.format(e.allocated_size),
+            });
+            (e.extension.clone(), value)
+        })
+        .collect();
+    let by_category: serde_json::Map<String, serde_json::Value> = categories
+        .iter()
+        .map(|c| {
+            let value = serde_json::json!({
+                "logical_size": sizes.format(c.logical_size),
+                "allocated_size": sizes.format(c.allocated_size),
+            });
+            (c.category.clone(), value)
+        })
+        .collect();
+    serde_json::json!({
+        "unit": sizes.header_unit(),
+        "total_logical_size": size("total_logical_size"),
+        "total_allocated_size": size("total_allocated_size"),
+        "dedup_saved_bytes": size("dedup_saved_bytes"),
+        "extensions": by_extension,
+        "categories": by_category,
+    })
 }
 
 /// Exports summary statistics for a scan.
 ///
+/// The response includes an `extensions` array with count, logical size and
+/// allocated size per (case-insensitive) file extension. With `?path=` the
+/// breakdown is limited to that subtree; `?top=` controls how many extensions
+/// are listed before the remainder is rolled into "other". A `categories` array
+/// holds the same totals per file category (see `[categories]` in the config),
+/// restricted to the same subtree. With `?units=` (and
+/// optionally `?locale=`) a `formatted` object repeats the sizes the way CSV
+/// exports write them; the numeric fields stay in bytes.
+///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan.
+/// * `q` - The statistics query parameters.
 ///
 /// # Returns
 ///
 /// * `AppResult<impl IntoResponse>` - A JSON response containing the scan statistics.
 pub async fn export_statistics(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
+    Query(q): Query<StatisticsQuery>,
 ) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let root = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(state.read_pool(), id, p).await?)
+        }
+        None => None,
+    };
+    let top = q.top.unwrap_or(EXTENSIONS_TOP_DEFAULT).clamp(1, EXTENSIONS_TOP_MAX);
+    let sizes = q.units.is_some().then(|| SizeFormat::from_query(q.units.as_deref(), q.locale.as_deref())).transpose()?;
+
     let stats = sqlx::query(
         r#"
         SELECT 
@@ -559,9 +1599,11 @@ pub async fn export_statistics(
             s.dir_count,
             s.file_count,
             s.warning_count,
+            s.dedup_saved_bytes,
             (SELECT COUNT(*) FROM nodes WHERE scan_id = s.id) as total_nodes,
             (SELECT COUNT(*) FROM files WHERE scan_id = s.id) as total_files,
             (SELECT MAX(depth) FROM nodes WHERE scan_id = s.id) as max_depth,
+            (SELECT SUM(empty_file_count) FROM nodes WHERE scan_id = s.id) as empty_file_count,
             (SELECT path FROM nodes WHERE scan_id = s.id ORDER BY allocated_size DESC LIMIT 1) as largest_dir,
             (SELECT path FROM files WHERE scan_id = s.id ORDER BY allocated_size DESC LIMIT 1) as largest_file
         FROM scans s
@@ -569,11 +1611,13 @@ pub async fn export_statistics(
         "#,
     )
     .bind(id.to_string())
-    .fetch_optional(&state.db)
+    .fetch_optional(state.read_pool())
     .await?;
 
     if let Some(row) = stats {
-        let stats_json = serde_json::json!({
+        let extensions = extension_breakdown(&state, id, root.as_deref(), top).await?;
+        let categories = category_breakdown(&state, id, root.as_deref()).await?;
+        let mut stats_json = serde_json::json!({
             "scan_id": row.get::<String, _>("id"),
             "status": row.get::<String, _>("status"),
             "started_at": row.get::<Option<String>, _>("started_at"),
@@ -583,16 +1627,562 @@ pub async fn export_statistics(
             "dir_count": row.get::<Option<i64>, _>("dir_count"),
             "file_count": row.get::<Option<i64>, _>("file_count"),
             "warning_count": row.get::<Option<i64>, _>("warning_count"),
+            "dedup_saved_bytes": row.get::<Option<i64>, _>("dedup_saved_bytes"),
             "total_nodes": row.get::<i64, _>("total_nodes"),
             "total_files": row.get::<i64, _>("total_files"),
             "max_depth": row.get::<Option<i64>, _>("max_depth"),
+            "empty_file_count": row.get::<Option<i64>, _>("empty_file_count"),
             "largest_dir": row.get::<Option<String>, _>("largest_dir"),
             "largest_file": row.get::<Option<String>, _>("largest_file"),
+            "extensions_path": root,
+            "extensions": extensions,
+            "categories": categories,
             "exported_at": chrono::Utc::now().to_rfc3339(),
         });
+        if let Some(sizes) = sizes {
+            stats_json["formatted"] = formatted_statistics(&stats_json, &extensions, &categories, &sizes);
+        }
 
         Ok(Json(stats_json))
     } else {
         Err(AppError::NotFound("Scan not found".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_scan, test_db, test_db_in, FileRow};
+
+    fn stat(ext: &str, count: i64, allocated: i64) -> ExtensionStat {
+        ExtensionStat { extension: ext.into(), count, logical_size: allocated, allocated_size: allocated }
+    }
+
+    #[test]
+    fn file_extension_is_lowercased() {
+        assert_eq!(file_extension("/data/Movie.MP4").as_deref(), Some(".mp4"));
+        assert_eq!(file_extension("C:\\Photos\\IMG_01.JPG").as_deref(), Some(".jpg"));
+        assert_eq!(file_extension("/data/archive.tar.gz").as_deref(), Some(".gz"));
+    }
+
+    #[test]
+    fn file_extension_ignores_dotfiles_and_dirs_with_dots() {
+        assert_eq!(file_extension("/home/u/.bashrc"), None);
+        assert_eq!(file_extension("/home/u/v1.2/README"), None);
+        assert_eq!(file_extension("/data/trailing."), None);
+    }
+
+    #[test]
+    fn rollup_keeps_top_and_sums_rest() {
+        let stats = vec![stat(".a", 1, 10), stat(".b", 2, 30), stat(".c", 3, 20), stat(".d", 4, 5)];
+        let out = rollup_extensions(stats, 2);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].extension, ".b");
+        assert_eq!(out[1].extension, ".c");
+        assert_eq!(out[2], stat("other", 5, 15));
+    }
+
+    #[test]
+    fn rollup_without_overflow_has_no_other() {
+        let out = rollup_extensions(vec![stat(".a", 1, 10)], 5);
+        assert_eq!(out, vec![stat(".a", 1, 10)]);
+    }
+
+    async fn ndjson_fixture(files: i64) -> (tempfile::TempDir, AppState, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &[], "default").await;
+        sqlx::query(
+            "INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count) \
+             VALUES (?1, '/data', NULL, 1, 1, ?2, ?2, ?2, 0)",
+        )
+        .bind(id.to_string())
+        .bind(files)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < ?2) \
+             INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size) \
+             SELECT ?1, '/data/file_' || n || '.bin', '/data', n, n FROM seq",
+        )
+        .bind(id.to_string())
+        .bind(files)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        (dir, state, id)
+    }
+
+    async fn export(state: &AppState, id: Uuid, scope: Option<&str>, limit: Option<i64>) -> Response {
+        let query = ExportQuery { format: "ndjson".into(), scope: scope.map(str::to_string), limit, ..Default::default() };
+        export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await.unwrap()
+    }
+
+    async fn collect_lines(response: Response) -> Vec<serde_json::Value> {
+        use http_body_util::BodyExt;
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        bytes.split(|b| *b == b'\n').filter(|l| !l.is_empty()).map(|l| serde_json::from_slice(l).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn ndjson_streams_large_scans_in_chunks() {
+        use http_body_util::BodyExt;
+        const FILES: i64 = 300_000;
+        let (_dir, state, id) = ndjson_fixture(FILES).await;
+
+        let response = export(&state, id, None, None).await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+        let disposition = response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap();
+        assert!(disposition.contains(&format!("scan_{}.ndjson", id)));
+
+        let mut body = response.into_body();
+        let (mut chunks, mut lines, mut largest) = (0usize, 0usize, 0usize);
+        let mut first_line: Option<Vec<u8>> = None;
+        while let Some(frame) = body.frame().await {
+            let data = frame.unwrap().into_data().unwrap();
+            chunks += 1;
+            largest = largest.max(data.len());
+            lines += data.iter().filter(|b| **b == b'\n').count();
+            if first_line.is_none() {
+                first_line = data.split(|b| *b == b'\n').next().map(<[u8]>::to_vec);
+            }
+        }
+
+        assert_eq!(lines, FILES as usize + 1);
+        assert!(chunks > 100, "expected a chunked body, got {} chunks", chunks);
+        assert!(largest < NDJSON_CHUNK_BYTES + 4096, "chunk of {} bytes", largest);
+        let first: serde_json::Value = serde_json::from_slice(&first_line.unwrap()).unwrap();
+        assert_eq!(first["type"], "node");
+        assert_eq!(first["path"], "/data");
+    }
+
+    #[tokio::test]
+    async fn ndjson_respects_scope_and_limit() {
+        let (_dir, state, id) = ndjson_fixture(50).await;
+
+        let files = collect_lines(export(&state, id, Some("files"), Some(5)).await).await;
+        assert_eq!(files.len(), 5);
+        assert!(files.iter().all(|l| l["type"] == "file"));
+        assert_eq!(files[0]["allocated_size"], 50);
+
+        let nodes = collect_lines(export(&state, id, Some("nodes"), None).await).await;
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0]["type"], "node");
+
+        // A limit of 0 means "no limit" for NDJSON, and "all" counts nodes towards the limit
+        assert_eq!(collect_lines(export(&state, id, None, Some(0)).await).await.len(), 51);
+        let limited = collect_lines(export(&state, id, Some("all"), Some(3)).await).await;
+        assert_eq!(limited.len(), 3);
+        assert_eq!(limited[0]["type"], "node");
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        let mut out = String::new();
+        push_csv_record(&mut out, ["plain", "a,b", "say \"hi\"", "two\nlines", "x;y"], ',');
+        assert_eq!(out, "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",x;y\r\n");
+
+        let mut out = String::new();
+        push_csv_record(&mut out, ["a,b", "x;y"], ';');
+        assert_eq!(out, "a,b;\"x;y\"\r\n");
+    }
+
+    #[test]
+    fn csv_options_parse_columns_and_separator() {
+        let opts = CsvOptions::from_query(Some("path, allocated_size,mtime,path"), Some("semicolon")).unwrap();
+        assert_eq!(opts.columns, Some(vec![CsvColumn::Path, CsvColumn::AllocatedSize, CsvColumn::Mtime]));
+        assert_eq!(opts.separator, ';');
+        assert_eq!(CsvOptions::from_query(None, None).unwrap(), CsvOptions::default());
+        assert!(matches!(CsvOptions::from_query(Some("path,size"), None), Err(AppError::BadRequest(_))));
+        assert!(matches!(CsvOptions::from_query(None, Some("tab")), Err(AppError::BadRequest(_))));
+    }
+
+    /// Minimal RFC 4180 reader used to check that exported CSV round-trips.
+    fn parse_csv(text: &str, sep: char) -> Vec<Vec<String>> {
+        let (mut records, mut record, mut field) = (Vec::new(), Vec::new(), String::new());
+        let mut chars = text.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = !quoted,
+                c if c == sep && !quoted => record.push(std::mem::take(&mut field)),
+                '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+                '\n' if !quoted => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                c => field.push(c),
+            }
+        }
+        records
+    }
+
+    async fn csv_export(state: &AppState, id: Uuid, query: ExportQuery) -> String {
+        use http_body_util::BodyExt;
+        let query = ExportQuery { format: "csv".into(), ..query };
+        let response = export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn csv_round_trips_paths_with_quotes_separators_and_newlines() {
+        let (_dir, state, id) = ndjson_fixture(0).await;
+        let tricky = ["/data/say \"cheese\".jpg", "/data/a,b;c.txt", "/data/two\nlines\r\n.txt"];
+        sqlx::query("DELETE FROM files").execute(&state.db).await.unwrap();
+        for (i, path) in tricky.into_iter().enumerate() {
+            let file = FileRow {
+                path,
+                parent: Some("/data"),
+                size: 100 - i as i64,
+                mtime: Some(1_700_000_000),
+                atime: None,
+            };
+            insert_file(&state.db, id, file).await;
+        }
+
+        for (sep, sep_name) in [(',', "comma"), (';', "semicolon")] {
+            let query = ExportQuery {
+                scope: Some("files".into()),
+                columns: Some("path,allocated_size,mtime".into()),
+                sep: Some(sep_name.into()),
+                ..Default::default()
+            };
+            let records = parse_csv(&csv_export(&state, id, query).await, sep);
+            assert_eq!(records[0], ["Path", "Allocated Size", "Modified"]);
+            let paths: Vec<&str> = records[1..].iter().map(|r| r[0].as_str()).collect();
+            assert_eq!(paths, tricky);
+            assert_eq!(records[1], [tricky[0], "100", "1700000000"]);
+            assert!(records.iter().all(|r| r.len() == 3));
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_carry_the_exact_bytes_of_lossy_paths() {
+        let (_dir, state, id) = ndjson_fixture(1).await;
+        sqlx::query("UPDATE files SET path='/data/f\u{FFFD}.bin', path_raw=x'2f646174612f66fe2e62696e'")
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        let columns = Some("path,path_raw".into());
+        let query = ExportQuery { scope: Some("files".into()), columns, ..Default::default() };
+        let records = parse_csv(&csv_export(&state, id, query).await, ',');
+        assert_eq!(records[0], ["Path", "Raw Path (Base64)"]);
+        assert_eq!(records[1], ["/data/f\u{FFFD}.bin", "L2RhdGEvZv4uYmlu"]);
+
+        let lines = collect_lines(export(&state, id, None, None).await).await;
+        assert_eq!(lines[0].get("path_raw"), None);
+        assert_eq!(lines[1]["path_raw"], "L2RhdGEvZv4uYmlu");
+    }
+
+    #[tokio::test]
+    async fn csv_keeps_default_sections_and_applies_filters() {
+        let (_dir, state, id) = ndjson_fixture(3).await;
+
+        let text = csv_export(&state, id, ExportQuery::default()).await;
+        let records = parse_csv(&text, ',');
+        assert_eq!(records[0].join(","), "Type,Path,Parent Path,Depth,Is Directory,Logical Size,Allocated Size,File Count,Dir Count");
+        assert_eq!(records[1], ["Dir", "/data", "", "1", "1", "3", "3", "3", "0"]);
+        assert_eq!(records[2], [""]);
+        assert_eq!(records[3].join(","), "Type,Path,Parent Path,Logical Size,Allocated Size");
+        assert_eq!(records.len(), 7);
+
+        let query = ExportQuery { scope: Some("files".into()), min_size: Some(2), path: Some("/data".into()), ..Default::default() };
+        let records = parse_csv(&csv_export(&state, id, query).await, ',');
+        let paths: Vec<&str> = records[1..].iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(paths, ["/data/file_3.bin", "/data/file_2.bin"]);
+
+        let query = ExportQuery { format: "csv".into(), columns: Some("size".into()), ..Default::default() };
+        let res = export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn sizes_round_to_two_decimals_half_up() {
+        let en = |unit| SizeFormat { unit, locale: NumberLocale::En };
+        let de = |unit| SizeFormat { unit, locale: NumberLocale::De };
+        assert_eq!(en(SizeUnit::Bytes).format(1536), "1536");
+        assert_eq!(de(SizeUnit::Bytes).format(1536), "1536");
+        assert_eq!(en(SizeUnit::Kib).format(1536), "1.50");
+        assert_eq!(de(SizeUnit::Kib).format(1536), "1,50");
+        // 0.125 and 0.375 KiB: exact halves round up, not to even
+        assert_eq!(en(SizeUnit::Kib).format(128), "0.13");
+        assert_eq!(en(SizeUnit::Kib).format(384), "0.38");
+        // 0.12402 KiB and 0.12597 KiB
+        assert_eq!(en(SizeUnit::Kib).format(127), "0.12");
+        assert_eq!(en(SizeUnit::Kib).format(129), "0.13");
+        assert_eq!(en(SizeUnit::Kib).format(0), "0.00");
+        assert_eq!(de(SizeUnit::Mib).format(1_179_648), "1,13");
+        assert_eq!(en(SizeUnit::Gib).format(5 << 30), "5.00");
+        assert_eq!(en(SizeUnit::Gib).format(1 << 20), "0.00");
+
+        assert_eq!(de(SizeUnit::Auto).format(0), "0 B");
+        assert_eq!(de(SizeUnit::Auto).format(1018), "1018 B");
+        // Rounds to 1.00 KiB, so it is written in KiB
+        assert_eq!(de(SizeUnit::Auto).format(1023), "1,00 KiB");
+        assert_eq!(de(SizeUnit::Auto).format(1024), "1,00 KiB");
+        assert_eq!(de(SizeUnit::Auto).format((1 << 20) - 1), "1,00 MiB");
+        assert_eq!(en(SizeUnit::Auto).format(1_610_612_736), "1.50 GiB");
+        assert_eq!(en(SizeUnit::Auto).format(3 << 40), "3.00 TiB");
+        assert_eq!(en(SizeUnit::Auto).format(i64::MAX), "8192.00 PiB");
+
+        assert_eq!(SizeFormat::from_query(None, None).unwrap(), SizeFormat::default());
+        assert_eq!(SizeFormat::from_query(Some("mib"), Some("de")).unwrap(), de(SizeUnit::Mib));
+        assert!(matches!(SizeFormat::from_query(Some("kb"), None), Err(AppError::BadRequest(_))));
+        assert!(matches!(SizeFormat::from_query(None, Some("fr")), Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn csv_sizes_follow_units_and_locale_and_keep_raw_bytes() {
+        let (_dir, state, id) = ndjson_fixture(0).await;
+        sqlx::query(
+            "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size) \
+             VALUES (?1, '/data/big.iso', '/data', 1610612736, 1610612736), (?1, '/data/tiny.txt', '/data', 100, 4096)",
+        )
+        .bind(id.to_string())
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+        let query = ExportQuery {
+            scope: Some("files".into()),
+            columns: Some("path,allocated_size,allocated_bytes".into()),
+            sep: Some("semicolon".into()),
+            units: Some("mib".into()),
+            locale: Some("de".into()),
+            ..Default::default()
+        };
+        let records = parse_csv(&csv_export(&state, id, query).await, ';');
+        assert_eq!(records[0], ["Path", "Allocated Size (MiB)", "Allocated Bytes"]);
+        assert_eq!(records[1], ["/data/big.iso", "1536,00", "1610612736"]);
+        assert_eq!(records[2], ["/data/tiny.txt", "0,00", "4096"]);
+
+        let query = ExportQuery {
+            scope: Some("files".into()),
+            columns: Some("path,logical_size,allocated_size".into()),
+            units: Some("auto".into()),
+            locale: Some("de".into()),
+            ..Default::default()
+        };
+        let records = parse_csv(&csv_export(&state, id, query).await, ',');
+        assert_eq!(records[0], ["Path", "Logical Size", "Allocated Size"]);
+        assert_eq!(records[1], ["/data/big.iso", "1,50 GiB", "1,50 GiB"]);
+        assert_eq!(records[2], ["/data/tiny.txt", "100 B", "4,00 KiB"]);
+
+        let query = ExportQuery { format: "json".into(), units: Some("kib".into()), ..Default::default() };
+        let res = export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+
+        let q = StatisticsQuery { units: Some("auto".into()), locale: Some("de".into()), ..Default::default() };
+        let stats = export_statistics(State(state.clone()), Namespace::default(), Path(id), Query(q)).await.unwrap();
+        use http_body_util::BodyExt;
+        let bytes = stats.into_response().into_body().collect().await.unwrap().to_bytes();
+        let stats: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let sizes = SizeFormat { unit: SizeUnit::Auto, locale: NumberLocale::De };
+        let iso = &stats["formatted"]["extensions"][".iso"];
+        assert_eq!(iso["allocated_size"], sizes.format(1_610_612_736));
+        assert_eq!(iso["allocated_size"], "1,50 GiB");
+    }
+
+    async fn wds_export(state: &AppState, id: Uuid, query: ExportQuery) -> Vec<Vec<String>> {
+        use http_body_util::BodyExt;
+        let query = ExportQuery { format: "wds".into(), ..query };
+        let response = export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        parse_csv(std::str::from_utf8(&bytes).unwrap(), ',')
+    }
+
+    /// Rebuilds the tree from a listing and checks every directory against the `nodes` table.
+    async fn check_wds_tree(state: &AppState, id: Uuid, records: &[Vec<String>], size_column: &str) {
+        use std::collections::HashMap;
+        assert_eq!(records[0], WDS_HEADER);
+        // Depth-first: the directories on the stack are the ancestors of the current row
+        let mut stack: Vec<String> = Vec::new();
+        let mut file_sizes: HashMap<String, i64> = HashMap::new();
+        let mut listed: Vec<(String, i64)> = Vec::new();
+        for record in &records[1..] {
+            let name = &record[0];
+            let is_dir = name.ends_with('/');
+            let path = name.trim_end_matches('/').to_string();
+            let parent = path.rsplit_once('/').map(|(p, _)| p.to_string()).unwrap_or_default();
+            while stack.last().is_some_and(|top| *top != parent) {
+                stack.pop();
+            }
+            assert!(stack.last().is_some() || listed.is_empty(), "{} does not follow its parent", name);
+            let size: i64 = record[1].parse().unwrap();
+            if is_dir {
+                listed.push((path.clone(), size));
+                stack.push(path);
+            } else {
+                assert_eq!((record[2].as_str(), record[3].as_str()), ("", ""));
+                for dir in &stack {
+                    *file_sizes.entry(dir.clone()).or_default() += size;
+                }
+            }
+        }
+        for (dir, size) in &listed {
+            let stored: i64 = sqlx::query_scalar(&format!("SELECT {} FROM nodes WHERE scan_id=?1 AND path=?2", size_column))
+                .bind(id.to_string())
+                .bind(dir)
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+            assert_eq!(*size, stored, "{}", dir);
+            assert_eq!(file_sizes.get(dir).copied().unwrap_or(0), stored, "{}", dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn wds_tree_round_trips_against_the_nodes_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        // Siblings whose names sort between a directory and its children
+        for sub in ["b", "b c", "b.d", "b/x", "b/x/y", "e"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        for (i, file) in ["top.bin", "b/1.bin", "b c/2.bin", "b.d/3.bin", "b/x/4.bin", "b/x/y/5.bin", "b/z.bin"]
+            .iter()
+            .enumerate()
+        {
+            std::fs::write(root.join(file), vec![0u8; 1000 * (i + 1) + i]).unwrap();
+        }
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let id = insert_scan(&state.db, "done", &[], "default").await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(1024);
+        crate::scanner::run_scan(
+            state.db.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            Default::default(),
+            tx,
+            tokio_util::sync::CancellationToken::new(),
+            Default::default(),
+            100,
+            200,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let records = wds_export(&state, id, ExportQuery::default()).await;
+        assert_eq!(records.len(), 1 + 7 + 7);
+        assert_eq!(records[1][0], format!("{}/", root.display()));
+        check_wds_tree(&state, id, &records, "allocated_size").await;
+        let query = ExportQuery { size: Some("logical".into()), ..Default::default() };
+        check_wds_tree(&state, id, &wds_export(&state, id, query).await, "logical_size").await;
+
+        let sub = root.join("b").to_string_lossy().to_string();
+        let records = wds_export(&state, id, ExportQuery { path: Some(sub.clone()), ..Default::default() }).await;
+        assert_eq!(records[1][0], format!("{}/", sub));
+        assert_eq!(records.len(), 1 + 3 + 4);
+        check_wds_tree(&state, id, &records, "allocated_size").await;
+
+        let query = ExportQuery { format: "wds".into(), scope: Some("files".into()), ..Default::default() };
+        let res = export_scan(State(state.clone()), Namespace::default(), Path(id), Query(query)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn statistics_total_files_per_category_of_the_scan() {
+        use crate::config::{CategoriesConfig, CategoryConfig};
+        use http_body_util::BodyExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("media")).unwrap();
+        let files = [("media/a.MP4", 3000), ("media/b.mkv", 2000), ("app.log", 500)];
+        for (file, len) in files.into_iter().chain([("notes.xyz", 70), ("plain", 30)]) {
+            std::fs::write(root.join(file), vec![0u8; len]).unwrap();
+        }
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let id = insert_scan(&state.db, "done", &[], "default").await;
+        // "mkv" is listed twice; the later category wins
+        let categories = CategoriesConfig {
+            enabled: true,
+            groups: vec![
+                CategoryConfig { name: "video".into(), extensions: vec!["mp4".into(), "mkv".into()] },
+                CategoryConfig { name: "logs".into(), extensions: vec!["log".into()] },
+                CategoryConfig { name: "recordings".into(), extensions: vec!["mkv".into()] },
+            ],
+        };
+        let (tx, _rx) = tokio::sync::broadcast::channel(1024);
+        crate::scanner::run_scan(
+            state.db.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            Default::default(),
+            tx,
+            tokio_util::sync::CancellationToken::new(),
+            Default::default(),
+            100,
+            200,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &categories,
+        )
+        .await
+        .unwrap();
+
+        let statistics = |q: StatisticsQuery| {
+            let state = state.clone();
+            async move {
+                let res =
+                    export_statistics(State(state), Namespace::default(), Path(id), Query(q)).await.unwrap();
+                let bytes = res.into_response().into_body().collect().await.unwrap().to_bytes();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+        let stats = statistics(StatisticsQuery { units: Some("bytes".into()), ..Default::default() }).await;
+        // Ordered by allocated size, which depends on the block size of the filesystem
+        let mut totals: Vec<(String, i64, i64)> = stats["categories"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                let name = c["category"].as_str().unwrap().to_string();
+                (name, c["count"].as_i64().unwrap(), c["logical_size"].as_i64().unwrap())
+            })
+            .collect();
+        totals.sort();
+        assert_eq!(
+            totals,
+            [
+                ("logs".to_string(), 1, 500),
+                ("other".to_string(), 2, 100),
+                ("recordings".to_string(), 1, 2000),
+                ("video".to_string(), 1, 3000),
+            ]
+        );
+        assert_eq!(stats["formatted"]["categories"]["video"]["logical_size"], "3000");
+
+        let media = root.join("media").to_string_lossy().to_string();
+        let stats = statistics(StatisticsQuery { path: Some(media), ..Default::default() }).await;
+        let mut names: Vec<&str> =
+            stats["categories"].as_array().unwrap().iter().map(|c| c["category"].as_str().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, ["recordings", "video"]);
+    }
+}
@@ -4,14 +4,24 @@
 //! metrics collection, and version information. These endpoints are commonly
 //! used by orchestration systems, monitoring tools, and load balancers.
 
-use crate::state::AppState;
+use crate::{
+    metrics::{HttpRouteLatency, MetricsSnapshot},
+    state::AppState,
+};
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
 
+/// Query parameters for the health endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct HealthQuery {
+    /// If set to `1`/`true`, a JSON body with diagnostic details is returned.
+    pub verbose: Option<String>,
+}
+
 /// A simple health check endpoint.
 ///
 /// This endpoint provides a basic liveness probe that indicates whether the
@@ -19,11 +29,52 @@ use axum::{
 /// making it suitable for frequent health checks by load balancers and
 /// orchestration systems.
 ///
+/// With `?verbose=1` it returns JSON including the location of the discovery
+/// file, so local tools can confirm which file describes this backend, and the
+/// SSE settings together with what a reverse proxy must allow for live events.
+///
+/// # Arguments
+///
+/// * `State(state)` - The application state
+/// * `Query(q)` - The health query parameters
+///
 /// # Returns
 ///
 /// * `impl IntoResponse` - HTTP 200 OK with "ok" text if the application is running
-pub async fn healthz() -> impl IntoResponse {
-    (StatusCode::OK, "ok")
+pub async fn healthz(State(state): State<AppState>, Query(q): Query<HealthQuery>) -> impl IntoResponse {
+    if !matches!(q.verbose.as_deref(), Some("1") | Some("true")) {
+        return (StatusCode::OK, "ok").into_response();
+    }
+    let discovery = state.discovery_path.as_ref().map(|p| {
+        serde_json::json!({
+            "path": p.display().to_string(),
+            "exists": p.is_file(),
+        })
+    });
+    let config = state.config.get();
+    let sse = &config.sse;
+    // The keep-alive comments bound the longest silence on an open stream
+    let idle_timeout =
+        format!("proxy read/idle timeout above {}s (sse.keep_alive_secs)", sse.keep_alive_secs);
+    let body = serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "pid": std::process::id(),
+        "discovery_file": discovery,
+        "sse": {
+            "keep_alive_secs": sse.keep_alive_secs,
+            "heartbeat_secs": sse.heartbeat_secs,
+            "padding_bytes": sse.padding_bytes,
+            "replay_grace_secs": sse.replay_grace_secs,
+            "proxy_requirements": [
+                "no response buffering for /scans/{id}/events (nginx: proxy_buffering off)",
+                "no compression or transformation of text/event-stream responses",
+                idle_timeout,
+                "no total response timeout or size cap; streams stay open for the whole scan",
+            ],
+        },
+    });
+    (StatusCode::OK, Json(body)).into_response()
 }
 
 /// A readiness probe that checks for database connectivity.
@@ -50,10 +101,26 @@ pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+/// The metrics snapshot together with the open event streams.
+#[derive(serde::Serialize)]
+struct MetricsResponse {
+    #[serde(flatten)]
+    metrics: MetricsSnapshot,
+    /// The number of open event streams.
+    sse_connections: usize,
+    /// The number of open event streams per scan ID.
+    sse_connections_per_scan: std::collections::BTreeMap<uuid::Uuid, usize>,
+    /// The request count and latency per route and status class.
+    http_requests: Vec<HttpRouteLatency>,
+}
+
 /// Returns a JSON snapshot of the application's metrics.
 ///
 /// This endpoint provides current application metrics in JSON format,
-/// including scan statistics, file processing counts, and system uptime.
+/// including scan statistics, file processing counts, the scanner gauges
+/// (running scans, active workers, queued batches, buffered records and
+/// bytes, the peak buffered bytes of a scan), the open event streams in total
+/// and per scan, the request count and latency per route, and system uptime.
 ///
 /// # Arguments
 ///
@@ -63,8 +130,13 @@ pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
 ///
 /// * `impl IntoResponse` - JSON response containing current metrics snapshot
 pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
-    let snapshot = state.metrics.get_snapshot();
-    Json(snapshot)
+    let per_scan = state.sse_connections.per_scan();
+    Json(MetricsResponse {
+        metrics: state.metrics.get_snapshot(),
+        sse_connections: per_scan.iter().map(|(_, n)| n).sum(),
+        sse_connections_per_scan: per_scan.into_iter().collect(),
+        http_requests: state.metrics.http_requests.summary(),
+    })
 }
 
 /// Returns the application's metrics in Prometheus exposition format.
@@ -90,6 +162,12 @@ pub async fn metrics_prometheus(State(state): State<AppState>) -> impl IntoRespo
 # HELP speicherwald_dirs_processed Directories processed\n# TYPE speicherwald_dirs_processed counter\nspeicherwald_dirs_processed {}\n\
 # HELP speicherwald_bytes_scanned Bytes scanned\n# TYPE speicherwald_bytes_scanned counter\nspeicherwald_bytes_scanned {}\n\
 # HELP speicherwald_warnings_count Warnings count\n# TYPE speicherwald_warnings_count counter\nspeicherwald_warnings_count {}\n\
+# HELP speicherwald_scans_running Scans currently running\n# TYPE speicherwald_scans_running gauge\nspeicherwald_scans_running {}\n\
+# HELP speicherwald_scanner_active_workers Scanner workers currently scanning a directory\n# TYPE speicherwald_scanner_active_workers gauge\nspeicherwald_scanner_active_workers {}\n\
+# HELP speicherwald_scanner_queue_depth Batches waiting for the aggregator\n# TYPE speicherwald_scanner_queue_depth gauge\nspeicherwald_scanner_queue_depth {}\n\
+# HELP speicherwald_scanner_buffered_records Records waiting to be persisted\n# TYPE speicherwald_scanner_buffered_records gauge\nspeicherwald_scanner_buffered_records {}\n\
+# HELP speicherwald_scanner_buffered_bytes Estimated bytes of the records waiting to be persisted\n# TYPE speicherwald_scanner_buffered_bytes gauge\nspeicherwald_scanner_buffered_bytes {}\n\
+# HELP speicherwald_scanner_buffer_peak_bytes Most bytes buffered by one scan since startup\n# TYPE speicherwald_scanner_buffer_peak_bytes gauge\nspeicherwald_scanner_buffer_peak_bytes {}\n\
 # HELP speicherwald_uptime_seconds Uptime seconds\n# TYPE speicherwald_uptime_seconds gauge\nspeicherwald_uptime_seconds {}\n",
         m.scans_started,
         m.scans_completed,
@@ -98,11 +176,38 @@ pub async fn metrics_prometheus(State(state): State<AppState>) -> impl IntoRespo
         m.dirs_processed,
         m.bytes_scanned,
         m.warnings_count,
+        m.scans_running,
+        m.scanner_active_workers,
+        m.scanner_queue_depth,
+        m.scanner_buffered_records,
+        m.scanner_buffered_bytes,
+        m.scanner_buffer_peak_bytes,
         m.uptime_seconds,
-    );
+    ) + &state.metrics.scan_durations.to_prometheus(
+        "speicherwald_scan_duration_seconds",
+        "Duration of ended scans, including cancelled and failed ones",
+    ) + &state.metrics.http_requests.to_prometheus(
+        "speicherwald_http_request_duration_seconds",
+        "Duration of HTTP requests until the response head, per route template and status class",
+    ) + &sse_connections_prometheus(&state.sse_connections.per_scan());
     ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
 }
 
+/// Renders the open event streams in total and per scan in the Prometheus text format.
+fn sse_connections_prometheus(per_scan: &[(uuid::Uuid, usize)]) -> String {
+    let total: usize = per_scan.iter().map(|(_, n)| n).sum();
+    let mut out = format!(
+        "# HELP speicherwald_sse_connections Open event streams\n# TYPE speicherwald_sse_connections gauge\n\
+         speicherwald_sse_connections {total}\n\
+         # HELP speicherwald_sse_scan_connections Open event streams per scan\n\
+         # TYPE speicherwald_sse_scan_connections gauge\n"
+    );
+    for (id, n) in per_scan {
+        out.push_str(&format!("speicherwald_sse_scan_connections{{scan_id=\"{id}\"}} {n}\n"));
+    }
+    out
+}
+
 /// Returns the application's version and build information.
 ///
 /// This endpoint provides detailed information about the application version,
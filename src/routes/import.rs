@@ -0,0 +1,328 @@
+//! Scan import API endpoints.
+//!
+//! Scans exported by another server with `GET /scans/{id}/export?scope=all`
+//! are uploaded as NDJSON or CSV (sizes in bytes) and become finished scans
+//! of this one. The `origin` of an upload names the server it came from and
+//! is given as `?origin=` or in the `x-speicherwald-origin` header. See
+//! [`crate::import`] for how uploads are checked and resumed.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /import/sessions?format=ndjson|csv&origin=` - Start a resumable upload
+//! - `GET /import/sessions/{id}` - Get a session and the offset its upload continues at
+//! - `PUT /import/sessions/{id}?offset=N` - Append the chunk of the export that starts at byte `N`
+//! - `POST /import/sessions/{id}/commit` - Finish the upload and get the scan
+//! - `POST /import/scan?format=ndjson|csv&origin=` - Import a whole export in one request
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    import::{self, ImportFormat, ImportSettings, ORIGIN_HEADER},
+    middleware::namespace::Namespace,
+    routes::scans::load_scan_summary,
+    state::AppState,
+    types::{ImportSession, ScanSummary},
+};
+
+/// Query parameters for starting an import.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ImportQuery {
+    /// `ndjson` (default) or `csv`.
+    pub format: Option<String>,
+    /// The server the export comes from; the `x-speicherwald-origin` header is used if absent.
+    pub origin: Option<String>,
+}
+
+/// Query parameters for appending a chunk.
+#[derive(Debug, serde::Deserialize)]
+pub struct AppendQuery {
+    /// The position of the chunk in the export, which must be the session's offset.
+    pub offset: u64,
+}
+
+/// The format and origin of an import request.
+fn import_params(q: &ImportQuery, headers: &HeaderMap) -> AppResult<(ImportFormat, String)> {
+    let format = ImportFormat::parse(q.format.as_deref())?;
+    let header = headers.get(ORIGIN_HEADER).and_then(|v| v.to_str().ok());
+    let origin = q.origin.as_deref().or(header).ok_or_else(|| {
+        AppError::BadRequest(format!("missing origin; pass ?origin= or the {} header", ORIGIN_HEADER))
+    })?;
+    Ok((format, import::parse_origin(origin)?))
+}
+
+/// Starts a resumable import.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace the scan is imported into.
+/// * `q` - The format and origin of the upload.
+/// * `headers` - The request headers, which may carry the origin.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A `201 Created` response containing the new `ImportSession`.
+pub async fn create_session(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Query(q): Query<ImportQuery>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let (format, origin) = import_params(&q, &headers)?;
+    let session = import::create(&state.db, &ns, format, &origin).await?;
+    Ok((StatusCode::CREATED, Json(session.to_dto())).into_response())
+}
+
+/// Gets an import session.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the session.
+///
+/// # Returns
+///
+/// * `AppResult<Json<ImportSession>>` - The session.
+pub async fn get_session(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ImportSession>> {
+    Ok(Json(import::load(&state.db, &ns, id).await?.to_dto()))
+}
+
+/// Appends a chunk of the export to an import session.
+///
+/// A chunk may end anywhere, also inside a record. When the upload breaks
+/// off or a record is invalid, what was accepted before stays accepted and
+/// `GET /import/sessions/{id}` tells the offset to continue at.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the session.
+/// * `q` - The offset of the chunk.
+/// * `body` - The chunk.
+///
+/// # Returns
+///
+/// * `AppResult<Json<ImportSession>>` - The updated session. `Conflict` if the
+///   offset is not the session's or another chunk is being appended.
+pub async fn append_chunk(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<AppendQuery>,
+    body: Body,
+) -> AppResult<Json<ImportSession>> {
+    let _guard = state.import_uploads.begin(id)?;
+    let mut session = import::load(&state.db, &ns, id).await?;
+    if q.offset != session.offset() {
+        return Err(AppError::Conflict(format!(
+            "the session has accepted {} bytes; continue at offset={}",
+            session.offset(),
+            session.offset()
+        )));
+    }
+    let settings = ImportSettings::from_config(&state.config.get());
+    import::append(&state.db, &mut session, body, &settings).await?;
+    Ok(Json(import::load(&state.db, &ns, id).await?.to_dto()))
+}
+
+/// Finishes an import session.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the session.
+///
+/// # Returns
+///
+/// * `AppResult<Json<ScanSummary>>` - The imported scan. `InvalidInput` if the
+///   upload is incomplete; the session then stays open.
+pub async fn commit_session(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ScanSummary>> {
+    let _guard = state.import_uploads.begin(id)?;
+    let session = import::load(&state.db, &ns, id).await?;
+    let scan_id = session.scan_id;
+    let settings = ImportSettings::from_config(&state.config.get());
+    import::commit(&state.db, session, &settings).await?;
+    Ok(Json(load_scan_summary(&state.db, &ns, scan_id).await?))
+}
+
+/// Imports a whole export in one request.
+///
+/// Nothing is kept if the import fails.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace the scan is imported into.
+/// * `q` - The format and origin of the upload.
+/// * `headers` - The request headers, which may carry the origin.
+/// * `body` - The export.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A `201 Created` response containing the `ScanSummary` of the imported scan.
+pub async fn import_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Query(q): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> AppResult<Response> {
+    let (format, origin) = import_params(&q, &headers)?;
+    let settings = ImportSettings::from_config(&state.config.get());
+    let mut session = import::create(&state.db, &ns, format, &origin).await?;
+    let scan_id = session.scan_id;
+    let outcome = match import::append(&state.db, &mut session, body, &settings).await {
+        Ok(()) => import::commit(&state.db, session, &settings).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = outcome {
+        // Deletes the session and the rows with the scan
+        if let Err(err) =
+            sqlx::query("DELETE FROM scans WHERE id=?1").bind(scan_id.to_string()).execute(&state.db).await
+        {
+            tracing::warn!("Failed to delete the failed import {}: {}", scan_id, err);
+        }
+        return Err(e);
+    }
+    let summary = load_scan_summary(&state.db, &ns, scan_id).await?;
+    Ok((StatusCode::CREATED, Json(summary)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+    use crate::{config::AppConfig, test_support::test_db};
+
+    async fn state() -> (tempfile::TempDir, AppState) {
+        let (dir, pool) = test_db().await;
+        (dir, AppState::new(pool, AppConfig::default()))
+    }
+
+    async fn start(state: &AppState, format: &str) -> ImportSession {
+        let q = ImportQuery { format: Some(format.into()), origin: None };
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN_HEADER, "branch-01".parse().unwrap());
+        let res =
+            create_session(State(state.clone()), Namespace::default(), Query(q), headers).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        serde_json::from_slice(&res.into_body().collect().await.unwrap().to_bytes()).unwrap()
+    }
+
+    async fn append(state: &AppState, id: Uuid, offset: u64, chunk: &str) -> AppResult<ImportSession> {
+        let body = Body::from(chunk.to_string());
+        let q = Query(AppendQuery { offset });
+        Ok(append_chunk(State(state.clone()), Namespace::default(), Path(id), q, body).await?.0)
+    }
+
+    async fn commit(state: &AppState, id: Uuid) -> AppResult<ScanSummary> {
+        Ok(commit_session(State(state.clone()), Namespace::default(), Path(id)).await?.0)
+    }
+
+    #[tokio::test]
+    async fn ndjson_uploads_resume_at_the_accepted_offset_and_commit_into_a_scan() {
+        let (_dir, state) = state().await;
+        let session = start(&state, "ndjson").await;
+        assert_eq!((session.offset, session.origin.as_str()), (0, "branch-01"));
+
+        let export = concat!(
+            r#"{"type":"node","path":"/srv","parent_path":null,"depth":0,"is_dir":true,"#,
+            r#""logical_size":30,"allocated_size":8192,"file_count":2,"dir_count":1,"mtime":1}"#,
+            "\n",
+            r#"{"type":"node","path":"/srv/a","parent_path":"/srv","depth":1,"is_dir":true,"#,
+            r#""logical_size":10,"allocated_size":4096,"file_count":1,"dir_count":0,"mtime":null}"#,
+            "\n",
+            r#"{"type":"file","path":"/srv/x.txt","parent_path":"/srv","logical_size":20,"#,
+            r#""allocated_size":4096,"mtime":2}"#,
+            "\n",
+            r#"{"type":"file","path":"/srv/a/y.bin","parent_path":"/srv/a","logical_size":10,"#,
+            r#""allocated_size":4096,"mtime":3}"#,
+        );
+        // The first chunk ends inside the second record, which is kept for the next chunk
+        let split = export.find("/srv/a\"").unwrap();
+        let session = append(&state, session.id, 0, &export[..split]).await.unwrap();
+        assert_eq!((session.offset, session.records), (split as u64, 1));
+
+        // A chunk at another offset is refused
+        let err = append(&state, session.id, 3, &export[split..]).await.err().unwrap();
+        assert!(
+            matches!(err, AppError::Conflict(ref m) if m.contains(&format!("offset={}", split))),
+            "{:?}",
+            err
+        );
+
+        // An invalid record is not accepted; the offset stays at its start
+        let rest = &export[split..];
+        let third = split + rest.find('\n').unwrap() + 1;
+        let bad = format!(
+            "{}{{\"type\":\"file\",\"path\":\"/srv/z\",\"logical_size\":\"big\"}}\n",
+            &rest[..third - split]
+        );
+        let err = append(&state, session.id, split as u64, &bad).await.err().unwrap();
+        assert!(matches!(err, AppError::InvalidInput(ref m) if m.contains("record 3")), "{:?}", err);
+        let session =
+            get_session(State(state.clone()), Namespace::default(), Path(session.id)).await.unwrap().0;
+        assert_eq!((session.offset, session.records), (third as u64, 2));
+
+        // The rest, without a final line break, completes the upload
+        let session = append(&state, session.id, third as u64, &export[third..]).await.unwrap();
+        assert_eq!(session.offset, export.len() as u64);
+        let scan = commit(&state, session.id).await.unwrap();
+        assert_eq!(scan.status, "done");
+        assert_eq!(scan.origin.as_deref(), Some("branch-01"));
+        assert_eq!(scan.root_paths, ["/srv"]);
+        assert_eq!((scan.total_logical_size, scan.file_count, scan.dir_count), (30, 2, 2));
+        let err =
+            get_session(State(state.clone()), Namespace::default(), Path(session.id)).await.err().unwrap();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn csv_imports_need_every_parent_and_keep_quoted_paths() {
+        let (_dir, state) = state().await;
+        let header = "Type;Path;Parent Path;Depth;Logical Size;Allocated Size;File Count;Dir Count\r\n";
+        let session = start(&state, "csv").await;
+        let orphan = format!("{}File;\"C:\\a;b\";C:\\;;5;4096;;\r\n", header);
+        let session = append(&state, session.id, 0, &orphan).await.unwrap();
+        let err = commit(&state, session.id).await.err().unwrap();
+        assert!(matches!(err, AppError::InvalidInput(ref m) if m.contains("C:\\a;b")), "{:?}", err);
+
+        // The session stays open, so the missing directory can follow
+        let root = format!("\r\n{}Dir;C:\\;;0;5;4096;1;0\r\n", header);
+        let session = append(&state, session.id, session.offset, &root).await.unwrap();
+        let scan = commit(&state, session.id).await.unwrap();
+        assert_eq!(scan.root_paths, ["C:\\"]);
+        assert_eq!((scan.total_logical_size, scan.file_count), (5, 1));
+
+        // Sizes in other units cannot be imported
+        let q = ImportQuery { format: Some("csv".into()), origin: Some("branch-02".into()) };
+        let body = Body::from("Type,Path,Logical Size (MiB)\n");
+        let err = import_scan(State(state.clone()), Namespace::default(), Query(q), HeaderMap::new(), body)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, AppError::InvalidInput(ref m) if m.contains("units=bytes")), "{:?}", err);
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&state.db).await.unwrap();
+        assert_eq!(left, 1);
+    }
+}
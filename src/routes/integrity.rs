@@ -0,0 +1,223 @@
+//! Content integrity API endpoint.
+//!
+//! Scans started with `hash_min_size` store a BLAKE3 hash of every file at or
+//! above the threshold. Comparing two scans of the same root finds files whose
+//! size did not change but whose content did, as bit-rot leaves them.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/integrity?other={other_id}` - Files of the same size whose hashes differ
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    state::AppState,
+    types::{ContentIntegrityResponse, IntegrityMismatch},
+};
+
+const INTEGRITY_LIMIT_MAX: i64 = 5_000;
+
+/// Query parameters for the integrity endpoint.
+#[derive(Debug, serde::Deserialize)]
+pub struct IntegrityQuery {
+    /// The scan to compare with.
+    pub other: Uuid,
+    /// The maximum number of files to return, 1000 by default.
+    pub limit: Option<i64>,
+}
+
+/// Lists the files whose size is the same in two scans but whose content hash differs.
+///
+/// Files are matched by path; only files hashed in both scans are compared.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request; both scans must be visible from it.
+/// * `id` - The ID of the scan.
+/// * `q` - The query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `ContentIntegrityResponse`.
+///   `BadRequest` if the scans share no root.
+pub async fn integrity_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<IntegrityQuery>,
+) -> AppResult<impl IntoResponse> {
+    let pool = state.read_pool();
+    ns.ensure_scan(pool, id).await?;
+    ns.ensure_scan(pool, q.other).await?;
+    let _leases = (state.scan_leases.acquire(id), state.scan_leases.acquire(q.other));
+    let (roots, other_roots) = (root_paths(pool, id).await?, root_paths(pool, q.other).await?);
+    if !roots.iter().any(|root| other_roots.contains(root)) {
+        return Err(AppError::BadRequest("the scans share no root path".into()));
+    }
+
+    let compared: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM files a JOIN files b ON b.scan_id=?2 AND b.path=a.path \
+         WHERE a.scan_id=?1 AND a.hash IS NOT NULL AND b.hash IS NOT NULL AND a.logical_size=b.logical_size",
+    )
+    .bind(id.to_string())
+    .bind(q.other.to_string())
+    .fetch_one(pool)
+    .await?;
+    let rows = sqlx::query(
+        "SELECT a.path, a.logical_size, a.mtime, b.mtime AS other_mtime, a.hash, b.hash AS other_hash \
+         FROM files a JOIN files b ON b.scan_id=?2 AND b.path=a.path \
+         WHERE a.scan_id=?1 AND a.hash IS NOT NULL AND b.hash IS NOT NULL \
+           AND a.logical_size=b.logical_size AND a.hash<>b.hash \
+         ORDER BY a.logical_size DESC, a.path LIMIT ?3",
+    )
+    .bind(id.to_string())
+    .bind(q.other.to_string())
+    .bind(q.limit.unwrap_or(1000).clamp(1, INTEGRITY_LIMIT_MAX))
+    .fetch_all(pool)
+    .await?;
+    let mismatches = rows
+        .iter()
+        .map(|r| IntegrityMismatch {
+            path: r.get("path"),
+            logical_size: r.get("logical_size"),
+            mtime: r.get("mtime"),
+            other_mtime: r.get("other_mtime"),
+            hash: r.get("hash"),
+            other_hash: r.get("other_hash"),
+        })
+        .collect();
+    Ok(Json(ContentIntegrityResponse { scan_id: id, other: q.other, compared: compared as u64, mismatches }))
+}
+
+async fn root_paths(pool: &sqlx::SqlitePool, id: Uuid) -> AppResult<Vec<String>> {
+    let json: String = sqlx::query_scalar("SELECT root_paths FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scan not found".into()))?;
+    Ok(serde_json::from_str(&json).unwrap_or_default())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use http_body_util::BodyExt;
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan_with_options, test_db_in};
+    use crate::types::{ScanEvent, ScanOptions};
+
+    async fn scan(pool: &sqlx::SqlitePool, root: &str, options: &ScanOptions) -> (Uuid, Vec<String>) {
+        let id = insert_scan_with_options(pool, "done", &[root], "default", options).await;
+        let (tx, mut rx) = broadcast::channel(1024);
+        crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![root.to_string()],
+            options.clone(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            100,
+            200,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        let mut warnings = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let ScanEvent::Warning { code, .. } = event {
+                warnings.push(code);
+            }
+        }
+        (id, warnings)
+    }
+
+    async fn hashes(pool: &sqlx::SqlitePool, id: Uuid) -> Vec<(String, Option<String>)> {
+        sqlx::query_as("SELECT path, hash FROM files WHERE scan_id=?1 ORDER BY path")
+            .bind(id.to_string())
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn files_with_the_same_size_and_another_hash_are_listed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("archive.bin"), vec![1u8; 4096]).unwrap();
+        std::fs::write(root.join("photo.raw"), vec![2u8; 2048]).unwrap();
+        std::fs::write(root.join("small.txt"), b"tiny").unwrap();
+        let pool = test_db_in(dir.path()).await;
+        let root_str = root.to_string_lossy().to_string();
+        let options = ScanOptions { hash_min_size: Some(1024), ..Default::default() };
+
+        let (before, warnings) = scan(&pool, &root_str, &options).await;
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let stored = hashes(&pool, before).await;
+        assert_eq!(stored[0].1.as_deref(), Some(blake3::hash(&[1u8; 4096]).to_hex().as_str()));
+        assert!(stored[1].1.is_some());
+        assert_eq!(stored[2].1, None);
+
+        // Bit-rot: same size, other content; a grown file is not compared
+        let mut rotten = vec![1u8; 4096];
+        rotten[100] = 0;
+        std::fs::write(root.join("archive.bin"), rotten).unwrap();
+        std::fs::write(root.join("photo.raw"), vec![3u8; 3000]).unwrap();
+        let (after, _) = scan(&pool, &root_str, &options).await;
+
+        let state = AppState::new(pool.clone(), AppConfig::default());
+        let q = IntegrityQuery { other: before, limit: None };
+        let res = integrity_scan(State(state.clone()), Namespace::default(), Path(after), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        let report: ContentIntegrityResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(report.compared, 1);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].path, root.join("archive.bin").to_string_lossy());
+        assert_ne!(report.mismatches[0].hash, report.mismatches[0].other_hash);
+
+        let (elsewhere, _) = scan(&pool, &dir.path().to_string_lossy(), &ScanOptions::default()).await;
+        let q = IntegrityQuery { other: elsewhere, limit: None };
+        let err =
+            integrity_scan(State(state), Namespace::default(), Path(after), Query(q)).await.err().unwrap();
+        assert!(matches!(err, AppError::BadRequest(_)), "{:?}", err);
+
+        // A file that cannot be read is a warning, not a failed scan
+        let locked = root.join("archive.bin");
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        let unreadable = std::fs::File::open(&locked).is_err();
+        let (id, warnings) = scan(&pool, &root_str, &options).await;
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o644)).unwrap();
+        if unreadable {
+            assert_eq!(warnings, ["hash_failed"]);
+            let stored = hashes(&pool, id).await;
+            assert_eq!(stored[0].1, None);
+            assert!(stored[1].1.is_some());
+        }
+    }
+}
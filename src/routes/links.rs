@@ -0,0 +1,235 @@
+//! Scan link API endpoints.
+//!
+//! Scans record the symbolic links, junctions and other reparse points they
+//! meet, including where each one points and whether it was followed. This
+//! makes it possible to audit links that leave the scanned root.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/links?kind=&outside_root=&followed=&limit=&offset=` - Recorded links of a scan
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    state::AppState,
+    types::{ScanLinkDto, ScanLinksResponse},
+};
+
+const LINKS_LIMIT_DEFAULT: i64 = 100;
+const LINKS_LIMIT_MAX: i64 = 1_000;
+
+/// Query parameters for the links endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct LinksQuery {
+    /// Only return links of this kind: `symlink`, `junction` or `reparse_point`.
+    pub kind: Option<String>,
+    /// Only return links whose target is (`true`) or is not (`false`) outside the root.
+    pub outside_root: Option<bool>,
+    /// Only return links that were (`true`) or were not (`false`) followed.
+    pub followed: Option<bool>,
+    /// The maximum number of links to return.
+    pub limit: Option<i64>,
+    /// The number of links to skip.
+    pub offset: Option<i64>,
+}
+
+/// Lists the links recorded by a scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The filter and pagination parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `ScanLinksResponse`,
+///   or `BadRequest` for an unknown kind.
+pub async fn get_links(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<LinksQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let kind = q.kind.as_deref().map(str::trim).filter(|k| !k.is_empty());
+    if let Some(k) = kind {
+        if !matches!(k, "symlink" | "junction" | "reparse_point") {
+            return Err(AppError::BadRequest("kind must be 'symlink', 'junction' or 'reparse_point'".into()));
+        }
+    }
+    let limit = q.limit.unwrap_or(LINKS_LIMIT_DEFAULT).clamp(1, LINKS_LIMIT_MAX);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let push_filter = |qb: &mut QueryBuilder<'_, Sqlite>| {
+        qb.push(" WHERE scan_id = ").push_bind(id.to_string());
+        if let Some(k) = kind {
+            qb.push(" AND kind = ").push_bind(k.to_string());
+        }
+        if let Some(outside) = q.outside_root {
+            qb.push(" AND outside_root = ").push_bind(outside);
+        }
+        if let Some(followed) = q.followed {
+            qb.push(" AND followed = ").push_bind(followed);
+        }
+    };
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM scan_links");
+    push_filter(&mut qb);
+    let total: i64 = qb.build_query_scalar().fetch_one(state.read_pool()).await?;
+
+    let mut qb =
+        QueryBuilder::<Sqlite>::new("SELECT path, kind, target, followed, outside_root FROM scan_links");
+    push_filter(&mut qb);
+    qb.push(" ORDER BY path LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    let items = rows
+        .iter()
+        .map(|r| ScanLinkDto {
+            path: r.get("path"),
+            kind: r.get("kind"),
+            target: r.get("target"),
+            followed: r.get("followed"),
+            outside_root: r.get("outside_root"),
+        })
+        .collect();
+
+    Ok(Json(ScanLinksResponse { scan_id: id, total, items }))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        config::AppConfig,
+        test_support::{insert_scan, test_db_in},
+        types::{ScanEvent, ScanOptions},
+    };
+    use http_body_util::BodyExt;
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    async fn links(state: &AppState, id: Uuid, q: LinksQuery) -> ScanLinksResponse {
+        let res = get_links(State(state.clone()), Namespace::default(), Path(id), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    async fn scan(
+        pool: &sqlx::SqlitePool,
+        root: &std::path::Path,
+        follow_symlinks: bool,
+    ) -> (Uuid, Vec<ScanEvent>) {
+        let id = insert_scan(pool, "running", &[], "default").await;
+        let (tx, mut rx) = broadcast::channel(1024);
+        let options = ScanOptions { follow_symlinks, measure_allocated: false, ..Default::default() };
+        crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            options,
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            100,
+            100,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &Default::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        let mut events = Vec::new();
+        while let Ok(ev) = rx.try_recv() {
+            events.push(ev);
+        }
+        (id, events)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn links_are_recorded_and_cycles_are_broken() {
+        use std::os::unix::fs::symlink;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        let outside = dir.path().join("elsewhere");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("sub/a.bin"), vec![0u8; 10]).unwrap();
+        std::fs::write(outside.join("b.bin"), vec![0u8; 20]).unwrap();
+        // A loop back to the root, a link leaving the root and a dangling link
+        symlink(&root, root.join("sub/loop")).unwrap();
+        symlink(&outside, root.join("out")).unwrap();
+        symlink(dir.path().join("missing"), root.join("dangling")).unwrap();
+
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool.clone(), AppConfig::default());
+
+        // Not following: links are recorded, nothing behind them is counted
+        let (id, _) = scan(&pool, &root, false).await;
+        let all = links(&state, id, LinksQuery::default()).await;
+        assert_eq!(all.total, 3);
+        assert!(all.items.iter().all(|l| !l.followed && l.kind == "symlink"));
+        let out = all.items.iter().find(|l| l.path.ends_with("out")).unwrap();
+        assert_eq!(out.target.as_deref(), Some(outside.to_string_lossy().as_ref()));
+        assert_eq!(out.outside_root, Some(true));
+        let looped = all.items.iter().find(|l| l.path.ends_with("loop")).unwrap();
+        assert_eq!(looped.outside_root, Some(false));
+        let dangling = all.items.iter().find(|l| l.path.ends_with("dangling")).unwrap();
+        assert_eq!(dangling.outside_root, None);
+        let leaving = links(&state, id, LinksQuery { outside_root: Some(true), ..Default::default() }).await;
+        assert_eq!(leaving.items.len(), 1);
+        let files: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(files, 1);
+
+        // Following: the loop ends with a warning, the outside target is scanned once
+        let (id, events) = scan(&pool, &root, true).await;
+        assert!(events.iter().any(|e| matches!(e, ScanEvent::Warning { code, .. } if code == "link_cycle")));
+        let followed = links(&state, id, LinksQuery { followed: Some(true), ..Default::default() }).await;
+        assert_eq!(followed.items.len(), 1);
+        assert!(followed.items[0].path.ends_with("out"));
+        let files: Vec<String> = sqlx::query_scalar("SELECT path FROM files WHERE scan_id=?1 ORDER BY path")
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 2, "{:?}", files);
+        let stored: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM warnings WHERE scan_id=?1 AND code='link_cycle'")
+                .bind(id.to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stored, 1);
+
+        let bad = get_links(
+            State(state.clone()),
+            Namespace::default(),
+            Path(id),
+            Query(LinksQuery { kind: Some("hardlink".into()), ..Default::default() }),
+        )
+        .await;
+        assert!(matches!(bad, Err(AppError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,339 @@
+//! Database maintenance API endpoints.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /admin/db/vacuum?mode=full|incremental` - Return the free pages of the database to the OS
+//! - `POST /admin/db/analyze` - Refresh the statistics the query planner uses
+//! - `GET /admin/db/integrity?limit=` - Check the database with `PRAGMA integrity_check`
+//!
+//! Like backups these act on the scans of every namespace, so they refuse
+//! requests authenticated with a token bound to any namespace but the admin one. A full `VACUUM` holds
+//! an exclusive lock for its whole run, so all of them refuse to run while a
+//! scan job is running.
+
+use axum::{
+    extract::{Query, Request, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sqlx::SqlitePool;
+
+use crate::{
+    backup,
+    error::{AppError, AppResult},
+    middleware::ip::MaybeRemoteAddr,
+    routes::backup::{check_rate_limit, require_admin},
+    state::AppState,
+    types::{DbMaintenanceResponse, IntegrityResponse},
+};
+
+const INTEGRITY_LIMIT_DEFAULT: i64 = 100;
+const INTEGRITY_LIMIT_MAX: i64 = 10_000;
+
+/// How `POST /admin/db/vacuum` frees pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VacuumMode {
+    /// `VACUUM`: rebuilds the whole file; slow, but also defragments.
+    #[default]
+    Full,
+    /// `PRAGMA incremental_vacuum`: only truncates free pages; needs incremental auto-vacuum.
+    Incremental,
+}
+
+/// Query parameters for the vacuum endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct VacuumQuery {
+    /// `full` (default) or `incremental`.
+    pub mode: Option<VacuumMode>,
+}
+
+/// Query parameters for the integrity endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct IntegrityQuery {
+    /// The maximum number of problems to report.
+    pub limit: Option<i64>,
+}
+
+/// The size of the database in bytes, as SQLite allocates it (pages times page size).
+async fn database_size(pool: &SqlitePool) -> AppResult<u64> {
+    let pages: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(pool).await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(pool).await?;
+    Ok((pages.max(0) as u64).saturating_mul(page_size.max(0) as u64))
+}
+
+/// Runs `operation` and reports its duration and the size of the database before and after.
+async fn measured<F>(state: &AppState, operation: &str, run: F) -> AppResult<DbMaintenanceResponse>
+where
+    F: std::future::Future<Output = AppResult<()>>,
+{
+    backup::ensure_idle(state, false).await?;
+    let started = std::time::Instant::now();
+    let size_before_bytes = database_size(&state.db).await?;
+    run.await?;
+    let size_after_bytes = database_size(&state.db).await?;
+    let duration_ms = started.elapsed().as_millis();
+    tracing::info!(
+        target: "audit",
+        "Database {} took {} ms ({} -> {} bytes)",
+        operation,
+        duration_ms,
+        size_before_bytes,
+        size_after_bytes
+    );
+    Ok(DbMaintenanceResponse {
+        operation: operation.to_string(),
+        size_before_bytes,
+        size_after_bytes,
+        reclaimed_bytes: size_before_bytes.saturating_sub(size_after_bytes),
+        duration_ms,
+    })
+}
+
+/// Frees the unused pages of the database.
+///
+/// A full `VACUUM` also switches databases created before incremental
+/// auto-vacuum was enabled over to it, so later incremental runs (and the
+/// retention task) can free pages without rebuilding the file.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `mode` - Whether to rebuild the file or only truncate free pages.
+///
+/// # Returns
+///
+/// * `AppResult<DbMaintenanceResponse>` - The sizes before and after, `Conflict`
+///   while a scan job is running or, for `incremental`, if the database does
+///   not use incremental auto-vacuum yet.
+pub async fn vacuum(state: &AppState, mode: VacuumMode) -> AppResult<DbMaintenanceResponse> {
+    match mode {
+        VacuumMode::Full => {
+            measured(state, "vacuum", async {
+                // The auto-vacuum mode only changes with a VACUUM on the same connection
+                let mut conn = state.db.acquire().await?;
+                sqlx::query("PRAGMA auto_vacuum=INCREMENTAL").execute(&mut *conn).await?;
+                sqlx::query("VACUUM").execute(&mut *conn).await?;
+                Ok(())
+            })
+            .await
+        }
+        VacuumMode::Incremental => {
+            measured(state, "incremental_vacuum", async {
+                // 2 = INCREMENTAL
+                let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum").fetch_one(&state.db).await?;
+                if auto_vacuum != 2 {
+                    return Err(AppError::Conflict(
+                        "the database does not use incremental auto-vacuum; run mode=full once".into(),
+                    ));
+                }
+                sqlx::query("PRAGMA incremental_vacuum").execute(&state.db).await?;
+                Ok(())
+            })
+            .await
+        }
+    }
+}
+
+/// Refreshes the statistics the query planner uses.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `AppResult<DbMaintenanceResponse>` - The duration, or `Conflict` while a scan job is running.
+pub async fn analyze(state: &AppState) -> AppResult<DbMaintenanceResponse> {
+    measured(state, "analyze", async {
+        sqlx::query("ANALYZE").execute(&state.db).await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Checks the database with `PRAGMA integrity_check`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `limit` - The maximum number of problems to report.
+///
+/// # Returns
+///
+/// * `AppResult<IntegrityResponse>` - The problems found, or `Conflict` while a scan job is running.
+pub async fn integrity_check(state: &AppState, limit: Option<i64>) -> AppResult<IntegrityResponse> {
+    backup::ensure_idle(state, false).await?;
+    let limit = limit.unwrap_or(INTEGRITY_LIMIT_DEFAULT).clamp(1, INTEGRITY_LIMIT_MAX);
+    let started = std::time::Instant::now();
+    let rows: Vec<String> =
+        sqlx::query_scalar(&format!("PRAGMA integrity_check({})", limit)).fetch_all(state.read_pool()).await?;
+    // A healthy database answers with a single row "ok"
+    let problems: Vec<String> = rows.into_iter().filter(|r| r != "ok").collect();
+    Ok(IntegrityResponse {
+        ok: problems.is_empty(),
+        truncated: problems.len() as i64 >= limit,
+        problems,
+        duration_ms: started.elapsed().as_millis(),
+    })
+}
+
+/// Frees the unused pages of the database.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `q` - The vacuum mode.
+/// * `req` - The request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `DbMaintenanceResponse`.
+pub async fn vacuum_db(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    Query(q): Query<VacuumQuery>,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, "/admin/db/vacuum", &maybe_remote, req.headers()).await {
+        return Ok(limited);
+    }
+    Ok(Json(vacuum(&state, q.mode.unwrap_or_default()).await?).into_response())
+}
+
+/// Refreshes the statistics the query planner uses.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `req` - The request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `DbMaintenanceResponse`.
+pub async fn analyze_db(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, "/admin/db/analyze", &maybe_remote, req.headers()).await {
+        return Ok(limited);
+    }
+    Ok(Json(analyze(&state).await?).into_response())
+}
+
+/// Checks the database for corruption.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `q` - The maximum number of problems to report.
+/// * `req` - The request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing an `IntegrityResponse`.
+pub async fn check_integrity(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    Query(q): Query<IntegrityQuery>,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, "/admin/db/integrity", &maybe_remote, req.headers()).await
+    {
+        return Ok(limited);
+    }
+    Ok(Json(integrity_check(&state, q.limit).await?).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::middleware::namespace::Namespace;
+    use crate::test_support::{insert_scan, test_db_in};
+    use axum::{body::Body, http::StatusCode};
+    use uuid::Uuid;
+
+    async fn state_with_purged_scan(dir: &tempfile::TempDir) -> AppState {
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "done", &["/d"], "default").await;
+        sqlx::query(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i+1 FROM n WHERE i < 5000)
+             INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size)
+             SELECT ?1, '/d/' || printf('%0200d', i), '/d', i, i FROM n",
+        )
+        .bind(id.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("DELETE FROM scans WHERE id=?1").bind(id.to_string()).execute(&pool).await.unwrap();
+        AppState::new(pool, AppConfig::default())
+    }
+
+    #[tokio::test]
+    async fn vacuum_reclaims_the_pages_of_purged_scans() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_with_purged_scan(&dir).await;
+        let full = vacuum(&state, VacuumMode::Full).await.unwrap();
+        assert_eq!(full.operation, "vacuum");
+        assert!(full.reclaimed_bytes > 500_000, "{:?}", full);
+        assert_eq!(full.size_before_bytes - full.size_after_bytes, full.reclaimed_bytes);
+
+        // Nothing left to free afterwards, but the mode is supported
+        let incremental = vacuum(&state, VacuumMode::Incremental).await.unwrap();
+        assert_eq!(incremental.reclaimed_bytes, 0);
+
+        let analyzed = analyze(&state).await.unwrap();
+        assert_eq!(analyzed.operation, "analyze");
+        let stats: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name='sqlite_stat1'")
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+        assert_eq!(stats, 1);
+
+        let integrity = integrity_check(&state, None).await.unwrap();
+        assert!(integrity.ok && integrity.problems.is_empty() && !integrity.truncated);
+    }
+
+    #[tokio::test]
+    async fn maintenance_waits_for_running_scans() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_with_purged_scan(&dir).await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(1);
+        let job = crate::state::JobHandle::new(tokio_util::sync::CancellationToken::new(), tx);
+        state.jobs.write().await.insert(Uuid::new_v4(), job);
+        assert!(matches!(vacuum(&state, VacuumMode::Full).await, Err(AppError::Conflict(_))));
+        assert!(matches!(analyze(&state).await, Err(AppError::Conflict(_))));
+        assert!(matches!(integrity_check(&state, Some(5)).await, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn maintenance_is_forbidden_for_namespace_bound_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_with_purged_scan(&dir).await;
+        let req = |token_namespace: &str| {
+            let mut req = Request::builder().method("POST").body(Body::empty()).unwrap();
+            req.extensions_mut().insert(Namespace::parse(token_namespace).unwrap());
+            req
+        };
+        let (remote, st) = (|| MaybeRemoteAddr(None), || State(state.clone()));
+
+        let vacuumed = vacuum_db(st(), remote(), Query(VacuumQuery::default()), req("hr")).await;
+        assert_eq!(vacuumed.unwrap_err().into_response().status(), StatusCode::FORBIDDEN);
+        let analyzed = analyze_db(st(), remote(), req("hr")).await;
+        assert_eq!(analyzed.unwrap_err().into_response().status(), StatusCode::FORBIDDEN);
+        let checked = check_integrity(st(), remote(), Query(IntegrityQuery::default()), req("hr")).await;
+        assert_eq!(checked.unwrap_err().into_response().status(), StatusCode::FORBIDDEN);
+
+        let checked = check_integrity(st(), remote(), Query(IntegrityQuery::default()), req("admin")).await;
+        assert_eq!(checked.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,213 @@
+//! Scan manifest API endpoint.
+//!
+//! External tools such as inventory jobs usually need the summary of a scan,
+//! its options, the drives it covers and its largest directories. The manifest
+//! combines them into one document with a `schema_version`, so consumers do
+//! not have to stitch several endpoints together and can detect format changes.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/manifest` - The scan summary, options, roots with drives and top directories
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::AppResult,
+    middleware::namespace::Namespace,
+    routes::scans::{load_scan_summary, top_dirs, TopFilter},
+    state::AppState,
+    types::{DriveInfo, ManifestRoot, ScanManifest, MANIFEST_SCHEMA_VERSION},
+};
+
+/// The number of directories listed in `top_dirs`.
+const MANIFEST_TOP_DIRS: i64 = 20;
+
+/// Gets the manifest of a scan.
+///
+/// The drive of each root is read when the request is made, so free space
+/// reflects the current state rather than the one at scan time.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `ScanManifest`.
+pub async fn get_manifest(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let pool = state.read_pool();
+    let scan = load_scan_summary(pool, &ns, id).await?;
+
+    let drives = match crate::routes::drives::enumerate_drives(false).await {
+        Ok(drives) => drives,
+        Err(e) => {
+            tracing::warn!("Drive enumeration for the manifest of scan {} failed: {}", id, e);
+            Vec::new()
+        }
+    };
+    let roots = scan
+        .root_paths
+        .iter()
+        .cloned()
+        .map(|path| ManifestRoot {
+            totals: scan.roots.iter().find(|r| r.root == path).cloned(),
+            drive: drive_for_root(&drives, &path).cloned(),
+            path,
+        })
+        .collect();
+    let top_dirs = top_dirs(pool, id, MANIFEST_TOP_DIRS, &TopFilter::default()).await?;
+
+    Ok(Json(ScanManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        options: scan.options.clone(),
+        scan,
+        roots,
+        top_dirs,
+    }))
+}
+
+/// Returns the drive a root path lies on, the most specific one if several match.
+///
+/// Paths are compared case-insensitively and only at separator boundaries, so
+/// `\\server\share` does not claim `\\server\shared`.
+fn drive_for_root<'a>(drives: &'a [DriveInfo], root: &str) -> Option<&'a DriveInfo> {
+    let root = root.to_ascii_lowercase();
+    drives
+        .iter()
+        .filter(|d| {
+            let prefix = d.path.trim_end_matches(['\\', '/']).to_ascii_lowercase();
+            !prefix.is_empty()
+                && root.starts_with(&prefix)
+                && matches!(root[prefix.len()..].chars().next(), None | Some('\\' | '/'))
+        })
+        .max_by_key(|d| d.path.trim_end_matches(['\\', '/']).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::types::ScanOptions;
+    use http_body_util::BodyExt;
+    use crate::test_support::{insert_scan_with_options, test_db_in};
+
+    fn drive(path: &str) -> DriveInfo {
+        DriveInfo {
+            path: path.into(),
+            drive_type: "fixed".into(),
+            total_bytes: 1000,
+            free_bytes: 500,
+            label: None,
+            filesystem: None,
+            serial_number: None,
+            bitlocker: None,
+        }
+    }
+
+    #[test]
+    fn roots_are_matched_to_the_most_specific_drive() {
+        let drives = [drive("C:\\"), drive("D:\\"), drive("C:\\Mount\\Data\\"), drive("\\\\srv\\share")];
+        let path_of = |root: &str| drive_for_root(&drives, root).map(|d| d.path.as_str());
+        assert_eq!(path_of("c:\\Users"), Some("C:\\"));
+        assert_eq!(path_of("C:\\"), Some("C:\\"));
+        assert_eq!(path_of("C:\\Mount\\Data\\x"), Some("C:\\Mount\\Data\\"));
+        assert_eq!(path_of("C:\\Mount\\Database"), Some("C:\\"));
+        assert_eq!(path_of("\\\\SRV\\Share\\dir"), Some("\\\\srv\\share"));
+        assert_eq!(path_of("\\\\srv\\shared"), None);
+        assert_eq!(path_of("E:\\"), None);
+    }
+
+    #[tokio::test]
+    async fn manifest_combines_summary_options_roots_and_top_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        for i in 0..25 {
+            let sub = root.join(format!("d{:02}", i));
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(sub.join("f.bin"), vec![0u8; 100 * (i + 1)]).unwrap();
+        }
+        let root = root.to_string_lossy().to_string();
+
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool, AppConfig::default());
+
+        let options = ScanOptions { excludes: vec!["*.tmp".into()], ..Default::default() };
+        let id = insert_scan_with_options(&state.db, "running", &[&root], "default", &options).await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(1024);
+        let summary = crate::scanner::run_scan(
+            state.db.clone(),
+            id,
+            vec![root.clone()],
+            options,
+            tx,
+            tokio_util::sync::CancellationToken::new(),
+            Default::default(),
+            100,
+            200,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE scans SET status='done', file_count=?1 WHERE id=?2")
+            .bind(summary.total_files as i64)
+            .bind(id.to_string())
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        let res = get_manifest(State(state.clone()), Namespace::default(), Path(id))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        let doc: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let mut keys: Vec<&str> = doc.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["generated_at", "options", "roots", "scan", "schema_version", "top_dirs"]);
+        assert_eq!(doc["schema_version"], MANIFEST_SCHEMA_VERSION);
+        assert_eq!(doc["scan"]["id"], id.to_string());
+        assert_eq!(doc["scan"]["status"], "done");
+        assert_eq!(doc["scan"]["file_count"], 25);
+        assert_eq!(doc["options"]["excludes"], serde_json::json!(["*.tmp"]));
+        assert_eq!(doc["roots"][0]["path"], root);
+
+        let manifest: ScanManifest = serde_json::from_value(doc).unwrap();
+        assert_eq!(manifest.roots.len(), 1);
+        assert_eq!(manifest.top_dirs.len(), MANIFEST_TOP_DIRS as usize);
+        let sizes: Vec<(i64, i64)> = manifest
+            .top_dirs
+            .iter()
+            .map(|item| match item {
+                crate::types::TopItem::Dir { allocated_size, logical_size, .. } => (*allocated_size, *logical_size),
+                other => panic!("unexpected item {:?}", other),
+            })
+            .collect();
+        assert!(sizes.windows(2).all(|w| w[0].0 >= w[1].0), "{:?}", sizes);
+        // The root itself holds everything and comes first
+        assert_eq!(sizes[0].1, (1..=25).map(|i| 100 * i).sum::<i64>());
+
+        let other = Uuid::new_v4();
+        let missing = get_manifest(State(state), Namespace::default(), Path(other)).await;
+        assert!(matches!(missing, Err(crate::error::AppError::NotFound(_))));
+    }
+}
@@ -3,18 +3,78 @@
 //! This module contains all the HTTP endpoint handlers for the file scanning and
 //! management system. Each sub-module handles a specific domain of functionality:
 //!
+//! - `analysis`: Aggregations over scan results, such as file age bands
+//! - `backup`: Downloading and restoring database backups
+//! - `consistency`: Checking and repairing directory totals that do not add up
+//! - `diff`: Comparison of two scans
 //! - `drives`: Drive management and detection endpoints
+//! - `duplicates`: Duplicate file search
+//! - `explain`: Why a scan takes in a path or leaves it out
 //! - `export`: Data export functionality
 //! - `health`: Health check and system status endpoints
+//! - `import`: Uploads of scans exported by other servers
+//! - `integrity`: Files whose content changed without changing size between two scans
+//! - `links`: Symbolic links, junctions and reparse points met by scans
+//! - `maintenance`: Vacuuming, analyzing and checking the database
+//! - `manifest`: A single document describing a scan for external tools
+//! - `negotiate`: JSON or MessagePack responses, chosen by `Accept` or `?format=`
+//! - `node`: A single directory with its breadcrumbs for deep links
+//! - `owners`: File owners per subtree for scans that captured them
 //! - `paths`: File path management and metadata
+//! - `paths_archive`: Writing zip and tar.zst archives of paths
 //! - `paths_helpers`: Utility functions for path handling
+//! - `paths_operations`: Tracking of background move and archive operations
+//! - `paths_recycle`: Recycle bin support for path deletion
+//! - `preview`: The direct children of a directory before it is scanned
+//! - `reports`: Reports spanning several scans, such as directory growth
+//! - `reload`: Applying a changed configuration without a restart
+//! - `remap`: Remapping scan roots to a new drive letter or location
+//! - `retention`: The scan retention policy and manual pruning
 //! - `scans`: File scanning operations and scan management
+//! - `schedules`: Recurring scan schedules
 //! - `search`: File search and filtering capabilities
+//! - `share`: Read-only share links of finished scans
+//! - `simulate`: What additional exclude patterns would remove from a scan
+//! - `streams`: Alternate data streams of files on NTFS
+//! - `verify`: Checking how far a finished scan drifted from the filesystem
+//! - `warnings`: Stored warning details of scans
+//! - `watch`: Keeping finished scans up to date from filesystem notifications
+//! - `ws`: WebSocket stream of scan events for proxies that buffer SSE
 
+pub mod analysis;
+pub mod backup;
+pub mod consistency;
+pub mod diff;
 pub mod drives;
+pub mod duplicates;
+pub mod explain;
 pub mod export;
 pub mod health;
+pub mod import;
+pub mod integrity;
+pub mod links;
+pub mod maintenance;
+pub mod manifest;
+pub mod negotiate;
+pub mod node;
+pub mod owners;
 pub mod paths;
+pub mod paths_archive;
 pub mod paths_helpers;
+pub mod paths_operations;
+pub mod paths_recycle;
+pub mod preview;
+pub mod reload;
+pub mod remap;
+pub mod reports;
+pub mod retention;
 pub mod scans;
+pub mod schedules;
 pub mod search;
+pub mod share;
+pub mod simulate;
+pub mod streams;
+pub mod verify;
+pub mod warnings;
+pub mod watch;
+pub mod ws;
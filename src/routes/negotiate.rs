@@ -0,0 +1,139 @@
+//! Content negotiation between JSON and MessagePack.
+//!
+//! Listings of a few hundred rows spend most of their JSON on field names and
+//! number formatting. Clients that send `Accept: application/msgpack`, or
+//! `?format=msgpack` where they cannot set headers, get the same data encoded
+//! as MessagePack instead. Structs are encoded as maps, so the field names and
+//! the DTOs stay the same for both formats. Errors are always JSON.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// The media type of MessagePack responses.
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// The encoding of a response body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// `application/json`, the default.
+    #[default]
+    Json,
+    /// `application/msgpack`
+    MsgPack,
+}
+
+impl ResponseFormat {
+    /// Picks the format of a request.
+    ///
+    /// `?format=json|msgpack` wins over the `Accept` header. MessagePack is
+    /// chosen from `Accept` if it is listed with a quality at least that of
+    /// `application/json`; everything else gets JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - The request headers.
+    /// * `uri` - The request URI.
+    pub fn negotiate(headers: &HeaderMap, uri: &Uri) -> Self {
+        #[derive(serde::Deserialize)]
+        struct FormatQuery {
+            format: Option<String>,
+        }
+        match Query::<FormatQuery>::try_from_uri(uri).ok().and_then(|q| q.0.format).as_deref() {
+            Some("msgpack") => return Self::MsgPack,
+            Some("json") => return Self::Json,
+            _ => {}
+        }
+        let (mut msgpack, mut json) = (0.0f32, 0.0f32);
+        let accept = headers.get_all(header::ACCEPT).iter().filter_map(|v| v.to_str().ok());
+        for range in accept.flat_map(|v| v.split(',')) {
+            let mut params = range.split(';').map(str::trim);
+            let media = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match media.as_str() {
+                "application/msgpack" | "application/x-msgpack" => msgpack = msgpack.max(quality),
+                "application/json" => json = json.max(quality),
+                _ => {}
+            }
+        }
+        if msgpack > 0.0 && msgpack >= json {
+            Self::MsgPack
+        } else {
+            Self::Json
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::negotiate(&parts.headers, &parts.uri))
+    }
+}
+
+/// Encodes a response body in the negotiated format.
+///
+/// The response varies by `Accept`, so caches keep both encodings apart.
+///
+/// # Arguments
+///
+/// * `format` - The format of the request, see [`ResponseFormat::negotiate`].
+/// * `data` - The response data.
+///
+/// # Returns
+///
+/// * `Response` - A `200 OK` response with the encoded data.
+pub fn respond_negotiated<T: Serialize>(format: ResponseFormat, data: &T) -> Response {
+    let mut res = match format {
+        ResponseFormat::Json => Json(data).into_response(),
+        ResponseFormat::MsgPack => match rmp_serde::to_vec_named(data) {
+            Ok(body) => {
+                ([(header::CONTENT_TYPE, HeaderValue::from_static(MSGPACK_CONTENT_TYPE))], body).into_response()
+            }
+            Err(e) => return AppError::Internal(anyhow::anyhow!("MessagePack encoding failed: {}", e)).into_response(),
+        },
+    };
+    res.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: Option<&str>, uri: &str) -> ResponseFormat {
+        let mut headers = HeaderMap::new();
+        if let Some(accept) = accept {
+            headers.insert(header::ACCEPT, accept.parse().unwrap());
+        }
+        ResponseFormat::negotiate(&headers, &uri.parse().unwrap())
+    }
+
+    #[test]
+    fn msgpack_is_chosen_by_accept_or_query() {
+        use ResponseFormat::*;
+        assert_eq!(negotiate(None, "/scans/x/list"), Json);
+        assert_eq!(negotiate(Some("application/msgpack"), "/scans/x/list"), MsgPack);
+        assert_eq!(negotiate(Some("application/json, application/x-msgpack;q=0.9"), "/"), Json);
+        assert_eq!(negotiate(Some("application/json;q=0.5, application/msgpack"), "/"), MsgPack);
+        assert_eq!(negotiate(Some("application/msgpack;q=0"), "/"), Json);
+        assert_eq!(negotiate(Some("*/*"), "/"), Json);
+        assert_eq!(negotiate(None, "/scans/x/list?limit=5&format=msgpack"), MsgPack);
+        assert_eq!(negotiate(Some("application/msgpack"), "/scans/x/list?format=json"), Json);
+    }
+}
@@ -0,0 +1,197 @@
+//! Single directory API endpoint for deep links into the Explorer.
+//!
+//! A search hit or a bookmarked path opens the Explorer at a directory that may
+//! lie many levels below the scan root. This endpoint answers the directory
+//! together with its breadcrumbs in one request, instead of one listing per
+//! ancestor.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/node?path=&with_siblings=&limit=` - A directory, its ancestors and siblings
+
+use std::collections::HashMap;
+use std::path::Path as StdPath;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::QueryBuilder;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::{node_dto, normalize_query_path, query_path_variants, NODE_COLUMNS},
+    state::AppState,
+    types::{NodeContextDto, NodeDto},
+};
+
+const SIBLINGS_LIMIT_DEFAULT: i64 = 200;
+const SIBLINGS_LIMIT_MAX: i64 = 5_000;
+
+/// Query parameters for the node endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct NodeQuery {
+    /// The path of the directory, normalized like the `path` of the list endpoint.
+    pub path: String,
+    /// Whether to return the other directories in the same parent.
+    pub with_siblings: Option<bool>,
+    /// The maximum number of siblings to return.
+    pub limit: Option<i64>,
+}
+
+/// Gets a directory of a scan together with its ancestors and, on request, its siblings.
+///
+/// The ancestors' paths are derived from the requested path the way the scanner
+/// derives parent paths, so the directory and all its ancestors are read in one
+/// query for every spelling the path may be stored under; the siblings take a
+/// second query.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The path and sibling parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `NodeContextDto`,
+///   or `NotFound` with the nearest stored ancestor as `details.nearest_ancestor`.
+pub async fn get_node(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<NodeQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    if q.path.len() > 4096 {
+        return Err(AppError::BadRequest("Path too long".into()));
+    }
+    let normalized = normalize_query_path(&q.path)?;
+    // One chain per spelling, each from the path itself up to the filesystem root
+    let chains: Vec<Vec<String>> = query_path_variants(&normalized)
+        .iter()
+        .map(|v| StdPath::new(v).ancestors().map(|a| a.to_string_lossy().into_owned()).collect())
+        .collect();
+
+    let mut qb = QueryBuilder::<sqlx::Sqlite>::new(format!(
+        "SELECT {} FROM nodes WHERE is_dir=1 AND scan_id=",
+        NODE_COLUMNS
+    ));
+    qb.push_bind(id.to_string()).push(" AND path IN (");
+    let mut list = qb.separated(", ");
+    for path in chains.iter().flatten() {
+        list.push_bind(path.clone());
+    }
+    qb.push(")");
+    let mut stored: HashMap<String, NodeDto> = qb
+        .build()
+        .fetch_all(state.read_pool())
+        .await?
+        .iter()
+        .map(node_dto)
+        .map(|n| (n.path.clone(), n))
+        .collect();
+
+    let Some(chain) = chains.iter().find(|chain| stored.contains_key(&chain[0])) else {
+        let nearest = chains.iter().flatten().filter_map(|p| stored.get(p)).max_by_key(|n| n.depth);
+        return Err(AppError::NotFoundWithDetails {
+            message: format!("Directory not found in scan: {}", normalized),
+            details: serde_json::json!({ "path": normalized, "nearest_ancestor": nearest }),
+        });
+    };
+    let node = stored.remove(&chain[0]).expect("chain start is stored");
+    // Paths above the scan root are not stored and drop out
+    let mut ancestors: Vec<NodeDto> = chain[1..].iter().filter_map(|p| stored.remove(p)).collect();
+    ancestors.reverse();
+
+    let siblings = match (q.with_siblings.unwrap_or(false), node.parent_path.as_deref()) {
+        (false, _) => None,
+        (true, None) => Some(Vec::new()),
+        (true, Some(parent)) => {
+            let limit = q.limit.unwrap_or(SIBLINGS_LIMIT_DEFAULT).clamp(1, SIBLINGS_LIMIT_MAX);
+            let rows = sqlx::query(&format!(
+                "SELECT {} FROM nodes WHERE scan_id=?1 AND parent_path=?2 AND is_dir=1 AND path<>?3 \
+                 ORDER BY allocated_size DESC, path ASC LIMIT ?4",
+                NODE_COLUMNS
+            ))
+            .bind(id.to_string())
+            .bind(parent)
+            .bind(&node.path)
+            .bind(limit)
+            .fetch_all(state.read_pool())
+            .await?;
+            Some(rows.iter().map(node_dto).collect())
+        }
+    };
+    Ok(Json(NodeContextDto { node, ancestors, siblings }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_node, insert_scan, test_db, NodeRow};
+
+    async fn fixture() -> (tempfile::TempDir, AppState, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &["/data"], "default").await;
+        let nodes = [
+            ("/data", "/", 2, 600),
+            ("/data/a", "/data", 3, 500),
+            ("/data/a/b", "/data/a", 4, 300),
+            ("/data/a/c", "/data/a", 4, 150),
+            ("/data/a/d", "/data/a", 4, 50),
+            ("/data/x", "/data", 3, 100),
+        ];
+        for (path, parent, depth, size) in nodes {
+            insert_node(&pool, id, NodeRow { path, parent: Some(parent), depth, size, ..Default::default() })
+                .await;
+        }
+        (dir, AppState::new(pool, AppConfig::default()), id)
+    }
+
+    async fn node(state: &AppState, id: Uuid, path: &str, siblings: bool) -> AppResult<serde_json::Value> {
+        use http_body_util::BodyExt;
+        let q = NodeQuery { path: path.into(), with_siblings: Some(siblings), limit: None };
+        let res = get_node(State(state.clone()), Namespace::default(), Path(id), Query(q)).await?;
+        let bytes = res.into_response().into_body().collect().await.unwrap().to_bytes();
+        Ok(serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn paths(nodes: &serde_json::Value) -> Vec<&str> {
+        nodes.as_array().unwrap().iter().map(|n| n["path"].as_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn node_comes_with_its_breadcrumbs_and_siblings() {
+        let (_dir, state, id) = fixture().await;
+        let plain = node(&state, id, "/data/a/c/", false).await.unwrap();
+        assert_eq!(plain["node"]["path"], "/data/a/c");
+        assert_eq!(paths(&plain["ancestors"]), ["/data", "/data/a"]);
+        assert!(plain.get("siblings").is_none());
+
+        let with_siblings = node(&state, id, "/data/a/c", true).await.unwrap();
+        assert_eq!(paths(&with_siblings["siblings"]), ["/data/a/b", "/data/a/d"]);
+
+        let root = node(&state, id, "/data", true).await.unwrap();
+        assert_eq!(paths(&root["ancestors"]), Vec::<&str>::new());
+        assert!(paths(&root["siblings"]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_node_names_the_nearest_ancestor() {
+        let (_dir, state, id) = fixture().await;
+        let err = node(&state, id, "/data/a/gone/deeper", false).await.unwrap_err();
+        let AppError::NotFoundWithDetails { details, .. } = err else { panic!("{:?}", err) };
+        assert_eq!(details["path"], "/data/a/gone/deeper");
+        assert_eq!(details["nearest_ancestor"]["path"], "/data/a");
+
+        let err = node(&state, id, "/elsewhere", false).await.unwrap_err();
+        let AppError::NotFoundWithDetails { details, .. } = err else { panic!("{:?}", err) };
+        assert!(details["nearest_ancestor"].is_null());
+    }
+}
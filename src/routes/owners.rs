@@ -0,0 +1,208 @@
+//! File owner API endpoints.
+//!
+//! Scans started with `capture_owner` record the owner of every file and sum
+//! the files of each directory per owner (see [`crate::scanner::owner`]). This
+//! report adds those sums up for a subtree, e.g. to charge storage back to the
+//! users who own it.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/owners?path=&limit=` - Owners ranked by allocated bytes below a directory
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::{resolve_query_path, subtree_like_pattern},
+    state::AppState,
+    types::{OwnerUsage, OwnersResponse},
+};
+
+const OWNERS_LIMIT_DEFAULT: i64 = 50;
+const OWNERS_LIMIT_MAX: i64 = 1_000;
+
+/// Query parameters for the owners endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct OwnersQuery {
+    /// The directory whose subtree is summed; the whole scan if omitted.
+    pub path: Option<String>,
+    /// The maximum number of owners to return.
+    pub limit: Option<i64>,
+}
+
+/// Lists the owners of the files below a directory, most allocated bytes first.
+///
+/// Scans that did not capture owners return an empty list with `captured`
+/// set to `false`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The subtree and the number of owners.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing an `OwnersResponse`.
+pub async fn get_owners(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<OwnersQuery>,
+) -> AppResult<impl IntoResponse> {
+    let pool = state.read_pool();
+    ns.ensure_scan(pool, id).await?;
+    let limit = q.limit.unwrap_or(OWNERS_LIMIT_DEFAULT).clamp(1, OWNERS_LIMIT_MAX);
+    let subtree = match q.path.as_deref() {
+        Some(p) => {
+            if p.len() > 4096 {
+                return Err(AppError::BadRequest("Path too long".into()));
+            }
+            Some(resolve_query_path(pool, id, p).await?)
+        }
+        None => None,
+    };
+
+    let options: Option<String> = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(pool)
+        .await?;
+    // Older scans may lack the flag, so the options are not parsed into `ScanOptions`
+    let captured = options
+        .and_then(|o| serde_json::from_str::<serde_json::Value>(&o).ok())
+        .and_then(|o| o.get("capture_owner").and_then(serde_json::Value::as_bool))
+        .unwrap_or(false);
+
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT owner, SUM(file_count) AS file_count, SUM(logical_size) AS logical_size, \
+         SUM(allocated_size) AS allocated_size FROM owner_usage WHERE scan_id=",
+    );
+    qb.push_bind(id.to_string());
+    if let Some(root) = subtree.as_deref() {
+        qb.push(" AND (path = ").push_bind(root.to_string());
+        qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(root));
+        qb.push(" ESCAPE '!')");
+    }
+    qb.push(" GROUP BY owner ORDER BY allocated_size DESC, owner LIMIT ").push_bind(limit);
+    let items = qb
+        .build()
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|r| OwnerUsage {
+            owner: r.get("owner"),
+            file_count: r.get("file_count"),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+        })
+        .collect();
+
+    Ok(Json(OwnersResponse { scan_id: id, path: subtree, captured, items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan_with_options, test_db_in};
+    use crate::types::ScanOptions;
+    use http_body_util::BodyExt;
+
+    async fn owners(state: &AppState, id: Uuid, q: OwnersQuery) -> OwnersResponse {
+        let res = get_owners(State(state.clone()), Namespace::default(), Path(id), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scanned_owners_are_ranked_per_subtree_and_follow_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("top.bin"), vec![0u8; 300]).unwrap();
+        std::fs::write(root.join("sub").join("a.bin"), vec![0u8; 1000]).unwrap();
+        std::fs::write(root.join("sub").join("b.bin"), vec![0u8; 500]).unwrap();
+
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool, AppConfig::default());
+
+        let mut ids = Vec::new();
+        for capture_owner in [true, false] {
+            let options = ScanOptions { capture_owner, measure_allocated: false, ..Default::default() };
+            let id = insert_scan_with_options(&state.db, "done", &[], "default", &options).await;
+            let (tx, _rx) = tokio::sync::broadcast::channel(1024);
+            crate::scanner::run_scan(
+                state.db.clone(),
+                id,
+                vec![root.to_string_lossy().to_string()],
+                options,
+                tx,
+                tokio_util::sync::CancellationToken::new(),
+                Default::default(),
+                100,
+                200,
+                64 << 20,
+                50,
+                None,
+                Some(2),
+                None,
+                Default::default(),
+                crate::scanner::Reuse::Nothing,
+                &crate::metrics::Metrics::default(),
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+        let (captured_id, plain_id) = (ids[0], ids[1]);
+        #[cfg(unix)]
+        let me = {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata(root.join("top.bin")).unwrap().uid().to_string()
+        };
+
+        let all = owners(&state, captured_id, OwnersQuery::default()).await;
+        assert!(all.captured);
+        assert_eq!(all.items.len(), 1);
+        assert_eq!((all.items[0].file_count, all.items[0].logical_size), (3, 1800));
+        #[cfg(unix)]
+        assert_eq!(all.items[0].owner, me);
+
+        let sub = root.join("sub").to_string_lossy().to_string();
+        let q = OwnersQuery { path: Some(sub.clone()), ..Default::default() };
+        let below = owners(&state, captured_id, q).await;
+        assert_eq!(below.path.as_deref(), Some(sub.as_str()));
+        assert_eq!((below.items[0].file_count, below.items[0].logical_size), (2, 1500));
+
+        let removed = root.join("sub").join("a.bin").to_string_lossy().to_string();
+        crate::routes::paths::remove_path_from_scan(&state.db, captured_id, &removed).await.unwrap();
+        let q = OwnersQuery { path: Some(sub.clone()), ..Default::default() };
+        let below = owners(&state, captured_id, q).await;
+        assert_eq!((below.items[0].file_count, below.items[0].logical_size), (1, 500));
+        crate::routes::paths::remove_path_from_scan(&state.db, captured_id, &sub).await.unwrap();
+        let all = owners(&state, captured_id, OwnersQuery::default()).await;
+        assert_eq!((all.items[0].file_count, all.items[0].logical_size), (1, 300));
+
+        let plain = owners(&state, plain_id, OwnersQuery::default()).await;
+        assert!(!plain.captured);
+        assert!(plain.items.is_empty());
+        let stored: Option<String> = sqlx::query_scalar("SELECT owner FROM files WHERE scan_id=?1 LIMIT 1")
+            .bind(plain_id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(stored, None);
+    }
+}
@@ -1,6 +1,6 @@
 //! File and directory path management API endpoints.
 //!
-//! This module provides HTTP endpoints for moving and copying files and directories.
+//! This module provides HTTP endpoints for moving, copying and deleting files and directories.
 //! It handles both simple renames (within the same filesystem) and cross-filesystem
 //! operations (copy-then-delete). The module includes comprehensive error handling,
 //! disk space checking, and rollback capabilities for failed operations.
@@ -12,7 +12,16 @@
 //! - **Cross-filesystem Support**: Automatic fallback from rename to copy+delete
 //! - **Disk Space Checking**: Pre-operation validation to prevent out-of-space errors
 //! - **Rollback Support**: Automatic cleanup of partial operations on failure
-//! - **Progress Tracking**: Detailed operation metrics and warnings
+//! - **Progress Tracking**: Moves run as background operations that can be polled
+//!   and cancelled between files
+//! - **Batch Moves**: Several items checked up front and moved one after another,
+//!   with a status per item
+//! - **Delete Operations**: Recycle bin by default, permanent deletion on request,
+//!   optionally keeping a scan in sync with the deleted paths
+//! - **Archive Operations**: Pack a path into a zip or tar.zst archive in the background,
+//!   optionally deleting the source afterwards; progress is polled via an operation ID
+//! - **Read-only Scans**: Paths below the roots of a scan created with `read_only`
+//!   are never modified; operations touching them are rejected with 403
 //! - **Windows Specific**: Special handling for junctions and reparse points
 //!
 //! ## Security Considerations
@@ -23,19 +32,20 @@
 //! - Source/destination relationships are validated
 
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
     time::Instant,
 };
 
 use anyhow::anyhow;
 use axum::{
-    extract::State,
+    extract::{Path as UrlPath, State},
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use chrono::Utc;
 use tokio::task::spawn_blocking;
 use walkdir::WalkDir;
 
@@ -43,30 +53,31 @@ use crate::{
     error::{AppError, AppResult},
     middleware::{
         ip::{extract_ip_from_headers, MaybeRemoteAddr},
-        validation::{sanitize_for_logging, validate_file_path},
+        namespace::Namespace,
+        validation::{sanitize_for_logging, validate_file_path, ValidJson},
+    },
+    routes::{
+        paths_archive::{run_archive, ArchiveProgress},
+        paths_helpers::get_volume_root,
+        paths_operations::{retire_operation, MoveProgress, PathOperationHandle},
+        paths_recycle,
+        scans::{is_within_root, subtree_like_pattern},
+    },
+    scanner::{
+        is_hidden_or_system, is_reparse_point,
+        owner::{refresh_usage, UsageScope},
     },
-    routes::paths_helpers::get_volume_root,
     state::AppState,
 
-    types::{MovePathRequest, MovePathResponse},
+    types::{
+        ArchivePathRequest, DeleteMode, DeletePathRequest, DeletePathResponse, DeleteStatus, DeletedPath,
+        MoveBatchRequest, MoveItemStatus, MovePathRequest, PathOperation,
+    },
 };
-use tokio_util::sync::CancellationToken;
-
-
-/// Result of a move/copy operation.
-///
-/// This structure captures the outcome of file or directory operations,
-/// including metrics about data transferred and any warnings encountered.
-struct MoveOutcome {
-    /// Total bytes that needed to be transferred
-    bytes_to_transfer: u64,
-    /// Actual bytes that were successfully moved/copied
-    bytes_moved: u64,
-    /// Bytes freed from source (only for move operations)
-    freed_bytes: u64,
-    /// Collection of warnings encountered during operation
-    warnings: Vec<String>,
-}
+use uuid::Uuid;
+
+/// The maximum number of items in one `POST /paths/move-batch` request.
+const MAX_BATCH_ITEMS: usize = 1_000;
 
 /// Moves or copies a file or directory.
 ///
@@ -75,6 +86,12 @@ struct MoveOutcome {
 /// (e.g., across different filesystems), it will fall back to a copy-then-delete
 /// operation.
 ///
+/// The work runs in the background: the response (`202 Accepted`) holds the
+/// `MoveOperation` with its `op_id`, which is polled via
+/// `GET /paths/operations/{op_id}` and cancelled via `DELETE` on the same path.
+/// Moves into or, with `remove_source`, out of the root of a read-only scan
+/// are rejected with `403 Forbidden`.
+///
 /// # Arguments
 ///
 /// * `state` - The application state.
@@ -84,17 +101,17 @@ struct MoveOutcome {
 ///
 /// # Returns
 ///
-/// * `AppResult<Response>` - A JSON response containing the details of the move operation.
+/// * `AppResult<Response>` - A JSON response containing the started `PathOperation`.
 pub async fn move_path(
     State(state): State<AppState>,
     maybe_remote: MaybeRemoteAddr,
     headers: HeaderMap,
-    Json(req): Json<MovePathRequest>,
+    ValidJson(req): ValidJson<MovePathRequest>,
 ) -> AppResult<Response> {
     let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
     let ip = extract_ip_from_headers(&headers, fallback_ip);
-    if let Err((status, body)) = state.rate_limiter.check_endpoint_limit("/paths/move", ip).await {
-        return Ok((status, body).into_response());
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/paths/move", ip).await {
+        return Ok(limited.into_response());
     }
 
     if req.sources.is_empty() || req.destinations.is_empty() {
@@ -107,30 +124,11 @@ pub async fn move_path(
     let mut valid_sources = Vec::new();
     let mut valid_destinations = Vec::new();
 
-    for (i, src) in req.sources.iter().enumerate() {
-        let src_trimmed = src.trim();
-        let dest_trimmed = req.destinations[i].trim();
-        
-        if src_trimmed.is_empty() {
-            return Err(AppError::BadRequest("source path must not be empty".into()));
-        }
-        if dest_trimmed.is_empty() {
-            return Err(AppError::BadRequest("destination path must not be empty".into()));
-        }
-
-        let source_valid = match validate_file_path(src_trimmed) {
-            Ok(path) => path,
-            Err((status, body)) => return Ok((status, body).into_response()),
-        };
-        let dest_valid = match validate_file_path(dest_trimmed) {
-            Ok(path) => path,
-            Err((status, body)) => return Ok((status, body).into_response()),
+    for (src, dest) in req.sources.iter().zip(&req.destinations) {
+        let (source_valid, dest_valid) = match validate_move_pair(src, dest) {
+            Ok(pair) => pair,
+            Err(resp) => return Ok(*resp),
         };
-
-        if source_valid.eq_ignore_ascii_case(&dest_valid) {
-            return Err(AppError::BadRequest("source and destination must be different".into()));
-        }
-
         valid_sources.push(source_valid);
         valid_destinations.push(dest_valid);
     }
@@ -142,56 +140,265 @@ pub async fn move_path(
         req.overwrite
     );
 
-    let started_at = Utc::now();
-    let started_instant = Instant::now();
-
     let mut job_req = req.clone();
-    job_req.sources = valid_sources.clone();
-    job_req.destinations = valid_destinations.clone();
-
-    // FIX Bug #36: Add timeout to blocking operations (30 minutes for large file ops)
-    // FIX Bug #6: Add cancellation token for detached task cleanup
-    let cancel_token = CancellationToken::new();
-    let cancel_child = cancel_token.clone();
-    
-    let outcome = tokio::select! {
-        res = spawn_blocking(move || perform_moves(job_req, cancel_child)) => {
-            res.map_err(|e| AppError::Internal(anyhow!("move task join error: {}", e)))?
-        }
-        _ = tokio::time::sleep(std::time::Duration::from_secs(1800)) => {
-             cancel_token.cancel();
-             return Err(AppError::ServiceUnavailable("File operation timed out after 30 minutes".into()));
-        }
-    }?;
-
-    let duration_ms = started_instant.elapsed().as_millis();
-    let response = MovePathResponse {
-        status: "completed".to_string(),
-        sources: valid_sources,
-        destinations: valid_destinations,
-        bytes_to_transfer: outcome.bytes_to_transfer,
-        bytes_moved: outcome.bytes_moved,
-        freed_bytes: outcome.freed_bytes,
-        duration_ms,
-        started_at: started_at.to_rfc3339(),
-        finished_at: Utc::now().to_rfc3339(),
-        warnings: outcome.warnings,
-    };
+    job_req.sources = valid_sources;
+    job_req.destinations = valid_destinations;
+    ensure_writable(&state, modified_by_move(&job_req)).await?;
 
-    Ok((StatusCode::OK, Json(response)).into_response())
+    Ok(start_move(&state, MoveProgress::new(Uuid::new_v4(), job_req)).await)
 }
 
-fn perform_moves(req: MovePathRequest, cancel: CancellationToken) -> AppResult<MoveOutcome> {
-    let mut total_bytes_to_transfer = 0;
-    let mut total_bytes_moved = 0;
-    let mut total_freed_bytes = 0;
-    let mut all_warnings = Vec::new();
+/// Moves or copies several files or directories one after another.
+///
+/// The items are checked before anything is touched: sources must not
+/// contain each other or any destination, no two items may share a
+/// destination, and each destination drive must have room for everything
+/// copied onto it. Any of these problems rejects the whole request. Items
+/// whose source is missing, or whose destination exists without `overwrite`,
+/// are marked `skipped` and left alone.
+///
+/// Like `POST /paths/move` the work runs in the background and answers
+/// `202 Accepted` with the `MoveOperation`, whose `items` report `succeeded`,
+/// `skipped` or `failed` per item. A failed item does not stop the others
+/// unless `stop_on_error` is set, which skips the rest.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+/// * `req` - The batch move request payload.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing the started `PathOperation`.
+pub async fn move_batch(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<MoveBatchRequest>,
+) -> AppResult<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/paths/move-batch", ip).await {
+        return Ok(limited.into_response());
+    }
+
+    let mut pairs: Vec<(String, String)> =
+        req.items.iter().map(|item| (item.source.clone(), item.destination.clone())).collect();
+    if !req.sources.is_empty() {
+        let Some(dir) = req.destination_dir.as_deref().map(str::trim).filter(|d| !d.is_empty()) else {
+            return Err(AppError::BadRequest("destination_dir is required with sources".into()));
+        };
+        for src in &req.sources {
+            let Some(name) = Path::new(src.trim()).file_name() else {
+                return Err(AppError::BadRequest(format!("source has no file name: {}", src)));
+            };
+            pairs.push((src.clone(), Path::new(dir).join(name).to_string_lossy().to_string()));
+        }
+    }
+    if pairs.is_empty() {
+        return Err(AppError::BadRequest("items or sources must not be empty".into()));
+    }
+    if pairs.len() > MAX_BATCH_ITEMS {
+        return Err(AppError::BadRequest(format!("at most {} items per batch", MAX_BATCH_ITEMS)));
+    }
 
+    let mut valid_pairs = Vec::with_capacity(pairs.len());
+    for (src, dest) in &pairs {
+        match validate_move_pair(src, dest) {
+            Ok(pair) => valid_pairs.push(pair),
+            Err(resp) => return Ok(*resp),
+        }
+    }
+    check_batch_overlaps(&valid_pairs)?;
+
+    let (sources, destinations) = valid_pairs.into_iter().unzip();
+    let job_req = MovePathRequest { sources, destinations, remove_source: req.remove_source, overwrite: req.overwrite };
+    ensure_writable(&state, modified_by_move(&job_req)).await?;
+    tracing::info!(
+        "Batch move request: {} items (remove_source={}, overwrite={}, stop_on_error={})",
+        job_req.sources.len(),
+        req.remove_source,
+        req.overwrite,
+        req.stop_on_error
+    );
+
+    let check_req = job_req.clone();
+    let (skipped, warnings) = spawn_blocking(move || prepare_move_batch(&check_req))
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("move check join error: {}", e)))??;
+
+    let progress = MoveProgress::new(Uuid::new_v4(), job_req).with_stop_on_error(req.stop_on_error);
+    for (index, reason) in skipped {
+        progress.set_item(index, MoveItemStatus::Skipped, Some(reason));
+    }
+    warnings.into_iter().for_each(|w| progress.warn(w));
+    Ok(start_move(&state, progress).await)
+}
+
+/// Rejects an operation that would modify a path below the root of a read-only scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `paths` - The validated paths the operation would create, change or remove.
+///
+/// # Returns
+///
+/// * `AppResult<()>` - `AppError::Forbidden` naming the first protected path.
+async fn ensure_writable<'a>(state: &AppState, paths: impl IntoIterator<Item = &'a str>) -> AppResult<()> {
+    let roots = state.read_only_roots.get(&state.db).await?;
+    if roots.is_empty() {
+        return Ok(());
+    }
+    for path in paths {
+        if let Some(root) = roots.iter().find(|root| is_within_root(root, path)) {
+            return Err(AppError::Forbidden(format!(
+                "{} lies within {}, the root of a read-only scan, and must not be modified",
+                path, root
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The paths a move modifies: every destination, and the sources if they are removed.
+fn modified_by_move(req: &MovePathRequest) -> impl Iterator<Item = &str> {
+    let sources = req.sources.iter().filter(|_| req.remove_source);
+    req.destinations.iter().chain(sources).map(String::as_str)
+}
+
+/// Trims and validates a source and destination pair.
+///
+/// Returns the error response for invalid paths.
+fn validate_move_pair(src: &str, dest: &str) -> Result<(String, String), Box<Response>> {
+    let reject = |resp: Response| Box::new(resp);
+    let (src_trimmed, dest_trimmed) = (src.trim(), dest.trim());
+    if src_trimmed.is_empty() {
+        return Err(reject(AppError::BadRequest("source path must not be empty".into()).into_response()));
+    }
+    if dest_trimmed.is_empty() {
+        return Err(reject(AppError::BadRequest("destination path must not be empty".into()).into_response()));
+    }
+    let source_valid = validate_file_path(src_trimmed).map_err(|e| reject(e.into_response()))?;
+    let dest_valid = validate_file_path(dest_trimmed).map_err(|e| reject(e.into_response()))?;
+    if source_valid.eq_ignore_ascii_case(&dest_valid) {
+        return Err(reject(AppError::BadRequest("source and destination must be different".into()).into_response()));
+    }
+    Ok((source_valid, dest_valid))
+}
+
+/// Rejects batches whose items would move into or out of each other.
+fn check_batch_overlaps(pairs: &[(String, String)]) -> AppResult<()> {
+    // Windows paths are case-insensitive
+    let key = |p: &str| PathBuf::from(if cfg!(windows) { p.to_lowercase() } else { p.to_string() });
+    let sources: Vec<PathBuf> = pairs.iter().map(|(src, _)| key(src)).collect();
+    let destinations: Vec<PathBuf> = pairs.iter().map(|(_, dest)| key(dest)).collect();
+    for (i, source) in sources.iter().enumerate() {
+        if let Some(j) = (0..sources.len()).find(|&j| j != i && sources[j].starts_with(source)) {
+            return Err(AppError::BadRequest(format!(
+                "source {} contains source {}",
+                pairs[i].0, pairs[j].0
+            )));
+        }
+        if let Some(j) = destinations.iter().position(|dest| dest.starts_with(source)) {
+            return Err(AppError::BadRequest(format!(
+                "destination {} is inside source {}",
+                pairs[j].1, pairs[i].0
+            )));
+        }
+        if let Some(j) = (i + 1..destinations.len()).find(|&j| destinations[j] == destinations[i]) {
+            return Err(AppError::BadRequest(format!(
+                "destination {} is used by more than one item",
+                pairs[j].1
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The indices of batch items to skip with the reason, and the warnings of the check.
+type BatchCheck = (Vec<(usize, String)>, Vec<String>);
+
+/// Checks a batch move and returns the items to skip with their reason, plus warnings.
+///
+/// Fails if a destination drive lacks the space for all items copied onto it;
+/// items renamed within their drive need none.
+fn prepare_move_batch(req: &MovePathRequest) -> AppResult<BatchCheck> {
+    let mut skipped = Vec::new();
+    let mut warnings = Vec::new();
+    // Bytes to copy per destination volume, with a directory on it
+    let mut needed: HashMap<String, (PathBuf, u64)> = HashMap::new();
+    for (i, (src, dest)) in req.sources.iter().zip(&req.destinations).enumerate() {
+        let (source_path, dest_path) = (Path::new(src), Path::new(dest));
+        let Ok(metadata) = fs::symlink_metadata(source_path) else {
+            skipped.push((i, "source does not exist".to_string()));
+            continue;
+        };
+        if !req.overwrite && fs::symlink_metadata(dest_path).is_ok() {
+            skipped.push((i, "destination already exists".to_string()));
+            continue;
+        }
+        let Some(parent) = dest_path.parent() else {
+            skipped.push((i, "destination path must include a parent directory".to_string()));
+            continue;
+        };
+        let volume = get_volume_root(dest_path);
+        if req.remove_source && get_volume_root(source_path).eq_ignore_ascii_case(&volume) {
+            continue;
+        }
+        let bytes =
+            if metadata.is_dir() { compute_directory_size(source_path, &mut warnings)? } else { metadata.len() };
+        needed.entry(volume.to_lowercase()).or_insert_with(|| (parent.to_path_buf(), 0)).1 += bytes;
+    }
+    for (dir, bytes) in needed.values() {
+        ensure_free_space(dir, *bytes)?;
+    }
+    Ok((skipped, warnings))
+}
+
+/// Registers a move operation and works through it in the background.
+///
+/// Clients poll `GET /paths/operations/{op_id}` for its progress.
+///
+/// # Returns
+///
+/// * `Response` - `202 Accepted` with the started `PathOperation`.
+async fn start_move(state: &AppState, progress: MoveProgress) -> Response {
+    let progress = Arc::new(progress);
+    let op_id = progress.op_id();
+    state.operations.write().await.insert(op_id, PathOperationHandle::Move(progress.clone()));
+    let snapshot = progress.snapshot();
+
+    let operations = state.operations.clone();
+    tokio::spawn(async move {
+        let worker = progress.clone();
+        let result = spawn_blocking(move || perform_moves(&worker))
+            .await
+            .map_err(|e| format!("move task join error: {}", e));
+        if let Err(e) = &result {
+            tracing::error!("Move operation {} failed: {}", op_id, e);
+        }
+        progress.finish(result);
+        retire_operation(operations, op_id).await;
+    });
+
+    (StatusCode::ACCEPTED, Json(PathOperation::Move(snapshot))).into_response()
+}
+
+fn perform_moves(progress: &MoveProgress) {
+    let req = progress.request();
     for i in 0..req.sources.len() {
-        if cancel.is_cancelled() {
-            all_warnings.push("Operation cancelled by user. Some items were not processed.".into());
+        if progress.is_cancelled() {
+            progress.warn("Operation cancelled by user. Some items were not processed.".into());
             break;
         }
+        // Batch items skipped by the up-front checks
+        if progress.item_status(i) != MoveItemStatus::Pending {
+            progress.item_done();
+            continue;
+        }
 
         let source_str = &req.sources[i];
         let dest_str = &req.destinations[i];
@@ -204,24 +411,45 @@ fn perform_moves(req: MovePathRequest, cancel: CancellationToken) -> AppResult<M
             overwrite: req.overwrite,
         };
         
-        match perform_single_move(&item_req, &cancel) {
-            Ok(outcome) => {
-                total_bytes_to_transfer += outcome.bytes_to_transfer;
-                total_bytes_moved += outcome.bytes_moved;
-                total_freed_bytes += outcome.freed_bytes;
-                all_warnings.extend(outcome.warnings);
-            },
+        progress.set_current_source(Some(source_str));
+        let mut warnings = Vec::new();
+        let result = perform_single_move(&item_req, &mut warnings, progress);
+        warnings.into_iter().for_each(|w| progress.warn(w));
+        match result {
+            Ok(()) => {
+                progress.set_item(i, MoveItemStatus::Succeeded, None);
+                progress.item_done();
+            }
+            Err(_) if progress.is_cancelled() => {
+                // Files copied so far stay at the destination; the source is left untouched
+                progress.warn(format!("Operation cancelled by user while processing {}.", source_str));
+                progress.set_item(i, MoveItemStatus::Failed, Some("cancelled".into()));
+                break;
+            }
             Err(e) => {
-                all_warnings.push(format!("Failed to move {}: {}", source_str, e));
+                progress.warn(format!("Failed to move {}: {}", source_str, e));
+                progress.set_item(i, MoveItemStatus::Failed, Some(e.to_string()));
+                progress.item_done();
+                if progress.stops_on_error() {
+                    for rest in i + 1..req.sources.len() {
+                        if progress.item_status(rest) == MoveItemStatus::Pending {
+                            progress.set_item(rest, MoveItemStatus::Skipped, Some("an earlier item failed".into()));
+                        }
+                        progress.item_done();
+                    }
+                    break;
+                }
                 // Continue with the next item instead of failing the whole batch
             }
         }
     }
-
-    Ok(MoveOutcome { bytes_to_transfer: total_bytes_to_transfer, bytes_moved: total_bytes_moved, freed_bytes: total_freed_bytes, warnings: all_warnings })
 }
 
-fn perform_single_move(req: &MovePathRequest, cancel: &CancellationToken) -> AppResult<MoveOutcome> {
+fn perform_single_move(
+    req: &MovePathRequest,
+    warnings: &mut Vec<String>,
+    progress: &MoveProgress,
+) -> AppResult<()> {
     let source_path = PathBuf::from(&req.sources[0]);
     if !source_path.exists() {
         return Err(AppError::NotFound(format!("source path does not exist: {}", req.sources[0])));
@@ -242,73 +470,84 @@ fn perform_single_move(req: &MovePathRequest, cancel: &CancellationToken) -> App
 
     // FIX Bug #7: Use symlink_metadata to correctly handle symlinks (don't follow them)
     let metadata = fs::symlink_metadata(&source_path)?;
-    let mut warnings = Vec::new();
     let bytes_to_transfer =
-        if metadata.is_dir() { compute_directory_size(&source_path, &mut warnings)? } else { metadata.len() };
+        if metadata.is_dir() { compute_directory_size(&source_path, warnings)? } else { metadata.len() };
+    progress.add_to_transfer(bytes_to_transfer);
 
     // FIX Bug #34: Check available disk space before proceeding
     if !req.remove_source {
         // For copy operations, check if destination has enough space
         if let Some(parent) = dest_path.parent() {
-            #[cfg(windows)]
-            {
-                use std::os::windows::ffi::OsStrExt;
-                use windows::core::PCWSTR;
-                use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
-                
-                let root_path = get_volume_root(parent);
-                let w: Vec<u16> = std::ffi::OsStr::new(&root_path)
-                    .encode_wide()
-                    .chain(std::iter::once(0))
-                    .collect();
-                
-                unsafe {
-                    let mut free_bytes_available = 0u64;
-                    if GetDiskFreeSpaceExW(
-                        PCWSTR(w.as_ptr()),
-                        Some(&mut free_bytes_available),
-                        None,
-                        None,
-                    ).is_ok() {
-                        // Add 10% buffer for safety
-                        let required = bytes_to_transfer + (bytes_to_transfer / 10);
-                        if free_bytes_available < required {
-                            return Err(AppError::BadRequest(format!(
-                                "Insufficient disk space: {} available, {} required",
-                                free_bytes_available, required
-                            )));
-                        }
-                    }
-                }
-            }
-            #[cfg(unix)]
-            {
-                // Unix disk space check could be added here using statvfs
-                tracing::debug!("Disk space check not implemented on Unix");
-            }
+            ensure_free_space(parent, bytes_to_transfer)?;
         }
     }
 
-    let bytes_moved = if metadata.is_file() {
-        move_file(&source_path, &dest_path, req, cancel)?
+    if metadata.is_file() {
+        move_file(&source_path, &dest_path, req, progress)?;
     } else if metadata.is_dir() {
-        move_directory(&source_path, &dest_path, req, &mut warnings, cancel)?
+        move_directory(&source_path, &dest_path, req, warnings, progress)?;
     } else {
         return Err(AppError::BadRequest("source must refer to a file or directory".into()));
-    };
+    }
 
     // FIX Bug #8: Correctly calculate freed bytes.
-    let freed_bytes = if req.remove_source && !source_path.exists() { 
-        bytes_to_transfer 
-    } else { 
-        0 
-    };
+    if req.remove_source && !source_path.exists() {
+        progress.add_freed(bytes_to_transfer);
+    }
+    Ok(())
+}
+
+/// Fails if the volume of `dir` has less than `bytes` plus a 10% buffer free.
+///
+/// Only checked on Windows; elsewhere the check always passes.
+fn ensure_free_space(dir: &Path, bytes: u64) -> AppResult<()> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use windows::core::PCWSTR;
+        use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
 
-    Ok(MoveOutcome { bytes_to_transfer, bytes_moved, freed_bytes, warnings })
+        let root_path = get_volume_root(dir);
+        let w: Vec<u16> = std::ffi::OsStr::new(&root_path)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        unsafe {
+            let mut free_bytes_available = 0u64;
+            if GetDiskFreeSpaceExW(
+                PCWSTR(w.as_ptr()),
+                Some(&mut free_bytes_available),
+                None,
+                None,
+            ).is_ok() {
+                // Add 10% buffer for safety
+                let required = bytes + (bytes / 10);
+                if free_bytes_available < required {
+                    return Err(AppError::BadRequest(format!(
+                        "Insufficient disk space: {} available, {} required",
+                        free_bytes_available, required
+                    )));
+                }
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        // Unix disk space check could be added here using statvfs
+        let _ = (dir, bytes);
+        tracing::debug!("Disk space check not implemented on this platform");
+    }
+    Ok(())
 }
 
-fn move_file(source: &Path, destination: &Path, req: &MovePathRequest, cancel: &CancellationToken) -> AppResult<u64> {
-    if cancel.is_cancelled() {
+fn move_file(
+    source: &Path,
+    destination: &Path,
+    req: &MovePathRequest,
+    progress: &MoveProgress,
+) -> AppResult<()> {
+    if progress.is_cancelled() {
         return Err(AppError::Internal(anyhow!("Operation cancelled")));
     }
     if destination.exists() {
@@ -335,7 +574,10 @@ fn move_file(source: &Path, destination: &Path, req: &MovePathRequest, cancel: &
 
     if req.remove_source {
         match fs::rename(source, destination) {
-            Ok(_) => return Ok(fs::metadata(destination)?.len()),
+            Ok(_) => {
+                progress.add_moved(fs::metadata(destination)?.len());
+                return Ok(());
+            }
             Err(err) => {
                 // FIX Bug #2: On Cross-device link error, fall back to copy.
                 // Other errors should be propagated unless we want to retry.
@@ -354,7 +596,11 @@ fn move_file(source: &Path, destination: &Path, req: &MovePathRequest, cancel: &
                      return Err(AppError::Conflict(format!("destination file already exists: {}", destination.display())));
                 }
 
-                let copied = copy_file(source, destination, cancel)?;
+                copy_file(source, destination, progress)?;
+                // A cancelled move never removes its source, even if the last copy finished
+                if progress.is_cancelled() {
+                    return Err(AppError::Internal(anyhow!("Operation cancelled")));
+                }
                 // FIX Bug #8: Handle partial failure (copy success, delete fail)
                 if let Err(e) = fs::remove_file(source) {
 
@@ -363,12 +609,12 @@ fn move_file(source: &Path, destination: &Path, req: &MovePathRequest, cancel: &
                     // Ideally we should warn the user, but we can't easily propagate warnings from here
                     // without changing the signature. For now, logging must suffice.
                 }
-                return Ok(copied);
+                return Ok(());
             }
         }
     }
 
-    copy_file(source, destination, cancel)
+    copy_file(source, destination, progress)
 }
 
 fn move_directory(
@@ -376,8 +622,11 @@ fn move_directory(
     destination: &Path,
     req: &MovePathRequest,
     warnings: &mut Vec<String>,
-    cancel: &CancellationToken,
-) -> AppResult<u64> {
+    progress: &MoveProgress,
+) -> AppResult<()> {
+    if progress.is_cancelled() {
+        return Err(AppError::Internal(anyhow!("Operation cancelled")));
+    }
     if destination.exists() {
         let dest_meta = fs::metadata(destination)?;
         if !dest_meta.is_dir() {
@@ -398,34 +647,42 @@ fn move_directory(
 
     if req.remove_source {
         match fs::rename(source, destination) {
-            Ok(_) => return compute_directory_size(destination, warnings),
+            Ok(_) => {
+                progress.add_moved(compute_directory_size(destination, warnings)?);
+                return Ok(());
+            }
             Err(err) => {
                 tracing::info!(
                     "Rename failed for directory {} ({}), falling back to copy",
                     source.display(),
                     err.kind()
                 );
-                let bytes = copy_directory(source, destination, req.overwrite, req.remove_source, warnings, cancel)?;
+                copy_directory(source, destination, req.overwrite, req.remove_source, warnings, progress)?;
+                // A cancelled move never removes its source, even if the last copy finished
+                if progress.is_cancelled() {
+                    return Err(AppError::Internal(anyhow!("Operation cancelled")));
+                }
                 // FIX Bug #8: Handle partial failure (copy success, delete fail)
                 if let Err(e) = fs::remove_dir_all(source) {
                     let msg = format!("Warnung: Quellordner konnte nach Verschieben nicht gelöscht werden: {}", e);
                     tracing::warn!("{}", msg);
                     warnings.push(msg);
                 }
-                return Ok(bytes);
+                return Ok(());
             }
         }
     }
 
-    copy_directory(source, destination, req.overwrite, req.remove_source, warnings, cancel)
+    copy_directory(source, destination, req.overwrite, req.remove_source, warnings, progress)
 }
 
-fn copy_file(source: &Path, destination: &Path, cancel: &CancellationToken) -> AppResult<u64> {
-    if cancel.is_cancelled() {
+fn copy_file(source: &Path, destination: &Path, progress: &MoveProgress) -> AppResult<()> {
+    if progress.is_cancelled() {
         return Err(AppError::Internal(anyhow!("Operation cancelled")));
     }
     let bytes = fs::copy(source, destination)?;
-    Ok(bytes)
+    progress.add_moved(bytes);
+    Ok(())
 }
 
 fn copy_directory(
@@ -434,10 +691,10 @@ fn copy_directory(
     overwrite: bool,
     remove_source: bool,
     warnings: &mut Vec<String>,
-    cancel: &CancellationToken,
-) -> AppResult<u64> {
+    progress: &MoveProgress,
+) -> AppResult<()> {
     // FIX Bug #6: Check cancellation
-    if cancel.is_cancelled() {
+    if progress.is_cancelled() {
         return Err(AppError::Internal(anyhow!("Operation cancelled")));
     }
     // FIX Bug #35: Log errors during rollback instead of silently ignoring
@@ -474,10 +731,9 @@ fn copy_directory(
                 continue;
             }
         };
-        if cancel.is_cancelled() {
-             // FIX Bug #5: Always rollback partial copies on cancellation to prevent garbage
-             rollback_partial(&created_files, &created_dirs);
-             return Err(AppError::Internal(anyhow!("Operation cancelled")));
+        if progress.is_cancelled() {
+            // Cancellation stops between files; what was copied so far stays in place
+            return Err(AppError::Internal(anyhow!("Operation cancelled")));
         }
         let rel = match entry.path().strip_prefix(source) {
             Ok(r) => r,
@@ -570,6 +826,7 @@ fn copy_directory(
         match fs::copy(entry.path(), &target) {
             Ok(bytes) => {
                 bytes_copied += bytes;
+                progress.add_moved(bytes);
                 created_files.push(target.clone());
             }
             Err(e) => {
@@ -605,7 +862,7 @@ fn copy_directory(
         }
     }
 
-    Ok(bytes_copied)
+    Ok(())
 }
 
 fn compute_directory_size(path: &Path, warnings: &mut Vec<String>) -> AppResult<u64> {
@@ -631,3 +888,937 @@ fn compute_directory_size(path: &Path, warnings: &mut Vec<String>) -> AppResult<
     }
     Ok(total)
 }
+
+/// Packs a file or directory into a zip or `tar.zst` archive.
+///
+/// The source is validated and sized up front; the archive itself is written
+/// in the background with bounded memory. The response (`202 Accepted`) holds
+/// the operation with its `op_id`; progress and the final `bytes_written` and
+/// `freed_bytes` are read from `GET /paths/operations/{op_id}`. With
+/// `remove_source` the source is deleted once the archive is complete.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+/// * `req` - The archive request payload.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing the started `PathOperation`.
+pub async fn archive_path(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<ArchivePathRequest>,
+) -> AppResult<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/paths/archive", ip).await {
+        return Ok(limited.into_response());
+    }
+
+    let (src_trimmed, dest_trimmed) = (req.source.trim(), req.destination.trim());
+    if src_trimmed.is_empty() {
+        return Err(AppError::BadRequest("source path must not be empty".into()));
+    }
+    if dest_trimmed.is_empty() {
+        return Err(AppError::BadRequest("destination path must not be empty".into()));
+    }
+    let source_valid = match validate_file_path(src_trimmed) {
+        Ok(path) => path,
+        Err((status, body)) => return Ok((status, body).into_response()),
+    };
+    let dest_valid = match validate_file_path(dest_trimmed) {
+        Ok(path) => path,
+        Err((status, body)) => return Ok((status, body).into_response()),
+    };
+    if source_valid.eq_ignore_ascii_case(&dest_valid) {
+        return Err(AppError::BadRequest("source and destination must be different".into()));
+    }
+
+    let mut job_req = req.clone();
+    job_req.source = source_valid;
+    job_req.destination = dest_valid;
+    let sources = job_req.remove_source.then_some(job_req.source.as_str());
+    ensure_writable(&state, std::iter::once(job_req.destination.as_str()).chain(sources)).await?;
+    tracing::info!(
+        "Archive request: {} -> {} ({:?}, remove_source={}, overwrite={})",
+        sanitize_for_logging(&job_req.source),
+        sanitize_for_logging(&job_req.destination),
+        job_req.format,
+        job_req.remove_source,
+        job_req.overwrite
+    );
+
+    let check_req = job_req.clone();
+    let (bytes_to_transfer, warnings) = spawn_blocking(move || prepare_archive(&check_req))
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("archive check join error: {}", e)))??;
+
+    let progress = Arc::new(ArchiveProgress::new(Uuid::new_v4(), job_req, bytes_to_transfer, warnings));
+    let op_id = progress.op_id();
+    state.operations.write().await.insert(op_id, PathOperationHandle::Archive(progress.clone()));
+    let snapshot = progress.snapshot();
+
+    let operations = state.operations.clone();
+    tokio::spawn(async move {
+        let worker = progress.clone();
+        let result = match spawn_blocking(move || run_archive(&worker)).await {
+            Ok(Ok(outcome)) => Ok(outcome),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(e) => Err(format!("archive task join error: {}", e)),
+        };
+        if let Err(e) = &result {
+            tracing::error!("Archive operation {} failed: {}", op_id, e);
+        }
+        progress.finish(result);
+        retire_operation(operations, op_id).await;
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(PathOperation::Archive(snapshot))).into_response())
+}
+
+/// Checks an archive request and returns the size of the source.
+fn prepare_archive(req: &ArchivePathRequest) -> AppResult<(u64, Vec<String>)> {
+    let source_path = PathBuf::from(&req.source);
+    if !source_path.exists() {
+        return Err(AppError::NotFound(format!("source path does not exist: {}", req.source)));
+    }
+    let dest_path = PathBuf::from(&req.destination);
+    if dest_path.starts_with(&source_path) {
+        return Err(AppError::BadRequest("destination cannot be inside the source path".into()));
+    }
+    if dest_path.exists() {
+        if fs::metadata(&dest_path)?.is_dir() {
+            return Err(AppError::Conflict(format!(
+                "destination refers to a directory: {}",
+                dest_path.display()
+            )));
+        }
+        if !req.overwrite {
+            return Err(AppError::Conflict(format!(
+                "destination file already exists: {}",
+                dest_path.display()
+            )));
+        }
+    }
+    let Some(parent) = dest_path.parent() else {
+        return Err(AppError::BadRequest("destination path must include a parent directory".into()));
+    };
+    fs::create_dir_all(parent)?;
+
+    let metadata = fs::symlink_metadata(&source_path)?;
+    let mut warnings = Vec::new();
+    let bytes_to_transfer =
+        if metadata.is_dir() { compute_directory_size(&source_path, &mut warnings)? } else { metadata.len() };
+    // Compression usually shrinks the data, but incompressible files can't be ruled out
+    ensure_free_space(parent, bytes_to_transfer)?;
+    Ok((bytes_to_transfer, warnings))
+}
+
+/// Lists the running and recently finished background path operations.
+///
+/// Operations are kept for an hour after they finish. The newest operation
+/// comes first.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON array of `PathOperation`s.
+pub async fn list_operations(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+    let mut items: Vec<PathOperation> =
+        state.operations.read().await.values().map(PathOperationHandle::snapshot).collect();
+    // RFC 3339 timestamps in UTC sort chronologically as strings
+    items.sort_by(|a, b| operation_started_at(b).cmp(operation_started_at(a)));
+    Ok(Json(items))
+}
+
+fn operation_started_at(op: &PathOperation) -> &str {
+    match op {
+        PathOperation::Move(m) => &m.started_at,
+        PathOperation::Archive(a) => &a.started_at,
+    }
+}
+
+/// Returns the progress or result of a background path operation.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `op_id` - The ID returned when the operation was started.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing the `PathOperation`.
+pub async fn get_operation(
+    State(state): State<AppState>,
+    UrlPath(op_id): UrlPath<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    match state.operations.read().await.get(&op_id) {
+        Some(handle) => Ok(Json(handle.snapshot())),
+        None => Err(AppError::NotFound("operation not found".into())),
+    }
+}
+
+/// Requests cancellation of a running move operation.
+///
+/// The move stops before its next file. Files that were already copied stay at
+/// the destination, and the source of the interrupted item is never deleted.
+/// Archive operations cannot be cancelled.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `op_id` - The ID returned when the operation was started.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - `202 Accepted` with the `PathOperation`, or its
+///   final state if it had already finished.
+pub async fn cancel_operation(
+    State(state): State<AppState>,
+    UrlPath(op_id): UrlPath<Uuid>,
+) -> AppResult<Response> {
+    let handle = match state.operations.read().await.get(&op_id) {
+        Some(handle) => handle.clone(),
+        None => return Err(AppError::NotFound("operation not found".into())),
+    };
+    match &handle {
+        PathOperationHandle::Move(progress) => {
+            let snapshot = progress.snapshot();
+            if snapshot.finished_at.is_some() {
+                return Ok((StatusCode::OK, Json(PathOperation::Move(snapshot))).into_response());
+            }
+            tracing::info!("Cancelling move operation {}", op_id);
+            progress.cancel();
+            Ok((StatusCode::ACCEPTED, Json(handle.snapshot())).into_response())
+        }
+        PathOperationHandle::Archive(_) => {
+            Err(AppError::Conflict("archive operations cannot be cancelled".into()))
+        }
+    }
+}
+
+/// Deletes files or directories, by default into the recycle bin.
+///
+/// Each path is processed independently; failures are reported per item and
+/// don't stop the remaining paths. With `mode=recycle` an item that the system
+/// deleted permanently instead (e.g. a volume without recycle bin) is reported
+/// as `deleted` with a message. Volume roots and top-level folders such as
+/// `C:\Windows` or `/home` are never deleted.
+///
+/// With `dry_run` nothing is deleted; every existing item is reported as
+/// `would_delete` with the number of files and bytes that would be removed.
+/// Reparse points and symlinks count as single entries without their targets,
+/// as in the scanner, and hidden or system files are counted separately.
+///
+/// If `scan_id` is given, the rows of every removed path and its subtree are
+/// deleted from that scan and the sizes of its ancestor directories and of the
+/// scan are reduced, so the scan reflects the deletion without a rescan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request; `scan_id` must be visible from it.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+/// * `req` - The delete path request payload.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `DeletePathResponse`.
+pub async fn delete_path(
+    State(state): State<AppState>,
+    ns: Namespace,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<DeletePathRequest>,
+) -> AppResult<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/paths/delete", ip).await {
+        return Ok(limited.into_response());
+    }
+
+    if req.paths.is_empty() {
+        return Err(AppError::BadRequest("paths array must not be empty".into()));
+    }
+    if req.mode == DeleteMode::Recycle && !paths_recycle::RECYCLE_SUPPORTED {
+        return Err(AppError::BadRequest(
+            "mode=recycle is not supported on this platform; use mode=permanent".into(),
+        ));
+    }
+    let mut valid_paths = Vec::with_capacity(req.paths.len());
+    for p in &req.paths {
+        let valid = match validate_file_path(p) {
+            Ok(path) => path,
+            Err((status, body)) => return Ok((status, body).into_response()),
+        };
+        if is_volume_root(Path::new(&valid)) {
+            return Err(AppError::BadRequest(format!("refusing to delete volume root: {}", valid)));
+        }
+        if path_depth(Path::new(&valid)) < MIN_DELETE_DEPTH {
+            return Err(AppError::BadRequest(format!("refusing to delete top-level folder: {}", valid)));
+        }
+        valid_paths.push(valid);
+    }
+    ensure_writable(&state, valid_paths.iter().map(String::as_str)).await?;
+    if let Some(scan_id) = req.scan_id {
+        ns.ensure_scan(&state.db, scan_id).await?;
+    }
+
+    tracing::info!(
+        "Delete request: {} items (mode={:?}, dry_run={}): {}",
+        valid_paths.len(),
+        req.mode,
+        req.dry_run,
+        sanitize_for_logging(&valid_paths.join(", "))
+    );
+    let started_instant = Instant::now();
+    let (mode, dry_run) = (req.mode, req.dry_run);
+    let job_paths = valid_paths.clone();
+    let items = spawn_blocking(move || perform_deletes(&job_paths, mode, dry_run))
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("delete task join error: {}", e)))?;
+
+    let mut scan_updated = false;
+    if let (Some(scan_id), false) = (req.scan_id, dry_run) {
+        for item in items.iter().filter(|i| i.status != DeleteStatus::Failed) {
+            scan_updated |= remove_path_from_scan(&state.db, scan_id, &item.path).await?;
+        }
+    }
+
+    let removed = items.iter().filter(|i| i.status != DeleteStatus::Failed);
+    let response = DeletePathResponse {
+        mode,
+        dry_run,
+        removed_bytes: removed.clone().map(|i| i.bytes).sum(),
+        freed_bytes: removed.clone().filter(|i| i.status == DeleteStatus::Deleted).map(|i| i.bytes).sum(),
+        files: removed.map(|i| i.files).sum(),
+        items,
+        scan_updated,
+        duration_ms: started_instant.elapsed().as_millis(),
+    };
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// Returns whether a path is a filesystem or volume root (`/`, `C:\`, `\\server\share`).
+fn is_volume_root(path: &Path) -> bool {
+    path.parent().is_none()
+}
+
+/// The minimum number of named components below the volume root of a path to delete.
+const MIN_DELETE_DEPTH: usize = 2;
+
+/// Returns the number of named components of a path (`C:\Users\me` has 2).
+fn path_depth(path: &Path) -> usize {
+    path.components().filter(|c| matches!(c, std::path::Component::Normal(_))).count()
+}
+
+/// What a deletion removes: files, hidden or system files among them, and bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct DeleteFootprint {
+    files: u64,
+    hidden_files: u64,
+    bytes: u64,
+}
+
+fn perform_deletes(paths: &[String], mode: DeleteMode, dry_run: bool) -> Vec<DeletedPath> {
+    paths
+        .iter()
+        .map(|p| {
+            let path = Path::new(p);
+            let (status, footprint, message) = match delete_single(path, mode, dry_run) {
+                Ok((_, footprint)) if dry_run => (DeleteStatus::WouldDelete, footprint, None),
+                Ok((recycled, footprint)) => match (mode, recycled) {
+                    (DeleteMode::Recycle, true) => (DeleteStatus::Recycled, footprint, None),
+                    (DeleteMode::Recycle, false) => (
+                        DeleteStatus::Deleted,
+                        footprint,
+                        Some("the system bypassed the recycle bin; the item was deleted permanently".to_string()),
+                    ),
+                    (DeleteMode::Permanent, _) => (DeleteStatus::Deleted, footprint, None),
+                },
+                Err(e) => {
+                    tracing::warn!("Failed to delete {}: {}", p, e);
+                    (DeleteStatus::Failed, DeleteFootprint::default(), Some(e.to_string()))
+                }
+            };
+            DeletedPath {
+                path: p.clone(),
+                status,
+                bytes: footprint.bytes,
+                files: footprint.files,
+                hidden_files: footprint.hidden_files,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Walks an item the way the scanner does and sums up what deleting it removes.
+///
+/// Symlinks and reparse points are counted as entries of their own; their
+/// targets are not entered, since deleting a link never touches the target.
+fn delete_footprint(path: &Path, metadata: &fs::Metadata) -> DeleteFootprint {
+    let mut footprint = DeleteFootprint::default();
+    let mut count = |p: &Path, md: &fs::Metadata| {
+        if md.is_file() {
+            footprint.files += 1;
+            footprint.bytes += md.len();
+            if is_hidden_or_system(p, md) {
+                footprint.hidden_files += 1;
+            }
+        }
+    };
+    if !metadata.is_dir() || is_reparse_point(metadata) {
+        count(path, metadata);
+        return footprint;
+    }
+    let mut walker = WalkDir::new(path).follow_links(false).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        let Ok(md) = entry.metadata() else { continue };
+        if entry.depth() > 0 && md.is_dir() && is_reparse_point(&md) {
+            walker.skip_current_dir();
+            continue;
+        }
+        count(entry.path(), &md);
+    }
+    footprint
+}
+
+/// Deletes one item and returns whether it was recycled and what it removed.
+///
+/// With `dry_run` the item is only measured.
+fn delete_single(path: &Path, mode: DeleteMode, dry_run: bool) -> AppResult<(bool, DeleteFootprint)> {
+    // symlink_metadata: a link is deleted itself, never its target
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(AppError::NotFound(format!("path does not exist: {}", path.display())));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let footprint = delete_footprint(path, &metadata);
+    if dry_run {
+        return Ok((false, footprint));
+    }
+
+    match mode {
+        DeleteMode::Recycle => {
+            let recycled = paths_recycle::recycle(path)?;
+            Ok((recycled, footprint))
+        }
+        DeleteMode::Permanent => {
+            if metadata.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else if let Err(e) = fs::remove_file(path) {
+                // Directory symlinks and junctions on Windows are removed like directories
+                if !(cfg!(windows) && metadata.file_type().is_symlink()) {
+                    return Err(e.into());
+                }
+                fs::remove_dir(path)?;
+            }
+            Ok((false, footprint))
+        }
+    }
+}
+
+/// Removes a deleted path and its subtree from a scan.
+///
+/// The sizes and counts of the removed entry are subtracted from all ancestor
+/// directories and from the scan totals.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `scan_id` - The ID of the scan.
+/// * `path` - The deleted path as stored by the scan.
+///
+/// # Returns
+///
+/// * `AppResult<bool>` - `false` if the scan doesn't contain the path.
+pub(crate) async fn remove_path_from_scan(pool: &sqlx::SqlitePool, scan_id: uuid::Uuid, path: &str) -> AppResult<bool> {
+    use sqlx::Row;
+
+    let id = scan_id.to_string();
+    let mut tx = pool.begin().await?;
+    let dir = sqlx::query(
+        "SELECT logical_size, allocated_size, file_count, dir_count FROM nodes WHERE scan_id=?1 AND path=?2 AND is_dir=1",
+    )
+    .bind(&id)
+    .bind(path)
+    .fetch_optional(&mut *tx)
+    .await?;
+    // (logical, allocated, files, dirs) removed from every ancestor
+    let removed: (i64, i64, i64, i64) = match dir {
+        Some(r) => (
+            r.get("logical_size"),
+            r.get("allocated_size"),
+            r.get("file_count"),
+            r.get::<i64, _>("dir_count") + 1,
+        ),
+        None => {
+            let file = sqlx::query("SELECT logical_size, allocated_size FROM files WHERE scan_id=?1 AND path=?2")
+                .bind(&id)
+                .bind(path)
+                .fetch_optional(&mut *tx)
+                .await?;
+            match file {
+                Some(r) => (r.get("logical_size"), r.get("allocated_size"), 1, 0),
+                None => return Ok(false),
+            }
+        }
+    };
+
+    let pattern = subtree_like_pattern(path);
+    for table in ["nodes", "files", "owner_usage"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE scan_id=?1 AND (path=?2 OR path LIKE ?3 ESCAPE '!')", table))
+            .bind(&id)
+            .bind(path)
+            .bind(&pattern)
+            .execute(&mut *tx)
+            .await?;
+    }
+    // The parent lost a child, so its stored fingerprint is stale
+    if let Some(parent) = Path::new(path).parent() {
+        let parent = parent.to_string_lossy();
+        crate::scanner::watch::clear_fingerprint(&mut tx, &id, &parent).await?;
+        // A removed file (no directory count) of zero bytes was one of the parent's empty files
+        if removed.3 == 0 && removed.0 == 0 {
+            crate::scanner::watch::add_empty_files(&mut tx, &id, &parent, -1).await?;
+        }
+        refresh_usage(&mut tx, &id, UsageScope::Dir(&parent)).await?;
+    }
+    for ancestor in Path::new(path).ancestors().skip(1) {
+        sqlx::query(
+            r#"UPDATE nodes SET logical_size = MAX(logical_size - ?1, 0), allocated_size = MAX(allocated_size - ?2, 0),
+                   file_count = MAX(file_count - ?3, 0), dir_count = MAX(dir_count - ?4, 0)
+               WHERE scan_id=?5 AND path=?6 AND is_dir=1"#,
+        )
+        .bind(removed.0)
+        .bind(removed.1)
+        .bind(removed.2)
+        .bind(removed.3)
+        .bind(&id)
+        .bind(ancestor.to_string_lossy().to_string())
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query(
+        r#"UPDATE scans SET total_logical_size = MAX(COALESCE(total_logical_size, 0) - ?1, 0),
+               total_allocated_size = MAX(COALESCE(total_allocated_size, 0) - ?2, 0),
+               file_count = MAX(COALESCE(file_count, 0) - ?3, 0), dir_count = MAX(COALESCE(dir_count, 0) - ?4, 0)
+           WHERE id=?5"#,
+    )
+    .bind(removed.0)
+    .bind(removed.1)
+    .bind(removed.2)
+    .bind(removed.3)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_file, insert_node, test_db_in, FileRow, NodeRow};
+    use crate::types::MoveBatchItem;
+
+    async fn test_state(dir: &Path) -> AppState {
+        let pool = test_db_in(dir).await;
+        AppState::new(pool, AppConfig::default())
+    }
+
+    async fn delete(state: &AppState, req: DeletePathRequest) -> AppResult<Response> {
+        delete_path(State(state.clone()), Namespace::default(), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(req)).await
+    }
+
+    async fn body(resp: Response) -> DeletePathResponse {
+        use http_body_util::BodyExt;
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn request(paths: Vec<String>, mode: DeleteMode) -> DeletePathRequest {
+        DeletePathRequest { paths, mode, scan_id: None, dry_run: false }
+    }
+
+    #[tokio::test]
+    async fn delete_request_is_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let res = delete(&state, request(vec![], DeleteMode::Permanent)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let root = if cfg!(windows) { "C:\\" } else { "/" };
+        let res = delete(&state, request(vec![root.into()], DeleteMode::Permanent)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let top = if cfg!(windows) { "C:\\Windows" } else { "/home" };
+        let res = delete(&state, request(vec![top.into()], DeleteMode::Permanent)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let res = delete(&state, request(vec!["a/../b".into()], DeleteMode::Permanent)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let mode: DeletePathRequest = serde_json::from_str(r#"{"paths":["x"]}"#).unwrap();
+        assert_eq!(mode.mode, DeleteMode::Recycle);
+    }
+
+    #[tokio::test]
+    async fn dry_run_counts_files_without_deleting() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let root = dir.path().join("old");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub").join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("b.bin"), vec![0u8; 20]).unwrap();
+        #[cfg(unix)]
+        {
+            fs::write(root.join(".hidden"), vec![0u8; 5]).unwrap();
+            std::os::unix::fs::symlink(dir.path(), root.join("link")).unwrap();
+        }
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+
+        let mut req = request(vec![root.to_string_lossy().to_string(), missing], DeleteMode::Permanent);
+        req.dry_run = true;
+        let resp = body(delete(&state, req).await.unwrap()).await;
+        assert!(resp.dry_run);
+        assert!(root.join("b.bin").exists());
+        assert_eq!(resp.items[0].status, DeleteStatus::WouldDelete);
+        assert_eq!(resp.items[1].status, DeleteStatus::Failed);
+        let (files, hidden, bytes) = if cfg!(unix) { (3, 1, 125) } else { (2, 0, 120) };
+        let item = &resp.items[0];
+        assert_eq!((item.files, item.hidden_files, item.bytes), (files, hidden, bytes));
+        assert_eq!((resp.files, resp.removed_bytes, resp.freed_bytes), (files, bytes, 0));
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    #[tokio::test]
+    async fn recycle_mode_is_rejected_without_support() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let file = dir.path().join("keep.txt");
+        fs::write(&file, b"data").unwrap();
+        let res = delete(&state, request(vec![file.to_string_lossy().to_string()], DeleteMode::Recycle)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        assert!(file.exists());
+    }
+
+    #[tokio::test]
+    async fn permanent_delete_updates_scan_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let root = dir.path().join("data");
+        let sub = root.join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(sub.join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("b.bin"), vec![0u8; 50]).unwrap();
+
+        let scan_id = uuid::Uuid::new_v4();
+        let (root_s, sub_s) = (root.to_string_lossy().to_string(), sub.to_string_lossy().to_string());
+        sqlx::query(
+            r#"INSERT INTO scans (id, status, root_paths, options, total_logical_size, total_allocated_size, dir_count, file_count)
+               VALUES (?1, 'done', '[]', '{}', 150, 150, 2, 2)"#,
+        )
+        .bind(scan_id.to_string())
+        .execute(&state.db)
+        .await
+        .unwrap();
+        for (path, parent, size, files, dirs) in
+            [(&root_s, None, 150, 2, 1), (&sub_s, Some(&root_s), 100, 1, 0)]
+        {
+            let parent = parent.map(String::as_str);
+            let node = NodeRow { path, parent, size, files, dirs, ..Default::default() };
+            insert_node(&state.db, scan_id, node).await;
+        }
+        let a_bin = sub.join("a.bin").to_string_lossy().to_string();
+        let a_bin = FileRow { path: &a_bin, parent: Some(&sub_s), size: 100, ..Default::default() };
+        insert_file(&state.db, scan_id, a_bin).await;
+
+        let missing = root.join("missing").to_string_lossy().to_string();
+        let req = DeletePathRequest {
+            paths: vec![sub_s.clone(), missing],
+            mode: DeleteMode::Permanent,
+            scan_id: Some(scan_id),
+            dry_run: false,
+        };
+        let resp = body(delete(&state, req).await.unwrap()).await;
+        assert!(!sub.exists());
+        assert!(root.join("b.bin").exists());
+        assert_eq!(resp.items[0].status, DeleteStatus::Deleted);
+        assert_eq!(resp.items[0].bytes, 100);
+        assert_eq!(resp.items[1].status, DeleteStatus::Failed);
+        assert_eq!(resp.removed_bytes, 100);
+        assert_eq!((resp.freed_bytes, resp.files), (100, 1));
+        assert!(resp.scan_updated);
+
+        let (logical, files, dirs): (i64, i64, i64) =
+            sqlx::query_as("SELECT logical_size, file_count, dir_count FROM nodes WHERE scan_id=?1 AND path=?2")
+                .bind(scan_id.to_string())
+                .bind(&root_s)
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+        assert_eq!((logical, files, dirs), (50, 1, 0));
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+            .bind(scan_id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+        let total: i64 = sqlx::query_scalar("SELECT total_logical_size FROM scans WHERE id=?1")
+            .bind(scan_id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(total, 50);
+    }
+
+    async fn operation(state: &AppState, op_id: Uuid) -> PathOperation {
+        use http_body_util::BodyExt;
+        let resp = get_operation(State(state.clone()), UrlPath(op_id)).await.unwrap().into_response();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn move_runs_as_a_pollable_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+        std::fs::write(src.join("sub").join("a.bin"), vec![1u8; 300]).unwrap();
+        std::fs::write(src.join("b.bin"), vec![2u8; 200]).unwrap();
+        let dest = dir.path().join("dest").join("src");
+        let req = MovePathRequest {
+            sources: vec![src.to_string_lossy().to_string()],
+            destinations: vec![dest.to_string_lossy().to_string()],
+            remove_source: false,
+            overwrite: false,
+        };
+
+        let resp =
+            move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(req)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        use http_body_util::BodyExt;
+        let started: PathOperation =
+            serde_json::from_slice(&resp.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        let PathOperation::Move(started) = started else { panic!("expected a move operation") };
+
+        let mut op = operation(&state, started.op_id).await;
+        for _ in 0..200 {
+            if matches!(&op, PathOperation::Move(m) if m.finished_at.is_some()) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            op = operation(&state, started.op_id).await;
+        }
+        let PathOperation::Move(op) = op else { panic!("expected a move operation") };
+        assert_eq!(op.status, "completed");
+        assert_eq!((op.bytes_to_transfer, op.bytes_moved, op.items_done), (500, 500, 1));
+        assert_eq!(std::fs::read(dest.join("b.bin")).unwrap().len(), 200);
+        assert!(src.exists());
+
+        let resp = list_operations(State(state.clone())).await.unwrap().into_response();
+        let listed: Vec<PathOperation> =
+            serde_json::from_slice(&resp.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        assert_eq!(listed.len(), 1);
+        let res = get_operation(State(state.clone()), UrlPath(Uuid::new_v4())).await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn moves_within_a_read_only_root_are_rejected_until_the_scan_is_purged() {
+        use axum::extract::Query;
+
+        use crate::routes::scans::{cancel_scan, CancelQuery};
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let root = dir.path().join("snapshot");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.bin"), vec![1u8; 10]).unwrap();
+        // Shares the root's name as a prefix but lies outside it
+        let sibling = dir.path().join("snapshot2");
+        std::fs::create_dir_all(&sibling).unwrap();
+        std::fs::write(sibling.join("b.bin"), vec![2u8; 10]).unwrap();
+        let scan_id = Uuid::new_v4();
+        let roots = serde_json::to_string(&[root.to_string_lossy()]).unwrap();
+        sqlx::query("INSERT INTO scans (id, status, root_paths, options, read_only) VALUES (?1, 'done', ?2, '{}', 1)")
+            .bind(scan_id.to_string())
+            .bind(roots)
+            .execute(&state.db)
+            .await
+            .unwrap();
+        let move_req = |src: &Path, dest: &Path| MovePathRequest {
+            sources: vec![src.to_string_lossy().to_string()],
+            destinations: vec![dest.to_string_lossy().to_string()],
+            remove_source: true,
+            overwrite: false,
+        };
+        let move_out = move_req(&root.join("a.bin"), &dir.path().join("a.bin"));
+
+        let res = move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(move_out.clone())).await;
+        let Err(err) = res else { panic!("move out of a read-only root must be rejected") };
+        assert!(err.to_string().contains("read-only scan"));
+        assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+        let move_in = move_req(&sibling.join("b.bin"), &root.join("b.bin"));
+        let res = move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(move_in)).await;
+        assert!(matches!(res, Err(AppError::Forbidden(_))));
+        let protected = root.join("a.bin").to_string_lossy().to_string();
+        let res = delete(&state, request(vec![protected], DeleteMode::Permanent)).await;
+        assert!(matches!(res, Err(AppError::Forbidden(_))));
+        let beside = move_req(&sibling.join("b.bin"), &sibling.join("c.bin"));
+        let resp = move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(beside)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let q = CancelQuery { purge: Some(true), finalize: None };
+        cancel_scan(State(state.clone()), Namespace::default(), UrlPath(scan_id), Query(q)).await.unwrap();
+        let resp = move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(move_out)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    }
+
+    async fn batch(state: &AppState, req: MoveBatchRequest) -> AppResult<Response> {
+        move_batch(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(req)).await
+    }
+
+    #[tokio::test]
+    async fn batch_moves_report_every_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let src = dir.path().join("src");
+        let dest = dir.path().join("dest");
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(src.join(name)).unwrap();
+            fs::write(src.join(name).join("f.bin"), vec![0u8; 100]).unwrap();
+        }
+        fs::create_dir_all(dest.join("b")).unwrap();
+        let s = |p: PathBuf| p.to_string_lossy().to_string();
+
+        // Overlapping items are rejected before anything moves
+        let overlapping = MoveBatchRequest {
+            sources: vec![s(src.clone()), s(src.join("a"))],
+            destination_dir: Some(s(dest.clone())),
+            ..Default::default()
+        };
+        assert!(matches!(batch(&state, overlapping).await, Err(AppError::BadRequest(_))));
+        let into_source = MoveBatchRequest {
+            items: vec![
+                MoveBatchItem { source: s(src.join("a")), destination: s(dest.join("a")) },
+                MoveBatchItem { source: s(src.join("b")), destination: s(src.join("a").join("b")) },
+            ],
+            ..Default::default()
+        };
+        assert!(matches!(batch(&state, into_source).await, Err(AppError::BadRequest(_))));
+        let no_dir = MoveBatchRequest { sources: vec![s(src.join("a"))], ..Default::default() };
+        assert!(matches!(batch(&state, no_dir).await, Err(AppError::BadRequest(_))));
+        assert!(src.join("a").exists());
+
+        let req = MoveBatchRequest {
+            sources: vec![s(src.join("a")), s(src.join("b")), s(src.join("missing")), s(src.join("c"))],
+            destination_dir: Some(s(dest.clone())),
+            remove_source: true,
+            ..Default::default()
+        };
+        let resp = batch(&state, req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        use http_body_util::BodyExt;
+        let started: PathOperation =
+            serde_json::from_slice(&resp.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        let PathOperation::Move(started) = started else { panic!("expected a move operation") };
+
+        let mut op = operation(&state, started.op_id).await;
+        for _ in 0..200 {
+            if matches!(&op, PathOperation::Move(m) if m.finished_at.is_some()) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            op = operation(&state, started.op_id).await;
+        }
+        let PathOperation::Move(op) = op else { panic!("expected a move operation") };
+        assert_eq!(op.status, "completed");
+        assert_eq!((op.items_done, op.items_total), (4, 4));
+        let statuses: Vec<_> = op.items.iter().map(|item| item.status).collect();
+        use MoveItemStatus::*;
+        assert_eq!(statuses, vec![Succeeded, Skipped, Skipped, Succeeded]);
+        assert_eq!(op.items[1].message.as_deref(), Some("destination already exists"));
+        assert_eq!(op.items[3].destination, s(dest.join("c")));
+        assert!(dest.join("a").join("f.bin").exists() && dest.join("c").join("f.bin").exists());
+        assert!(!src.join("a").exists());
+        assert!(src.join("b").join("f.bin").exists());
+    }
+
+    #[test]
+    fn stop_on_error_skips_the_remaining_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("b.bin"), b"b").unwrap();
+        let s = |p: PathBuf| p.to_string_lossy().to_string();
+        let req = MovePathRequest {
+            sources: vec![s(src.join("gone.bin")), s(src.join("b.bin"))],
+            destinations: vec![s(dir.path().join("gone.bin")), s(dir.path().join("b.bin"))],
+            remove_source: false,
+            overwrite: false,
+        };
+
+        let progress = MoveProgress::new(Uuid::new_v4(), req.clone()).with_stop_on_error(true);
+        perform_moves(&progress);
+        let op = progress.snapshot();
+        assert_eq!(op.items_done, 2);
+        assert_eq!((op.items[0].status, op.items[1].status), (MoveItemStatus::Failed, MoveItemStatus::Skipped));
+        assert!(!dir.path().join("b.bin").exists());
+
+        let progress = MoveProgress::new(Uuid::new_v4(), req);
+        perform_moves(&progress);
+        let op = progress.snapshot();
+        assert_eq!((op.items[0].status, op.items[1].status), (MoveItemStatus::Failed, MoveItemStatus::Succeeded));
+    }
+
+    #[test]
+    fn cancelled_move_keeps_copied_files_and_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.bin"), b"aaaa").unwrap();
+        let dest = dir.path().join("dest");
+        let progress = MoveProgress::new(
+            Uuid::new_v4(),
+            MovePathRequest {
+                sources: vec![src.to_string_lossy().to_string()],
+                destinations: vec![dest.join("src").to_string_lossy().to_string()],
+                remove_source: true,
+                overwrite: false,
+            },
+        );
+        progress.cancel();
+        perform_moves(&progress);
+        progress.finish(Ok(()));
+
+        let op = progress.snapshot();
+        assert_eq!(op.status, "cancelled");
+        assert_eq!((op.items_done, op.freed_bytes), (0, 0));
+        assert!(src.join("a.bin").exists());
+        assert!(!dest.exists());
+
+        // A cancellation that arrives during a copy leaves copied files in place
+        let target = dir.path().join("copy");
+        let mut warnings = Vec::new();
+        let running = MoveProgress::new(Uuid::new_v4(), progress.request().clone());
+        copy_directory(&src, &target, false, true, &mut warnings, &running).unwrap();
+        running.cancel();
+        let moved = dir.path().join("moved");
+        let res = move_directory(&src, &moved, progress.request(), &mut warnings, &running);
+        assert!(res.is_err());
+        assert!(target.join("a.bin").exists());
+        assert!(src.join("a.bin").exists());
+    }
+}
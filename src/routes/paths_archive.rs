@@ -0,0 +1,487 @@
+//! Packing files and directories into zip or zstd-compressed tar archives.
+//!
+//! Archives are streamed entry by entry, so memory use does not depend on the
+//! size of the source. They are written to a `.partial` file next to the
+//! destination and only renamed into place once complete, so a failed run never
+//! leaves a truncated archive under the requested name.
+//!
+//! Symlinks, junctions and unreadable entries are skipped with a warning. The
+//! source is only removed if nothing was skipped, since anything left out of
+//! the archive would otherwise be lost.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use chrono::{DateTime, Datelike, Local, Timelike, Utc};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::types::{ArchiveFormat, ArchiveOperation, ArchivePathRequest};
+
+/// The zstd level of `tar.zst` archives; favours speed over ratio.
+const ZSTD_LEVEL: i32 = 3;
+
+/// The result of a completed archive operation.
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveOutcome {
+    /// The size of the written archive.
+    pub bytes_written: u64,
+    /// The bytes freed by removing the source.
+    pub freed_bytes: u64,
+}
+
+/// The result of a finished operation with its end time and duration.
+type Finished = (DateTime<Utc>, u64, Result<ArchiveOutcome, String>);
+
+/// The progress of one archive operation, shared by the worker and status requests.
+pub struct ArchiveProgress {
+    op_id: Uuid,
+    request: ArchivePathRequest,
+    bytes_to_transfer: u64,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    bytes_processed: AtomicU64,
+    current_file: Mutex<Option<String>>,
+    warnings: Mutex<Vec<String>>,
+    finished: Mutex<Option<Finished>>,
+}
+
+impl ArchiveProgress {
+    /// Creates the progress of an operation that is about to start.
+    ///
+    /// # Arguments
+    ///
+    /// * `op_id` - The ID of the operation.
+    /// * `request` - The validated request.
+    /// * `bytes_to_transfer` - The total size of the files to archive.
+    /// * `warnings` - Warnings collected while sizing the source.
+    pub fn new(
+        op_id: Uuid,
+        request: ArchivePathRequest,
+        bytes_to_transfer: u64,
+        warnings: Vec<String>,
+    ) -> Self {
+        Self {
+            op_id,
+            request,
+            bytes_to_transfer,
+            started_at: Utc::now(),
+            started: Instant::now(),
+            bytes_processed: AtomicU64::new(0),
+            current_file: Mutex::new(None),
+            warnings: Mutex::new(warnings),
+            finished: Mutex::new(None),
+        }
+    }
+
+    /// Returns the ID of the operation.
+    pub fn op_id(&self) -> Uuid {
+        self.op_id
+    }
+
+    fn warn(&self, message: String) {
+        self.warnings.lock().unwrap_or_else(|e| e.into_inner()).push(message);
+    }
+
+    fn set_current_file(&self, path: Option<&Path>) {
+        *self.current_file.lock().unwrap_or_else(|e| e.into_inner()) =
+            path.map(|p| p.to_string_lossy().to_string());
+    }
+
+    /// Records the result of the operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The outcome, or the error message if the operation failed.
+    pub fn finish(&self, result: Result<ArchiveOutcome, String>) {
+        self.set_current_file(None);
+        let duration_ms = self.started.elapsed().as_millis() as u64;
+        *self.finished.lock().unwrap_or_else(|e| e.into_inner()) = Some((Utc::now(), duration_ms, result));
+    }
+
+    /// Returns the current state of the operation.
+    pub fn snapshot(&self) -> ArchiveOperation {
+        let finished = self.finished.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let (status, finished_at, duration_ms, outcome, error) = match finished {
+            None => ("running", None, self.started.elapsed().as_millis() as u64, None, None),
+            Some((at, ms, Ok(outcome))) => ("completed", Some(at), ms, Some(outcome), None),
+            Some((at, ms, Err(e))) => ("failed", Some(at), ms, None, Some(e)),
+        };
+        ArchiveOperation {
+            op_id: self.op_id,
+            status: status.to_string(),
+            source: self.request.source.clone(),
+            destination: self.request.destination.clone(),
+            format: self.request.format,
+            bytes_to_transfer: self.bytes_to_transfer,
+            bytes_processed: self.bytes_processed.load(Ordering::Relaxed),
+            current_file: self.current_file.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            bytes_written: outcome.map_or(0, |o| o.bytes_written),
+            freed_bytes: outcome.map_or(0, |o| o.freed_bytes),
+            duration_ms,
+            started_at: self.started_at.to_rfc3339(),
+            finished_at: finished_at.map(|at| at.to_rfc3339()),
+            warnings: self.warnings.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            error,
+        }
+    }
+}
+
+/// Writes the archive of an operation and removes the source if requested.
+///
+/// Blocks until done, so it must run on a blocking thread.
+///
+/// # Arguments
+///
+/// * `progress` - The operation, updated as entries are written.
+///
+/// # Returns
+///
+/// * `io::Result<ArchiveOutcome>` - The archive size and the freed bytes.
+pub fn run_archive(progress: &ArchiveProgress) -> io::Result<ArchiveOutcome> {
+    let source = PathBuf::from(&progress.request.source);
+    let destination = PathBuf::from(&progress.request.destination);
+    let mut partial = destination.clone().into_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let skipped = match write_archive(&source, &partial, progress.request.format, progress) {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    if let Err(e) = fs::rename(&partial, &destination) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    let bytes_written = fs::metadata(&destination)?.len();
+
+    let mut freed_bytes = 0;
+    if progress.request.remove_source {
+        if skipped > 0 {
+            progress.warn(format!("Quelle nicht gelöscht, {} Einträge fehlen im Archiv", skipped));
+        } else {
+            let removed = if fs::symlink_metadata(&source)?.is_dir() {
+                fs::remove_dir_all(&source)
+            } else {
+                fs::remove_file(&source)
+            };
+            match removed {
+                Ok(()) => freed_bytes = progress.bytes_to_transfer,
+                Err(e) => {
+                    progress.warn(format!("Quelle konnte nach dem Archivieren nicht gelöscht werden: {}", e))
+                }
+            }
+        }
+    }
+    Ok(ArchiveOutcome { bytes_written, freed_bytes })
+}
+
+/// Streams `source` into a new archive at `target`.
+///
+/// Entry names are relative to the parent of `source`, so the archive holds
+/// the source directory itself.
+///
+/// # Returns
+///
+/// * `io::Result<u64>` - The number of entries that were skipped.
+fn write_archive(
+    source: &Path,
+    target: &Path,
+    format: ArchiveFormat,
+    progress: &ArchiveProgress,
+) -> io::Result<u64> {
+    let base = source.parent().unwrap_or(source);
+    let mut sink = Sink::create(File::create(target)?, format)?;
+    let mut skipped = 0u64;
+
+    for entry in WalkDir::new(source).follow_links(false).sort_by_file_name() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                skipped += 1;
+                progress.warn(format!("Eintrag uebersprungen: {}", e));
+                continue;
+            }
+        };
+        let name = match entry_name(base, entry.path()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let md = match entry.metadata() {
+            Ok(md) => md,
+            Err(e) => {
+                skipped += 1;
+                progress.warn(format!("Eintrag uebersprungen: {}", e));
+                continue;
+            }
+        };
+        if entry.file_type().is_symlink() || is_reparse_point(&md) {
+            skipped += 1;
+            progress.warn(format!("Symlink uebersprungen (manuell pruefen): {}", entry.path().display()));
+            continue;
+        }
+        if md.is_dir() {
+            sink.add_dir(&name, &md)?;
+        } else if md.is_file() {
+            let file = match File::open(entry.path()) {
+                Ok(f) => f,
+                Err(e) => {
+                    skipped += 1;
+                    progress.warn(format!(
+                        "Datei konnte nicht gelesen werden ({}): {}",
+                        e.kind(),
+                        entry.path().display()
+                    ));
+                    continue;
+                }
+            };
+            progress.set_current_file(Some(entry.path()));
+            // Capped at the stat size: tar headers announce the length up front
+            let mut reader = CountingReader { inner: file.take(md.len()), progress };
+            sink.add_file(&name, &md, &mut reader)?;
+        } else {
+            skipped += 1;
+            progress.warn(format!("Kein regulärer Eintrag, uebersprungen: {}", entry.path().display()));
+        }
+    }
+
+    progress.set_current_file(None);
+    sink.finish()?.sync_all()?;
+    Ok(skipped)
+}
+
+/// Returns the `/`-separated archive name of `path` relative to `base`.
+fn entry_name(base: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(base).ok()?;
+    let parts: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[cfg(windows)]
+fn is_reparse_point(md: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    (md.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT) != 0
+}
+
+#[cfg(not(windows))]
+fn is_reparse_point(_md: &fs::Metadata) -> bool {
+    false
+}
+
+/// A reader that adds the bytes it reads to the progress of an operation.
+struct CountingReader<'a, R> {
+    inner: R,
+    progress: &'a ArchiveProgress,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.bytes_processed.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// An archive being written.
+enum Sink {
+    Zip(Box<zip::ZipWriter<BufWriter<File>>>),
+    TarZst(tar::Builder<zstd::stream::write::Encoder<'static, BufWriter<File>>>),
+}
+
+impl Sink {
+    fn create(file: File, format: ArchiveFormat) -> io::Result<Self> {
+        let out = BufWriter::new(file);
+        Ok(match format {
+            ArchiveFormat::Zip => Sink::Zip(Box::new(zip::ZipWriter::new(out))),
+            ArchiveFormat::TarZst => {
+                Sink::TarZst(tar::Builder::new(zstd::stream::write::Encoder::new(out, ZSTD_LEVEL)?))
+            }
+        })
+    }
+
+    fn add_dir(&mut self, name: &str, md: &fs::Metadata) -> io::Result<()> {
+        match self {
+            Sink::Zip(zip) => zip.add_directory(name.to_string(), zip_options(md)).map_err(io::Error::other),
+            Sink::TarZst(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(md);
+                header.set_size(0);
+                tar.append_data(&mut header, name, io::empty())
+            }
+        }
+    }
+
+    fn add_file(&mut self, name: &str, md: &fs::Metadata, data: &mut dyn Read) -> io::Result<()> {
+        match self {
+            Sink::Zip(zip) => {
+                zip.start_file(name.to_string(), zip_options(md)).map_err(io::Error::other)?;
+                io::copy(data, zip)?;
+                Ok(())
+            }
+            Sink::TarZst(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(md);
+                tar.append_data(&mut header, name, data)
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<File> {
+        let out = match self {
+            Sink::Zip(zip) => zip.finish().map_err(io::Error::other)?,
+            Sink::TarZst(tar) => tar.into_inner()?.finish()?,
+        };
+        out.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+/// Deflate options for one zip entry, keeping its modification time.
+fn zip_options(md: &fs::Metadata) -> zip::write::SimpleFileOptions {
+    let mut options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(md.len() >= u32::MAX as u64);
+    if let Ok(modified) = md.modified() {
+        // Zip stores local time without a zone and can't represent years before 1980
+        let t: DateTime<Local> = modified.into();
+        if let Ok(dt) = zip::DateTime::from_date_and_time(
+            t.year().clamp(1980, 2107) as u16,
+            t.month() as u8,
+            t.day() as u8,
+            t.hour() as u8,
+            t.minute() as u8,
+            t.second() as u8,
+        ) {
+            options = options.last_modified_time(dt);
+        }
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        source: &Path,
+        destination: &Path,
+        format: ArchiveFormat,
+        remove_source: bool,
+    ) -> ArchivePathRequest {
+        ArchivePathRequest {
+            source: source.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            format,
+            remove_source,
+            overwrite: false,
+        }
+    }
+
+    fn fixture() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("cold");
+        fs::create_dir_all(source.join("sub").join("empty")).unwrap();
+        fs::write(source.join("a.txt"), "a".repeat(10_000)).unwrap();
+        fs::write(source.join("sub").join("b.log"), b"hello").unwrap();
+        (dir, source)
+    }
+
+    #[test]
+    fn zip_archive_holds_the_tree_and_source_is_removed() {
+        let (dir, source) = fixture();
+        let dest = dir.path().join("out").join("cold.zip");
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        let progress = ArchiveProgress::new(
+            Uuid::new_v4(),
+            request(&source, &dest, ArchiveFormat::Zip, true),
+            10_005,
+            vec![],
+        );
+
+        let outcome = run_archive(&progress).unwrap();
+        assert!(!source.exists());
+        assert_eq!(outcome.freed_bytes, 10_005);
+        assert_eq!(outcome.bytes_written, fs::metadata(&dest).unwrap().len());
+        assert!(outcome.bytes_written < 10_005, "deflate must shrink repeated text");
+        assert!(!dest.with_extension("zip.partial").exists());
+        assert_eq!(progress.snapshot().bytes_processed, 10_005);
+
+        let mut zip = zip::ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let mut names: Vec<String> = zip.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(names, vec!["cold/", "cold/a.txt", "cold/sub/", "cold/sub/b.log", "cold/sub/empty/"]);
+        let mut content = String::new();
+        zip.by_name("cold/sub/b.log").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn tar_zst_archive_keeps_source_by_default() {
+        let (dir, source) = fixture();
+        let dest = dir.path().join("cold.tar.zst");
+        let progress = ArchiveProgress::new(
+            Uuid::new_v4(),
+            request(&source, &dest, ArchiveFormat::TarZst, false),
+            10_005,
+            vec![],
+        );
+
+        let outcome = run_archive(&progress).unwrap();
+        assert!(source.join("a.txt").exists());
+        assert_eq!(outcome.freed_bytes, 0);
+
+        let decoder = zstd::stream::read::Decoder::new(File::open(&dest).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        let mut files = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().trim_end_matches('/').to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            files.push((path, data.len()));
+        }
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                ("cold".to_string(), 0),
+                ("cold/a.txt".to_string(), 10_000),
+                ("cold/sub".to_string(), 0),
+                ("cold/sub/b.log".to_string(), 5),
+                ("cold/sub/empty".to_string(), 0),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skipped_entries_keep_the_source() {
+        let (dir, source) = fixture();
+        std::os::unix::fs::symlink(source.join("a.txt"), source.join("link")).unwrap();
+        let dest = dir.path().join("cold.zip");
+        let progress = ArchiveProgress::new(
+            Uuid::new_v4(),
+            request(&source, &dest, ArchiveFormat::Zip, true),
+            10_005,
+            vec![],
+        );
+
+        let outcome = run_archive(&progress).unwrap();
+        assert!(source.exists());
+        assert_eq!(outcome.freed_bytes, 0);
+        let warnings = progress.snapshot().warnings;
+        assert!(warnings.iter().any(|w| w.contains("Symlink")), "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains("nicht gelöscht")), "{:?}", warnings);
+    }
+}
@@ -0,0 +1,270 @@
+//! Tracking of background path operations.
+//!
+//! Moves and archives run in tokio tasks after their request has been answered.
+//! Each running or recently finished operation is kept in `AppState::operations`
+//! so clients can poll `GET /paths/operations/{op_id}` for progress, even after
+//! the browser tab that started it was closed.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    routes::paths_archive::ArchiveProgress,
+    types::{MoveItemResult, MoveItemStatus, MoveOperation, MovePathRequest, PathOperation},
+};
+
+/// How long a finished operation stays queryable.
+pub const OPERATION_RETENTION: Duration = Duration::from_secs(3600);
+
+/// The background path operations, keyed by operation ID.
+pub type Operations = Arc<RwLock<HashMap<Uuid, PathOperationHandle>>>;
+
+/// A running or recently finished path operation.
+#[derive(Clone)]
+pub enum PathOperationHandle {
+    /// A move or copy.
+    Move(Arc<MoveProgress>),
+    /// An archive.
+    Archive(Arc<ArchiveProgress>),
+}
+
+impl PathOperationHandle {
+    /// Returns the current state of the operation.
+    pub fn snapshot(&self) -> PathOperation {
+        match self {
+            PathOperationHandle::Move(progress) => PathOperation::Move(progress.snapshot()),
+            PathOperationHandle::Archive(progress) => PathOperation::Archive(progress.snapshot()),
+        }
+    }
+}
+
+/// Removes a finished operation once the retention period has passed.
+///
+/// # Arguments
+///
+/// * `operations` - The operation registry.
+/// * `op_id` - The ID of the finished operation.
+pub async fn retire_operation(operations: Operations, op_id: Uuid) {
+    tokio::time::sleep(OPERATION_RETENTION).await;
+    operations.write().await.remove(&op_id);
+}
+
+/// How a move operation ended, with its end time and duration.
+type Finished = (DateTime<Utc>, u64, Result<(), String>);
+
+/// The progress of one move operation, shared by the worker and status requests.
+pub struct MoveProgress {
+    op_id: Uuid,
+    request: MovePathRequest,
+    stop_on_error: bool,
+    cancel: CancellationToken,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    bytes_to_transfer: AtomicU64,
+    bytes_moved: AtomicU64,
+    freed_bytes: AtomicU64,
+    items_done: AtomicUsize,
+    current_source: Mutex<Option<String>>,
+    items: Mutex<Vec<MoveItemResult>>,
+    warnings: Mutex<Vec<String>>,
+    finished: Mutex<Option<Finished>>,
+}
+
+impl MoveProgress {
+    /// Creates the progress of an operation that is about to start.
+    ///
+    /// # Arguments
+    ///
+    /// * `op_id` - The ID of the operation.
+    /// * `request` - The validated request.
+    pub fn new(op_id: Uuid, request: MovePathRequest) -> Self {
+        let items = request
+            .sources
+            .iter()
+            .zip(&request.destinations)
+            .map(|(source, destination)| MoveItemResult {
+                source: source.clone(),
+                destination: destination.clone(),
+                status: MoveItemStatus::Pending,
+                message: None,
+            })
+            .collect();
+        Self {
+            op_id,
+            request,
+            stop_on_error: false,
+            cancel: CancellationToken::new(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+            bytes_to_transfer: AtomicU64::new(0),
+            bytes_moved: AtomicU64::new(0),
+            freed_bytes: AtomicU64::new(0),
+            items_done: AtomicUsize::new(0),
+            current_source: Mutex::new(None),
+            items: Mutex::new(items),
+            warnings: Mutex::new(Vec::new()),
+            finished: Mutex::new(None),
+        }
+    }
+
+    /// Returns the ID of the operation.
+    pub fn op_id(&self) -> Uuid {
+        self.op_id
+    }
+
+    /// Makes the worker skip the remaining items after the first failed one.
+    pub fn with_stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+
+    /// Returns the request the operation works through.
+    pub fn request(&self) -> &MovePathRequest {
+        &self.request
+    }
+
+    /// Returns whether the remaining items are skipped after a failed one.
+    pub fn stops_on_error(&self) -> bool {
+        self.stop_on_error
+    }
+
+    /// Asks the worker to stop before the next file.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Returns whether cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub(crate) fn add_to_transfer(&self, bytes: u64) {
+        self.bytes_to_transfer.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_moved(&self, bytes: u64) {
+        self.bytes_moved.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_freed(&self, bytes: u64) {
+        self.freed_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn item_done(&self) {
+        self.items_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_current_source(&self, source: Option<&str>) {
+        *self.current_source.lock().unwrap_or_else(|e| e.into_inner()) = source.map(str::to_string);
+    }
+
+    pub(crate) fn item_status(&self, index: usize) -> MoveItemStatus {
+        self.items.lock().unwrap_or_else(|e| e.into_inner())[index].status
+    }
+
+    pub(crate) fn set_item(&self, index: usize, status: MoveItemStatus, message: Option<String>) {
+        let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        items[index].status = status;
+        items[index].message = message;
+    }
+
+    pub(crate) fn warn(&self, message: String) {
+        self.warnings.lock().unwrap_or_else(|e| e.into_inner()).push(message);
+    }
+
+    /// Records the end of the operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - `Ok` when the worker ran to the end or stopped after a
+    ///   cancellation, the error message if it failed.
+    pub fn finish(&self, result: Result<(), String>) {
+        self.set_current_source(None);
+        let duration_ms = self.started.elapsed().as_millis() as u64;
+        *self.finished.lock().unwrap_or_else(|e| e.into_inner()) = Some((Utc::now(), duration_ms, result));
+    }
+
+    /// Returns the current state of the operation.
+    pub fn snapshot(&self) -> MoveOperation {
+        let finished = self.finished.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let cancelled = self.is_cancelled();
+        let (status, finished_at, duration_ms, error) = match finished {
+            None if cancelled => ("cancelling", None, self.started.elapsed().as_millis() as u64, None),
+            None => ("running", None, self.started.elapsed().as_millis() as u64, None),
+            Some((at, ms, Ok(()))) if cancelled => ("cancelled", Some(at), ms, None),
+            Some((at, ms, Ok(()))) => ("completed", Some(at), ms, None),
+            Some((at, ms, Err(e))) => ("failed", Some(at), ms, Some(e)),
+        };
+        MoveOperation {
+            op_id: self.op_id,
+            status: status.to_string(),
+            sources: self.request.sources.clone(),
+            destinations: self.request.destinations.clone(),
+            remove_source: self.request.remove_source,
+            bytes_to_transfer: self.bytes_to_transfer.load(Ordering::Relaxed),
+            bytes_moved: self.bytes_moved.load(Ordering::Relaxed),
+            freed_bytes: self.freed_bytes.load(Ordering::Relaxed),
+            items_total: self.request.sources.len(),
+            items_done: self.items_done.load(Ordering::Relaxed),
+            current_source: self.current_source.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            items: self.items.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            duration_ms,
+            started_at: self.started_at.to_rfc3339(),
+            finished_at: finished_at.map(|at| at.to_rfc3339()),
+            warnings: self.warnings.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> MovePathRequest {
+        MovePathRequest {
+            sources: vec!["C:\\a".into(), "C:\\b".into()],
+            destinations: vec!["D:\\a".into(), "D:\\b".into()],
+            remove_source: true,
+            overwrite: false,
+        }
+    }
+
+    #[test]
+    fn move_status_follows_cancellation_and_finish() {
+        let progress = MoveProgress::new(Uuid::new_v4(), request());
+        progress.add_to_transfer(100);
+        progress.add_moved(40);
+        progress.item_done();
+        let snap = progress.snapshot();
+        assert_eq!(snap.status, "running");
+        assert_eq!(
+            (snap.bytes_to_transfer, snap.bytes_moved, snap.items_done, snap.items_total),
+            (100, 40, 1, 2)
+        );
+
+        progress.cancel();
+        assert_eq!(progress.snapshot().status, "cancelling");
+        progress.finish(Ok(()));
+        let snap = progress.snapshot();
+        assert_eq!(snap.status, "cancelled");
+        assert!(snap.finished_at.is_some());
+
+        let failed = MoveProgress::new(Uuid::new_v4(), request());
+        failed.finish(Err("boom".into()));
+        let snap = failed.snapshot();
+        assert_eq!(snap.status, "failed");
+        assert_eq!(snap.error.as_deref(), Some("boom"));
+    }
+}
@@ -0,0 +1,282 @@
+//! Moving files and directories to the recycle bin.
+//!
+//! On Windows the shell's `SHFileOperationW` is used with `FOF_ALLOWUNDO`. The
+//! shell silently deletes permanently when a volume has no recycle bin, so
+//! network paths are refused up front and the item count of the volume's bin
+//! is compared before and after the operation to detect the fallback.
+//!
+//! On Linux items are moved into the home trash of the freedesktop.org trash
+//! specification (`$XDG_DATA_HOME/Trash`, usually `~/.local/share/Trash`).
+//! Per-volume trash directories are not implemented, so items on another
+//! filesystem than the home trash are refused.
+//!
+//! Other platforms have no recycle support.
+
+use std::io;
+use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+/// Whether recycling is implemented on this platform.
+pub const RECYCLE_SUPPORTED: bool = cfg!(any(windows, target_os = "linux"));
+
+/// Moves a file or directory to the recycle bin.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to recycle.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the item landed in the recycle bin, `false`
+///   if it is gone but the system deleted it permanently instead. Items that
+///   can't be recycled fail with `ErrorKind::Unsupported` and are left untouched.
+#[cfg(windows)]
+pub fn recycle(path: &Path) -> io::Result<bool> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::{SHFileOperationW, SHFILEOPSTRUCTW};
+
+    // Values from shellapi.h
+    const FO_DELETE: u32 = 0x3;
+    const FOF_SILENT: u32 = 0x4;
+    const FOF_NOCONFIRMATION: u32 = 0x10;
+    const FOF_ALLOWUNDO: u32 = 0x40;
+    const FOF_NOERRORUI: u32 = 0x400;
+
+    std::fs::symlink_metadata(path)?;
+    let root = crate::routes::paths_helpers::get_volume_root(path);
+    if is_remote_volume(&root) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "network paths have no recycle bin"));
+    }
+    let before = recycle_bin_items(&root).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, format!("no recycle bin available for {}", root))
+    })?;
+
+    // pFrom is a list of paths terminated by an empty string, hence two NULs
+    let from: Vec<u16> = path.as_os_str().encode_wide().chain([0, 0]).collect();
+    let mut op = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: PCWSTR(from.as_ptr()),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT) as u16,
+        ..Default::default()
+    };
+    let rc = unsafe { SHFileOperationW(&mut op) };
+    if rc != 0 {
+        return Err(io::Error::other(format!("SHFileOperationW failed with code {:#x}", rc)));
+    }
+    if op.fAnyOperationsAborted.as_bool() || std::fs::symlink_metadata(path).is_ok() {
+        return Err(io::Error::other("the shell aborted the operation"));
+    }
+
+    Ok(recycle_bin_items(&root).is_some_and(|after| after > before))
+}
+
+/// Returns the number of items in the recycle bin of a volume, or `None` if it has none.
+#[cfg(windows)]
+fn recycle_bin_items(root: &str) -> Option<i64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::{SHQueryRecycleBinW, SHQUERYRBINFO};
+
+    let w: Vec<u16> = std::ffi::OsStr::new(root).encode_wide().chain(std::iter::once(0)).collect();
+    let mut info = SHQUERYRBINFO { cbSize: std::mem::size_of::<SHQUERYRBINFO>() as u32, ..Default::default() };
+    unsafe { SHQueryRecycleBinW(PCWSTR(w.as_ptr()), &mut info) }.ok()?;
+    Some(info.i64NumItems)
+}
+
+/// Returns whether a volume root is a UNC share or a mapped network drive.
+#[cfg(windows)]
+fn is_remote_volume(root: &str) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    const DRIVE_REMOTE: u32 = 4;
+    if root.starts_with("\\\\") {
+        return true;
+    }
+    let w: Vec<u16> = std::ffi::OsStr::new(root).encode_wide().chain(std::iter::once(0)).collect();
+    unsafe { GetDriveTypeW(PCWSTR(w.as_ptr())) == DRIVE_REMOTE }
+}
+
+/// Moves a file or directory to the recycle bin.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to recycle.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - Always `true` on success; the home trash never falls
+///   back to permanent deletion. Items that can't be recycled fail with
+///   `ErrorKind::Unsupported` and are left untouched.
+#[cfg(target_os = "linux")]
+pub fn recycle(path: &Path) -> io::Result<bool> {
+    let trash = home_trash_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no home directory for the trash"))?;
+    move_to_trash(path, &trash)?;
+    Ok(true)
+}
+
+/// Moves a file or directory to the recycle bin.
+///
+/// Not supported on this platform.
+///
+/// # Arguments
+///
+/// * `_path` - The file or directory to recycle.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - Always fails with `ErrorKind::Unsupported`.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn recycle(_path: &Path) -> io::Result<bool> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "recycle bin is not supported on this platform"))
+}
+
+#[cfg(target_os = "linux")]
+fn home_trash_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .map(|d| d.join("Trash"))
+}
+
+/// Moves an item into a freedesktop.org trash directory.
+///
+/// The `.trashinfo` file is created first with `create_new`, which reserves
+/// the name in `files/` against concurrent deletions of items with the same name.
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The location of the item inside the trash.
+#[cfg(target_os = "linux")]
+fn move_to_trash(path: &Path, trash: &Path) -> io::Result<PathBuf> {
+    use std::io::Write;
+
+    let abs = std::path::absolute(path)?;
+    std::fs::symlink_metadata(&abs)?;
+    let name = abs
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy()
+        .to_string();
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    std::fs::create_dir_all(&files_dir)?;
+    std::fs::create_dir_all(&info_dir)?;
+
+    for n in 0..1_000u32 {
+        let candidate = if n == 0 { name.clone() } else { format!("{}.{}", name, n) };
+        let info_path = info_dir.join(format!("{}.trashinfo", candidate));
+        let mut info = match std::fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        let target = files_dir.join(&candidate);
+        if target.exists() {
+            // Leftover without info file; keep it and pick the next name
+            drop(info);
+            let _ = std::fs::remove_file(&info_path);
+            continue;
+        }
+        let written = write!(
+            info,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_trash_path(&abs),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+        );
+        let moved = written.and_then(|_| std::fs::rename(&abs, &target));
+        if let Err(e) = moved {
+            let _ = std::fs::remove_file(&info_path);
+            if e.kind() == io::ErrorKind::CrossesDevices {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "item is on another filesystem than the trash directory",
+                ));
+            }
+            return Err(e);
+        }
+        return Ok(target);
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "no free name in the trash directory"))
+}
+
+/// Percent-encodes a path for the `Path=` key of a `.trashinfo` file.
+#[cfg(target_os = "linux")]
+fn encode_trash_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut out = String::new();
+    for &b in path.as_os_str().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn trash_keeps_item_and_records_origin() {
+        let dir = tempfile::tempdir().unwrap();
+        let trash = dir.path().join("Trash");
+        let file = dir.path().join("report 1.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let target = move_to_trash(&file, &trash).unwrap();
+        assert!(!file.exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"data");
+        let info = std::fs::read_to_string(trash.join("info/report 1.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains(&format!("Path={}\n", encode_trash_path(&file))));
+        assert!(info.contains("report%201.txt"));
+
+        // A second item with the same name gets a new slot
+        std::fs::create_dir(&file).unwrap();
+        let target2 = move_to_trash(&file, &trash).unwrap();
+        assert_eq!(target2.file_name().unwrap(), "report 1.txt.1");
+        assert!(target2.is_dir());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn trash_rejects_missing_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = move_to_trash(&dir.path().join("missing"), &dir.path().join("Trash")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!dir.path().join("Trash/info/missing.trashinfo").exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn recycle_removes_temp_file_from_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("recycle-me.txt");
+        std::fs::write(&file, b"data").unwrap();
+        recycle(&file).unwrap();
+        assert!(!file.exists());
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    #[test]
+    fn recycle_is_rejected_as_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("keep.txt");
+        std::fs::write(&file, b"data").unwrap();
+        assert_eq!(recycle(&file).unwrap_err().kind(), io::ErrorKind::Unsupported);
+        assert!(file.exists());
+    }
+}
@@ -0,0 +1,126 @@
+//! Directory preview API endpoints.
+//!
+//! Before a scan is started, the UI shows the direct children of the path the
+//! user typed. The preview lists them with a quick `stat` each, leaves out
+//! what a scan with the default options would skip and writes nothing.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /preview?path=` - The direct children of a directory with their sizes
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::validation::validate_file_path,
+    routes::scans::{apply_scan_defaults, normalize_root_paths},
+    scanner,
+    state::AppState,
+    types::{CreateScanRequest, DirPreview},
+};
+
+/// Query parameters for the preview endpoint.
+#[derive(Debug, serde::Deserialize)]
+pub struct PreviewQuery {
+    /// The directory to preview.
+    pub path: String,
+    /// The most entries returned, 200 by default and at most 5000.
+    pub limit: Option<usize>,
+    /// How long the listing may take in milliseconds, 2000 by default and at most 10000.
+    pub budget_ms: Option<u64>,
+}
+
+/// Lists the direct children of a directory without scanning it.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `q` - The query parameters, see [`PreviewQuery`].
+///
+/// # Returns
+///
+/// * `AppResult<Json<DirPreview>>` - The children of the directory. A directory
+///   that cannot be read for lack of permissions is answered with 403.
+pub async fn preview_path(
+    State(state): State<AppState>,
+    Query(q): Query<PreviewQuery>,
+) -> AppResult<Json<DirPreview>> {
+    validate_file_path(&q.path).map_err(|_| AppError::InvalidInput(format!("Invalid path: {}", q.path)))?;
+    let (mut roots, _) = normalize_root_paths(std::slice::from_ref(&q.path))?;
+    let path = roots.remove(0);
+    let options = apply_scan_defaults(
+        &state,
+        &CreateScanRequest { root_paths: vec![path.clone()], ..Default::default() },
+    )?;
+    let limit = q.limit.unwrap_or(200).clamp(1, 5000);
+    let budget = Duration::from_millis(q.budget_ms.unwrap_or(2000).clamp(1, 10_000));
+
+    let dir = path.clone();
+    let preview = tokio::task::spawn_blocking(move || {
+        scanner::preview::preview_dir(Path::new(&dir), &options, limit, budget)
+    })
+    .await
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Preview task failed: {}", e)))?;
+    match preview {
+        Ok(preview) => Ok(Json(preview)),
+        Err(e) => Err(match e.kind() {
+            ErrorKind::NotFound => AppError::NotFound(format!("path not found: {}", path)),
+            ErrorKind::PermissionDenied => AppError::Forbidden(format!("access denied: {}", path)),
+            ErrorKind::NotADirectory => AppError::BadRequest(format!("not a directory: {}", path)),
+            _ => e.into(),
+        }),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use axum::response::IntoResponse;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::test_db_in;
+
+    async fn preview(state: &AppState, path: &Path) -> AppResult<DirPreview> {
+        let q = PreviewQuery { path: path.to_string_lossy().to_string(), limit: None, budget_ms: None };
+        preview_path(State(state.clone()), Query(q)).await.map(|Json(p)| p)
+    }
+
+    #[tokio::test]
+    async fn previews_directories_and_rejects_what_cannot_be_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("photos")).unwrap();
+        std::fs::write(root.join("notes.txt"), b"hello").unwrap();
+        let pool = test_db_in(dir.path()).await;
+        let state = AppState::new(pool, AppConfig::default());
+
+        let p = preview(&state, &root).await.unwrap();
+        assert_eq!(p.path, root.to_string_lossy());
+        assert_eq!(p.entries.len(), 2);
+        assert_eq!(p.entries[1].logical_size, Some(5));
+
+        let err = preview(&state, &root.join("missing")).await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+        let err = preview(&state, &root.join("notes.txt")).await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)), "{:?}", err);
+
+        use std::os::unix::fs::PermissionsExt;
+        let locked = root.join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // Privileged users can read the directory anyway
+        let unreadable = std::fs::read_dir(&locked).is_err();
+        let res = preview(&state, &locked).await;
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        if unreadable {
+            assert_eq!(res.unwrap_err().into_response().status(), axum::http::StatusCode::FORBIDDEN);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Configuration reload API endpoint.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /admin/reload` - Load the configuration again and apply it
+//!
+//! On Unix the server also reloads on `SIGHUP`. Scan defaults, rate limits,
+//! security headers and the other settings read per request apply to later
+//! requests; running scans keep the options they were started with. Settings
+//! only read at startup are reported as requiring a restart. Like backups,
+//! the endpoint refuses requests authenticated with a token bound to any
+//! namespace but the admin one.
+
+use axum::{
+    extract::{Request, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{
+    config,
+    error::{AppError, AppResult},
+    middleware::ip::MaybeRemoteAddr,
+    routes::backup::{check_rate_limit, require_admin},
+    state::AppState,
+    types::ReloadResponse,
+};
+
+/// Loads the configuration again and applies it.
+///
+/// Shared by `POST /admin/reload` and the `SIGHUP` handler. An invalid
+/// configuration is rejected as a whole and the running one stays in place.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `AppResult<ReloadResponse>` - The settings that require a restart, or
+///   `BadRequest` if the new configuration does not load or validate.
+pub async fn reload(state: &AppState) -> AppResult<ReloadResponse> {
+    let new = config::load().map_err(|e| match e.downcast_ref::<config::ConfigErrors>() {
+        Some(errors) => AppError::BadRequest(format!("Invalid configuration: {}", errors.0.join("; "))),
+        None => AppError::BadRequest(format!("Invalid configuration: {}", e)),
+    })?;
+    let requires_restart = state.reload_config(new).await;
+    tracing::info!(target: "audit", "Configuration reloaded");
+    for name in &requires_restart {
+        tracing::warn!("Configuration reloaded; {} changed but requires a restart", name);
+    }
+    Ok(ReloadResponse { requires_restart })
+}
+
+/// Reloads the configuration, for platforms without `SIGHUP`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `req` - The request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `ReloadResponse`.
+pub async fn reload_config(
+    State(state): State<AppState>,
+    maybe_remote: MaybeRemoteAddr,
+    req: Request,
+) -> AppResult<Response> {
+    require_admin(&req)?;
+    if let Some(limited) = check_rate_limit(&state, "/admin/reload", &maybe_remote, req.headers()).await {
+        return Ok(limited);
+    }
+    Ok(Json(reload(&state).await?).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::middleware::namespace::Namespace;
+    use crate::routes::scans::start_scan;
+    use crate::types::{CreateScanRequest, ScanOptions};
+    use uuid::Uuid;
+    use crate::test_support::test_db;
+
+    async fn stored_options(state: &AppState, id: Uuid) -> ScanOptions {
+        let json: String = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+            .bind(id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn new_scans_use_reloaded_defaults_while_running_ones_keep_theirs() {
+        let (dir, pool) = test_db().await;
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(&root).unwrap();
+        let mut config = AppConfig::default();
+        config.scan_defaults.include_hidden = false;
+        config.scan_defaults.excludes = vec!["**/old".into()];
+        let state = AppState::new(pool, config);
+        let req =
+            CreateScanRequest { root_paths: vec![root.to_string_lossy().to_string()], ..Default::default() };
+
+        let first = start_scan(&state, req.clone(), &Namespace::default()).await.unwrap();
+
+        let mut reloaded = AppConfig::default();
+        reloaded.scan_defaults.include_hidden = true;
+        reloaded.scan_defaults.excludes = vec!["**/new".into()];
+        reloaded.server.port = state.config.get().server.port + 1;
+        let requires_restart = state.reload_config(reloaded).await;
+        assert_eq!(requires_restart, ["server.port"]);
+        assert_eq!(state.config.get().server.port, AppConfig::default().server.port);
+
+        let second = start_scan(&state, req, &Namespace::default()).await.unwrap();
+        let (old, new) = (stored_options(&state, first.id).await, stored_options(&state, second.id).await);
+        assert!(!old.include_hidden);
+        assert_eq!(old.excludes, ["**/old"]);
+        assert!(new.include_hidden);
+        assert_eq!(new.excludes, ["**/new"]);
+
+        // Let both jobs finish before the database goes away
+        for id in [first.id, second.id] {
+            let job = state.jobs.read().await.get(&id).cloned();
+            if let Some(job) = job {
+                job.finished.cancelled().await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reloaded_rate_limits_apply_to_later_requests() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let state = AppState::new(pool, AppConfig::default());
+        let ip = std::net::IpAddr::from([127, 0, 0, 1]);
+        assert!(state.rate_limiter.check_endpoint_limit("/drives", ip).await.is_ok());
+
+        let reloaded = AppConfig {
+            rate_limits: vec![crate::config::RateLimitConfig {
+                pattern: "/drives".into(),
+                max_requests: 1,
+                window_secs: 60,
+            }],
+            ..Default::default()
+        };
+        assert!(state.reload_config(reloaded).await.is_empty());
+        assert!(state.rate_limiter.check_endpoint_limit("/drives", ip).await.is_ok());
+        assert!(state.rate_limiter.check_endpoint_limit("/drives", ip).await.is_err());
+        // Patterns dropped from the config are no longer limited
+        for _ in 0..50 {
+            assert!(state.rate_limiter.check_endpoint_limit("/search", ip).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn reload_is_forbidden_for_namespace_bound_tokens() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let state = AppState::new(pool, AppConfig::default());
+        let mut req = Request::builder().method("POST").body(axum::body::Body::empty()).unwrap();
+        req.extensions_mut().insert(Namespace::parse("hr").unwrap());
+        let res = reload_config(State(state), MaybeRemoteAddr(None), req).await;
+        assert_eq!(res.unwrap_err().into_response().status(), axum::http::StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,527 @@
+//! Scan root remapping API endpoints.
+//!
+//! When a drive is remapped to another letter (or a share is mounted elsewhere)
+//! the stored paths of older scans no longer resolve. Remapping rewrites the
+//! scan's root paths and the path prefixes of all its rows, so listing, moving
+//! and deleting work against the new location without a rescan.
+//!
+//! Before rewriting, the new location is checked: every affected root must
+//! exist and at least half of a sample of its known direct children must be
+//! found there. Each remap is recorded in the `scan_remaps` table, which serves
+//! as the audit trail and provenance of the scan's paths.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/remap-root` - Rewrite the paths below `from` to `to`
+//! - `GET /scans/{id}/remap-root` - List the remaps applied to a scan, oldest first
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::{namespace::Namespace, validation::ValidJson},
+    routes::scans::{escape_like_pattern, normalize_query_path},
+    scanner::owner::{refresh_usage, UsageScope},
+    state::AppState,
+    types::{RemapRootRequest, ScanRemapDto},
+};
+
+/// The number of known direct children checked per affected root.
+const SPOT_CHECKS_PER_ROOT: i64 = 4;
+/// The number of row IDs covered by one prefix `UPDATE`.
+const REMAP_ID_BATCH: i64 = 50_000;
+
+/// A prefix translation from one root to another.
+///
+/// Matching is ASCII case-insensitive, like SQLite's `LIKE`, so drive letters
+/// and Windows paths match regardless of case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RootTranslation {
+    /// `from` without trailing separators.
+    from_base: String,
+    /// `from_base` followed by a separator; every path below `from` starts with it.
+    from_sep: String,
+    to_base: String,
+    to_sep: String,
+}
+
+impl RootTranslation {
+    /// Creates a translation from `from` to `to`, both already normalized.
+    ///
+    /// # Returns
+    ///
+    /// * `AppResult<Self>` - `BadRequest` if both roots are the same.
+    pub(crate) fn new(from: &str, to: &str) -> AppResult<Self> {
+        let (from_base, from_sep) = split_root(from);
+        let (to_base, to_sep) = split_root(to);
+        if from_sep.eq_ignore_ascii_case(&to_sep) {
+            return Err(AppError::BadRequest("from and to must be different".into()));
+        }
+        Ok(Self { from_base, from_sep, to_base, to_sep })
+    }
+
+    /// Translates a path, or returns `None` if it is not `from` or below it.
+    pub(crate) fn apply(&self, path: &str) -> Option<String> {
+        if path.eq_ignore_ascii_case(&self.from_base) {
+            return Some(self.to_base.clone());
+        }
+        let head = path.get(..self.from_sep.len())?;
+        head.eq_ignore_ascii_case(&self.from_sep).then(|| format!("{}{}", self.to_sep, &path[self.from_sep.len()..]))
+    }
+}
+
+/// Splits a root into its form without and with a trailing separator.
+fn split_root(root: &str) -> (String, String) {
+    let is_drive = root.len() >= 2 && root.as_bytes()[1] == b':';
+    let sep = if root.contains('\\') || is_drive { '\\' } else { '/' };
+    let base = root.trim_end_matches(['/', '\\']).to_string();
+    let with_sep = format!("{}{}", base, sep);
+    (base, with_sep)
+}
+
+/// Remaps the root of a scan to a new location.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `req` - The old and new root.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing the recorded `ScanRemapDto`.
+///   `BadRequest` if no root of the scan is below `from` or the new location
+///   doesn't exist or doesn't look like the scanned tree, `Conflict` while a
+///   job for the scan is running or the scan is watched.
+pub async fn remap_root(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    ValidJson(req): ValidJson<RemapRootRequest>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    if state.jobs.read().await.contains_key(&id) {
+        return Err(AppError::Conflict("cannot remap a scan while a job for it is running".into()));
+    }
+    if state.watchers.read().await.contains_key(&id) {
+        return Err(AppError::Conflict("cannot remap a watched scan; stop watching it first".into()));
+    }
+    let from = normalize_query_path(&req.from)?;
+    let to = normalize_query_path(&req.to)?;
+    let translation = RootTranslation::new(&from, &to)?;
+
+    let roots_json: String = sqlx::query_scalar("SELECT root_paths FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await?;
+    let roots: Vec<String> = serde_json::from_str(&roots_json)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid root_paths of scan {}: {}", id, e)))?;
+    let affected: Vec<String> = roots.iter().filter(|r| translation.apply(r).is_some()).cloned().collect();
+    if affected.is_empty() {
+        return Err(AppError::BadRequest(format!("no root of the scan is at or below {}", from)));
+    }
+
+    verify_new_location(&state.db, id, &affected, &translation).await?;
+
+    let new_roots: Vec<String> = roots.iter().map(|r| translation.apply(r).unwrap_or_else(|| r.clone())).collect();
+    let new_roots_json = serde_json::to_string(&new_roots)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize root_paths: {}", e)))?;
+    let (nodes_updated, files_updated) =
+        rewrite_scan_paths(&state.db, id, &translation, &new_roots_json, ns.as_str(), &from, &to).await?;
+
+    tracing::info!(
+        target: "audit",
+        scan_id = %id,
+        namespace = ns.as_str(),
+        from = %from,
+        to = %to,
+        nodes_updated,
+        files_updated,
+        "scan root remapped"
+    );
+
+    let remap = sqlx::query(
+        "SELECT id, from_root, to_root, namespace, nodes_updated, files_updated, remapped_at FROM scan_remaps WHERE scan_id=?1 ORDER BY id DESC LIMIT 1",
+    )
+    .bind(id.to_string())
+    .fetch_one(&state.db)
+    .await?;
+    Ok(Json(remap_from_row(&remap)))
+}
+
+/// Lists the remaps applied to a scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON array of `ScanRemapDto`, oldest first.
+pub async fn list_remaps(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let rows = sqlx::query(
+        "SELECT id, from_root, to_root, namespace, nodes_updated, files_updated, remapped_at FROM scan_remaps WHERE scan_id=?1 ORDER BY id",
+    )
+    .bind(id.to_string())
+    .fetch_all(state.read_pool())
+    .await?;
+    Ok(Json(rows.iter().map(remap_from_row).collect::<Vec<_>>()))
+}
+
+fn remap_from_row(r: &sqlx::sqlite::SqliteRow) -> ScanRemapDto {
+    ScanRemapDto {
+        id: r.get("id"),
+        from: r.get("from_root"),
+        to: r.get("to_root"),
+        namespace: r.get("namespace"),
+        nodes_updated: r.get("nodes_updated"),
+        files_updated: r.get("files_updated"),
+        remapped_at: r.get("remapped_at"),
+    }
+}
+
+/// Checks that the translated roots exist and contain most of their known children.
+async fn verify_new_location(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    affected: &[String],
+    translation: &RootTranslation,
+) -> AppResult<()> {
+    let mut samples = Vec::new();
+    for root in affected {
+        let children: Vec<String> = sqlx::query_scalar(
+            r#"SELECT path FROM (
+                   SELECT path FROM nodes WHERE scan_id=?1 AND parent_path=?2 LIMIT ?3
+               ) UNION ALL SELECT path FROM (
+                   SELECT path FROM files WHERE scan_id=?1 AND parent_path=?2 LIMIT ?3
+               ) LIMIT ?3"#,
+        )
+        .bind(id.to_string())
+        .bind(root)
+        .bind(SPOT_CHECKS_PER_ROOT)
+        .fetch_all(pool)
+        .await?;
+        samples.extend(children.iter().filter_map(|c| translation.apply(c)));
+    }
+    let new_roots: Vec<String> = affected.iter().filter_map(|r| translation.apply(r)).collect();
+
+    // Network drives can block on stat, keep it off the async runtime
+    let (missing_root, found) = spawn_blocking({
+        let samples = samples.clone();
+        move || {
+            let missing_root = new_roots.into_iter().find(|r| !std::path::Path::new(r).is_dir());
+            let found = samples.iter().filter(|s| std::fs::symlink_metadata(s).is_ok()).count();
+            (missing_root, found)
+        }
+    })
+    .await
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("remap check join error: {}", e)))?;
+
+    if let Some(root) = missing_root {
+        return Err(AppError::BadRequest(format!("new root does not exist or is not a directory: {}", root)));
+    }
+    if found * 2 < samples.len() {
+        return Err(AppError::BadRequest(format!(
+            "new location does not look like the scanned tree: only {} of {} known entries found",
+            found,
+            samples.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Rewrites the root paths and all node, file and duplicate paths of a scan in
+/// one transaction and records the remap.
+///
+/// # Returns
+///
+/// * `AppResult<(i64, i64)>` - The number of node and file rows rewritten.
+async fn rewrite_scan_paths(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    translation: &RootTranslation,
+    new_roots_json: &str,
+    namespace: &str,
+    from: &str,
+    to: &str,
+) -> AppResult<(i64, i64)> {
+    let pattern = format!("{}%", escape_like_pattern(&translation.from_sep));
+    let mut tx = pool.begin().await?;
+    let mut updated = [0i64; 2];
+    for (i, table) in ["nodes", "files"].into_iter().enumerate() {
+        let (min_id, max_id): (Option<i64>, Option<i64>) =
+            sqlx::query_as(&format!("SELECT MIN(id), MAX(id) FROM {} WHERE scan_id=?1", table))
+                .bind(id.to_string())
+                .fetch_one(&mut *tx)
+                .await?;
+        let (Some(mut lo), Some(max_id)) = (min_id, max_id) else { continue };
+        let sql = format!(
+            r#"UPDATE {} SET
+                   path = CASE WHEN path = ?2 COLLATE NOCASE THEN ?3 ELSE ?5 || substr(path, length(?4) + 1) END,
+                   parent_path = CASE
+                       WHEN parent_path = ?2 COLLATE NOCASE THEN ?3
+                       WHEN parent_path LIKE ?6 ESCAPE '!' THEN ?5 || substr(parent_path, length(?4) + 1)
+                       ELSE parent_path END
+               WHERE scan_id = ?1 AND id >= ?7 AND id < ?8
+                 AND (path = ?2 COLLATE NOCASE OR path LIKE ?6 ESCAPE '!')"#,
+            table
+        );
+        while lo <= max_id {
+            let hi = lo.saturating_add(REMAP_ID_BATCH);
+            let res = sqlx::query(&sql)
+                .bind(id.to_string())
+                .bind(&translation.from_base)
+                .bind(&translation.to_base)
+                .bind(&translation.from_sep)
+                .bind(&translation.to_sep)
+                .bind(&pattern)
+                .bind(lo)
+                .bind(hi)
+                .execute(&mut *tx)
+                .await?;
+            updated[i] += res.rows_affected() as i64;
+            lo = hi;
+        }
+    }
+
+    // Owner totals are keyed by directory path, so scans that captured owners rebuild them
+    let has_owners: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM owner_usage WHERE scan_id=?1)")
+        .bind(id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+    if has_owners {
+        refresh_usage(&mut tx, &id.to_string(), UsageScope::Scan).await?;
+    }
+
+    let groups = sqlx::query("SELECT id, paths FROM duplicates WHERE scan_id=?1")
+        .bind(id.to_string())
+        .fetch_all(&mut *tx)
+        .await?;
+    for g in groups {
+        let Ok(paths) = serde_json::from_str::<Vec<String>>(&g.get::<String, _>("paths")) else { continue };
+        if !paths.iter().any(|p| translation.apply(p).is_some()) {
+            continue;
+        }
+        let paths: Vec<String> = paths.iter().map(|p| translation.apply(p).unwrap_or_else(|| p.clone())).collect();
+        sqlx::query("UPDATE duplicates SET paths=?1 WHERE id=?2")
+            .bind(serde_json::to_string(&paths).map_err(|e| AppError::Internal(e.into()))?)
+            .bind(g.get::<i64, _>("id"))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("UPDATE scans SET root_paths=?1 WHERE id=?2")
+        .bind(new_roots_json)
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+    let root_rows = sqlx::query("SELECT root_index, root_path FROM scan_roots WHERE scan_id=?1")
+        .bind(id.to_string())
+        .fetch_all(&mut *tx)
+        .await?;
+    for r in root_rows {
+        let Some(new_root) = translation.apply(&r.get::<String, _>("root_path")) else { continue };
+        sqlx::query("UPDATE scan_roots SET root_path=?1 WHERE scan_id=?2 AND root_index=?3")
+            .bind(new_root)
+            .bind(id.to_string())
+            .bind(r.get::<i64, _>("root_index"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(
+        r#"INSERT INTO scan_remaps (scan_id, from_root, to_root, namespace, nodes_updated, files_updated)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+    )
+    .bind(id.to_string())
+    .bind(from)
+    .bind(to)
+    .bind(namespace)
+    .bind(updated[0])
+    .bind(updated[1])
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok((updated[0], updated[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::test_support::{insert_scan, test_db_in};
+
+    use axum::{
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+    };
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::{
+        config::AppConfig,
+        middleware::ip::MaybeRemoteAddr,
+        routes::{
+            negotiate::ResponseFormat,
+            paths::{get_operation, move_path},
+            scans::{get_list, ListQuery},
+        },
+        types::{MovePathRequest, ScanOptions},
+    };
+
+    async fn json_body(res: impl IntoResponse) -> serde_json::Value {
+        use http_body_util::BodyExt;
+        let bytes = res.into_response().into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Scans `<tmp>/old/tree`, then moves it to `<tmp>/new/tree`.
+    async fn scanned_and_moved() -> (tempfile::TempDir, AppState, Uuid, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old").join("tree");
+        std::fs::create_dir_all(old.join("sub")).unwrap();
+        std::fs::create_dir_all(old.join("other")).unwrap();
+        std::fs::write(old.join("sub").join("a.txt"), b"aaaa").unwrap();
+        std::fs::write(old.join("b.txt"), b"bb").unwrap();
+
+        let pool = test_db_in(dir.path()).await;
+        let root = old.to_string_lossy().to_string();
+        let id = insert_scan(&pool, "running", &[&root], "default").await;
+        let (tx, _rx) = broadcast::channel(256);
+        let options = ScanOptions { measure_allocated: false, ..Default::default() };
+        crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![root],
+            options,
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            1_000,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE scans SET status='done' WHERE id=?1").bind(id.to_string()).execute(&pool).await.unwrap();
+
+        let new = dir.path().join("new").join("tree");
+        std::fs::create_dir_all(new.parent().unwrap()).unwrap();
+        std::fs::rename(&old, &new).unwrap();
+        (dir, AppState::new(pool, AppConfig::default()), id, old, new)
+    }
+
+    fn request(from: &std::path::Path, to: &std::path::Path) -> RemapRootRequest {
+        RemapRootRequest { from: from.to_string_lossy().to_string(), to: to.to_string_lossy().to_string() }
+    }
+
+    #[test]
+    fn translation_maps_root_and_subtree_only() {
+        let t = RootTranslation::new("X:\\", "Y:\\").unwrap();
+        assert_eq!(t.apply("X:\\"), Some("Y:\\".to_string()));
+        assert_eq!(t.apply("x:\\data\\a.txt"), Some("Y:\\data\\a.txt".to_string()));
+        assert_eq!(t.apply("Z:\\data"), None);
+
+        let t = RootTranslation::new("/mnt/old/", "/srv/new").unwrap();
+        assert_eq!(t.apply("/mnt/old"), Some("/srv/new".to_string()));
+        assert_eq!(t.apply("/mnt/old/a/b"), Some("/srv/new/a/b".to_string()));
+        assert_eq!(t.apply("/mnt/older/a"), None);
+        assert!(RootTranslation::new("/a/", "/a").is_err());
+    }
+
+    #[tokio::test]
+    async fn remapped_scan_lists_and_moves_at_new_location() {
+        let (_dir, state, id, old, new) = scanned_and_moved().await;
+        let ns = Namespace::default();
+
+        let remap = json_body(remap_root(State(state.clone()), ns.clone(), Path(id), ValidJson(request(&old, &new))).await.unwrap()).await;
+        assert_eq!(remap["to"], new.to_string_lossy().to_string());
+        assert!(remap["files_updated"].as_i64().unwrap() >= 2);
+
+        let roots = json_body(get_list(State(state.clone()), ns.clone(), Path(id), Query(ListQuery::default()), ResponseFormat::Json).await.unwrap()).await;
+        assert_eq!(roots[0]["path"], new.to_string_lossy().to_string());
+        let sub = new.join("sub");
+        let q = ListQuery { path: Some(sub.to_string_lossy().to_string()), ..Default::default() };
+        let listing = json_body(get_list(State(state.clone()), ns.clone(), Path(id), Query(q), ResponseFormat::Json).await.unwrap()).await;
+        let expected = sub.join("a.txt").to_string_lossy().to_string();
+        assert!(listing.as_array().unwrap().iter().any(|i| i["path"] == expected.as_str()));
+        let stale: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1 AND path LIKE ?2")
+            .bind(id.to_string())
+            .bind(format!("{}%", old.to_string_lossy()))
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(stale, 0);
+
+        let move_req = MovePathRequest {
+            sources: vec![new.join("b.txt").to_string_lossy().to_string()],
+            destinations: vec![new.join("c.txt").to_string_lossy().to_string()],
+            remove_source: true,
+            overwrite: false,
+        };
+        let res = move_path(State(state.clone()), MaybeRemoteAddr(None), HeaderMap::new(), ValidJson(move_req)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let op_id: Uuid = json_body(res).await["op_id"].as_str().unwrap().parse().unwrap();
+        for _ in 0..200 {
+            let op = json_body(get_operation(State(state.clone()), Path(op_id)).await.unwrap()).await;
+            if op["status"] != "running" {
+                assert_eq!(op["status"], "completed");
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(new.join("c.txt").exists());
+
+        let history = json_body(list_remaps(State(state.clone()), ns, Path(id)).await.unwrap()).await;
+        assert_eq!(history.as_array().unwrap().len(), 1);
+        assert_eq!(history[0]["from"], old.to_string_lossy().to_string());
+    }
+
+    #[tokio::test]
+    async fn remap_rejects_unrelated_or_missing_location() {
+        let (dir, state, id, old, _new) = scanned_and_moved().await;
+        let ns = Namespace::default();
+        let empty = dir.path().join("empty");
+        std::fs::create_dir_all(&empty).unwrap();
+
+        let res = remap_root(State(state.clone()), ns.clone(), Path(id), ValidJson(request(&old, &empty))).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let res = remap_root(State(state.clone()), ns.clone(), Path(id), ValidJson(request(&old, &dir.path().join("nope")))).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let res = remap_root(State(state.clone()), ns.clone(), Path(id), ValidJson(request(&empty, &old))).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+        let other = Namespace::parse("hr").unwrap();
+        let res = remap_root(State(state.clone()), other, Path(id), ValidJson(request(&old, &empty))).await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+
+        let roots: String = sqlx::query_scalar("SELECT root_paths FROM scans WHERE id=?1")
+            .bind(id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        let roots: Vec<String> = serde_json::from_str(&roots).unwrap();
+        assert_eq!(roots, vec![old.to_string_lossy().to_string()]);
+    }
+}
@@ -0,0 +1,293 @@
+//! Reports spanning several scans.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /reports/growth?root=&window=30d&limit=50&sort=absolute|relative` - The
+//!   directories of a root that grew the most between its earliest and latest
+//!   finished scan within a time window
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::{
+        diff::{diff_scans, DiffQuery},
+        scans::{normalize_query_path, query_path_variants},
+    },
+    state::AppState,
+    types::{DiffStatus, DiffValues, GrowthItem, GrowthReportResponse},
+};
+
+const GROWTH_LIMIT_DEFAULT: i64 = 50;
+
+/// Query parameters for the growth report.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct GrowthQuery {
+    /// The scanned root, spelled as in any of its scans.
+    pub root: String,
+    /// How far back to look for scans, e.g. `30d`, `12h` or `8w`; all scans if omitted.
+    pub window: Option<String>,
+    /// The maximum number of directories to return.
+    pub limit: Option<i64>,
+    /// The sort key: "absolute" (growth in bytes, default) or "relative" (growth in percent).
+    pub sort: Option<String>,
+}
+
+/// A finished scan of the requested root.
+struct RootScan {
+    id: Uuid,
+    started_at: String,
+}
+
+/// Parses a window like `30d` into seconds.
+fn parse_window(raw: &str) -> AppResult<i64> {
+    let invalid = || AppError::BadRequest("window must be a number followed by h, d or w, e.g. 30d".into());
+    let raw = raw.trim();
+    let unit = match raw.chars().last() {
+        Some('h') => 3_600,
+        Some('d') => 86_400,
+        Some('w') => 7 * 86_400,
+        _ => return Err(invalid()),
+    };
+    let count: i64 = raw[..raw.len() - 1].parse().map_err(|_| invalid())?;
+    if count <= 0 {
+        return Err(invalid());
+    }
+    count.checked_mul(unit).ok_or_else(invalid)
+}
+
+/// Finds the finished scans visible from `ns` that include `root`, oldest first.
+///
+/// Roots are stored as given when the scan was created, so both sides are
+/// normalized and every spelling of a Windows prefix is accepted.
+async fn scans_of_root(
+    pool: &sqlx::SqlitePool,
+    ns: &Namespace,
+    root: &str,
+    window_secs: Option<i64>,
+) -> AppResult<Vec<RootScan>> {
+    let variants = query_path_variants(&normalize_query_path(root)?);
+    let rows = sqlx::query(
+        r#"SELECT id, root_paths, started_at FROM scans
+           WHERE status = 'done' AND (?1 OR namespace = ?2)
+             AND (?3 IS NULL OR started_at >= strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-' || ?3 || ' seconds'))
+           ORDER BY started_at ASC, id ASC"#,
+    )
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .bind(window_secs)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .filter(|r| {
+            serde_json::from_str::<Vec<String>>(&r.get::<String, _>("root_paths"))
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|stored| normalize_query_path(stored).ok())
+                .any(|stored| variants.contains(&stored))
+        })
+        .filter_map(|r| {
+            let id = Uuid::parse_str(&r.get::<String, _>("id")).ok()?;
+            Some(RootScan { id, started_at: r.get("started_at") })
+        })
+        .collect())
+}
+
+/// Lists the directories of a root that grew the most within a time window.
+///
+/// The earliest and latest finished scan of the root in the window are
+/// compared with the same logic as `GET /scans/{id}/diff`, restricted to the
+/// root's subtree. Only directories that grew are returned; directories
+/// missing from the older scan count with their full size and are flagged
+/// with `is_new`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request; only its scans are considered.
+/// * `q` - The report parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `GrowthReportResponse`.
+pub async fn get_growth(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Query(q): Query<GrowthQuery>,
+) -> AppResult<impl IntoResponse> {
+    Ok(Json(growth_report(&state, &ns, &q).await?))
+}
+
+/// Computes the report behind `GET /reports/growth`.
+pub async fn growth_report(
+    state: &AppState,
+    ns: &Namespace,
+    q: &GrowthQuery,
+) -> AppResult<GrowthReportResponse> {
+    if q.root.len() > 4096 {
+        return Err(AppError::BadRequest("Path too long".into()));
+    }
+    let window_secs = q.window.as_deref().map(parse_window).transpose()?;
+    let scans = scans_of_root(state.read_pool(), ns, &q.root, window_secs).await?;
+    let mut resp = GrowthReportResponse {
+        root: q.root.clone(),
+        older_scan_id: None,
+        newer_scan_id: None,
+        older_started_at: None,
+        newer_started_at: None,
+        size_basis: "allocated".to_string(),
+        items: Vec::new(),
+    };
+    let (Some(older), Some(newer)) = (scans.first(), scans.last().filter(|_| scans.len() > 1)) else {
+        return Ok(resp);
+    };
+    resp.older_scan_id = Some(older.id);
+    resp.newer_scan_id = Some(newer.id);
+    resp.older_started_at = Some(older.started_at.clone());
+    resp.newer_started_at = Some(newer.started_at.clone());
+
+    // Keep both scans from being pruned while they are compared
+    let _leases = (state.scan_leases.acquire(newer.id), state.scan_leases.acquire(older.id));
+    let dq = DiffQuery {
+        other: older.id,
+        sort: q.sort.clone(),
+        order: None,
+        limit: Some(q.limit.unwrap_or(GROWTH_LIMIT_DEFAULT)),
+        path: Some(q.root.clone()),
+    };
+    let diff = diff_scans(state.read_pool(), newer.id, &dq).await?;
+    let size = |v: &DiffValues| if diff.allocated_comparable { v.allocated_size } else { v.logical_size };
+    resp.items = diff
+        .items
+        .into_iter()
+        .map(|item| {
+            let old_size = item.old.as_ref().map(size).unwrap_or(0);
+            let new_size = item.new.as_ref().map(size).unwrap_or(0);
+            GrowthItem {
+                path: item.path,
+                is_new: item.status == DiffStatus::Added,
+                old_size,
+                new_size,
+                growth: new_size - old_size,
+                growth_percent: item.relative_growth.map(|r| r * 100.0),
+            }
+        })
+        .filter(|item| item.growth > 0)
+        .collect();
+    resp.size_basis = diff.size_basis;
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_node, test_db, NodeRow};
+
+    async fn insert_scan(
+        state: &AppState,
+        root: &str,
+        started_at: &str,
+        namespace: &str,
+        dirs: &[(&str, i64)],
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO scans (id, status, root_paths, options, started_at, namespace) VALUES (?1, 'done', ?2, '{}', ?3, ?4)",
+        )
+        .bind(id.to_string())
+        .bind(serde_json::to_string(&[root]).unwrap())
+        .bind(started_at)
+        .bind(namespace)
+        .execute(&state.db)
+        .await
+        .unwrap();
+        for &(path, size) in dirs {
+            insert_node(&state.db, id, NodeRow { path, size, files: 1, ..Default::default() }).await;
+        }
+        id
+    }
+
+    fn days_ago(days: i64) -> String {
+        (chrono::Utc::now() - chrono::Duration::days(days)).format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    fn query(root: &str, window: Option<&str>) -> GrowthQuery {
+        GrowthQuery { root: root.into(), window: window.map(Into::into), ..Default::default() }
+    }
+
+    #[test]
+    fn windows_parse_with_units() {
+        assert_eq!(parse_window("30d").unwrap(), 30 * 86_400);
+        assert_eq!(parse_window("12h").unwrap(), 12 * 3_600);
+        assert_eq!(parse_window("2w").unwrap(), 14 * 86_400);
+        for bad in ["", "d", "30", "-1d", "0d", "1y", "xd"] {
+            assert!(parse_window(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn growth_compares_earliest_and_latest_scan_in_window() {
+        let (_dir, pool) = test_db().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+
+        // Outside the window, so the 40-day-old scan is not the baseline
+        insert_scan(&state, "/data", &days_ago(40), "default", &[("/data", 1), ("/data/a", 1)]).await;
+        let older = insert_scan(
+            &state,
+            "/data/",
+            &days_ago(20),
+            "default",
+            &[("/data", 600), ("/data/a", 100), ("/data/b", 500)],
+        )
+        .await;
+        insert_scan(&state, "/data", &days_ago(10), "default", &[("/data", 5000), ("/data/a", 5000)]).await;
+        let newer = insert_scan(
+            &state,
+            "/data",
+            &days_ago(1),
+            "default",
+            &[("/data", 1300), ("/data/a", 400), ("/data/b", 300), ("/data/new", 600)],
+        )
+        .await;
+        // Other roots and other namespaces are ignored
+        insert_scan(&state, "/other", &days_ago(0), "default", &[("/other", 9999)]).await;
+        insert_scan(&state, "/data", &days_ago(0), "team", &[("/data", 9999)]).await;
+
+        let ns = Namespace::parse("default").unwrap();
+        let resp = growth_report(&state, &ns, &query("/data", Some("30d"))).await.unwrap();
+        assert_eq!(resp.older_scan_id, Some(older));
+        assert_eq!(resp.newer_scan_id, Some(newer));
+        assert_eq!(resp.size_basis, "allocated");
+        let paths: Vec<&str> = resp.items.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["/data", "/data/new", "/data/a"]);
+
+        let new = &resp.items[1];
+        assert!(new.is_new);
+        assert_eq!((new.old_size, new.new_size, new.growth), (0, 600, 600));
+        assert_eq!(new.growth_percent, None);
+        let grown = &resp.items[2];
+        assert!(!grown.is_new);
+        assert_eq!(grown.growth, 300);
+        assert_eq!(grown.growth_percent, Some(300.0));
+
+        let mut q = query("/data", Some("30d"));
+        q.sort = Some("relative".into());
+        q.limit = Some(1);
+        let resp = growth_report(&state, &ns, &q).await.unwrap();
+        assert_eq!(resp.items.len(), 1);
+        assert_eq!(resp.items[0].path, "/data/new");
+
+        let resp = growth_report(&state, &ns, &query("/data", Some("5d"))).await.unwrap();
+        assert!(resp.older_scan_id.is_none());
+        assert!(resp.items.is_empty());
+        assert!(growth_report(&state, &ns, &query("/data", Some("soon"))).await.is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! Scan retention API endpoints.
+//!
+//! The policy itself is configured in the `[retention]` config section and
+//! applied periodically by [`crate::retention`].
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/retention` - The policy and the scans the next prune would remove
+//! - `POST /scans/retention/run` - Prune right away
+//!
+//! The policy is server-wide, but both endpoints only report scans visible
+//! from the request's namespace.
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{
+    error::AppResult,
+    middleware::{
+        ip::{extract_ip_from_headers, MaybeRemoteAddr},
+        namespace::Namespace,
+    },
+    retention::{self, Selected},
+    state::AppState,
+    types::{RetentionCandidate, RetentionReport, RetentionRunResponse},
+};
+
+fn visible(ns: &Namespace, selected: &[Selected]) -> Vec<RetentionCandidate> {
+    selected
+        .iter()
+        .filter(|s| ns.is_admin() || s.record.namespace == ns.as_str())
+        .map(Selected::to_dto)
+        .collect()
+}
+
+/// Reports the retention policy and the scans the next prune would remove.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `RetentionReport`.
+pub async fn get_retention(State(state): State<AppState>, ns: Namespace) -> AppResult<impl IntoResponse> {
+    let selected = retention::plan(&state).await?;
+    Ok(Json(RetentionReport {
+        policy: retention::policy_dto(&state.config.get().retention),
+        candidates: visible(&ns, &selected),
+    }))
+}
+
+/// Applies the retention policy right away.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON response containing a `RetentionRunResponse`.
+pub async fn run_retention(
+    State(state): State<AppState>,
+    ns: Namespace,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/scans/retention/run", ip).await {
+        return Ok(limited.into_response());
+    }
+
+    let started = std::time::Instant::now();
+    let outcome = retention::prune(&state).await?;
+    Ok(Json(RetentionRunResponse {
+        deleted: visible(&ns, &outcome.deleted),
+        skipped: visible(&ns, &outcome.skipped),
+        vacuumed: outcome.vacuumed,
+        duration_ms: started.elapsed().as_millis(),
+    })
+    .into_response())
+}
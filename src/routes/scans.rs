@@ -20,11 +20,17 @@
 //! - `GET /scans` - List all scans
 //! - `GET /scans/{id}` - Get scan details
 //! - `DELETE /scans/{id}` - Cancel/delete scan
-//! - `GET /scans/{id}/events` - Stream real-time scan events
+//! - `POST /scans/{id}/pause` - Pause a running scan
+//! - `POST /scans/{id}/resume` - Resume a paused scan or restart an interrupted one
+//! - `GET /scans/{id}/events?types=&progress_interval=` - Stream real-time scan events
 //! - `GET /scans/{id}/tree` - Get hierarchical directory tree
 //! - `GET /scans/{id}/top` - Get largest items
 //! - `GET /scans/{id}/recent` - Get recently accessed items
 //! - `GET /scans/{id}/list` - List directory contents
+//! - `GET /scans/{id}/complete` - Autocomplete paths from scan data
+//!
+//! Tree, top and list responses carry a weak `ETag` (see [`scan_etag`]); for
+//! settled scans a matching `If-None-Match` is answered with `304 Not Modified`.
 //!
 //! ## Security Considerations
 //!
@@ -33,12 +39,17 @@
 //! - Database operations use transactions for consistency
 //! - Large result sets are paginated to prevent resource exhaustion
 
-use std::{path::{Path as StdPath, PathBuf}, time::Duration};
+use std::{
+    path::{Path as StdPath, PathBuf},
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
 
 use axum::response::sse::{Event, Sse};
 use axum::{
-    extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
@@ -49,18 +60,23 @@ use sqlx::{QueryBuilder, Row};
 use tokio::{sync::broadcast, task::JoinHandle};
 use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::{
+    config::SystemExcludesConfig,
     error::{AppError, AppResult},
     middleware::ip::{extract_ip_from_headers, MaybeRemoteAddr},
-    middleware::validation::{validate_file_path, validate_scan_options},
-    scanner,
-    state::{AppState, JobHandle},
+    middleware::namespace::Namespace,
+    middleware::validation::{validate_file_path, validate_scan_options, ValidJson},
+    routes::negotiate::{respond_negotiated, ResponseFormat},
+    scanner::{self, ScanResultSummary},
+    state::{retain_finished_events, AppState, EventLog, JobHandle},
     types::{
-        CreateScanRequest, CreateScanResponse, ListItem, NodeDto, ScanEvent, ScanOptions, ScanSummary,
-        TopItem,
+        CompletionDto, CreateScanRequest, CreateScanResponse, ListItem, ListResponse, NodeDto, RecentItem,
+        ExpectedTotals, RootSummary, ScanEstimate, ScanEvent, ScanOptions, ScanSummary, TopItem,
     },
+    webhooks,
 };
 
 /// Creates a new scan.
@@ -73,6 +89,7 @@ use crate::{
 ///
 /// * `state` - The application state.
 /// * `remote` - The optional remote address of the client.
+/// * `ns` - The namespace the new scan is created in.
 /// * `headers` - The request headers.
 /// * `req` - The create scan request payload.
 ///
@@ -82,16 +99,101 @@ use crate::{
 pub async fn create_scan(
     State(state): State<AppState>,
     remote: MaybeRemoteAddr,
+    ns: Namespace,
     headers: HeaderMap,
-    Json(req): Json<CreateScanRequest>,
+    ValidJson(req): ValidJson<CreateScanRequest>,
 ) -> AppResult<Response> {
     // Per-endpoint rate limit: "/scans"
     let fallback_ip = remote.0.map(|addr| addr.ip());
     let ip = extract_ip_from_headers(&headers, fallback_ip);
-    if let Err((status, body)) = state.rate_limiter.check_endpoint_limit("/scans", ip).await {
-        return Ok((status, body).into_response());
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/scans", ip).await {
+        return Ok(limited.into_response());
+    }
+
+    let resp = start_scan(&state, req, &ns).await?;
+    Ok((StatusCode::ACCEPTED, Json(resp)).into_response())
+}
+
+/// Query parameters for the scan estimate endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct EstimateQuery {
+    /// How long sampling may take in seconds, 10 by default and at most 120.
+    pub budget_secs: Option<u64>,
+}
+
+/// Estimates how large a scan would get without running it.
+///
+/// Takes the same body as `POST /scans`. The first levels below each root are
+/// listed completely and deeper levels are sampled until the time budget runs
+/// out; nothing is written to the database. Roots that cannot be scanned are
+/// reported per root instead of failing the request.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `q` - The query parameters, see [`EstimateQuery`].
+/// * `req` - The create scan request to estimate.
+///
+/// # Returns
+///
+/// * `AppResult<Json<ScanEstimate>>` - The estimated counts with their confidence bands.
+pub async fn estimate_scan(
+    State(state): State<AppState>,
+    Query(q): Query<EstimateQuery>,
+    ValidJson(req): ValidJson<CreateScanRequest>,
+) -> AppResult<Json<ScanEstimate>> {
+    validate_scan_request(&req)?;
+    let (root_paths, warnings) = normalize_root_paths(&req.root_paths)?;
+    let options = apply_scan_defaults(&state, &req)?;
+    let budget = Duration::from_secs(q.budget_secs.unwrap_or(10).clamp(1, 120));
+    let seed = Uuid::new_v4().as_u64_pair().0;
+
+    let mut estimate = tokio::task::spawn_blocking(move || {
+        let _priority = scanner::priority::enter(options.io_priority);
+        scanner::estimate::estimate_roots(&root_paths, &options, budget, seed)
+    })
+    .await
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Estimate task failed: {}", e)))??;
+    estimate.warnings = warnings;
+    Ok(Json(estimate))
+}
+
+/// Validates a create scan request and resolves the effective scan options.
+///
+/// Missing fields are filled from the configured scan defaults and exclude
+/// patterns are normalized, so the returned options are exactly what the
+/// scanner will run with.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `req` - The create scan request to validate.
+///
+/// # Returns
+///
+/// * `AppResult<ScanOptions>` - The effective scan options.
+pub async fn resolve_scan_options(state: &AppState, req: &CreateScanRequest) -> AppResult<ScanOptions> {
+    validate_scan_request(req)?;
+    // Rejects duplicates; start_scan also drops nested roots
+    normalize_root_paths(&req.root_paths)?;
+
+    // Validate roots exist
+    for p in &req.root_paths {
+        let pb = PathBuf::from(p);
+        let meta = tokio::fs::metadata(&pb)
+            .await
+            .map_err(|_| AppError::BadRequest(format!("root path does not exist: {}", p)))?;
+        if !meta.is_dir() {
+            return Err(AppError::BadRequest(format!("root path is not a directory: {}", p)));
+        }
     }
 
+    apply_scan_defaults(state, req)
+}
+
+/// Validates the root paths and options of a create scan request without
+/// touching the root paths on disk.
+fn validate_scan_request(req: &CreateScanRequest) -> AppResult<()> {
     if req.root_paths.is_empty() {
         return Err(AppError::BadRequest("root_paths must not be empty".into()));
     }
@@ -104,94 +206,376 @@ pub async fn create_scan(
     // Validate scan options
     validate_scan_options(req.max_depth, req.concurrency)
         .map_err(|_| AppError::InvalidInput("Invalid scan options".into()))?;
+    Ok(())
+}
 
-    // Validate roots exist
-    for p in &req.root_paths {
-        let pb = PathBuf::from(p);
-        let meta = tokio::fs::metadata(&pb)
-            .await
-            .map_err(|_| AppError::BadRequest(format!("root path does not exist: {}", p)))?;
-        if !meta.is_dir() {
-            return Err(AppError::BadRequest(format!("root path is not a directory: {}", p)));
+/// Fills the options a create scan request leaves out from the configured scan defaults.
+///
+/// Scans of a system drive also get the `[system_excludes]` patterns unless the
+/// request opts out with `no_system_excludes`.
+pub(crate) fn apply_scan_defaults(state: &AppState, req: &CreateScanRequest) -> AppResult<ScanOptions> {
+    let config = state.config.get();
+    let d = &config.scan_defaults;
+    // Normalize and validate glob patterns early (improves cache hit-rate and avoids late failures)
+    let mut excludes_norm =
+        normalize_patterns(req.excludes.clone().unwrap_or_else(|| d.excludes.clone()), "exclude")?;
+    let opt_out = req.no_system_excludes.unwrap_or(false);
+    if !opt_out && scans_system_drive(&config.system_excludes, &req.root_paths) {
+        let system = normalize_patterns(config.system_excludes.patterns().to_vec(), "system exclude")?;
+        merge_patterns(&mut excludes_norm, system);
+    }
+    let includes_norm =
+        normalize_patterns(req.includes.clone().unwrap_or_else(|| d.includes.clone()), "include")?;
+
+    Ok(ScanOptions {
+        follow_symlinks: req.follow_symlinks.unwrap_or(d.follow_symlinks),
+        include_hidden: req.include_hidden.unwrap_or(d.include_hidden),
+        measure_logical: req.measure_logical.unwrap_or(d.measure_logical),
+        measure_allocated: req.measure_allocated.unwrap_or(d.measure_allocated),
+        excludes: excludes_norm,
+        includes: includes_norm,
+        max_depth: req.max_depth.or(d.max_depth),
+        concurrency: req.concurrency.or(d.concurrency),
+        measure_hardlinks: req.measure_hardlinks.unwrap_or(d.measure_hardlinks),
+        measure_ads: req.measure_ads.unwrap_or(d.measure_ads),
+        io_priority: req.io_priority.unwrap_or(d.io_priority),
+        capture_owner: req.capture_owner.unwrap_or(d.capture_owner),
+        access_mode: req.access_mode.unwrap_or(d.access_mode),
+        hash_min_size: req.hash_min_size.or(d.hash_min_size),
+    })
+}
+
+/// Returns whether one of `roots` is a system drive as configured in `[system_excludes]`.
+fn scans_system_drive(config: &SystemExcludesConfig, roots: &[String]) -> bool {
+    let drives: Vec<String> =
+        config.system_drives().iter().filter_map(|d| normalize_root(d).ok()).map(|(_, key)| key).collect();
+    roots.iter().filter_map(|r| normalize_root(r).ok()).any(|(_, key)| drives.contains(&key))
+}
+
+/// Appends the normalized `extra` patterns that `patterns` does not contain yet.
+///
+/// The patterns already present keep their order and are never dropped, so the
+/// merged list stays reproducible from the stored scan options.
+fn merge_patterns(patterns: &mut Vec<String>, extra: Vec<String>) {
+    for pat in extra {
+        if !patterns.contains(&pat) {
+            patterns.push(pat);
         }
     }
+}
 
-    let id = Uuid::new_v4();
-    // Larger broadcast channel to prevent dropped messages in fast scans
-    // Use configurable channel size with safe bounds
-    let channel_size = std::env::var("SPEICHERWALD_EVENT_CHANNEL_SIZE")
-        .ok()
-        .and_then(|v| v.parse::<usize>().ok())
-        .unwrap_or(4096)
-        .clamp(512, 16384);
-    let (tx, _rx) = broadcast::channel::<ScanEvent>(channel_size);
-    let cancel = CancellationToken::new();
+/// Normalizes root paths and drops roots inside another root.
+///
+/// Trailing separators and `.` components are removed and Windows paths get
+/// backslashes and an upper-case drive letter. Windows paths are compared
+/// case-insensitively; a root only counts as nested if the other root is
+/// followed by a separator, so `D:\Data` does not contain `D:\Database`.
+///
+/// # Arguments
+///
+/// * `roots` - The root paths as given in the request.
+///
+/// # Returns
+///
+/// * `AppResult<(Vec<String>, Vec<String>)>` - The roots to scan, in request
+///   order, and one warning per dropped nested root. Roots naming the same
+///   directory are rejected.
+pub(crate) fn normalize_root_paths(roots: &[String]) -> AppResult<(Vec<String>, Vec<String>)> {
+    let mut normalized: Vec<(String, String)> = Vec::with_capacity(roots.len());
+    for root in roots {
+        let (path, key) = normalize_root(root)?;
+        if let Some((first, _)) = normalized.iter().find(|(_, k)| *k == key) {
+            return Err(AppError::BadRequest(format!("duplicate root path: {} (same as {})", root, first)));
+        }
+        normalized.push((path, key));
+    }
 
-    // Metrics: count scan start
-    state.metrics.inc_scans_started();
+    let mut kept = Vec::with_capacity(normalized.len());
+    let mut warnings = Vec::new();
+    for (path, key) in &normalized {
+        match normalized.iter().find(|(_, other)| key_contains(other, key)) {
+            Some((outer, _)) => {
+                warnings.push(format!("Skipped root path {}: it is inside root path {}", path, outer))
+            }
+            None => kept.push(path.clone()),
+        }
+    }
+    Ok((kept, warnings))
+}
 
-    // Persist initial scan row
-    let root_paths_json = serde_json::to_string(&req.root_paths)
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize root_paths: {}", e)))?;
-    // Apply config defaults if fields are None
-    let d = &state.config.scan_defaults;
-    // Normalize and validate exclude patterns early (improves cache hit-rate and avoids late failures)
-    let excludes_src: Vec<String> = req.excludes.clone().unwrap_or_else(|| d.excludes.clone());
-    let mut excludes_norm: Vec<String> = Vec::with_capacity(excludes_src.len());
-    for pat in excludes_src {
+/// Returns whether the normalized key `inner` lies strictly below `outer`.
+fn key_contains(outer: &str, inner: &str) -> bool {
+    inner.len() > outer.len()
+        && inner.starts_with(outer)
+        && (outer.ends_with(['/', '\\']) || inner[outer.len()..].starts_with(['/', '\\']))
+}
+
+/// Normalizes a root path and returns it with the key roots are compared by;
+/// the key of a Windows path is lower-cased.
+fn normalize_root(root: &str) -> AppResult<(String, String)> {
+    let mut path = normalize_query_path(root.trim())
+        .map_err(|_| AppError::InvalidInput(format!("Invalid path: {}", root)))?;
+    if starts_with_drive(&path) {
+        path[..1].make_ascii_uppercase();
+    }
+    let key = if split_windows_prefix(&path).is_some() { path.to_lowercase() } else { path.clone() };
+    Ok((path, key))
+}
+
+/// Returns whether `path` is the root path `root` or lies below it.
+///
+/// Both are normalized like the roots of `POST /scans`, so Windows paths are
+/// compared case-insensitively and `D:\Data` does not contain `D:\Database`.
+/// Paths that cannot be normalized are never within a root.
+pub(crate) fn is_within_root(root: &str, path: &str) -> bool {
+    match (normalize_root(root), normalize_root(path)) {
+        (Ok((_, root)), Ok((_, path))) => root == path || key_contains(&root, &path),
+        _ => false,
+    }
+}
+
+/// Trims glob patterns, normalizes their separators and rejects invalid ones.
+///
+/// # Arguments
+///
+/// * `patterns` - The patterns as given in the request or the defaults.
+/// * `kind` - `exclude` or `include`, used in the error message.
+pub(crate) fn normalize_patterns(patterns: Vec<String>, kind: &str) -> AppResult<Vec<String>> {
+    let mut norm_patterns: Vec<String> = Vec::with_capacity(patterns.len());
+    for pat in patterns {
         let norm = pat.trim().replace('\\', "/");
         if norm.is_empty() {
             continue;
         }
         if let Err(e) = Glob::new(&norm) {
-            return Err(AppError::InvalidInput(format!("Invalid exclude pattern: {} ({})", pat, e)));
+            return Err(AppError::InvalidInput(format!("Invalid {} pattern: {} ({})", kind, pat, e)));
         }
-        excludes_norm.push(norm);
+        norm_patterns.push(norm);
     }
+    Ok(norm_patterns)
+}
 
-    let options = ScanOptions {
-        follow_symlinks: req.follow_symlinks.unwrap_or(d.follow_symlinks),
-        include_hidden: req.include_hidden.unwrap_or(d.include_hidden),
-        measure_logical: req.measure_logical.unwrap_or(d.measure_logical),
-        measure_allocated: req.measure_allocated.unwrap_or(d.measure_allocated),
-        excludes: excludes_norm,
-        max_depth: req.max_depth.or(d.max_depth),
-        concurrency: req.concurrency.or(d.concurrency),
+/// Registers a scan job and runs the scanner in the background.
+///
+/// This is shared by `POST /scans` and the recurring scan scheduler, so both
+/// paths get the same job registration, SSE events and metrics.
+/// Root paths are normalized first; roots inside another root are dropped
+/// with a warning in the response instead of being counted twice.
+/// With `rescan_of` set, directories unchanged since that finished scan are
+/// taken over from it instead of being traversed again.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `req` - The create scan request.
+/// * `ns` - The namespace the scan is stamped with.
+///
+/// # Returns
+///
+/// * `AppResult<CreateScanResponse>` - The ID, status, start time and root path warnings of the new scan.
+pub async fn start_scan(state: &AppState, mut req: CreateScanRequest, ns: &Namespace) -> AppResult<CreateScanResponse> {
+    let (root_paths, warnings) = normalize_root_paths(&req.root_paths)?;
+    req.root_paths = root_paths;
+    let options = resolve_scan_options(state, &req).await?;
+    let reuse = match req.rescan_of {
+        Some(previous) => {
+            ns.ensure_scan(&state.db, previous).await?;
+            let status: String = sqlx::query_scalar("SELECT status FROM scans WHERE id=?1")
+                .bind(previous.to_string())
+                .fetch_one(&state.db)
+                .await?;
+            if status != "done" {
+                return Err(AppError::Conflict(format!(
+                    "scan {} is {}, only finished scans can be rescanned",
+                    previous, status
+                )));
+            }
+            scanner::Reuse::Rescan(previous)
+        }
+        None => scanner::Reuse::Nothing,
     };
+    for warning in &warnings {
+        tracing::warn!("{}", warning);
+    }
+    let expected = expected_totals(&state.db, ns.as_str(), &req.root_paths, req.rescan_of).await?;
+
+    let id = Uuid::new_v4();
+
+    // Metrics: count scan start
+    state.metrics.inc_scans_started();
+
+    // Persist initial scan row
+    let root_paths_json = serde_json::to_string(&req.root_paths)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize root_paths: {}", e)))?;
     let options_json = serde_json::to_string(&options)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize options: {}", e)))?;
 
+    let read_only = req.read_only.unwrap_or(false);
     sqlx::query(
-        r#"INSERT INTO scans (id, status, root_paths, options)
-           VALUES (?1, 'running', ?2, ?3)"#,
+        r#"INSERT INTO scans (id, status, root_paths, options, namespace, read_only)
+           VALUES (?1, 'running', ?2, ?3, ?4, ?5)"#,
     )
     .bind(id.to_string())
     .bind(root_paths_json)
     .bind(options_json)
+    .bind(ns.as_str())
+    .bind(read_only)
     .execute(&state.db)
     .await?;
+    if read_only {
+        state.read_only_roots.invalidate().await;
+    }
+
+    spawn_scan_job(state, id, req.root_paths.clone(), options, reuse, expected).await;
+
+    Ok(CreateScanResponse { id, status: "running".into(), started_at: started_at(state, id).await, warnings })
+}
+
+/// Looks up the totals a new scan of `root_paths` is expected to reach.
+///
+/// The scan a rescan takes over from is used if given, otherwise the most
+/// recently finished scan of the namespace with the same roots in any order.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `namespace` - The namespace of the new scan.
+/// * `root_paths` - The normalized roots of the new scan.
+/// * `rescan_of` - The finished scan a rescan takes over from.
+///
+/// # Returns
+///
+/// * `AppResult<Option<ExpectedTotals>>` - The totals, or `None` without an earlier scan.
+async fn expected_totals(
+    pool: &sqlx::SqlitePool,
+    namespace: &str,
+    root_paths: &[String],
+    rescan_of: Option<Uuid>,
+) -> AppResult<Option<ExpectedTotals>> {
+    let totals = |r: &sqlx::sqlite::SqliteRow| -> AppResult<Option<ExpectedTotals>> {
+        let dirs = r.get::<Option<i64>, _>("dir_count").unwrap_or(0).max(0) as u64;
+        let files = r.get::<Option<i64>, _>("file_count").unwrap_or(0).max(0) as u64;
+        let scan_id = Uuid::parse_str(&r.get::<String, _>("id"))
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid scan id: {}", e)))?;
+        Ok((dirs + files > 0).then_some(ExpectedTotals { scan_id, dirs, files }))
+    };
+    if let Some(previous) = rescan_of {
+        let row = sqlx::query("SELECT id, dir_count, file_count FROM scans WHERE id=?1")
+            .bind(previous.to_string())
+            .fetch_one(pool)
+            .await?;
+        return totals(&row);
+    }
+
+    let keys = |roots: &[String]| -> Option<Vec<String>> {
+        let mut keys: Vec<String> =
+            roots.iter().map(|root| normalize_root(root).ok().map(|(_, key)| key)).collect::<Option<_>>()?;
+        keys.sort();
+        Some(keys)
+    };
+    let wanted = keys(root_paths);
+    let mut rows = sqlx::query(
+        "SELECT id, root_paths, dir_count, file_count FROM scans \
+         WHERE status='done' AND namespace=?1 AND origin IS NULL ORDER BY finished_at DESC",
+    )
+    .bind(namespace)
+    .fetch(pool);
+    while let Some(row) = rows.next().await {
+        let row = row?;
+        let roots: Vec<String> =
+            serde_json::from_str(&row.get::<String, _>("root_paths")).unwrap_or_default();
+        if wanted.is_some() && keys(&roots) == wanted {
+            return totals(&row);
+        }
+    }
+    Ok(None)
+}
+
+/// Reads back the ISO UTC start time of a scan for responses.
+async fn started_at(state: &AppState, id: Uuid) -> String {
+    sqlx::query("SELECT started_at FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await
+        .ok()
+        .and_then(|row| row.try_get::<String, _>("started_at").ok())
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339())
+}
+
+/// Registers the job of a scan whose row is `running` and runs it in the background.
+///
+/// The task writes the final status and totals of the scan, keeps its last
+/// events for replay and removes the job again.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `id` - The ID of the scan.
+/// * `root_paths` - The roots to scan.
+/// * `options` - The resolved scan options.
+/// * `reuse` - The stored rows the scan takes over. A resumed scan's totals
+///   are recomputed from the stored rows when it finishes.
+/// * `expected` - The totals the scan's progress is estimated against.
+async fn spawn_scan_job(
+    state: &AppState,
+    id: Uuid,
+    root_paths: Vec<String>,
+    options: ScanOptions,
+    reuse: scanner::Reuse,
+    expected: Option<ExpectedTotals>,
+) {
+    // Larger broadcast channel to prevent dropped messages in fast scans
+    // Use configurable channel size with safe bounds
+    let channel_size = std::env::var("SPEICHERWALD_EVENT_CHANNEL_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(4096)
+        .clamp(512, 16384);
+    let (tx, _rx) = broadcast::channel::<ScanEvent>(channel_size);
+    let cancel = CancellationToken::new();
+    let started = ScanEvent::Started { root_paths: root_paths.clone(), expected_totals: expected };
 
     // FIX Bug #2: Register job BEFORE spawning background task to avoid race condition
     // where the task completes/cleans up before we insert the handle.
+    let handle = JobHandle::new(cancel.clone(), tx.clone()).with_expected_totals(expected);
+    let finalize = handle.finalize.clone();
+    let finished = handle.finished.clone();
+    let events = handle.events.clone();
+    let own_events = events.clone();
+    let pause = handle.pause.clone();
     {
         let mut jobs = state.jobs.write().await;
-        jobs.insert(id, JobHandle { cancel: cancel.clone(), sender: tx.clone() });
+        jobs.insert(id, handle);
     }
 
     // Spawn background task
     let db = state.db.clone();
     let tx_clone = tx.clone();
     let cancel_child = cancel.clone();
-    let root_paths = req.root_paths.clone();
-    let batch_size = state.config.scanner.batch_size;
-    let flush_threshold = state.config.scanner.flush_threshold;
-    let flush_interval_ms = state.config.scanner.flush_interval_ms;
-    let handle_limit = state.config.scanner.handle_limit;
-    let dir_concurrency = options.concurrency.or(state.config.scanner.dir_concurrency);
+    // The job keeps the settings it starts with even if the config is reloaded
+    let config = state.config.get();
+    let batch_size = config.scanner.batch_size;
+    let flush_threshold = config.scanner.flush_threshold;
+    let max_buffer_bytes = config.scanner.max_buffer_bytes;
+    let flush_interval_ms = config.scanner.flush_interval_ms;
+    let handle_limit = config.scanner.handle_limit;
+    let dir_concurrency = options.concurrency.or(config.scanner.dir_concurrency);
+    let max_entries_per_dir = config.scanner.max_entries_per_dir;
+    let persist_retry = scanner::PersistRetry::from_config(&config.scanner);
     let jobs_map = state.jobs.clone();
+    let finished_events = state.finished_events.clone();
+    let replay_grace = Duration::from_secs(config.sse.replay_grace_secs);
     let metrics = state.metrics.clone();
+    let categories = config.categories.clone();
+    let analyze_after_scan = config.database.analyze_after_scan;
+    // Read when the scan ends, so receivers added by a reload are notified as well
+    let shared_config = state.config.clone();
 
-    let _handle: JoinHandle<()> = tokio::spawn(async move {
+    // Logged in the request that started the scan, whose records the scan's own do not carry
+    tracing::info!(scan_id = %id, "Scan started");
+    let span = tracing::info_span!(parent: None, "scan", scan_id = %id);
+    let job = async move {
+        let scan_started = Instant::now();
         let res = scanner::run_scan(
             db.clone(),
             id,
@@ -199,17 +583,32 @@ pub async fn create_scan(
             options.clone(),
             tx_clone.clone(),
             cancel_child.clone(),
+            pause,
             batch_size,
             flush_threshold,
+            max_buffer_bytes,
             flush_interval_ms,
             handle_limit,
             dir_concurrency,
+            max_entries_per_dir,
+            persist_retry,
+            reuse,
+            &metrics,
+            &categories,
         )
         .await;
+        let elapsed = scan_started.elapsed();
+        tracing::info!(duration_ms = elapsed.as_millis() as u64, ok = res.is_ok(), "Scan finished");
+        let mut error = None;
         match res {
             Ok(summary) => {
                 // FIX Bug #10: Check cancellation before marking as done
-                if cancel_child.is_cancelled() {
+                if cancel_child.is_cancelled() && finalize.load(Ordering::SeqCst) {
+                    let _ = tx_clone.send(ScanEvent::Cancelled);
+                    if let Err(e) = finalize_partial_scan(&db, id, Some(&summary)).await {
+                        tracing::error!("Failed to finalize partial scan {}: {}", id, e);
+                    }
+                } else if cancel_child.is_cancelled() {
                    // ... (same as Err(cancelled) block)
                    let _ = tx_clone.send(ScanEvent::Cancelled);
                    if let Err(e) = sqlx::query(
@@ -227,31 +626,57 @@ pub async fn create_scan(
                     metrics.add_files(summary.total_files);
                     metrics.add_bytes(summary.total_allocated_size);
                     metrics.add_warnings(summary.warnings as usize);
+                    let elapsed_ms = elapsed.as_millis().min(u64::MAX as u128) as u64;
                     let _ = tx_clone.send(ScanEvent::Done {
                         total_dirs: summary.total_dirs,
                         total_files: summary.total_files,
                         total_logical_size: summary.total_logical_size,
                         total_allocated_size: summary.total_allocated_size,
+                        roots: summary.roots.clone(),
+                        duration_ms: elapsed_ms,
+                        dirs_per_sec: per_second(summary.total_dirs, elapsed_ms),
+                        files_per_sec: per_second(summary.total_files, elapsed_ms),
+                        bytes_per_sec: per_second(summary.total_allocated_size, elapsed_ms),
+                        buffer_peak_bytes: summary.buffer_peak_bytes,
                     });
                     // FIX Bug #59 - Log DB update errors
-                    if let Err(e) = sqlx::query(
+                    if reuse == scanner::Reuse::Resume {
+                        // Subtrees taken over from the earlier run are only counted in the rows
+                        if let Err(e) = store_totals_from_rows(&db, id, "done", Some(&summary)).await {
+                            tracing::error!("Failed to update resumed scan status to done: {}", e);
+                        }
+                    } else if let Err(e) = sqlx::query(
                         r#"UPDATE scans SET status='done', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now'),
-                            total_logical_size=?1, total_allocated_size=?2, dir_count=?3, file_count=?4, warning_count=?5
-                            WHERE id=?6"#
+                            total_logical_size=?1, total_allocated_size=?2, dir_count=?3, file_count=?4, warning_count=?5,
+                            dedup_saved_bytes=?6
+                            WHERE id=?7"#
                     )
                     .bind(summary.total_logical_size as i64)
                     .bind(summary.total_allocated_size as i64)
                     .bind(summary.total_dirs as i64)
                     .bind(summary.total_files as i64)
                     .bind(summary.warnings as i64)
+                    .bind(summary.dedup_saved_bytes.min(i64::MAX as u64) as i64)
                     .bind(id.to_string())
                     .execute(&db).await {
                         tracing::error!("Failed to update scan status to done: {}", e);
                     }
+                    // Still counted as running, so a VACUUM cannot start in between
+                    if analyze_after_scan {
+                        if let Err(e) = sqlx::query("ANALYZE").execute(&db).await {
+                            tracing::warn!("ANALYZE after scan {} failed: {}", id, e);
+                        }
+                    }
                 }
             }
             Err(e) => {
-                if cancel_child.is_cancelled() {
+                if cancel_child.is_cancelled() && finalize.load(Ordering::SeqCst) {
+                    // Records still buffered when the error hit are lost; keep what was persisted
+                    let _ = tx_clone.send(ScanEvent::Cancelled);
+                    if let Err(e) = finalize_partial_scan(&db, id, None).await {
+                        tracing::error!("Failed to finalize partial scan {}: {}", id, e);
+                    }
+                } else if cancel_child.is_cancelled() {
                     let _ = tx_clone.send(ScanEvent::Cancelled);
                     // FIX Bug #60 - Log DB update errors
                     if let Err(e) = sqlx::query(
@@ -265,6 +690,7 @@ pub async fn create_scan(
                     // Metrics: failed scan
                     metrics.inc_scans_failed();
                     let _ = tx_clone.send(ScanEvent::Failed { message: format!("{}", e) });
+                    error = Some(e.to_string());
                     if let Err(e) = sqlx::query(
                         r#"UPDATE scans SET status='failed', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now') WHERE id=?1"#
                     )
@@ -275,48 +701,55 @@ pub async fn create_scan(
                 }
             }
         }
-        // Always remove job handle after completion
+        // Aborted and failed runs are timed too, so they show up next to the finished ones
+        metrics.observe_scan_duration(elapsed);
+        if let Err(e) = store_duration(&db, id, elapsed).await {
+            tracing::error!("Failed to store the duration of scan {}: {}", id, e);
+        }
+        webhooks::notify_scan_finished(&db, id, error, &shared_config.get().webhooks).await;
+        // Keep the last events for reloading clients, then always remove the job handle
+        retain_finished_events(&finished_events, id, events, replay_grace).await;
         {
+            // A cancelled scan may have been resumed already; its new job must stay
             let mut jobs = jobs_map.write().await;
-            jobs.remove(&id);
+            if jobs.get(&id).is_some_and(|job| Arc::ptr_eq(&job.events, &own_events)) {
+                jobs.remove(&id);
+            }
         }
-    });
+        finished.cancel();
+    };
+    let _handle: JoinHandle<()> = tokio::spawn(job.instrument(span));
 
     // Signal started
-    let _ = tx.send(ScanEvent::Started { root_paths: req.root_paths.clone() });
-
-    // Read back ISO UTC started_at from DB for response
-    let started_at_iso: String = sqlx::query("SELECT started_at FROM scans WHERE id=?1")
-        .bind(id.to_string())
-        .fetch_one(&state.db)
-        .await
-        .ok()
-        .and_then(|row| row.try_get::<String, _>("started_at").ok())
-        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
-    let resp = CreateScanResponse { id, status: "running".into(), started_at: started_at_iso };
-    Ok((StatusCode::ACCEPTED, Json(resp)).into_response())
+    let _ = tx.send(started);
 }
 
-/// Lists the most recent scans.
+/// Lists the most recent scans visible from the request's namespace.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 ///
 /// # Returns
 ///
 /// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `ScanSummary` objects.
-pub async fn list_scans(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+pub async fn list_scans(State(state): State<AppState>, ns: Namespace) -> AppResult<impl IntoResponse> {
     let rows = sqlx::query(
         r#"SELECT id, status, started_at, finished_at,
                    COALESCE(total_logical_size,0) AS total_logical_size,
                    COALESCE(total_allocated_size,0) AS total_allocated_size,
                    COALESCE(dir_count,0) AS dir_count,
                    COALESCE(file_count,0) AS file_count,
-                   COALESCE(warning_count,0) AS warning_count
-            FROM scans ORDER BY started_at DESC LIMIT 1000"#,
+                   COALESCE(warning_count,0) AS warning_count,
+                   COALESCE(dedup_saved_bytes,0) AS dedup_saved_bytes,
+                   duration_ms, dirs_per_sec, files_per_sec, bytes_per_sec,
+                   namespace, origin, root_paths, read_only, backup_mode
+            FROM scans WHERE (?1 OR namespace = ?2) ORDER BY started_at DESC LIMIT 1000"#,
     )
-    .fetch_all(&state.db)
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .fetch_all(state.read_pool())
     .await?;
 
     // FIX Bug #28: Fail fast on invalid UUIDs instead of silently filtering
@@ -327,9 +760,11 @@ pub async fn list_scans(State(state): State<AppState>) -> AppResult<impl IntoRes
             tracing::error!("Invalid UUID in scans table: {} - {} (data corruption detected)", id_str, e);
             AppError::Database(format!("Database corruption: invalid UUID {}", id_str))
         })?;
+        let status = r.get::<String, _>("status");
         items.push(ScanSummary {
             id,
-            status: r.get::<String, _>("status"),
+            resumable: is_resumable(&status),
+            status,
             started_at: r.get::<Option<String>, _>("started_at"),
             finished_at: r.get::<Option<String>, _>("finished_at"),
             total_logical_size: r.get::<i64, _>("total_logical_size"),
@@ -337,6 +772,18 @@ pub async fn list_scans(State(state): State<AppState>) -> AppResult<impl IntoRes
             dir_count: r.get::<i64, _>("dir_count"),
             file_count: r.get::<i64, _>("file_count"),
             warning_count: r.get::<i64, _>("warning_count"),
+            dedup_saved_bytes: r.get::<i64, _>("dedup_saved_bytes"),
+            duration_ms: r.get::<Option<i64>, _>("duration_ms"),
+            dirs_per_sec: r.get::<Option<f64>, _>("dirs_per_sec"),
+            files_per_sec: r.get::<Option<f64>, _>("files_per_sec"),
+            bytes_per_sec: r.get::<Option<f64>, _>("bytes_per_sec"),
+            namespace: r.get::<String, _>("namespace"),
+            origin: r.get::<Option<String>, _>("origin"),
+            root_paths: parse_root_paths(id, &r.get::<String, _>("root_paths")),
+            options: None,
+            read_only: r.get::<bool, _>("read_only"),
+            backup_mode: r.get::<bool, _>("backup_mode"),
+            roots: Vec::new(),
         });
     }
 
@@ -348,88 +795,188 @@ pub async fn list_scans(State(state): State<AppState>) -> AppResult<impl IntoRes
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan to retrieve.
 ///
 /// # Returns
 ///
 /// * `AppResult<impl IntoResponse>` - A JSON response containing the `ScanSummary` of the scan.
-pub async fn get_scan(State(state): State<AppState>, Path(id): Path<Uuid>) -> AppResult<impl IntoResponse> {
+pub async fn get_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    Ok(Json(load_scan_summary(state.read_pool(), &ns, id).await?))
+}
+
+/// Loads the summary of a scan visible to `ns`, with its per-root subtotals.
+pub(crate) async fn load_scan_summary(
+    pool: &sqlx::SqlitePool,
+    ns: &Namespace,
+    id: Uuid,
+) -> AppResult<ScanSummary> {
     let r = sqlx::query(
         r#"SELECT id, status, started_at, finished_at,
                    COALESCE(total_logical_size,0) AS total_logical_size,
                    COALESCE(total_allocated_size,0) AS total_allocated_size,
                    COALESCE(dir_count,0) AS dir_count,
                    COALESCE(file_count,0) AS file_count,
-                   COALESCE(warning_count,0) AS warning_count
-            FROM scans WHERE id = ?1"#,
+                   COALESCE(warning_count,0) AS warning_count,
+                   COALESCE(dedup_saved_bytes,0) AS dedup_saved_bytes,
+                   duration_ms, dirs_per_sec, files_per_sec, bytes_per_sec,
+                   namespace, origin, root_paths, options, read_only, backup_mode
+            FROM scans WHERE id = ?1 AND (?2 OR namespace = ?3)"#,
     )
     .bind(id.to_string())
-    .fetch_optional(&state.db)
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .fetch_optional(pool)
     .await?;
 
-    if let Some(r) = r {
-        let item = ScanSummary {
-            id,
-            status: r.get::<String, _>("status"),
-            started_at: r.get::<Option<String>, _>("started_at"),
-            finished_at: r.get::<Option<String>, _>("finished_at"),
-            total_logical_size: r.get::<i64, _>("total_logical_size"),
-            total_allocated_size: r.get::<i64, _>("total_allocated_size"),
-            dir_count: r.get::<i64, _>("dir_count"),
-            file_count: r.get::<i64, _>("file_count"),
-            warning_count: r.get::<i64, _>("warning_count"),
-        };
-        Ok(Json(item))
-    } else {
-        Err(AppError::NotFound("scan not found".into()))
-    }
+    let Some(r) = r else {
+        return Err(AppError::NotFound("scan not found".into()));
+    };
+    let status = r.get::<String, _>("status");
+    Ok(ScanSummary {
+        id,
+        resumable: is_resumable(&status),
+        status,
+        started_at: r.get::<Option<String>, _>("started_at"),
+        finished_at: r.get::<Option<String>, _>("finished_at"),
+        total_logical_size: r.get::<i64, _>("total_logical_size"),
+        total_allocated_size: r.get::<i64, _>("total_allocated_size"),
+        dir_count: r.get::<i64, _>("dir_count"),
+        file_count: r.get::<i64, _>("file_count"),
+        warning_count: r.get::<i64, _>("warning_count"),
+        dedup_saved_bytes: r.get::<i64, _>("dedup_saved_bytes"),
+        duration_ms: r.get::<Option<i64>, _>("duration_ms"),
+        dirs_per_sec: r.get::<Option<f64>, _>("dirs_per_sec"),
+        files_per_sec: r.get::<Option<f64>, _>("files_per_sec"),
+        bytes_per_sec: r.get::<Option<f64>, _>("bytes_per_sec"),
+        namespace: r.get::<String, _>("namespace"),
+        origin: r.get::<Option<String>, _>("origin"),
+        root_paths: parse_root_paths(id, &r.get::<String, _>("root_paths")),
+        options: parse_options(id, &r.get::<String, _>("options")),
+        read_only: r.get::<bool, _>("read_only"),
+        backup_mode: r.get::<bool, _>("backup_mode"),
+        roots: load_root_summaries(pool, id).await?,
+    })
+}
+
+/// Parses the stored `root_paths` column of a scan; empty if it is corrupt.
+fn parse_root_paths(id: Uuid, json: &str) -> Vec<String> {
+    serde_json::from_str(json).unwrap_or_else(|e| {
+        tracing::warn!("Unreadable root paths of scan {}: {}", id, e);
+        Vec::new()
+    })
+}
+
+/// Parses the stored `options` column of a scan; `None` if it is corrupt or
+/// misses fields that have no default, as options of older scans may.
+pub(crate) fn parse_options(id: Uuid, json: &str) -> Option<ScanOptions> {
+    serde_json::from_str(json)
+        .map_err(|e| tracing::warn!("Unreadable options of scan {}: {}", id, e))
+        .ok()
 }
 
+/// Loads the per-root subtotals of a scan in the order of its roots.
+async fn load_root_summaries(pool: &sqlx::SqlitePool, id: Uuid) -> AppResult<Vec<RootSummary>> {
+    let rows = sqlx::query(
+        r#"SELECT root_path, dir_count, file_count, logical_size, allocated_size, warning_count
+           FROM scan_roots WHERE scan_id=?1 ORDER BY root_index"#,
+    )
+    .bind(id.to_string())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| RootSummary {
+            root: r.get("root_path"),
+            dir_count: r.get::<i64, _>("dir_count").max(0) as u64,
+            file_count: r.get::<i64, _>("file_count").max(0) as u64,
+            logical_size: r.get::<i64, _>("logical_size").max(0) as u64,
+            allocated_size: r.get::<i64, _>("allocated_size").max(0) as u64,
+            warning_count: r.get::<i64, _>("warning_count").max(0) as u64,
+        })
+        .collect())
+}
+
+/// How long `DELETE /scans/{id}?finalize=true` waits for the scan task to persist its records.
+const FINALIZE_WAIT: Duration = Duration::from_secs(30);
+
 /// Query parameters for the cancel scan endpoint.
 #[derive(Debug, Default, serde::Deserialize)]
 pub struct CancelQuery {
     /// Whether to delete the scan data from the database.
     pub purge: Option<bool>,
+    /// Whether to keep the records persisted so far as a `partial` scan.
+    pub finalize: Option<bool>,
 }
 
 /// Cancels a running scan.
 ///
 /// If the `purge` query parameter is set to `true`, the scan data will also be
-/// deleted from the database.
+/// deleted from the database. If `finalize` is set to `true`, the scan task
+/// flushes its buffered records and the scan is kept with the status `partial`
+/// and totals recomputed from the persisted rows.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan to cancel.
 /// * `q` - The cancel query parameters.
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A `204 No Content` response on success.
+/// * `AppResult<impl IntoResponse>` - A `204 No Content` response on success, or
+///   `202 Accepted` if a finalizing scan did not finish persisting in time.
 pub async fn cancel_scan(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
     Query(q): Query<CancelQuery>,
 ) -> AppResult<impl IntoResponse> {
     let purge = q.purge.unwrap_or(false);
+    let finalize = q.finalize.unwrap_or(false);
+    if purge && finalize {
+        return Err(AppError::BadRequest("purge and finalize cannot be combined".into()));
+    }
+    // Scans of other namespaces are treated like unknown scans: an idempotent no-op
+    match ns.ensure_scan(&state.db, id).await {
+        Ok(()) => {}
+        Err(AppError::NotFound(_)) => return Ok((StatusCode::NO_CONTENT, "")),
+        Err(e) => return Err(e),
+    }
 
     // FIX Bug #12 - Race condition: check status first, then cancel
-    let was_running = {
+    let job = {
         let mut jobs = state.jobs.write().await;
-        if let Some(handle) = jobs.remove(&id) {
+        let job = jobs.remove(&id);
+        drop(jobs); // Release lock before any async operations
+        if let Some(handle) = &job {
+            // The flag must be visible before the task observes the cancellation
+            handle.finalize.store(finalize, Ordering::SeqCst);
             handle.cancel.cancel();
-            drop(jobs); // Release lock before any async operations
-            true
-        } else {
-            false
         }
+        job
     };
+    let was_running = job.is_some();
+
+    if let (true, Some(handle)) = (finalize, job) {
+        // The task flushes the aggregator and flips the status to 'partial' itself
+        if tokio::time::timeout(FINALIZE_WAIT, handle.finished.cancelled()).await.is_err() {
+            tracing::warn!("Scan {} is still persisting its partial results", id);
+            return Ok((StatusCode::ACCEPTED, ""));
+        }
+        return Ok((StatusCode::NO_CONTENT, ""));
+    }
 
     // FIX Bug #27: Use transaction for atomic operation
     // Update DB after releasing lock to avoid deadlock
     if was_running && !purge {
         if let Err(e) = sqlx::query(
-            r#"UPDATE scans SET status='canceled', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now') WHERE id=?1 AND status='running'"#
+            r#"UPDATE scans SET status='canceled', finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now') WHERE id=?1 AND status IN ('running','paused')"#
         )
         .bind(id.to_string())
         .execute(&state.db).await {
@@ -441,62 +988,546 @@ pub async fn cancel_scan(
     }
 
     if purge {
+        // A watcher would otherwise keep writing rows for the deleted scan
+        if let Some(watcher) = state.watchers.write().await.remove(&id) {
+            watcher.cancel.cancel();
+        }
         // Delete scan row (cascade to nodes/files/warnings)
         let _ = sqlx::query(r#"DELETE FROM scans WHERE id=?1"#).bind(id.to_string()).execute(&state.db).await;
+        state.read_only_roots.invalidate().await;
     }
 
     Ok((StatusCode::NO_CONTENT, ""))
 }
 
-/// Streams real-time events for a running scan.
+/// Pauses a running scan.
 ///
-/// This endpoint uses Server-Sent Events (SSE) to push `ScanEvent` messages to
-/// the client as they occur.
+/// The workers finish the directory listings they are in and then wait; records
+/// already collected are still written. The scan keeps the status `paused` until
+/// it is resumed or cancelled.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
-/// * `id` - The ID of the scan to stream events for.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan to pause.
 ///
 /// # Returns
 ///
-/// * `AppResult<Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>>>` -
-///  An SSE stream of scan events.
-pub async fn scan_events(
+/// * `AppResult<impl IntoResponse>` - A `204 No Content` response on success, or
+///   `Conflict` if the scan is not running.
+pub async fn pause_scan(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
-) -> AppResult<Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>>> {
-    // FIX Bug #14 - Race condition: ensure job exists before subscribing
-    let rx = {
-        let jobs = state.jobs.read().await;
-        if let Some(handle) = jobs.get(&id) {
-            let rx = handle.sender.subscribe();
-            drop(jobs); // Release lock
-            rx
-        } else {
-            return Err(AppError::NotFound("scan not running".into()));
-        }
-    };
-
-    let stream = BroadcastStream::new(rx)
-        .filter_map(move |res| match res {
-            Ok(event) => Some(event),
-            Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(n)) => {
-                tracing::warn!("SSE stream lagged by {} messages for scan {}", n, id);
-                // FIX Bug #4: Handle Lagged error by keeping stream alive but notifying client
-                // We return a specialized warning event so the client knows it missed data
-                Some(ScanEvent::Failed { message: format!("Stream lagged, missed {} events", n) })
-            }
-        })
-        .map(|ev| {
-            let data = serde_json::to_string(&ev)
-                .unwrap_or_else(|_| json!({"type":"warning","message":"serialization error"}).to_string());
-            Ok::<Event, std::convert::Infallible>(Event::default().data(data))
-        });
-
-    Ok(Sse::new(stream).keep_alive(
-        axum::response::sse::KeepAlive::new().interval(Duration::from_secs(10)).text("keep-alive"),
-    ))
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    set_paused(&state, id, true).await?;
+    Ok((StatusCode::NO_CONTENT, ""))
+}
+
+/// Resumes a paused scan, or restarts one that was cut off.
+///
+/// Paused scans continue where their workers hold. Scans without a job that
+/// are `interrupted` (by a crash or restart) or `partial` (cancelled with
+/// `finalize`) are run again under the same ID with their stored roots and
+/// options: directories whose node row already exists are taken over with
+/// their stored totals, only the missing subtrees are traversed, and the
+/// totals are recomputed from the rows once the run is done.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan to resume.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - `204 No Content` for a paused scan, `202 Accepted`
+///   with a `CreateScanResponse` for a restarted one, or `Conflict` if the scan
+///   is neither paused nor resumable.
+pub async fn resume_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<Response> {
+    ns.ensure_scan(&state.db, id).await?;
+    if state.jobs.read().await.contains_key(&id) {
+        set_paused(&state, id, false).await?;
+        return Ok((StatusCode::NO_CONTENT, "").into_response());
+    }
+    let resp = restart_scan(&state, id).await?;
+    Ok((StatusCode::ACCEPTED, Json(resp)).into_response())
+}
+
+/// Whether a scan with this status can be restarted with `POST /scans/{id}/resume`.
+pub(crate) fn is_resumable(status: &str) -> bool {
+    matches!(status, "interrupted" | "partial")
+}
+
+/// Runs an interrupted or partial scan again, skipping what it already stored.
+async fn restart_scan(state: &AppState, id: Uuid) -> AppResult<CreateScanResponse> {
+    let row = sqlx::query("SELECT status, root_paths, options, namespace FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("scan not found".into()))?;
+    if !is_resumable(&row.get::<String, _>("status")) {
+        return Err(AppError::Conflict("scan is neither paused nor resumable".into()));
+    }
+    let root_paths: Vec<String> = serde_json::from_str(&row.get::<String, _>("root_paths"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse root_paths: {}", e)))?;
+    let options: ScanOptions = serde_json::from_str(&row.get::<String, _>("options"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse options: {}", e)))?;
+    let expected = expected_totals(&state.db, &row.get::<String, _>("namespace"), &root_paths, None).await?;
+
+    // Claim the scan, so concurrent requests cannot both restart it
+    let res = sqlx::query(
+        "UPDATE scans SET status='running', finished_at=NULL WHERE id=?1 AND status IN ('interrupted','partial')",
+    )
+    .bind(id.to_string())
+    .execute(&state.db)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::Conflict("scan is neither paused nor resumable".into()));
+    }
+    tracing::info!("Scan {} restarted from its persisted records", id);
+    spawn_scan_job(state, id, root_paths, options, scanner::Reuse::Resume, expected).await;
+
+    Ok(CreateScanResponse { id, status: "running".into(), started_at: started_at(state, id).await, warnings: Vec::new() })
+}
+
+/// Flips the pause gate of a scan job and its status between `running` and `paused`.
+async fn set_paused(state: &AppState, id: Uuid, paused: bool) -> AppResult<()> {
+    let (from, to) = if paused { ("running", "paused") } else { ("paused", "running") };
+    // Duplicate searches share the job map but run on finished scans; the status tells them apart
+    let job = state.jobs.read().await.get(&id).cloned();
+    let job = job.ok_or_else(|| AppError::Conflict(format!("scan is not {}", from)))?;
+    let res = sqlx::query("UPDATE scans SET status=?1 WHERE id=?2 AND status=?3")
+        .bind(to)
+        .bind(id.to_string())
+        .bind(from)
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("scan is not {}", from)));
+    }
+    if paused {
+        job.pause.pause();
+        let _ = job.sender.send(ScanEvent::Paused);
+    } else {
+        job.pause.resume();
+        let _ = job.sender.send(ScanEvent::Resumed);
+    }
+    tracing::info!("Scan {} {}", id, to);
+    Ok(())
+}
+
+/// Marks a cancelled scan as `partial` and recomputes its totals from the persisted rows.
+///
+/// Must only run after `run_scan` has returned, so that its final flush is
+/// included. Directories whose traversal was interrupted have no node row, so
+/// their files count towards the totals but are not part of the tree.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `id` - The ID of the scan.
+/// * `summary` - The summary returned by the scanner, used for the warning and
+///   hardlink counters. `None` keeps the last values written during the scan.
+///
+/// # Returns
+///
+/// * `AppResult<()>` - An empty result indicating success.
+pub(crate) async fn finalize_partial_scan(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    summary: Option<&ScanResultSummary>,
+) -> AppResult<()> {
+    store_totals_from_rows(pool, id, "partial", summary).await
+}
+
+/// Returns `count` per second of `elapsed_ms`, counting runs under a millisecond as one.
+fn per_second(count: u64, elapsed_ms: u64) -> f64 {
+    count as f64 * 1000.0 / elapsed_ms.max(1) as f64
+}
+
+/// Stores how long a scan ran and its throughput, based on the stored totals.
+///
+/// Must run after the final status was written, since partial scans only get
+/// their totals then.
+async fn store_duration(pool: &sqlx::SqlitePool, id: Uuid, elapsed: Duration) -> AppResult<()> {
+    let elapsed_ms = elapsed.as_millis().min(i64::MAX as u128) as i64;
+    sqlx::query(
+        r#"UPDATE scans SET duration_ms=?2,
+            dirs_per_sec=COALESCE(dir_count,0)*1000.0/MAX(?2,1),
+            files_per_sec=COALESCE(file_count,0)*1000.0/MAX(?2,1),
+            bytes_per_sec=COALESCE(total_allocated_size,0)*1000.0/MAX(?2,1)
+           WHERE id=?1"#,
+    )
+    .bind(id.to_string())
+    .bind(elapsed_ms)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Ends a scan with `status` and totals recomputed from its persisted rows.
+async fn store_totals_from_rows(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    status: &str,
+    summary: Option<&ScanResultSummary>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"UPDATE scans SET status=?4, finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now'),
+            total_logical_size=(SELECT COALESCE(SUM(logical_size),0) FROM files WHERE scan_id=?1),
+            total_allocated_size=(SELECT COALESCE(SUM(allocated_size),0) FROM files WHERE scan_id=?1),
+            file_count=(SELECT COUNT(*) FROM files WHERE scan_id=?1),
+            dir_count=(SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND is_dir=1),
+            warning_count=COALESCE(?2, warning_count),
+            dedup_saved_bytes=COALESCE(?3, dedup_saved_bytes)
+           WHERE id=?1"#,
+    )
+    .bind(id.to_string())
+    .bind(summary.map(|s| s.warnings as i64))
+    .bind(summary.map(|s| s.dedup_saved_bytes.min(i64::MAX as u64) as i64))
+    .bind(status)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Query parameters for the event stream.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct EventsQuery {
+    /// Comma-separated event types to send (e.g. `progress,done`); all types if unset.
+    pub types: Option<String>,
+    /// The minimum time between two `progress` events of the stream, e.g. `5s` or `500ms`.
+    pub progress_interval: Option<String>,
+}
+
+/// The `type` names of [`ScanEvent`], in the order of their bits in [`EventFilter`].
+const EVENT_TYPES: [&str; 13] = [
+    "started",
+    "progress",
+    "hash_progress",
+    "duplicates_done",
+    "updated",
+    "warning",
+    "paused",
+    "resumed",
+    "done",
+    "cancelled",
+    "interrupted",
+    "failed",
+    "heartbeat",
+];
+
+/// The `type` name an event is serialized with.
+fn event_type(ev: &ScanEvent) -> &'static str {
+    match ev {
+        ScanEvent::Started { .. } => "started",
+        ScanEvent::Progress { .. } => "progress",
+        ScanEvent::HashProgress { .. } => "hash_progress",
+        ScanEvent::DuplicatesDone { .. } => "duplicates_done",
+        ScanEvent::Updated { .. } => "updated",
+        ScanEvent::Warning { .. } => "warning",
+        ScanEvent::Paused => "paused",
+        ScanEvent::Resumed => "resumed",
+        ScanEvent::Done { .. } => "done",
+        ScanEvent::Cancelled => "cancelled",
+        ScanEvent::Interrupted { .. } => "interrupted",
+        ScanEvent::Failed { .. } => "failed",
+        ScanEvent::Heartbeat { .. } => "heartbeat",
+    }
+}
+
+/// Parses an interval like `500ms`, `5s`, `2m` or `1h`; a bare number counts as seconds.
+fn parse_interval(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (digits, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+    let n: u64 = digits.parse().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(n)),
+        "" | "s" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_secs(n.checked_mul(60)?)),
+        "h" => Some(Duration::from_secs(n.checked_mul(3600)?)),
+        _ => None,
+    }
+}
+
+/// Decides which events one stream sends, see [`EventsQuery`].
+///
+/// Every connection has its own filter, so the `progress` throttle of one
+/// client does not affect the others.
+struct EventFilter {
+    /// One bit per entry of [`EVENT_TYPES`].
+    types: u16,
+    progress_interval: Option<Duration>,
+    last_progress: Option<Instant>,
+}
+
+impl EventFilter {
+    /// Validates the filter parameters of an event stream request.
+    fn from_query(q: &EventsQuery) -> AppResult<Self> {
+        let types = match q.types.as_deref() {
+            None => u16::MAX,
+            Some(list) => {
+                let mut types = 0u16;
+                for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                    let bit = EVENT_TYPES.iter().position(|t| t.eq_ignore_ascii_case(name)).ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "unknown event type '{}'; valid types: {}",
+                            name,
+                            EVENT_TYPES.join(", ")
+                        ))
+                    })?;
+                    types |= 1 << bit;
+                }
+                if types == 0 {
+                    return Err(AppError::BadRequest(format!(
+                        "types must name at least one of: {}",
+                        EVENT_TYPES.join(", ")
+                    )));
+                }
+                types
+            }
+        };
+        let progress_interval = match q.progress_interval.as_deref() {
+            None => None,
+            Some(v) => Some(parse_interval(v).ok_or_else(|| {
+                AppError::BadRequest("progress_interval must look like 500ms, 5s, 2m or 1h".into())
+            })?),
+        };
+        Ok(Self { types, progress_interval, last_progress: None })
+    }
+
+    /// Whether events of type `name` are sent at all.
+    fn sends(&self, name: &str) -> bool {
+        EVENT_TYPES.iter().position(|t| *t == name).is_some_and(|bit| self.types & (1 << bit) != 0)
+    }
+
+    /// Whether `ev` is sent; a sent `progress` event starts the throttle interval.
+    fn pass(&mut self, ev: &ScanEvent) -> bool {
+        if !self.sends(event_type(ev)) {
+            return false;
+        }
+        if let (ScanEvent::Progress { .. }, Some(every)) = (ev, self.progress_interval) {
+            let now = Instant::now();
+            if self.last_progress.is_some_and(|last| now.duration_since(last) < every) {
+                return false;
+            }
+            self.last_progress = Some(now);
+        }
+        true
+    }
+}
+
+/// Streams real-time events for a running or watched scan.
+///
+/// This endpoint uses Server-Sent Events (SSE) to push `ScanEvent` messages to
+/// the client as they occur.
+/// Scans interrupted by a server restart get a single `Interrupted` event and
+/// the stream ends.
+///
+/// Every event carries an incrementing id. New streams first replay the last
+/// events of the scan (see [`EventLog`]), skipping those up to the standard
+/// `Last-Event-ID` header, so reloaded pages get the earlier log and
+/// reconnecting clients no duplicates. Finished scans stay replayable for
+/// `sse.replay_grace_secs`.
+///
+/// To survive buffering proxies the stream starts with a padding comment
+/// (`sse.padding_bytes`), carries `X-Accel-Buffering: no` and
+/// `Cache-Control: no-transform`, and sends `heartbeat` events every
+/// `sse.heartbeat_secs` besides the protocol keep-alive comments.
+///
+/// `?types=progress,warning,done` limits the stream to those event types
+/// (`heartbeat` included); unknown names are rejected with 400.
+/// `?progress_interval=5s` sends at most one `progress` event per interval on
+/// this connection, however often the scanner reports progress. Filtered
+/// events keep their ids, so `Last-Event-ID` works as before.
+///
+/// One client IP may keep at most `sse.max_connections_per_client` streams
+/// open; further ones are refused with 429 until one of them is dropped.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `id` - The ID of the scan to stream events for.
+/// * `q` - The event filter.
+/// * `headers` - The request headers, read for `Last-Event-ID`.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - An SSE stream of scan events.
+pub async fn scan_events(
+    State(state): State<AppState>,
+    ns: Namespace,
+    maybe_remote: MaybeRemoteAddr,
+    Path(id): Path<Uuid>,
+    Query(q): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> AppResult<impl IntoResponse> {
+    let mut filter = EventFilter::from_query(&q)?;
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let config = state.config.get();
+    let sse_cfg = &config.sse;
+    let client = extract_ip_from_headers(&headers, maybe_remote.0.map(|addr| addr.ip()));
+    // Moved into the stream, so the slot is given back when the connection is dropped
+    let connection = state.sse_connections.try_open(client, id, sse_cfg.max_connections_per_client).ok_or(
+        // A vanished client is only noticed when the next keep-alive cannot be written
+        AppError::RateLimited { retry_after_seconds: sse_cfg.keep_alive_secs },
+    )?;
+    // Unparsable ids replay everything kept rather than failing the reconnect
+    let last_event_id =
+        headers.get("last-event-id").and_then(|v| v.to_str().ok()).and_then(|v| v.trim().parse::<u64>().ok());
+    let log = event_log(&state, id).await?;
+    let (backlog, rx) = log.subscribe(last_event_id);
+
+    let live = BroadcastStream::new(rx).map(move |res| match res {
+        Ok((event_id, event)) => (Some(event_id), event),
+        Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(n)) => {
+            tracing::warn!("SSE stream lagged by {} messages for scan {}", n, id);
+            // FIX Bug #4: Handle Lagged error by keeping stream alive but notifying client
+            // We return a specialized warning event so the client knows it missed data
+            (None, ScanEvent::Failed { message: format!("Stream lagged, missed {} events", n) })
+        }
+    });
+    let sends_heartbeat = filter.sends("heartbeat");
+    let stream = tokio_stream::iter(backlog)
+        .map(|(event_id, event)| (Some(event_id), event))
+        .chain(live)
+        .filter_map(move |(event_id, event)| {
+            filter.pass(&event).then(|| match event_id {
+                Some(event_id) => sse_event(&event).id(event_id.to_string()),
+                None => sse_event(&event),
+            })
+        });
+
+    // Some proxies only start flushing once a few KB have passed through
+    let padding =
+        (sse_cfg.padding_bytes > 0).then(|| Event::default().comment(" ".repeat(sse_cfg.padding_bytes)));
+    let heartbeat = (sse_cfg.heartbeat_secs > 0 && sends_heartbeat)
+        .then(|| Duration::from_secs(sse_cfg.heartbeat_secs));
+    let stream = tokio_stream::iter(padding)
+        .chain(with_heartbeat(Box::pin(stream), heartbeat))
+        .map(move |event| {
+            let _connection = &connection;
+            Ok::<Event, std::convert::Infallible>(event)
+        });
+
+    let sse = Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(sse_cfg.keep_alive_secs))
+            .text("keep-alive"),
+    );
+    Ok((
+        [
+            (header::HeaderName::from_static("x-accel-buffering"), "no"),
+            (header::CACHE_CONTROL, "no-cache, no-transform"),
+        ],
+        sse,
+    ))
+}
+
+/// Finds the event log a stream of scan `id` subscribes to.
+///
+/// Running jobs come first, then watched scans and the kept logs of finished
+/// ones; interrupted scans get a replay of their end. Shared by the SSE and
+/// the WebSocket stream.
+pub(crate) async fn event_log(state: &AppState, id: Uuid) -> AppResult<Arc<EventLog>> {
+    // FIX Bug #14 - Race condition: ensure job exists before subscribing
+    let jobs = state.jobs.read().await;
+    if let Some(handle) = jobs.get(&id) {
+        let log = handle.events.clone();
+        drop(jobs); // Release lock
+        return Ok(log);
+    }
+    drop(jobs);
+    // Finished scans still stream updates while they are watched
+    let watched = state.watchers.read().await.get(&id).map(|handle| handle.events.clone());
+    let kept = match watched {
+        Some(log) => Some(log),
+        None => state.finished_events.read().await.get(&id).cloned(),
+    };
+    match kept {
+        Some(log) => Ok(log),
+        None => interrupted_replay(state.read_pool(), id).await,
+    }
+}
+
+/// Serializes a scan event into an SSE event; heartbeats get the event name `heartbeat`.
+fn sse_event(ev: &ScanEvent) -> Event {
+    let data = serde_json::to_string(ev)
+        .unwrap_or_else(|_| json!({"type":"warning","message":"serialization error"}).to_string());
+    match ev {
+        ScanEvent::Heartbeat { .. } => Event::default().event("heartbeat").data(data),
+        _ => Event::default().data(data),
+    }
+}
+
+/// Interleaves `heartbeat` events into a stream of scan events.
+///
+/// The first heartbeat follows one `every` after the stream starts. The
+/// resulting stream ends with `events`, so replayed streams still close.
+///
+/// # Arguments
+///
+/// * `events` - The scan events.
+/// * `every` - The heartbeat interval, `None` to pass `events` through unchanged.
+fn with_heartbeat(
+    events: std::pin::Pin<Box<dyn Stream<Item = Event> + Send>>,
+    every: Option<Duration>,
+) -> impl Stream<Item = Event> + Send {
+    let ticker = every.map(|period| {
+        let mut t = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        t.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        t
+    });
+    futures::stream::unfold((events, ticker), |(mut events, mut ticker)| async move {
+        let ev = match ticker.as_mut() {
+            Some(t) => tokio::select! {
+                biased;
+                ev = events.next() => ev?,
+                _ = t.tick() => {
+                    sse_event(&ScanEvent::Heartbeat { timestamp: chrono::Utc::now().timestamp() })
+                }
+            },
+            None => events.next().await?,
+        };
+        Some((ev, (events, ticker)))
+    })
+}
+
+/// Replays the terminal event of a scan that was cut off by a server restart.
+///
+/// Clients reconnecting after a restart get a closed stream carrying a single
+/// `Interrupted` event instead of a 404, so they can offer to start the scan again.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool to query.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<Arc<EventLog>>` - A closed log holding the event, or `NotFound` if
+///   the scan was not interrupted.
+async fn interrupted_replay(pool: &sqlx::SqlitePool, id: Uuid) -> AppResult<Arc<EventLog>> {
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(pool)
+        .await?;
+    if status.as_deref() != Some("interrupted") {
+        return Err(AppError::NotFound("scan not running".into()));
+    }
+    let log = EventLog::new(1);
+    log.record(ScanEvent::Interrupted { resumable: true });
+    log.close();
+    Ok(Arc::new(log))
 }
 
 // Removed - inline usage is clearer and avoids potential timezone issues
@@ -510,7 +1541,7 @@ pub async fn scan_events(
 const LIKE_ESCAPE: char = '!';
 const TREE_LIMIT_MAX: i64 = 10_000_000;
 
-fn escape_like_pattern(p: &str) -> String {
+pub(crate) fn escape_like_pattern(p: &str) -> String {
     let mut out = String::with_capacity(p.len());
     for ch in p.chars() {
         if matches!(ch, '%' | '_' | LIKE_ESCAPE) {
@@ -521,13 +1552,179 @@ fn escape_like_pattern(p: &str) -> String {
     out
 }
 
-fn normalize_query_path(p: &str) -> AppResult<String> {
+/// Builds a `LIKE` pattern (escaped with [`LIKE_ESCAPE`]) that matches every
+/// path strictly below `path`. Callers must add `ESCAPE '!'` to the query.
+pub(crate) fn subtree_like_pattern(path: &str) -> String {
+    let mut pfx = path.to_string();
+    if !pfx.ends_with('/') && !pfx.ends_with('\\') {
+        if pfx.contains('\\') {
+            pfx.push('\\');
+        } else {
+            pfx.push('/');
+        }
+    }
+    // FIX Bug #8: Escape special characters to prevent SQL injection via path
+    format!("{}%", escape_like_pattern(&pfx))
+}
+
+/// Restricts a query on `nodes` to `path` and every path below it.
+///
+/// Unlike [`subtree_like_pattern`] the condition is a range on `path`, which
+/// SQLite answers from the `(scan_id, path)` index instead of testing every
+/// node of the scan. The range ends at the path with its trailing separator
+/// raised by one (`/` to `0`, `\` to `]`); separators are ASCII, so this holds
+/// for any Unicode path. Siblings that sort inside the range, such as
+/// `dir.bak` next to `dir`, are filtered out by the last condition.
+fn push_subtree_range(qb: &mut QueryBuilder<'_, sqlx::Sqlite>, path: &str) {
+    let mut prefix = path.to_string();
+    if !prefix.ends_with(['/', '\\']) {
+        prefix.push(if prefix.contains('\\') { '\\' } else { '/' });
+    }
+    let mut upper = prefix.clone();
+    let sep = upper.pop().unwrap_or('/');
+    upper.push(if sep == '\\' { ']' } else { '0' });
+    qb.push(" AND path >= ").push_bind(path.to_string());
+    qb.push(" AND path < ").push_bind(upper);
+    qb.push(" AND (path = ").push_bind(path.to_string());
+    qb.push(" OR path >= ").push_bind(prefix).push(")");
+}
+
+/// The drive or UNC prefix of a Windows path.
+///
+/// The scanner stores paths exactly as traversed, so the same directory may be
+/// stored with or without the extended-length `\\?\` prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WindowsPrefix {
+    /// `C:`
+    Disk(String),
+    /// `\\?\C:`
+    VerbatimDisk(String),
+    /// `\\server\share`
+    Unc(String, String),
+    /// `\\?\UNC\server\share`
+    VerbatimUnc(String, String),
+}
+
+impl WindowsPrefix {
+    fn render(&self) -> String {
+        match self {
+            WindowsPrefix::Disk(drive) => drive.clone(),
+            WindowsPrefix::VerbatimDisk(drive) => format!(r"\\?\{}", drive),
+            WindowsPrefix::Unc(server, share) => format!(r"\\{}\{}", server, share),
+            WindowsPrefix::VerbatimUnc(server, share) => format!(r"\\?\UNC\{}\{}", server, share),
+        }
+    }
+
+    /// The same drive with an upper-case letter, as roots are stored (see
+    /// `normalize_root`), or `None` if the letter is upper-case already or this is a share.
+    fn with_upper_drive(&self) -> Option<Self> {
+        match self {
+            WindowsPrefix::Disk(drive) if drive.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                Some(WindowsPrefix::Disk(drive.to_ascii_uppercase()))
+            }
+            WindowsPrefix::VerbatimDisk(drive) if drive.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                Some(WindowsPrefix::VerbatimDisk(drive.to_ascii_uppercase()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The same location with the extended-length prefix added or removed.
+    fn toggled(&self) -> Self {
+        match self.clone() {
+            WindowsPrefix::Disk(drive) => WindowsPrefix::VerbatimDisk(drive),
+            WindowsPrefix::VerbatimDisk(drive) => WindowsPrefix::Disk(drive),
+            WindowsPrefix::Unc(server, share) => WindowsPrefix::VerbatimUnc(server, share),
+            WindowsPrefix::VerbatimUnc(server, share) => WindowsPrefix::Unc(server, share),
+        }
+    }
+}
+
+fn starts_with_drive(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':'
+}
+
+/// Splits `server\share\rest` into its parts; either separator is accepted.
+///
+/// The rest keeps its leading separator.
+fn split_server_share(s: &str) -> Option<(String, String, &str)> {
+    let server_end = s.find(['/', '\\'])?;
+    let (server, after) = (&s[..server_end], &s[server_end + 1..]);
+    let share_end = after.find(['/', '\\']).unwrap_or(after.len());
+    let share = &after[..share_end];
+    if server.is_empty() || share.is_empty() {
+        return None;
+    }
+    Some((server.to_string(), share.to_string(), &after[share_end..]))
+}
+
+/// Splits a Windows path with a drive or UNC prefix into the prefix and the rest.
+///
+/// Either separator is accepted. Outside Windows a path only counts as a
+/// Windows path if it starts with a drive letter or contains a backslash, so a
+/// Unix path like `//data` keeps its meaning.
+fn split_windows_prefix(p: &str) -> Option<(WindowsPrefix, &str)> {
+    if !cfg!(windows) && !p.contains('\\') && !starts_with_drive(p) {
+        return None;
+    }
+    let b = p.as_bytes();
+    let sep = |i: usize| b.get(i).is_some_and(|c| *c == b'/' || *c == b'\\');
+    if sep(0) && sep(1) {
+        if b.get(2) == Some(&b'?') && sep(3) {
+            let rest = &p[4..];
+            if rest.get(..3).is_some_and(|s| s.eq_ignore_ascii_case("UNC")) && sep(7) {
+                let (server, share, rest) = split_server_share(&rest[4..])?;
+                return Some((WindowsPrefix::VerbatimUnc(server, share), rest));
+            }
+            if starts_with_drive(rest) {
+                return Some((WindowsPrefix::VerbatimDisk(rest[..2].to_string()), &rest[2..]));
+            }
+            return None;
+        }
+        let (server, share, rest) = split_server_share(&p[2..])?;
+        return Some((WindowsPrefix::Unc(server, share), rest));
+    }
+    if starts_with_drive(p) {
+        return Some((WindowsPrefix::Disk(p[..2].to_string()), &p[2..]));
+    }
+    None
+}
+
+/// Joins a prefix and the remaining components with backslashes.
+///
+/// A bare prefix keeps a trailing backslash (`C:\`, `\\server\share\`), like
+/// the root directories the scanner stores.
+fn join_windows_path(prefix: &WindowsPrefix, rest: &str) -> AppResult<String> {
+    let mut out = prefix.render();
+    let mut components = 0;
+    for part in rest.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(AppError::BadRequest("path traversal is not allowed".into())),
+            _ => {}
+        }
+        out.push('\\');
+        out.push_str(part);
+        components += 1;
+    }
+    if components == 0 {
+        out.push('\\');
+    }
+    Ok(out)
+}
+
+pub(crate) fn normalize_query_path(p: &str) -> AppResult<String> {
     if p.trim().is_empty() {
         return Err(AppError::BadRequest("path must not be empty".into()));
     }
     if p.contains('\0') {
         return Err(AppError::BadRequest("path contains null byte".into()));
     }
+    // Drive, UNC and extended-length paths keep their prefix exactly as the scanner stores it
+    if let Some((prefix, rest)) = split_windows_prefix(p) {
+        return join_windows_path(&prefix, rest);
+    }
 
     #[cfg(windows)]
     {
@@ -547,13 +1744,10 @@ fn normalize_query_path(p: &str) -> AppResult<String> {
             }
         }
 
-        let mut result = sanitized.to_string_lossy().to_string();
+        let result = sanitized.to_string_lossy().to_string();
         if result.is_empty() {
             return Err(AppError::BadRequest("normalized path is empty".into()));
         }
-        if result.len() == 2 && result.chars().nth(1) == Some(':') {
-            result.push('\\');
-        }
         Ok(result)
     }
     #[cfg(not(windows))]
@@ -581,129 +1775,349 @@ fn normalize_query_path(p: &str) -> AppResult<String> {
     }
 }
 
-// ---------------------- TREE ENDPOINT ----------------------
-
-/// Query parameters for the tree endpoint.
-#[derive(Debug, Default, serde::Deserialize)]
-pub struct TreeQuery {
-    /// The root path of the subtree to retrieve.
-    pub path: Option<String>,
-    /// The maximum depth of the subtree to retrieve.
-    pub depth: Option<i64>,
-    /// The sort order for the results (e.g., "size", "name").
-    pub sort: Option<String>, // size|name
-    /// The maximum number of results to return.
-    pub limit: Option<i64>,
+/// Returns the spellings a normalized path may be stored under.
+///
+/// Besides the path itself these are the same path with the extended-length
+/// prefix added or removed (`\\?\UNC\server\share` and `\\server\share`,
+/// `\\?\C:` and `C:`), for share roots also the forms without the trailing
+/// backslash, and for a lower-case drive letter the upper-case forms.
+pub(crate) fn query_path_variants(normalized: &str) -> Vec<String> {
+    let mut variants = vec![normalized.to_string()];
+    if let Some((prefix, rest)) = split_windows_prefix(normalized) {
+        let mut prefixes = vec![prefix.clone(), prefix.toggled()];
+        if let Some(upper) = prefix.with_upper_drive() {
+            prefixes.extend([upper.clone(), upper.toggled()]);
+        }
+        for prefix in prefixes {
+            let rendered = prefix.render();
+            let mut candidates = vec![format!("{}{}", rendered, rest)];
+            if rest == "\\" && matches!(prefix, WindowsPrefix::Unc(..) | WindowsPrefix::VerbatimUnc(..)) {
+                candidates.push(rendered);
+            }
+            for candidate in candidates {
+                if !variants.contains(&candidate) {
+                    variants.push(candidate);
+                }
+            }
+        }
+    }
+    variants
 }
 
-/// Gets a hierarchical view of the scanned directory tree.
+/// Normalizes a query path and resolves it to the spelling stored in a scan.
 ///
-/// This endpoint can be used to retrieve the entire directory tree or a specific
-/// subtree.
+/// Falls back to the normalized path if no spelling is stored, so callers get
+/// empty results for unknown paths as before.
 ///
 /// # Arguments
 ///
-/// * `state` - The application state.
+/// * `pool` - The database pool to query.
 /// * `id` - The ID of the scan.
-/// * `q` - The tree query parameters.
+/// * `p` - The path as given in the query.
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `NodeDto` objects.
-pub async fn get_tree(
-    State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-    Query(q): Query<TreeQuery>,
-) -> AppResult<impl IntoResponse> {
-    if let Some(depth) = q.depth {
-        if depth < 0 {
-            return Err(AppError::BadRequest("depth must be >= 0".into()));
-        }
+/// * `AppResult<String>` - The path as stored, `BadRequest` for invalid paths.
+pub(crate) async fn resolve_query_path(pool: &sqlx::SqlitePool, id: Uuid, p: &str) -> AppResult<String> {
+    let normalized = normalize_query_path(p)?;
+    let variants = query_path_variants(&normalized);
+    if variants.len() == 1 {
+        return Ok(normalized);
     }
-    // Determine base depth if path provided
-    let mut base_depth: Option<i64> = None;
-    let mut normalized_path: Option<String> = None;
-    if let Some(ref p) = q.path {
-        // FIX Bug #50 - Validate path BEFORE normalization
-        if p.len() > 4096 {
-            return Err(AppError::BadRequest("Path too long".into()));
+    let mut qb = QueryBuilder::<sqlx::Sqlite>::new("SELECT path FROM nodes WHERE scan_id=");
+    qb.push_bind(id.to_string()).push(" AND path IN (");
+    let mut list = qb.separated(", ");
+    for v in &variants {
+        list.push_bind(v.clone());
+    }
+    qb.push(") UNION SELECT path FROM files WHERE scan_id=");
+    qb.push_bind(id.to_string()).push(" AND path IN (");
+    let mut list = qb.separated(", ");
+    for v in &variants {
+        list.push_bind(v.clone());
+    }
+    qb.push(")");
+    let stored: Vec<String> = qb.build().fetch_all(pool).await?.iter().map(|r| r.get("path")).collect();
+    Ok(variants.into_iter().find(|v| stored.contains(v)).unwrap_or(normalized))
+}
+
+/// Rewrites a search term that is a Windows path into the part every spelling shares.
+///
+/// `\\?\UNC\server\share\dir` and `\\server\share\dir` both contain
+/// `\server\share\dir`, and `\\?\C:\dir` contains `C:\dir`, so a substring
+/// search for the reduced term finds the path however it was stored. Other
+/// terms are returned unchanged.
+pub(crate) fn search_path_term(term: &str) -> String {
+    let Ok(normalized) = normalize_query_path(term) else { return term.to_string() };
+    match split_windows_prefix(&normalized) {
+        Some((WindowsPrefix::Unc(server, share) | WindowsPrefix::VerbatimUnc(server, share), rest)) => {
+            format!(r"\{}\{}{}", server, share, rest.trim_end_matches('\\'))
         }
-        let p_norm = normalize_query_path(p)?;
-        if p_norm.len() > 4096 {
-            return Err(AppError::BadRequest("Normalized path too long".into()));
+        Some((WindowsPrefix::Disk(drive) | WindowsPrefix::VerbatimDisk(drive), rest)) => {
+            format!("{}{}", drive, rest.trim_end_matches('\\'))
         }
-        if let Ok(Some(row)) = sqlx::query(r#"SELECT depth FROM nodes WHERE scan_id=?1 AND path=?2 LIMIT 1"#)
-            .bind(id.to_string())
-            .bind(&p_norm)
-            .fetch_optional(&state.db)
-            .await
-        {
+        None => term.to_string(),
+    }
+}
+
+// ---------------------- ETAGS ----------------------
+
+/// Statuses of scans whose stored results no longer change on their own.
+const SETTLED_STATUSES: [&str; 5] = ["done", "partial", "failed", "canceled", "interrupted"];
+
+/// Returns a weak ETag over the given fields.
+fn etag_of(fields: &[&str]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update(&[0]);
+    }
+    format!("W/\"{}\"", &hasher.finalize().to_hex()[..32])
+}
+
+/// Returns whether an `If-None-Match` header matches `etag` (weak comparison).
+fn if_none_match_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// Computes the ETag of a tree, top or list response of a scan.
+///
+/// The tag covers the scan's status, end time, roots and totals, so new scan
+/// data, watch updates, deletions and remaps all change it, plus the query
+/// string and the response format, so every distinct query and encoding has
+/// its own tag.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool to query.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `query` - The raw query string of the request.
+/// * `format` - The negotiated response format.
+///
+/// # Returns
+///
+/// * `AppResult<Option<(String, bool)>>` - The tag and whether the scan's status
+///   is settled, or `None` if the scan is not visible from `ns`.
+async fn scan_etag_value(
+    pool: &sqlx::SqlitePool,
+    ns: &Namespace,
+    id: Uuid,
+    query: Option<&str>,
+    format: ResponseFormat,
+) -> AppResult<Option<(String, bool)>> {
+    let row = sqlx::query(
+        r#"SELECT status, finished_at, root_paths,
+                  COALESCE(dir_count,0) AS dir_count, COALESCE(file_count,0) AS file_count,
+                  COALESCE(total_logical_size,0) AS total_logical_size,
+                  COALESCE(total_allocated_size,0) AS total_allocated_size
+           FROM scans WHERE id = ?1 AND (?2 OR namespace = ?3)"#,
+    )
+    .bind(id.to_string())
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .fetch_optional(pool)
+    .await?;
+    let Some(r) = row else { return Ok(None) };
+    let status: String = r.get("status");
+    let totals = ["dir_count", "file_count", "total_logical_size", "total_allocated_size"]
+        .map(|column| r.get::<i64, _>(column).to_string());
+    let etag = etag_of(&[
+        &id.to_string(),
+        &status,
+        r.get::<Option<String>, _>("finished_at").as_deref().unwrap_or(""),
+        &r.get::<String, _>("root_paths"),
+        &totals.join(","),
+        query.unwrap_or(""),
+        if format == ResponseFormat::MsgPack { "msgpack" } else { "" },
+    ]);
+    Ok(Some((etag, SETTLED_STATUSES.contains(&status.as_str()))))
+}
+
+/// Adds an `ETag` to tree, top and list responses and answers matching
+/// conditional requests for settled scans with `304 Not Modified`.
+///
+/// Running and watched scans still get a tag, but are always answered in
+/// full because their data can change between two updates of the totals.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `req` - The request.
+/// * `next` - The handler of the route.
+///
+/// # Returns
+///
+/// * `Response` - The handler's response with an `ETag`, or an empty `304`.
+pub async fn scan_etag(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let format = ResponseFormat::negotiate(req.headers(), req.uri());
+    let (etag, settled) = match scan_etag_value(state.read_pool(), &ns, id, req.uri().query(), format).await {
+        Ok(Some(found)) => found,
+        // Invisible scans and lookup errors are left to the handler
+        Ok(None) | Err(_) => return next.run(req).await,
+    };
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else { return next.run(req).await };
+    let live = state.jobs.read().await.contains_key(&id) || state.watchers.read().await.contains_key(&id);
+
+    if settled && !live && if_none_match_matches(req.headers(), &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag_value), (header::CACHE_CONTROL, HeaderValue::from_static("no-cache"))],
+        )
+            .into_response();
+    }
+    let mut res = next.run(req).await;
+    if res.status().is_success() {
+        res.headers_mut().insert(header::ETAG, etag_value);
+    }
+    res
+}
+
+// ---------------------- TREE ENDPOINT ----------------------
+
+/// Query parameters for the tree endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct TreeQuery {
+    /// The root path of the subtree to retrieve.
+    pub path: Option<String>,
+    /// The maximum depth of the subtree to retrieve.
+    pub depth: Option<i64>,
+    /// The sort order for the results (e.g., "size", "name").
+    pub sort: Option<String>, // size|name
+    /// The maximum number of results to return.
+    pub limit: Option<i64>,
+}
+
+/// Gets a hierarchical view of the scanned directory tree.
+///
+/// This endpoint can be used to retrieve the entire directory tree or a specific
+/// subtree.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The tree query parameters.
+/// * `format` - JSON or MessagePack, see [`ResponseFormat::negotiate`].
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A JSON or MessagePack response containing a list of `NodeDto` objects.
+pub async fn get_tree(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<TreeQuery>,
+    format: ResponseFormat,
+) -> AppResult<Response> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    if let Some(depth) = q.depth {
+        if depth < 0 {
+            return Err(AppError::BadRequest("depth must be >= 0".into()));
+        }
+    }
+    // Determine base depth if path provided
+    let mut base_depth: Option<i64> = None;
+    let mut normalized_path: Option<String> = None;
+    if let Some(ref p) = q.path {
+        // FIX Bug #50 - Validate path BEFORE normalization
+        if p.len() > 4096 {
+            return Err(AppError::BadRequest("Path too long".into()));
+        }
+        let p_norm = resolve_query_path(state.read_pool(), id, p).await?;
+        if p_norm.len() > 4096 {
+            return Err(AppError::BadRequest("Normalized path too long".into()));
+        }
+        if let Ok(Some(row)) = sqlx::query(r#"SELECT depth FROM nodes WHERE scan_id=?1 AND path=?2 LIMIT 1"#)
+            .bind(id.to_string())
+            .bind(&p_norm)
+            .fetch_optional(state.read_pool())
+            .await
+        {
             base_depth = Some(row.get::<i64, _>("depth"));
         }
         normalized_path = Some(p_norm);
     }
 
+    let max_depth = base_depth.zip(q.depth).map(|(bd, d)| bd + d);
+    // Clamp limit to a safe range to prevent overly large responses while allowing larger exports for power users
+    let limit = q.limit.unwrap_or(200).clamp(1, TREE_LIMIT_MAX);
+    let by_name = q.sort.as_deref() == Some("name");
+    let mut qb = tree_query(id, normalized_path.as_deref(), max_depth, by_name, limit);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    let items: Vec<NodeDto> = rows.iter().map(node_dto).collect();
+    Ok(respond_negotiated(format, &items))
+}
+
+/// Builds the query of the tree endpoint.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the scan.
+/// * `path` - The directory whose subtree, itself included, is returned; the whole scan if `None`.
+/// * `max_depth` - The deepest absolute depth to return.
+/// * `by_name` - Order by path instead of by allocated size, largest first.
+/// * `limit` - The maximum number of nodes.
+fn tree_query(
+    id: Uuid,
+    path: Option<&str>,
+    max_depth: Option<i64>,
+    by_name: bool,
+    limit: i64,
+) -> QueryBuilder<'static, sqlx::Sqlite> {
     // FIX Bugs #5,#6,#7 - Use QueryBuilder properly instead of string formatting
-    let mut qb = QueryBuilder::new(
-        "SELECT path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count, mtime, atime FROM nodes WHERE scan_id="
-    );
+    let mut qb = QueryBuilder::new(format!("SELECT {} FROM nodes WHERE scan_id=", NODE_COLUMNS));
     qb.push_bind(id.to_string());
-
-    if let Some(ref peq) = normalized_path {
-        // Restrict to subtree: include the node itself and everything under it using a trailing separator
-        let mut pfx = peq.clone();
-        if !pfx.ends_with('/') && !pfx.ends_with('\\') {
-            if pfx.contains('\\') {
-                pfx.push('\\');
-            } else {
-                pfx.push('/');
-            }
+    match path {
+        Some(path) => push_subtree_range(&mut qb, path),
+        // Nodes only hold directories; the condition lets the (scan_id, is_dir, allocated_size) index
+        // return the largest ones first
+        None => {
+            qb.push(" AND is_dir=1");
         }
-        // FIX Bug #8: Escape special characters to prevent SQL injection via path
-        let pfx_escaped = escape_like_pattern(&pfx);
-        let pfx_upper = format!("{}~", pfx_escaped);
-        // FIX Bug #3 (Unicode Query): Use LIKE instead of range optimization
-        // Range optimization (path >= pfx AND path < pfx_upper) is tricky with Unicode.
-        // SQLite's LIKE operator is safer and sufficient here given the index.
-        qb.push(" AND (path = ").push_bind(peq.clone());
-        qb.push(" OR path LIKE ").push_bind(format!("{}%", pfx_escaped));
-        qb.push(" ESCAPE '!')"); // Ensure we use the escape character defined in helper
-        qb.push(")");
-    }
-    if let (Some(bd), Some(d)) = (base_depth, q.depth) {
-        let max_depth = bd + d;
+    }
+    if let Some(max_depth) = max_depth {
         qb.push(" AND depth <= ").push_bind(max_depth);
     }
-
-    match q.sort.as_deref() {
-        Some("name") => qb.push(" ORDER BY path ASC"),
-        _ => qb.push(" ORDER BY allocated_size DESC"),
-    };
-    // Clamp limit to a safe range to prevent overly large responses while allowing larger exports for power users
-    let limit = q.limit.unwrap_or(200).clamp(1, TREE_LIMIT_MAX);
+    qb.push(if by_name { " ORDER BY path ASC" } else { " ORDER BY allocated_size DESC" });
     qb.push(" LIMIT ").push_bind(limit);
+    qb
+}
 
-    let rows = qb.build().fetch_all(&state.db).await?;
-    let mut items: Vec<NodeDto> = Vec::with_capacity(rows.len());
-    for r in rows {
-        let path: String = r.get("path");
-        let mtime = r.get::<Option<i64>, _>("mtime");
-        let atime = r.get::<Option<i64>, _>("atime");
-        items.push(NodeDto {
-            path,
-            parent_path: r.get("parent_path"),
-            depth: r.get("depth"),
-            is_dir: r.get::<i64, _>("is_dir") != 0,
-            logical_size: r.get("logical_size"),
-            allocated_size: r.get("allocated_size"),
-            file_count: r.get("file_count"),
-            dir_count: r.get("dir_count"),
-            mtime,
-            atime,
-        });
-    }
+/// The columns of `nodes` that [`node_dto`] reads.
+pub(crate) const NODE_COLUMNS: &str = "path, parent_path, depth, is_dir, logical_size, allocated_size, \
+                                       file_count, dir_count, mtime, atime, fingerprint";
 
-    Ok(Json(items))
+/// Builds a `NodeDto` from a row with the [`NODE_COLUMNS`].
+pub(crate) fn node_dto(r: &sqlx::sqlite::SqliteRow) -> NodeDto {
+    NodeDto {
+        path: r.get("path"),
+        parent_path: r.get("parent_path"),
+        depth: r.get("depth"),
+        is_dir: r.get::<i64, _>("is_dir") != 0,
+        logical_size: r.get("logical_size"),
+        allocated_size: r.get("allocated_size"),
+        file_count: r.get("file_count"),
+        dir_count: r.get("dir_count"),
+        mtime: r.get("mtime"),
+        atime: r.get("atime"),
+        fingerprint: r.get::<Option<i64>, _>("fingerprint").map(|f| format!("{:016x}", f as u64)),
+    }
 }
 
 // ---------------------- TOP ENDPOINT ----------------------
@@ -715,62 +2129,156 @@ pub struct TopQuery {
     pub scope: Option<String>, // dirs|files
     /// The maximum number of results to return.
     pub limit: Option<i64>,
+    /// Only rank items below this directory.
+    pub path: Option<String>,
+    /// How many levels below `path` to look, 1 for direct children only; requires `path`.
+    pub max_depth_relative: Option<i64>,
+    /// Only return items with at least this many allocated bytes.
+    pub min_size: Option<i64>,
+}
+
+/// The part of a scan the top endpoint ranks.
+#[derive(Debug, Default)]
+pub(crate) struct TopFilter {
+    /// The directory whose descendants are ranked; the whole scan if `None`.
+    pub path: Option<String>,
+    /// The deepest directory depth to rank, absolute.
+    pub max_depth: Option<i64>,
+    /// The minimum allocated size of an item.
+    pub min_size: Option<i64>,
+}
+
+impl TopFilter {
+    /// Restricts a query on `column` to the descendants of the filter's path.
+    fn push_subtree(&self, qb: &mut QueryBuilder<'_, sqlx::Sqlite>, column: &str, include_self: bool) {
+        if let Some(root) = self.path.as_deref() {
+            if include_self {
+                qb.push(format!(" AND ({} = ", column)).push_bind(root.to_string());
+                qb.push(format!(" OR {} LIKE ", column));
+            } else {
+                qb.push(format!(" AND ({} LIKE ", column));
+            }
+            qb.push_bind(subtree_like_pattern(root)).push(" ESCAPE '!')");
+        }
+    }
 }
 
 /// Gets the top N largest files or directories in a scan.
 ///
+/// With `path` only items below that directory are ranked, the directory
+/// itself is left out. `max_depth_relative` limits how deep below it items
+/// may lie: 1 ranks its subdirectories and the files directly inside it.
+///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan.
 /// * `q` - The top query parameters.
+/// * `format` - JSON or MessagePack, see [`ResponseFormat::negotiate`].
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `TopItem` objects.
+/// * `AppResult<Response>` - A JSON or MessagePack response containing a list of `TopItem` objects.
 pub async fn get_top(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
     Query(q): Query<TopQuery>,
-) -> AppResult<impl IntoResponse> {
+    format: ResponseFormat,
+) -> AppResult<Response> {
+    let pool = state.read_pool();
+    ns.ensure_scan(pool, id).await?;
     // Clamp limit to a safe range to prevent overly large responses
     let limit = q.limit.unwrap_or(100).clamp(1, 500);
     let scope = q.scope.as_deref().unwrap_or("dirs");
-    if scope == "files" {
-        let rows = sqlx::query(
-            r#"SELECT path, parent_path, logical_size, allocated_size, mtime, atime
-               FROM files WHERE scan_id=?1 ORDER BY allocated_size DESC LIMIT ?2"#,
-        )
-        .bind(id.to_string())
-        .bind(limit)
-        .fetch_all(&state.db)
-        .await?;
-        let mut items: Vec<TopItem> = Vec::with_capacity(rows.len());
-        for r in rows {
-            let p: String = r.get("path");
-            let mtime = r.get::<Option<i64>, _>("mtime");
-            let atime = r.get::<Option<i64>, _>("atime");
-            items.push(TopItem::File {
-                path: p,
-                parent_path: r.get("parent_path"),
-                logical_size: r.get("logical_size"),
-                allocated_size: r.get("allocated_size"),
-                mtime,
-                atime,
-            });
+    if q.max_depth_relative.is_some_and(|d| d < 1) {
+        return Err(AppError::BadRequest("max_depth_relative must be >= 1".into()));
+    }
+    if q.max_depth_relative.is_some() && q.path.is_none() {
+        return Err(AppError::BadRequest("max_depth_relative requires path".into()));
+    }
+
+    let mut filter = TopFilter { min_size: q.min_size, ..Default::default() };
+    if let Some(p) = q.path.as_deref() {
+        if p.len() > 4096 {
+            return Err(AppError::BadRequest("Path too long".into()));
         }
-        return Ok(Json(items));
+        let root = resolve_query_path(pool, id, p).await?;
+        if let Some(relative) = q.max_depth_relative {
+            let base: Option<i64> = sqlx::query_scalar("SELECT depth FROM nodes WHERE scan_id=?1 AND path=?2 LIMIT 1")
+                .bind(id.to_string())
+                .bind(&root)
+                .fetch_optional(pool)
+                .await?;
+            filter.max_depth = base.map(|b| b + relative);
+        }
+        filter.path = Some(root);
     }
 
-    // default: dirs
-    let rows = sqlx::query(
-        r#"SELECT path, parent_path, depth, logical_size, allocated_size, file_count, dir_count, mtime, atime
-           FROM nodes WHERE scan_id=?1 AND is_dir=1 ORDER BY allocated_size DESC LIMIT ?2"#,
-    )
-    .bind(id.to_string())
-    .bind(limit)
-    .fetch_all(&state.db)
-    .await?;
+    if scope == "files" {
+        return Ok(respond_negotiated(format, &top_files(pool, id, limit, &filter).await?));
+    }
+    Ok(respond_negotiated(format, &top_dirs(pool, id, limit, &filter).await?))
+}
+
+/// Loads the `limit` files of a scan with the most allocated bytes.
+async fn top_files(pool: &sqlx::SqlitePool, id: Uuid, limit: i64, filter: &TopFilter) -> AppResult<Vec<TopItem>> {
+    let mut qb = QueryBuilder::new(
+        "SELECT path, parent_path, logical_size, allocated_size, mtime, atime FROM files WHERE scan_id=",
+    );
+    qb.push_bind(id.to_string());
+    filter.push_subtree(&mut qb, "path", false);
+    if let Some(max_depth) = filter.max_depth {
+        // Files carry no depth; they lie one level below their directory
+        qb.push(" AND parent_path IN (SELECT path FROM nodes WHERE is_dir=1 AND scan_id=");
+        qb.push_bind(id.to_string());
+        filter.push_subtree(&mut qb, "path", true);
+        qb.push(" AND depth < ").push_bind(max_depth).push(")");
+    }
+    if let Some(min_size) = filter.min_size {
+        qb.push(" AND allocated_size >= ").push_bind(min_size);
+    }
+    qb.push(" ORDER BY allocated_size DESC LIMIT ").push_bind(limit);
+    let rows = qb.build().fetch_all(pool).await?;
+    let mut items: Vec<TopItem> = Vec::with_capacity(rows.len());
+    for r in rows {
+        let p: String = r.get("path");
+        let mtime = r.get::<Option<i64>, _>("mtime");
+        let atime = r.get::<Option<i64>, _>("atime");
+        items.push(TopItem::File {
+            path: p,
+            parent_path: r.get("parent_path"),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+            mtime,
+            atime,
+        });
+    }
+    Ok(items)
+}
+
+/// Loads the `limit` directories of a scan with the most allocated bytes.
+pub(crate) async fn top_dirs(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    limit: i64,
+    filter: &TopFilter,
+) -> AppResult<Vec<TopItem>> {
+    let mut qb = QueryBuilder::new(
+        "SELECT path, parent_path, depth, logical_size, allocated_size, file_count, dir_count, mtime, atime \
+         FROM nodes WHERE is_dir=1 AND scan_id=",
+    );
+    qb.push_bind(id.to_string());
+    filter.push_subtree(&mut qb, "path", false);
+    if let Some(max_depth) = filter.max_depth {
+        qb.push(" AND depth <= ").push_bind(max_depth);
+    }
+    if let Some(min_size) = filter.min_size {
+        qb.push(" AND allocated_size >= ").push_bind(min_size);
+    }
+    qb.push(" ORDER BY allocated_size DESC LIMIT ").push_bind(limit);
+    let rows = qb.build().fetch_all(pool).await?;
     let mut items: Vec<TopItem> = Vec::with_capacity(rows.len());
     for r in rows {
         let p: String = r.get("path");
@@ -788,7 +2296,7 @@ pub async fn get_top(
             atime,
         });
     }
-    Ok(Json(items))
+    Ok(items)
 }
 
 // ---------------------- LIST ENDPOINT ----------------------
@@ -799,33 +2307,130 @@ pub struct ListQuery {
     /// The path of the directory to list. If not provided, the root directories of the scan are listed.
     pub path: Option<String>,  // if None: list roots only (directories)
     /// The sort order for the results (e.g., "allocated", "logical", "name", "type").
-    pub sort: Option<String>,  // allocated|logical|name|type
+    /// "name_natural" sorts names with embedded numbers numerically ("file2" before "file10").
+    pub sort: Option<String>,  // allocated|logical|name|name_natural|type
     /// The sort direction ("asc" or "desc").
     pub order: Option<String>, // asc|desc
     /// The maximum number of results to return.
     pub limit: Option<i64>,
     /// The number of results to skip.
     pub offset: Option<i64>,
+    /// Only return items with at least this many allocated bytes.
+    pub min_size: Option<i64>,
+    /// Only return items with at most this many allocated bytes.
+    pub max_size: Option<i64>,
+    /// Only return items modified at or after this time (seconds since the Unix epoch).
+    pub modified_after: Option<i64>,
+    /// Only return items modified at or before this time (seconds since the Unix epoch).
+    pub modified_before: Option<i64>,
+    /// The kind of items to return ("dirs", "files" or "all").
+    pub kind: Option<String>, // dirs|files|all
+    /// Whether to wrap the page in a `ListResponse` with totals over all matching items.
+    pub with_totals: Option<bool>,
+}
+
+/// The validated filters of a list query.
+#[derive(Debug, Default)]
+struct ListFilter {
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+    modified_after: Option<i64>,
+    modified_before: Option<i64>,
+    dirs: bool,
+    files: bool,
+}
+
+impl ListFilter {
+    /// Validates the filter parameters of a list query.
+    fn from_query(q: &ListQuery) -> AppResult<Self> {
+        let (dirs, files) = match q.kind.as_deref() {
+            None | Some("all") => (true, true),
+            Some("dirs") => (true, false),
+            Some("files") => (false, true),
+            Some(_) => return Err(AppError::BadRequest("kind must be 'dirs', 'files' or 'all'".into())),
+        };
+        if q.min_size.is_some_and(|s| s < 0) || q.max_size.is_some_and(|s| s < 0) {
+            return Err(AppError::BadRequest("min_size and max_size must be >= 0".into()));
+        }
+        if let (Some(min), Some(max)) = (q.min_size, q.max_size) {
+            if min > max {
+                return Err(AppError::BadRequest("min_size must be <= max_size".into()));
+            }
+        }
+        if let (Some(after), Some(before)) = (q.modified_after, q.modified_before) {
+            if after > before {
+                return Err(AppError::BadRequest("modified_after must be <= modified_before".into()));
+            }
+        }
+        Ok(Self {
+            min_size: q.min_size,
+            max_size: q.max_size,
+            modified_after: q.modified_after,
+            modified_before: q.modified_before,
+            dirs,
+            files,
+        })
+    }
+
+    /// Appends the size and mtime conditions to a query over `nodes` or `files`.
+    fn push_conditions(&self, qb: &mut QueryBuilder<'_, sqlx::Sqlite>) {
+        if let Some(min) = self.min_size {
+            qb.push(" AND allocated_size >= ").push_bind(min);
+        }
+        if let Some(max) = self.max_size {
+            qb.push(" AND allocated_size <= ").push_bind(max);
+        }
+        if let Some(after) = self.modified_after {
+            qb.push(" AND mtime >= ").push_bind(after);
+        }
+        if let Some(before) = self.modified_before {
+            qb.push(" AND mtime <= ").push_bind(before);
+        }
+    }
+
+    /// Applies the filter to a scan root, which is listed without a query.
+    fn matches_root(&self, allocated_size: i64, mtime: Option<i64>) -> bool {
+        self.dirs
+            && self.min_size.is_none_or(|min| allocated_size >= min)
+            && self.max_size.is_none_or(|max| allocated_size <= max)
+            && self.modified_after.is_none_or(|after| mtime.is_some_and(|m| m >= after))
+            && self.modified_before.is_none_or(|before| mtime.is_some_and(|m| m <= before))
+    }
 }
 
 /// Lists the contents of a directory.
 ///
-/// This endpoint can be used to navigate the scanned directory tree.
+/// This endpoint can be used to navigate the scanned directory tree. The size,
+/// mtime and kind filters are applied before sorting and pagination, so
+/// `limit`/`offset` page through the filtered items. Sizes are allocated bytes;
+/// items without a modification time never match a time filter.
+///
+/// The response is a plain array of items. With `with_totals=true` it is a
+/// `ListResponse` that also carries the count and sizes of all filtered items;
+/// without filters the sizes equal those `get_tree` reports for the directory,
+/// because directory items already hold the sizes of their subtrees.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan.
 /// * `q` - The list query parameters.
+/// * `format` - JSON or MessagePack, see [`ResponseFormat::negotiate`].
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `ListItem` objects.
+/// * `AppResult<Response>` - A JSON or MessagePack response containing a list of `ListItem` objects or a
+///   `ListResponse`.
 pub async fn get_list(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
     Query(q): Query<ListQuery>,
-) -> AppResult<impl IntoResponse> {
+    format: ResponseFormat,
+) -> AppResult<Response> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let filter = ListFilter::from_query(&q)?;
     let limit = q.limit.unwrap_or(500).clamp(1, 2000);
     let offset_raw = q.offset.unwrap_or(0);
     if offset_raw < 0 {
@@ -850,7 +2455,7 @@ pub async fn get_list(
     if q.path.is_none() {
         let row = sqlx::query("SELECT root_paths FROM scans WHERE id=?1")
             .bind(id.to_string())
-            .fetch_optional(&state.db)
+            .fetch_optional(state.read_pool())
             .await?;
         let mut items: Vec<ListItem> = vec![];
         if let Some(r) = row {
@@ -858,16 +2463,17 @@ pub async fn get_list(
                 // fetch nodes for these paths to get sizes/counts
                 for root in roots {
                     let original_root = root.clone();
-                    let normalized_root = normalize_query_path(&original_root)?;
+                    let normalized_root =
+                        resolve_query_path(state.read_pool(), id, &original_root).await?;
                     let (total_files, total_dirs) =
-                        get_subtree_totals(id, &normalized_root, &state.db).await?;
+                        get_subtree_totals(id, &normalized_root, state.read_pool()).await?;
 
                     let node_stats = sqlx::query(
                         "SELECT logical_size, allocated_size, mtime, atime FROM nodes WHERE scan_id = ?1 AND path = ?2 LIMIT 1",
                     )
                     .bind(id.to_string())
                     .bind(&normalized_root)
-                    .fetch_optional(&state.db)
+                    .fetch_optional(state.read_pool())
                     .await?;
 
                     let (logical_size, allocated_size, db_mtime, db_atime) = if let Some(ns) = node_stats {
@@ -896,6 +2502,9 @@ pub async fn get_list(
                         .map(|s| s.to_string())
                         .unwrap_or_else(|| original_root.clone());
 
+                    if !filter.matches_root(allocated_size, mtime) {
+                        continue;
+                    }
                     items.push(ListItem::Dir {
                         name,
                         path: normalized_root,
@@ -913,29 +2522,29 @@ pub async fn get_list(
         }
         // simple sort
         sort_items(&mut items[..], q.sort.as_deref(), q.order.as_deref());
-        let slice = items.into_iter().skip(offset).take(limit_usize).collect::<Vec<_>>();
-        return Ok(Json(slice));
+        return Ok(list_page(items, offset, limit_usize, q.with_totals.unwrap_or(false), format));
     }
 
     // With path: list children
     let path = q.path.as_ref().unwrap();
-    let pnorm = normalize_query_path(path)?;
-    let dir_rows = sqlx::query(
-        r#"SELECT path, parent_path, depth, logical_size, allocated_size, file_count, dir_count, mtime, atime
-           FROM nodes WHERE scan_id=?1 AND is_dir=1 AND parent_path=?2"#,
-    )
-    .bind(id.to_string())
-    .bind(&pnorm)
-    .fetch_all(&state.db)
-    .await?;
-    let file_rows = sqlx::query(
-        r#"SELECT path, parent_path, logical_size, allocated_size, mtime, atime
-           FROM files WHERE scan_id=?1 AND parent_path=?2"#,
-    )
-    .bind(id.to_string())
-    .bind(&pnorm)
-    .fetch_all(&state.db)
-    .await?;
+    let pnorm = resolve_query_path(state.read_pool(), id, path).await?;
+    // The largest children come straight from the size-ordered index; totals need every child
+    let largest = (matches!(q.sort.as_deref(), None | Some("allocated"))
+        && matches!(q.order.as_deref(), None | Some("desc"))
+        && !q.with_totals.unwrap_or(false))
+    .then_some(total_span as i64);
+    let dir_rows = if filter.dirs {
+        let mut qb = children_query(ChildTable::Nodes, id, &pnorm, &filter, largest);
+        qb.build().fetch_all(state.read_pool()).await?
+    } else {
+        Vec::new()
+    };
+    let file_rows = if filter.files {
+        let mut qb = children_query(ChildTable::Files, id, &pnorm, &filter, largest);
+        qb.build().fetch_all(state.read_pool()).await?
+    } else {
+        Vec::new()
+    };
 
     let mut items: Vec<ListItem> = Vec::with_capacity(dir_rows.len() + file_rows.len());
     for r in dir_rows {
@@ -983,8 +2592,79 @@ pub async fn get_list(
     }
 
     sort_items(&mut items[..], q.sort.as_deref(), q.order.as_deref());
-    let slice = items.into_iter().skip(offset).take(limit_usize).collect::<Vec<_>>();
-    Ok(Json(slice))
+    Ok(list_page(items, offset, limit_usize, q.with_totals.unwrap_or(false), format))
+}
+
+/// The table [`children_query`] lists.
+#[derive(Debug, Clone, Copy)]
+enum ChildTable {
+    Nodes,
+    Files,
+}
+
+/// Builds the query for the directories or files directly inside `parent`.
+///
+/// With `largest` only that many children are returned, largest first with
+/// ties ordered by path, which the `(scan_id, parent_path, allocated_size, path)`
+/// indexes answer without sorting. Without it the order is left to the caller.
+fn children_query(
+    table: ChildTable,
+    id: Uuid,
+    parent: &str,
+    filter: &ListFilter,
+    largest: Option<i64>,
+) -> QueryBuilder<'static, sqlx::Sqlite> {
+    let mut qb = QueryBuilder::new(match table {
+        ChildTable::Nodes => {
+            r#"SELECT path, parent_path, depth, logical_size, allocated_size, file_count, dir_count,
+                      mtime, atime
+               FROM nodes WHERE scan_id="#
+        }
+        ChildTable::Files => {
+            r#"SELECT path, parent_path, logical_size, allocated_size, mtime, atime
+               FROM files WHERE scan_id="#
+        }
+    });
+    // Nodes only hold directories; testing is_dir would let SQLite pick the
+    // (scan_id, is_dir, allocated_size) index and walk every directory of the scan
+    qb.push_bind(id.to_string()).push(" AND parent_path=").push_bind(parent.to_string());
+    filter.push_conditions(&mut qb);
+    if let Some(limit) = largest {
+        qb.push(" ORDER BY allocated_size DESC, path LIMIT ").push_bind(limit);
+    }
+    qb
+}
+
+/// Cuts a page out of the sorted, filtered items of a listing.
+///
+/// With `with_totals` the page is wrapped in a `ListResponse` whose totals
+/// cover all `items`, i.e. exactly the rows the filters matched.
+fn list_page(
+    items: Vec<ListItem>,
+    offset: usize,
+    limit: usize,
+    with_totals: bool,
+    format: ResponseFormat,
+) -> Response {
+    if !with_totals {
+        let slice = items.into_iter().skip(offset).take(limit).collect::<Vec<_>>();
+        return respond_negotiated(format, &slice);
+    }
+    let (mut total_allocated_size, mut total_logical_size) = (0i64, 0i64);
+    for item in &items {
+        let (ListItem::Dir { allocated_size, logical_size, .. }
+        | ListItem::File { allocated_size, logical_size, .. }) = item;
+        total_allocated_size = total_allocated_size.saturating_add(*allocated_size);
+        total_logical_size = total_logical_size.saturating_add(*logical_size);
+    }
+    let total_count = items.len() as i64;
+    let page = ListResponse {
+        items: items.into_iter().skip(offset).take(limit).collect(),
+        total_count,
+        total_allocated_size,
+        total_logical_size,
+    };
+    respond_negotiated(format, &page)
 }
 
 // ---------------------- RECENT ENDPOINT ----------------------
@@ -998,143 +2678,318 @@ pub struct RecentQuery {
     pub limit: Option<i64>,
     /// An optional path to filter the results to a specific subtree.
     pub path: Option<String>, // optional subtree filter
+    /// Only items modified within this many days before now are returned.
+    pub within_days: Option<u32>,
+    /// If true, the returned items are re-checked against the filesystem.
+    pub verify: Option<bool>,
 }
 
-/// Returns the most recently accessed files and directories in a scan.
+/// Number of paths re-checked concurrently by `verify=true`.
+const RECENT_VERIFY_CONCURRENCY: usize = 16;
+
+/// Returns the most recently modified files and directories in a scan.
 ///
-/// This endpoint provides a list of items based on their access time, which may
-/// not be available on all filesystems.
+/// The answer comes from the modification times stored by the scan, so it
+/// works on scans of unreachable shares or cold snapshots. With `verify=true`
+/// only the returned items are stat-ed again and marked if they changed.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
+/// * `ns` - The namespace of the request.
 /// * `id` - The ID of the scan.
 /// * `q` - The recent query parameters.
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `TopItem` objects.
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `RecentItem` objects,
+///   newest first.
 pub async fn get_recent(
     State(state): State<AppState>,
+    ns: Namespace,
     Path(id): Path<Uuid>,
     Query(q): Query<RecentQuery>,
 ) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
     let scope = q.scope.as_deref().unwrap_or("dirs");
+    let want_dirs = scope == "dirs" || scope == "all";
+    let want_files = scope == "files" || scope == "all";
+    if !want_dirs && !want_files {
+        return Err(AppError::BadRequest("scope must be 'dirs', 'files' or 'all'".into()));
+    }
     let limit = q.limit.unwrap_or(50).clamp(1, 500);
-    // Fetch a superset to compute atime and then take top-N
-    // Use saturating_mul to prevent overflow, but keep reasonable bounds
-    let fetch_multiplier = std::env::var("SPEICHERWALD_RECENT_FETCH_MULTIPLIER")
-        .ok()
-        .and_then(|v| v.parse::<i64>().ok())
-        .unwrap_or(10)
-        .clamp(5, 20);
-    let fetch_cap = limit.saturating_mul(fetch_multiplier).clamp(100, 2000) as i64;
-
-    // Optional subtree filter: build path range [prefix, prefix + high]
-    let mut subtree_eq: Option<String> = None;
-    let mut subtree_lo: Option<String> = None;
-    let mut subtree_hi: Option<String> = None;
-    if let Some(p) = q.path.as_ref() {
-        let peq = normalize_query_path(p)?;
-        let mut pfx = peq.clone();
-        if !pfx.ends_with('/') && !pfx.ends_with('\\') {
-            if pfx.contains('\\') {
-                pfx.push('\\');
-            } else {
-                pfx.push('/');
-            }
+    let cutoff = q.within_days.map(|days| chrono::Utc::now().timestamp() - i64::from(days) * 86_400);
+    let subtree = match q.path.as_ref() {
+        Some(p) => Some(resolve_query_path(state.read_pool(), id, p).await?),
+        None => None,
+    };
+
+    // Each table is sorted and limited in SQL, so `all` merges two top-N lists
+    let push_filters = |qb: &mut QueryBuilder<'_, sqlx::Sqlite>| {
+        qb.push(" AND mtime IS NOT NULL");
+        if let Some(cutoff) = cutoff {
+            qb.push(" AND mtime >= ").push_bind(cutoff);
         }
-        subtree_eq = Some(peq);
-        subtree_lo = Some(pfx.clone());
-        // Use a high but valid ASCII character instead of Unicode max
-        subtree_hi = Some(format!("{}~", pfx));
-    }
+        if let Some(eq) = subtree.as_ref() {
+            qb.push(" AND (path = ").push_bind(eq.clone());
+            qb.push(" OR path LIKE ").push_bind(subtree_like_pattern(eq)).push(" ESCAPE '!')");
+        }
+        qb.push(" ORDER BY mtime DESC, path ASC LIMIT ").push_bind(limit);
+    };
 
     let mut items: Vec<TopItem> = Vec::new();
-    let want_dirs = scope == "dirs" || scope == "all";
-    let want_files = scope == "files" || scope == "all";
-
-    // FIX Bug #2,#8 - Use QueryBuilder instead of string replacement
     if want_dirs {
         let mut qb = QueryBuilder::new(
             "SELECT path, parent_path, depth, logical_size, allocated_size, file_count, dir_count, mtime, atime FROM nodes WHERE scan_id="
         );
         qb.push_bind(id.to_string()).push(" AND is_dir=1");
+        push_filters(&mut qb);
 
-        if let (Some(eq), Some(lo), Some(hi)) =
-            (subtree_eq.as_ref(), subtree_lo.as_ref(), subtree_hi.as_ref())
-        {
-            qb.push(" AND (path = ").push_bind(eq);
-            qb.push(" OR (path >= ").push_bind(lo);
-            qb.push(" AND path < ").push_bind(hi).push("))");
-        }
-        qb.push(" LIMIT ").push_bind(fetch_cap);
-
-        let rows = qb.build().fetch_all(&state.db).await?;
-        for r in rows {
-            let p: String = r.get("path");
-            let mtime = r.get::<Option<i64>, _>("mtime");
-            let atime = r.get::<Option<i64>, _>("atime");
-            items.push(TopItem::Dir {
-                path: p,
-                parent_path: r.get("parent_path"),
-                depth: r.get("depth"),
-                logical_size: r.get("logical_size"),
-                allocated_size: r.get("allocated_size"),
-                file_count: r.get("file_count"),
-                dir_count: r.get("dir_count"),
-                mtime,
-                atime,
-            });
-        }
+        let rows = qb.build().fetch_all(state.read_pool()).await?;
+        items.extend(rows.iter().map(|r| TopItem::Dir {
+            path: r.get("path"),
+            parent_path: r.get("parent_path"),
+            depth: r.get("depth"),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+            file_count: r.get("file_count"),
+            dir_count: r.get("dir_count"),
+            mtime: r.get("mtime"),
+            atime: r.get("atime"),
+        }));
     }
-    // FIX Bug #3,#9 - Use QueryBuilder instead of string replacement
     if want_files {
         let mut qb = QueryBuilder::new(
             "SELECT path, parent_path, logical_size, allocated_size, mtime, atime FROM files WHERE scan_id=",
         );
         qb.push_bind(id.to_string());
+        push_filters(&mut qb);
 
-        if let (Some(eq), Some(lo), Some(hi)) =
-            (subtree_eq.as_ref(), subtree_lo.as_ref(), subtree_hi.as_ref())
-        {
-            qb.push(" AND (path = ").push_bind(eq);
-            qb.push(" OR (path >= ").push_bind(lo);
-            qb.push(" AND path < ").push_bind(hi).push("))");
-        }
-        qb.push(" LIMIT ").push_bind(fetch_cap);
-
-        let rows = qb.build().fetch_all(&state.db).await?;
-        for r in rows {
-            let p: String = r.get("path");
-            let mtime = r.get::<Option<i64>, _>("mtime");
-            let atime = r.get::<Option<i64>, _>("atime");
-            items.push(TopItem::File {
-                path: p,
-                parent_path: r.get("parent_path"),
-                logical_size: r.get("logical_size"),
-                allocated_size: r.get("allocated_size"),
-                mtime,
-                atime,
-            });
-        }
+        let rows = qb.build().fetch_all(state.read_pool()).await?;
+        items.extend(rows.iter().map(|r| TopItem::File {
+            path: r.get("path"),
+            parent_path: r.get("parent_path"),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+            mtime: r.get("mtime"),
+            atime: r.get("atime"),
+        }));
     }
 
-    items.sort_by_key(|i| match i {
-        TopItem::Dir { atime, .. } => atime.unwrap_or(0),
-        TopItem::File { atime, .. } => atime.unwrap_or(0),
-    });
-    items.reverse();
+    let (mtime_of, path_of) = (
+        |i: &TopItem| match i {
+            TopItem::Dir { mtime, .. } | TopItem::File { mtime, .. } => *mtime,
+        },
+        |i: &TopItem| match i {
+            TopItem::Dir { path, .. } | TopItem::File { path, .. } => path.clone(),
+        },
+    );
+    items.sort_by(|a, b| mtime_of(b).cmp(&mtime_of(a)).then_with(|| path_of(a).cmp(&path_of(b))));
     items.truncate(limit as usize);
 
+    let items: Vec<RecentItem> = if q.verify.unwrap_or(false) {
+        let checks = items.into_iter().map(|item| async move {
+            let current_mtime = get_mtime_secs(&path_of(&item)).await;
+            let changed = current_mtime.is_none() || current_mtime != mtime_of(&item);
+            RecentItem { item, changed: Some(changed), current_mtime }
+        });
+        let checked = futures::StreamExt::buffered(futures::stream::iter(checks), RECENT_VERIFY_CONCURRENCY);
+        futures::StreamExt::collect(checked).await
+    } else {
+        items.into_iter().map(|item| RecentItem { item, changed: None, current_mtime: None }).collect()
+    };
+
     Ok(Json(items))
 }
 
+// ---------------------- COMPLETE ENDPOINT ----------------------
+
+/// Query parameters for the complete endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct CompleteQuery {
+    /// The path prefix typed so far. A trailing separator means "children of".
+    pub prefix: String,
+    /// The maximum number of candidates to return.
+    pub limit: Option<i64>,
+    /// If true, only the next path segment after the prefix is completed.
+    pub segment: Option<bool>,
+}
+
+/// Suggests directory paths from a finished (or running) scan.
+///
+/// Candidates come from the `nodes` table via an indexed prefix range on the
+/// stored path, ranked by allocated size. With `segment=true` the results are
+/// grouped by the next path component after the prefix and their sizes are
+/// aggregated, which works even if the component itself was not scanned
+/// (e.g. two scan roots below a common parent).
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The complete query parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `CompletionDto` objects.
+pub async fn get_complete(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<CompleteQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let limit = q.limit.unwrap_or(20).clamp(1, 100);
+    let prefix = normalize_completion_prefix(&q.prefix)?;
+
+    let items = if q.segment.unwrap_or(false) {
+        complete_segments(state.read_pool(), id, &prefix, limit).await?
+    } else {
+        complete_paths(state.read_pool(), id, &prefix, limit).await?
+    };
+    Ok(Json(items))
+}
+
+/// Normalizes an autocomplete prefix like any other query path, but keeps a
+/// trailing separator so that `D:\proj\` completes children of `D:\proj`.
+fn normalize_completion_prefix(raw: &str) -> AppResult<String> {
+    if raw.len() > 4096 {
+        return Err(AppError::BadRequest("prefix too long".into()));
+    }
+    let mut prefix = normalize_query_path(raw)?;
+    if raw.ends_with(['/', '\\']) && !prefix.ends_with(['/', '\\']) {
+        prefix.push(path_separator(&prefix));
+    }
+    Ok(prefix)
+}
+
+fn path_separator(path: &str) -> char {
+    if path.contains('\\') {
+        '\\'
+    } else {
+        '/'
+    }
+}
+
+/// Exclusive upper bound for a prefix range scan on the `path` column.
+///
+/// Unlike the `~` bound used by the recent endpoint this also covers
+/// non-ASCII segments, since `char::MAX` sorts after every other code point.
+fn prefix_range_upper(prefix: &str) -> String {
+    format!("{}{}", prefix, char::MAX)
+}
+
+fn last_segment(path: &str) -> String {
+    path.trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .filter(|s| !s.is_empty())
+        .unwrap_or(path)
+        .to_string()
+}
+
+async fn count_children(pool: &sqlx::SqlitePool, id: Uuid, path: &str) -> AppResult<i64> {
+    let row = sqlx::query(
+        r#"SELECT (SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND parent_path=?2)
+                + (SELECT COUNT(*) FROM files WHERE scan_id=?1 AND parent_path=?2) AS n"#,
+    )
+    .bind(id.to_string())
+    .bind(path)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.get::<i64, _>("n"))
+}
+
+/// Returns every scanned directory whose path starts with `prefix`, largest first.
+async fn complete_paths(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    prefix: &str,
+    limit: i64,
+) -> AppResult<Vec<CompletionDto>> {
+    let rows = sqlx::query(
+        r#"SELECT path, logical_size, allocated_size FROM nodes
+           WHERE scan_id=?1 AND is_dir=1 AND path >= ?2 AND path < ?3
+           ORDER BY allocated_size DESC, path ASC LIMIT ?4"#,
+    )
+    .bind(id.to_string())
+    .bind(prefix)
+    .bind(prefix_range_upper(prefix))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let mut items = Vec::with_capacity(rows.len());
+    for r in rows {
+        let path: String = r.get("path");
+        let child_count = count_children(pool, id, &path).await?;
+        items.push(CompletionDto {
+            name: last_segment(&path),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+            child_count,
+            path,
+        });
+    }
+    Ok(items)
+}
+
+/// Completes only the next path segment after `prefix`.
+///
+/// Only the top-most matching directories are fetched (those whose parent is
+/// not itself in the range), so their subtree totals can be summed per
+/// segment without double counting.
+async fn complete_segments(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    prefix: &str,
+    limit: i64,
+) -> AppResult<Vec<CompletionDto>> {
+    let rows = sqlx::query(
+        r#"SELECT n.path, n.logical_size, n.allocated_size FROM nodes n
+           WHERE n.scan_id=?1 AND n.is_dir=1 AND n.path >= ?2 AND n.path < ?3
+             AND NOT EXISTS (
+                 SELECT 1 FROM nodes p
+                 WHERE p.scan_id=n.scan_id AND p.path=n.parent_path AND p.path >= ?2 AND p.path < ?3
+             )"#,
+    )
+    .bind(id.to_string())
+    .bind(prefix)
+    .bind(prefix_range_upper(prefix))
+    .fetch_all(pool)
+    .await?;
+
+    let mut groups: std::collections::HashMap<String, (i64, i64)> = std::collections::HashMap::new();
+    for r in rows {
+        let path: String = r.get("path");
+        let Some(rest) = path.strip_prefix(prefix) else { continue };
+        let segment = match rest.find(['/', '\\']) {
+            Some(idx) => &path[..prefix.len() + idx],
+            None => path.as_str(),
+        };
+        let entry = groups.entry(segment.to_string()).or_insert((0, 0));
+        entry.0 += r.get::<i64, _>("logical_size");
+        entry.1 += r.get::<i64, _>("allocated_size");
+    }
+
+    let mut ranked: Vec<(String, (i64, i64))> = groups.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(limit as usize);
+
+    let mut items = Vec::with_capacity(ranked.len());
+    for (path, (logical_size, allocated_size)) in ranked {
+        let child_count = count_children(pool, id, &path).await?;
+        items.push(CompletionDto { name: last_segment(&path), logical_size, allocated_size, child_count, path });
+    }
+    Ok(items)
+}
+
 fn sort_items(items: &mut [ListItem], sort: Option<&str>, order: Option<&str>) {
     // FIX Bug #68 - Default should depend on sort type
     let sort_key = match sort {
-        Some("name") | Some("logical") | Some("type") | Some("modified") | Some("accessed")
-        | Some("allocated") => sort.unwrap(),
+        Some("name") | Some("name_natural") | Some("logical") | Some("type") | Some("modified")
+        | Some("accessed") | Some("allocated") => sort.unwrap(),
         _ => "allocated", // default fallback
     };
 
@@ -1153,6 +3008,16 @@ fn sort_items(items: &mut [ListItem], sort: Option<&str>, order: Option<&str>) {
                 items.reverse();
             }
         }
+        "name_natural" => {
+            // Ties (names differing only in case or leading zeros) keep a fixed order across pages
+            items.sort_by(|a, b| {
+                let (name_a, name_b) = (get_name(a), get_name(b));
+                natural_cmp(&name_a, &name_b).then_with(|| name_a.cmp(&name_b))
+            });
+            if matches!(order, Some("desc")) {
+                items.reverse();
+            }
+        }
         "logical" => {
             items.sort_by_key(get_logical);
             if desc {
@@ -1177,11 +3042,40 @@ fn sort_items(items: &mut [ListItem], sort: Option<&str>, order: Option<&str>) {
                 items.reverse();
             }
         }
-        _ => {
-            items.sort_by_key(get_alloc);
-            if desc {
-                items.reverse();
+        // Stable in both directions, so a page cut from the largest children of
+        // `children_query` keeps their order
+        _ if desc => items.sort_by_key(|i| std::cmp::Reverse(get_alloc(i))),
+        _ => items.sort_by_key(get_alloc),
+    }
+}
+
+/// Compares names case-insensitively, with runs of digits compared by their numeric value.
+///
+/// "a1b" < "a2" < "a10" < "b1". Digit runs of any length are compared without
+/// parsing, so numbers beyond `u64` still order correctly.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.is_empty().cmp(&b.is_empty()).reverse();
+        };
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let len_a = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let len_b = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let (num_a, num_b) = (a[..len_a].trim_start_matches('0'), b[..len_b].trim_start_matches('0'));
+            let ord = num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b));
+            if ord != Ordering::Equal {
+                return ord;
             }
+            (a, b) = (&a[len_a..], &b[len_b..]);
+        } else {
+            let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
         }
     }
 }
@@ -1255,3 +3149,1592 @@ async fn get_atime_secs(path: &str) -> Option<i64> {
         .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
         .map(|d| d.as_secs() as i64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_node, insert_scan, test_db, test_db_in, FileRow, NodeRow};
+
+    async fn fixture() -> (tempfile::TempDir, sqlx::SqlitePool, Uuid) {
+        let (dir, pool) = test_db().await;
+
+        let id = insert_scan(&pool, "done", &[], "default").await;
+
+        // (path, parent_path, allocated_size)
+        let dirs: &[(&str, &str, i64)] = &[
+            ("/data", "/", 1000),
+            ("/data/proj", "/data", 300),
+            ("/data/proj/a", "/data/proj", 200),
+            ("/data/projects", "/data", 400),
+            ("/data/projects/b", "/data/projects", 50),
+            ("/data/prognosis", "/data", 100),
+            ("/data/pröj", "/data", 150),
+            ("/data/проект", "/data", 50),
+            // Two scan roots below an unscanned common parent
+            ("/mnt/share/x", "/mnt/share", 70),
+            ("/mnt/share/y", "/mnt/share", 30),
+        ];
+        for &(path, parent, size) in dirs {
+            insert_node(&pool, id, NodeRow { path, parent: Some(parent), size, ..Default::default() }).await;
+        }
+        let readme = FileRow {
+            path: "/data/proj/readme.txt",
+            parent: Some("/data/proj"),
+            size: 100,
+            ..Default::default()
+        };
+        insert_file(&pool, id, readme).await;
+        (dir, pool, id)
+    }
+
+    fn paths(items: &[CompletionDto]) -> Vec<&str> {
+        items.iter().map(|i| i.path.as_str()).collect()
+    }
+
+    /// Returns the plan SQLite picks for a query, one detail per line.
+    async fn query_plan(pool: &sqlx::SqlitePool, qb: QueryBuilder<'_, sqlx::Sqlite>) -> String {
+        // Unbound parameters are NULL, which does not change the plan
+        let rows = sqlx::query(&format!("EXPLAIN QUERY PLAN {}", qb.sql())).fetch_all(pool).await.unwrap();
+        rows.iter().map(|r| r.get::<String, _>("detail")).collect::<Vec<_>>().join("\n")
+    }
+
+    #[tokio::test]
+    async fn hot_listing_queries_read_from_indexes() {
+        let (_dir, pool, id) = fixture().await;
+        let filter = ListFilter { dirs: true, files: true, min_size: Some(1), ..Default::default() };
+        let queries = [
+            (tree_query(id, None, None, false, 200), "idx_nodes_scan_isdir_alloc_desc"),
+            (tree_query(id, Some("/data"), Some(3), false, 200), "ux_nodes_scan_path"),
+            (tree_query(id, Some("/data"), None, true, 200), "ux_nodes_scan_path"),
+            (children_query(ChildTable::Nodes, id, "/data", &filter, None), "idx_nodes_scan_parent_alloc"),
+            (children_query(ChildTable::Files, id, "/data", &filter, None), "idx_files_scan_parent_alloc"),
+        ];
+        for (qb, index) in queries {
+            let plan = query_plan(&pool, qb).await;
+            assert!(plan.contains(&format!("INDEX {} (scan_id=?", index)), "{}", plan);
+        }
+        // The largest children come in index order without sorting
+        for table in [ChildTable::Nodes, ChildTable::Files] {
+            let plan = query_plan(&pool, children_query(table, id, "/data", &filter, Some(500))).await;
+            assert!(plan.contains("_scan_parent_alloc (scan_id=? AND parent_path=?"), "{}", plan);
+            assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+        }
+    }
+
+    #[tokio::test]
+    async fn tree_subtrees_exclude_siblings_with_the_same_prefix() {
+        let (_dir, pool, id) = fixture().await;
+        let subtree = |path: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let mut qb = tree_query(id, Some(path), None, true, 100);
+                let rows = qb.build().fetch_all(&pool).await.unwrap();
+                rows.iter().map(|r| r.get::<String, _>("path")).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(subtree("/data/proj").await, ["/data/proj", "/data/proj/a"]);
+        assert_eq!(subtree("/data/pröj").await, ["/data/pröj"]);
+        assert_eq!(subtree("/mnt/share").await, ["/mnt/share/x", "/mnt/share/y"]);
+        assert_eq!(subtree("/").await.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn complete_full_paths_ranked_by_size() {
+        let (_dir, pool, id) = fixture().await;
+        let prefix = normalize_completion_prefix("/data/pro").unwrap();
+        let items = complete_paths(&pool, id, &prefix, 20).await.unwrap();
+        assert_eq!(
+            paths(&items),
+            vec!["/data/projects", "/data/proj", "/data/proj/a", "/data/prognosis", "/data/projects/b"]
+        );
+        let proj = items.iter().find(|i| i.path == "/data/proj").unwrap();
+        assert_eq!(proj.name, "proj");
+        assert_eq!(proj.child_count, 2);
+
+        let limited = complete_paths(&pool, id, &prefix, 2).await.unwrap();
+        assert_eq!(paths(&limited), vec!["/data/projects", "/data/proj"]);
+    }
+
+    #[tokio::test]
+    async fn complete_full_paths_with_unicode_prefix() {
+        let (_dir, pool, id) = fixture().await;
+        let items = complete_paths(&pool, id, "/data/prö", 20).await.unwrap();
+        assert_eq!(paths(&items), vec!["/data/pröj"]);
+        let items = complete_paths(&pool, id, "/data/про", 20).await.unwrap();
+        assert_eq!(paths(&items), vec!["/data/проект"]);
+        assert_eq!(items[0].name, "проект");
+    }
+
+    #[tokio::test]
+    async fn complete_segments_groups_next_component() {
+        let (_dir, pool, id) = fixture().await;
+        let items = complete_segments(&pool, id, "/data/pro", 20).await.unwrap();
+        assert_eq!(paths(&items), vec!["/data/projects", "/data/proj", "/data/prognosis"]);
+
+        let prefix = normalize_completion_prefix("/data/").unwrap();
+        assert_eq!(prefix, "/data/");
+        let items = complete_segments(&pool, id, &prefix, 20).await.unwrap();
+        assert_eq!(
+            paths(&items),
+            vec!["/data/projects", "/data/proj", "/data/pröj", "/data/prognosis", "/data/проект"]
+        );
+    }
+
+    #[tokio::test]
+    async fn complete_segments_aggregates_unscanned_parent() {
+        let (_dir, pool, id) = fixture().await;
+        let items = complete_segments(&pool, id, "/mnt/", 20).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].path, "/mnt/share");
+        assert_eq!(items[0].name, "share");
+        assert_eq!(items[0].allocated_size, 100);
+        assert_eq!(items[0].child_count, 2);
+    }
+
+    async fn namespaced_fixture() -> (tempfile::TempDir, AppState, Uuid, Uuid) {
+        let (dir, pool) = test_db().await;
+        let mut ids = Vec::new();
+        for ns in ["hr", "finance"] {
+            let id = insert_scan(&pool, "done", &[], ns).await;
+            let path = format!("/{}", ns);
+            insert_node(&pool, id, NodeRow { path: &path, depth: 1, size: 10, ..Default::default() }).await;
+            ids.push(id);
+        }
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        (dir, state, ids[0], ids[1])
+    }
+
+    async fn json_body(res: impl IntoResponse) -> serde_json::Value {
+        use http_body_util::BodyExt;
+        let bytes = res.into_response().into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    async fn listed_ids(state: &AppState, ns: &str) -> Vec<String> {
+        let ns = Namespace::parse(ns).unwrap();
+        let body = json_body(list_scans(State(state.clone()), ns).await.unwrap()).await;
+        let mut ids: Vec<String> = body.as_array().unwrap().iter().map(|s| s["id"].as_str().unwrap().to_string()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn is_not_found<T>(res: AppResult<T>) -> bool {
+        matches!(res, Err(AppError::NotFound(_)))
+    }
+
+    #[tokio::test]
+    async fn namespaces_are_isolated() {
+        let (_dir, state, hr_id, fin_id) = namespaced_fixture().await;
+        let hr = Namespace::parse("hr").unwrap();
+
+        assert_eq!(listed_ids(&state, "hr").await, vec![hr_id.to_string()]);
+        assert_eq!(listed_ids(&state, "finance").await, vec![fin_id.to_string()]);
+        assert!(listed_ids(&state, "default").await.is_empty());
+
+        let own = json_body(get_scan(State(state.clone()), hr.clone(), Path(hr_id)).await.unwrap()).await;
+        assert_eq!(own["namespace"], "hr");
+        assert!(is_not_found(get_scan(State(state.clone()), hr.clone(), Path(fin_id)).await));
+        assert!(is_not_found(
+            get_tree(State(state.clone()), hr.clone(), Path(fin_id), Query(TreeQuery::default()), ResponseFormat::Json).await
+        ));
+        assert!(is_not_found(get_list(State(state.clone()), hr.clone(), Path(fin_id), Query(ListQuery::default()), ResponseFormat::Json).await));
+        assert!(is_not_found(
+            open_events(&state, hr.clone(), fin_id, all_events(), HeaderMap::new()).await
+        ));
+        let export = crate::routes::export::ExportQuery { format: "ndjson".into(), scope: None, limit: None, ..Default::default() };
+        assert!(is_not_found(
+            crate::routes::export::export_scan(State(state.clone()), hr.clone(), Path(fin_id), Query(export)).await
+        ));
+        let diff = crate::routes::diff::DiffQuery { other: fin_id, sort: None, order: None, limit: None, path: None };
+        assert!(is_not_found(crate::routes::diff::diff_scan(State(state.clone()), hr.clone(), Path(hr_id), Query(diff)).await));
+
+        // Purging a foreign scan is a silent no-op and leaves its data in place
+        cancel_scan(State(state.clone()), hr.clone(), Path(fin_id), Query(CancelQuery { purge: Some(true), finalize: None }))
+            .await
+            .unwrap();
+        assert_eq!(listed_ids(&state, "finance").await, vec![fin_id.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn scans_expose_root_paths_and_options_and_tolerate_corrupt_json() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        crate::db::init_db(&pool).await.unwrap();
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let ns = Namespace::default();
+        let options =
+            ScanOptions { include_hidden: false, excludes: vec!["**/node_modules".into()], ..Default::default() };
+        let (good, corrupt) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, roots, opts) in [
+            (good, r#"["D:\\Data","E:\\"]"#.to_string(), serde_json::to_string(&options).unwrap()),
+            (corrupt, "[not json".to_string(), "{\"excludes\":".to_string()),
+        ] {
+            sqlx::query("INSERT INTO scans (id, status, root_paths, options) VALUES (?1, 'done', ?2, ?3)")
+                .bind(id.to_string())
+                .bind(roots)
+                .bind(opts)
+                .execute(&state.db)
+                .await
+                .unwrap();
+        }
+
+        let scan = json_body(get_scan(State(state.clone()), ns.clone(), Path(good)).await.unwrap()).await;
+        assert_eq!(scan["root_paths"], serde_json::json!([r"D:\Data", r"E:\"]));
+        assert_eq!(scan["options"]["include_hidden"], false);
+        assert_eq!(scan["options"]["excludes"], serde_json::json!(["**/node_modules"]));
+
+        let scan = json_body(get_scan(State(state.clone()), ns.clone(), Path(corrupt)).await.unwrap()).await;
+        assert_eq!(scan["status"], "done");
+        assert!(scan["options"].is_null());
+        assert_eq!(scan["root_paths"], serde_json::json!([]));
+
+        let listed = json_body(list_scans(State(state.clone()), ns).await.unwrap()).await;
+        let listed_good = listed.as_array().unwrap().iter().find(|s| s["id"] == good.to_string()).unwrap();
+        assert_eq!(listed_good["root_paths"], serde_json::json!([r"D:\Data", r"E:\"]));
+        assert!(listed_good["options"].is_null());
+    }
+
+    #[tokio::test]
+    async fn admin_namespace_sees_all_scans() {
+        let (_dir, state, hr_id, fin_id) = namespaced_fixture().await;
+        let admin = Namespace::parse("admin").unwrap();
+        assert!(admin.is_admin());
+
+        let mut expected = vec![hr_id.to_string(), fin_id.to_string()];
+        expected.sort();
+        assert_eq!(listed_ids(&state, "admin").await, expected);
+        let fin = json_body(get_scan(State(state.clone()), admin.clone(), Path(fin_id)).await.unwrap()).await;
+        assert_eq!(fin["namespace"], "finance");
+        assert!(get_tree(State(state.clone()), admin, Path(hr_id), Query(TreeQuery::default()), ResponseFormat::Json).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn finalize_keeps_persisted_records_as_partial_scan() {
+        let (dir, pool) = test_db().await;
+        let mut config = crate::config::AppConfig::default();
+        config.scanner.batch_size = 100;
+        config.scanner.flush_threshold = 100;
+        config.scanner.flush_interval_ms = 20;
+        let state = AppState::new(pool, config);
+
+        // Large enough that the scan is still running when the first rows are persisted
+        let root = dir.path().join("data");
+        for d in 0..100 {
+            let sub = root.join(format!("dir{:03}", d)).join("nested");
+            std::fs::create_dir_all(&sub).unwrap();
+            for f in 0..300 {
+                std::fs::write(sub.join(format!("f{:03}.bin", f)), vec![0u8; 1 + f % 7]).unwrap();
+            }
+        }
+        let req = CreateScanRequest {
+            root_paths: vec![root.to_string_lossy().to_string()],
+            follow_symlinks: None,
+            include_hidden: None,
+            measure_logical: None,
+            measure_allocated: None,
+            excludes: None,
+            includes: None,
+            max_depth: None,
+            concurrency: Some(1),
+            measure_hardlinks: None,
+            measure_ads: None,
+            io_priority: None,
+            capture_owner: None,
+            access_mode: None,
+            hash_min_size: None,
+            read_only: None,
+            no_system_excludes: None,
+            rescan_of: None,
+        };
+        let ns = Namespace::parse("default").unwrap();
+        let id = start_scan(&state, req, &ns).await.unwrap().id;
+
+        let files_persisted = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+                .bind(id.to_string())
+                .fetch_one(&state.db)
+                .await
+                .unwrap()
+        };
+        for _ in 0..2000 {
+            if files_persisted().await > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        let q = CancelQuery { purge: None, finalize: Some(true) };
+        let res = cancel_scan(State(state.clone()), ns.clone(), Path(id), Query(q)).await.unwrap().into_response();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(!state.jobs.read().await.contains_key(&id));
+
+        let scan = json_body(get_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap()).await;
+        assert_eq!(scan["status"], "partial");
+        assert!(scan["finished_at"].is_string());
+        assert!(scan["duration_ms"].is_i64());
+        assert!(scan["bytes_per_sec"].is_f64());
+        let listed = json_body(list_scans(State(state.clone()), ns.clone()).await.unwrap()).await;
+        assert_eq!(listed[0]["status"], "partial");
+
+        let persisted = sqlx::query(
+            r#"SELECT COUNT(*) AS files, COALESCE(SUM(logical_size),0) AS logical, COALESCE(SUM(allocated_size),0) AS allocated
+               FROM files WHERE scan_id=?1"#,
+        )
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+        let dirs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND is_dir=1")
+            .bind(id.to_string())
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        let files: i64 = persisted.get("files");
+        assert!(files > 0);
+        assert!(files < 30_000, "scan finished before it was cancelled");
+        assert_eq!(scan["file_count"], files);
+        assert_eq!(scan["total_logical_size"], persisted.get::<i64, _>("logical"));
+        assert_eq!(scan["total_allocated_size"], persisted.get::<i64, _>("allocated"));
+        assert_eq!(scan["dir_count"], dirs);
+
+        // Partial scans stay explorable
+        assert!(get_tree(State(state.clone()), ns.clone(), Path(id), Query(TreeQuery::default()), ResponseFormat::Json).await.is_ok());
+        let top = json_body(
+            get_top(State(state.clone()), ns.clone(), Path(id), Query(TopQuery { scope: Some("files".into()), ..Default::default() }), ResponseFormat::Json)
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert!(!top.as_array().unwrap().is_empty());
+        assert!(get_list(State(state.clone()), ns, Path(id), Query(ListQuery::default()), ResponseFormat::Json).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn event_stream_is_padded_and_sends_heartbeats() {
+        use http_body_util::BodyExt;
+        let (_dir, state, hr_id, _) = namespaced_fixture().await;
+        let mut config = crate::config::AppConfig::default();
+        config.sse.heartbeat_secs = 1;
+        config.sse.keep_alive_secs = 30;
+        config.sse.padding_bytes = 2048;
+        state.config.replace(config);
+        let (tx, _) = broadcast::channel(16);
+        state.jobs.write().await.insert(hr_id, JobHandle::new(CancellationToken::new(), tx.clone()));
+
+        let hr = Namespace::parse("hr").unwrap();
+        let res = open_events(&state, hr, hr_id, all_events(), HeaderMap::new()).await.unwrap();
+        let res = res.into_response();
+        assert_eq!(res.headers()["x-accel-buffering"], "no");
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "no-cache, no-transform");
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+        // Read the raw stream frame by frame and note when each heartbeat arrives
+        let mut body = res.into_body();
+        let mut raw = String::new();
+        let mut heartbeats = Vec::new();
+        let started = std::time::Instant::now();
+        while heartbeats.len() < 2 {
+            let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .expect("heartbeat must arrive")
+                .unwrap()
+                .unwrap();
+            let chunk = String::from_utf8_lossy(frame.data_ref().unwrap()).to_string();
+            if raw.is_empty() {
+                // Padding first, before any event
+                assert!(chunk.starts_with(':'), "unexpected first chunk: {:?}", chunk);
+                assert!(chunk.len() >= 2048);
+                tx.send(ScanEvent::Cancelled).unwrap();
+            }
+            if chunk.contains("event: heartbeat") {
+                assert!(chunk.contains(r#""type":"heartbeat""#), "unexpected heartbeat: {:?}", chunk);
+                heartbeats.push(started.elapsed());
+            }
+            raw.push_str(&chunk);
+        }
+        assert!(raw.contains(r#"data: {"type":"cancelled"}"#), "unexpected stream: {}", raw);
+        assert!(heartbeats[0] >= Duration::from_millis(800), "first heartbeat too early: {:?}", heartbeats);
+        let gap = heartbeats[1] - heartbeats[0];
+        assert!(gap >= Duration::from_millis(800) && gap < Duration::from_millis(2500), "cadence: {:?}", gap);
+    }
+
+    #[tokio::test]
+    async fn event_filters_apply_per_connection() {
+        use http_body_util::BodyExt;
+        let (_dir, state, hr_id, _) = namespaced_fixture().await;
+        let mut config = crate::config::AppConfig::default();
+        config.sse.heartbeat_secs = 0;
+        config.sse.padding_bytes = 0;
+        state.config.replace(config);
+        let (tx, _) = broadcast::channel(64);
+        state.jobs.write().await.insert(hr_id, JobHandle::new(CancellationToken::new(), tx.clone()));
+        let hr = Namespace::parse("hr").unwrap();
+        let open = |types: &str, progress_interval: Option<&str>| {
+            let q = EventsQuery {
+                types: Some(types.into()),
+                progress_interval: progress_interval.map(Into::into),
+            };
+            open_events(&state, hr.clone(), hr_id, Query(q), HeaderMap::new())
+        };
+        let progress = open("progress,done", Some("1h")).await.unwrap().into_response();
+        let warnings = open("warning, DONE", None).await.unwrap().into_response();
+
+        let progress_event = |files: u64| ScanEvent::Progress {
+            current_path: String::new(),
+            dirs_scanned: 1,
+            files_scanned: files,
+            logical_size: 0,
+            allocated_size: 0,
+            estimated_percent: None,
+        };
+        for files in 1..=3 {
+            tx.send(progress_event(files)).unwrap();
+        }
+        let warning = ScanEvent::Warning { path: "/x".into(), code: "io".into(), message: "denied".into() };
+        tx.send(warning).unwrap();
+        tx.send(ScanEvent::Cancelled).unwrap();
+        tx.send(ScanEvent::Done {
+            total_dirs: 1,
+            total_files: 3,
+            total_logical_size: 0,
+            total_allocated_size: 0,
+            roots: Vec::new(),
+            duration_ms: 0,
+            dirs_per_sec: 0.0,
+            files_per_sec: 0.0,
+            bytes_per_sec: 0.0,
+            buffer_peak_bytes: 0,
+        })
+        .unwrap();
+
+        // Both connections read concurrently until their terminal event
+        let types_of = |res: Response| async move {
+            let mut body = res.into_body();
+            let mut types = Vec::new();
+            while types.last().map(String::as_str) != Some("done") {
+                let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                    .await
+                    .expect("done must arrive")
+                    .unwrap()
+                    .unwrap();
+                let chunk = String::from_utf8_lossy(frame.data_ref().unwrap()).to_string();
+                for data in chunk.lines().filter_map(|l| l.strip_prefix("data: ")) {
+                    let ev: serde_json::Value = serde_json::from_str(data).unwrap();
+                    types.push(ev["type"].as_str().unwrap().to_string());
+                }
+            }
+            types
+        };
+        let (progress, warnings) = tokio::join!(types_of(progress), types_of(warnings));
+        // The throttle lets only the first progress event of the hour through
+        assert_eq!(progress, ["progress", "done"]);
+        assert_eq!(warnings, ["warning", "done"]);
+
+        for (types, interval) in [("progress,bogus", None), ("", None), ("done", Some("5 parsecs"))] {
+            match open(types, interval).await {
+                // The message lists the valid names
+                Err(AppError::BadRequest(msg)) => {
+                    assert!(types != "progress,bogus" || msg.contains("hash_progress"), "{}", msg)
+                }
+                other => panic!("expected 400 for {:?}/{:?}, got {:?}", types, interval, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn event_streams_are_limited_per_client_and_released_on_disconnect() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (_dir, state, hr_id, fin_id) = namespaced_fixture().await;
+        let mut config = crate::config::AppConfig::default();
+        config.sse.heartbeat_secs = 0;
+        config.sse.padding_bytes = 0;
+        config.sse.keep_alive_secs = 1;
+        config.sse.max_connections_per_client = 2;
+        state.config.replace(config);
+        for id in [hr_id, fin_id] {
+            let (tx, _) = broadcast::channel(16);
+            state.jobs.write().await.insert(id, JobHandle::new(CancellationToken::new(), tx));
+        }
+
+        let app = axum::Router::new()
+            .route("/scans/{id}/events", axum::routing::get(scan_events))
+            .route("/metrics", axum::routing::get(crate::routes::health::metrics))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+            axum::serve(listener, app).await.unwrap()
+        });
+
+        // Opens a stream and returns the connection with the status code of the response
+        let open = |id: Uuid, ns: &'static str, real_ip: Option<&'static str>| async move {
+            let mut conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let forwarded = real_ip.map(|ip| format!("x-real-ip: {}\r\n", ip)).unwrap_or_default();
+            let req = format!(
+                "GET /scans/{}/events HTTP/1.1\r\nhost: test\r\nx-speicherwald-namespace: {}\r\n{}\r\n",
+                id, ns, forwarded
+            );
+            conn.write_all(req.as_bytes()).await.unwrap();
+            let mut head = [0u8; 12];
+            conn.read_exact(&mut head).await.unwrap();
+            (conn, String::from_utf8_lossy(&head[9..12]).to_string())
+        };
+        let local: std::net::IpAddr = "127.0.0.1".parse().unwrap();
+
+        let (first, status) = open(hr_id, "hr", None).await;
+        assert_eq!(status, "200");
+        let (_second, status) = open(fin_id, "finance", None).await;
+        assert_eq!(status, "200");
+        let (_, status) = open(hr_id, "hr", None).await;
+        assert_eq!(status, "429");
+        // Another client behind the same proxy has its own limit
+        let (_other, status) = open(hr_id, "hr", Some("10.0.0.7")).await;
+        assert_eq!(status, "200");
+        assert_eq!(state.sse_connections.of_client(local), 2);
+        assert_eq!(state.sse_connections.per_scan(), {
+            let mut expected = vec![(hr_id, 2), (fin_id, 1)];
+            expected.sort();
+            expected
+        });
+
+        let metrics = json_body(crate::routes::health::metrics(State(state.clone())).await).await;
+        assert_eq!(metrics["sse_connections"], 3);
+        assert_eq!(metrics["sse_connections_per_scan"][hr_id.to_string()], 2);
+
+        // The slot is given back once the server notices the closed connection
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while state.sse_connections.of_client(local) > 1 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("dropped stream must be released");
+        assert_eq!(state.sse_connections.per_scan().iter().find(|(id, _)| *id == hr_id), Some(&(hr_id, 1)));
+        let (_third, status) = open(hr_id, "hr", None).await;
+        assert_eq!(status, "200");
+    }
+
+    #[test]
+    fn event_intervals_parse_units() {
+        assert_eq!(parse_interval("5s"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_interval("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_interval("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_interval("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_interval("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_interval("1.5s"), None);
+        assert_eq!(parse_interval("s"), None);
+    }
+
+    fn all_events() -> Query<EventsQuery> {
+        Query(EventsQuery::default())
+    }
+
+    /// Opens an event stream for a client without connection info.
+    async fn open_events(
+        state: &AppState,
+        ns: Namespace,
+        id: Uuid,
+        q: Query<EventsQuery>,
+        headers: HeaderMap,
+    ) -> AppResult<impl IntoResponse> {
+        scan_events(State(state.clone()), ns, MaybeRemoteAddr::default(), Path(id), q, headers).await
+    }
+
+    async fn event_text(
+        state: &AppState,
+        ns: &Namespace,
+        id: Uuid,
+        last_event_id: Option<&str>,
+    ) -> AppResult<String> {
+        use http_body_util::BodyExt;
+        let mut headers = HeaderMap::new();
+        if let Some(last) = last_event_id {
+            headers.insert("last-event-id", last.parse().unwrap());
+        }
+        let res = open_events(state, ns.clone(), id, all_events(), headers).await?;
+        let res = res.into_response();
+        let bytes = tokio::time::timeout(Duration::from_secs(5), res.into_body().collect())
+            .await
+            .expect("stream of a finished scan must end")
+            .unwrap()
+            .to_bytes();
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    #[tokio::test]
+    async fn finished_scans_replay_their_events_for_the_grace_period() {
+        let (dir, state, _, _) = namespaced_fixture().await;
+        let mut config = crate::config::AppConfig::default();
+        config.sse.heartbeat_secs = 0;
+        config.sse.padding_bytes = 0;
+        config.sse.replay_grace_secs = 1;
+        state.config.replace(config);
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.bin"), b"abc").unwrap();
+        std::fs::write(root.join("sub").join("b.bin"), b"de").unwrap();
+        let req = CreateScanRequest {
+            root_paths: vec![root.to_string_lossy().to_string()],
+            follow_symlinks: None,
+            include_hidden: None,
+            measure_logical: None,
+            measure_allocated: Some(false),
+            excludes: None,
+            includes: None,
+            max_depth: None,
+            concurrency: None,
+            measure_hardlinks: None,
+            measure_ads: None,
+            io_priority: None,
+            capture_owner: None,
+            access_mode: None,
+            hash_min_size: None,
+            read_only: None,
+            no_system_excludes: None,
+            rescan_of: None,
+        };
+        let ns = Namespace::parse("default").unwrap();
+        let id = start_scan(&state, req, &ns).await.unwrap().id;
+        for _ in 0..2000 {
+            if !state.jobs.read().await.contains_key(&id) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(!state.jobs.read().await.contains_key(&id));
+
+        // A reloaded page gets the whole log, numbered from 1
+        let full = event_text(&state, &ns, id, None).await.unwrap();
+        assert!(full.contains(r#""type":"started""#), "unexpected stream: {}", full);
+        assert!(full.contains(r#""type":"done""#), "unexpected stream: {}", full);
+        assert!(full.contains("\nid: 1\n"), "unexpected stream: {}", full);
+        let ids: Vec<u64> =
+            full.lines().filter_map(|l| l.strip_prefix("id: ")).map(|v| v.parse().unwrap()).collect();
+        assert_eq!(ids, (1..=ids.len() as u64).collect::<Vec<_>>());
+
+        // A reconnecting client only gets what it has not seen yet
+        let resumed = event_text(&state, &ns, id, Some("1")).await.unwrap();
+        assert!(!resumed.contains("\nid: 1\n"), "unexpected stream: {}", resumed);
+        assert_eq!(resumed.matches("\nid: ").count(), ids.len() - 1);
+        let last = ids.last().unwrap().to_string();
+        assert!(!event_text(&state, &ns, id, Some(&last)).await.unwrap().contains("data:"));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(is_not_found(event_text(&state, &ns, id, None).await));
+    }
+
+    #[tokio::test]
+    async fn finalize_cannot_be_combined_with_purge() {
+        let (_dir, state, hr_id, _) = namespaced_fixture().await;
+        let hr = Namespace::parse("hr").unwrap();
+        let q = CancelQuery { purge: Some(true), finalize: Some(true) };
+        let res = cancel_scan(State(state.clone()), hr, Path(hr_id), Query(q)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn interrupted_scans_are_resumable_and_replay_their_end() {
+        use http_body_util::BodyExt;
+        let (_dir, state, hr_id, fin_id) = namespaced_fixture().await;
+        sqlx::query("UPDATE scans SET status='running' WHERE id=?1")
+            .bind(hr_id.to_string())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        let marked = crate::db::mark_interrupted_scans(&state.db).await.unwrap();
+        assert_eq!(marked, vec![hr_id.to_string()]);
+
+        let hr = Namespace::parse("hr").unwrap();
+        let body = json_body(get_scan(State(state.clone()), hr.clone(), Path(hr_id)).await.unwrap()).await;
+        assert_eq!(body["status"], "interrupted");
+        assert_eq!(body["resumable"], true);
+
+        let sse = open_events(&state, hr, hr_id, all_events(), HeaderMap::new()).await.unwrap();
+        let sse = sse.into_response();
+        let bytes = tokio::time::timeout(Duration::from_secs(5), sse.into_body().collect())
+            .await
+            .expect("replay stream must end")
+            .unwrap()
+            .to_bytes();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains(r#"{"type":"interrupted","resumable":true}"#), "unexpected stream: {}", text);
+
+        // Finished scans without a job still have no event stream
+        let finance = Namespace::parse("finance").unwrap();
+        let finance_events = || {
+            open_events(&state, finance.clone(), fin_id, all_events(), HeaderMap::new())
+        };
+        let res = finance_events().await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+        let body = json_body(get_scan(State(state.clone()), finance, Path(fin_id)).await.unwrap()).await;
+        assert_eq!(body["resumable"], false);
+    }
+
+    #[test]
+    fn normalize_patterns_trims_and_rejects_invalid_globs() {
+        let pats =
+            normalize_patterns(vec![" **\\*.vhdx ".into(), "".into(), "*.iso".into()], "include").unwrap();
+        assert_eq!(pats, vec!["**/*.vhdx", "*.iso"]);
+        match normalize_patterns(vec!["[".into()], "include") {
+            Err(AppError::InvalidInput(msg)) => assert!(msg.starts_with("Invalid include pattern"), "{}", msg),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn system_excludes_are_merged_for_system_drives() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let mut config = crate::config::AppConfig::default();
+        config.scan_defaults.excludes = vec!["**/node_modules".into()];
+        let system = vec!["pagefile.sys".to_string(), "\\proc".to_string()];
+        config.system_excludes = SystemExcludesConfig {
+            windows: system.clone(),
+            unix: system,
+            drives: vec!["/srv/sys".into()],
+        };
+        let state = AppState::new(pool, config);
+        let excludes = |root: &str, excludes: Option<&[&str]>, opt_out: Option<bool>| {
+            let req = CreateScanRequest {
+                root_paths: vec![root.to_string()],
+                excludes: excludes.map(|e| e.iter().map(|p| p.to_string()).collect()),
+                no_system_excludes: opt_out,
+                ..Default::default()
+            };
+            apply_scan_defaults(&state, &req).unwrap().excludes
+        };
+
+        // User patterns come first and stay, duplicates of them are not added twice
+        let merged = excludes("/srv/sys/", Some(&["*.iso", "pagefile.sys"]), None);
+        assert_eq!(merged, ["*.iso", "pagefile.sys", "/proc"]);
+        assert_eq!(excludes("/srv/sys", None, Some(false)), ["**/node_modules", "pagefile.sys", "/proc"]);
+        assert_eq!(excludes("/srv/sys", Some(&[]), None), ["pagefile.sys", "/proc"]);
+        // Opting out and roots that are no system drive keep the patterns as requested
+        assert_eq!(excludes("/srv/sys", Some(&["*.iso"]), Some(true)), ["*.iso"]);
+        assert_eq!(excludes("/srv/sys/data", Some(&["*.iso"]), None), ["*.iso"]);
+        assert_eq!(excludes("/srv/other", None, None), ["**/node_modules"]);
+    }
+
+    fn roots(paths: &[&str]) -> AppResult<(Vec<String>, Vec<String>)> {
+        normalize_root_paths(&paths.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn root_paths_reject_duplicates_and_drop_nested_roots() {
+        // Duplicates after normalization, case-insensitive for Windows paths
+        for dup in [&["D:\\Data", "d:/data/"][..], &["/srv/data", "/srv/data/"], &["\\\\nas\\share\\x", "\\\\NAS\\Share\\X\\"]] {
+            assert!(matches!(roots(dup), Err(AppError::BadRequest(_))), "{:?}", dup);
+        }
+        assert_eq!(roots(&["/srv/Data", "/srv/data"]).unwrap().0, ["/srv/Data", "/srv/data"]);
+
+        let (kept, warnings) = roots(&["D:\\Data\\Projects", "d:\\data\\", "D:\\Database"]).unwrap();
+        assert_eq!(kept, ["D:\\data", "D:\\Database"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("D:\\Data\\Projects"), "{}", warnings[0]);
+
+        // A volume root contains everything on it, but not other volumes
+        let (kept, warnings) = roots(&["C:\\", "c:\\Users", "d:\\x"]).unwrap();
+        assert_eq!(kept, ["C:\\", "D:\\x"]);
+        assert_eq!(warnings.len(), 1);
+        let (kept, _) = roots(&["/srv", "/srv/data/a", "/srv-old", "/srv/data/a/b"]).unwrap();
+        assert_eq!(kept, ["/srv", "/srv-old"]);
+    }
+
+    async fn list_paths(state: &AppState, id: Uuid, q: ListQuery) -> AppResult<Vec<String>> {
+        let res = get_list(State(state.clone()), Namespace::default(), Path(id), Query(q), ResponseFormat::Json).await?;
+        let body = json_body(res).await;
+        Ok(body.as_array().unwrap().iter().map(|i| i["path"].as_str().unwrap().to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn list_filters_apply_before_pagination() {
+        let (_dir, pool, id) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let data = || ListQuery {
+            path: Some("/data".into()),
+            sort: Some("allocated".into()),
+            order: Some("desc".into()),
+            ..Default::default()
+        };
+
+        let q = ListQuery { min_size: Some(150), limit: Some(2), offset: Some(1), ..data() };
+        assert_eq!(list_paths(&state, id, q).await.unwrap(), vec!["/data/proj", "/data/pröj"]);
+        let q = ListQuery { min_size: Some(100), max_size: Some(150), ..data() };
+        assert_eq!(list_paths(&state, id, q).await.unwrap(), vec!["/data/pröj", "/data/prognosis"]);
+        // Items without an mtime never match a time filter
+        let q = ListQuery { modified_after: Some(0), ..data() };
+        assert!(list_paths(&state, id, q).await.unwrap().is_empty());
+
+        let proj = || ListQuery { path: Some("/data/proj".into()), ..Default::default() };
+        let q = ListQuery { kind: Some("files".into()), ..proj() };
+        assert_eq!(list_paths(&state, id, q).await.unwrap(), vec!["/data/proj/readme.txt"]);
+        let q = ListQuery { kind: Some("dirs".into()), ..proj() };
+        assert_eq!(list_paths(&state, id, q).await.unwrap(), vec!["/data/proj/a"]);
+
+        for q in [
+            ListQuery { min_size: Some(10), max_size: Some(5), ..data() },
+            ListQuery { min_size: Some(-1), ..data() },
+            ListQuery { modified_after: Some(10), modified_before: Some(5), ..data() },
+            ListQuery { kind: Some("links".into()), ..data() },
+        ] {
+            assert!(matches!(list_paths(&state, id, q).await, Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn list_sorts_names_naturally_across_pages() {
+        let (_dir, pool, id) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let natural = NodeRow {
+            path: "/data/natural",
+            parent: Some("/data"),
+            depth: 1,
+            files: 4,
+            ..Default::default()
+        };
+        insert_node(&state.db, id, natural).await;
+        for name in ["b1", "a10", "a1b", "a2"] {
+            let path = format!("/data/natural/{}", name);
+            let file = FileRow { path: &path, parent: Some("/data/natural"), size: 1, ..Default::default() };
+            insert_file(&state.db, id, file).await;
+        }
+        let natural = |offset: i64, order: Option<&str>| ListQuery {
+            path: Some("/data/natural".into()),
+            sort: Some("name_natural".into()),
+            order: order.map(Into::into),
+            limit: Some(2),
+            offset: Some(offset),
+            ..Default::default()
+        };
+        let names = |paths: Vec<String>| -> Vec<String> {
+            paths.iter().map(|p| p.trim_start_matches("/data/natural/").to_string()).collect()
+        };
+
+        let first = names(list_paths(&state, id, natural(0, None)).await.unwrap());
+        let second = names(list_paths(&state, id, natural(2, None)).await.unwrap());
+        assert_eq!([first, second].concat(), ["a1b", "a2", "a10", "b1"]);
+        let desc = names(list_paths(&state, id, natural(0, Some("desc"))).await.unwrap());
+        assert_eq!(desc, ["b1", "a10"]);
+        // Plain name order stays lexicographic
+        let q = ListQuery { sort: Some("name".into()), limit: Some(4), ..natural(0, None) };
+        assert_eq!(names(list_paths(&state, id, q).await.unwrap()), ["a10", "a1b", "a2", "b1"]);
+    }
+
+    #[test]
+    fn natural_order_compares_digit_runs_by_value() {
+        use std::cmp::Ordering;
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("File2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.9", "v1.10"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("x007", "x7"), Ordering::Equal);
+        assert_eq!(natural_cmp("n99999999999999999999999", "n100000000000000000000000"), Ordering::Less);
+    }
+
+    #[tokio::test]
+    async fn list_totals_cover_all_pages() {
+        let (_dir, pool, id) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let list = |q: ListQuery| get_list(State(state.clone()), Namespace::default(), Path(id), Query(q), ResponseFormat::Json);
+        let data = || ListQuery { path: Some("/data".into()), limit: Some(2), ..Default::default() };
+
+        // The plain array stays the default
+        assert!(json_body(list(data()).await.unwrap()).await.is_array());
+
+        let body = json_body(list(ListQuery { with_totals: Some(true), ..data() }).await.unwrap()).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        assert_eq!(body["total_count"], 5);
+        let node: (i64, i64) = sqlx::query_as(
+            "SELECT allocated_size, logical_size FROM nodes WHERE scan_id=?1 AND path='/data'",
+        )
+        .bind(id.to_string())
+        .fetch_one(state.read_pool())
+        .await
+        .unwrap();
+        let sizes = |body: &serde_json::Value| {
+            (body["total_allocated_size"].as_i64().unwrap(), body["total_logical_size"].as_i64().unwrap())
+        };
+        assert_eq!(sizes(&body), node);
+
+        // Totals follow the filters, files included
+        let q = ListQuery { min_size: Some(150), with_totals: Some(true), ..data() };
+        let body = json_body(list(q).await.unwrap()).await;
+        assert_eq!(body["total_count"], 3);
+        assert_eq!(sizes(&body), (850, 850));
+        let q = ListQuery { path: Some("/data/proj".into()), with_totals: Some(true), ..Default::default() };
+        let body = json_body(list(q).await.unwrap()).await;
+        assert_eq!(body["total_count"], 2);
+        assert_eq!(sizes(&body), (300, 300));
+
+        // Scan roots
+        let q = ListQuery { with_totals: Some(true), ..Default::default() };
+        let body = json_body(list(q).await.unwrap()).await;
+        assert_eq!(body["total_count"], body["items"].as_array().unwrap().len());
+    }
+
+    /// Decodes a response in both formats into `T` and compares what the DTOs hold.
+    async fn same_in_both_formats<T, F, Fut>(call: F)
+    where
+        T: serde::de::DeserializeOwned + serde::Serialize,
+        F: Fn(ResponseFormat) -> Fut,
+        Fut: std::future::Future<Output = AppResult<Response>>,
+    {
+        use http_body_util::BodyExt;
+        let json = call(ResponseFormat::Json).await.unwrap().into_body().collect().await.unwrap().to_bytes();
+        let json: T = serde_json::from_slice(&json).unwrap();
+        let res = call(ResponseFormat::MsgPack).await.unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/msgpack");
+        assert_eq!(res.headers()[header::VARY], "accept");
+        let packed = res.into_body().collect().await.unwrap().to_bytes();
+        let packed: T = rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(serde_json::to_value(&packed).unwrap(), serde_json::to_value(&json).unwrap());
+    }
+
+    #[tokio::test]
+    async fn msgpack_responses_decode_to_the_json_dtos() {
+        let (_dir, pool, id) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let ns = Namespace::default;
+        same_in_both_formats::<Vec<NodeDto>, _, _>(|f| {
+            get_tree(State(state.clone()), ns(), Path(id), Query(TreeQuery::default()), f)
+        })
+        .await;
+        same_in_both_formats::<Vec<TopItem>, _, _>(|f| {
+            let q = TopQuery { scope: Some("files".into()), ..Default::default() };
+            get_top(State(state.clone()), ns(), Path(id), Query(q), f)
+        })
+        .await;
+        same_in_both_formats::<Vec<ListItem>, _, _>(|f| {
+            let q = ListQuery { path: Some("/data".into()), ..Default::default() };
+            get_list(State(state.clone()), ns(), Path(id), Query(q), f)
+        })
+        .await;
+        same_in_both_formats::<ListResponse, _, _>(|f| {
+            let q = ListQuery { path: Some("/data".into()), with_totals: Some(true), ..Default::default() };
+            get_list(State(state.clone()), ns(), Path(id), Query(q), f)
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn tree_etag_short_circuits_settled_scans() {
+        use axum::{body::Body, http::Request as HttpRequest, middleware::from_fn_with_state, routing::get};
+        use tower::ServiceExt;
+
+        let (_dir, pool, id) = fixture().await;
+        let state = AppState::new(pool.clone(), crate::config::AppConfig::default());
+        let app = axum::Router::new()
+            .route("/scans/{id}/tree", get(get_tree).layer(from_fn_with_state(state.clone(), scan_etag)))
+            .with_state(state);
+        let request = |query: &str, etag: Option<&str>| {
+            let mut builder = HttpRequest::builder().uri(format!("/scans/{}/tree?{}", id, query));
+            if let Some(etag) = etag {
+                builder = builder.header(header::IF_NONE_MATCH, etag);
+            }
+            app.clone().oneshot(builder.body(Body::empty()).unwrap())
+        };
+
+        let res = request("limit=5", None).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+
+        let res = request("limit=5", Some(&etag)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[header::ETAG], etag.as_str());
+
+        // Another query string gets another tag and a full response
+        let res = request("limit=6", Some(&etag)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_ne!(res.headers()[header::ETAG], etag.as_str());
+
+        // Running scans are always answered in full
+        sqlx::query("UPDATE scans SET status='running' WHERE id=?1")
+            .bind(id.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let running_etag =
+            request("limit=5", None).await.unwrap().headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_ne!(running_etag, etag);
+        assert_eq!(request("limit=5", Some(&running_etag)).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn paused_scans_stop_and_resume_to_completion() {
+        let (dir, pool) = test_db().await;
+        let mut config = crate::config::AppConfig::default();
+        config.scanner.flush_interval_ms = 20;
+        let state = AppState::new(pool, config);
+
+        let root = dir.path().join("data");
+        for d in 0..60 {
+            let sub = root.join(format!("dir{:02}", d));
+            std::fs::create_dir_all(&sub).unwrap();
+            for f in 0..100 {
+                std::fs::write(sub.join(format!("f{:03}.bin", f)), b"x").unwrap();
+            }
+        }
+        let req = CreateScanRequest {
+            root_paths: vec![root.to_string_lossy().to_string()],
+            follow_symlinks: None,
+            include_hidden: None,
+            measure_logical: None,
+            measure_allocated: None,
+            excludes: None,
+            includes: None,
+            max_depth: None,
+            concurrency: Some(1),
+            measure_hardlinks: None,
+            measure_ads: None,
+            io_priority: None,
+            capture_owner: None,
+            access_mode: None,
+            hash_min_size: None,
+            read_only: None,
+            no_system_excludes: None,
+            rescan_of: None,
+        };
+        let ns = Namespace::parse("default").unwrap();
+        let id = start_scan(&state, req, &ns).await.unwrap().id;
+        let mut rx = state.jobs.read().await[&id].sender.subscribe();
+
+        let res = pause_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap().into_response();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(pause_scan(State(state.clone()), ns.clone(), Path(id)).await.is_err());
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap() {
+                ScanEvent::Paused => break,
+                ScanEvent::Done { .. } => panic!("scan finished before it was paused"),
+                _ => {}
+            }
+        }
+        let scan = json_body(get_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap()).await;
+        assert_eq!(scan["status"], "paused");
+
+        // Once the workers wait and the aggregator has flushed, nothing new is written
+        let files_persisted = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+                .bind(id.to_string())
+                .fetch_one(&state.db)
+                .await
+                .unwrap()
+        };
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let while_paused = files_persisted().await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(files_persisted().await, while_paused);
+        assert!(while_paused < 6000);
+
+        let res = resume_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap().into_response();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(resume_scan(State(state.clone()), ns.clone(), Path(id)).await.is_err());
+        let finished = state.jobs.read().await.get(&id).map(|job| job.finished.clone());
+        if let Some(finished) = finished {
+            tokio::time::timeout(Duration::from_secs(30), finished.cancelled()).await.unwrap();
+        }
+        let scan = json_body(get_scan(State(state.clone()), ns, Path(id)).await.unwrap()).await;
+        assert_eq!(scan["status"], "done");
+        assert_eq!(scan["file_count"], 6000);
+        // The pause counts towards the duration, which the throughput is based on
+        let duration_ms = scan["duration_ms"].as_i64().unwrap();
+        assert!(duration_ms >= 600, "{}", duration_ms);
+        let files_per_sec = scan["files_per_sec"].as_f64().unwrap();
+        assert!((files_per_sec - 6000.0 * 1000.0 / duration_ms as f64).abs() < 1e-6);
+        assert_eq!(state.metrics.get_snapshot().scans_completed, 1);
+    }
+
+    #[tokio::test]
+    async fn resumed_scans_skip_stored_subtrees_and_match_a_full_scan() {
+        let (dir, pool) = test_db().await;
+        let mut config = crate::config::AppConfig::default();
+        config.scanner.batch_size = 100;
+        config.scanner.flush_threshold = 100;
+        config.scanner.flush_interval_ms = 20;
+        let state = AppState::new(pool, config);
+
+        let root = dir.path().join("data");
+        for d in 0..100 {
+            let sub = root.join(format!("dir{:03}", d)).join("nested");
+            std::fs::create_dir_all(&sub).unwrap();
+            for f in 0..200 {
+                std::fs::write(sub.join(format!("f{:03}.bin", f)), vec![0u8; 1 + f % 5]).unwrap();
+            }
+        }
+        std::fs::write(root.join("top.bin"), b"top").unwrap();
+        let req = CreateScanRequest {
+            root_paths: vec![root.to_string_lossy().to_string()],
+            follow_symlinks: None,
+            include_hidden: None,
+            measure_logical: None,
+            measure_allocated: None,
+            excludes: None,
+            includes: None,
+            max_depth: None,
+            concurrency: Some(1),
+            measure_hardlinks: None,
+            measure_ads: None,
+            io_priority: None,
+            capture_owner: None,
+            access_mode: None,
+            hash_min_size: None,
+            read_only: None,
+            no_system_excludes: None,
+            rescan_of: None,
+        };
+        let ns = Namespace::parse("default").unwrap();
+        let wait_done = |id: Uuid| {
+            let state = state.clone();
+            async move {
+                let finished = state.jobs.read().await.get(&id).map(|job| job.finished.clone());
+                if let Some(finished) = finished {
+                    tokio::time::timeout(Duration::from_secs(30), finished.cancelled()).await.unwrap();
+                }
+            }
+        };
+        let full_id = start_scan(&state, req.clone(), &ns).await.unwrap().id;
+        wait_done(full_id).await;
+        let full = json_body(get_scan(State(state.clone()), ns.clone(), Path(full_id)).await.unwrap()).await;
+        assert_eq!(full["status"], "done");
+
+        // Cut the scan off once the first directories are stored
+        let id = start_scan(&state, req, &ns).await.unwrap().id;
+        let dirs_persisted = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM nodes WHERE scan_id=?1 AND path LIKE '%nested'")
+                .bind(id.to_string())
+                .fetch_one(&state.db)
+                .await
+                .unwrap()
+        };
+        for _ in 0..2000 {
+            if dirs_persisted().await > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        let q = CancelQuery { purge: None, finalize: Some(true) };
+        cancel_scan(State(state.clone()), ns.clone(), Path(id), Query(q)).await.unwrap();
+        let partial = json_body(get_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap()).await;
+        assert_eq!(partial["status"], "partial");
+        assert_eq!(partial["resumable"], true);
+        assert!(partial["file_count"].as_i64().unwrap() < full["file_count"].as_i64().unwrap());
+
+        // Stored directories are taken over, so a marker on one of them survives the resume
+        let kept: String = sqlx::query_scalar(
+            "SELECT path FROM nodes WHERE scan_id=?1 AND path LIKE '%nested' ORDER BY path LIMIT 1",
+        )
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE nodes SET fingerprint=42 WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(&kept)
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        let res = resume_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let body = json_body(res).await;
+        assert_eq!(body["status"], "running");
+        wait_done(id).await;
+
+        let resumed = json_body(get_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap()).await;
+        assert_eq!(resumed["status"], "done");
+        assert_eq!(resumed["resumable"], false);
+        for key in ["file_count", "dir_count", "total_logical_size", "total_allocated_size"] {
+            assert_eq!(resumed[key], full[key], "{}", key);
+        }
+        assert_eq!(resumed["roots"][0]["file_count"], full["roots"][0]["file_count"]);
+        assert_eq!(resumed["roots"][0]["dir_count"], full["roots"][0]["dir_count"]);
+        let root_size = |id: Uuid| {
+            let state = state.clone();
+            let root = root.to_string_lossy().to_string();
+            async move {
+                sqlx::query_scalar::<_, i64>("SELECT allocated_size FROM nodes WHERE scan_id=?1 AND path=?2")
+                    .bind(id.to_string())
+                    .bind(root)
+                    .fetch_one(&state.db)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(root_size(id).await, root_size(full_id).await);
+        let marker: Option<i64> = sqlx::query_scalar("SELECT fingerprint FROM nodes WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(&kept)
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(marker, Some(42));
+
+        // Finished scans cannot be resumed
+        let res = resume_scan(State(state.clone()), ns, Path(id)).await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn io_priority_round_trips_and_scans_still_complete() {
+        use crate::types::IoPriority;
+
+        let (dir, pool) = test_db().await;
+        let mut config = crate::config::AppConfig::default();
+        config.scan_defaults.io_priority = IoPriority::Low;
+        let state = AppState::new(pool, config);
+
+        let root = dir.path().join("data");
+        for d in 0..4 {
+            let sub = root.join(format!("dir{d}"));
+            std::fs::create_dir_all(&sub).unwrap();
+            for f in 0..25 {
+                std::fs::write(sub.join(format!("f{f:02}.bin")), b"abc").unwrap();
+            }
+        }
+        let root = root.to_string_lossy().to_string();
+        assert!(serde_json::from_value::<CreateScanRequest>(
+            serde_json::json!({"root_paths": [root], "io_priority": "idle"})
+        )
+        .is_err());
+
+        let ns = Namespace::parse("default").unwrap();
+        for (body, expected) in [
+            (serde_json::json!({"root_paths": [root], "io_priority": "background"}), "background"),
+            (serde_json::json!({"root_paths": [root], "concurrency": 2}), "low"),
+        ] {
+            let req: CreateScanRequest = serde_json::from_value(body).unwrap();
+            let id = start_scan(&state, req, &ns).await.unwrap().id;
+            let finished = state.jobs.read().await.get(&id).map(|job| job.finished.clone());
+            if let Some(finished) = finished {
+                tokio::time::timeout(Duration::from_secs(30), finished.cancelled()).await.unwrap();
+            }
+            let options: String = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+                .bind(id.to_string())
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+            let options: ScanOptions = serde_json::from_str(&options).unwrap();
+            assert_eq!(serde_json::to_value(options.io_priority).unwrap(), expected);
+
+            let scan = json_body(get_scan(State(state.clone()), ns.clone(), Path(id)).await.unwrap()).await;
+            assert_eq!(scan["status"], "done");
+            assert_eq!(scan["file_count"], 100);
+            assert_eq!(scan["total_logical_size"], 300);
+        }
+    }
+
+    #[test]
+    fn windows_query_paths_keep_their_prefix() {
+        let cases = [
+            (r"C:\Users\me", r"C:\Users\me"),
+            ("C:/Users/me/", r"C:\Users\me"),
+            (r"C:\Users/me\.\docs", r"C:\Users\me\docs"),
+            ("C:", r"C:\"),
+            ("C:/", r"C:\"),
+            (r"\\?\C:\Very\Long\Path", r"\\?\C:\Very\Long\Path"),
+            (r"\\?\C:\", r"\\?\C:\"),
+            (r"\\server\share\dir\", r"\\server\share\dir"),
+            (r"\\server/share/dir", r"\\server\share\dir"),
+            (r"\\server\share", r"\\server\share\"),
+            (r"\\?\UNC\server\share\dir", r"\\?\UNC\server\share\dir"),
+            (r"//?/unc/server\share/dir/", r"\\?\UNC\server\share\dir"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_query_path(input).unwrap(), expected, "{}", input);
+        }
+        assert!(normalize_query_path(r"\\server\share\..\other").is_err());
+        assert!(normalize_query_path(r"\\?\C:\a\..\b").is_err());
+        // Without a backslash or drive letter a Unix path stays a Unix path
+        #[cfg(not(windows))]
+        assert_eq!(normalize_query_path("//data/x/").unwrap(), "/data/x");
+    }
+
+    #[test]
+    fn query_path_variants_cover_both_spellings() {
+        assert_eq!(
+            query_path_variants(r"\\server\share\dir"),
+            vec![r"\\server\share\dir".to_string(), r"\\?\UNC\server\share\dir".to_string()]
+        );
+        assert_eq!(
+            query_path_variants(r"\\?\UNC\server\share\"),
+            vec![
+                r"\\?\UNC\server\share\".to_string(),
+                r"\\?\UNC\server\share".to_string(),
+                r"\\server\share\".to_string(),
+                r"\\server\share".to_string(),
+            ]
+        );
+        assert_eq!(query_path_variants(r"C:\x"), vec![r"C:\x".to_string(), r"\\?\C:\x".to_string()]);
+        // Roots are stored with an upper-case drive letter
+        assert_eq!(query_path_variants(r"c:\x"), [r"c:\x", r"\\?\c:\x", r"C:\x", r"\\?\C:\x"]);
+        #[cfg(not(windows))]
+        assert_eq!(query_path_variants("/data/x"), vec!["/data/x".to_string()]);
+
+        assert_eq!(search_path_term(r"\\?\UNC\server\share\dir\"), r"\server\share\dir");
+        assert_eq!(search_path_term(r"\\server/share/dir"), r"\server\share\dir");
+        assert_eq!(search_path_term(r"\\?\C:\Very/Long"), r"C:\Very\Long");
+        assert_eq!(search_path_term("holiday photos"), "holiday photos");
+    }
+
+    #[tokio::test]
+    async fn unc_paths_resolve_to_the_stored_spelling() {
+        let (_dir, pool, id) = fixture().await;
+        for path in [r"\\?\UNC\server\share", r"\\?\UNC\server\share\dir"] {
+            insert_node(&pool, id, NodeRow { path, depth: 1, size: 10, ..Default::default() }).await;
+        }
+        let resolve = |p: &'static str| resolve_query_path(&pool, id, p);
+        assert_eq!(resolve(r"\\server\share\dir").await.unwrap(), r"\\?\UNC\server\share\dir");
+        assert_eq!(resolve(r"\\server/share/").await.unwrap(), r"\\?\UNC\server\share");
+        assert_eq!(resolve(r"\\?\UNC\server\share\dir\").await.unwrap(), r"\\?\UNC\server\share\dir");
+        // Unknown paths fall back to their normalized form
+        assert_eq!(resolve(r"\\server\share\none").await.unwrap(), r"\\server\share\none");
+    }
+
+    async fn top_paths(state: &AppState, id: Uuid, q: TopQuery) -> AppResult<Vec<String>> {
+        let res = get_top(State(state.clone()), Namespace::default(), Path(id), Query(q), ResponseFormat::Json).await?;
+        let body = json_body(res).await;
+        Ok(body.as_array().unwrap().iter().map(|i| i["path"].as_str().unwrap().to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn top_is_scoped_to_a_subtree_with_separator_aware_prefixes() {
+        let (_dir, pool, id) = fixture().await;
+        for (path, parent, depth, size) in [
+            (r"D:\A", r"D:\", 1, 900),
+            (r"D:\A\x", r"D:\A", 2, 500),
+            (r"D:\A\x\deep", r"D:\A\x", 3, 300),
+            (r"D:\A\y", r"D:\A", 2, 50),
+            (r"D:\AB", r"D:\", 1, 5000),
+            (r"D:\AB\z", r"D:\AB", 2, 4000),
+        ] {
+            insert_node(&pool, id, NodeRow { path, parent: Some(parent), depth, size, ..Default::default() })
+                .await;
+        }
+        for (path, parent, size) in [
+            (r"D:\A\top.bin", r"D:\A", 100),
+            (r"D:\A\x\deep\f.bin", r"D:\A\x\deep", 300),
+            (r"D:\AB\big.bin", r"D:\AB", 1000),
+        ] {
+            insert_file(&pool, id, FileRow { path, parent: Some(parent), size, ..Default::default() }).await;
+        }
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let scoped = |scope: &str| TopQuery { scope: Some(scope.into()), path: Some(r"D:\A\".into()), ..Default::default() };
+
+        assert_eq!(top_paths(&state, id, scoped("dirs")).await.unwrap(), [r"D:\A\x", r"D:\A\x\deep", r"D:\A\y"]);
+        assert_eq!(top_paths(&state, id, scoped("files")).await.unwrap(), [r"D:\A\x\deep\f.bin", r"D:\A\top.bin"]);
+        let q = TopQuery { max_depth_relative: Some(1), ..scoped("dirs") };
+        assert_eq!(top_paths(&state, id, q).await.unwrap(), [r"D:\A\x", r"D:\A\y"]);
+        let q = TopQuery { max_depth_relative: Some(1), ..scoped("files") };
+        assert_eq!(top_paths(&state, id, q).await.unwrap(), [r"D:\A\top.bin"]);
+        let q = TopQuery { min_size: Some(100), ..scoped("dirs") };
+        assert_eq!(top_paths(&state, id, q).await.unwrap(), [r"D:\A\x", r"D:\A\x\deep"]);
+        // Unscoped rankings are unchanged
+        let all = top_paths(&state, id, TopQuery { limit: Some(2), ..Default::default() }).await.unwrap();
+        assert_eq!(all, [r"D:\AB", r"D:\AB\z"]);
+
+        for q in [
+            TopQuery { max_depth_relative: Some(0), ..scoped("dirs") },
+            TopQuery { max_depth_relative: Some(1), ..Default::default() },
+        ] {
+            assert!(matches!(top_paths(&state, id, q).await, Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn recent_answers_from_stored_mtimes_and_verifies_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(&root).unwrap();
+        let kept = root.join("kept.txt");
+        let touched = root.join("touched.txt");
+        std::fs::write(&kept, b"a").unwrap();
+        std::fs::write(&touched, b"b").unwrap();
+        let mtime_of = |p: &std::path::Path| {
+            std::fs::metadata(p).unwrap().modified().unwrap().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+                as i64
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "done", &[], "default").await;
+        let root_str = root.to_string_lossy().to_string();
+        let root_node = NodeRow {
+            path: &root_str,
+            size: 3,
+            files: 3,
+            mtime: Some(now - 5 * 86_400),
+            ..Default::default()
+        };
+        insert_node(&pool, id, root_node).await;
+        let files = [
+            (kept.to_string_lossy().to_string(), Some(mtime_of(&kept))),
+            // Recorded before it was modified again
+            (touched.to_string_lossy().to_string(), Some(mtime_of(&touched) - 100)),
+            // Deleted since the scan, and older than the others
+            (root.join("gone.txt").to_string_lossy().to_string(), Some(now - 40 * 86_400)),
+            // Without an mtime a file never shows up
+            (root.join("unknown.txt").to_string_lossy().to_string(), None),
+        ];
+        for (path, mtime) in &files {
+            let file =
+                FileRow { path, parent: Some(&root_str), size: 1, mtime: *mtime, ..Default::default() };
+            insert_file(&pool, id, file).await;
+        }
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let recent = |q: RecentQuery| async {
+            let res = get_recent(State(state.clone()), Namespace::default(), Path(id), Query(q)).await;
+            serde_json::from_value::<Vec<RecentItem>>(json_body(res?.into_response()).await)
+                .map_err(|e| AppError::Internal(e.into()))
+        };
+        let path_of = |i: &RecentItem| match &i.item {
+            TopItem::Dir { path, .. } | TopItem::File { path, .. } => path.clone(),
+        };
+        let file_name = |i: &RecentItem| path_of(i).rsplit(['/', '\\']).next().unwrap().to_string();
+
+        let files_only = || RecentQuery { scope: Some("files".into()), ..Default::default() };
+        let items = recent(files_only()).await.unwrap();
+        assert_eq!(items.iter().map(file_name).collect::<Vec<_>>(), vec!["kept.txt", "touched.txt", "gone.txt"]);
+        assert!(items.iter().all(|i| i.changed.is_none()));
+
+        let q = RecentQuery { scope: Some("all".into()), within_days: Some(30), ..Default::default() };
+        let items = recent(q).await.unwrap();
+        assert_eq!(items.iter().map(file_name).collect::<Vec<_>>(), vec!["kept.txt", "touched.txt", "data"]);
+
+        let q = RecentQuery { verify: Some(true), ..files_only() };
+        let items = recent(q).await.unwrap();
+        let changed: Vec<_> = items.iter().map(|i| (file_name(i), i.changed.unwrap())).collect();
+        assert_eq!(
+            changed,
+            vec![("kept.txt".to_string(), false), ("touched.txt".to_string(), true), ("gone.txt".to_string(), true)]
+        );
+        assert!(items[2].current_mtime.is_none());
+
+        let q = RecentQuery { path: Some(root.join("kept.txt").to_string_lossy().to_string()), ..files_only() };
+        assert_eq!(recent(q).await.unwrap().len(), 1);
+        let q = RecentQuery { scope: Some("links".into()), ..Default::default() };
+        assert!(matches!(recent(q).await, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn estimate_reports_problem_roots_without_storing_anything() {
+        let (dir, pool, _) = fixture().await;
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        std::fs::write(root.join("sub/a.bin"), b"a").unwrap();
+        std::fs::write(root.join("b.bin"), b"b").unwrap();
+        let root = root.to_string_lossy().to_string();
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+        let scans_before: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&pool).await.unwrap();
+        let state = AppState::new(pool.clone(), crate::config::AppConfig::default());
+
+        let req = CreateScanRequest { root_paths: vec![root.clone(), missing.clone()], ..Default::default() };
+        let Json(est) = estimate_scan(State(state.clone()), Query(EstimateQuery::default()), ValidJson(req)).await.unwrap();
+        assert!(est.complete);
+        assert_eq!((est.dirs.estimate, est.files.estimate), (3, 2));
+        assert_eq!(est.roots[0].problem, None);
+        assert_eq!((est.roots[1].path.as_str(), est.roots[1].problem.as_deref()), (missing.as_str(), Some("missing_root")));
+        let scans_after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scans").fetch_one(&pool).await.unwrap();
+        assert_eq!(scans_after, scans_before);
+
+        // Nested roots are dropped with a warning like for POST /scans
+        let nested = format!("{}/sub", root);
+        let req = CreateScanRequest { root_paths: vec![root.clone(), nested], ..Default::default() };
+        let Json(est) = estimate_scan(State(state.clone()), Query(EstimateQuery::default()), ValidJson(req)).await.unwrap();
+        assert_eq!((est.roots.len(), est.warnings.len()), (1, 1));
+
+        let req = CreateScanRequest::default();
+        let res = estimate_scan(State(state), Query(EstimateQuery::default()), ValidJson(req)).await;
+        assert!(matches!(res, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn rescans_need_a_finished_scan() {
+        let (dir, pool, _) = fixture().await;
+        let running = insert_scan(&pool, "running", &[], "default").await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let root = dir.path().to_string_lossy().to_string();
+        let req =
+            |rescan_of| CreateScanRequest { root_paths: vec![root.clone()], rescan_of, ..Default::default() };
+        let res = start_scan(&state, req(Some(Uuid::new_v4())), &Namespace::default()).await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+        let res = start_scan(&state, req(Some(running)), &Namespace::default()).await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn progress_is_estimated_against_the_last_scan_of_the_same_roots() {
+        let (dir, pool, _) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let root = dir.path().to_string_lossy().to_string();
+        let run = |ns: &'static str| {
+            let state = state.clone();
+            let req = CreateScanRequest { root_paths: vec![root.clone()], ..Default::default() };
+            async move {
+                let id = start_scan(&state, req, &Namespace::parse(ns).unwrap()).await.unwrap().id;
+                let finished = state.jobs.read().await.get(&id).map(|job| job.finished.clone());
+                if let Some(finished) = finished {
+                    tokio::time::timeout(Duration::from_secs(30), finished.cancelled()).await.unwrap();
+                }
+                let (events, _) = event_log(&state, id).await.unwrap().subscribe(None);
+                match &events[0].1 {
+                    ScanEvent::Started { expected_totals, .. } => (id, *expected_totals),
+                    other => panic!("unexpected first event: {:?}", other),
+                }
+            }
+        };
+        let (first, expected) = run("hr").await;
+        assert_eq!(expected, None);
+        let (_, expected) = run("hr").await;
+        let expected = expected.unwrap();
+        assert_eq!(expected.scan_id, first);
+        assert!(expected.files > 0);
+        // Scans of other namespaces are not looked at
+        assert_eq!(run("ops").await.1, None);
+
+        // Estimates only grow and stay below 100 until the scan is done
+        let log = EventLog::new(10);
+        log.expect(ExpectedTotals { scan_id: first, dirs: 10, files: 90 });
+        let progress = |files: u64| ScanEvent::Progress {
+            current_path: String::new(),
+            dirs_scanned: 5,
+            files_scanned: files,
+            logical_size: 0,
+            allocated_size: 0,
+            estimated_percent: None,
+        };
+        for files in [45, 20, 200] {
+            log.record(progress(files));
+        }
+        let (events, _) = log.subscribe(None);
+        let percents: Vec<_> = events
+            .iter()
+            .map(|(_, ev)| match ev {
+                ScanEvent::Progress { estimated_percent, .. } => *estimated_percent,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(percents, [Some(50), Some(50), Some(99)]);
+    }
+
+    #[tokio::test]
+    async fn scan_jobs_log_with_their_scan_id() {
+        let (subscriber, captured) = crate::logging::tests::json_subscriber();
+        let _default = tracing::subscriber::set_default(subscriber);
+        let (dir, pool, _) = fixture().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+        let root = dir.path().to_string_lossy().to_string();
+        let req = CreateScanRequest { root_paths: vec![root], ..Default::default() };
+        let id = start_scan(&state, req, &Namespace::default()).await.unwrap().id;
+        let finished = state.jobs.read().await.get(&id).map(|job| job.finished.clone());
+        if let Some(finished) = finished {
+            tokio::time::timeout(Duration::from_secs(30), finished.cancelled()).await.unwrap();
+        }
+
+        let records = captured.records();
+        let message = |m: &str| records.iter().find(|r| r["fields"]["message"] == m).cloned().unwrap();
+        assert_eq!(message("Scan started")["fields"]["scan_id"], id.to_string());
+        let done = message("Scan finished");
+        assert_eq!(done["span"]["name"], "scan");
+        assert_eq!(done["span"]["scan_id"], id.to_string());
+        assert_eq!(done["fields"]["ok"], true);
+    }
+}
@@ -0,0 +1,177 @@
+//! Recurring scan schedule API endpoints.
+//!
+//! Schedules start scans automatically, either every N minutes or according
+//! to a cron expression. They are persisted in the database and picked up by
+//! the background scheduler in [`crate::scheduler`].
+//!
+//! ## API Endpoints
+//!
+//! - `POST /schedules` - Create a schedule
+//! - `GET /schedules` - List all schedules of the request's namespace
+//! - `DELETE /schedules/{id}` - Remove a schedule
+//!
+//! Schedules belong to the namespace they were created in, and the scans they
+//! start are stamped with that namespace.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use sqlx::{sqlite::SqliteRow, Row};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::{namespace::Namespace, validation::ValidJson},
+    routes::scans::resolve_scan_options,
+    scheduler::{self, MAX_INTERVAL_MINUTES},
+    state::AppState,
+    types::{CreateScanRequest, CreateScheduleRequest, ScheduleDto},
+};
+
+/// Creates a new recurring scan schedule.
+///
+/// The embedded scan request is validated exactly like `POST /scans`, so a
+/// schedule that could never start is rejected up front.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace the schedule is created in.
+/// * `req` - The create schedule request payload.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A `201 Created` response containing the new `ScheduleDto`.
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    ns: Namespace,
+    ValidJson(req): ValidJson<CreateScheduleRequest>,
+) -> AppResult<Response> {
+    let cron = req.cron.as_deref().map(str::trim).filter(|c| !c.is_empty()).map(str::to_string);
+    match (req.interval_minutes, cron.as_ref()) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::BadRequest("specify either interval_minutes or cron, not both".into()))
+        }
+        (None, None) => return Err(AppError::BadRequest("either interval_minutes or cron is required".into())),
+        (Some(m), None) if m == 0 || m > MAX_INTERVAL_MINUTES => {
+            return Err(AppError::ValidationError {
+                field: "interval_minutes".into(),
+                message: format!("must be in 1..={}", MAX_INTERVAL_MINUTES),
+            })
+        }
+        _ => {}
+    }
+
+    resolve_scan_options(&state, &req.scan).await?;
+
+    let next_run = scheduler::next_run_after(req.interval_minutes, cron.as_deref(), Utc::now())
+        .map_err(|e| AppError::ValidationError { field: "cron".into(), message: e.to_string() })?;
+    let next_run_at = scheduler::format_timestamp(next_run);
+
+    let id = Uuid::new_v4();
+    let request_json = serde_json::to_string(&req.scan)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize scan request: {}", e)))?;
+    sqlx::query(
+        r#"INSERT INTO schedules (id, request, interval_minutes, cron, next_run_at, namespace)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+    )
+    .bind(id.to_string())
+    .bind(request_json)
+    .bind(req.interval_minutes.map(|m| m as i64))
+    .bind(cron.as_deref())
+    .bind(&next_run_at)
+    .bind(ns.as_str())
+    .execute(&state.db)
+    .await?;
+
+    let row = sqlx::query(
+        r#"SELECT id, request, interval_minutes, cron, created_at, last_run_at, next_run_at, last_scan_id, namespace
+           FROM schedules WHERE id=?1"#,
+    )
+    .bind(id.to_string())
+    .fetch_one(&state.db)
+    .await?;
+    Ok((StatusCode::CREATED, Json(schedule_from_row(&row)?)).into_response())
+}
+
+/// Lists the recurring scan schedules visible from the request's namespace.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a list of `ScheduleDto` objects.
+pub async fn list_schedules(State(state): State<AppState>, ns: Namespace) -> AppResult<impl IntoResponse> {
+    let rows = sqlx::query(
+        r#"SELECT id, request, interval_minutes, cron, created_at, last_run_at, next_run_at, last_scan_id, namespace
+           FROM schedules WHERE (?1 OR namespace = ?2) ORDER BY created_at ASC"#,
+    )
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .fetch_all(state.read_pool())
+    .await?;
+
+    let mut items: Vec<ScheduleDto> = Vec::with_capacity(rows.len());
+    for r in &rows {
+        items.push(schedule_from_row(r)?);
+    }
+    Ok(Json(items))
+}
+
+/// Removes a recurring scan schedule.
+///
+/// Scans that were already started by the schedule keep running.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the schedule to remove.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A `204 No Content` response on success.
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let res = sqlx::query("DELETE FROM schedules WHERE id=?1 AND (?2 OR namespace = ?3)")
+        .bind(id.to_string())
+        .bind(ns.is_admin())
+        .bind(ns.as_str())
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound("schedule not found".into()));
+    }
+    Ok((StatusCode::NO_CONTENT, ""))
+}
+
+fn schedule_from_row(r: &SqliteRow) -> AppResult<ScheduleDto> {
+    let id_str = r.get::<String, _>("id");
+    let id = Uuid::parse_str(&id_str).map_err(|e| {
+        tracing::error!("Invalid UUID in schedules table: {} - {}", id_str, e);
+        AppError::Database(format!("Database corruption: invalid UUID {}", id_str))
+    })?;
+    let scan: CreateScanRequest = serde_json::from_str(&r.get::<String, _>("request"))
+        .map_err(|e| AppError::Database(format!("Invalid stored scan request for schedule {}: {}", id, e)))?;
+    Ok(ScheduleDto {
+        id,
+        scan,
+        interval_minutes: r.get::<Option<i64>, _>("interval_minutes").and_then(|m| u32::try_from(m).ok()),
+        cron: r.get("cron"),
+        created_at: r.get("created_at"),
+        last_run_at: r.get("last_run_at"),
+        next_run_at: r.get("next_run_at"),
+        last_scan_id: r.get::<Option<String>, _>("last_scan_id").and_then(|s| Uuid::parse_str(&s).ok()),
+        namespace: r.get("namespace"),
+    })
+}
@@ -20,6 +20,7 @@
 //! - **Size Ranges**: Specify min_size and/or max_size filters
 //! - **File Types**: Filter by extensions (e.g., "pdf", "jpg", "txt")
 //! - **Result Types**: Control inclusion of files vs directories
+//! - **Path Terms**: Windows paths match with or without the `\\?\` prefix
 //! - **Sorting**: Results sorted by allocated size (largest first)
 //!
 //! ## Security Considerations
@@ -33,36 +34,61 @@ use axum::{
     extract::{Path, Query, State},
     http::HeaderMap,
     response::IntoResponse,
-    Json,
 };
-use serde::{Deserialize, Serialize};
-use sqlx::{QueryBuilder, Row};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, Sqlite};
 use uuid::Uuid;
 
 use crate::{
     error::{AppError, AppResult},
     middleware::ip::{extract_ip_from_headers, MaybeRemoteAddr},
+    middleware::namespace::Namespace,
+    routes::{
+        negotiate::{respond_negotiated, ResponseFormat},
+        scans::search_path_term,
+    },
     state::AppState,
+    types::{GlobalSearchHit, GlobalSearchResult, SearchItem, SearchResult},
 };
 
 /// Query parameters for the search endpoint.
+///
+/// All filters are optional and combine with AND, but at least one of
+/// `query`, `q`, the size, time or extension filters must be given.
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
-    /// The search term.
-    pub query: String,
+    /// A substring of the path.
+    #[serde(default)]
+    pub query: Option<String>,
+    /// A substring of the name, or a glob the whole name must match (`*.log`, `report-202?-*`).
+    #[serde(default)]
+    pub q: Option<String>,
     /// The maximum number of results to return.
     #[serde(default = "default_limit")]
     pub limit: i64,
     /// The number of results to skip.
     #[serde(default)]
     pub offset: i64,
-    /// The minimum file size in bytes.
+    /// The minimum allocated size in bytes.
     #[serde(default)]
     pub min_size: Option<i64>,
-    /// The maximum file size in bytes.
+    /// The maximum allocated size in bytes.
     #[serde(default)]
     pub max_size: Option<i64>,
-    /// The file extension to filter by.
+    /// Only items modified at or after this time (seconds since the Unix epoch).
+    #[serde(default)]
+    pub modified_after: Option<i64>,
+    /// Only items modified at or before this time (seconds since the Unix epoch).
+    #[serde(default)]
+    pub modified_before: Option<i64>,
+    /// The kind of items to return (`file`, `dir` or `all`).
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Comma-separated file extensions, e.g. `.log,.tmp`; directories never match.
+    #[serde(default)]
+    pub ext: Option<String>,
+    /// The file extension to filter by; combined with `ext`.
     #[serde(default)]
     #[serde(alias = "type")]
     pub file_type: Option<String>, // e.g., "txt", "pdf", "jpg" (also accepts query param 'type')
@@ -72,59 +98,18 @@ pub struct SearchQuery {
     /// Whether to include directories in the search results.
     #[serde(default)]
     pub include_dirs: Option<bool>,
+    /// The sort key: `allocated` (default), `logical`, `mtime`, `name` or `path`.
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// The sort direction: `desc` (default) or `asc`.
+    #[serde(default)]
+    pub order: Option<String>,
 }
 
 fn default_limit() -> i64 {
     100
 }
 
-/// The response from the search endpoint.
-#[derive(Debug, Serialize)]
-pub struct SearchResult {
-    /// The search results.
-    pub items: Vec<SearchItem>,
-    /// The total number of matching items.
-    pub total_count: i64,
-    /// The original search query.
-    pub query: String,
-}
-
-/// An item in the search results.
-#[derive(Debug, Serialize)]
-#[serde(tag = "type")]
-pub enum SearchItem {
-    /// A directory search result.
-    Dir {
-        /// The path of the directory.
-        path: String,
-        /// The name of the directory.
-        name: String,
-        /// The allocated size of the directory in bytes.
-        allocated_size: i64,
-        /// The logical size of the directory in bytes.
-        logical_size: i64,
-        /// The number of files in the directory.
-        file_count: i64,
-        /// The number of subdirectories in the directory.
-        dir_count: i64,
-        /// The depth of the directory in the directory tree.
-        depth: i64,
-    },
-    /// A file search result.
-    File {
-        /// The path of the file.
-        path: String,
-        /// The name of the file.
-        name: String,
-        /// The allocated size of the file in bytes.
-        allocated_size: i64,
-        /// The logical size of the file in bytes.
-        logical_size: i64,
-        /// The file extension.
-        extension: Option<String>,
-    },
-}
-
 /// Escape character used for SQL LIKE patterns.
 ///
 /// This character is used to escape special SQL LIKE wildcards (% and _) to
@@ -194,209 +179,914 @@ fn sanitize_search_term(raw: &str) -> Result<String, AppError> {
     Ok(sanitized)
 }
 
+/// The number of rows a glob too complex for `LIKE` may read before the rest is dropped.
+const GLOB_SCAN_CAP: i64 = 20_000;
+
+/// The maximum number of extensions in one search.
+const MAX_EXTENSIONS: usize = 20;
+
+/// The last component of `path` in SQL, cut off behind `parent_path`.
+///
+/// Roots have no parent and are matched with their whole path.
+const NAME_SQL: &str = "(CASE WHEN parent_path IS NULL OR parent_path = '' THEN path \
+     WHEN substr(parent_path, -1) IN ('/', '\\') THEN substr(path, length(parent_path) + 1) \
+     ELSE substr(path, length(parent_path) + 2) END)";
+
+/// How the `q` parameter of a search matches names.
+#[derive(Debug)]
+enum NameMatch {
+    /// A `LIKE` pattern that matches exactly the wanted names.
+    Like(String),
+    /// A glob with classes (`[ab]`) or alternatives (`{a,b}`): the `LIKE`
+    /// pattern selects a superset that the matcher narrows down.
+    Glob { prefilter: String, matcher: GlobMatcher },
+}
+
+impl NameMatch {
+    /// Parses a name term; terms without glob characters match as substrings.
+    ///
+    /// `*` and `?` translate to `%` and `_`, a class to `_` and an alternative
+    /// to `%`. Like names in `LIKE`, globs match ASCII letters case-insensitively.
+    fn parse(term: &str) -> AppResult<Self> {
+        if !term.contains(['*', '?', '[', '{']) {
+            return Ok(NameMatch::Like(format!("%{}%", escape_like_pattern(term))));
+        }
+        let invalid =
+            |e: globset::Error| AppError::InvalidInput(format!("Invalid name pattern: {} ({})", term, e));
+        let glob = GlobBuilder::new(term).case_insensitive(true).build().map_err(invalid)?;
+
+        let mut like = String::with_capacity(term.len() + 2);
+        let mut exact = true;
+        let mut any = false; // whether `like` ends with `%`, so `**` adds only one
+        let mut chars = term.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '*' | '{' => {
+                    if ch == '{' {
+                        exact = false;
+                        let mut depth = 1;
+                        for c in chars.by_ref() {
+                            match c {
+                                '{' => depth += 1,
+                                '}' if depth == 1 => break,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                        }
+                    }
+                    if !any {
+                        like.push('%');
+                    }
+                    any = true;
+                    continue;
+                }
+                '?' => like.push('_'),
+                '[' => {
+                    exact = false;
+                    // The first character of a class may be a literal `]`
+                    let mut first = true;
+                    for c in chars.by_ref() {
+                        if c == ']' && !first {
+                            break;
+                        }
+                        first = c == '!' && first;
+                    }
+                    like.push('_');
+                }
+                '%' | '_' | LIKE_ESCAPE => {
+                    like.push(LIKE_ESCAPE);
+                    like.push(ch);
+                }
+                _ => like.push(ch),
+            }
+            any = false;
+        }
+        Ok(if exact {
+            NameMatch::Like(like)
+        } else {
+            NameMatch::Glob { prefilter: like, matcher: glob.compile_matcher() }
+        })
+    }
+
+    /// Returns the `LIKE` pattern to apply in SQL.
+    fn like(&self) -> &str {
+        match self {
+            NameMatch::Like(like) | NameMatch::Glob { prefilter: like, .. } => like,
+        }
+    }
+}
+
+/// The validated filters of a search within a scan.
+#[derive(Debug, Default)]
+struct SearchFilter {
+    /// The `LIKE` pattern of the path substring.
+    path: Option<String>,
+    name: Option<NameMatch>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+    modified_after: Option<i64>,
+    modified_before: Option<i64>,
+    /// Lower-case extensions without the leading dot.
+    extensions: Vec<String>,
+    dirs: bool,
+    files: bool,
+}
+
+impl SearchFilter {
+    /// Validates the filter parameters of a search query.
+    fn from_query(q: &SearchQuery) -> AppResult<Self> {
+        let term = |t: &Option<String>| t.clone().filter(|t| !t.trim().is_empty());
+        // Path terms are reduced to the part shared by all spellings of the path
+        let path = match term(&q.query) {
+            Some(t) => {
+                Some(format!("%{}%", escape_like_pattern(&search_path_term(&sanitize_search_term(&t)?))))
+            }
+            None => None,
+        };
+        let name = match term(&q.q) {
+            Some(t) => Some(NameMatch::parse(&sanitize_search_term(&t)?)?),
+            None => None,
+        };
+
+        let mut extensions: Vec<String> = Vec::new();
+        for raw in q.ext.iter().flat_map(|e| e.split(',')).chain(q.file_type.as_deref()) {
+            // Sanitize extensions to safe characters (FIX Bug #53)
+            let ext = raw
+                .trim()
+                .trim_start_matches('.')
+                .chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+                .take(20)
+                .collect::<String>()
+                .to_lowercase();
+            if !ext.is_empty() && !extensions.contains(&ext) {
+                extensions.push(ext);
+            }
+        }
+        if extensions.len() > MAX_EXTENSIONS {
+            return Err(AppError::InvalidInput(format!(
+                "At most {} extensions can be searched",
+                MAX_EXTENSIONS
+            )));
+        }
+
+        let (mut dirs, mut files) = match q.kind.as_deref() {
+            None | Some("all") => (true, true),
+            Some("dir") | Some("dirs") => (true, false),
+            Some("file") | Some("files") => (false, true),
+            Some(_) => return Err(AppError::InvalidInput("kind must be 'file', 'dir' or 'all'".into())),
+        };
+        dirs &= q.include_dirs.unwrap_or(true) && extensions.is_empty();
+        files &= q.include_files.unwrap_or(true);
+        if !dirs && !files {
+            return Err(AppError::InvalidInput("Must include at least files or directories".to_string()));
+        }
+
+        if q.min_size.is_some_and(|s| s < 0) || q.max_size.is_some_and(|s| s < 0) {
+            return Err(AppError::InvalidInput("min_size and max_size must be >= 0".into()));
+        }
+        if let (Some(min), Some(max)) = (q.min_size, q.max_size) {
+            if min > max {
+                return Err(AppError::InvalidInput("min_size must be <= max_size".into()));
+            }
+        }
+        if let (Some(after), Some(before)) = (q.modified_after, q.modified_before) {
+            if after > before {
+                return Err(AppError::InvalidInput("modified_after must be <= modified_before".into()));
+            }
+        }
+
+        let filter = Self {
+            path,
+            name,
+            min_size: q.min_size,
+            max_size: q.max_size,
+            modified_after: q.modified_after,
+            modified_before: q.modified_before,
+            extensions,
+            dirs,
+            files,
+        };
+        let unfiltered = filter.path.is_none()
+            && filter.name.is_none()
+            && (filter.min_size, filter.max_size, filter.modified_after, filter.modified_before)
+                == (None, None, None, None)
+            && filter.extensions.is_empty();
+        if unfiltered {
+            return Err(AppError::InvalidInput("Search query cannot be empty".to_string()));
+        }
+        Ok(filter)
+    }
+
+    /// Appends the conditions shared by directories and files.
+    fn push_conditions(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        if let Some(path) = &self.path {
+            qb.push(" AND path LIKE ").push_bind(path.clone()).push(" ESCAPE '!'");
+        }
+        if let Some(name) = &self.name {
+            qb.push(format!(" AND {} LIKE ", NAME_SQL))
+                .push_bind(name.like().to_string())
+                .push(" ESCAPE '!'");
+        }
+        if let Some(min) = self.min_size {
+            qb.push(" AND allocated_size >= ").push_bind(min);
+        }
+        if let Some(max) = self.max_size {
+            qb.push(" AND allocated_size <= ").push_bind(max);
+        }
+        if let Some(after) = self.modified_after {
+            qb.push(" AND mtime >= ").push_bind(after);
+        }
+        if let Some(before) = self.modified_before {
+            qb.push(" AND mtime <= ").push_bind(before);
+        }
+    }
+
+    /// Pushes the matching directories and files of a scan as one `UNION ALL`.
+    fn push_hits(&self, qb: &mut QueryBuilder<'_, Sqlite>, scan_id: &str) {
+        if self.dirs {
+            qb.push(format!(
+                "SELECT 'dir' AS kind, path, logical_size, allocated_size, file_count, dir_count, depth, mtime, \
+                 {} AS name FROM nodes WHERE is_dir = 1 AND scan_id = ",
+                NAME_SQL
+            ))
+            .push_bind(scan_id.to_string());
+            self.push_conditions(qb);
+        }
+        if self.files {
+            if self.dirs {
+                qb.push(" UNION ALL ");
+            }
+            qb.push(format!(
+                "SELECT 'file' AS kind, path, logical_size, allocated_size, NULL AS file_count, \
+                 NULL AS dir_count, NULL AS depth, mtime, {} AS name FROM files WHERE scan_id = ",
+                NAME_SQL
+            ))
+            .push_bind(scan_id.to_string());
+            self.push_conditions(qb);
+            if !self.extensions.is_empty() {
+                qb.push(" AND (");
+                let mut any = qb.separated(" OR ");
+                for ext in &self.extensions {
+                    any.push("LOWER(path) LIKE ")
+                        .push_bind_unseparated(format!("%.{}", escape_like_pattern(ext)))
+                        .push_unseparated(" ESCAPE '!'");
+                }
+                qb.push(")");
+            }
+        }
+    }
+}
+
+/// Returns the `ORDER BY` clause of a search; ties are broken by path, so pages are stable.
+fn search_order(q: &SearchQuery) -> AppResult<String> {
+    let column = match q.sort.as_deref() {
+        None | Some("allocated") => "allocated_size",
+        Some("logical") => "logical_size",
+        Some("mtime") => "mtime",
+        Some("name") => "name COLLATE NOCASE",
+        Some("path") => "path",
+        Some(other) => return Err(AppError::InvalidInput(format!("Invalid sort: {}", other))),
+    };
+    let direction = match q.order.as_deref() {
+        None | Some("desc") => "DESC",
+        Some("asc") => "ASC",
+        Some(other) => return Err(AppError::InvalidInput(format!("Invalid order: {}", other))),
+    };
+    Ok(format!("{} {}, path ASC, kind ASC", column, direction))
+}
+
+/// Converts a row of the search `UNION` into a result item.
+fn search_item(row: &SqliteRow) -> AppResult<SearchItem> {
+    let kind: String = row.try_get("kind")?;
+    let path: String = row.try_get("path")?;
+    // FIX Bug #32 - Better path name extraction
+    let name = std::path::Path::new(&path).file_name().and_then(|n| n.to_str()).unwrap_or(&path).to_string();
+    if kind == "dir" {
+        return Ok(SearchItem::Dir {
+            path,
+            name,
+            allocated_size: row.try_get("allocated_size")?,
+            logical_size: row.try_get("logical_size")?,
+            file_count: row.try_get("file_count")?,
+            dir_count: row.try_get("dir_count")?,
+            depth: row.try_get("depth")?,
+        });
+    }
+    // Extract file extension properly with better validation (FIX Bug #4)
+    let extension = std::path::Path::new(&path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| {
+            !ext.is_empty()
+                && ext.len() <= 15
+                && ext.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(|ext| ext.to_lowercase());
+    Ok(SearchItem::File {
+        path,
+        name,
+        allocated_size: row.try_get("allocated_size")?,
+        logical_size: row.try_get("logical_size")?,
+        extension,
+    })
+}
 
 /// Searches for files and directories within a scan.
 ///
-/// This endpoint supports full-text search, size filtering, and type filtering.
+/// `query` matches a substring of the path and `q` a substring of the name,
+/// or with glob characters the whole name. Size, modification time, kind and
+/// extension filters combine with them, and everything is applied in SQL with
+/// bound parameters. Globs with classes or alternatives, which `LIKE` cannot
+/// express, are narrowed down in memory over at most `GLOB_SCAN_CAP` rows; if
+/// that cap is hit the result is marked `truncated`.
 ///
 /// # Arguments
 ///
 /// * `state` - The application state.
 /// * `scan_id` - The ID of the scan to search.
+/// * `ns` - The namespace of the request.
 /// * `maybe_remote` - The optional remote address of the client.
 /// * `headers` - The request headers.
 /// * `query` - The search query parameters.
+/// * `format` - JSON or MessagePack, see [`ResponseFormat::negotiate`].
 ///
 /// # Returns
 ///
-/// * `AppResult<impl IntoResponse>` - A JSON response containing the search results.
+/// * `AppResult<impl IntoResponse>` - A JSON or MessagePack response containing the search results.
 pub async fn search_scan(
     State(state): State<AppState>,
     Path(scan_id): Path<Uuid>,
+    ns: Namespace,
     maybe_remote: MaybeRemoteAddr,
     headers: HeaderMap,
     Query(query): Query<SearchQuery>,
+    format: ResponseFormat,
 ) -> AppResult<impl IntoResponse> {
     // Per-endpoint rate limit: "/scans/:id/search"
     let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
     let ip = extract_ip_from_headers(&headers, fallback_ip);
-    if let Err((status, body)) = state.rate_limiter.check_endpoint_limit("/scans/:id/search", ip).await {
-        return Ok((status, body).into_response());
-    }
-    // Sanitize search query to prevent LIKE injection while preserving legitimate characters
-    let sanitized_query = sanitize_search_term(&query.query)?;
-    let search_pattern = format!("%{}%", escape_like_pattern(&sanitized_query));
-    let include_files = query.include_files.unwrap_or(true);
-    let include_dirs = query.include_dirs.unwrap_or(true);
-
-    if !include_files && !include_dirs {
-        return Err(AppError::InvalidInput("Must include at least files or directories".to_string()));
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/scans/:id/search", ip).await {
+        return Ok(limited.into_response());
     }
+    ns.ensure_scan(state.read_pool(), scan_id).await?;
+    let filter = SearchFilter::from_query(&query)?;
+    let order_by = search_order(&query)?;
 
-    // We'll execute a single UNION query with global ORDER+LIMIT+OFFSET.
     // Clamp to keep resource usage bounded even with large offsets. (FIX Bug #19)
     let limit_clamped = query.limit.clamp(1, 1000);
-    let offset_clamped = query.offset.max(0).min(10_000); // Prevent excessive offset and performance issues
-
-    // Validate that offset + limit doesn't overflow
-    if let Some(_overflow) = offset_clamped.checked_add(limit_clamped) {
-        // OK
-    } else {
-        return Err(AppError::InvalidInput("Offset and limit combination would overflow".to_string()));
-    }
-
-    // Build COUNT queries (parameterized)
-    let total_dirs = if include_dirs {
-        let mut qb = QueryBuilder::new("SELECT COUNT(*) AS cnt FROM nodes WHERE scan_id = ");
-        qb.push_bind(scan_id.to_string())
-            .push(" AND is_dir = 1 AND path LIKE ")
-            .push_bind(&search_pattern)
-            .push(" ESCAPE '!'");
-        if let Some(min_size) = query.min_size {
-            qb.push(" AND allocated_size >= ").push_bind(min_size);
+    let offset_clamped = query.offset.clamp(0, 10_000);
+    let scan = scan_id.to_string();
+
+    let (rows, total_count, truncated) = match &filter.name {
+        Some(NameMatch::Glob { matcher, .. }) => {
+            let mut qb = QueryBuilder::new("SELECT * FROM (");
+            filter.push_hits(&mut qb, &scan);
+            qb.push(format!(") ORDER BY {} LIMIT ", order_by)).push_bind(GLOB_SCAN_CAP);
+            let rows = qb.build().fetch_all(state.read_pool()).await?;
+            let truncated = rows.len() as i64 >= GLOB_SCAN_CAP;
+            let matching: Vec<SqliteRow> = rows
+                .into_iter()
+                .filter(|r| matcher.is_match(r.try_get::<String, _>("name").unwrap_or_default()))
+                .collect();
+            let total = matching.len() as i64;
+            let page =
+                matching.into_iter().skip(offset_clamped as usize).take(limit_clamped as usize).collect();
+            (page, total, truncated)
         }
-        if let Some(max_size) = query.max_size {
-            qb.push(" AND allocated_size <= ").push_bind(max_size);
+        _ => {
+            let mut qb = QueryBuilder::new("SELECT COUNT(*) AS cnt FROM (");
+            filter.push_hits(&mut qb, &scan);
+            qb.push(")");
+            let total: i64 = qb.build().fetch_one(state.read_pool()).await?.try_get("cnt")?;
+
+            let mut qb = QueryBuilder::new("SELECT * FROM (");
+            filter.push_hits(&mut qb, &scan);
+            qb.push(format!(") ORDER BY {} LIMIT ", order_by))
+                .push_bind(limit_clamped)
+                .push(" OFFSET ")
+                .push_bind(offset_clamped);
+            (qb.build().fetch_all(state.read_pool()).await?, total, false)
         }
-        let row = qb.build().fetch_one(&state.db).await?;
-        row.try_get::<i64, _>("cnt")?
-    } else {
-        0
     };
+    let items = rows.iter().map(search_item).collect::<AppResult<Vec<_>>>()?;
+    let echoed = query.query.or(query.q).unwrap_or_default();
+    Ok(respond_negotiated(format, &SearchResult { items, total_count, query: echoed, truncated }))
+}
+
+/// The maximum number of scans a cross-scan search may name explicitly.
+const MAX_SEARCH_SCANS: usize = 50;
+
+/// Query parameters for the cross-scan search endpoint.
+#[derive(Debug, Deserialize)]
+pub struct GlobalSearchQuery {
+    /// The search term.
+    #[serde(alias = "query")]
+    pub q: String,
+    /// Comma-separated scan IDs to search. Defaults to the most recent finished
+    /// scan of every distinct set of root paths.
+    #[serde(default)]
+    pub scans: Option<String>,
+    /// The maximum number of results to return.
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    /// The number of results to skip.
+    #[serde(default)]
+    pub offset: i64,
+    /// Whether to include files in the search results.
+    #[serde(default)]
+    pub include_files: Option<bool>,
+    /// Whether to include directories in the search results.
+    #[serde(default)]
+    pub include_dirs: Option<bool>,
+}
 
-    let total_files = if include_files {
-        let mut qb = QueryBuilder::new("SELECT COUNT(*) AS cnt FROM files WHERE scan_id = ");
-        qb.push_bind(scan_id.to_string())
-            .push(" AND path LIKE ")
-            .push_bind(&search_pattern)
-            .push(" ESCAPE '!'");
-        if let Some(min_size) = query.min_size {
-            qb.push(" AND allocated_size >= ").push_bind(min_size);
+/// Resolves the scans a cross-scan search runs against.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool to query.
+/// * `ns` - The namespace of the request.
+/// * `scans` - The comma-separated scan IDs from the query, if any.
+///
+/// # Returns
+///
+/// * `AppResult<Vec<Uuid>>` - The scan IDs, `BadRequest` for malformed or too many
+///   IDs, `NotFound` for scans that are not visible from `ns`.
+async fn resolve_search_scans(
+    pool: &sqlx::SqlitePool,
+    ns: &Namespace,
+    scans: Option<&str>,
+) -> AppResult<Vec<Uuid>> {
+    if let Some(raw) = scans.filter(|s| !s.trim().is_empty()) {
+        let mut ids: Vec<Uuid> = Vec::new();
+        for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let id = Uuid::parse_str(part)
+                .map_err(|_| AppError::BadRequest(format!("invalid scan id: {}", part)))?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
         }
-        if let Some(max_size) = query.max_size {
-            qb.push(" AND allocated_size <= ").push_bind(max_size);
+        if ids.len() > MAX_SEARCH_SCANS {
+            return Err(AppError::BadRequest(format!("at most {} scans can be searched", MAX_SEARCH_SCANS)));
         }
-        if let Some(file_type) = &query.file_type {
-            // Sanitize file_type to prevent injection (for COUNT query) (FIX Bug #53)
-            let sanitized = file_type
-                .chars()
-                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
-                .take(20)
-                .collect::<String>();
-            if !sanitized.is_empty() {
-                // Use parameterized pattern to prevent any LIKE injection
-                let ext_pattern = format!(".{}", sanitized.to_lowercase());
-                qb.push(" AND LOWER(path) LIKE '%' || ").push_bind(ext_pattern).push(" ESCAPE '!'");
-            }
+        for id in &ids {
+            ns.ensure_scan(pool, *id).await?;
         }
-        let row = qb.build().fetch_one(&state.db).await?;
-        row.try_get::<i64, _>("cnt")?
-    } else {
-        0
-    };
+        return Ok(ids);
+    }
+    let rows: Vec<String> = sqlx::query_scalar(
+        r#"SELECT id FROM (
+               SELECT id, ROW_NUMBER() OVER (PARTITION BY root_paths ORDER BY started_at DESC, id) AS rn
+               FROM scans WHERE status IN ('done', 'partial') AND (?1 OR namespace = ?2)
+           ) WHERE rn = 1 LIMIT ?3"#,
+    )
+    .bind(ns.is_admin())
+    .bind(ns.as_str())
+    .bind(MAX_SEARCH_SCANS as i64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().filter_map(|s| Uuid::parse_str(s).ok()).collect())
+}
 
-    let total_count = total_dirs + total_files;
+/// Pushes the scan and path conditions shared by both halves of the `hits` CTE.
+fn push_scan_filter(qb: &mut QueryBuilder<'_, sqlx::Sqlite>, scan_ids: &[Uuid], pattern: &str) {
+    qb.push(" scan_id IN (");
+    let mut sep = qb.separated(", ");
+    for id in scan_ids {
+        sep.push_bind(id.to_string());
+    }
+    sep.push_unseparated(") AND path LIKE ").push_bind_unseparated(pattern.to_string());
+    qb.push(" ESCAPE '!'");
+}
 
-    // Build UNION query via QueryBuilder
-    let mut qb = QueryBuilder::new(
-        "SELECT kind, path, logical_size, allocated_size, file_count, dir_count, depth FROM (",
-    );
+/// Pushes the deduplicated hits of a cross-scan search as a `ranked` CTE.
+///
+/// Every path is kept once, from the most recently started scan containing it.
+fn push_ranked_hits(
+    qb: &mut QueryBuilder<'_, sqlx::Sqlite>,
+    scan_ids: &[Uuid],
+    pattern: &str,
+    include_dirs: bool,
+    include_files: bool,
+) {
+    qb.push("WITH hits AS (");
     let mut first = true;
     if include_dirs {
-        qb.push("SELECT 'dir' AS kind, path, logical_size, allocated_size, file_count, dir_count, depth FROM nodes WHERE scan_id = ")
-            .push_bind(scan_id.to_string())
-            .push(" AND is_dir = 1 AND path LIKE ")
-            .push_bind(&search_pattern)
-            .push(" ESCAPE '!'");
-        if let Some(min_size) = query.min_size {
-            qb.push(" AND allocated_size >= ").push_bind(min_size);
-        }
-        if let Some(max_size) = query.max_size {
-            qb.push(" AND allocated_size <= ").push_bind(max_size);
-        }
+        qb.push("SELECT 'dir' AS kind, scan_id, path, logical_size, allocated_size, file_count, dir_count, depth FROM nodes WHERE is_dir = 1 AND");
+        push_scan_filter(qb, scan_ids, pattern);
         first = false;
     }
     if include_files {
         if !first {
             qb.push(" UNION ALL ");
         }
-        qb.push("SELECT 'file' AS kind, path, logical_size, allocated_size, NULL AS file_count, NULL AS dir_count, NULL AS depth FROM files WHERE scan_id = ")
-            .push_bind(scan_id.to_string())
-            .push(" AND path LIKE ")
-            .push_bind(&search_pattern)
-            .push(" ESCAPE '!'");
-        if let Some(min_size) = query.min_size {
-            qb.push(" AND allocated_size >= ").push_bind(min_size);
-        }
-        if let Some(max_size) = query.max_size {
-            qb.push(" AND allocated_size <= ").push_bind(max_size);
-        }
-        if let Some(file_type) = &query.file_type {
-            // Sanitize file_type to prevent injection (for UNION query) (FIX Bug #53)
-            let sanitized = file_type
-                .chars()
-                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
-                .take(20)
-                .collect::<String>();
-            if !sanitized.is_empty() {
-                // Use parameterized pattern to prevent any LIKE injection
-                let ext_pattern = format!(".{}", sanitized.to_lowercase());
-                qb.push(" AND LOWER(path) LIKE '%' || ").push_bind(ext_pattern).push(" ESCAPE '!'");
-            }
-        }
+        qb.push("SELECT 'file' AS kind, scan_id, path, logical_size, allocated_size, NULL AS file_count, NULL AS dir_count, NULL AS depth FROM files WHERE");
+        push_scan_filter(qb, scan_ids, pattern);
     }
-    qb.push(") ORDER BY allocated_size DESC LIMIT ")
-        .push_bind(limit_clamped)
-        .push(" OFFSET ")
-        .push_bind(offset_clamped);
+    qb.push(
+        "), ranked AS (SELECT h.*, s.started_at, s.root_paths, \
+         ROW_NUMBER() OVER (PARTITION BY h.path ORDER BY s.started_at DESC, h.scan_id) AS rn \
+         FROM hits h JOIN scans s ON s.id = h.scan_id) ",
+    );
+}
+
+/// Returns the longest root of a scan that contains `path`.
+fn containing_root(roots: &[String], path: &str) -> Option<String> {
+    roots
+        .iter()
+        .filter(|r| {
+            path.strip_prefix(r.as_str()).is_some_and(|rest| {
+                rest.is_empty() || r.ends_with(['/', '\\']) || rest.starts_with(['/', '\\'])
+            })
+        })
+        .max_by_key(|r| r.len())
+        .cloned()
+}
 
-    let rows = qb.build().fetch_all(&state.db).await?;
-    let mut items: Vec<SearchItem> = Vec::with_capacity(rows.len());
+/// Searches for files and directories across several scans.
+///
+/// Hits are deduplicated by path, keeping the one from the most recent scan,
+/// and annotated with the scan they were found in.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `headers` - The request headers.
+/// * `query` - The search query parameters.
+/// * `format` - JSON or MessagePack, see [`ResponseFormat::negotiate`].
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON or MessagePack response containing the search results.
+pub async fn search_all(
+    State(state): State<AppState>,
+    ns: Namespace,
+    maybe_remote: MaybeRemoteAddr,
+    headers: HeaderMap,
+    Query(query): Query<GlobalSearchQuery>,
+    format: ResponseFormat,
+) -> AppResult<impl IntoResponse> {
+    let fallback_ip = maybe_remote.0.map(|addr| addr.ip());
+    let ip = extract_ip_from_headers(&headers, fallback_ip);
+    if let Err(limited) = state.rate_limiter.check_endpoint_limit("/search", ip).await {
+        return Ok(limited.into_response());
+    }
+    let sanitized_query = search_path_term(&sanitize_search_term(&query.q)?);
+    let search_pattern = format!("%{}%", escape_like_pattern(&sanitized_query));
+    let include_files = query.include_files.unwrap_or(true);
+    let include_dirs = query.include_dirs.unwrap_or(true);
+    if !include_files && !include_dirs {
+        return Err(AppError::InvalidInput("Must include at least files or directories".to_string()));
+    }
+    let limit_clamped = query.limit.clamp(1, 1000);
+    let offset_clamped = query.offset.clamp(0, 10_000);
+
+    let scan_ids = resolve_search_scans(state.read_pool(), &ns, query.scans.as_deref()).await?;
+    if scan_ids.is_empty() {
+        let empty = GlobalSearchResult { items: Vec::new(), total_count: 0, query: query.q, scans: scan_ids };
+        return Ok(respond_negotiated(format, &empty));
+    }
+
+    let mut qb = QueryBuilder::new("");
+    push_ranked_hits(&mut qb, &scan_ids, &search_pattern, include_dirs, include_files);
+    qb.push("SELECT COUNT(*) AS cnt FROM ranked WHERE rn = 1");
+    let total_count: i64 = qb.build().fetch_one(state.read_pool()).await?.try_get("cnt")?;
+
+    let mut qb = QueryBuilder::new("");
+    push_ranked_hits(&mut qb, &scan_ids, &search_pattern, include_dirs, include_files);
+    qb.push(
+        "SELECT kind, scan_id, path, logical_size, allocated_size, file_count, dir_count, depth, started_at, root_paths \
+         FROM ranked WHERE rn = 1 ORDER BY allocated_size DESC, path LIMIT ",
+    )
+    .push_bind(limit_clamped)
+    .push(" OFFSET ")
+    .push_bind(offset_clamped);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+
+    let mut items = Vec::with_capacity(rows.len());
     for row in rows {
         let kind: String = row.try_get("kind")?;
         let path: String = row.try_get("path")?;
-        // FIX Bug #32 - Better path name extraction
+        let roots: Vec<String> =
+            serde_json::from_str(&row.try_get::<String, _>("root_paths")?).unwrap_or_default();
+        let scan_id: String = row.try_get("scan_id")?;
         let name =
             std::path::Path::new(&path).file_name().and_then(|n| n.to_str()).unwrap_or(&path).to_string();
-        if kind == "dir" {
-            items.push(SearchItem::Dir {
-                path,
+        let item = if kind == "dir" {
+            SearchItem::Dir {
+                path: path.clone(),
                 name,
                 allocated_size: row.try_get("allocated_size")?,
                 logical_size: row.try_get("logical_size")?,
                 file_count: row.try_get("file_count")?,
                 dir_count: row.try_get("dir_count")?,
                 depth: row.try_get("depth")?,
-            });
+            }
         } else {
-            // Extract file extension properly with better validation (FIX Bug #4)
             let extension = std::path::Path::new(&path)
                 .extension()
                 .and_then(|ext| ext.to_str())
-                .and_then(|ext| {
-                    // Validate extension:
-                    // 1. Not empty
-                    // 2. No path separators in extension (Path::extension handles this mostly, but good to double check)
-                    // 3. Reasonable length
-                    // 4. Only alphanumeric characters
-                    if !ext.is_empty()
-                        && ext.len() <= 15
-                        && ext.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-                    {
-                        Some(ext.to_lowercase())
-                    } else {
-                        None
-                    }
-                });
-            items.push(SearchItem::File {
-                path,
+                .filter(|ext| {
+                    ext.len() <= 15 && ext.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                })
+                .map(|ext| ext.to_lowercase());
+            SearchItem::File {
+                path: path.clone(),
                 name,
                 allocated_size: row.try_get("allocated_size")?,
                 logical_size: row.try_get("logical_size")?,
                 extension,
-            });
+            }
+        };
+        items.push(GlobalSearchHit {
+            scan_id: Uuid::parse_str(&scan_id).map_err(|e| AppError::Internal(e.into()))?,
+            started_at: row.try_get("started_at")?,
+            root_path: containing_root(&roots, &path),
+            item,
+        });
+    }
+
+    let result = GlobalSearchResult { items, total_count, query: query.q, scans: scan_ids };
+    Ok(respond_negotiated(format, &result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_node, test_db, FileRow, NodeRow};
+    use http_body_util::BodyExt;
+
+    async fn insert_scan(pool: &sqlx::SqlitePool, root: &str, started_at: &str, ns: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO scans (id, status, root_paths, options, started_at, namespace) VALUES (?1, 'done', ?2, '{}', ?3, ?4)",
+        )
+        .bind(id.to_string())
+        .bind(serde_json::to_string(&vec![root]).unwrap())
+        .bind(started_at)
+        .bind(ns)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn insert_dir(pool: &sqlx::SqlitePool, id: Uuid, path: &str, size: i64) {
+        insert_node(pool, id, NodeRow { path, depth: 1, size, ..Default::default() }).await;
+    }
+
+    async fn search(state: &AppState, ns: &str, raw: &str) -> serde_json::Value {
+        let uri: axum::http::Uri = format!("/search?{}", raw).parse().unwrap();
+        let query: Query<GlobalSearchQuery> = Query::try_from_uri(&uri).unwrap();
+        let res = search_all(
+            State(state.clone()),
+            Namespace::parse(ns).unwrap(),
+            MaybeRemoteAddr(None),
+            HeaderMap::new(),
+            query,
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn searches_latest_scan_per_root_and_dedups_paths() {
+        let (_dir, pool) = test_db().await;
+
+        let old_d = insert_scan(&pool, "D:\\", "2026-01-01T00:00:00Z", "default").await;
+        let new_d = insert_scan(&pool, "D:\\", "2026-02-01T00:00:00Z", "default").await;
+        let e = insert_scan(&pool, "E:\\", "2026-01-15T00:00:00Z", "default").await;
+        let other_ns = insert_scan(&pool, "F:\\", "2026-01-15T00:00:00Z", "finance").await;
+        insert_dir(&pool, old_d, "D:\\old\\node_modules", 1).await;
+        insert_dir(&pool, new_d, "D:\\app\\node_modules", 30).await;
+        insert_dir(&pool, e, "E:\\web\\node_modules", 20).await;
+        insert_dir(&pool, other_ns, "F:\\node_modules", 10).await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+
+        let body = search(&state, "default", "q=node_modules").await;
+        assert_eq!(body["total_count"], 2);
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items[0]["path"], "D:\\app\\node_modules");
+        assert_eq!(items[0]["scan_id"], new_d.to_string());
+        assert_eq!(items[0]["root_path"], "D:\\");
+        assert_eq!(items[0]["started_at"], "2026-02-01T00:00:00Z");
+        assert_eq!(items[1]["path"], "E:\\web\\node_modules");
+
+        let page = search(&state, "default", "q=node_modules&limit=1&offset=1").await;
+        assert_eq!(page["total_count"], 2);
+        assert_eq!(page["items"].as_array().unwrap().len(), 1);
+        assert_eq!(page["items"][0]["path"], "E:\\web\\node_modules");
+
+        // An explicit scan list overrides the default; the same path in two scans is kept once
+        insert_dir(&state.db, old_d, "D:\\app\\node_modules", 5).await;
+        let body = search(&state, "default", &format!("q=node_modules&scans={},{}", old_d, new_d)).await;
+        assert_eq!(body["total_count"], 2);
+        let hit =
+            body["items"].as_array().unwrap().iter().find(|i| i["path"] == "D:\\app\\node_modules").unwrap();
+        assert_eq!(hit["scan_id"], new_d.to_string());
+    }
+
+    async fn insert_entry(
+        pool: &sqlx::SqlitePool,
+        id: Uuid,
+        path: &str,
+        size: i64,
+        mtime: i64,
+        is_dir: bool,
+    ) {
+        let parent = std::path::Path::new(path).parent().map(|p| p.to_string_lossy().into_owned());
+        let (parent, mtime) = (parent.as_deref(), Some(mtime));
+        if is_dir {
+            insert_node(pool, id, NodeRow { path, parent, depth: 1, size, mtime, ..Default::default() })
+                .await;
+        } else {
+            insert_file(pool, id, FileRow { path, parent, size, mtime, ..Default::default() }).await;
+        }
+    }
+
+    async fn scan_fixture() -> (tempfile::TempDir, AppState, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "/d", "2026-01-01T00:00:00Z", "default").await;
+        let entries = [
+            ("/d/logs", 600, 300, true),
+            ("/d/cache", 50, 400, true),
+            ("/d/logs/a.log", 300, 100, false),
+            ("/d/logs/b.LOG", 200, 200, false),
+            ("/d/logs/x.txt", 100, 300, false),
+            ("/d/cache/t.tmp", 50, 400, false),
+            ("/d/catalog_2%.db", 1000, 50, false),
+        ];
+        for (path, size, mtime, is_dir) in entries {
+            insert_entry(&pool, id, path, size, mtime, is_dir).await;
+        }
+        (dir, AppState::new(pool, crate::config::AppConfig::default()), id)
+    }
+
+    async fn search_in(state: &AppState, id: Uuid, raw: &str) -> AppResult<serde_json::Value> {
+        let uri: axum::http::Uri = format!("/scans/{}/search?{}", id, raw).parse().unwrap();
+        let query: Query<SearchQuery> = Query::try_from_uri(&uri).unwrap();
+        let res = search_scan(
+            State(state.clone()),
+            Path(id),
+            Namespace::default(),
+            MaybeRemoteAddr(None),
+            HeaderMap::new(),
+            query,
+            ResponseFormat::Json,
+        )
+        .await?
+        .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        Ok(serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn hit_paths(body: &serde_json::Value) -> Vec<&str> {
+        body["items"].as_array().unwrap().iter().map(|i| i["path"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn name_terms_translate_to_like_where_possible() {
+        let like = |term: &str| match NameMatch::parse(term).unwrap() {
+            NameMatch::Like(like) => like,
+            other => panic!("{} fell back: {:?}", term, other),
+        };
+        assert_eq!(like("report"), "%report%");
+        assert_eq!(like("50%_off!"), "%50!%!_off!!%");
+        assert_eq!(like("*.log"), "%.log");
+        assert_eq!(like("**a?c"), "%a_c");
+        assert_eq!(like("100%*"), "100!%%");
+
+        let prefilter = |term: &str| match NameMatch::parse(term).unwrap() {
+            NameMatch::Glob { prefilter, matcher } => (prefilter, matcher),
+            other => panic!("{} did not fall back: {:?}", term, other),
+        };
+        let (pre, matcher) = prefilter("[ab].log");
+        assert_eq!(pre, "_.log");
+        assert!(matcher.is_match("B.LOG") && !matcher.is_match("c.log"));
+        assert_eq!(prefilter("[]x]*").0, "_%");
+        assert_eq!(prefilter("[!]x]?").0, "__");
+        let (pre, matcher) = prefilter("*.{tmp,temp}");
+        assert_eq!(pre, "%.%");
+        assert!(matcher.is_match("a.temp") && matcher.is_match("a.tmp") && !matcher.is_match("a.txt"));
+        assert!(matches!(NameMatch::parse("[ab"), Err(AppError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn search_sql_only_contains_the_requested_filters() {
+        let sql = |raw: &str| {
+            let uri: axum::http::Uri = format!("/s?{}", raw).parse().unwrap();
+            let Query(q): Query<SearchQuery> = Query::try_from_uri(&uri).unwrap();
+            let mut qb = QueryBuilder::new("");
+            SearchFilter::from_query(&q).unwrap().push_hits(&mut qb, "id");
+            qb.sql().to_string()
+        };
+        let both = sql("query=x");
+        assert!(both.contains("FROM nodes") && both.contains("UNION ALL") && both.contains("FROM files"));
+        assert_eq!(both.matches("path LIKE").count(), 2);
+        assert!(!both.contains("allocated_size >=") && !both.contains("mtime >="));
+
+        let dirs = sql("q=x&kind=dir&min_size=1&modified_before=5");
+        assert!(dirs.contains("FROM nodes") && !dirs.contains("FROM files"));
+        assert!(
+            dirs.contains("allocated_size >=") && dirs.contains("mtime <=") && !dirs.contains("mtime >=")
+        );
+        assert_eq!(dirs.matches(NAME_SQL).count(), 2);
+
+        // Extensions only match files and become one OR group
+        let exts = sql("ext=.log,TMP,.log&type=txt");
+        assert!(!exts.contains("FROM nodes") && !exts.contains("UNION"));
+        assert_eq!(exts.matches("LOWER(path) LIKE").count(), 3);
+        assert_eq!(exts.matches(" OR LOWER(path)").count(), 2);
+
+        // Every value is bound, nothing from the query lands in the SQL
+        let bound = sql("query=zz'q&q=yy&max_size=9&modified_after=1&ext=ww");
+        assert!(
+            !bound.contains("zz") && !bound.contains("yy") && !bound.contains("ww") && !bound.contains('9')
+        );
+    }
+
+    #[tokio::test]
+    async fn search_combines_name_size_date_and_extension_filters() {
+        let (_dir, state, id) = scan_fixture().await;
+        // Names only: the directory `logs` matches, its files do not because of their parent
+        let body = search_in(&state, id, "q=log").await.unwrap();
+        assert_eq!(hit_paths(&body), ["/d/catalog_2%.db", "/d/logs", "/d/logs/a.log", "/d/logs/b.LOG"]);
+        assert_eq!(body["total_count"], 4);
+        assert_eq!(body["truncated"], false);
+
+        let cases: &[(&str, &[&str])] = &[
+            ("q=*.log", &["/d/logs/a.log", "/d/logs/b.LOG"]),
+            ("q=_2%", &["/d/catalog_2%.db"]),
+            ("query=/logs/", &["/d/logs/a.log", "/d/logs/b.LOG", "/d/logs/x.txt"]),
+            ("min_size=100&max_size=300", &["/d/logs/a.log", "/d/logs/b.LOG", "/d/logs/x.txt"]),
+            // Equal sizes fall back to the path order
+            ("modified_after=300", &["/d/logs", "/d/logs/x.txt", "/d/cache", "/d/cache/t.tmp"]),
+            ("modified_after=100&modified_before=200&kind=file", &["/d/logs/a.log", "/d/logs/b.LOG"]),
+            ("min_size=1&kind=dir", &["/d/logs", "/d/cache"]),
+            ("ext=.log,tmp", &["/d/logs/a.log", "/d/logs/b.LOG", "/d/cache/t.tmp"]),
+            ("type=txt&query=logs", &["/d/logs/x.txt"]),
+            ("q=*.log&min_size=250", &["/d/logs/a.log"]),
+            ("q=[ab].log", &["/d/logs/a.log", "/d/logs/b.LOG"]),
+            ("q=*.{tmp,txt}&max_size=60", &["/d/cache/t.tmp"]),
+            (
+                "min_size=1&sort=name&order=asc&kind=file",
+                &["/d/logs/a.log", "/d/logs/b.LOG", "/d/catalog_2%.db", "/d/cache/t.tmp", "/d/logs/x.txt"],
+            ),
+            ("min_size=50&max_size=50", &["/d/cache", "/d/cache/t.tmp"]),
+        ];
+        for (raw, expected) in cases {
+            let body = search_in(&state, id, raw).await.unwrap();
+            assert_eq!(hit_paths(&body), *expected, "{}", raw);
+            assert_eq!(body["total_count"], expected.len(), "{}", raw);
         }
     }
 
-    Ok(Json(SearchResult { items, total_count, query: query.query }).into_response())
+    #[tokio::test]
+    async fn search_pages_are_stable_and_invalid_filters_are_rejected() {
+        let (_dir, state, id) = scan_fixture().await;
+        let all = hit_paths(&search_in(&state, id, "min_size=0").await.unwrap()).join(",");
+        let mut paged = Vec::new();
+        for offset in 0..4 {
+            let body =
+                search_in(&state, id, &format!("min_size=0&limit=2&offset={}", offset * 2)).await.unwrap();
+            assert_eq!(body["total_count"], 7);
+            paged.extend(hit_paths(&body).into_iter().map(str::to_string));
+        }
+        assert_eq!(paged.join(","), all);
+        let globbed = search_in(&state, id, "q=[abx].*&limit=1&offset=1").await.unwrap();
+        assert_eq!(hit_paths(&globbed), ["/d/logs/b.LOG"]);
+        assert_eq!(globbed["total_count"], 3);
+
+        for raw in [
+            "",
+            "q=%20&query=",
+            "kind=everything&min_size=1",
+            "kind=dir&ext=log",
+            "min_size=5&max_size=1",
+            "modified_after=5&modified_before=1",
+            "min_size=-1",
+            "min_size=1&sort=owner",
+            "min_size=1&order=up",
+            "q=[ab",
+        ] {
+            let res = search_in(&state, id, raw).await;
+            assert!(matches!(res, Err(AppError::InvalidInput(_))), "{}: {:?}", raw, res);
+        }
+    }
+
+    #[test]
+    fn containing_root_respects_component_boundaries() {
+        let roots = vec!["/data".to_string(), "/data2".to_string(), "/".to_string()];
+        assert_eq!(containing_root(&roots, "/data2/x").as_deref(), Some("/data2"));
+        assert_eq!(containing_root(&roots, "/data/x").as_deref(), Some("/data"));
+        assert_eq!(containing_root(&roots, "/other").as_deref(), Some("/"));
+        assert_eq!(containing_root(&["C:\\".to_string()], "D:\\x"), None);
+    }
 }
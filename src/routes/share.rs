@@ -0,0 +1,207 @@
+//! Read-only share link API endpoints.
+//!
+//! A share link lets someone without an auth token browse one finished scan
+//! through `GET /scans/{id}/tree|top|list|export|statistics?share={token}`;
+//! everything else stays closed to them (see [`crate::middleware::share`]).
+//! Expired links are deleted by the periodic cleanup task.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/share` - Create a share link, optionally expiring after `expires_in_secs`
+//! - `DELETE /scans/{id}/share/{token}` - Revoke a share link
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::{namespace::Namespace, share::find_link, validation::ValidJson},
+    state::AppState,
+    types::{CreateShareRequest, ShareLink},
+};
+
+/// The longest lifetime of an expiring share link, a year.
+const MAX_EXPIRES_IN_SECS: u64 = 365 * 24 * 3600;
+
+/// Creates a share link for a finished scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `req` - The create share request payload.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - A `201 Created` response containing the new `ShareLink`.
+///   `Conflict` if the scan has not finished.
+pub async fn create_share(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    ValidJson(req): ValidJson<CreateShareRequest>,
+) -> AppResult<Response> {
+    ns.ensure_scan(&state.db, id).await?;
+    if let Some(secs) = req.expires_in_secs {
+        if secs == 0 || secs > MAX_EXPIRES_IN_SECS {
+            return Err(AppError::ValidationError {
+                field: "expires_in_secs".into(),
+                message: format!("must be in 1..={}", MAX_EXPIRES_IN_SECS),
+            });
+        }
+    }
+    let status: String = sqlx::query_scalar("SELECT status FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await?;
+    if !matches!(status.as_str(), "done" | "partial") {
+        return Err(AppError::Conflict(format!("only finished scans can be shared, the scan is {}", status)));
+    }
+
+    // Two random v4 UUIDs give 244 random bits
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let link_id = sqlx::query(
+        "INSERT INTO share_links (scan_id, token, expires_at) VALUES (?1, ?2, \
+         CASE WHEN ?3 IS NULL THEN NULL \
+         ELSE strftime('%Y-%m-%dT%H:%M:%SZ','now', '+' || ?3 || ' seconds') END)",
+    )
+    .bind(id.to_string())
+    .bind(&token)
+    .bind(req.expires_in_secs.map(|s| s as i64))
+    .execute(&state.db)
+    .await?
+    .last_insert_rowid();
+    let (created_at, expires_at): (String, Option<String>) =
+        sqlx::query_as("SELECT created_at, expires_at FROM share_links WHERE id=?1")
+            .bind(link_id)
+            .fetch_one(&state.db)
+            .await?;
+    tracing::info!(scan_id = %id, expires_at = expires_at.as_deref(), "share link created");
+    Ok((StatusCode::CREATED, Json(ShareLink { scan_id: id, token, created_at, expires_at })).into_response())
+}
+
+/// Revokes a share link of a scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `token` - The token of the link.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A `204 No Content` response on success.
+pub async fn revoke_share(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path((id, token)): Path<(Uuid, String)>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    let link = find_link(&state.db, id, &token, false)
+        .await?
+        .ok_or_else(|| AppError::NotFound("share link not found".into()))?;
+    sqlx::query("DELETE FROM share_links WHERE id=?1").bind(link.link_id).execute(&state.db).await?;
+    tracing::info!(scan_id = %id, "share link revoked");
+    Ok((StatusCode::NO_CONTENT, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, middleware::from_fn_with_state, routing::get, Router};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::config::{AppConfig, AuthConfig};
+    use crate::middleware::{
+        auth::{auth_middleware, AuthTokens},
+        share::{purge_expired, share_middleware},
+    };
+    use crate::test_support::{insert_scan, test_db};
+
+    async fn share(state: &AppState, id: Uuid, expires_in_secs: Option<u64>) -> AppResult<ShareLink> {
+        let req = CreateShareRequest { expires_in_secs };
+        let res =
+            create_share(State(state.clone()), Namespace::parse("hr")?, Path(id), ValidJson(req)).await?;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        Ok(serde_json::from_slice(&bytes).unwrap())
+    }
+
+    /// The app's auth and share layers around handlers that echo the request's namespace.
+    fn app(state: &AppState) -> Router {
+        let auth = AuthTokens::from_config(&AuthConfig { enabled: true, tokens: vec!["admin-token".into()] });
+        let echo = |ns: Namespace| async move { ns.as_str().to_string() };
+        Router::new()
+            .route("/scans/{id}/tree", get(echo))
+            .route("/scans/{id}/top", get(echo).post(echo))
+            .route("/scans/{id}/warnings", get(echo))
+            .layer(from_fn_with_state(std::sync::Arc::new(auth), auth_middleware))
+            .layer(from_fn_with_state(state.clone(), share_middleware))
+    }
+
+    async fn call(app: &Router, method: &str, uri: String) -> (StatusCode, String) {
+        let req = Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn share_links_open_only_the_read_endpoints_of_their_scan() {
+        let (_dir, pool) = test_db().await;
+        let state = AppState::new(pool.clone(), AppConfig::default());
+        let shared = insert_scan(&pool, "done", &[], "hr").await;
+        let other = insert_scan(&pool, "done", &[], "hr").await;
+        let running = insert_scan(&pool, "running", &[], "hr").await;
+
+        let err = share(&state, running, None).await.err().unwrap();
+        assert!(matches!(err, AppError::Conflict(_)), "{:?}", err);
+        let err = share(&state, shared, Some(0)).await.err().unwrap();
+        assert!(matches!(err, AppError::ValidationError { .. }), "{:?}", err);
+        let link = share(&state, shared, None).await.unwrap();
+        assert_eq!((link.scan_id, link.expires_at.as_deref()), (shared, None));
+        assert_eq!(link.token.len(), 64);
+
+        // The link opens its scan's read endpoints in the scan's namespace
+        let app = app(&state);
+        let t = &link.token;
+        assert_eq!(
+            call(&app, "GET", format!("/scans/{}/tree?share={}", shared, t)).await,
+            (StatusCode::OK, "hr".into())
+        );
+        assert_eq!(call(&app, "GET", format!("/scans/{}/top?share={}", shared, t)).await.0, StatusCode::OK);
+        // Not another scan, other endpoints or methods, or a wrong token
+        let denied = StatusCode::UNAUTHORIZED;
+        assert_eq!(call(&app, "GET", format!("/scans/{}/tree?share={}", other, t)).await.0, denied);
+        assert_eq!(call(&app, "GET", format!("/scans/{}/warnings?share={}", shared, t)).await.0, denied);
+        assert_eq!(call(&app, "POST", format!("/scans/{}/top?share={}", shared, t)).await.0, denied);
+        let wrong = format!("{}{}", &t[..63], if t.ends_with('0') { '1' } else { '0' });
+        assert_eq!(call(&app, "GET", format!("/scans/{}/tree?share={}", shared, wrong)).await.0, denied);
+
+        // Revoking closes the link; a link of another scan cannot be revoked through this one
+        let hr = Namespace::parse("hr").unwrap();
+        let err =
+            revoke_share(State(state.clone()), hr.clone(), Path((other, t.clone()))).await.err().unwrap();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+        revoke_share(State(state.clone()), hr, Path((shared, t.clone()))).await.unwrap();
+        assert_eq!(call(&app, "GET", format!("/scans/{}/tree?share={}", shared, t)).await.0, denied);
+
+        // Expired links are refused and purged
+        let link = share(&state, shared, Some(60)).await.unwrap();
+        assert!(link.expires_at.is_some());
+        sqlx::query("UPDATE share_links SET expires_at='2000-01-01T00:00:00Z'").execute(&pool).await.unwrap();
+        let uri = format!("/scans/{}/tree?share={}", shared, link.token);
+        assert_eq!(call(&app, "GET", uri).await.0, denied);
+        assert_eq!(purge_expired(&pool).await.unwrap(), 1);
+        assert_eq!(purge_expired(&pool).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,101 @@
+//! Exclude simulation API endpoint.
+//!
+//! Before an exclude pattern is added to scheduled scans, the simulation tells
+//! how much it would remove from the totals of an existing scan, see
+//! [`crate::scanner::simulate`].
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/simulate-exclude?pattern=` - What the patterns would exclude; `pattern` may be repeated
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::normalize_patterns,
+    scanner,
+    state::AppState,
+    types::ExcludeSimulation,
+};
+
+/// Simulates additional exclude patterns against a stored scan.
+///
+/// The patterns are normalized like the excludes of `POST /scans` and matched
+/// like in a scan: a matching directory removes its whole subtree, a matching
+/// file itself; both match against the full path or the name.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `params` - The query parameters; every `pattern` is simulated.
+///
+/// # Returns
+///
+/// * `AppResult<Json<ExcludeSimulation>>` - What the patterns would remove and the
+///   directories contributing most. `InvalidInput` for a pattern that is not a valid glob.
+pub async fn simulate_exclude(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> AppResult<Json<ExcludeSimulation>> {
+    let patterns: Vec<String> =
+        params.into_iter().filter(|(key, _)| key == "pattern").map(|(_, value)| value).collect();
+    let patterns = normalize_patterns(patterns, "exclude")?;
+    if patterns.is_empty() {
+        return Err(AppError::BadRequest("at least one pattern is required".into()));
+    }
+    ns.ensure_scan(state.read_pool(), id).await?;
+    // Keep the scan from being pruned while it is read
+    let _lease = state.scan_leases.acquire(id);
+    let simulation = scanner::simulate::simulate_excludes(state.read_pool(), id, &patterns).await?;
+    Ok(Json(simulation))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::FromRequestParts;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_file, insert_scan, test_db, FileRow};
+
+    async fn simulate(state: &AppState, id: Uuid, query: &str) -> AppResult<ExcludeSimulation> {
+        let req = axum::http::Request::builder().uri(format!("/?{}", query)).body(()).unwrap();
+        let (mut parts, _) = req.into_parts();
+        let Query(params) =
+            Query::<Vec<(String, String)>>::from_request_parts(&mut parts, &()).await.unwrap();
+        simulate_exclude(State(state.clone()), Namespace::default(), Path(id), Query(params))
+            .await
+            .map(|Json(s)| s)
+    }
+
+    #[tokio::test]
+    async fn repeated_patterns_are_simulated_together() {
+        let (_dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &["/r"], "default").await;
+        for (path, size) in [("/r/a.iso", 700), ("/r/b.vhdx", 300), ("/r/c.txt", 5)] {
+            insert_file(&pool, id, FileRow { path, parent: Some("/r"), size, ..Default::default() }).await;
+        }
+        let state = AppState::new(pool, AppConfig::default());
+
+        let sim = simulate(&state, id, "pattern=*.iso&pattern=%2A%2A%5C*.vhdx").await.unwrap();
+        assert_eq!(sim.patterns, ["*.iso", "**/*.vhdx"]);
+        assert_eq!((sim.excluded_files, sim.allocated_size), (2, 1000));
+        assert_eq!(sim.top_dirs.len(), 1);
+
+        let err = simulate(&state, id, "path=/r").await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)), "{:?}", err);
+        let err = simulate(&state, id, "pattern=a%5B").await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)), "{:?}", err);
+        let err = simulate(&state, Uuid::new_v4(), "pattern=*.iso").await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+}
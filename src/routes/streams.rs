@@ -0,0 +1,187 @@
+//! Alternate data stream API endpoints.
+//!
+//! Scans started with `measure_ads` enumerate the named NTFS streams of every
+//! file and store their number and size next to the file. The stream bytes are
+//! part of the file's logical and allocated size, so this report is where
+//! they become visible on their own.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/streams?min_size=&limit=&offset=` - Files with the most stream bytes
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    error::AppResult,
+    middleware::namespace::Namespace,
+    state::AppState,
+    types::{StreamFile, StreamsResponse},
+};
+
+const STREAMS_LIMIT_DEFAULT: i64 = 100;
+const STREAMS_LIMIT_MAX: i64 = 1_000;
+
+/// Query parameters for the streams endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct StreamsQuery {
+    /// Only list files with more stream bytes than this (default 0).
+    pub min_size: Option<i64>,
+    /// The maximum number of files to return.
+    pub limit: Option<i64>,
+    /// The number of files to skip.
+    pub offset: Option<i64>,
+}
+
+/// Lists the files of a scan whose alternate data streams exceed a size.
+///
+/// Scans that did not measure streams return an empty list with `measured`
+/// set to `false`.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The threshold and pagination parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `StreamsResponse`.
+pub async fn get_streams(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<StreamsQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let min_size = q.min_size.unwrap_or(0).max(0);
+    let limit = q.limit.unwrap_or(STREAMS_LIMIT_DEFAULT).clamp(1, STREAMS_LIMIT_MAX);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let options: Option<String> = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(state.read_pool())
+        .await?;
+    // Older scans may lack the flag, so the options are not parsed into `ScanOptions`
+    let measured = options
+        .and_then(|o| serde_json::from_str::<serde_json::Value>(&o).ok())
+        .and_then(|o| o.get("measure_ads").and_then(serde_json::Value::as_bool))
+        .unwrap_or(false);
+
+    let totals = sqlx::query(
+        "SELECT COUNT(*) AS total, COALESCE(SUM(ads_size), 0) AS total_ads_size FROM files
+         WHERE scan_id=?1 AND ads_size > ?2",
+    )
+    .bind(id.to_string())
+    .bind(min_size)
+    .fetch_one(state.read_pool())
+    .await?;
+
+    let rows = sqlx::query(
+        "SELECT path, ads_count, ads_size, logical_size, allocated_size FROM files
+         WHERE scan_id=?1 AND ads_size > ?2
+         ORDER BY ads_size DESC, path LIMIT ?3 OFFSET ?4",
+    )
+    .bind(id.to_string())
+    .bind(min_size)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(state.read_pool())
+    .await?;
+    let items = rows
+        .iter()
+        .map(|r| StreamFile {
+            path: r.get("path"),
+            ads_count: r.get::<Option<i64>, _>("ads_count").unwrap_or(0),
+            ads_size: r.get("ads_size"),
+            logical_size: r.get("logical_size"),
+            allocated_size: r.get("allocated_size"),
+        })
+        .collect();
+
+    Ok(Json(StreamsResponse {
+        scan_id: id,
+        measured,
+        min_size,
+        total: totals.get("total"),
+        total_ads_size: totals.get("total_ads_size"),
+        items,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use http_body_util::BodyExt;
+    use crate::test_support::test_db;
+
+    async fn streams(state: &AppState, id: Uuid, q: StreamsQuery) -> StreamsResponse {
+        let res = get_streams(State(state.clone()), Namespace::default(), Path(id), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn files_are_listed_by_stream_bytes_above_the_threshold() {
+        let (_dir, pool) = test_db().await;
+        let (measured_id, plain_id) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, options) in [(measured_id, r#"{"measure_ads":true}"#), (plain_id, "{}")] {
+            sqlx::query("INSERT INTO scans (id, status, root_paths, options) VALUES (?1, 'done', '[]', ?2)")
+                .bind(id.to_string())
+                .bind(options)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for (path, ads) in [
+            (r"C:\data\a.txt", Some((1, 4_000))),
+            (r"C:\data\b.txt", Some((2, 50))),
+            (r"C:\data\c.txt", Some((0, 0))),
+            (r"C:\data\d.txt", None),
+        ] {
+            sqlx::query(
+                "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size,
+                                    ads_count, ads_size)
+                 VALUES (?1, ?2, 'C:\\data', 10000, 12288, ?3, ?4)",
+            )
+            .bind(measured_id.to_string())
+            .bind(path)
+            .bind(ads.map(|(count, _)| count as i64))
+            .bind(ads.map(|(_, size)| size as i64))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let state = AppState::new(pool, AppConfig::default());
+
+        let all = streams(&state, measured_id, StreamsQuery::default()).await;
+        assert!(all.measured);
+        assert_eq!(all.total, 2);
+        assert_eq!(all.total_ads_size, 4_050);
+        assert_eq!(all.items[0].path, r"C:\data\a.txt");
+        assert_eq!(all.items[1].ads_count, 2);
+
+        let big =
+            streams(&state, measured_id, StreamsQuery { min_size: Some(50), ..Default::default() }).await;
+        assert_eq!(big.total, 1);
+        assert_eq!(big.items.len(), 1);
+        let paged = StreamsQuery { limit: Some(1), offset: Some(1), ..Default::default() };
+        let page = streams(&state, measured_id, paged).await;
+        assert_eq!(page.items[0].path, r"C:\data\b.txt");
+
+        let plain = streams(&state, plain_id, StreamsQuery::default()).await;
+        assert!(!plain.measured);
+        assert_eq!(plain.total, 0);
+        assert!(plain.items.is_empty());
+    }
+}
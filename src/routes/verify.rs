@@ -0,0 +1,381 @@
+//! Scan verification API endpoints.
+//!
+//! After a cleanup a finished scan drifts away from the filesystem. Verifying
+//! re-stats a sample of its stored paths and reports how many are gone, how
+//! many changed and the bytes that amounts to. Half of the sample are the
+//! biggest directories and files, the other half is drawn at random, so large
+//! deletions show up even in small samples. Every run is recorded in the
+//! `scan_verifications` table; purging the scan deletes its records as well.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/verify?sample=` - Re-check a sample of the scan's paths
+//! - `GET /scans/{id}/verify` - List the verifications of a scan, newest first
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use futures::stream::{self, StreamExt};
+use sqlx::Row;
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    routes::scans::parse_options,
+    state::AppState,
+    types::ScanVerificationDto,
+};
+
+const SAMPLE_DEFAULT: i64 = 1_000;
+const SAMPLE_MAX: i64 = 100_000;
+/// The number of paths re-stated at the same time; network shares answer slowly.
+const STAT_CONCURRENCY: usize = 16;
+
+/// Query parameters for starting a verification.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct VerifyQuery {
+    /// The number of paths to check, split evenly between directories and files.
+    pub sample: Option<i64>,
+}
+
+/// A stored path of the scan, as checked against the filesystem.
+#[derive(Debug)]
+struct SampledPath {
+    path: String,
+    is_dir: bool,
+    logical_size: i64,
+    mtime: Option<i64>,
+}
+
+/// What the re-stat of one path found, with the bytes of drift it adds.
+enum Checked {
+    Missing(i64),
+    Changed(i64),
+    Unchanged,
+}
+
+/// The totals of a verification before they are stored.
+#[derive(Debug, Default, PartialEq)]
+struct Drift {
+    sampled_dirs: i64,
+    sampled_files: i64,
+    missing: i64,
+    changed: i64,
+    drift_bytes: i64,
+}
+
+/// Re-checks a sample of a scan's paths and records the result.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The sample size.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing the recorded
+///   `ScanVerificationDto`. `Conflict` while a job for the scan is running,
+///   `NotFound` if the scan was purged before the result could be stored.
+pub async fn verify_scan(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<VerifyQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    if state.jobs.read().await.contains_key(&id) {
+        return Err(AppError::Conflict("cannot verify a scan while a job for it is running".into()));
+    }
+    let sample = q.sample.unwrap_or(SAMPLE_DEFAULT);
+    if !(1..=SAMPLE_MAX).contains(&sample) {
+        return Err(AppError::BadRequest(format!("sample must be between 1 and {}", SAMPLE_MAX)));
+    }
+    let options: String = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(state.read_pool())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scan not found".into()))?;
+    // Without measured logical sizes only the modification time tells a change
+    let compare_sizes = parse_options(id, &options).is_none_or(|o| o.measure_logical);
+
+    let paths = sample_paths(state.read_pool(), id, sample).await?;
+    let drift = check_paths(paths, compare_sizes).await?;
+    let verification = record(&state.db, id, &drift).await?;
+    tracing::info!(
+        scan_id = %id,
+        missing = drift.missing,
+        changed = drift.changed,
+        drift_bytes = drift.drift_bytes,
+        "scan verified"
+    );
+    Ok(Json(verification))
+}
+
+/// Lists the verifications of a scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON array of `ScanVerificationDto`, newest first.
+pub async fn list_verifications(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let rows = sqlx::query(
+        "SELECT id, sampled_dirs, sampled_files, missing, changed, drift_bytes, verified_at \
+         FROM scan_verifications WHERE scan_id=?1 ORDER BY id DESC",
+    )
+    .bind(id.to_string())
+    .fetch_all(state.read_pool())
+    .await?;
+    Ok(Json(rows.iter().map(verification_from_row).collect::<Vec<_>>()))
+}
+
+fn verification_from_row(r: &sqlx::sqlite::SqliteRow) -> ScanVerificationDto {
+    ScanVerificationDto {
+        id: r.get("id"),
+        sampled_dirs: r.get("sampled_dirs"),
+        sampled_files: r.get("sampled_files"),
+        missing: r.get("missing"),
+        changed: r.get("changed"),
+        drift_bytes: r.get("drift_bytes"),
+        verified_at: r.get("verified_at"),
+    }
+}
+
+/// Picks up to `sample` paths: per table, half the biggest and half at random.
+async fn sample_paths(pool: &sqlx::SqlitePool, id: Uuid, sample: i64) -> AppResult<Vec<SampledPath>> {
+    let dirs = sample / 2;
+    let files = sample - dirs;
+    let mut paths: Vec<SampledPath> = Vec::with_capacity(sample as usize);
+    for (source, is_dir, n) in [("nodes WHERE is_dir=1 AND", true, dirs), ("files WHERE", false, files)] {
+        let biggest = n - n / 2;
+        // UNION drops the random picks that are among the biggest already
+        let rows = sqlx::query(&format!(
+            r#"SELECT path, logical_size, mtime FROM (
+                   SELECT path, logical_size, mtime FROM {source} scan_id=?1
+                   ORDER BY allocated_size DESC LIMIT ?2
+               ) UNION SELECT path, logical_size, mtime FROM (
+                   SELECT path, logical_size, mtime FROM {source} scan_id=?1
+                   ORDER BY RANDOM() LIMIT ?3
+               )"#,
+            source = source
+        ))
+        .bind(id.to_string())
+        .bind(biggest)
+        .bind(n / 2)
+        .fetch_all(pool)
+        .await?;
+        paths.extend(rows.iter().map(|r| SampledPath {
+            path: r.get("path"),
+            is_dir,
+            logical_size: r.get("logical_size"),
+            mtime: r.get("mtime"),
+        }));
+    }
+    Ok(paths)
+}
+
+/// Re-stats the sampled paths, [`STAT_CONCURRENCY`] at a time, and sums up the drift.
+async fn check_paths(paths: Vec<SampledPath>, compare_sizes: bool) -> AppResult<Drift> {
+    let mut checks = stream::iter(paths)
+        .map(|p| async move {
+            let is_dir = p.is_dir;
+            spawn_blocking(move || (is_dir, check(&p, compare_sizes)))
+                .await
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("verify join error: {}", e)))
+        })
+        .buffer_unordered(STAT_CONCURRENCY);
+    let mut drift = Drift::default();
+    while let Some(checked) = checks.next().await {
+        let (is_dir, checked) = checked?;
+        if is_dir {
+            drift.sampled_dirs += 1;
+        } else {
+            drift.sampled_files += 1;
+        }
+        match checked {
+            Checked::Missing(bytes) => {
+                drift.missing += 1;
+                drift.drift_bytes = drift.drift_bytes.saturating_add(bytes);
+            }
+            Checked::Changed(bytes) => {
+                drift.changed += 1;
+                drift.drift_bytes = drift.drift_bytes.saturating_add(bytes);
+            }
+            Checked::Unchanged => {}
+        }
+    }
+    Ok(drift)
+}
+
+/// Compares one stored path with what is on disk now.
+///
+/// Missing files count their stored size as drift; directories only count as
+/// missing or changed, since their files are sampled on their own.
+fn check(p: &SampledPath, compare_sizes: bool) -> Checked {
+    let Ok(md) = std::fs::symlink_metadata(&p.path) else {
+        return Checked::Missing(if p.is_dir || !compare_sizes { 0 } else { p.logical_size });
+    };
+    let mtime = md
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    let mtime_changed = p.mtime.is_some() && mtime != p.mtime;
+    if p.is_dir {
+        return if mtime_changed { Checked::Changed(0) } else { Checked::Unchanged };
+    }
+    let size = md.len().min(i64::MAX as u64) as i64;
+    let size_drift = if compare_sizes { (size - p.logical_size).abs() } else { 0 };
+    if size_drift != 0 || mtime_changed {
+        Checked::Changed(size_drift)
+    } else {
+        Checked::Unchanged
+    }
+}
+
+/// Stores a verification, unless the scan was purged while its paths were checked.
+async fn record(pool: &sqlx::SqlitePool, id: Uuid, drift: &Drift) -> AppResult<ScanVerificationDto> {
+    // fetch_all waits for the statement to finish; with fetch_optional the returned
+    // row can arrive before the insert commits, and a listing right after misses it
+    let rows = sqlx::query(
+        r#"INSERT INTO scan_verifications
+               (scan_id, sampled_dirs, sampled_files, missing, changed, drift_bytes)
+           SELECT ?1, ?2, ?3, ?4, ?5, ?6 WHERE EXISTS (SELECT 1 FROM scans WHERE id=?1)
+           RETURNING id, sampled_dirs, sampled_files, missing, changed, drift_bytes, verified_at"#,
+    )
+    .bind(id.to_string())
+    .bind(drift.sampled_dirs)
+    .bind(drift.sampled_files)
+    .bind(drift.missing)
+    .bind(drift.changed)
+    .bind(drift.drift_bytes)
+    .fetch_all(pool)
+    .await?;
+    rows.first().map(verification_from_row).ok_or_else(|| AppError::NotFound("Scan not found".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan, test_db_in};
+
+    async fn json_body(res: impl IntoResponse) -> serde_json::Value {
+        use http_body_util::BodyExt;
+        let bytes = res.into_response().into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Scans `<tmp>/tree` with three files at the top and one in `sub`.
+    async fn scanned() -> (tempfile::TempDir, AppState, Uuid, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("keep.bin"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("grow.bin"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("gone.bin"), vec![0u8; 300]).unwrap();
+        std::fs::write(root.join("sub").join("x.bin"), vec![0u8; 50]).unwrap();
+
+        let pool = test_db_in(dir.path()).await;
+        let root_str = root.to_string_lossy().to_string();
+        let id = insert_scan(&pool, "done", &[&root_str], "default").await;
+        let (tx, _rx) = broadcast::channel(256);
+        crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![root_str],
+            Default::default(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            1_000,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        (dir, AppState::new(pool, AppConfig::default()), id, root)
+    }
+
+    async fn verify(state: &AppState, id: Uuid, sample: Option<i64>) -> AppResult<serde_json::Value> {
+        let q = VerifyQuery { sample };
+        let res = verify_scan(State(state.clone()), Namespace::default(), Path(id), Query(q)).await?;
+        Ok(json_body(res).await)
+    }
+
+    #[tokio::test]
+    async fn verification_counts_missing_and_changed_paths() {
+        let (_dir, state, id, root) = scanned().await;
+        let clean = verify(&state, id, None).await.unwrap();
+        assert_eq!((clean["sampled_dirs"].as_i64(), clean["sampled_files"].as_i64()), (Some(2), Some(4)));
+        assert_eq!((clean["missing"].as_i64(), clean["drift_bytes"].as_i64()), (Some(0), Some(0)));
+
+        std::fs::remove_file(root.join("gone.bin")).unwrap();
+        std::fs::write(root.join("grow.bin"), vec![0u8; 250]).unwrap();
+        let drifted = verify(&state, id, Some(100)).await.unwrap();
+        assert_eq!(drifted["missing"], 1);
+        // The file grew; the root directory changed too unless the deletion fell into the same second
+        let changed = drifted["changed"].as_i64().unwrap();
+        assert!((1..=2).contains(&changed), "{}", drifted);
+        assert_eq!(drifted["drift_bytes"], 300 + 150);
+
+        // Both verifications are committed by the time verify_scan returns, newest listed first
+        let listed = list_verifications(State(state.clone()), Namespace::default(), Path(id)).await.unwrap();
+        let listed = json_body(listed).await;
+        let ids: Vec<i64> = listed.as_array().unwrap().iter().map(|v| v["id"].as_i64().unwrap()).collect();
+        assert_eq!(ids, [drifted["id"].as_i64().unwrap(), clean["id"].as_i64().unwrap()]);
+
+        for sample in [0, SAMPLE_MAX + 1] {
+            assert!(matches!(verify(&state, id, Some(sample)).await, Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn small_samples_prefer_the_biggest_entries() {
+        let (_dir, state, id, root) = scanned().await;
+        let paths = sample_paths(state.read_pool(), id, 2).await.unwrap();
+        let paths: Vec<&str> = paths.iter().map(|p| p.path.as_str()).collect();
+        let root_str = root.to_string_lossy().to_string();
+        let gone = root.join("gone.bin").to_string_lossy().to_string();
+        assert_eq!(paths, [root_str.as_str(), gone.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn purged_scans_leave_no_verification_behind() {
+        let (_dir, state, id, _root) = scanned().await;
+        let paths = sample_paths(state.read_pool(), id, 10).await.unwrap();
+        let drift = check_paths(paths, true).await.unwrap();
+        // The scan is purged while its paths are checked
+        sqlx::query("DELETE FROM scans WHERE id=?1").bind(id.to_string()).execute(&state.db).await.unwrap();
+        assert!(matches!(record(&state.db, id, &drift).await, Err(AppError::NotFound(_))));
+        let rows: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM scan_verifications").fetch_one(&state.db).await.unwrap();
+        assert_eq!(rows, 0);
+    }
+}
@@ -0,0 +1,195 @@
+//! Scan warning API endpoints.
+//!
+//! Scans store the details of every warning they emit (up to a cap), so they
+//! remain available after the event stream of the scan is gone. Purging a scan
+//! deletes its warnings as well.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/warnings?code=&limit=&offset=` - Stored warnings of a scan
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    state::AppState,
+    types::{ScanWarningDto, ScanWarningsResponse},
+};
+
+const WARNINGS_LIMIT_DEFAULT: i64 = 100;
+const WARNINGS_LIMIT_MAX: i64 = 1_000;
+const CODE_MAX_LEN: usize = 64;
+
+/// Query parameters for the warnings endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct WarningsQuery {
+    /// Only return warnings with this code, e.g. `metadata_failed`.
+    pub code: Option<String>,
+    /// The maximum number of warnings to return.
+    pub limit: Option<i64>,
+    /// The number of warnings to skip.
+    pub offset: Option<i64>,
+}
+
+/// Lists the stored warnings of a scan.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+/// * `q` - The filter and pagination parameters.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A JSON response containing a `ScanWarningsResponse`,
+///   or `BadRequest` for an invalid code filter.
+pub async fn get_warnings(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+    Query(q): Query<WarningsQuery>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let code = q.code.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    if let Some(c) = code {
+        let valid = c.len() <= CODE_MAX_LEN && c.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+        if !valid {
+            return Err(AppError::BadRequest("code must consist of letters, digits and '_'".into()));
+        }
+    }
+    let limit = q.limit.unwrap_or(WARNINGS_LIMIT_DEFAULT).clamp(1, WARNINGS_LIMIT_MAX);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let push_filter = |qb: &mut QueryBuilder<'_, Sqlite>| {
+        qb.push(" WHERE scan_id = ").push_bind(id.to_string());
+        if let Some(c) = code {
+            qb.push(" AND code = ").push_bind(c.to_string());
+        }
+    };
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM warnings");
+    push_filter(&mut qb);
+    let total: i64 = qb.build_query_scalar().fetch_one(state.read_pool()).await?;
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT path, code, message, created_at FROM warnings");
+    push_filter(&mut qb);
+    qb.push(" ORDER BY id LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let rows = qb.build().fetch_all(state.read_pool()).await?;
+    let items = rows
+        .iter()
+        .map(|r| ScanWarningDto {
+            path: r.get("path"),
+            code: r.get("code"),
+            message: r.get("message"),
+            created_at: r.get("created_at"),
+        })
+        .collect();
+
+    Ok(Json(ScanWarningsResponse { scan_id: id, total, items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_scan, test_db_in};
+    use crate::{config::AppConfig, types::ScanOptions};
+    use http_body_util::BodyExt;
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    async fn warnings(state: &AppState, id: Uuid, q: WarningsQuery) -> ScanWarningsResponse {
+        let res = get_warnings(State(state.clone()), Namespace::default(), Path(id), Query(q))
+            .await
+            .unwrap()
+            .into_response();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scan_warnings_are_stored_filtered_and_purged() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        let locked = root.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::write(locked.join("secret.txt"), b"x").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        }
+        // Privileged users can read the directory anyway
+        let unreadable = std::fs::read_dir(&locked).is_err();
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let (tx, _rx) = broadcast::channel(1024);
+        let summary = crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            vec![missing.clone(), root.to_string_lossy().to_string()],
+            ScanOptions::default(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            100,
+            100,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let state = AppState::new(pool.clone(), AppConfig::default());
+
+        let all = warnings(&state, id, WarningsQuery::default()).await;
+        assert_eq!(all.total, summary.warnings as i64);
+        assert_eq!(all.items[0].code, "missing_root");
+        assert_eq!(all.items[0].path, missing);
+        if unreadable {
+            assert!(all.items.iter().any(|w| w.code == "read_dir_failed" && w.path.ends_with("locked")));
+        }
+
+        let only =
+            warnings(&state, id, WarningsQuery { code: Some("missing_root".into()), ..Default::default() })
+                .await;
+        assert_eq!(only.total, 1);
+        let page =
+            warnings(&state, id, WarningsQuery { limit: Some(1), offset: Some(1), ..Default::default() })
+                .await;
+        assert_eq!(page.items.len(), usize::from(unreadable));
+
+        let bad = get_warnings(
+            State(state.clone()),
+            Namespace::default(),
+            Path(id),
+            Query(WarningsQuery { code: Some("x' OR 1".into()), ..Default::default() }),
+        )
+        .await;
+        assert!(matches!(bad, Err(AppError::BadRequest(_))));
+
+        sqlx::query("DELETE FROM scans WHERE id=?1").bind(id.to_string()).execute(&pool).await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM warnings").fetch_one(&pool).await.unwrap();
+        assert_eq!(left, 0);
+    }
+}
@@ -0,0 +1,194 @@
+//! Watch mode API endpoints.
+//!
+//! A watched scan is kept up to date from filesystem notifications after it
+//! has finished (see [`crate::scanner::watch`]). Updates are streamed by
+//! `GET /scans/{id}/events`. Watchers are not persisted and stop when the
+//! server shuts down or the scan is purged.
+//!
+//! ## API Endpoints
+//!
+//! - `POST /scans/{id}/watch` - Start watching the roots of a finished scan
+//! - `DELETE /scans/{id}/watch` - Stop watching
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::Row;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::namespace::Namespace,
+    scanner::{watch::watch_scan, PersistRetry},
+    state::{AppState, JobHandle},
+    types::{ScanEvent, ScanOptions, WatchResponse},
+};
+
+/// Starts keeping a finished scan up to date.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - `202 Accepted` with a `WatchResponse`, or
+///   `Conflict` if the scan is not finished, already watched or has a running job.
+pub async fn start_watch(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    let row = sqlx::query("SELECT status, root_paths, options FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_one(&state.db)
+        .await?;
+    let status: String = row.get("status");
+    if status != "done" {
+        return Err(AppError::Conflict(format!("watching requires a finished scan (status: {})", status)));
+    }
+    let root_paths: Vec<String> = serde_json::from_str(&row.get::<String, _>("root_paths"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid root_paths of scan {}: {}", id, e)))?;
+    let options: ScanOptions = serde_json::from_str(&row.get::<String, _>("options"))
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid options of scan {}: {}", id, e)))?;
+
+    if state.jobs.read().await.contains_key(&id) {
+        return Err(AppError::Conflict("a job for this scan is already running".into()));
+    }
+    let (tx, _rx) = broadcast::channel::<ScanEvent>(4096);
+    let cancel = CancellationToken::new();
+    let handle = JobHandle::new(cancel.clone(), tx.clone());
+    let finished = handle.finished.clone();
+    {
+        let mut watchers = state.watchers.write().await;
+        if watchers.contains_key(&id) {
+            return Err(AppError::Conflict("scan is already watched".into()));
+        }
+        watchers.insert(id, handle);
+    }
+
+    let db = state.db.clone();
+    let watchers_map = state.watchers.clone();
+    let config = state.config.get();
+    let batch_size = config.scanner.batch_size;
+    let debounce = Duration::from_millis(config.scanner.watch_debounce_ms.max(1));
+    let max_entries_per_dir = config.scanner.max_entries_per_dir;
+    let persist_retry = PersistRetry::from_config(&config.scanner);
+    let categories = config.categories.clone();
+    let roots = root_paths.clone();
+    tokio::spawn(async move {
+        let res = watch_scan(
+            db,
+            id,
+            roots,
+            options,
+            tx.clone(),
+            cancel.clone(),
+            batch_size,
+            debounce,
+            max_entries_per_dir,
+            persist_retry,
+            &categories,
+        )
+        .await;
+        if let Err(e) = res {
+            tracing::error!("Watcher for scan {} failed: {}", id, e);
+            let _ = tx.send(ScanEvent::Failed { message: format!("{}", e) });
+        }
+        // A stopped watcher was already removed; its slot may belong to a new watcher by now
+        if !cancel.is_cancelled() {
+            watchers_map.write().await.remove(&id);
+        }
+        finished.cancel();
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(WatchResponse { scan_id: id, watching: true, root_paths })))
+}
+
+/// Stops watching a scan.
+///
+/// Stopping a scan that is not watched is a no-op.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `id` - The ID of the scan.
+///
+/// # Returns
+///
+/// * `AppResult<impl IntoResponse>` - A `204 No Content` response.
+pub async fn stop_watch(
+    State(state): State<AppState>,
+    ns: Namespace,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    ns.ensure_scan(&state.db, id).await?;
+    if let Some(handle) = state.watchers.write().await.remove(&id) {
+        handle.cancel.cancel();
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_support::{insert_scan_with_options, test_db};
+
+    async fn fixture() -> (tempfile::TempDir, AppState, Uuid) {
+        let (dir, pool) = test_db().await;
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(&root).unwrap();
+        let root_str = root.to_string_lossy();
+        let id = insert_scan_with_options(&pool, "done", &[&root_str], "hr", &ScanOptions::default()).await;
+        (dir, AppState::new(pool, AppConfig::default()), id)
+    }
+
+    #[tokio::test]
+    async fn watch_starts_once_and_stops() {
+        let (_dir, state, id) = fixture().await;
+        let hr = Namespace::parse("hr").unwrap();
+        let resp = start_watch(State(state.clone()), hr.clone(), Path(id)).await.unwrap().into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert!(state.watchers.read().await.contains_key(&id));
+        let again = start_watch(State(state.clone()), hr.clone(), Path(id)).await;
+        assert!(matches!(again, Err(AppError::Conflict(_))));
+
+        let finished = state.watchers.read().await.get(&id).unwrap().finished.clone();
+        let resp = stop_watch(State(state.clone()), hr.clone(), Path(id)).await.unwrap().into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        tokio::time::timeout(Duration::from_secs(5), finished.cancelled()).await.unwrap();
+        assert!(!state.watchers.read().await.contains_key(&id));
+        // Stopping again is a no-op
+        assert!(stop_watch(State(state.clone()), hr, Path(id)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn watch_requires_finished_scan_in_own_namespace() {
+        let (_dir, state, id) = fixture().await;
+        let other = Namespace::parse("finance").unwrap();
+        assert!(matches!(
+            start_watch(State(state.clone()), other, Path(id)).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        sqlx::query("UPDATE scans SET status='running' WHERE id=?1")
+            .bind(id.to_string())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        let hr = Namespace::parse("hr").unwrap();
+        assert!(matches!(start_watch(State(state.clone()), hr, Path(id)).await, Err(AppError::Conflict(_))));
+        assert!(state.watchers.read().await.is_empty());
+    }
+}
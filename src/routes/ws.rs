@@ -0,0 +1,269 @@
+//! WebSocket stream of scan events.
+//!
+//! Some reverse proxies buffer Server-Sent Events no matter which headers are
+//! sent, so the live log arrives in bursts. This endpoint carries the same
+//! events over a WebSocket, which proxies pass through unbuffered once the
+//! connection is upgraded.
+//!
+//! ## API Endpoints
+//!
+//! - `GET /scans/{id}/ws` - Upgrade to a WebSocket streaming the scan's events
+//!
+//! Every event is sent as a JSON text frame, exactly as the SSE stream's data
+//! lines, starting with the replayed backlog. The server pings every
+//! [`PING_INTERVAL`] and drops connections that stop answering. When the scan
+//! ends the socket is closed with a close frame whose reason is the final
+//! status of the scan, e.g. `done` or `canceled`.
+//!
+//! WebSockets count against the same `sse.max_connections_per_client` limit as
+//! SSE streams; upgrades beyond it are refused with 429.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::{
+        ip::{extract_ip_from_headers, MaybeRemoteAddr},
+        namespace::Namespace,
+    },
+    routes::scans::event_log,
+    state::{AppState, EventLog, SseConnection},
+    types::ScanEvent,
+};
+
+/// How often the server pings the client.
+pub const PING_INTERVAL: Duration = Duration::from_secs(10);
+/// Missed pongs after which a connection counts as dead.
+const MAX_MISSED_PONGS: u32 = 3;
+
+/// Upgrades the request to a WebSocket streaming the events of a scan.
+///
+/// The scan is looked up like `GET /scans/{id}/events`: running jobs,
+/// watched scans, finished scans within the replay grace period and
+/// interrupted scans are streamed, everything else is answered with 404
+/// before the upgrade. A client that already has
+/// `sse.max_connections_per_client` streams open gets 429 instead.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `ns` - The namespace of the request.
+/// * `maybe_remote` - The optional remote address of the client.
+/// * `id` - The ID of the scan to stream events for.
+/// * `headers` - The request headers, read for the client IP behind proxies.
+/// * `ws` - The WebSocket upgrade of the request.
+///
+/// # Returns
+///
+/// * `AppResult<Response>` - The `101 Switching Protocols` response.
+pub async fn scan_ws(
+    State(state): State<AppState>,
+    ns: Namespace,
+    maybe_remote: MaybeRemoteAddr,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> AppResult<Response> {
+    ns.ensure_scan(state.read_pool(), id).await?;
+    let sse_cfg = state.config.get().sse.clone();
+    let client = extract_ip_from_headers(&headers, maybe_remote.0.map(|addr| addr.ip()));
+    // Moved into the socket task, so the slot is given back when the socket ends
+    let connection = state
+        .sse_connections
+        .try_open(client, id, sse_cfg.max_connections_per_client)
+        .ok_or(AppError::RateLimited { retry_after_seconds: sse_cfg.keep_alive_secs })?;
+    let log = event_log(&state, id).await?;
+    Ok(ws.on_upgrade(move |socket| forward_events(socket, state, id, log, connection)))
+}
+
+/// Sends the events of `log` to the socket until the scan ends or the client leaves.
+///
+/// `_connection` keeps the socket counted against the client's limit while it is open.
+async fn forward_events(
+    mut socket: WebSocket,
+    state: AppState,
+    id: Uuid,
+    log: Arc<EventLog>,
+    _connection: SseConnection,
+) {
+    let (backlog, mut rx) = log.subscribe(None);
+    for (_, event) in backlog {
+        if socket.send(event_frame(&event)).await.is_err() {
+            return;
+        }
+    }
+
+    let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut missed_pongs = 0u32;
+    loop {
+        tokio::select! {
+            ev = rx.recv() => {
+                let frame = match ev {
+                    Ok((_, event)) => event_frame(&event),
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("WebSocket stream lagged by {} messages for scan {}", n, id);
+                        event_frame(&ScanEvent::Warning {
+                            path: String::new(),
+                            code: "stream_lagged".into(),
+                            message: format!("Stream lagged, missed {} events", n),
+                        })
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if socket.send(frame).await.is_err() {
+                    return;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Pong(_))) => missed_pongs = 0,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Pings are answered by axum; clients have nothing else to say
+                Some(Ok(_)) => {}
+            },
+            _ = ping.tick() => {
+                if missed_pongs >= MAX_MISSED_PONGS {
+                    tracing::debug!("WebSocket client of scan {} stopped answering pings", id);
+                    return;
+                }
+                missed_pongs += 1;
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    // The job writes its final status before its event channel closes
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM scans WHERE id=?1")
+        .bind(id.to_string())
+        .fetch_optional(state.read_pool())
+        .await
+        .unwrap_or_default();
+    let close = CloseFrame { code: close_code::NORMAL, reason: status.unwrap_or_default().into() };
+    let _ = socket.send(Message::Close(Some(close))).await;
+}
+
+/// Serializes a scan event into a text frame.
+fn event_frame(ev: &ScanEvent) -> Message {
+    let data = serde_json::to_string(ev).unwrap_or_else(|_| {
+        serde_json::json!({"type":"warning","message":"serialization error"}).to_string()
+    });
+    Message::Text(data.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_scan, test_db};
+    use futures::{Stream, StreamExt};
+    use tokio_tungstenite::tungstenite;
+
+    /// Returns the next text frame, or `close:<reason>` for the close frame.
+    async fn next_text<S>(client: &mut S) -> String
+    where
+        S: Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let msg =
+                tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+            match msg {
+                tungstenite::Message::Text(text) => return text.to_string(),
+                tungstenite::Message::Close(frame) => return format!("close:{}", frame.unwrap().reason),
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn events_are_forwarded_and_the_socket_closes_with_the_final_status() {
+        let (_dir, pool) = test_db().await;
+        let state = AppState::new(pool, crate::config::AppConfig::default());
+
+        let id = insert_scan(&state.db, "running", &[], "default").await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(64);
+        let job = crate::state::JobHandle::new(Default::default(), tx.clone());
+        state.jobs.write().await.insert(id, job);
+        tx.send(ScanEvent::Started { root_paths: vec!["/data".into()], expected_totals: None }).unwrap();
+
+        let app = axum::Router::new()
+            .route("/scans/{id}/ws", axum::routing::get(scan_ws))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let unknown = format!("ws://{}/scans/{}/ws", addr, Uuid::new_v4());
+        assert!(tokio_tungstenite::connect_async(unknown).await.is_err());
+
+        let (mut client, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/scans/{}/ws", addr, id)).await.unwrap();
+        // The backlog comes first, then live events
+        assert!(next_text(&mut client).await.contains(r#""type":"started""#));
+        tx.send(ScanEvent::Paused).unwrap();
+        assert_eq!(next_text(&mut client).await, r#"{"type":"paused"}"#);
+
+        // Ending the job closes the event channel and with it the socket
+        sqlx::query("UPDATE scans SET status='done' WHERE id=?1")
+            .bind(id.to_string())
+            .execute(&state.db)
+            .await
+            .unwrap();
+        state.jobs.write().await.remove(&id);
+        drop(tx);
+        assert_eq!(next_text(&mut client).await, "close:done");
+        let _ = client.close(None).await;
+    }
+
+    #[tokio::test]
+    async fn sockets_count_against_the_connection_limit_of_the_client() {
+        let (_dir, pool) = test_db().await;
+        let mut config = crate::config::AppConfig::default();
+        config.sse.max_connections_per_client = 1;
+        let state = AppState::new(pool, config);
+        let id = insert_scan(&state.db, "running", &[], "default").await;
+        let (tx, _rx) = tokio::sync::broadcast::channel(64);
+        state.jobs.write().await.insert(id, crate::state::JobHandle::new(Default::default(), tx));
+
+        let app = axum::Router::new()
+            .route("/scans/{id}/ws", axum::routing::get(scan_ws))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+            axum::serve(listener, app).await.unwrap()
+        });
+        let url = format!("ws://{}/scans/{}/ws", addr, id);
+        let local: std::net::IpAddr = "127.0.0.1".parse().unwrap();
+
+        let (mut first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        assert_eq!(state.sse_connections.of_client(local), 1);
+        match tokio_tungstenite::connect_async(&url).await {
+            Err(tungstenite::Error::Http(res)) => assert_eq!(res.status(), 429),
+            other => panic!("expected 429, got {:?}", other.map(|(_, res)| res.status())),
+        }
+
+        // Closing the socket gives the slot back
+        first.close(None).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while state.sse_connections.of_client(local) > 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("closed socket must be released");
+        assert!(tokio_tungstenite::connect_async(&url).await.is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+//! Backup privileges for scans with `access_mode = "backup"`.
+//!
+//! Directories restricted to administrators can only be listed and measured
+//! with `SeBackupPrivilege`, which Windows grants to administrators and backup
+//! operators but leaves disabled in their tokens. A backup scan enables it once
+//! when it starts. Directory enumeration (`FindFirstFileExW`) and the metadata
+//! calls of the standard library open their handles with backup intent, so
+//! they bypass the access checks while the privilege is enabled; sizes that
+//! `GetCompressedFileSizeW` cannot read are taken from a handle opened with
+//! `FILE_FLAG_BACKUP_SEMANTICS` instead.
+//!
+//! The privilege belongs to the process token, so it is shared by all scans:
+//! it stays enabled as long as one backup scan runs and is disabled again after
+//! the last one. Other platforms have no equivalent; a backup scan there runs
+//! like a normal one and says so in a warning.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The number of running scans holding the privilege.
+static HOLDERS: Mutex<usize> = Mutex::new(0);
+
+/// Keeps the backup privilege enabled until dropped.
+#[must_use = "the privilege is released as soon as the guard is dropped"]
+pub struct BackupPrivilege(());
+
+/// Enables the backup privilege of the process for one scan.
+///
+/// Returns why the privilege could not be enabled, e.g. because the account
+/// does not hold it.
+pub fn acquire() -> Result<BackupPrivilege, String> {
+    let mut holders = HOLDERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *holders == 0 {
+        set_enabled(true)?;
+    }
+    *holders += 1;
+    Ok(BackupPrivilege(()))
+}
+
+impl Drop for BackupPrivilege {
+    fn drop(&mut self) {
+        let mut holders = HOLDERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *holders = holders.saturating_sub(1);
+        if *holders == 0 {
+            if let Err(e) = set_enabled(false) {
+                tracing::debug!("disabling the backup privilege failed: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn set_enabled(enable: bool) -> Result<(), String> {
+    windows_impl::set_enabled(enable)
+}
+
+#[cfg(not(windows))]
+fn set_enabled(_enable: bool) -> Result<(), String> {
+    Err("backup privileges only exist on Windows".into())
+}
+
+/// Returns the allocated size of a file read through a handle with backup semantics.
+#[cfg(windows)]
+pub fn allocated_size(path: &Path) -> Option<u64> {
+    windows_impl::allocated_size(path)
+}
+
+#[cfg(not(windows))]
+pub fn allocated_size(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(windows)]
+mod windows_impl {
+    use std::fs;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, GetLastError, ERROR_NOT_ALL_ASSIGNED, HANDLE, LUID};
+    use windows::Win32::Security::{
+        AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_BACKUP_NAME,
+        SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_PRIVILEGES_ATTRIBUTES,
+        TOKEN_QUERY,
+    };
+    use windows::Win32::Storage::FileSystem::{
+        FileStandardInfo, GetFileInformationByHandleEx, FILE_FLAG_BACKUP_SEMANTICS, FILE_READ_ATTRIBUTES,
+        FILE_STANDARD_INFO,
+    };
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    pub fn set_enabled(enable: bool) -> Result<(), String> {
+        let mut token = HANDLE::default();
+        // SAFETY: GetCurrentProcess returns a pseudo handle; the token handle is closed below.
+        unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token) }
+            .map_err(|e| format!("OpenProcessToken failed: {e}"))?;
+        let result = adjust(token, enable);
+        // SAFETY: The token was opened above and is not used afterwards.
+        let _ = unsafe { CloseHandle(token) };
+        result
+    }
+
+    fn adjust(token: HANDLE, enable: bool) -> Result<(), String> {
+        let mut luid = LUID::default();
+        // SAFETY: SE_BACKUP_NAME is a static string, `luid` outlives the call.
+        unsafe { LookupPrivilegeValueW(PCWSTR::null(), SE_BACKUP_NAME, &mut luid) }
+            .map_err(|e| format!("LookupPrivilegeValueW failed: {e}"))?;
+        let attributes = if enable { SE_PRIVILEGE_ENABLED } else { TOKEN_PRIVILEGES_ATTRIBUTES(0) };
+        let privileges = TOKEN_PRIVILEGES {
+            PrivilegeCount: 1,
+            Privileges: [LUID_AND_ATTRIBUTES { Luid: luid, Attributes: attributes }],
+        };
+        // SAFETY: `privileges` outlives the call; no previous state is requested.
+        unsafe { AdjustTokenPrivileges(token, false, Some(&privileges as *const _), 0, None, None) }
+            .map_err(|e| format!("AdjustTokenPrivileges failed: {e}"))?;
+        // AdjustTokenPrivileges also succeeds for privileges the token does not hold
+        // SAFETY: Reads the error code of the call above on this thread.
+        if enable && unsafe { GetLastError() } == ERROR_NOT_ALL_ASSIGNED {
+            return Err("the account does not hold SeBackupPrivilege".into());
+        }
+        Ok(())
+    }
+
+    pub fn allocated_size(path: &Path) -> Option<u64> {
+        let file = fs::OpenOptions::new()
+            .access_mode(FILE_READ_ATTRIBUTES.0)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS.0)
+            .open(path)
+            .ok()?;
+        let mut info = FILE_STANDARD_INFO::default();
+        // SAFETY: `info` is a FILE_STANDARD_INFO of the size passed along.
+        unsafe {
+            GetFileInformationByHandleEx(
+                HANDLE(file.as_raw_handle()),
+                FileStandardInfo,
+                &mut info as *mut FILE_STANDARD_INFO as *mut core::ffi::c_void,
+                std::mem::size_of::<FILE_STANDARD_INFO>() as u32,
+            )
+        }
+        .ok()?;
+        Some(info.AllocationSize.max(0) as u64)
+    }
+}
@@ -0,0 +1,123 @@
+//! Byte accounting of the records a scan buffers before persisting them.
+//!
+//! The flush threshold counts records, but a record's size is dominated by
+//! its paths: a wide directory of deeply nested, long names fills the
+//! buffers with gigabytes long before the count is reached. Every worker and
+//! the aggregator therefore also sum up the estimated heap footprint of what
+//! they buffer and flush once it exceeds `scanner.max_buffer_bytes`. The
+//! [`BufferMeter`] of a scan adds up all of these buffers and remembers the
+//! high-water mark; batches on their way through the aggregator channel are
+//! not counted.
+
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::{FileRecord, NodeRecord};
+
+impl NodeRecord {
+    /// The estimated number of bytes the record occupies while buffered.
+    pub(crate) fn buffered_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.path.capacity()
+            + self.parent_path.as_ref().map_or(0, String::capacity)
+            + self.path_raw.as_ref().map_or(0, Vec::capacity)
+    }
+}
+
+impl FileRecord {
+    /// The estimated number of bytes the record occupies while buffered.
+    ///
+    /// The category is shared by all files of the scan and not counted.
+    pub(super) fn buffered_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.path.capacity()
+            + self.parent_path.as_ref().map_or(0, String::capacity)
+            + self.owner.as_ref().map_or(0, String::capacity)
+            + self.hash.as_ref().map_or(0, String::capacity)
+            + self.path_raw.as_ref().map_or(0, Vec::capacity)
+    }
+}
+
+/// The estimated number of bytes a directory taken over by a rescan occupies while buffered.
+pub(super) fn copied_bytes(dir: &str) -> usize {
+    size_of::<String>() + dir.len()
+}
+
+/// Sums up the bytes buffered by all workers and the aggregator of one scan.
+///
+/// The buffered bytes are also added to a gauge shared by all scans, and
+/// taken back from it when the meter is dropped.
+#[derive(Debug)]
+pub struct BufferMeter {
+    limit: usize,
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    gauge: Arc<AtomicUsize>,
+}
+
+impl BufferMeter {
+    /// Creates a meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The bytes after which every single buffer is flushed.
+    /// * `gauge` - The gauge of all scans' buffered bytes.
+    pub fn new(limit: usize, gauge: &Arc<AtomicUsize>) -> Self {
+        Self {
+            limit: limit.max(1),
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            gauge: gauge.clone(),
+        }
+    }
+
+    /// The bytes after which a buffer is flushed.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Counts `bytes` that were added to a buffer.
+    pub fn add(&self, bytes: usize) {
+        let now = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(now, Ordering::Relaxed);
+        self.gauge.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Takes back `bytes` that left a buffer.
+    pub fn sub(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+        self.gauge.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    /// The most bytes buffered at the same time so far.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for BufferMeter {
+    fn drop(&mut self) {
+        // A scan that failed leaves records in its buffers
+        self.gauge.fetch_sub(*self.current.get_mut(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_meter_tracks_the_high_water_mark_and_releases_the_gauge() {
+        let gauge = Arc::new(AtomicUsize::new(0));
+        let meter = BufferMeter::new(100, &gauge);
+        meter.add(60);
+        meter.add(30);
+        meter.sub(60);
+        meter.add(10);
+        assert_eq!(meter.peak(), 90);
+        assert_eq!(gauge.load(Ordering::Relaxed), 40);
+        drop(meter);
+        assert_eq!(gauge.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,119 @@
+//! Classification of files into categories such as `video` or `logs`.
+//!
+//! Only the extension is looked at, the content is never read. The configured
+//! categories are resolved into one map per scan, so classifying a file is a
+//! single lookup.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::CategoriesConfig;
+
+/// The category of files whose extension is in no configured category.
+pub const OTHER: &str = "other";
+
+/// Maps lowercase extensions to their category.
+#[derive(Debug, Clone)]
+pub struct CategoryMap {
+    by_extension: HashMap<String, Arc<str>>,
+    other: Arc<str>,
+}
+
+impl CategoryMap {
+    /// Resolves the configured categories.
+    ///
+    /// An extension listed in several categories belongs to the last of them;
+    /// each such extension is logged as a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The categories configuration.
+    pub fn new(config: &CategoriesConfig) -> Self {
+        let mut by_extension: HashMap<String, Arc<str>> = HashMap::new();
+        for group in &config.groups {
+            let name: Arc<str> = Arc::from(group.name.as_str());
+            for ext in &group.extensions {
+                if let Some(previous) = by_extension.insert(ext.to_lowercase(), name.clone()) {
+                    if previous != name {
+                        tracing::warn!(
+                            "Extension '{}' is listed in categories '{}' and '{}'; using '{}'",
+                            ext,
+                            previous,
+                            name,
+                            name
+                        );
+                    }
+                }
+            }
+        }
+        Self { by_extension, other: Arc::from(OTHER) }
+    }
+
+    /// Resolves the categories for one scan, `None` if classification is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The categories configuration.
+    pub fn for_scan(config: &CategoriesConfig) -> Option<Arc<Self>> {
+        config.enabled.then(|| Arc::new(Self::new(config)))
+    }
+
+    /// Returns the category of a file, [`OTHER`] if its extension has none.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    pub fn classify(&self, path: &Path) -> Arc<str> {
+        path.extension()
+            .and_then(|ext| self.by_extension.get(&ext.to_string_lossy().to_lowercase()))
+            .unwrap_or(&self.other)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CategoryConfig;
+
+    fn group(name: &str, extensions: &[&str]) -> CategoryConfig {
+        CategoryConfig { name: name.into(), extensions: extensions.iter().map(|e| e.to_string()).collect() }
+    }
+
+    #[test]
+    fn defaults_classify_case_insensitively() {
+        let map = CategoryMap::new(&CategoriesConfig::default());
+        assert_eq!(&*map.classify(Path::new("/media/Holiday.MKV")), "video");
+        assert_eq!(&*map.classify(Path::new("/photos/img_001.jpg")), "images");
+        assert_eq!(&*map.classify(Path::new("/backup/data.tar.gz")), "archives");
+        assert_eq!(&*map.classify(Path::new("/srv/app.sqlite")), "databases");
+        assert_eq!(&*map.classify(Path::new("/var/log/system.log")), "logs");
+    }
+
+    #[test]
+    fn unknown_and_missing_extensions_are_other() {
+        let map = CategoryMap::new(&CategoriesConfig::default());
+        assert_eq!(&*map.classify(Path::new("/data/blob.xyz")), OTHER);
+        assert_eq!(&*map.classify(Path::new("/data/README")), OTHER);
+        assert_eq!(&*map.classify(Path::new("/home/u/.bashrc")), OTHER);
+    }
+
+    #[test]
+    fn extension_in_two_categories_belongs_to_the_last() {
+        let config = CategoriesConfig {
+            enabled: true,
+            groups: vec![
+                group("video", &["mp4", "ts"]),
+                group("code", &["rs", "ts"]),
+                group("web", &["html"]),
+            ],
+        };
+        let map = CategoryMap::new(&config);
+        assert_eq!(&*map.classify(Path::new("/src/app.ts")), "code");
+        assert_eq!(&*map.classify(Path::new("/media/clip.mp4")), "video");
+
+        let swapped = CategoriesConfig { enabled: true, groups: config.groups.into_iter().rev().collect() };
+        assert_eq!(&*CategoryMap::new(&swapped).classify(Path::new("/src/app.ts")), "video");
+    }
+}
@@ -0,0 +1,194 @@
+//! Checks that the stored totals of a scan's directories add up.
+//!
+//! A directory's sizes and counts must equal the sum over its direct
+//! subdirectories plus its own files. The aggregator writes a directory once
+//! all its subdirectories reported, but a summary that arrives late leaves a
+//! parent with too small totals. [`record`] runs after every completed scan
+//! and stores such directories in `scan_inconsistencies`; [`repair`]
+//! recomputes the totals level by level, deepest first, so every parent is
+//! rewritten after its children are correct.
+//!
+//! Logical sizes are only compared for scans that measured them; otherwise
+//! the directories store 0 while the files keep their length.
+
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+/// The aggregates a directory's totals are compared with. `?1` is the scan;
+/// `LEVEL` is replaced by a condition on the directory rows `n`.
+const EXPECTED: &str = r#"
+    subdirs AS (
+        SELECT parent_path, SUM(logical_size) AS l, SUM(allocated_size) AS a,
+               SUM(file_count) AS f, SUM(dir_count) + COUNT(*) AS d
+        FROM nodes WHERE scan_id=?1 AND is_dir=1 AND parent_path IN (SELECT path FROM dirs)
+        GROUP BY parent_path
+    ),
+    own AS (
+        SELECT parent_path, SUM(logical_size) AS l, SUM(allocated_size) AS a, COUNT(*) AS f
+        FROM files WHERE scan_id=?1 AND parent_path IN (SELECT path FROM dirs)
+        GROUP BY parent_path
+    ),
+    expected AS (
+        SELECT n.id, n.path, n.depth, n.logical_size, n.allocated_size, n.file_count, n.dir_count,
+               COALESCE(s.l,0) + COALESCE(o.l,0) AS el, COALESCE(s.a,0) + COALESCE(o.a,0) AS ea,
+               COALESCE(s.f,0) + COALESCE(o.f,0) AS ef, COALESCE(s.d,0) AS ed
+        FROM dirs n
+        LEFT JOIN subdirs s ON s.parent_path = n.path
+        LEFT JOIN own o ON o.parent_path = n.path
+    )"#;
+
+/// Whether a row of `expected` does not add up; `?2` tells whether logical sizes count.
+const MISMATCH: &str =
+    "(e.allocated_size <> e.ea OR e.file_count <> e.ef OR e.dir_count <> e.ed \
+     OR (?2 AND e.logical_size <> e.el))";
+
+/// Finds the directories of a scan whose totals do not add up and records them.
+///
+/// Replaces what an earlier check recorded for the scan.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `id` - The ID of the scan.
+/// * `measure_logical` - Whether the scan measured logical sizes.
+///
+/// # Returns
+///
+/// * `anyhow::Result<u64>` - The number of inconsistent directories.
+pub async fn record(pool: &SqlitePool, id: Uuid, measure_logical: bool) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM scan_inconsistencies WHERE scan_id=?1")
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+    let sql = format!(
+        r#"WITH dirs AS (SELECT * FROM nodes WHERE scan_id=?1 AND is_dir=1), {}
+        INSERT INTO scan_inconsistencies (scan_id, path, depth, logical_size, expected_logical_size,
+            allocated_size, expected_allocated_size, file_count, expected_file_count,
+            dir_count, expected_dir_count)
+        SELECT ?1, e.path, e.depth, e.logical_size, e.el, e.allocated_size, e.ea, e.file_count, e.ef,
+               e.dir_count, e.ed
+        FROM expected e WHERE {}"#,
+        EXPECTED, MISMATCH
+    );
+    let found = sqlx::query(&sql).bind(id.to_string()).bind(measure_logical).execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(found.rows_affected())
+}
+
+/// Recomputes the totals of every directory of a scan from its subdirectories
+/// and own files, deepest directories first.
+///
+/// The recorded inconsistencies are marked as repaired.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `id` - The ID of the scan.
+/// * `measure_logical` - Whether the scan measured logical sizes.
+///
+/// # Returns
+///
+/// * `anyhow::Result<u64>` - The number of directories whose totals changed.
+pub async fn repair(pool: &SqlitePool, id: Uuid, measure_logical: bool) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+    let max_depth: Option<i64> =
+        sqlx::query("SELECT MAX(depth) AS d FROM nodes WHERE scan_id=?1 AND is_dir=1")
+            .bind(id.to_string())
+            .fetch_one(&mut *tx)
+            .await?
+            .get("d");
+    let sql = format!(
+        r#"WITH dirs AS (SELECT * FROM nodes WHERE scan_id=?1 AND is_dir=1 AND depth=?3), {}
+        UPDATE nodes SET logical_size = CASE WHEN ?2 THEN e.el ELSE nodes.logical_size END,
+            allocated_size = e.ea, file_count = e.ef, dir_count = e.ed
+        FROM expected e WHERE nodes.id = e.id AND {}"#,
+        EXPECTED, MISMATCH
+    );
+    let mut repaired = 0;
+    // Parents are recomputed from children that are already correct
+    for depth in (0..=max_depth.unwrap_or(-1)).rev() {
+        let res = sqlx::query(&sql)
+            .bind(id.to_string())
+            .bind(measure_logical)
+            .bind(depth)
+            .execute(&mut *tx)
+            .await?;
+        repaired += res.rows_affected();
+    }
+    sqlx::query(
+        "UPDATE scan_inconsistencies SET repaired_at = strftime('%Y-%m-%dT%H:%M:%SZ','now')
+         WHERE scan_id=?1 AND repaired_at IS NULL",
+    )
+    .bind(id.to_string())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_node, insert_scan, test_db, FileRow, NodeRow};
+
+    /// `/r` with the file `/r/f` and the directories `/r/a` and `/r/a/b`, each with one file.
+    async fn fixture() -> (tempfile::TempDir, SqlitePool, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &["/r"], "default").await;
+        for (path, parent, depth, size, files, dirs) in [
+            ("/r", None, 0, 700, 3, 2),
+            ("/r/a", Some("/r"), 1, 600, 2, 1),
+            ("/r/a/b", Some("/r/a"), 2, 400, 1, 0),
+        ] {
+            insert_node(&pool, id, NodeRow { path, parent, depth, size, files, dirs, ..Default::default() })
+                .await;
+        }
+        for (path, parent, size) in
+            [("/r/f", "/r", 100), ("/r/a/g", "/r/a", 200), ("/r/a/b/h", "/r/a/b", 400)]
+        {
+            insert_file(&pool, id, FileRow { path, parent: Some(parent), size, ..Default::default() }).await;
+        }
+        (dir, pool, id)
+    }
+
+    async fn sizes(pool: &SqlitePool, id: Uuid) -> Vec<(String, i64, i64)> {
+        sqlx::query_as("SELECT path, allocated_size, file_count FROM nodes WHERE scan_id=?1 ORDER BY path")
+            .bind(id.to_string())
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn corrupted_totals_are_recorded_and_repaired_bottom_up() {
+        let (_dir, pool, id) = fixture().await;
+        assert_eq!(record(&pool, id, true).await.unwrap(), 0);
+        let consistent = sizes(&pool, id).await;
+
+        // A late summary: the deepest directory misses its file, its parent the
+        // subdirectory's bytes and the root one file
+        sqlx::query("UPDATE nodes SET allocated_size=0, file_count=0 WHERE path='/r/a/b'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE nodes SET allocated_size=200 WHERE path='/r/a'").execute(&pool).await.unwrap();
+        sqlx::query("UPDATE nodes SET file_count=2 WHERE path='/r'").execute(&pool).await.unwrap();
+
+        assert_eq!(record(&pool, id, false).await.unwrap(), 3);
+        let recorded: Vec<(String, i64, i64)> = sqlx::query_as(
+            "SELECT path, allocated_size, expected_allocated_size FROM scan_inconsistencies \
+             WHERE scan_id=?1 ORDER BY depth DESC",
+        )
+        .bind(id.to_string())
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        // The parent is compared with the corrupted child, so it looks right on its own
+        assert_eq!(recorded, [("/r/a/b".into(), 0, 400), ("/r/a".into(), 200, 200), ("/r".into(), 700, 300)]);
+
+        assert_eq!(repair(&pool, id, false).await.unwrap(), 3);
+        assert_eq!(sizes(&pool, id).await, consistent);
+        assert_eq!(record(&pool, id, true).await.unwrap(), 0);
+    }
+}
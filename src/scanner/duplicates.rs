@@ -0,0 +1,423 @@
+//! Duplicate file detection for a finished scan.
+//!
+//! Candidates are taken from the `files` table of the scan: only files whose
+//! logical size is shared by at least one other file (and reaches the minimum
+//! size) can be duplicates. Each size bucket is hashed with BLAKE3 on blocking
+//! workers, bounded by a semaphore like the root workers of [`super::run_scan`],
+//! and files with equal size and hash are stored as one group in the
+//! `duplicates` table. Files that disappeared or changed since the scan are
+//! skipped with a warning.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use sqlx::{QueryBuilder, Row};
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::task;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::types::ScanEvent;
+
+/// The read buffer used when hashing a file.
+const HASH_BUF_SIZE: usize = 256 * 1024;
+/// The number of finished groups written to the database in one statement.
+const GROUP_BATCH: usize = 200;
+/// The minimum interval between two progress events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A summary of a duplicate search.
+#[derive(Debug, Default, Clone)]
+pub struct DuplicateSummary {
+    /// The number of duplicate groups found.
+    pub groups: u64,
+    /// The number of files hashed.
+    pub files_hashed: u64,
+    /// The number of bytes hashed.
+    pub bytes_hashed: u64,
+    /// The bytes that would be freed by keeping one file of every group.
+    pub wasted_bytes: u64,
+    /// The number of files skipped with a warning.
+    pub warnings: u64,
+}
+
+/// A group of identical files waiting to be written.
+struct DuplicateGroup {
+    hash: String,
+    file_size: u64,
+    paths: Vec<String>,
+}
+
+/// The messages sent from the bucket producer and the hash workers to the collector.
+enum HashMsg {
+    /// A size bucket with `count` files was scheduled for hashing.
+    Bucket { size: u64, count: usize },
+    /// A file of the bucket `size` was hashed (`Ok(None)` if the job was cancelled).
+    Hashed { size: u64, path: String, result: std::io::Result<Option<String>> },
+}
+
+/// The hashes collected so far for one size bucket.
+struct PendingBucket {
+    remaining: usize,
+    by_hash: HashMap<String, Vec<String>>,
+}
+
+/// Finds duplicate files among the files of a scan.
+///
+/// Existing duplicate groups of the scan are replaced. Progress is reported as
+/// `HashProgress` events, files that can't be hashed as `Warning` events.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `scan_id` - The ID of the scan whose files are compared.
+/// * `min_size` - Files smaller than this many bytes are ignored.
+/// * `concurrency` - The maximum number of files hashed at the same time.
+/// * `tx` - A broadcast sender for sending progress events.
+/// * `cancel` - A cancellation token for stopping the search.
+///
+/// # Returns
+///
+/// * `anyhow::Result<DuplicateSummary>` - The summary of the search. A cancelled
+///   search returns the groups completed so far.
+pub async fn find_duplicates(
+    pool: sqlx::SqlitePool,
+    scan_id: Uuid,
+    min_size: u64,
+    concurrency: usize,
+    tx: broadcast::Sender<ScanEvent>,
+    cancel: CancellationToken,
+) -> anyhow::Result<DuplicateSummary> {
+    let mut summary = DuplicateSummary::default();
+    let min_size = min_size.max(1).min(i64::MAX as u64) as i64;
+
+    sqlx::query("DELETE FROM duplicates WHERE scan_id=?1").bind(scan_id.to_string()).execute(&pool).await?;
+
+    let buckets: Vec<(i64, i64)> = sqlx::query(
+        r#"SELECT logical_size, COUNT(*) AS cnt FROM files
+           WHERE scan_id=?1 AND logical_size >= ?2
+           GROUP BY logical_size HAVING COUNT(*) > 1
+           ORDER BY logical_size DESC"#,
+    )
+    .bind(scan_id.to_string())
+    .bind(min_size)
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|r| (r.get::<i64, _>("logical_size"), r.get::<i64, _>("cnt")))
+    .collect();
+    let files_total: u64 = buckets.iter().map(|(_, c)| *c as u64).sum();
+    let bytes_total: u64 = buckets.iter().map(|(s, c)| (*s as u64).saturating_mul(*c as u64)).sum();
+
+    let concurrency = concurrency.max(1);
+    let (res_tx, mut res_rx) = mpsc::channel::<HashMsg>(concurrency.saturating_mul(4).max(16));
+    let producer = tokio::spawn(schedule_buckets(
+        pool.clone(),
+        scan_id,
+        buckets,
+        Arc::new(Semaphore::new(concurrency)),
+        res_tx,
+        cancel.clone(),
+    ));
+
+    let mut pending: HashMap<u64, PendingBucket> = HashMap::new();
+    let mut finished: Vec<DuplicateGroup> = Vec::new();
+    let mut last_progress = Instant::now();
+    while let Some(msg) = res_rx.recv().await {
+        match msg {
+            HashMsg::Bucket { size, count } => {
+                pending.insert(size, PendingBucket { remaining: count, by_hash: HashMap::new() });
+                continue;
+            }
+            HashMsg::Hashed { size, path, result } => {
+                match result {
+                    Ok(Some(hash)) => {
+                        summary.files_hashed += 1;
+                        summary.bytes_hashed += size;
+                        if let Some(bucket) = pending.get_mut(&size) {
+                            bucket.by_hash.entry(hash).or_default().push(path.clone());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        summary.warnings += 1;
+                        let (code, message) = match e.kind() {
+                            ErrorKind::NotFound => ("file_missing", "file no longer exists".to_string()),
+                            ErrorKind::InvalidData => ("file_changed", e.to_string()),
+                            _ => ("hash_failed", format!("failed to hash file: {}", e)),
+                        };
+                        let _ = tx.send(ScanEvent::Warning { path: path.clone(), code: code.into(), message });
+                    }
+                }
+                let done = match pending.get_mut(&size) {
+                    Some(bucket) => {
+                        bucket.remaining = bucket.remaining.saturating_sub(1);
+                        bucket.remaining == 0
+                    }
+                    None => false,
+                };
+                if done && !cancel.is_cancelled() {
+                    if let Some(bucket) = pending.remove(&size) {
+                        for (hash, mut paths) in bucket.by_hash {
+                            if paths.len() > 1 {
+                                paths.sort();
+                                finished.push(DuplicateGroup { hash, file_size: size, paths });
+                            }
+                        }
+                    }
+                }
+                if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                    last_progress = Instant::now();
+                    let _ = tx.send(ScanEvent::HashProgress {
+                        current_path: path,
+                        files_hashed: summary.files_hashed,
+                        files_total,
+                        bytes_hashed: summary.bytes_hashed,
+                        bytes_total,
+                    });
+                }
+            }
+        }
+        if finished.len() >= GROUP_BATCH {
+            write_groups(&pool, scan_id, &mut finished, &mut summary).await?;
+        }
+    }
+    write_groups(&pool, scan_id, &mut finished, &mut summary).await?;
+    producer.await.map_err(|e| anyhow::anyhow!("duplicate producer task failed: {}", e))??;
+
+    let _ = tx.send(ScanEvent::HashProgress {
+        current_path: String::new(),
+        files_hashed: summary.files_hashed,
+        files_total,
+        bytes_hashed: summary.bytes_hashed,
+        bytes_total,
+    });
+    Ok(summary)
+}
+
+/// Loads the files of every size bucket and hands them to hash workers.
+///
+/// A `Bucket` message is always sent before the workers of that bucket are
+/// spawned, so the collector knows how many results to wait for.
+async fn schedule_buckets(
+    pool: sqlx::SqlitePool,
+    scan_id: Uuid,
+    buckets: Vec<(i64, i64)>,
+    sem: Arc<Semaphore>,
+    res_tx: mpsc::Sender<HashMsg>,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    for (size, _) in buckets {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let paths: Vec<String> = sqlx::query_scalar("SELECT path FROM files WHERE scan_id=?1 AND logical_size=?2")
+            .bind(scan_id.to_string())
+            .bind(size)
+            .fetch_all(&pool)
+            .await?;
+        let size = size as u64;
+        if res_tx.send(HashMsg::Bucket { size, count: paths.len() }).await.is_err() {
+            break;
+        }
+        for path in paths {
+            let permit = match sem.clone().acquire_owned().await {
+                Ok(p) => p,
+                Err(e) => {
+                    tracing::error!("Semaphore acquisition failed: {}", e);
+                    return Ok(());
+                }
+            };
+            let res_tx = res_tx.clone();
+            let cancel = cancel.clone();
+            task::spawn_blocking(move || {
+                let result = hash_file(Path::new(&path), size, &cancel);
+                drop(permit);
+                let _ = res_tx.blocking_send(HashMsg::Hashed { size, path, result });
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Hashes a file with BLAKE3.
+///
+/// # Arguments
+///
+/// * `path` - The file to hash.
+/// * `expected_size` - The size recorded by the scan; a file of another size
+///   changed since the scan and is rejected with `InvalidData`.
+/// * `cancel` - Checked between reads so large files don't delay cancellation.
+///
+/// # Returns
+///
+/// * `std::io::Result<Option<String>>` - The hex digest, or `None` if cancelled.
+pub(super) fn hash_file(
+    path: &Path,
+    expected_size: u64,
+    cancel: &CancellationToken,
+) -> std::io::Result<Option<String>> {
+    if cancel.is_cancelled() {
+        return Ok(None);
+    }
+    let mut file = fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; HASH_BUF_SIZE];
+    let mut read_total: u64 = 0;
+    loop {
+        if cancel.is_cancelled() {
+            return Ok(None);
+        }
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        read_total += n as u64;
+        if read_total > expected_size {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    if read_total != expected_size {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, "file size changed since the scan"));
+    }
+    Ok(Some(hasher.finalize().to_hex().to_string()))
+}
+
+/// Writes finished groups and adds them to the summary.
+async fn write_groups(
+    pool: &sqlx::SqlitePool,
+    scan_id: Uuid,
+    groups: &mut Vec<DuplicateGroup>,
+    summary: &mut DuplicateSummary,
+) -> anyhow::Result<()> {
+    if groups.is_empty() {
+        return Ok(());
+    }
+    let scan_id = scan_id.to_string();
+    let mut qb: QueryBuilder<sqlx::Sqlite> =
+        QueryBuilder::new("INSERT INTO duplicates (scan_id, hash, file_size, file_count, wasted_bytes, paths) ");
+    let mut rows = Vec::with_capacity(groups.len());
+    for g in groups.drain(..) {
+        let count = g.paths.len() as u64;
+        let wasted = g.file_size.saturating_mul(count - 1);
+        summary.groups += 1;
+        summary.wasted_bytes = summary.wasted_bytes.saturating_add(wasted);
+        rows.push((g.hash, g.file_size as i64, count as i64, wasted.min(i64::MAX as u64) as i64, serde_json::to_string(&g.paths)?));
+    }
+    qb.push_values(rows, |mut b, (hash, size, count, wasted, paths)| {
+        b.push_bind(scan_id.clone())
+            .push_bind(hash)
+            .push_bind(size)
+            .push_bind(count)
+            .push_bind(wasted)
+            .push_bind(paths);
+    });
+    qb.build().execute(pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_scan, test_db_in, FileRow};
+
+    async fn fixture(dir: &Path) -> (sqlx::SqlitePool, Uuid) {
+        let pool = test_db_in(dir).await;
+        let id = insert_scan(&pool, "done", &[], "default").await;
+        (pool, id)
+    }
+
+    async fn add_file(pool: &sqlx::SqlitePool, id: Uuid, path: &Path, size: i64) {
+        insert_file(pool, id, FileRow { path: &path.to_string_lossy(), size, ..Default::default() }).await;
+    }
+
+    async fn write(pool: &sqlx::SqlitePool, id: Uuid, path: &Path, content: &[u8]) {
+        fs::write(path, content).unwrap();
+        add_file(pool, id, path, content.len() as i64).await;
+    }
+
+    #[tokio::test]
+    async fn groups_identical_files_and_skips_missing_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pool, id) = fixture(dir.path()).await;
+        let root = dir.path();
+        write(&pool, id, &root.join("a1"), &[1u8; 4096]).await;
+        write(&pool, id, &root.join("a2"), &[1u8; 4096]).await;
+        write(&pool, id, &root.join("a3"), &[1u8; 4096]).await;
+        // Same size, different content
+        write(&pool, id, &root.join("b"), &[2u8; 4096]).await;
+        write(&pool, id, &root.join("c1"), &[3u8; 2048]).await;
+        write(&pool, id, &root.join("c2"), &[3u8; 2048]).await;
+        // Unique size: never hashed
+        write(&pool, id, &root.join("d"), &[4u8; 1000]).await;
+        // Below the minimum size
+        write(&pool, id, &root.join("e1"), &[5u8; 10]).await;
+        write(&pool, id, &root.join("e2"), &[5u8; 10]).await;
+        // Recorded by the scan but deleted since
+        add_file(&pool, id, &root.join("gone"), 2048).await;
+
+        let (tx, mut rx) = broadcast::channel(64);
+        let summary = find_duplicates(pool.clone(), id, 100, 2, tx, CancellationToken::new()).await.unwrap();
+        assert_eq!(summary.groups, 2);
+        assert_eq!(summary.files_hashed, 6);
+        assert_eq!(summary.wasted_bytes, 2 * 4096 + 2048);
+        assert_eq!(summary.warnings, 1);
+
+        let rows = sqlx::query("SELECT file_size, file_count, wasted_bytes, paths FROM duplicates WHERE scan_id=?1 ORDER BY wasted_bytes DESC")
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get::<i64, _>("file_count"), 3);
+        assert_eq!(rows[0].get::<i64, _>("wasted_bytes"), 2 * 4096);
+        let paths: Vec<String> = serde_json::from_str(&rows[1].get::<String, _>("paths")).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|p| p.ends_with("c1") || p.ends_with("c2")));
+
+        let mut missing = false;
+        while let Ok(ev) = rx.try_recv() {
+            if let ScanEvent::Warning { code, path, .. } = ev {
+                missing |= code == "file_missing" && path.ends_with("gone");
+            }
+        }
+        assert!(missing);
+    }
+
+    #[tokio::test]
+    async fn rerun_replaces_previous_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pool, id) = fixture(dir.path()).await;
+        write(&pool, id, &dir.path().join("x1"), b"same content").await;
+        write(&pool, id, &dir.path().join("x2"), b"same content").await;
+
+        for _ in 0..2 {
+            let (tx, _rx) = broadcast::channel(16);
+            find_duplicates(pool.clone(), id, 1, 4, tx, CancellationToken::new()).await.unwrap();
+        }
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM duplicates WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn changed_file_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f");
+        fs::write(&path, b"abcdef").unwrap();
+        let cancel = CancellationToken::new();
+        assert!(hash_file(&path, 6, &cancel).unwrap().is_some());
+        assert_eq!(hash_file(&path, 5, &cancel).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(hash_file(&path, 7, &cancel).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
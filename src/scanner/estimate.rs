@@ -0,0 +1,474 @@
+//! Quick size estimates for scans that have not run yet.
+//!
+//! Each root is walked level by level. The first [`FULL_LEVELS`] levels are
+//! listed completely; on deeper levels only a random subset of up to
+//! [`SAMPLE_DIRS_PER_LEVEL`] directories is listed, and every sampled
+//! directory stands in for `level size / sample size` directories of its level.
+//! The children of a sampled directory inherit that weight, so the counts of
+//! the next level are scaled the same way. Sums of this kind are unbiased and
+//! their variance follows from the spread of the sampled counts, which gives
+//! the confidence band.
+//!
+//! Sampling stops when the time budget runs out. The level that was cut off
+//! counts as a smaller sample; directories that were found but never listed
+//! get the per-directory averages of the last listed level, and the band of
+//! such an incomplete estimate is widened.
+//!
+//! The same exclude, include, hidden, reparse point and `max_depth` rules as
+//! [`scan_dir`](super::scan_dir) decide what is counted. Nothing is written to
+//! the database.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use globset::GlobSet;
+
+use super::{
+    build_globset, is_hidden_or_system, is_network_path, is_reparse_point, matches_excludes, matches_includes,
+};
+use crate::types::{EstimateRange, RootEstimate, ScanEstimate, ScanOptions};
+
+/// The number of levels, the root's listing included, that are listed completely.
+pub const FULL_LEVELS: u32 = 2;
+/// The number of directories listed per level below [`FULL_LEVELS`].
+pub const SAMPLE_DIRS_PER_LEVEL: usize = 256;
+/// The most directories kept as candidates for the next level; larger levels
+/// are thinned out uniformly and their weights raised accordingly.
+const FRONTIER_CAP: usize = 100_000;
+/// How many entries are read between two checks of the deadline.
+const DEADLINE_CHECK_EVERY: u64 = 4096;
+
+/// A small splitmix64 generator; sampling needs no cryptographic quality.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform index below `n`, which must not be 0.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// The filters of a scan, compiled once for all roots.
+struct Rules<'a> {
+    options: &'a ScanOptions,
+    excludes: GlobSet,
+    includes: GlobSet,
+}
+
+/// The counts of one directory listing.
+struct Listing {
+    files: u64,
+    subdirs: Vec<PathBuf>,
+}
+
+/// The estimate for one root, before it is turned into a [`RootEstimate`].
+#[derive(Default)]
+struct RootSample {
+    dirs: f64,
+    files: f64,
+    dirs_var: f64,
+    files_var: f64,
+    counted_dirs: u64,
+    counted_files: u64,
+    sampled: u64,
+    unreadable: u64,
+    complete: bool,
+}
+
+/// Estimates how many directories and files a scan of `roots` would find.
+///
+/// This blocks on filesystem calls; run it on a blocking thread.
+///
+/// # Arguments
+///
+/// * `roots` - The normalized root paths.
+/// * `options` - The resolved scan options.
+/// * `budget` - How long sampling may take for all roots together.
+/// * `seed` - Seeds the choice of sampled directories.
+///
+/// # Returns
+///
+/// * `anyhow::Result<ScanEstimate>` - The estimate; fails only if a glob pattern does not compile.
+pub fn estimate_roots(
+    roots: &[String],
+    options: &ScanOptions,
+    budget: Duration,
+    seed: u64,
+) -> anyhow::Result<ScanEstimate> {
+    let start = Instant::now();
+    let rules = Rules {
+        options,
+        excludes: build_globset(&options.excludes)?,
+        includes: build_globset(&options.includes)?,
+    };
+    let mut rng = SplitMix(seed);
+    let mut total = RootSample { complete: true, ..Default::default() };
+    let mut root_estimates = Vec::with_capacity(roots.len());
+
+    for (i, root) in roots.iter().enumerate() {
+        // Time left over by earlier roots goes to the later ones
+        let deadline = start + budget.mul_f64((i + 1) as f64 / roots.len() as f64);
+        let root_path = Path::new(root);
+        let (problem, sample) = match root_problem(root_path, &rules) {
+            Some(problem) => (Some(problem), RootSample { complete: true, ..Default::default() }),
+            None => match fs::read_dir(root_path) {
+                Err(e) => (
+                    Some(io_problem(&e, "read_dir_failed")),
+                    RootSample { complete: true, ..Default::default() },
+                ),
+                Ok(_) => (None, sample_root(root_path, &rules, deadline, &mut rng)),
+            },
+        };
+        total.dirs += sample.dirs;
+        total.files += sample.files;
+        total.dirs_var += sample.dirs_var;
+        total.files_var += sample.files_var;
+        total.counted_dirs += sample.counted_dirs;
+        total.counted_files += sample.counted_files;
+        total.sampled += sample.sampled;
+        total.complete &= sample.complete;
+        root_estimates.push(RootEstimate {
+            path: root.clone(),
+            problem,
+            dirs: sample.dirs.round() as u64,
+            files: sample.files.round() as u64,
+            unreadable_dirs: sample.unreadable,
+        });
+    }
+
+    Ok(ScanEstimate {
+        dirs: band(total.dirs, total.dirs_var, total.counted_dirs, total.complete),
+        files: band(total.files, total.files_var, total.counted_files, total.complete),
+        sampled_dirs: total.sampled,
+        complete: total.complete,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        roots: root_estimates,
+        warnings: Vec::new(),
+    })
+}
+
+/// Why a root cannot be scanned, using the checks the scanner applies to roots.
+fn root_problem(root: &Path, rules: &Rules) -> Option<String> {
+    let meta = match fs::metadata(root) {
+        Ok(m) => m,
+        Err(e) => return Some(io_problem(&e, "metadata_failed")),
+    };
+    if !meta.is_dir() {
+        return Some("not_a_directory".into());
+    }
+    let skipped_reparse = !rules.options.follow_symlinks && is_reparse_point(&meta) && !is_network_path(root);
+    let hidden = !rules.options.include_hidden && is_hidden_or_system(root, &meta);
+    if matches_excludes(root, &rules.excludes) || skipped_reparse || hidden {
+        return Some("excluded".into());
+    }
+    None
+}
+
+/// Maps an I/O error to a problem code, `fallback` for unexpected kinds.
+fn io_problem(e: &std::io::Error, fallback: &str) -> String {
+    match e.kind() {
+        ErrorKind::NotFound => "missing_root".into(),
+        ErrorKind::PermissionDenied => "access_denied".into(),
+        _ => fallback.into(),
+    }
+}
+
+/// Samples the tree below one root until it is exhausted or `deadline` passes.
+fn sample_root(root: &Path, rules: &Rules, deadline: Instant, rng: &mut SplitMix) -> RootSample {
+    let mut sample = RootSample { dirs: 1.0, counted_dirs: 1, ..Default::default() };
+    let mut entered = HashSet::new();
+    // Directories of the current level with the number of directories each stands for
+    let mut frontier: Vec<(PathBuf, f64)> = vec![(root.to_path_buf(), 1.0)];
+    // Files and subdirectories per unit of weight on the last listed level
+    let (mut files_rate, mut dirs_rate) = (0.0, 0.0);
+    let mut depth = 0u32;
+
+    while !frontier.is_empty() && Instant::now() < deadline {
+        let n = frontier.len();
+        let want = if depth < FULL_LEVELS { n } else { n.min(SAMPLE_DIRS_PER_LEVEL) };
+        // Partial Fisher-Yates: the first `want` entries become a uniform sample
+        for i in 0..want {
+            let j = i + rng.below(n - i);
+            frontier.swap(i, j);
+        }
+
+        let descend = rules.options.max_depth.is_none_or(|max| depth < max);
+        let mut listed: Vec<(f64, u64, Vec<PathBuf>)> = Vec::with_capacity(want);
+        for (dir, weight) in &frontier[..want] {
+            if Instant::now() >= deadline {
+                break;
+            }
+            match list_dir(dir, rules, descend, deadline, &mut entered) {
+                Some(listing) => listed.push((*weight, listing.files, listing.subdirs)),
+                None if Instant::now() >= deadline => break,
+                None => {
+                    sample.unreadable += 1;
+                    listed.push((*weight, 0, Vec::new()));
+                }
+            }
+        }
+        if listed.is_empty() {
+            break;
+        }
+
+        // Every listed directory stands in for n / m directories of its level
+        let m = listed.len();
+        let scale = n as f64 / m as f64;
+        let files: Vec<f64> = listed.iter().map(|(w, f, _)| w * *f as f64).collect();
+        let dirs: Vec<f64> = listed.iter().map(|(w, _, s)| w * s.len() as f64).collect();
+        sample.files += scale * files.iter().sum::<f64>();
+        sample.dirs += scale * dirs.iter().sum::<f64>();
+        sample.files_var += sampling_variance(&files, n);
+        sample.dirs_var += sampling_variance(&dirs, n);
+        sample.counted_files += listed.iter().map(|(_, f, _)| f).sum::<u64>();
+        sample.counted_dirs += listed.iter().map(|(_, _, s)| s.len() as u64).sum::<u64>();
+        sample.sampled += m as u64;
+        let weight: f64 = listed.iter().map(|(w, _, _)| w).sum();
+        files_rate = files.iter().sum::<f64>() / weight;
+        dirs_rate = dirs.iter().sum::<f64>() / weight;
+
+        frontier = next_frontier(listed, scale, rng);
+        depth += 1;
+    }
+
+    sample.complete = frontier.is_empty();
+    if !sample.complete {
+        // Found but never listed: assume they look like the last listed level
+        let pending: f64 = frontier.iter().map(|(_, w)| w).sum();
+        sample.files += pending * files_rate;
+        sample.dirs += pending * dirs_rate;
+    }
+    sample
+}
+
+/// Collects the subdirectories of a level as the next level, thinned out to [`FRONTIER_CAP`].
+fn next_frontier(
+    listed: Vec<(f64, u64, Vec<PathBuf>)>,
+    scale: f64,
+    rng: &mut SplitMix,
+) -> Vec<(PathBuf, f64)> {
+    let mut next: Vec<(PathBuf, f64)> = Vec::new();
+    let mut seen = 0usize;
+    for (weight, _, subdirs) in listed {
+        for sub in subdirs {
+            seen += 1;
+            if next.len() < FRONTIER_CAP {
+                next.push((sub, weight * scale));
+            } else {
+                // Reservoir sampling keeps every subdirectory with the same probability
+                let j = rng.below(seen);
+                if j < FRONTIER_CAP {
+                    next[j] = (sub, weight * scale);
+                }
+            }
+        }
+    }
+    if seen > next.len() {
+        let thin = seen as f64 / next.len() as f64;
+        for (_, w) in &mut next {
+            *w *= thin;
+        }
+    }
+    next
+}
+
+/// The variance of the estimated level total from `values` sampled out of `n` directories.
+fn sampling_variance(values: &[f64], n: usize) -> f64 {
+    let m = values.len();
+    if m < 2 || m >= n {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / m as f64;
+    let s2 = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (m - 1) as f64;
+    let n = n as f64;
+    n * n * (1.0 - m as f64 / n) * s2 / m as f64
+}
+
+/// Turns an estimate and its variance into a band of about two standard deviations.
+fn band(estimate: f64, variance: f64, counted: u64, complete: bool) -> EstimateRange {
+    let spread = 2.0 * variance.sqrt();
+    let estimate = estimate.max(counted as f64);
+    let mut high = estimate + spread;
+    if !complete {
+        // The unlisted levels may be far deeper than the averages suggest
+        high = high.max(2.0 * estimate);
+    }
+    EstimateRange {
+        estimate: estimate.round() as u64,
+        low: (estimate - spread).max(counted as f64).round() as u64,
+        high: high.round() as u64,
+    }
+}
+
+/// Lists one directory with the scanner's filters.
+///
+/// # Returns
+///
+/// * `Option<Listing>` - `None` if the directory cannot be read, was already
+///   entered through a followed link, or `deadline` passed while reading it.
+fn list_dir(
+    dir: &Path,
+    rules: &Rules,
+    descend: bool,
+    deadline: Instant,
+    entered: &mut HashSet<PathBuf>,
+) -> Option<Listing> {
+    let options = rules.options;
+    if options.follow_symlinks {
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !entered.insert(canonical) {
+            return None;
+        }
+    }
+    let mut listing = Listing { files: 0, subdirs: Vec::new() };
+    let mut entries = 0u64;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        entries += 1;
+        if entries.is_multiple_of(DEADLINE_CHECK_EVERY) && Instant::now() >= deadline {
+            return None;
+        }
+        let path = entry.path();
+        if matches_excludes(&path, &rules.excludes) {
+            continue;
+        }
+        let Ok(md) = entry.metadata() else { continue };
+        let md = if md.file_type().is_symlink() {
+            if !options.follow_symlinks {
+                continue;
+            }
+            match fs::metadata(&path) {
+                Ok(target) => target,
+                Err(_) => continue,
+            }
+        } else {
+            md
+        };
+        if !options.include_hidden && is_hidden_or_system(&path, &md) {
+            continue;
+        }
+        if md.is_dir() {
+            if !descend || (is_reparse_point(&md) && !options.follow_symlinks) {
+                continue;
+            }
+            listing.subdirs.push(path);
+        } else if md.is_file() && matches_includes(&path, &rules.includes) {
+            listing.files += 1;
+        }
+    }
+    Some(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ScanOptions {
+        ScanOptions {
+            follow_symlinks: false,
+            include_hidden: true,
+            measure_logical: true,
+            measure_allocated: true,
+            excludes: Vec::new(),
+            includes: Vec::new(),
+            max_depth: None,
+            concurrency: None,
+            measure_hardlinks: false,
+            measure_ads: false,
+            io_priority: Default::default(),
+            capture_owner: false,
+            access_mode: Default::default(),
+            hash_min_size: None,
+        }
+    }
+
+    fn estimate(roots: &[String], options: &ScanOptions) -> ScanEstimate {
+        estimate_roots(roots, options, Duration::from_secs(30), 7).unwrap()
+    }
+
+    #[test]
+    fn small_trees_are_counted_exactly() {
+        let dir = tempfile::tempdir().unwrap();
+        for d in ["a/x/deep", "a/y", "b"] {
+            fs::create_dir_all(dir.path().join(d)).unwrap();
+        }
+        for f in ["top.txt", "a/one.txt", "a/x/deep/two.txt", "a/x/deep/three.log", "b/four.txt"] {
+            fs::write(dir.path().join(f), b"data").unwrap();
+        }
+        let root = dir.path().to_string_lossy().to_string();
+
+        let est = estimate(std::slice::from_ref(&root), &options());
+        assert!(est.complete);
+        assert_eq!(est.dirs, EstimateRange { estimate: 6, low: 6, high: 6 });
+        assert_eq!(est.files, EstimateRange { estimate: 5, low: 5, high: 5 });
+        assert_eq!(est.roots[0].problem, None);
+
+        let filtered =
+            ScanOptions { excludes: vec!["**/x".into()], includes: vec!["*.txt".into()], ..options() };
+        let est = estimate(std::slice::from_ref(&root), &filtered);
+        assert_eq!((est.dirs.estimate, est.files.estimate), (4, 3));
+        let shallow = ScanOptions { max_depth: Some(1), ..options() };
+        assert_eq!(estimate(std::slice::from_ref(&root), &shallow).dirs.estimate, 3);
+    }
+
+    #[test]
+    fn sampled_levels_are_scaled_up() {
+        let dir = tempfile::tempdir().unwrap();
+        // Level 2 holds 600 directories, more than one sample, each with two files
+        for a in 0..3 {
+            for b in 0..200 {
+                let d = dir.path().join(format!("l1-{a}")).join(format!("l2-{b}"));
+                fs::create_dir_all(&d).unwrap();
+                fs::write(d.join("f1"), b"").unwrap();
+                fs::write(d.join("f2"), b"").unwrap();
+            }
+        }
+        let est = estimate(&[dir.path().to_string_lossy().to_string()], &options());
+        assert!(est.complete);
+        assert_eq!(est.sampled_dirs, 1 + 3 + SAMPLE_DIRS_PER_LEVEL as u64);
+        // Every sampled directory has the same contents, so the estimate is exact
+        assert_eq!(est.dirs.estimate, 604);
+        assert_eq!(est.files.estimate, 1200);
+        assert!(est.files.low <= 1200 && est.files.high >= 1200);
+    }
+
+    #[test]
+    fn problematic_roots_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        fs::write(&file, b"x").unwrap();
+        fs::create_dir(dir.path().join("skip")).unwrap();
+        let roots = [
+            dir.path().join("missing").to_string_lossy().to_string(),
+            file.to_string_lossy().to_string(),
+            dir.path().join("skip").to_string_lossy().to_string(),
+        ];
+        let excluded = ScanOptions { excludes: vec!["skip".into()], ..options() };
+        let est = estimate(&roots, &excluded);
+        let problems: Vec<_> = est.roots.iter().map(|r| r.problem.as_deref()).collect();
+        assert_eq!(problems, [Some("missing_root"), Some("not_a_directory"), Some("excluded")]);
+        assert_eq!(est.dirs.estimate, 0);
+    }
+
+    #[test]
+    fn an_exhausted_budget_leaves_the_estimate_incomplete() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        let est = estimate_roots(&[dir.path().to_string_lossy().to_string()], &options(), Duration::ZERO, 1)
+            .unwrap();
+        assert!(!est.complete);
+        assert_eq!(est.sampled_dirs, 0);
+        assert_eq!(est.dirs.estimate, 1);
+        assert!(est.dirs.high >= 2);
+    }
+}
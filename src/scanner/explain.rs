@@ -0,0 +1,210 @@
+//! Explanations of why a scan takes in a path or leaves it out.
+//!
+//! The path is checked against the live filesystem with the stored options of
+//! the scan, using the same [`check_listed`] and [`check_entry`] rules as
+//! [`run_scan`](super::run_scan). The directories between the root and the
+//! path are checked as well, because a directory the scanner leaves out keeps
+//! everything below it out. Nothing is written to the database.
+
+use std::fs;
+use std::path::Path;
+
+use globset::GlobSet;
+
+use super::{build_globset, check_entry, check_listed, matches_excludes};
+use crate::types::{PathCheck, PathExplanation, ScanOptions};
+
+/// Explains whether a scan with `options` takes in `path`, which lies in `root`.
+///
+/// # Arguments
+///
+/// * `scan_id` - The ID of the scan, returned in the explanation.
+/// * `root` - The root of the scan that contains `path`.
+/// * `path` - The path to explain.
+/// * `options` - The options the scan was started with.
+///
+/// # Returns
+///
+/// The checks applied to the path, or an error if the stored patterns are invalid.
+pub fn explain_path(
+    scan_id: uuid::Uuid,
+    root: &str,
+    path: &str,
+    options: &ScanOptions,
+) -> anyhow::Result<PathExplanation> {
+    let excludes = build_globset(&options.excludes)?;
+    let includes = build_globset(&options.includes)?;
+    let target = Path::new(path);
+    let depth = target.components().count().saturating_sub(Path::new(root).components().count());
+
+    // The root first, the path itself last
+    let mut chain: Vec<&Path> = target.ancestors().take(depth + 1).collect();
+    chain.reverse();
+    let skipped_ancestor = chain[..depth]
+        .iter()
+        .enumerate()
+        .find(|(d, dir)| !checks_at(dir, *d as u32, options, &excludes, &includes).0.iter().all(|c| c.passed))
+        .map(|(_, dir)| dir.to_string_lossy().to_string());
+    let (checks, is_dir) = checks_at(target, depth as u32, options, &excludes, &includes);
+
+    Ok(PathExplanation {
+        scan_id,
+        path: path.to_string(),
+        root: Some(root.to_string()),
+        depth: Some(depth as u32),
+        is_dir,
+        included: skipped_ancestor.is_none() && checks.iter().all(|c| c.passed),
+        skipped_ancestor,
+        checks,
+    })
+}
+
+fn check(name: &str, passed: bool, detail: Option<String>) -> PathCheck {
+    PathCheck { check: name.into(), passed, pattern: None, detail }
+}
+
+/// Applies the scanner's checks to a path at `depth` below its root, in the
+/// scanner's order, and returns them with whether the path is a directory.
+///
+/// Checking stops at the first check that needs metadata which cannot be read.
+fn checks_at(
+    path: &Path,
+    depth: u32,
+    options: &ScanOptions,
+    excludes: &GlobSet,
+    includes: &GlobSet,
+) -> (Vec<PathCheck>, bool) {
+    let excluded = matches_excludes(path, excludes);
+    let pattern = if excluded { matching_pattern(path, &options.excludes) } else { None };
+    let mut checks = vec![PathCheck { check: "excluded".into(), passed: !excluded, pattern, detail: None }];
+
+    // Roots are read through links, entries found while listing a directory are not
+    let md = if depth == 0 { fs::metadata(path) } else { fs::symlink_metadata(path) };
+    let md = match md {
+        Ok(md) => md,
+        Err(e) => {
+            checks.push(check("exists", false, Some(e.to_string())));
+            return (checks, false);
+        }
+    };
+    checks.push(check("exists", true, None));
+    let md = if md.file_type().is_symlink() {
+        let target = if options.follow_symlinks { fs::metadata(path).ok() } else { None };
+        let detail = match (&target, options.follow_symlinks) {
+            (Some(_), _) => "followed with follow_symlinks",
+            (None, true) => "the link target cannot be read",
+            (None, false) => "links are only followed with follow_symlinks",
+        };
+        checks.push(check("symlink", target.is_some(), Some(detail.into())));
+        match target {
+            Some(md) => md,
+            None => return (checks, false),
+        }
+    } else {
+        md
+    };
+
+    let entry = match depth.checked_sub(1) {
+        Some(parent_depth) => check_entry(path, &md, options, includes, parent_depth),
+        None => check_listed(path, &md, options, true),
+    };
+    if md.is_dir() {
+        let detail = match (entry.reparse_point, entry.network_share, entry.followed) {
+            (false, _, _) => None,
+            (true, true, _) => Some("followed as a network share"),
+            (true, false, true) => Some("followed with follow_symlinks"),
+            (true, false, false) => Some("reparse points are only followed with follow_symlinks"),
+        };
+        checks.push(check("reparse_point", !entry.reparse_point || entry.followed, detail.map(Into::into)));
+    }
+    let detail =
+        entry.hidden.then(|| "hidden and system entries are only scanned with include_hidden".into());
+    checks.push(check("hidden", !entry.hidden, detail));
+    if let (true, Some(max_d)) = (md.is_dir() && depth > 0, options.max_depth) {
+        checks.push(check(
+            "max_depth",
+            !entry.too_deep,
+            Some(format!("depth {}, max_depth {}", depth, max_d)),
+        ));
+    }
+    if md.is_file() && !options.includes.is_empty() {
+        let detail = entry.not_included.then(|| "matches none of the include patterns".into());
+        checks.push(check("included", !entry.not_included, detail));
+    }
+    (checks, md.is_dir())
+}
+
+/// Returns the first exclude pattern that matches `path` on its own.
+fn matching_pattern(path: &Path, patterns: &[String]) -> Option<String> {
+    patterns
+        .iter()
+        .find(|p| build_globset(std::slice::from_ref(p)).is_ok_and(|set| matches_excludes(path, &set)))
+        .cloned()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn explain(root: &Path, path: &Path, options: &ScanOptions) -> PathExplanation {
+        let root = root.to_string_lossy();
+        explain_path(uuid::Uuid::nil(), &root, &path.to_string_lossy(), options).unwrap()
+    }
+
+    fn failed(e: &PathExplanation) -> Vec<&str> {
+        e.checks.iter().filter(|c| !c.passed).map(|c| c.check.as_str()).collect()
+    }
+
+    #[test]
+    fn explains_excluded_hidden_and_too_deep_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        // The temporary directory itself is hidden
+        let root = &tmp.path().join("root");
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("node_modules/index.js"), b"x").unwrap();
+        fs::create_dir(root.join(".cache")).unwrap();
+        fs::write(root.join("a/file.txt"), b"x").unwrap();
+        let options = ScanOptions {
+            excludes: vec!["*.tmp".into(), "**/node_modules".into()],
+            max_depth: Some(1),
+            include_hidden: false,
+            ..Default::default()
+        };
+
+        let e = explain(root, &root.join("a/file.txt"), &options);
+        assert!(e.included, "{:?}", e);
+        assert_eq!(e.depth, Some(2));
+        assert!(!e.is_dir);
+
+        let e = explain(root, &root.join("node_modules"), &options);
+        assert!(!e.included);
+        assert_eq!(failed(&e), ["excluded"]);
+        assert_eq!(e.checks[0].pattern.as_deref(), Some("**/node_modules"));
+        // Below an excluded directory the path itself passes, its ancestor does not
+        let e = explain(root, &root.join("node_modules/index.js"), &options);
+        assert!(!e.included);
+        assert!(failed(&e).is_empty());
+        assert_eq!(e.skipped_ancestor, Some(root.join("node_modules").to_string_lossy().to_string()));
+
+        let e = explain(root, &root.join(".cache"), &options);
+        assert!(!e.included);
+        assert_eq!(failed(&e), ["hidden"]);
+        let e = explain(root, &root.join(".cache"), &ScanOptions { include_hidden: true, ..options.clone() });
+        assert!(e.included);
+
+        let e = explain(root, &root.join("a"), &options);
+        assert!(e.included);
+        let e = explain(root, &root.join("a/b"), &options);
+        assert!(!e.included);
+        assert_eq!(failed(&e), ["max_depth"]);
+        let depth = e.checks.iter().find(|c| c.check == "max_depth").unwrap();
+        assert_eq!(depth.detail.as_deref(), Some("depth 2, max_depth 1"));
+        let e = explain(root, &root.join("a/b/c"), &options);
+        assert_eq!(e.skipped_ancestor, Some(root.join("a/b").to_string_lossy().to_string()));
+
+        let e = explain(root, &root.join("missing"), &options);
+        assert!(!e.included);
+        assert_eq!(failed(&e), ["exists"]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Content hashes of large files for scans with `hash_min_size`.
+//!
+//! The workers hash the files at or above the threshold while they list
+//! directories. Reading a big file takes far longer than a listing, so the
+//! workers share a [`HashGate`] with fewer permits than there are workers:
+//! the others keep traversing while files are hashed. Like [`PauseGate`]
+//! the gate is a mutex and a condition variable, as the workers are
+//! blocking threads.
+//!
+//! [`PauseGate`]: super::pause::PauseGate
+
+use std::io;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+
+use tokio_util::sync::CancellationToken;
+
+use super::duplicates;
+use super::pause::CANCEL_POLL;
+
+/// Limits how many workers of a scan hash files at the same time.
+#[derive(Debug)]
+pub struct HashGate {
+    free: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A permit of a [`HashGate`], returned when dropped.
+pub struct HashPermit<'a> {
+    gate: &'a HashGate,
+}
+
+impl HashGate {
+    /// Creates a gate for a scan with `workers` workers; half of them may hash at once.
+    pub fn for_workers(workers: usize) -> Self {
+        Self::new((workers / 2).max(1))
+    }
+
+    /// Creates a gate with `permits` permits.
+    pub fn new(permits: usize) -> Self {
+        Self { free: Mutex::new(permits.max(1)), released: Condvar::new() }
+    }
+
+    /// Blocks the calling thread until a permit is free.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - The cancellation token of the scan; cancelling ends the wait.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<HashPermit>` - The permit, or `None` if the scan was cancelled.
+    pub fn acquire(&self, cancel: &CancellationToken) -> Option<HashPermit<'_>> {
+        let mut free = self.lock();
+        while *free == 0 {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            free = match self.released.wait_timeout(free, CANCEL_POLL) {
+                Ok((guard, _)) => guard,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        *free -= 1;
+        Some(HashPermit { gate: self })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, usize> {
+        // A worker panicking while hashing must not wedge the scan
+        self.free.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for HashPermit<'_> {
+    fn drop(&mut self) {
+        *self.gate.lock() += 1;
+        self.gate.released.notify_one();
+    }
+}
+
+/// Hashes the content of a file with BLAKE3 once the gate lets it.
+///
+/// # Arguments
+///
+/// * `gate` - The gate of the scan.
+/// * `path` - The file to hash.
+/// * `size` - The size the scan recorded; a file that grows or shrinks while it
+///   is read fails with `InvalidData`.
+/// * `cancel` - The cancellation token of the scan, checked between reads.
+///
+/// # Returns
+///
+/// * `io::Result<Option<String>>` - The hex digest, or `None` if the scan was cancelled.
+pub fn hash_file(
+    gate: &HashGate,
+    path: &Path,
+    size: u64,
+    cancel: &CancellationToken,
+) -> io::Result<Option<String>> {
+    let Some(_permit) = gate.acquire(cancel) else { return Ok(None) };
+    duplicates::hash_file(path, size, cancel)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn the_gate_bounds_concurrent_hashing() {
+        let gate = Arc::new(HashGate::for_workers(5));
+        let (active, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let threads: Vec<_> = (0..6)
+            .map(|_| {
+                let (gate, active, peak) = (gate.clone(), active.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    let _permit = gate.acquire(&CancellationToken::new()).unwrap();
+                    peak.fetch_max(active.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        let held = gate.acquire(&CancellationToken::new()).unwrap();
+        let _second = gate.acquire(&CancellationToken::new()).unwrap();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(gate.acquire(&cancel).is_none());
+        drop(held);
+        assert!(gate.acquire(&cancel).is_some());
+    }
+
+    #[test]
+    fn hashes_match_blake3_and_changed_sizes_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        let gate = HashGate::new(1);
+        let hash = hash_file(&gate, &path, 3, &CancellationToken::new()).unwrap();
+        assert_eq!(hash.as_deref(), Some(blake3::hash(b"abc").to_hex().as_str()));
+        let err = hash_file(&gate, &path, 4, &CancellationToken::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = hash_file(&gate, &dir.path().join("gone.bin"), 3, &CancellationToken::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
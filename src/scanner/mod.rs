@@ -8,15 +8,41 @@ use std::{
 use std::os::windows::fs::MetadataExt;
 
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use sqlx::QueryBuilder;
+use sqlx::{QueryBuilder, Row};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Semaphore};
+use tokio::sync::mpsc;
 use tokio::task;
 use tokio::time::{interval, Duration};
 use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 use uuid::Uuid;
 
-use crate::types::{ScanEvent, ScanOptions};
+use crate::config::CategoriesConfig;
+use crate::metrics::{GaugeShare, Metrics};
+use crate::types::{AccessMode, RootSummary, ScanEvent, ScanOptions};
+use budget::BufferMeter;
+use category::CategoryMap;
+use fingerprint::DirFingerprint;
+use hashing::HashGate;
+use owner::OwnerLookup;
+use pause::PauseGate;
+
+pub mod backup;
+pub mod budget;
+pub mod category;
+pub mod consistency;
+pub mod duplicates;
+pub mod estimate;
+pub mod explain;
+pub mod fingerprint;
+pub mod hashing;
+pub mod owner;
+pub mod pause;
+pub mod preview;
+pub mod priority;
+pub mod simulate;
+pub mod watch;
 
 /// A summary of the results of a scan.
 #[derive(Debug, Default, Clone)]
@@ -29,12 +55,48 @@ pub struct ScanResultSummary {
     pub total_logical_size: u64,
     /// The total allocated size of all files scanned.
     pub total_allocated_size: u64,
+    /// The allocated bytes skipped because another link to the same file was already counted.
+    pub dedup_saved_bytes: u64,
+    /// The number of zero-byte files scanned.
+    pub empty_files: u64,
     /// The number of warnings generated during the scan.
     pub warnings: u64,
     /// The most recent modification time of any file or directory scanned.
     pub latest_mtime: Option<i64>,
     /// The most recent access time of any file or directory scanned.
     pub latest_atime: Option<i64>,
+    /// The subtotals per root, in the order of the scan request.
+    ///
+    /// Only filled in the summary returned by [`run_scan`].
+    pub roots: Vec<RootSummary>,
+    /// Whether the scan ran with backup privileges, see [`backup`].
+    pub backup_mode: bool,
+    /// The most bytes the scan's buffers held at the same time, see [`budget`].
+    ///
+    /// Only filled in the summary returned by [`run_scan`].
+    pub buffer_peak_bytes: u64,
+}
+
+/// Records sent to the aggregator, tagged with the index of the root they belong to.
+///
+/// The paths are the directories a rescan took over, whose files are copied
+/// from the earlier scan (see [`copy_unchanged_files`]).
+type ScanBatch = (usize, Vec<NodeRecord>, Vec<FileRecord>, Vec<String>, ScanResultSummary);
+
+/// The stored rows a scan takes over instead of traversing directories again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reuse {
+    /// Every directory is traversed.
+    #[default]
+    Nothing,
+    /// The scan continues an earlier run of the same ID. Every directory below
+    /// a root whose node row already exists is taken over with its stored
+    /// totals instead of being traversed again (see [`PersistedDir`]).
+    Resume,
+    /// The scan repeats the finished scan with this ID. Directories whose
+    /// modification time did not change since get their files copied from it
+    /// instead of being listed (see [`PreviousDir`]).
+    Rescan(Uuid),
 }
 
 /// A record of a scanned node (file or directory).
@@ -60,16 +122,60 @@ pub struct NodeRecord {
     pub mtime: Option<i64>,
     /// The access time of the node.
     pub atime: Option<i64>,
+    /// The fingerprint of the node's direct children, `None` if the listing was incomplete.
+    pub fingerprint: Option<i64>,
+    /// The number of zero-byte files directly inside the directory.
+    pub empty_file_count: u64,
+    /// The exact path if `path` is a lossy rendering of it, see [`raw_path_bytes`].
+    pub path_raw: Option<Vec<u8>>,
 }
 
+/// A record of a scanned file.
 #[derive(Debug, Clone)]
-struct FileRecord {
+pub(crate) struct FileRecord {
     path: String,
     parent_path: Option<String>,
     logical_size: u64,
     allocated_size: u64,
     mtime: Option<i64>,
     atime: Option<i64>,
+    /// (count, bytes) of the alternate data streams, `None` if not measured.
+    ads: Option<(u64, u64)>,
+    /// The owner of the file, `None` unless the scan captures owners.
+    owner: Option<String>,
+    /// The category of the file, `None` if classification is disabled.
+    category: Option<Arc<str>>,
+    /// The BLAKE3 hash of the content, `None` below `hash_min_size` or if hashing failed.
+    hash: Option<String>,
+    /// The exact path if `path` is a lossy rendering of it.
+    path_raw: Option<Vec<u8>>,
+}
+
+impl FileRecord {
+    /// A file record of an imported scan; exports carry no streams, owners or hashes.
+    pub(crate) fn imported(
+        path: String,
+        parent_path: Option<String>,
+        logical_size: u64,
+        allocated_size: u64,
+        mtime: Option<i64>,
+        category: Option<Arc<str>>,
+        path_raw: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            path,
+            parent_path,
+            logical_size,
+            allocated_size,
+            mtime,
+            atime: None,
+            ads: None,
+            owner: None,
+            category,
+            hash: None,
+            path_raw,
+        }
+    }
 }
 
 fn system_time_to_secs(st: Option<SystemTime>) -> Option<i64> {
@@ -87,10 +193,13 @@ fn max_opt(a: Option<i64>, b: Option<i64>) -> Option<i64> {
 
 /// Runs a directory scan and persists the results to the database.
 ///
-/// This is the main entry point for the scanning process. It spawgns a pool of
-/// worker threads to traverse the directory tree and collect file and directory
-/// information. The results are then collected and inserted into the database in
-/// batches.
+/// This is the main entry point for the scanning process. It spawns a pool of
+/// blocking workers that take directories of all roots from one queue (see
+/// [`DirQueue`]) and collect file and directory information. The results are
+/// then collected and inserted into the database in batches.
+///
+/// The scan runs in a `run_scan` span with the scan ID as `scan_id` field,
+/// which the workers enter as well, so every log record of the scan carries it.
 ///
 /// # Arguments
 ///
@@ -100,16 +209,33 @@ fn max_opt(a: Option<i64>, b: Option<i64>) -> Option<i64> {
 /// * `options` - The scan options.
 /// * `tx` - A broadcast sender for sending scan events.
 /// * `cancel` - A cancellation token for stopping the scan.
+/// * `pause` - Holds the workers before their next directory while the scan is paused.
 /// * `batch_size` - The number of records to insert in a single database transaction.
 /// * `flush_threshold` - The number of pending records that triggers a flush to the database.
+/// * `max_buffer_bytes` - The estimated bytes of pending records that trigger a
+///   flush, whichever of the two limits is reached first; see [`budget`].
 /// * `flush_interval_ms` - The interval in milliseconds at which to flush pending records.
-/// * `handle_limit` - The maximum number of open file handles.
-/// * `dir_concurrency` - The number of concurrent directory traversers.
+/// * `handle_limit` - The maximum number of open file handles; caps the number
+///   of workers.
+/// * `dir_concurrency` - The number of workers if `options.concurrency` is not
+///   set. The workers are shared by all roots of the scan.
+/// * `max_entries_per_dir` - If set, entries of a directory beyond this count are
+///   skipped and reported as a `dir_entry_limit` warning.
+/// * `persist_retry` - How writes are retried while another connection holds the
+///   database lock.
+/// * `reuse` - The stored rows the scan takes over, see [`Reuse`]. A rescan
+///   whose options differ from those of the earlier scan traverses everything
+///   and says so in a `rescan_options_changed` warning.
+/// * `metrics` - The metrics whose scanner gauges (running scans, active workers,
+///   queued batches, buffered records and bytes) the scan contributes to while it runs.
+/// * `categories` - The categories files are classified into by extension,
+///   resolved once for the whole scan.
 ///
 /// # Returns
 ///
 /// * `anyhow::Result<ScanResultSummary>` - The summary of the scan results.
 #[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip_all, fields(scan_id = %id))]
 pub async fn run_scan(
     pool: sqlx::SqlitePool,
     id: Uuid,
@@ -117,13 +243,30 @@ pub async fn run_scan(
     options: ScanOptions,
     tx: tokio::sync::broadcast::Sender<ScanEvent>,
     cancel: CancellationToken,
+    pause: Arc<PauseGate>,
     batch_size: usize,
     flush_threshold: usize,
+    max_buffer_bytes: usize,
     flush_interval_ms: u64,
     handle_limit: Option<usize>,
     dir_concurrency: Option<usize>,
+    max_entries_per_dir: Option<u64>,
+    persist_retry: PersistRetry,
+    reuse: Reuse,
+    metrics: &Metrics,
+    categories: &CategoriesConfig,
 ) -> anyhow::Result<ScanResultSummary> {
+    let _running = GaugeShare::one(&metrics.scans_running);
+    let mut queue_depth = GaugeShare::new(&metrics.scanner_queue_depth);
+    let mut buffered = GaugeShare::new(&metrics.scanner_buffered_records);
+    let meter = Arc::new(BufferMeter::new(max_buffer_bytes, &metrics.scanner_buffered_bytes));
+    // The estimated bytes of `nodes`, `files` and `copied`
+    let mut buffered_bytes = 0;
     let mut summary = ScanResultSummary::default();
+    // Warnings are sent by many blocking workers; collect their details from the event channel
+    let mut warn_rx = tx.subscribe();
+    let mut warn_rx_open = true;
+    let mut warnings = WarningBuffer::default();
     // Limit capacity to prevent excessive memory allocation
     let safe_capacity = flush_threshold.max(batch_size).saturating_mul(2).min(50_000);
     let mut nodes: Vec<NodeRecord> = Vec::with_capacity(safe_capacity);
@@ -139,12 +282,12 @@ pub async fn run_scan(
     } else {
         1
     };
-    // FIX Bug #27 - Better channel size calculation with overflow protection
-    let mut concurrency = options.concurrency.unwrap_or(optimal_workers).max(1);
+    // One budget for the whole scan: the worker pool is shared by all roots
+    let mut concurrency = options.concurrency.or(dir_concurrency).unwrap_or(optimal_workers).max(1);
     if let Some(h) = handle_limit {
         concurrency = concurrency.min(h.max(1));
     }
-    let sem = Arc::new(Semaphore::new(concurrency));
+    // FIX Bug #27 - Better channel size calculation with overflow protection
     // Channel buffer size: ensure it's large enough but bounded
     // FIX Bug #7: Log warning when overflow occurs
     let channel_size = match concurrency.checked_mul(8).and_then(|v| v.checked_add(128)) {
@@ -155,15 +298,96 @@ pub async fn run_scan(
         }
     };
     let (tx_res, mut rx_res) =
-        mpsc::channel::<(Vec<NodeRecord>, Vec<FileRecord>, ScanResultSummary)>(channel_size);
+        mpsc::channel::<ScanBatch>(channel_size);
+    // One seen-set per scan, shared by all roots so links spanning roots are counted once too
+    let hardlinks = options.measure_hardlinks.then(HardlinkSet::default);
+    let owners = options.capture_owner.then(|| Arc::new(OwnerLookup::default()));
+    let links = Arc::new(LinkTracker::new(&root_paths, options.follow_symlinks));
+    let persisted = match reuse {
+        Reuse::Resume => Some(load_persisted_dirs(&pool, id, &root_paths).await?),
+        _ => None,
+    };
+    let previous = match reuse {
+        Reuse::Rescan(previous) => {
+            let dirs = load_previous_dirs(&pool, previous, &options).await?;
+            if dirs.is_none() {
+                summary.warnings += 1;
+                let _ = tx.send(ScanEvent::Warning {
+                    path: String::new(),
+                    code: "rescan_options_changed".into(),
+                    message: format!(
+                        "the options differ from scan {}; every directory is traversed",
+                        previous
+                    ),
+                });
+            }
+            dirs.map(|dirs| (previous, dirs))
+        }
+        _ => None,
+    };
+    // Enabled once for the whole scan and released when it returns
+    let privilege = match options.access_mode {
+        AccessMode::Normal => None,
+        AccessMode::Backup => match backup::acquire() {
+            Ok(privilege) => Some(privilege),
+            Err(reason) => {
+                summary.warnings += 1;
+                let _ = tx.send(ScanEvent::Warning {
+                    path: String::new(),
+                    code: "backup_privilege_unavailable".into(),
+                    message: format!(
+                        "backup mode was requested but is not active: {}; entries only readable with backup \
+                         privileges are skipped",
+                        reason
+                    ),
+                });
+                None
+            }
+        },
+    };
+    summary.backup_mode = privilege.is_some();
+    sqlx::query("UPDATE scans SET backup_mode=?1 WHERE id=?2")
+        .bind(summary.backup_mode)
+        .bind(id.to_string())
+        .execute(&pool)
+        .await?;
+    // Only set while the earlier scan's files are copied
+    let copy_from = previous.as_ref().map(|(previous, _)| *previous);
+    let mut copied: Vec<String> = Vec::new();
+    summary.roots =
+        root_paths.iter().map(|root| RootSummary { root: root.clone(), ..Default::default() }).collect();
+
+    let filters = build_globset(&options.excludes)
+        .map_err(|e| ("invalid_exclude_pattern", format!("Failed to build exclude pattern: {}", e)))
+        .and_then(|gs| {
+            build_globset(&options.includes)
+                .map(|inc| (gs, inc))
+                .map_err(|e| ("invalid_include_pattern", format!("Failed to build include pattern: {}", e)))
+        });
+    let filters = match filters {
+        Ok(filters) => Some(filters),
+        Err((code, message)) => {
+            for root in &root_paths {
+                let _ = tx.send(ScanEvent::Warning {
+                    path: root.clone(),
+                    code: code.into(),
+                    message: message.clone(),
+                });
+            }
+            None
+        }
+    };
 
-    for root in root_paths {
-        if cancel.is_cancelled() {
+    // Roots are pushed in reverse, as workers take the most recently queued directory first
+    let mut root_tasks = Vec::new();
+    for (root_index, root) in root_paths.iter().enumerate().rev() {
+        if cancel.is_cancelled() || filters.is_none() {
             break;
         }
-        let root_path = PathBuf::from(&root);
+        let root_path = PathBuf::from(root);
         if !root_path.exists() {
             summary.warnings += 1;
+            summary.roots[root_index].warning_count += 1;
             let _ = tx.send(ScanEvent::Warning {
                 path: root.clone(),
                 code: "missing_root".into(),
@@ -171,290 +395,54 @@ pub async fn run_scan(
             });
             continue;
         }
+        root_tasks.push(DirTask { path: root_path, depth: 0, root_index, parent: None });
+    }
 
-        let permit = match sem.clone().acquire_owned().await {
-            Ok(p) => p,
-            Err(e) => {
-                // Semaphore closed, likely shutdown
-                tracing::error!("Semaphore acquisition failed: {}", e);
-                summary.warnings += 1;
-                continue;
-            }
-        };
-        let tx_res_cl = tx_res.clone();
-        let tx_clone = tx.clone();
-        let cancel_child = cancel.clone();
-        let options_cl = options.clone();
-        let root_clone = root_path.clone();
-        let flush_thr = flush_threshold;
-        let dir_conc = dir_concurrency.or(options_cl.concurrency).unwrap_or(1);
-        let root_str = root_clone.to_string_lossy().to_string();
-        task::spawn_blocking(move || {
-            let gs = match build_globset(&options_cl.excludes) {
-                Ok(gs) => gs,
-                Err(e) => {
-                    let _ = tx_clone.send(ScanEvent::Warning {
-                        path: root_str.clone(),
-                        code: "invalid_exclude_pattern".into(),
-                        message: format!("Failed to build exclude pattern: {}", e),
-                    });
-                    drop(permit);
-                    return;
-                }
-            };
-
-            // Skip excluded/hidden/reparse roots
-            if matches_excludes(&root_clone, &gs) {
-                drop(permit);
-                return;
-            }
-            let meta = match fs::metadata(&root_clone) {
-                Ok(m) => m,
-                Err(_) => {
-                    let _ = tx_clone.send(ScanEvent::Warning {
-                        path: root_clone.to_string_lossy().to_string(),
-                        code: "metadata_failed".into(),
-                        message: "failed to stat root".into(),
-                    });
-                    let warn_summary = ScanResultSummary { warnings: 1, ..Default::default() };
-                    let _ = tx_res_cl.blocking_send((Vec::new(), Vec::new(), warn_summary));
-                    drop(permit);
-                    return;
-                }
-            };
-            let root_mtime = system_time_to_secs(meta.modified().ok());
-            let root_atime = system_time_to_secs(meta.accessed().ok());
-            let mut root_latest_mtime = root_mtime;
-            let mut root_latest_atime = root_atime;
-            if !options_cl.follow_symlinks && is_reparse_point(&meta) {
-                // UNC/DFS shares and mapped network drives should be traversed even if marked as reparse points
-                if !is_network_path(&root_clone) {
-                    drop(permit);
-                    return;
-                }
-            }
-            if !options_cl.include_hidden && is_hidden_or_system(&root_clone, &meta) {
-                drop(permit);
-                return;
-            }
-
-            // Enumerate root entries
-            let mut subdirs: Vec<PathBuf> = Vec::new();
-            let mut root_files: u64 = 0;
-            let mut root_files_logical: u64 = 0;
-            let mut root_files_alloc: u64 = 0;
-            let mut root_file_buf: Vec<FileRecord> = Vec::with_capacity(flush_thr);
-            match fs::read_dir(&root_clone) {
-                Ok(rd) => {
-                    for entry in rd.flatten() {
-                        if cancel_child.is_cancelled() {
-                            break;
-                        }
-                        let p = entry.path();
-                        if matches_excludes(&p, &gs) {
-                            continue;
-                        }
-                        let md = match entry.metadata() {
-                            Ok(m) => m,
-                            Err(_) => {
-                                let _ = tx_clone.send(ScanEvent::Warning {
-                                    path: p.to_string_lossy().to_string(),
-                                    code: "metadata_failed".into(),
-                                    message: "failed to stat".into(),
-                                });
-                                let warn_summary = ScanResultSummary { warnings: 1, ..Default::default() };
-                                let _ = tx_res_cl.blocking_send((Vec::new(), Vec::new(), warn_summary));
-                                continue;
-                            }
-                        };
-                        let entry_mtime = system_time_to_secs(md.modified().ok());
-                        let entry_atime = system_time_to_secs(md.accessed().ok());
-                        root_latest_mtime = max_opt(root_latest_mtime, entry_mtime);
-                        root_latest_atime = max_opt(root_latest_atime, entry_atime);
-                        if md.is_dir() {
-                            if !options_cl.follow_symlinks && is_reparse_point(&md) {
-                                // Allow DFS/UNC and mapped network dirs even if marked as reparse points
-                                if !is_network_path(&p) {
-                                    continue;
-                                }
-                            }
-                            if !options_cl.include_hidden && is_hidden_or_system(&p, &md) {
-                                continue;
-                            }
-                            // FIX Bug #66 - max_depth = 0 means scan only root
-                            if let Some(max_d) = options_cl.max_depth {
-                                if max_d == 0 {
-                                    // Don't recurse into subdirectories
-                                    continue;
-                                }
-                            }
-                            subdirs.push(p);
-                        } else if md.is_file() {
-                            if !options_cl.include_hidden && is_hidden_or_system(&p, &md) {
-                                continue;
-                            }
-                            root_files += 1;
-                            let logical_sz = md.len();
-                            // FIX Bug #4: Use saturating_add to prevent overflow/panic
-                            root_files_logical = root_files_logical.saturating_add(logical_sz);
-
-                            let alloc_sz = if options_cl.measure_allocated {
-                                unsafe_get_allocated_size(&p).unwrap_or(logical_sz)
-                            } else {
-                                logical_sz
-                            };
-                            root_files_alloc = root_files_alloc.saturating_add(alloc_sz);
-                            // buffer file record at root level, flush in batches (ensure flush_thr >= 1)
-                            let flush_limit = flush_thr.max(1);
-                            root_file_buf.push(FileRecord {
-                                path: p.to_string_lossy().to_string(),
-                                parent_path: Some(root_str.clone()),
-                                logical_size: logical_sz,
-                                allocated_size: alloc_sz,
-                                mtime: entry_mtime,
-                                atime: entry_atime,
-                            });
-                            if root_file_buf.len() >= flush_limit {
-                                let mut out_files: Vec<FileRecord> = Vec::new();
-                                std::mem::swap(&mut out_files, &mut root_file_buf);
-                                let _ = tx_res_cl.blocking_send((
-                                    Vec::new(),
-                                    out_files,
-                                    ScanResultSummary::default(),
-                                ));
-                                if tx_res_cl.is_closed() {
-                                    return; // Stop processing if receiver is gone (Zombie prevention)
-                                }
-
-                            }
-                        }
-                    }
-                    // final flush of root file buffer
-                    if !root_file_buf.is_empty() {
-                        let mut out_files: Vec<FileRecord> = Vec::new();
-                        std::mem::swap(&mut out_files, &mut root_file_buf);
-                        let _ =
-                            tx_res_cl.blocking_send((Vec::new(), out_files, ScanResultSummary::default()));
-                    }
-                }
-                Err(_) => {
-                    let _ = tx_clone.send(ScanEvent::Warning {
-                        path: root_clone.to_string_lossy().to_string(),
-                        code: "read_dir_failed".into(),
-                        message: "failed to read directory".into(),
-                    });
-                    let warn_summary = ScanResultSummary { warnings: 1, ..Default::default() };
-                    let _ = tx_res_cl.blocking_send((Vec::new(), Vec::new(), warn_summary));
-                }
+    let (globset, includes) = filters.unwrap_or_else(|| (GlobSet::empty(), GlobSet::empty()));
+    let ctx = Arc::new(ScanCtx {
+        options: options.clone(),
+        globset,
+        includes,
+        tx_sse: tx.clone(),
+        tx_out: tx_res,
+        stop: cancel.child_token(),
+        pause: pause.clone(),
+        flush_threshold,
+        meter: meter.clone(),
+        max_entries_per_dir,
+        hardlinks,
+        owners: owners.clone(),
+        categories: CategoryMap::for_scan(categories),
+        hashes: options.hash_min_size.map(|_| HashGate::for_workers(concurrency)),
+        persisted,
+        previous: previous.map(|(_, dirs)| dirs),
+        links: links.clone(),
+        metrics: metrics.clone(),
+        queue: DirQueue::new(id, root_tasks),
+        roots: (0..summary.roots.len()).map(|_| std::sync::OnceLock::new()).collect(),
+    });
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let ctx = ctx.clone();
+            let span = tracing::Span::current();
+            task::spawn_blocking(move || span.in_scope(|| ctx.work()))
+        })
+        .collect();
+    // Once all workers are done, store what a cancelled scan found below its unfinished roots
+    let finish_roots = async move {
+        for worker in workers {
+            if let Err(e) = worker.await {
+                tracing::error!("Scan worker failed: {:?}", e);
             }
-
-            // FIX Bug #39 - Limit total threads spawned
-            let mut idx = 0usize;
-            let mut running: Vec<std::thread::JoinHandle<ScanResultSummary>> = Vec::new();
-            let sub_count = subdirs.len();
-            // Cap dir_limit to prevent resource exhaustion
-            let dir_limit = dir_conc.max(1).min(64);
-            let mut sub_dirs_total: u64 = 0;
-            let mut sub_files_total: u64 = 0;
-            let mut subtree_logical: u64 = 0;
-            let mut subtree_alloc: u64 = 0;
-            while idx < sub_count || !running.is_empty() {
-                while running.len() < dir_limit && idx < sub_count {
-                    let sub = subdirs[idx].clone();
-                    idx += 1;
-                    let tx_res_sub = tx_res_cl.clone();
-                    let tx_sse = tx_clone.clone();
-                    let cancel_th = cancel_child.clone();
-                    let opt = options_cl.clone();
-                    let gs2 = gs.clone();
-                    let handle = std::thread::spawn(move || {
-                        // FIX Bug #11: Ensure proper cleanup even on panic
-                        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                            let mut ssum = ScanResultSummary::default();
-                            let last_sent_summary = ScanResultSummary::default();
-                            let mut snodes: Vec<NodeRecord> = Vec::with_capacity(flush_thr);
-                            let mut sfiles: Vec<FileRecord> = Vec::with_capacity(flush_thr);
-                            let _ = scan_dir(
-                                id,
-                                &sub,
-                                1,
-                                &opt,
-                                &gs2,
-                                &tx_sse,
-                                &cancel_th,
-                                &mut ssum,
-                                &mut snodes,
-                                &mut sfiles,
-                                &tx_res_sub,
-                                flush_thr,
-                            );
-                            // send remaining
-                            let delta = diff_summary(&ssum, &last_sent_summary);
-                            let _ = tx_res_sub.blocking_send((snodes, sfiles, delta));
-                            ssum
-                        }));
-                        result.unwrap_or_else(|_| {
-                            tracing::error!("Thread panicked during scan");
-                            ScanResultSummary::default()
-                        })
-                    });
-                    running.push(handle);
-                }
-                if !running.is_empty() {
-                    if let Some(handle) = running.pop() {
-                        // FIX Bug #41 - Handle thread panics
-                        match handle.join() {
-                            Ok(ssum) => {
-                                // accumulate into root aggregates
-                                subtree_logical = subtree_logical.saturating_add(ssum.total_logical_size);
-                                subtree_alloc = subtree_alloc.saturating_add(ssum.total_allocated_size);
-                                // directories/files from subtrees
-                                sub_dirs_total = sub_dirs_total.saturating_add(ssum.total_dirs);
-                                sub_files_total = sub_files_total.saturating_add(ssum.total_files);
-                            }
-                            Err(e) => {
-                                tracing::error!("Worker thread panicked: {:?}", e);
-                                // FIX Bug #3: Track panic as warning to avoid silent data loss
-                                let mut warn_summary = ScanResultSummary { warnings: 1, ..Default::default() };
-                                // accumulate into root aggregates
-                                subtree_logical = subtree_logical.saturating_add(warn_summary.total_logical_size);
-                                subtree_alloc = subtree_alloc.saturating_add(warn_summary.total_allocated_size);
-                                sub_dirs_total = sub_dirs_total.saturating_add(warn_summary.total_dirs);
-                                sub_files_total = sub_files_total.saturating_add(warn_summary.total_files);
-                            }
-                        }
-                    }
-                }
+        }
+        for (root_index, root) in ctx.roots.iter().enumerate() {
+            if let Some(node) = root.get().and_then(|dir| dir.take_record()) {
+                let batch = (root_index, vec![node], Vec::new(), Vec::new(), Default::default());
+                let _ = ctx.tx_out.send(batch).await;
             }
-
-            // Emit root node record
-            let root_node = NodeRecord {
-                path: root_str,
-                parent_path: parent_path_string(&root_clone),
-                depth: calc_depth(&root_clone),
-                is_dir: true,
-                logical_size: root_files_logical.saturating_add(subtree_logical),
-                allocated_size: root_files_alloc.saturating_add(subtree_alloc),
-                file_count: root_files.saturating_add(sub_files_total),
-                dir_count: sub_dirs_total,
-                mtime: root_latest_mtime,
-                atime: root_latest_atime,
-            };
-            let root_delta = ScanResultSummary {
-                total_dirs: 1,
-                total_files: root_files,
-                total_logical_size: root_files_logical,
-                total_allocated_size: root_files_alloc,
-                warnings: 0,
-                latest_mtime: root_latest_mtime,
-                latest_atime: root_latest_atime,
-            };
-            let _ = tx_res_cl.blocking_send((vec![root_node], Vec::new(), root_delta));
-            drop(permit);
-        });
-    }
-
-    drop(tx_res);
+        }
+    };
+    tokio::spawn(finish_roots.instrument(tracing::Span::current()));
 
     let mut ticker = interval(Duration::from_millis(flush_interval_ms.max(1)));
     // Remember last sent totals and time to avoid spamming, but still emit a heartbeat on slow shares
@@ -465,36 +453,79 @@ pub async fn run_scan(
         tokio::select! {
             maybe = rx_res.recv() => {
                 match maybe {
-                    Some((mut ns, mut fs, sum)) => {
+                    Some((root_index, mut ns, mut fs, mut cs, sum)) => {
+                        if let Some(root) = summary.roots.get_mut(root_index) {
+                            root.dir_count = root.dir_count.saturating_add(sum.total_dirs);
+                            root.file_count = root.file_count.saturating_add(sum.total_files);
+                            root.logical_size = root.logical_size.saturating_add(sum.total_logical_size);
+                            root.allocated_size =
+                                root.allocated_size.saturating_add(sum.total_allocated_size);
+                            root.warning_count = root.warning_count.saturating_add(sum.warnings);
+                        }
                         // aggregate summary
                         summary.total_dirs = summary.total_dirs.saturating_add(sum.total_dirs);
                         summary.total_files = summary.total_files.saturating_add(sum.total_files);
                         summary.total_logical_size = summary.total_logical_size.saturating_add(sum.total_logical_size);
                         summary.total_allocated_size = summary.total_allocated_size.saturating_add(sum.total_allocated_size);
+                        summary.dedup_saved_bytes = summary.dedup_saved_bytes.saturating_add(sum.dedup_saved_bytes);
+                        summary.empty_files = summary.empty_files.saturating_add(sum.empty_files);
                         summary.warnings = summary.warnings.saturating_add(sum.warnings);
                         summary.latest_mtime = max_opt(summary.latest_mtime, sum.latest_mtime);
                         summary.latest_atime = max_opt(summary.latest_atime, sum.latest_atime);
 
                         // accumulate and persist in batches
+                        let bytes = ns.iter().map(NodeRecord::buffered_bytes).sum::<usize>()
+                            + fs.iter().map(FileRecord::buffered_bytes).sum::<usize>()
+                            + cs.iter().map(|dir| budget::copied_bytes(dir)).sum::<usize>();
+                        meter.add(bytes);
+                        buffered_bytes += bytes;
                         nodes.append(&mut ns);
                         files.append(&mut fs);
-                        if nodes.len() + files.len() >= flush_threshold.max(batch_size) {
-                            if let Err(e) = persist_batches(&pool, id, &mut nodes, &mut files, batch_size).await {
-                                tracing::error!("Failed to persist scan batch: {:?}", e);
-                                return Err(e);
+                        copied.append(&mut cs);
+                        if nodes.len() + files.len() + copied.len() >= flush_threshold.max(batch_size)
+                            || buffered_bytes >= meter.limit()
+                        {
+                            copy_unchanged_files(&pool, id, copy_from, &mut copied).await?;
+                            match persist_batches(&pool, id, &mut nodes, &mut files, batch_size, &persist_retry, &tx).await {
+                                Ok(n) => summary.warnings = summary.warnings.saturating_add(n),
+                                Err(e) => {
+                                    tracing::error!("Failed to persist scan batch: {:?}", e);
+                                    return Err(e);
+                                }
                             }
+                            meter.sub(std::mem::take(&mut buffered_bytes));
                         }
                     }
                     None => break,
                 }
             }
+            ev = warn_rx.recv(), if warn_rx_open => {
+                match ev {
+                    Ok(event) => {
+                        warnings.push(event);
+                        if warnings.pending.len() >= batch_size.max(1) {
+                            warnings.persist(&pool, id).await;
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Warning collector for scan {} lagged, {} events not stored", id, n);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => warn_rx_open = false,
+                }
+            }
             _ = ticker.tick() => {
-                if !nodes.is_empty() || !files.is_empty() {
-                    if let Err(e) = persist_batches(&pool, id, &mut nodes, &mut files, batch_size).await {
-                        tracing::error!("Failed to persist scan batch: {:?}", e);
-                        return Err(e);
+                if !nodes.is_empty() || !files.is_empty() || !copied.is_empty() {
+                    copy_unchanged_files(&pool, id, copy_from, &mut copied).await?;
+                    match persist_batches(&pool, id, &mut nodes, &mut files, batch_size, &persist_retry, &tx).await {
+                        Ok(n) => summary.warnings = summary.warnings.saturating_add(n),
+                        Err(e) => {
+                            tracing::error!("Failed to persist scan batch: {:?}", e);
+                            return Err(e);
+                        }
                     }
+                    meter.sub(std::mem::take(&mut buffered_bytes));
                 }
+                warnings.persist(&pool, id).await;
                 // Fortschritt periodisch in scans Tabelle schreiben, damit UI während running Zahlen sieht
                 let _ = sqlx::query(
                     r#"UPDATE scans SET
@@ -502,14 +533,16 @@ pub async fn run_scan(
                         total_allocated_size=?2,
                         dir_count=?3,
                         file_count=?4,
-                        warning_count=?5
-                      WHERE id=?6"#
+                        warning_count=?5,
+                        dedup_saved_bytes=?6
+                      WHERE id=?7"#
                 )
                 .bind(summary.total_logical_size as i64)
                 .bind(summary.total_allocated_size as i64)
                 .bind(summary.total_dirs as i64)
                 .bind(summary.total_files as i64)
                 .bind(summary.warnings as i64)
+                .bind(summary.dedup_saved_bytes.min(i64::MAX as u64) as i64)
                 .bind(id.to_string())
                 .execute(&pool).await;
 
@@ -528,317 +561,1486 @@ pub async fn run_scan(
                         files_scanned: summary.total_files,
                         logical_size: summary.total_logical_size,
                         allocated_size: summary.total_allocated_size,
+                        estimated_percent: None,
                     });
                     last_progress_totals = current_totals;
                     last_sse_emit = Instant::now();
                 }
             }
         }
+        queue_depth.set(rx_res.len());
+        buffered.set(nodes.len() + files.len() + copied.len());
     }
 
     // Persist any remaining records
-    persist_batches(&pool, id, &mut nodes, &mut files, batch_size).await?;
-
-    Ok(summary)
-}
-
-#[allow(clippy::too_many_arguments)]
-fn scan_dir(
-    _scan_id: Uuid,
-    dir: &Path,
-    depth: u32,
-    options: &ScanOptions,
-    globset: &GlobSet,
-    tx: &tokio::sync::broadcast::Sender<ScanEvent>,
-    cancel: &CancellationToken,
-    summary: &mut ScanResultSummary,
-    nodes: &mut Vec<NodeRecord>,
-    files: &mut Vec<FileRecord>,
-    tx_out: &mpsc::Sender<(Vec<NodeRecord>, Vec<FileRecord>, ScanResultSummary)>,
-    flush_threshold: usize,
-) -> anyhow::Result<(u64, u64, u64, u64)> {
-    // (dirs, files, logical, allocated)
-    if cancel.is_cancelled() {
-        anyhow::bail!("cancelled")
-    }
-
-    if matches_excludes(dir, globset) {
-        return Ok((0, 0, 0, 0));
-    }
-
-    let meta = match fs::metadata(dir) {
-        Ok(m) => m,
-        Err(_) => {
-            summary.warnings += 1;
+    copy_unchanged_files(&pool, id, copy_from, &mut copied).await?;
+    let n = persist_batches(&pool, id, &mut nodes, &mut files, batch_size, &persist_retry, &tx).await?;
+    summary.warnings = summary.warnings.saturating_add(n);
+    meter.sub(buffered_bytes);
+    summary.buffer_peak_bytes = meter.peak() as u64;
+    metrics.scanner_buffer_peak_bytes.fetch_max(meter.peak(), Ordering::Relaxed);
+    persist_roots(&pool, id, &summary.roots).await?;
+    persist_links(&pool, id, &links.take()).await?;
+    if let Some(owners) = owners {
+        let mut conn = pool.acquire().await?;
+        owner::refresh_usage(&mut conn, &id.to_string(), owner::UsageScope::Scan).await?;
+        if owners.failures() > 0 {
+            summary.warnings = summary.warnings.saturating_add(1);
             let _ = tx.send(ScanEvent::Warning {
-                path: dir.to_string_lossy().to_string(),
-                code: "metadata_failed".into(),
-                message: "failed to stat directory".into(),
+                path: String::new(),
+                code: "owner_unknown".into(),
+                message: format!("the owner of {} files could not be read", owners.failures()),
             });
-            return Ok((0, 0, 0, 0));
         }
-    };
-
-    let dir_mtime = system_time_to_secs(meta.modified().ok());
-    let dir_atime = system_time_to_secs(meta.accessed().ok());
-
-    summary.latest_mtime = max_opt(summary.latest_mtime, dir_mtime);
-    summary.latest_atime = max_opt(summary.latest_atime, dir_atime);
-
-    if !options.follow_symlinks && is_reparse_point(&meta) {
-        return Ok((0, 0, 0, 0));
     }
-    if !options.include_hidden && is_hidden_or_system(dir, &meta) {
-        return Ok((0, 0, 0, 0));
+    // Totals written from summaries that arrived late do not add up; a cancelled scan lacks subtrees
+    if !cancel.is_cancelled() {
+        match consistency::record(&pool, id, options.measure_logical).await {
+            Ok(0) => {}
+            Ok(n) => {
+                summary.warnings = summary.warnings.saturating_add(1);
+                let _ = tx.send(ScanEvent::Warning {
+                    path: String::new(),
+                    code: "inconsistent_totals".into(),
+                    message: format!(
+                        "the totals of {} directories do not add up, \
+                         POST /scans/{}/recompute?repair=true fixes them",
+                        n, id
+                    ),
+                });
+            }
+            Err(e) => tracing::warn!("Failed to check the directory totals of scan {}: {}", id, e),
+        }
     }
+    // All workers are done, so every warning they sent is already buffered in the channel
+    while let Ok(event) = warn_rx.try_recv() {
+        warnings.push(event);
+    }
+    warnings.persist(&pool, id).await;
 
-    let mut local_dirs: u64 = 1; // count this dir
-    let mut local_files: u64 = 0;
-    let mut logical: u64 = 0;
-    let mut allocated: u64 = 0;
-
-    // FIX Bug #12: Use u64 instead of u32 to prevent overflow on large directories
-    let mut sent = 0u64;
-    let mut last_emit = Instant::now();
-    let dir_str = dir.to_string_lossy().to_string();
-
-    match fs::read_dir(dir) {
-        Ok(rd) => {
-            for entry in rd.flatten() {
-                if cancel.is_cancelled() {
-                    anyhow::bail!("cancelled");
-                }
-                let path = entry.path();
-                if matches_excludes(&path, globset) {
-                    continue;
-                }
-                let md = match entry.metadata() {
-                    Ok(m) => m,
-                    Err(_) => {
-                        summary.warnings += 1;
-                        continue;
-                    }
-                };
-
-                let entry_mtime = system_time_to_secs(md.modified().ok());
-                let entry_atime = system_time_to_secs(md.accessed().ok());
-                summary.latest_mtime = max_opt(summary.latest_mtime, entry_mtime);
-                summary.latest_atime = max_opt(summary.latest_atime, entry_atime);
-
-                if md.is_dir() {
-                    if !options.follow_symlinks && is_reparse_point(&md) {
-                        continue;
-                    }
-                    if !options.include_hidden && is_hidden_or_system(&path, &md) {
-                        continue;
-                    }
-                    // FIX Bug #9: Check max_depth: depth is 0-indexed from root
-                    // If we're at depth N and max_depth is N, we can still recurse one level
-                    // Only block when depth > max_depth (not >=)
-                    if let Some(max_d) = options.max_depth {
-                        if depth >= max_d {
-                            continue; // Don't recurse deeper
-                        }
-                    }
-                    let (d_dirs, d_files, d_logical, d_alloc) = scan_dir(
-                        _scan_id,
-                        &path,
-                        depth + 1,
-                        options,
-                        globset,
-                        tx,
-                        cancel,
-                        summary,
-                        nodes,
-                        files,
-                        tx_out,
-                        flush_threshold,
-                    )?;
-                    local_dirs += d_dirs;
-                    local_files += d_files;
-                    logical = logical.saturating_add(d_logical);
-                    allocated = allocated.saturating_add(d_alloc);
-                } else if md.is_file() {
-                    if !options.include_hidden && is_hidden_or_system(&path, &md) {
-                        continue;
-                    }
-                    local_files += 1;
-                    let logical_sz = md.len();
-                    let alloc_sz = if options.measure_allocated {
-                        unsafe_get_allocated_size(&path).unwrap_or(logical_sz)
-                    } else {
-                        logical_sz
-                    };
-                    // FIX Bug #4: Use saturating_add for consistency
-                    if options.measure_logical {
-                        logical = logical.saturating_add(logical_sz);
-                    }
-                    allocated = allocated.saturating_add(alloc_sz);
-
-                    // collect file record
-                    files.push(FileRecord {
-                        path: path.to_string_lossy().to_string(),
-                        parent_path: Some(dir_str.clone()),
-                        logical_size: logical_sz,
-                        allocated_size: alloc_sz,
-                        mtime: entry_mtime,
-                        atime: entry_atime,
-                    });
-                }
+    Ok(summary)
+}
 
-                sent = sent.saturating_add(1);
-                // Reduzierte Progress-Updates für bessere Performance
-                // FIX Bug #13: Remove redundant sent > 0 check (modulo handles zero)
-                if sent % 512 == 0 {
-                    let _ = tx.send(ScanEvent::Progress {
-                        current_path: path.to_string_lossy().to_string(),
-                        dirs_scanned: summary.total_dirs + local_dirs,
-                        files_scanned: summary.total_files + local_files,
-                        logical_size: summary.total_logical_size + logical,
-                        allocated_size: summary.total_allocated_size + allocated,
-                    });
-                }
+/// The maximum number of warnings stored per scan.
+///
+/// Scans of trees with many unreadable entries would otherwise store one row
+/// per entry; `warning_count` still counts all of them.
+const MAX_STORED_WARNINGS: usize = 100_000;
+
+/// Collects the warning events of a scan and stores them in the `warnings` table.
+#[derive(Default)]
+struct WarningBuffer {
+    /// Warnings not yet written: path, code, message
+    pending: Vec<(String, String, String)>,
+    /// The number of warnings written or dropped because of the cap
+    accepted: usize,
+}
 
-                // Zusätzlich: Zeitbasierte Fortschrittsupdates (z. B. auf langsamen Netzlaufwerken)
-                if last_emit.elapsed() >= std::time::Duration::from_millis(2000) {
-                    let _ = tx.send(ScanEvent::Progress {
-                        current_path: path.to_string_lossy().to_string(),
-                        dirs_scanned: summary.total_dirs + local_dirs,
-                        files_scanned: summary.total_files + local_files,
-                        logical_size: summary.total_logical_size + logical,
-                        allocated_size: summary.total_allocated_size + allocated,
-                    });
-                    last_emit = Instant::now();
-                }
+impl WarningBuffer {
+    /// Buffers a warning event; other events and warnings beyond the cap are ignored.
+    fn push(&mut self, event: ScanEvent) {
+        if let ScanEvent::Warning { path, code, message } = event {
+            if self.accepted < MAX_STORED_WARNINGS {
+                self.accepted += 1;
+                self.pending.push((path, code, message));
+            }
+        }
+    }
 
-                // FIX Bug #45 - Partial flush with proper error handling
-                let flush_limit = flush_threshold.max(1);
-                if (nodes.len() + files.len()) >= flush_limit {
-                    let mut out_nodes: Vec<NodeRecord> = Vec::new();
-                    let mut out_files: Vec<FileRecord> = Vec::new();
-                    std::mem::swap(&mut out_nodes, nodes);
-                    std::mem::swap(&mut out_files, files);
-                    if tx_out.blocking_send((out_nodes, out_files, ScanResultSummary::default())).is_err() {
-                        tracing::warn!("Channel closed during partial flush");
-                        anyhow::bail!("Aggregator channel closed");
-                    }
-                }
+    /// Writes the buffered warnings.
+    ///
+    /// Failures are logged and not returned: losing warning details must not
+    /// fail the scan.
+    async fn persist(&mut self, pool: &sqlx::SqlitePool, id: Uuid) {
+        if self.pending.is_empty() {
+            return;
+        }
+        // 4 binds per row, well below SQLite's variable limit
+        const ROWS_PER_STMT: usize = 200;
+        let sid = id.to_string();
+        let res: anyhow::Result<()> = async {
+            let mut txdb = pool.begin().await?;
+            for chunk in self.pending.chunks(ROWS_PER_STMT) {
+                let mut qb = QueryBuilder::new("INSERT INTO warnings (scan_id, path, code, message) ");
+                qb.push_values(chunk, |mut b, (path, code, message)| {
+                    b.push_bind(&sid).push_bind(path).push_bind(code).push_bind(message);
+                });
+                qb.build().execute(&mut *txdb).await?;
             }
+            txdb.commit().await?;
+            Ok(())
         }
-        Err(_) => {
-            summary.warnings += 1;
-            let _ = tx.send(ScanEvent::Warning {
-                path: dir_str.clone(),
-                code: "read_dir_failed".into(),
-                message: "failed to read directory".into(),
-            });
+        .await;
+        if let Err(e) = res {
+            tracing::warn!("Failed to store {} warnings of scan {}: {:?}", self.pending.len(), id, e);
         }
+        self.pending.clear();
     }
+}
+
+/// Warning emitted when a directory has more entries than `max_entries_per_dir`.
+fn entry_limit_warning(dir: &Path, limit: u64) -> ScanEvent {
+    tracing::warn!("Directory {:?} exceeds max_entries_per_dir ({}); skipping remaining entries", dir, limit);
+    ScanEvent::Warning {
+        path: dir.to_string_lossy().to_string(),
+        code: "dir_entry_limit".into(),
+        message: format!("more than {} entries; remaining entries skipped", limit),
+    }
+}
 
-    summary.total_dirs = summary.total_dirs.saturating_add(local_dirs);
-    summary.total_files = summary.total_files.saturating_add(local_files);
-    summary.total_logical_size = summary.total_logical_size.saturating_add(logical);
-    summary.total_allocated_size = summary.total_allocated_size.saturating_add(allocated);
+/// The sizes of a file as recorded by the scanner.
+pub(crate) struct FileSizes {
+    /// The logical size, including alternate data streams if measured.
+    pub logical: u64,
+    /// The allocated size, including alternate data streams if measured.
+    pub allocated: u64,
+    /// (count, bytes) of the alternate data streams, `None` if not measured.
+    pub ads: Option<(u64, u64)>,
+}
 
-    // collect node record for this directory
-    // FIX Bug #18 & #23: Return error if local_dirs is invalid instead of continuing
-    // local_dirs includes this dir (initialized to 1) plus all recursive subdirs
-    // Subtract 1 to exclude self from the count
-    let dir_count_value = if local_dirs > 0 {
-        local_dirs - 1
+/// Measures a file according to the scan options.
+///
+/// Issues blocking system calls, so it must only run on the scanner's worker
+/// threads or inside `spawn_blocking`.
+pub(crate) fn measure_file(options: &ScanOptions, path: &Path, md: &fs::Metadata) -> FileSizes {
+    let ads = if options.measure_ads { alternate_streams(path) } else { None };
+    let ads_bytes = ads.map_or(0, |(_, bytes)| bytes);
+    let logical = md.len().saturating_add(ads_bytes);
+    let allocated = if options.measure_allocated {
+        // GetCompressedFileSizeW only reports the unnamed stream
+        let backup = options.access_mode == AccessMode::Backup;
+        unsafe_get_allocated_size(path, md)
+            .or_else(|| backup.then(|| backup::allocated_size(path)).flatten())
+            .unwrap_or(md.len())
+            .saturating_add(ads_bytes)
     } else {
-        tracing::error!("local_dirs is 0 at {:?}, this indicates a logic error", dir);
-        anyhow::bail!("Invalid directory count detected");
+        logical
     };
-    nodes.push(NodeRecord {
-        path: dir_str,
-        parent_path: parent_path_string(dir),
-        depth: calc_depth(dir),
-        is_dir: true,
-        logical_size: logical,
-        allocated_size: allocated,
-        file_count: local_files,
-        dir_count: dir_count_value,
-        mtime: dir_mtime,
-        atime: dir_atime,
-    });
-
-    Ok((local_dirs, local_files, logical, allocated))
+    FileSizes { logical, allocated, ads }
 }
 
-fn build_globset(patterns: &[String]) -> anyhow::Result<GlobSet> {
-    let mut b = GlobSetBuilder::new();
-    for p in patterns {
-        let trimmed = p.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        // FIX Bug #29: Validate pattern length and complexity to prevent DoS
-        if trimmed.len() > 1024 {
-            tracing::warn!("Glob pattern too long, skipping: {} chars", trimmed.len());
-            continue;
+/// Returns the number and total size of the named streams of a file.
+///
+/// `None` if the streams cannot be listed, e.g. on filesystems without streams.
+#[cfg(windows)]
+fn alternate_streams(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard, WIN32_FIND_STREAM_DATA,
+    };
+
+    let w: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut data = WIN32_FIND_STREAM_DATA::default();
+    let data_ptr = &mut data as *mut WIN32_FIND_STREAM_DATA as *mut core::ffi::c_void;
+    let handle =
+        unsafe { FindFirstStreamW(PCWSTR(w.as_ptr()), FindStreamInfoStandard, data_ptr, None) }.ok()?;
+    let (mut count, mut bytes) = (0u64, 0u64);
+    loop {
+        // The unnamed data stream is listed as "::$DATA", named ones as ":name:$DATA"
+        if data.cStreamName[1] != u16::from(b':') {
+            count += 1;
+            bytes = bytes.saturating_add(data.StreamSize.max(0) as u64);
         }
-        // Check for excessive wildcards that could cause exponential backtracking
-        let wildcard_count = trimmed.chars().filter(|&c| c == '*' || c == '?').count();
-        if wildcard_count > 20 {
-            tracing::warn!("Glob pattern has too many wildcards ({}), skipping: {}", wildcard_count, trimmed);
-            continue;
+        if unsafe { FindNextStreamW(handle, data_ptr) }.is_err() {
+            break;
         }
-        // FIX Bug #24: Normalize path separators consistently
-        // We use forward slashes internally for cross-platform compatibility
-        // Windows APIs handle forward slashes correctly in most cases
-        let norm = trimmed.replace('\\', "/");
-        // Catch glob compilation errors
-        let g = Glob::new(&norm).map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", norm, e))?;
-        b.add(g);
     }
-    Ok(b.build()?)
+    let _ = unsafe { FindClose(handle) };
+    Some((count, bytes))
 }
 
-fn matches_excludes(path: &Path, set: &GlobSet) -> bool {
-    if set.is_empty() {
-        return false;
+#[cfg(not(windows))]
+fn alternate_streams(_path: &Path) -> Option<(u64, u64)> {
+    // Alternate data streams only exist on NTFS
+    None
+}
+
+/// Identities of multiply linked files already counted during one scan.
+///
+/// Only files with more than one link are recorded, so the set stays small on
+/// volumes without hardlinks.
+#[derive(Default)]
+struct HardlinkSet {
+    seen: std::sync::Mutex<std::collections::HashSet<(u64, u64)>>,
+}
+
+impl HardlinkSet {
+    /// Records `key` and returns whether this is its first occurrence in the scan.
+    fn first_seen(&self, key: (u64, u64)) -> bool {
+        match self.seen.lock() {
+            Ok(mut seen) => seen.insert(key),
+            // Keep deduplicating even if a worker panicked while holding the lock
+            Err(poisoned) => poisoned.into_inner().insert(key),
+        }
     }
-    // FIX Bug #25: Check for replacement characters from invalid UTF-8
-    let s = path.to_string_lossy();
-    if s.contains('\u{FFFD}') {
-        // FIX Bug #9: Allow invalid UTF-8 paths (they are lossy converted but should still be scanned)
-        // tracing::warn!("Path contains invalid UTF-8: {:?}", path);
-        // return true; 
+}
+
+/// Returns the allocated size to count for a file and the bytes skipped because
+/// another link to the same data was already counted in this scan.
+fn dedupe_hardlink(hardlinks: Option<&HardlinkSet>, path: &Path, md: &fs::Metadata, alloc_sz: u64) -> (u64, u64) {
+    let Some(set) = hardlinks else { return (alloc_sz, 0) };
+    match file_identity(path, md) {
+        Some(key) if !set.first_seen(key) => (0, alloc_sz),
+        _ => (alloc_sz, 0),
     }
-    let normalized = s.replace('\\', "/");
-    if set.is_match(&normalized) {
-        return true;
+}
+
+/// Returns (volume serial, file index) for files with more than one hardlink.
+#[cfg(windows)]
+fn file_identity(path: &Path, _md: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS, FILE_READ_ATTRIBUTES,
+    };
+
+    // Attribute-only access also works for files that are opened exclusively by other processes
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES.0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS.0)
+        .open(path)
+        .ok()?;
+    let mut info = BY_HANDLE_FILE_INFORMATION::default();
+    unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle()), &mut info) }.ok()?;
+    if info.nNumberOfLinks <= 1 {
+        return None;
     }
-    // Also check just the filename for convenience
-    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-        if set.is_match(name) {
-            return true;
-        }
+    let index = ((info.nFileIndexHigh as u64) << 32) | (info.nFileIndexLow as u64);
+    Some((info.dwVolumeSerialNumber as u64, index))
+}
+
+/// Returns (device, inode) for files with more than one hardlink.
+#[cfg(unix)]
+fn file_identity(_path: &Path, md: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if md.nlink() <= 1 {
+        return None;
     }
-    false
+    Some((md.dev(), md.ino()))
+}
 
+#[cfg(not(any(windows, unix)))]
+fn file_identity(_path: &Path, _md: &fs::Metadata) -> Option<(u64, u64)> {
+    None
 }
 
-#[cfg(windows)]
-#[inline]
-fn is_unc_path(path: &Path) -> bool {
-    // Detect classic UNC (\\server\share\...), and extended UNC (\\?\UNC\server\share\...)
-    let s = path.as_os_str().to_string_lossy();
-    s.starts_with("\\\\?\\UNC\\") || s.starts_with("\\\\")
+/// The maximum number of links stored per scan.
+const MAX_STORED_LINKS: usize = 100_000;
+
+/// A symbolic link, junction or other reparse point met during a scan.
+#[derive(Debug, Clone)]
+struct LinkRecord {
+    path: String,
+    parent_path: Option<String>,
+    /// "symlink", "junction" or "reparse_point".
+    kind: &'static str,
+    /// Where the link points, as stored in the link; `None` if it cannot be read.
+    target: Option<String>,
+    /// Whether the scan descended into (or measured) the target.
+    followed: bool,
+    /// Whether the target lies outside the scanned root; `None` if it cannot be resolved.
+    outside_root: Option<bool>,
 }
 
-#[cfg(not(windows))]
-#[inline]
-fn is_unc_path(_path: &Path) -> bool {
-    false
+/// The links met during one scan and, when links are followed, the directories
+/// already entered.
+///
+/// Following links can reach a directory twice, or forever through a loop of
+/// junctions. Every directory is therefore entered at most once, identified by
+/// its canonical path.
+struct LinkTracker {
+    /// Canonical root paths by root index, to tell whether a link leaves its root.
+    roots: Vec<Option<PathBuf>>,
+    links: std::sync::Mutex<Vec<LinkRecord>>,
+    /// `None` unless the scan follows links.
+    visited: Option<std::sync::Mutex<std::collections::HashSet<PathBuf>>>,
 }
 
-#[cfg(windows)]
-#[inline]
-fn is_network_path(path: &Path) -> bool {
-    if is_unc_path(path) {
-        return true;
+impl LinkTracker {
+    fn new(root_paths: &[String], follow_symlinks: bool) -> Self {
+        Self {
+            roots: root_paths.iter().map(|root| fs::canonicalize(root).ok()).collect(),
+            links: Default::default(),
+            visited: follow_symlinks.then(Default::default),
+        }
+    }
+
+    fn record(&self, link: LinkRecord) {
+        let mut links = self.links.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if links.len() < MAX_STORED_LINKS {
+            links.push(link);
+        }
+    }
+
+    /// Marks `dir` as entered and returns whether this is the first time.
+    ///
+    /// Always `true` when links are not followed.
+    fn enter_dir(&self, dir: &Path) -> bool {
+        let Some(visited) = &self.visited else { return true };
+        let Ok(canonical) = fs::canonicalize(dir) else { return true };
+        visited.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(canonical)
+    }
+
+    /// Whether the directory with this canonical path was entered already.
+    fn was_entered(&self, canonical: &Path) -> bool {
+        self.visited
+            .as_ref()
+            .is_some_and(|v| v.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(canonical))
+    }
+
+    fn take(&self) -> Vec<LinkRecord> {
+        std::mem::take(&mut *self.links.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+/// What the traversal does with a symbolic link or junction.
+enum LinkStep {
+    /// The link is not followed.
+    Skip,
+    /// The link is followed; the metadata is that of its target.
+    Follow(fs::Metadata),
+    /// The target directory was already scanned; a `link_cycle` warning was sent.
+    Cycle,
+}
+
+/// Records the symbolic link or junction at `path` and decides whether to follow it.
+///
+/// Links are only followed with `follow_symlinks`. Dangling links are recorded
+/// but never followed.
+fn visit_link(
+    path: &Path,
+    md: &fs::Metadata,
+    root_index: usize,
+    options: &ScanOptions,
+    links: &LinkTracker,
+    tx: &tokio::sync::broadcast::Sender<ScanEvent>,
+) -> LinkStep {
+    let target = fs::read_link(path).ok().map(|t| t.to_string_lossy().to_string());
+    let resolved = fs::canonicalize(path).ok();
+    let outside_root = match (&resolved, links.roots.get(root_index).and_then(Option::as_ref)) {
+        (Some(resolved), Some(root)) => Some(!resolved.starts_with(root)),
+        _ => None,
+    };
+    let mut step = LinkStep::Skip;
+    if options.follow_symlinks {
+        if let (Some(resolved), Ok(target_md)) = (&resolved, fs::metadata(path)) {
+            step = if target_md.is_dir() && links.was_entered(resolved) {
+                let _ = tx.send(link_cycle_warning(path, resolved));
+                LinkStep::Cycle
+            } else {
+                LinkStep::Follow(target_md)
+            };
+        }
+    }
+    links.record(LinkRecord {
+        path: path.to_string_lossy().to_string(),
+        parent_path: parent_path_string(path),
+        kind: link_kind(path, md),
+        target,
+        followed: matches!(step, LinkStep::Follow(_)),
+        outside_root,
+    });
+    step
+}
+
+/// Records a directory that is a reparse point but not a link, such as a cloud
+/// placeholder, together with whether it is traversed.
+fn record_reparse_dir(path: &Path, followed: bool, links: &LinkTracker) {
+    links.record(LinkRecord {
+        path: path.to_string_lossy().to_string(),
+        parent_path: parent_path_string(path),
+        kind: "reparse_point",
+        target: None,
+        followed,
+        outside_root: None,
+    });
+}
+
+/// Warning emitted instead of entering a directory a second time.
+fn link_cycle_warning(path: &Path, target: &Path) -> ScanEvent {
+    ScanEvent::Warning {
+        path: path.to_string_lossy().to_string(),
+        code: "link_cycle".into(),
+        message: format!("{} was already scanned; not followed again", target.display()),
+    }
+}
+
+/// Tells junctions (mount points) and symbolic links apart by their reparse tag.
+#[cfg(windows)]
+fn link_kind(path: &Path, _md: &fs::Metadata) -> &'static str {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{FindClose, FindFirstFileW, WIN32_FIND_DATAW};
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+
+    let w: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut data = WIN32_FIND_DATAW::default();
+    // For reparse points the find data carries the reparse tag in dwReserved0
+    match unsafe { FindFirstFileW(PCWSTR(w.as_ptr()), &mut data) } {
+        Ok(handle) => {
+            let _ = unsafe { FindClose(handle) };
+            if data.dwReserved0 == IO_REPARSE_TAG_MOUNT_POINT {
+                "junction"
+            } else {
+                "symlink"
+            }
+        }
+        Err(_) => "symlink",
+    }
+}
+
+#[cfg(not(windows))]
+fn link_kind(_path: &Path, _md: &fs::Metadata) -> &'static str {
+    "symlink"
+}
+
+/// A directory waiting for a worker.
+struct DirTask {
+    path: PathBuf,
+    /// The depth below the root, 0 for the root itself.
+    depth: u32,
+    root_index: usize,
+    /// The directory this one was found in, `None` for roots.
+    parent: Option<Arc<OpenDir>>,
+}
+
+/// The totals of a directory tree: (dirs, files, logical, allocated).
+///
+/// `dirs` counts the directory itself, so a parent can add the totals of a
+/// finished subdirectory as they are.
+#[derive(Debug, Default, Clone, Copy)]
+struct Subtree {
+    dirs: u64,
+    files: u64,
+    logical: u64,
+    allocated: u64,
+}
+
+impl Subtree {
+    fn of(node: &NodeRecord) -> Self {
+        Self {
+            dirs: node.dir_count.saturating_add(1),
+            files: node.file_count,
+            logical: node.logical_size,
+            allocated: node.allocated_size,
+        }
+    }
+}
+
+/// A listed directory whose subdirectories may still be scanned by other workers.
+///
+/// The node record collects the totals of the directory's own files and of each
+/// finished subdirectory. Whoever finishes the last piece sends the record and
+/// adds its totals to the parent, so records are still only written once the
+/// whole subtree has been traversed.
+struct OpenDir {
+    /// `None` once the record was sent.
+    record: std::sync::Mutex<Option<NodeRecord>>,
+    /// The subdirectories not finished yet, plus one until the listing is done.
+    pending: AtomicUsize,
+    parent: Option<Arc<OpenDir>>,
+    /// Whether the directory was taken over from the scan a rescan repeats.
+    taken_over: bool,
+}
+
+impl OpenDir {
+    fn new(record: NodeRecord, parent: Option<Arc<OpenDir>>) -> Self {
+        Self {
+            record: std::sync::Mutex::new(Some(record)),
+            pending: AtomicUsize::new(1),
+            parent,
+            taken_over: false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<NodeRecord>> {
+        self.record.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn add(&self, sub: Subtree) {
+        if let Some(node) = self.lock().as_mut() {
+            node.dir_count = node.dir_count.saturating_add(sub.dirs);
+            node.file_count = node.file_count.saturating_add(sub.files);
+            node.logical_size = node.logical_size.saturating_add(sub.logical);
+            node.allocated_size = node.allocated_size.saturating_add(sub.allocated);
+        }
+    }
+
+    /// Takes the record, `None` once it was sent.
+    fn take_record(&self) -> Option<NodeRecord> {
+        self.lock().take()
+    }
+}
+
+/// The maximum number of directories waiting in the queue.
+///
+/// A worker finding more subdirectories while the queue is full keeps them, up
+/// to [`MAX_SPILLED_DIRS`], and offers them again or scans them itself once the
+/// directory's listing is closed.
+const MAX_QUEUED_DIRS: usize = 4_096;
+
+/// The maximum number of subdirectories one worker keeps while the queue is full.
+///
+/// A directory with more subdirectories than fit into the queue and the kept
+/// ones does not pile them up: the worker scans the most recent ones depth-first
+/// before it lists further, holding a handle per level for that time.
+const MAX_SPILLED_DIRS: usize = 1_024;
+
+/// The directories of all roots of a scan, waiting for the workers.
+///
+/// Workers take the most recently queued directory first, which keeps the
+/// traversal close to depth-first and the number of open directories small.
+struct DirQueue {
+    /// The scan the directories belong to.
+    scan_id: Uuid,
+    state: std::sync::Mutex<QueueState>,
+    ready: std::sync::Condvar,
+}
+
+struct QueueState {
+    tasks: Vec<DirTask>,
+    /// The number of workers currently scanning a directory taken from the queue.
+    busy: usize,
+}
+
+impl QueueState {
+    /// Takes the most recently queued directory for a worker.
+    fn take(&mut self) -> Option<DirTask> {
+        let task = self.tasks.pop()?;
+        self.busy += 1;
+        Some(task)
+    }
+}
+
+impl DirQueue {
+    fn new(scan_id: Uuid, tasks: Vec<DirTask>) -> Self {
+        let state = QueueState { tasks, busy: 0 };
+        Self { scan_id, state: std::sync::Mutex::new(state), ready: std::sync::Condvar::new() }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queues `task`, or hands it back if the queue is full.
+    fn push(&self, task: DirTask) -> Result<(), DirTask> {
+        let mut state = self.lock();
+        if state.tasks.len() >= MAX_QUEUED_DIRS {
+            return Err(task);
+        }
+        state.tasks.push(task);
+        note_peak(self.scan_id, Peak::QueuedDirs, state.tasks.len());
+        drop(state);
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Takes a directory without waiting.
+    fn try_pop(&self) -> Option<DirTask> {
+        let mut state = self.lock();
+        let task = state.take();
+        note_peak(self.scan_id, Peak::ActiveWorkers, state.busy);
+        task
+    }
+
+    /// Waits for a directory; `None` once the scan is stopped or all directories
+    /// are done.
+    fn pop(&self, stop: &CancellationToken) -> Option<DirTask> {
+        let mut state = self.lock();
+        loop {
+            if stop.is_cancelled() {
+                return None;
+            }
+            if let Some(task) = state.take() {
+                note_peak(self.scan_id, Peak::ActiveWorkers, state.busy);
+                return Some(task);
+            }
+            if state.busy == 0 {
+                return None;
+            }
+            // Busy workers may still queue subdirectories
+            state = match self.ready.wait_timeout(state, pause::CANCEL_POLL) {
+                Ok((guard, _)) => guard,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+
+    /// Marks a directory taken from the queue as done.
+    fn done(&self) {
+        let mut state = self.lock();
+        state.busy -= 1;
+        if state.busy == 0 && state.tasks.is_empty() {
+            drop(state);
+            self.ready.notify_all();
+        }
+    }
+}
+
+/// The records and totals a worker has not sent to the aggregator yet.
+struct Outbox {
+    /// The scan the records belong to.
+    scan_id: Uuid,
+    tx: mpsc::Sender<ScanBatch>,
+    /// Cancelled when the aggregator is gone.
+    stop: CancellationToken,
+    limit: usize,
+    /// Counts the bytes of the buffers; its limit flushes them as well.
+    meter: Arc<BufferMeter>,
+    /// The estimated bytes of `nodes`, `files` and `copied`.
+    bytes: usize,
+    root_index: usize,
+    nodes: Vec<NodeRecord>,
+    files: Vec<FileRecord>,
+    /// The directories taken over by a rescan, see [`ScanBatch`].
+    copied: Vec<String>,
+    summary: ScanResultSummary,
+    /// The totals sent so far, for progress events.
+    sent: Subtree,
+}
+
+impl Outbox {
+    /// Makes the buffered records and totals go to `root_index`, sending those of another root first.
+    fn switch_root(&mut self, root_index: usize) {
+        if root_index != self.root_index {
+            self.flush();
+            self.root_index = root_index;
+        }
+    }
+
+    fn push_node(&mut self, node: NodeRecord) {
+        let bytes = node.buffered_bytes();
+        self.nodes.push(node);
+        self.buffered(bytes);
+    }
+
+    fn push_file(&mut self, file: FileRecord) {
+        let bytes = file.buffered_bytes();
+        self.files.push(file);
+        self.buffered(bytes);
+    }
+
+    fn push_copied(&mut self, dir: String) {
+        let bytes = budget::copied_bytes(&dir);
+        self.copied.push(dir);
+        self.buffered(bytes);
+    }
+
+    fn buffered(&mut self, bytes: usize) {
+        self.meter.add(bytes);
+        self.bytes += bytes;
+        // FIX Bug #45 - Partial flush with proper error handling
+        let buffered = self.nodes.len() + self.files.len() + self.copied.len();
+        if buffered >= self.limit || self.bytes >= self.meter.limit() {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let buffered = self.nodes.len() + self.files.len() + self.copied.len();
+        note_peak(self.scan_id, Peak::BufferedRecords, buffered);
+        let s = &self.summary;
+        if buffered == 0 && s.total_dirs == 0 && s.warnings == 0 {
+            return;
+        }
+        self.sent.dirs = self.sent.dirs.saturating_add(s.total_dirs);
+        self.sent.files = self.sent.files.saturating_add(s.total_files);
+        self.sent.logical = self.sent.logical.saturating_add(s.total_logical_size);
+        self.sent.allocated = self.sent.allocated.saturating_add(s.total_allocated_size);
+        let batch = (
+            self.root_index,
+            std::mem::take(&mut self.nodes),
+            std::mem::take(&mut self.files),
+            std::mem::take(&mut self.copied),
+            std::mem::take(&mut self.summary),
+        );
+        // The aggregator counts the records again once it received them
+        self.meter.sub(std::mem::take(&mut self.bytes));
+        if self.tx.blocking_send(batch).is_err() {
+            tracing::warn!("Channel closed during partial flush");
+            self.stop.cancel();
+        }
+    }
+
+    /// A progress event with the totals of this worker, including the files listed in `own`.
+    fn progress(&self, path: &Path, own: &Subtree) -> ScanEvent {
+        ScanEvent::Progress {
+            current_path: path.to_string_lossy().to_string(),
+            dirs_scanned: self.sent.dirs + self.summary.total_dirs,
+            files_scanned: self.sent.files + self.summary.total_files + own.files,
+            logical_size: self.sent.logical + self.summary.total_logical_size + own.logical,
+            allocated_size: self.sent.allocated + self.summary.total_allocated_size + own.allocated,
+            estimated_percent: None,
+        }
+    }
+}
+
+/// Everything the workers of one scan share.
+struct ScanCtx {
+    options: ScanOptions,
+    globset: GlobSet,
+    includes: GlobSet,
+    tx_sse: tokio::sync::broadcast::Sender<ScanEvent>,
+    tx_out: mpsc::Sender<ScanBatch>,
+    /// Cancelled with the scan, or by a worker when the aggregator is gone.
+    stop: CancellationToken,
+    pause: Arc<PauseGate>,
+    flush_threshold: usize,
+    /// Counts the bytes buffered by the workers and the aggregator.
+    meter: Arc<BufferMeter>,
+    max_entries_per_dir: Option<u64>,
+    hardlinks: Option<HardlinkSet>,
+    owners: Option<Arc<OwnerLookup>>,
+    categories: Option<Arc<CategoryMap>>,
+    /// Bounds the workers hashing files, only set for scans with `hash_min_size`.
+    hashes: Option<HashGate>,
+    /// The directories finished by an earlier run, only set when resuming.
+    persisted: Option<PersistedDirs>,
+    /// The directories of the scan a rescan repeats, only set for rescans.
+    previous: Option<PreviousDirs>,
+    links: Arc<LinkTracker>,
+    /// Counts the busy workers in `scanner_active_workers`.
+    metrics: Metrics,
+    queue: DirQueue,
+    /// The listed roots by index, to store the totals of roots a cancelled scan did not finish.
+    roots: Vec<std::sync::OnceLock<Arc<OpenDir>>>,
+}
+
+impl ScanCtx {
+    /// Runs one worker: scans directories from the queue until all are done.
+    fn work(&self) {
+        let _priority = priority::enter(self.options.io_priority);
+        let mut out = Outbox {
+            scan_id: self.queue.scan_id,
+            tx: self.tx_out.clone(),
+            stop: self.stop.clone(),
+            limit: self.flush_threshold.max(1),
+            meter: self.meter.clone(),
+            bytes: 0,
+            root_index: 0,
+            nodes: Vec::new(),
+            files: Vec::new(),
+            copied: Vec::new(),
+            summary: ScanResultSummary::default(),
+            sent: Subtree::default(),
+        };
+        loop {
+            let task = match self.queue.try_pop() {
+                Some(task) => task,
+                None => {
+                    // Let the aggregator have the records while waiting
+                    out.flush();
+                    match self.queue.pop(&self.stop) {
+                        Some(task) => task,
+                        None => break,
+                    }
+                }
+            };
+            let _active = GaugeShare::one(&self.metrics.scanner_active_workers);
+            out.switch_root(task.root_index);
+            self.run(task, &mut out);
+            self.queue.done();
+        }
+        out.flush();
+    }
+
+    /// Scans a directory taken from the queue, and the subdirectories the full queue handed back.
+    fn run(&self, task: DirTask, out: &mut Outbox) {
+        let mut spilled = Vec::new();
+        self.run_one(task, out, &mut spilled);
+        // Offered to the queue again first, so idle workers can take them
+        while let Some(child) = spilled.pop() {
+            if let Err(child) = self.queue.push(child) {
+                self.run_one(child, out, &mut spilled);
+            }
+        }
+    }
+
+    /// Scans one directory and finishes it, and its ancestors, if nothing below is left.
+    ///
+    /// Subdirectories the full queue does not take go to `spilled`, see [`ScanCtx::spill`].
+    fn run_one(&self, task: DirTask, out: &mut Outbox, spilled: &mut Vec<DirTask>) {
+        let mut opened = None;
+        // FIX Bug #11: Ensure proper cleanup even on panic
+        let listed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.list(&task, out, &mut opened, spilled)
+        }));
+        let taken_over = listed.unwrap_or_else(|_| {
+            tracing::error!("Worker panicked while scanning {:?}", task.path);
+            // FIX Bug #3: Track panic as warning to avoid silent data loss
+            out.summary.warnings += 1;
+            None
+        });
+        if self.stop.is_cancelled() {
+            // The listing may be incomplete, so neither the directory nor its ancestors may be finished
+            return;
+        }
+        match (opened, task.parent) {
+            (Some(dir), _) => self.release(dir, out),
+            (None, Some(parent)) => {
+                if let Some(sub) = taken_over {
+                    parent.add(sub);
+                }
+                self.release(parent, out);
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Keeps a subdirectory the full queue handed back, for [`ScanCtx::run`] to scan after the listing.
+    ///
+    /// Once [`MAX_SPILLED_DIRS`] are kept, the most recent ones are offered to
+    /// the queue again or scanned right away, before the listing goes on.
+    fn spill(&self, child: DirTask, out: &mut Outbox, spilled: &mut Vec<DirTask>) {
+        spilled.push(child);
+        note_peak(self.queue.scan_id, Peak::SpilledDirs, spilled.len());
+        while spilled.len() >= MAX_SPILLED_DIRS {
+            let Some(child) = spilled.pop() else { break };
+            if let Err(child) = self.queue.push(child) {
+                self.run_one(child, out, spilled);
+            }
+        }
+    }
+
+    /// Drops one pending piece of `dir`; the last one sends its record and moves on to the parent.
+    fn release(&self, mut dir: Arc<OpenDir>, out: &mut Outbox) {
+        while dir.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let Some(node) = dir.take_record() else { return };
+            let sub = Subtree::of(&node);
+            // Sent no later than the node row, which must only be stored after the copied files
+            if dir.taken_over {
+                out.push_copied(node.path.clone());
+            }
+            out.push_node(node);
+            match dir.parent.clone() {
+                Some(parent) => {
+                    parent.add(sub);
+                    dir = parent;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Lists a directory: records its files and queues its subdirectories.
+    ///
+    /// Sets `opened` once the directory has a node record. Returns the stored
+    /// totals instead if an earlier run of a resumed scan finished it.
+    /// Subdirectories that do not fit into the queue go to `spilled`.
+    fn list(
+        &self,
+        task: &DirTask,
+        out: &mut Outbox,
+        opened: &mut Option<Arc<OpenDir>>,
+        spilled: &mut Vec<DirTask>,
+    ) -> Option<Subtree> {
+        let dir = task.path.as_path();
+        let is_root = task.parent.is_none();
+        let options = &self.options;
+        if self.pause.is_paused() {
+            // Let the aggregator persist the buffered records while paused
+            out.flush();
+            self.pause.wait_while_paused(&self.stop);
+        }
+        if self.stop.is_cancelled() || matches_excludes(dir, &self.globset) {
+            return None;
+        }
+        if let Some(done) = self.persisted.as_ref().and_then(|p| p.get(dir.to_string_lossy().as_ref())) {
+            let summary = &mut out.summary;
+            let dirs = done.dir_count.saturating_add(1);
+            summary.total_dirs = summary.total_dirs.saturating_add(dirs);
+            summary.total_files = summary.total_files.saturating_add(done.file_count);
+            summary.total_logical_size = summary.total_logical_size.saturating_add(done.logical_size);
+            summary.total_allocated_size = summary.total_allocated_size.saturating_add(done.allocated_size);
+            summary.latest_mtime = max_opt(summary.latest_mtime, done.mtime);
+            summary.latest_atime = max_opt(summary.latest_atime, done.atime);
+            return Some(Subtree {
+                dirs,
+                files: done.file_count,
+                logical: done.logical_size,
+                allocated: done.allocated_size,
+            });
+        }
+
+        let meta = match fs::metadata(dir) {
+            Ok(m) => m,
+            Err(_) => {
+                out.summary.warnings += 1;
+                let _ = self.tx_sse.send(ScanEvent::Warning {
+                    path: dir.to_string_lossy().to_string(),
+                    code: "metadata_failed".into(),
+                    message: if is_root { "failed to stat root" } else { "failed to stat directory" }.into(),
+                });
+                return None;
+            }
+        };
+        if check_listed(dir, &meta, options, is_root).skipped() {
+            return None;
+        }
+        // Roots are entered even if an earlier root contains them
+        if !self.links.enter_dir(dir) && !is_root {
+            // Reached a second time through a followed link
+            out.summary.warnings += 1;
+            let target = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+            let _ = self.tx_sse.send(link_cycle_warning(dir, &target));
+            return None;
+        }
+
+        let dir_mtime = system_time_to_secs(meta.modified().ok());
+        let dir_atime = system_time_to_secs(meta.accessed().ok());
+        out.summary.total_dirs += 1;
+        out.summary.latest_mtime = max_opt(out.summary.latest_mtime, dir_mtime);
+        out.summary.latest_atime = max_opt(out.summary.latest_atime, dir_atime);
+        let dir_str = dir.to_string_lossy().to_string();
+        if has_lossy_name(dir) {
+            out.summary.warnings += 1;
+            let _ = self.tx_sse.send(lossy_name_warning(dir));
+        }
+        let record = NodeRecord {
+            path: dir_str.clone(),
+            parent_path: parent_path_string(dir),
+            depth: calc_depth(dir),
+            is_dir: true,
+            logical_size: 0,
+            allocated_size: 0,
+            file_count: 0,
+            dir_count: 0,
+            mtime: dir_mtime,
+            atime: dir_atime,
+            fingerprint: None,
+            empty_file_count: 0,
+            path_raw: raw_path_bytes(dir),
+        };
+        // Roots are always listed, their node rows carry the times of their entries
+        let unchanged = self.previous.as_ref().filter(|_| !is_root).and_then(|dirs| dirs.get(&dir_str));
+        if let Some(previous) = unchanged.filter(|p| p.reusable && p.mtime.is_some() && p.mtime == dir_mtime)
+        {
+            self.take_over(task, previous, record, out, opened, spilled);
+            return None;
+        }
+        let open = Arc::new(OpenDir::new(record, task.parent.clone()));
+        *opened = Some(open.clone());
+        if is_root {
+            let _ = self.roots[task.root_index].set(open.clone());
+        }
+
+        let mut own = Subtree::default();
+        let mut empty: u64 = 0;
+        // A root's node carries the latest times of its direct entries too
+        let (mut node_mtime, mut node_atime) = (dir_mtime, dir_atime);
+        // FIX Bug #12: Use u64 instead of u32 to prevent overflow on large directories
+        let mut sent = 0u64;
+        let mut entries = 0u64;
+        let mut last_emit = Instant::now();
+        let mut fp = DirFingerprint::default();
+        // Skipped entries make the fingerprint unreliable, so it is not stored then
+        let mut listing_complete = true;
+
+        match fs::read_dir(dir) {
+            Ok(rd) => {
+                for entry in rd.flatten() {
+                    if self.pause.is_paused() {
+                        out.flush();
+                        self.pause.wait_while_paused(&self.stop);
+                    }
+                    if self.stop.is_cancelled() {
+                        listing_complete = false;
+                        break;
+                    }
+                    entries += 1;
+                    if let Some(max) = self.max_entries_per_dir {
+                        if entries > max {
+                            listing_complete = false;
+                            out.summary.warnings += 1;
+                            let _ = self.tx_sse.send(entry_limit_warning(dir, max));
+                            break;
+                        }
+                    }
+                    let path = entry.path();
+                    if matches_excludes(&path, &self.globset) {
+                        continue;
+                    }
+                    let md = match entry.metadata() {
+                        Ok(m) => m,
+                        Err(_) => {
+                            listing_complete = false;
+                            out.summary.warnings += 1;
+                            if is_root {
+                                let _ = self.tx_sse.send(ScanEvent::Warning {
+                                    path: path.to_string_lossy().to_string(),
+                                    code: "metadata_failed".into(),
+                                    message: "failed to stat".into(),
+                                });
+                            }
+                            continue;
+                        }
+                    };
+
+                    let entry_mtime = system_time_to_secs(md.modified().ok());
+                    let entry_atime = system_time_to_secs(md.accessed().ok());
+                    fp.add(&entry.file_name().to_string_lossy(), md.is_dir(), md.len(), entry_mtime);
+                    out.summary.latest_mtime = max_opt(out.summary.latest_mtime, entry_mtime);
+                    out.summary.latest_atime = max_opt(out.summary.latest_atime, entry_atime);
+                    if is_root {
+                        node_mtime = max_opt(node_mtime, entry_mtime);
+                        node_atime = max_opt(node_atime, entry_atime);
+                    }
+
+                    let md = if md.file_type().is_symlink() {
+                        match visit_link(&path, &md, task.root_index, options, &self.links, &self.tx_sse) {
+                            LinkStep::Follow(target_md) => target_md,
+                            LinkStep::Cycle => {
+                                out.summary.warnings += 1;
+                                continue;
+                            }
+                            LinkStep::Skip => continue,
+                        }
+                    } else {
+                        md
+                    };
+
+                    let checks = check_entry(&path, &md, options, &self.includes, task.depth);
+                    if md.is_dir() {
+                        if checks.reparse_point {
+                            record_reparse_dir(&path, checks.followed, &self.links);
+                        }
+                        if checks.skipped() {
+                            continue;
+                        }
+                        open.pending.fetch_add(1, Ordering::AcqRel);
+                        let child = DirTask {
+                            path: path.clone(),
+                            depth: task.depth + 1,
+                            root_index: task.root_index,
+                            parent: Some(open.clone()),
+                        };
+                        if let Err(child) = self.queue.push(child) {
+                            self.spill(child, out, spilled);
+                        }
+                    } else if md.is_file() {
+                        if checks.skipped() {
+                            continue;
+                        }
+                        own.files += 1;
+                        if md.len() == 0 {
+                            empty += 1;
+                        }
+                        let sizes = measure_file(options, &path, &md);
+                        let (alloc_sz, saved) =
+                            dedupe_hardlink(self.hardlinks.as_ref(), &path, &md, sizes.allocated);
+                        out.summary.dedup_saved_bytes = out.summary.dedup_saved_bytes.saturating_add(saved);
+                        // FIX Bug #4: Use saturating_add to prevent overflow/panic
+                        if options.measure_logical {
+                            own.logical = own.logical.saturating_add(sizes.logical);
+                        }
+                        own.allocated = own.allocated.saturating_add(alloc_sz);
+                        if has_lossy_name(&path) {
+                            out.summary.warnings += 1;
+                            let _ = self.tx_sse.send(lossy_name_warning(&path));
+                        }
+                        let hash = self.hash_of(&path, md.len(), out);
+                        out.push_file(FileRecord {
+                            owner: self.owners.as_deref().map(|o| o.owner_of(&path, &md)),
+                            category: self.categories.as_deref().map(|c| c.classify(&path)),
+                            hash,
+                            path_raw: raw_path_bytes(&path),
+                            path: path.to_string_lossy().to_string(),
+                            parent_path: Some(dir_str.clone()),
+                            logical_size: sizes.logical,
+                            allocated_size: alloc_sz,
+                            mtime: entry_mtime,
+                            atime: entry_atime,
+                            ads: sizes.ads,
+                        });
+                    }
+
+                    sent = sent.saturating_add(1);
+                    // Reduzierte Progress-Updates für bessere Performance
+                    // Zusätzlich: Zeitbasierte Fortschrittsupdates (z. B. auf langsamen Netzlaufwerken)
+                    if sent.is_multiple_of(512) || last_emit.elapsed() >= std::time::Duration::from_millis(2000) {
+                        let _ = self.tx_sse.send(out.progress(&path, &own));
+                        last_emit = Instant::now();
+                    }
+                }
+            }
+            Err(_) => {
+                listing_complete = false;
+                out.summary.warnings += 1;
+                let _ = self.tx_sse.send(ScanEvent::Warning {
+                    path: dir_str,
+                    code: "read_dir_failed".into(),
+                    message: "failed to read directory".into(),
+                });
+            }
+        }
+
+        let summary = &mut out.summary;
+        summary.total_files = summary.total_files.saturating_add(own.files);
+        summary.total_logical_size = summary.total_logical_size.saturating_add(own.logical);
+        summary.total_allocated_size = summary.total_allocated_size.saturating_add(own.allocated);
+        summary.empty_files = summary.empty_files.saturating_add(empty);
+        if let Some(node) = open.lock().as_mut() {
+            node.mtime = node_mtime;
+            node.atime = node_atime;
+            node.fingerprint = listing_complete.then(|| fp.finish());
+            node.empty_file_count = empty;
+        }
+        open.add(own);
+        None
+    }
+
+    /// Hashes a file of at least `hash_min_size` bytes.
+    ///
+    /// A file that cannot be read (locked, vanished, changed while read) is
+    /// reported as a `hash_failed` warning and stored without a hash.
+    fn hash_of(&self, path: &Path, size: u64, out: &mut Outbox) -> Option<String> {
+        let min = self.options.hash_min_size?;
+        let gate = self.hashes.as_ref().filter(|_| size >= min)?;
+        match hashing::hash_file(gate, path, size, &self.stop) {
+            Ok(hash) => hash,
+            Err(e) => {
+                out.summary.warnings += 1;
+                let _ = self.tx_sse.send(ScanEvent::Warning {
+                    path: path.to_string_lossy().to_string(),
+                    code: "hash_failed".into(),
+                    message: format!("failed to hash the file: {}", e),
+                });
+                None
+            }
+        }
+    }
+
+    /// Takes over a directory that did not change since the scan a rescan repeats.
+    ///
+    /// Its files are counted with their stored totals and copied once the
+    /// directory is finished. A directory's modification time only changes
+    /// with its own entries, so the subdirectories stored for it are queued and
+    /// checked on their own.
+    fn take_over(
+        &self,
+        task: &DirTask,
+        previous: &PreviousDir,
+        mut record: NodeRecord,
+        out: &mut Outbox,
+        opened: &mut Option<Arc<OpenDir>>,
+        spilled: &mut Vec<DirTask>,
+    ) {
+        record.fingerprint = previous.fingerprint;
+        record.empty_file_count = previous.empty_files;
+        let mut open = OpenDir::new(record, task.parent.clone());
+        open.taken_over = true;
+        let open = Arc::new(open);
+        *opened = Some(open.clone());
+        for sub in &previous.subdirs {
+            open.pending.fetch_add(1, Ordering::AcqRel);
+            let child = DirTask {
+                path: PathBuf::from(sub),
+                depth: task.depth + 1,
+                root_index: task.root_index,
+                parent: Some(open.clone()),
+            };
+            if let Err(child) = self.queue.push(child) {
+                self.spill(child, out, spilled);
+            }
+        }
+        let own = Subtree {
+            dirs: 0,
+            files: previous.files,
+            logical: if self.options.measure_logical { previous.logical } else { 0 },
+            allocated: previous.allocated,
+        };
+        let summary = &mut out.summary;
+        summary.total_files = summary.total_files.saturating_add(own.files);
+        summary.total_logical_size = summary.total_logical_size.saturating_add(own.logical);
+        summary.total_allocated_size = summary.total_allocated_size.saturating_add(own.allocated);
+        summary.empty_files = summary.empty_files.saturating_add(previous.empty_files);
+        summary.latest_mtime = max_opt(summary.latest_mtime, previous.latest_mtime);
+        summary.latest_atime = max_opt(summary.latest_atime, previous.latest_atime);
+        open.add(own);
+    }
+}
+
+/// Scans the subtree of `dir` on the calling thread and returns its records and
+/// totals.
+///
+/// Used for directories added to a finished scan, whose records are written in
+/// one go; `depth` is the depth of `dir` below its root. Hardlinks are not
+/// deduplicated and links met are not recorded, the tracker only guards
+/// against cycles.
+#[allow(clippy::too_many_arguments)]
+fn scan_subtree(
+    dir: &Path,
+    depth: u32,
+    options: &ScanOptions,
+    globset: &GlobSet,
+    includes: &GlobSet,
+    tx: &tokio::sync::broadcast::Sender<ScanEvent>,
+    cancel: &CancellationToken,
+    max_entries_per_dir: Option<u64>,
+    categories: Option<Arc<CategoryMap>>,
+) -> anyhow::Result<(Vec<NodeRecord>, Vec<FileRecord>, Subtree)> {
+    // Collects the totals of `dir` without ever being finished itself
+    let collector = Arc::new(OpenDir::new(
+        NodeRecord {
+            path: String::new(),
+            parent_path: None,
+            depth: 0,
+            is_dir: true,
+            logical_size: 0,
+            allocated_size: 0,
+            file_count: 0,
+            dir_count: 0,
+            mtime: None,
+            atime: None,
+            fingerprint: None,
+            empty_file_count: 0,
+            path_raw: None,
+        },
+        None,
+    ));
+    collector.pending.fetch_add(1, Ordering::AcqRel);
+    let task = DirTask { path: dir.to_path_buf(), depth, root_index: 0, parent: Some(collector.clone()) };
+    // The aggregator channel stays unused because the threshold is never reached
+    let (tx_out, _rx_out) = mpsc::channel(1);
+    let ctx = ScanCtx {
+        options: options.clone(),
+        globset: globset.clone(),
+        includes: includes.clone(),
+        tx_sse: tx.clone(),
+        tx_out: tx_out.clone(),
+        stop: cancel.child_token(),
+        pause: Default::default(),
+        flush_threshold: usize::MAX,
+        meter: Arc::new(BufferMeter::new(usize::MAX, &Metrics::default().scanner_buffered_bytes)),
+        max_entries_per_dir,
+        hardlinks: None,
+        owners: options.capture_owner.then(|| Arc::new(OwnerLookup::default())),
+        categories,
+        hashes: options.hash_min_size.map(|_| HashGate::new(1)),
+        persisted: None,
+        previous: None,
+        links: Arc::new(LinkTracker::new(&[], options.follow_symlinks)),
+        metrics: Metrics::default(),
+        // Not a scan of its own, so nothing is recorded under a scan ID
+        queue: DirQueue::new(Uuid::nil(), vec![task]),
+        roots: Vec::new(),
+    };
+    let mut out = Outbox {
+        scan_id: ctx.queue.scan_id,
+        tx: tx_out,
+        stop: ctx.stop.clone(),
+        limit: usize::MAX,
+        meter: ctx.meter.clone(),
+        bytes: 0,
+        root_index: 0,
+        nodes: Vec::new(),
+        files: Vec::new(),
+        copied: Vec::new(),
+        summary: ScanResultSummary::default(),
+        sent: Subtree::default(),
+    };
+    while let Some(task) = ctx.queue.try_pop() {
+        ctx.run(task, &mut out);
+        ctx.queue.done();
+    }
+    if cancel.is_cancelled() {
+        anyhow::bail!("cancelled")
+    }
+    let mut totals = collector.take_record().map(|node| Subtree::of(&node)).unwrap_or_default();
+    // The collector counts itself as a directory
+    totals.dirs -= 1;
+    Ok((out.nodes, out.files, totals))
+}
+
+/// A size [`note_peak`] records per scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Peak {
+    /// File/node records buffered by one worker before a flush.
+    BufferedRecords,
+    /// Workers busy with a directory at the same time.
+    ActiveWorkers,
+    /// Directories waiting in the queue.
+    QueuedDirs,
+    /// Subdirectories one worker kept while the queue was full.
+    SpilledDirs,
+}
+
+#[inline]
+fn note_peak(_scan_id: Uuid, _peak: Peak, _n: usize) {
+    #[cfg(test)]
+    instrumentation::note(_scan_id, _peak, _n);
+}
+
+/// Peak sizes observed while scanning, by scan, recorded only in test builds.
+#[cfg(test)]
+pub(crate) mod instrumentation {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use uuid::Uuid;
+
+    use super::Peak;
+
+    static PEAKS: Mutex<BTreeMap<(Uuid, Peak), usize>> = Mutex::new(BTreeMap::new());
+
+    pub(super) fn note(scan_id: Uuid, peak: Peak, n: usize) {
+        let mut peaks = PEAKS.lock().unwrap_or_else(|e| e.into_inner());
+        let max = peaks.entry((scan_id, peak)).or_insert(0);
+        *max = (*max).max(n);
+    }
+
+    /// The largest size of `peak` a scan reached; 0 if none was recorded.
+    pub fn peak(scan_id: Uuid, peak: Peak) -> usize {
+        let peaks = PEAKS.lock().unwrap_or_else(|e| e.into_inner());
+        peaks.get(&(scan_id, peak)).copied().unwrap_or(0)
+    }
+}
+
+/// The outcome of the checks that decide whether the scanner takes in a
+/// directory or file, apart from the exclude patterns.
+///
+/// [`check_entry`] and [`check_listed`] are the only place these rules live:
+/// the scanner acts on their outcome and `GET /scans/{id}/explain` reports it,
+/// so the explanation cannot drift from what a scan does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EntryChecks {
+    /// A directory that is a reparse point, such as a junction or cloud placeholder.
+    pub reparse_point: bool,
+    /// The reparse point is traversed anyway because it lies on a network share.
+    pub network_share: bool,
+    /// The reparse point is traversed, with `follow_symlinks` or as a network share.
+    pub followed: bool,
+    /// Hidden or system, while hidden entries are left out.
+    pub hidden: bool,
+    /// A directory below `max_depth`.
+    pub too_deep: bool,
+    /// A file that matches none of the include patterns.
+    pub not_included: bool,
+}
+
+impl EntryChecks {
+    /// Whether the scanner leaves the entry out.
+    pub fn skipped(&self) -> bool {
+        (self.reparse_point && !self.followed) || self.hidden || self.too_deep || self.not_included
+    }
+}
+
+/// Checks a directory before it is listed; roots only get these checks.
+///
+/// `share_exempt` lets a reparse point on a network share through, which
+/// applies to roots and to the directories directly below a root.
+pub(crate) fn check_listed(
+    dir: &Path,
+    md: &fs::Metadata,
+    options: &ScanOptions,
+    share_exempt: bool,
+) -> EntryChecks {
+    let reparse_point = is_reparse_point(md);
+    // UNC/DFS shares and mapped network drives should be traversed even if marked as reparse points
+    let network_share = reparse_point && !options.follow_symlinks && share_exempt && is_network_path(dir);
+    EntryChecks {
+        reparse_point,
+        network_share,
+        followed: reparse_point && (options.follow_symlinks || network_share),
+        hidden: !options.include_hidden && is_hidden_or_system(dir, md),
+        ..Default::default()
+    }
+}
+
+/// Checks an entry found while listing a directory at `parent_depth` below its root.
+///
+/// `md` is the metadata of the entry, or of the target of a followed link.
+pub(crate) fn check_entry(
+    path: &Path,
+    md: &fs::Metadata,
+    options: &ScanOptions,
+    includes: &GlobSet,
+    parent_depth: u32,
+) -> EntryChecks {
+    if md.is_dir() {
+        let mut checks = check_listed(path, md, options, parent_depth == 0);
+        // FIX Bug #9: depth is 0-indexed from the root, so max_depth = 0 scans only the root
+        checks.too_deep = options.max_depth.is_some_and(|max_d| parent_depth >= max_d);
+        checks
+    } else {
+        EntryChecks {
+            hidden: !options.include_hidden && is_hidden_or_system(path, md),
+            not_included: md.is_file() && !matches_includes(path, includes),
+            ..Default::default()
+        }
+    }
+}
+
+fn build_globset(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut b = GlobSetBuilder::new();
+    for p in patterns {
+        let trimmed = p.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        // FIX Bug #29: Validate pattern length and complexity to prevent DoS
+        if trimmed.len() > 1024 {
+            tracing::warn!("Glob pattern too long, skipping: {} chars", trimmed.len());
+            continue;
+        }
+        // Check for excessive wildcards that could cause exponential backtracking
+        let wildcard_count = trimmed.chars().filter(|&c| c == '*' || c == '?').count();
+        if wildcard_count > 20 {
+            tracing::warn!("Glob pattern has too many wildcards ({}), skipping: {}", wildcard_count, trimmed);
+            continue;
+        }
+        // FIX Bug #24: Normalize path separators consistently
+        // We use forward slashes internally for cross-platform compatibility
+        // Windows APIs handle forward slashes correctly in most cases
+        let norm = trimmed.replace('\\', "/");
+        // Catch glob compilation errors
+        let g = Glob::new(&norm).map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", norm, e))?;
+        b.add(g);
+    }
+    Ok(b.build()?)
+}
+
+fn matches_excludes(path: &Path, set: &GlobSet) -> bool {
+    if set.is_empty() {
+        return false;
+    }
+    // Paths that are not valid Unicode match with replacement characters in place of the invalid parts
+    let s = path.to_string_lossy();
+    let normalized = s.replace('\\', "/");
+    if set.is_match(&normalized) {
+        return true;
+    }
+    // Also check just the filename for convenience
+    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+        if set.is_match(name) {
+            return true;
+        }
+    }
+    false
+
+}
+
+/// Returns whether a file matches the include patterns; an empty set includes every file.
+///
+/// Includes match like excludes: against the full path or just the file name.
+fn matches_includes(path: &Path, set: &GlobSet) -> bool {
+    set.is_empty() || matches_excludes(path, set)
+}
+
+#[cfg(windows)]
+#[inline]
+fn is_unc_path(path: &Path) -> bool {
+    // Detect classic UNC (\\server\share\...), and extended UNC (\\?\UNC\server\share\...)
+    let s = path.as_os_str().to_string_lossy();
+    s.starts_with("\\\\?\\UNC\\") || s.starts_with("\\\\")
+}
+
+#[cfg(windows)]
+#[inline]
+fn is_network_path(path: &Path) -> bool {
+    if is_unc_path(path) {
+        return true;
     }
     // Detect mapped network drives (e.g., Z:\)
     let s = path.as_os_str().to_string_lossy();
@@ -878,7 +2080,7 @@ fn is_network_path(_path: &Path) -> bool {
 }
 
 #[cfg(windows)]
-fn is_hidden_or_system(_path: &Path, md: &fs::Metadata) -> bool {
+pub(crate) fn is_hidden_or_system(_path: &Path, md: &fs::Metadata) -> bool {
     const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
     const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
     let attrs = md.file_attributes();
@@ -886,7 +2088,7 @@ fn is_hidden_or_system(_path: &Path, md: &fs::Metadata) -> bool {
 }
 
 #[cfg(not(windows))]
-fn is_hidden_or_system(path: &Path, _md: &fs::Metadata) -> bool {
+pub(crate) fn is_hidden_or_system(path: &Path, _md: &fs::Metadata) -> bool {
     // Check for dotfiles on Unix
     path.file_name()
         .and_then(|n| n.to_str())
@@ -895,21 +2097,24 @@ fn is_hidden_or_system(path: &Path, _md: &fs::Metadata) -> bool {
 }
 
 #[cfg(windows)]
-fn is_reparse_point(md: &fs::Metadata) -> bool {
+pub(crate) fn is_reparse_point(md: &fs::Metadata) -> bool {
     const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
     (md.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT) != 0
 }
 
 #[cfg(not(windows))]
-fn is_reparse_point(_md: &fs::Metadata) -> bool {
+pub(crate) fn is_reparse_point(_md: &fs::Metadata) -> bool {
     false
 }
 
-// Cache für häufig abgefragte Pfade
+// Cache für häufig abgefragte Pfade (nur Windows braucht einen zusätzlichen Systemaufruf)
+#[cfg(windows)]
 use lru::LruCache;
+#[cfg(windows)]
 use std::sync::Mutex;
 
 // Configurable cache size via environment variable, default 10000
+#[cfg(windows)]
 fn get_cache_size() -> usize {
     std::env::var("SPEICHERWALD_SIZE_CACHE_ENTRIES")
         .ok()
@@ -918,6 +2123,7 @@ fn get_cache_size() -> usize {
         .clamp(100, 100_000)
 }
 
+#[cfg(windows)]
 lazy_static::lazy_static! {
     static ref SIZE_CACHE: Mutex<LruCache<PathBuf, Option<u64>>> = {
         let size = get_cache_size();
@@ -928,8 +2134,12 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Returns the bytes a file occupies on disk, `None` if unknown.
+///
+/// Windows reports the compressed or sparse size only through the path, so
+/// `md` is unused and results are cached per path.
 #[cfg(windows)]
-fn unsafe_get_allocated_size(path: &Path) -> Option<u64> {
+fn unsafe_get_allocated_size(path: &Path, _md: &fs::Metadata) -> Option<u64> {
     // FIX Bug #3: Use opportunistic caching with try_lock to avoid global lock contention.
     // If the lock is busy, we just skip the cache and calculate the size directly.
     match SIZE_CACHE.try_lock() {
@@ -979,10 +2189,57 @@ fn unsafe_get_allocated_size(path: &Path) -> Option<u64> {
     }
 }
 
-#[cfg(not(windows))]
-fn unsafe_get_allocated_size(_path: &Path) -> Option<u64> {
-    // Auf Nicht-Windows approximieren wir die Allokationsgröße mit der logischen Größe (None -> Fallback im Aufrufer)
-    None
+/// Returns the bytes a file occupies on disk, `None` if unknown.
+///
+/// `md` is the metadata already read for `path`, so no extra stat is needed. The
+/// block count reflects sparse files and filesystem compression (ZFS, Btrfs),
+/// which is why no cache is kept here.
+#[cfg(unix)]
+fn unsafe_get_allocated_size(_path: &Path, md: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is counted in 512-byte units regardless of the filesystem block size
+    Some(md.blocks().saturating_mul(512))
+}
+
+#[cfg(not(any(windows, unix)))]
+fn unsafe_get_allocated_size(_path: &Path, _md: &fs::Metadata) -> Option<u64> {
+    // Ohne Blockangaben gilt die logische Größe (None -> Fallback im Aufrufer)
+    None
+}
+
+/// The exact bytes of `path` if its display string is lossy, `None` if the path is valid Unicode.
+///
+/// Unix paths are stored as they are; Windows paths as their UTF-16 code units in little
+/// endian order, so unpaired surrogates survive.
+pub(crate) fn raw_path_bytes(path: &Path) -> Option<Vec<u8>> {
+    if path.to_str().is_some() {
+        return None;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        Some(path.as_os_str().encode_wide().flat_map(u16::to_le_bytes).collect())
+    }
+    #[cfg(not(windows))]
+    {
+        Some(path.as_os_str().as_encoded_bytes().to_vec())
+    }
+}
+
+/// Whether the last component of `path` is not valid Unicode; its descendants are lossy
+/// as well but only the entry itself is worth a warning.
+fn has_lossy_name(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_str().is_none())
+}
+
+/// Warning emitted for an entry whose name is not valid Unicode.
+fn lossy_name_warning(path: &Path) -> ScanEvent {
+    ScanEvent::Warning {
+        path: path.to_string_lossy().to_string(),
+        code: "lossy_path".into(),
+        message: "name is not valid Unicode; shown with replacement characters, exported exactly as path_raw"
+            .into(),
+    }
 }
 
 fn parent_path_string(path: &Path) -> Option<String> {
@@ -1001,22 +2258,302 @@ fn calc_depth(path: &Path) -> u32 {
     }
 }
 
+/// How `persist_batches` retries writes that fail because the database is locked.
+///
+/// Another process (a backup, an external SQLite client, a second server on
+/// the same file) can hold the write lock longer than the busy timeout of the
+/// pool. Such writes are retried with exponential backoff instead of failing
+/// the scan, until either limit is reached.
+#[derive(Debug, Clone, Copy)]
+pub struct PersistRetry {
+    /// The maximum number of retries of one transaction.
+    pub max_retries: u32,
+    /// The total time one transaction may spend waiting between retries.
+    pub budget: Duration,
+}
+
+impl Default for PersistRetry {
+    fn default() -> Self {
+        Self { max_retries: 8, budget: Duration::from_secs(120) }
+    }
+}
+
+impl PersistRetry {
+    /// Reads the retry limits from the scanner configuration.
+    pub fn from_config(cfg: &crate::config::ScannerConfig) -> Self {
+        Self { max_retries: cfg.persist_max_retries, budget: Duration::from_millis(cfg.persist_retry_budget_ms) }
+    }
+}
+
+/// The first delay before a locked write is retried; it doubles with every retry.
+const PERSIST_RETRY_BASE: Duration = Duration::from_millis(50);
+/// The longest delay between two retries.
+const PERSIST_RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+/// The number of insert statements committed per transaction.
+const CHUNKS_PER_TX: usize = 5;
+const SQLITE_CONSTRAINT_FOREIGNKEY: i32 = 787;
+
+/// Why writing a group of records failed.
+enum WriteFailure {
+    /// The database stayed locked until the retry budget was used up.
+    Busy(sqlx::Error),
+    /// SQLite rejected the data itself (e.g. a too large value or a constraint),
+    /// so a smaller group without the offending record can still succeed.
+    Rejected(sqlx::Error),
+    /// Anything else, such as I/O errors or a full disk.
+    Fatal(sqlx::Error),
+}
+
+fn classify_write_error(e: sqlx::Error) -> WriteFailure {
+    // SQLite reports extended result codes; the primary code is the low byte
+    let code = match &e {
+        sqlx::Error::Database(db) => db.code().and_then(|c| c.parse::<i32>().ok()),
+        _ => None,
+    };
+    match code {
+        // A missing scan row (e.g. purged while running) fails every record alike
+        Some(SQLITE_CONSTRAINT_FOREIGNKEY) => WriteFailure::Fatal(e),
+        // SQLITE_BUSY, SQLITE_LOCKED
+        Some(c) if matches!(c & 0xff, 5 | 6) => WriteFailure::Busy(e),
+        // SQLITE_TOOBIG, SQLITE_CONSTRAINT, SQLITE_MISMATCH
+        Some(c) if matches!(c & 0xff, 18..=20) => WriteFailure::Rejected(e),
+        _ => WriteFailure::Fatal(e),
+    }
+}
+
+/// A slice of records of one table.
+#[derive(Clone, Copy)]
+enum Records<'a> {
+    Nodes(&'a [NodeRecord]),
+    Files(&'a [FileRecord]),
+}
+
+impl<'a> Records<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Records::Nodes(r) => r.len(),
+            Records::Files(r) => r.len(),
+        }
+    }
+
+    fn first_path(&self) -> &'a str {
+        match self {
+            Records::Nodes(r) => r.first().map_or("", |n| n.path.as_str()),
+            Records::Files(r) => r.first().map_or("", |f| f.path.as_str()),
+        }
+    }
+
+    fn split(self) -> (Self, Self) {
+        match self {
+            Records::Nodes(r) => {
+                let (a, b) = r.split_at(r.len() / 2);
+                (Records::Nodes(a), Records::Nodes(b))
+            }
+            Records::Files(r) => {
+                let (a, b) = r.split_at(r.len() / 2);
+                (Records::Files(a), Records::Files(b))
+            }
+        }
+    }
+}
+
+/// Writes records in one transaction, one insert statement per `chunk_size` records.
+async fn insert_records(
+    pool: &sqlx::SqlitePool,
+    sid: &str,
+    records: Records<'_>,
+    chunk_size: usize,
+) -> Result<(), sqlx::Error> {
+    let mut txdb = pool.begin().await?;
+    match records {
+        Records::Nodes(nodes) => {
+            for chunk in nodes.chunks(chunk_size) {
+                let mut qb = QueryBuilder::new(
+                    "INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count, mtime, atime, fingerprint, empty_file_count, path_raw) "
+                );
+                qb.push_values(chunk, |mut b, n| {
+                    // Clamp u64 values to i64::MAX to prevent overflow when converting to i64 for SQLite
+                    let logical_size_safe = n.logical_size.min(i64::MAX as u64) as i64;
+                    let allocated_size_safe = n.allocated_size.min(i64::MAX as u64) as i64;
+                    let file_count_safe = n.file_count.min(i64::MAX as u64) as i64;
+                    let dir_count_safe = n.dir_count.min(i64::MAX as u64) as i64;
+                    let empty_file_count_safe = n.empty_file_count.min(i64::MAX as u64) as i64;
+
+                    b.push_bind(sid)
+                        .push_bind(&n.path)
+                        .push_bind(n.parent_path.as_deref())
+                        .push_bind(n.depth as i64)
+                        .push_bind(if n.is_dir { 1i64 } else { 0i64 })
+                        .push_bind(logical_size_safe)
+                        .push_bind(allocated_size_safe)
+                        .push_bind(file_count_safe)
+                        .push_bind(dir_count_safe)
+                        .push_bind(n.mtime)
+                        .push_bind(n.atime)
+                        .push_bind(n.fingerprint)
+                        .push_bind(empty_file_count_safe)
+                        .push_bind(n.path_raw.as_deref());
+                });
+                // Writing a directory again refreshes its row; the clause adds SQL text but no binds
+                qb.push(
+                    " ON CONFLICT(scan_id, path) DO UPDATE SET parent_path=excluded.parent_path, \
+                     depth=excluded.depth, is_dir=excluded.is_dir, logical_size=excluded.logical_size, \
+                     allocated_size=excluded.allocated_size, file_count=excluded.file_count, \
+                     dir_count=excluded.dir_count, mtime=excluded.mtime, atime=excluded.atime, \
+                     fingerprint=excluded.fingerprint, empty_file_count=excluded.empty_file_count, \
+                     path_raw=excluded.path_raw",
+                );
+                qb.build().execute(&mut *txdb).await?;
+            }
+        }
+        Records::Files(files) => {
+            for chunk in files.chunks(chunk_size) {
+                let mut qb = QueryBuilder::new(
+                    "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size, mtime, atime, \
+                     ads_count, ads_size, owner, category, path_raw, hash) ",
+                );
+                qb.push_values(chunk, |mut b, f| {
+                    // Clamp u64 values to i64::MAX to prevent overflow when converting to i64 for SQLite
+                    let logical_size_safe = f.logical_size.min(i64::MAX as u64) as i64;
+                    let allocated_size_safe = f.allocated_size.min(i64::MAX as u64) as i64;
+
+                    b.push_bind(sid)
+                        .push_bind(&f.path)
+                        .push_bind(f.parent_path.as_deref())
+                        .push_bind(logical_size_safe)
+                        .push_bind(allocated_size_safe)
+                        .push_bind(f.mtime)
+                        .push_bind(f.atime)
+                        .push_bind(f.ads.map(|(count, _)| count.min(i64::MAX as u64) as i64))
+                        .push_bind(f.ads.map(|(_, bytes)| bytes.min(i64::MAX as u64) as i64))
+                        .push_bind(f.owner.as_deref())
+                        .push_bind(f.category.as_deref())
+                        .push_bind(f.path_raw.as_deref())
+                        .push_bind(f.hash.as_deref());
+                });
+                qb.push(
+                    " ON CONFLICT(scan_id, path) DO UPDATE SET parent_path=excluded.parent_path, \
+                     logical_size=excluded.logical_size, allocated_size=excluded.allocated_size, \
+                     mtime=excluded.mtime, atime=excluded.atime, ads_count=excluded.ads_count, \
+                     ads_size=excluded.ads_size, owner=excluded.owner, category=excluded.category, \
+                     path_raw=excluded.path_raw, hash=excluded.hash",
+                );
+                qb.build().execute(&mut *txdb).await?;
+            }
+        }
+    }
+    txdb.commit().await
+}
+
+/// Writes records, retrying with exponential backoff while the database is locked.
+///
+/// Every retry is announced as a `db_busy` warning; `warnings` counts them.
+async fn insert_with_retry(
+    pool: &sqlx::SqlitePool,
+    sid: &str,
+    records: Records<'_>,
+    chunk_size: usize,
+    retry: &PersistRetry,
+    events: &tokio::sync::broadcast::Sender<ScanEvent>,
+    warnings: &mut u64,
+) -> Result<(), WriteFailure> {
+    let mut waited = Duration::ZERO;
+    let mut delay = PERSIST_RETRY_BASE;
+    let mut attempt = 0u32;
+    loop {
+        let e = match insert_records(pool, sid, records, chunk_size).await {
+            Ok(()) => return Ok(()),
+            Err(e) => classify_write_error(e),
+        };
+        let WriteFailure::Busy(e) = e else { return Err(e) };
+        if attempt >= retry.max_retries || waited + delay > retry.budget {
+            return Err(WriteFailure::Busy(e));
+        }
+        attempt += 1;
+        tracing::warn!("Database busy while persisting scan batch, retry {} in {:?}: {}", attempt, delay, e);
+        *warnings += 1;
+        let _ = events.send(ScanEvent::Warning {
+            path: records.first_path().to_string(),
+            code: "db_busy".into(),
+            message: format!(
+                "Database locked while writing {} records, retry {}/{} in {} ms",
+                records.len(),
+                attempt,
+                retry.max_retries,
+                delay.as_millis()
+            ),
+        });
+        tokio::time::sleep(delay).await;
+        waited += delay;
+        delay = (delay * 2).min(PERSIST_RETRY_MAX_DELAY);
+    }
+}
+
+/// Writes records, isolating records SQLite rejects.
+///
+/// A group that is rejected is split in half and the halves are written on
+/// their own, down to single records, which are skipped with a
+/// `persist_failed` warning. Locked databases and other errors end the write.
+async fn insert_isolating(
+    pool: &sqlx::SqlitePool,
+    sid: &str,
+    records: Records<'_>,
+    chunk_size: usize,
+    retry: &PersistRetry,
+    events: &tokio::sync::broadcast::Sender<ScanEvent>,
+) -> anyhow::Result<u64> {
+    let mut warnings = 0;
+    let mut pending = vec![records];
+    while let Some(records) = pending.pop() {
+        match insert_with_retry(pool, sid, records, chunk_size, retry, events, &mut warnings).await {
+            Ok(()) => {}
+            Err(WriteFailure::Rejected(_)) if records.len() > 1 => {
+                let (first, second) = records.split();
+                pending.push(second);
+                pending.push(first);
+            }
+            Err(WriteFailure::Rejected(e)) => {
+                tracing::warn!("Skipping record {} that could not be stored: {}", records.first_path(), e);
+                warnings += 1;
+                let _ = events.send(ScanEvent::Warning {
+                    path: records.first_path().to_string(),
+                    code: "persist_failed".into(),
+                    message: format!("Record could not be stored: {}", e),
+                });
+            }
+            Err(WriteFailure::Busy(e)) => {
+                return Err(anyhow::Error::new(e).context("database stayed locked after all retries"))
+            }
+            Err(WriteFailure::Fatal(e)) => return Err(e.into()),
+        }
+    }
+    Ok(warnings)
+}
+
+/// Writes buffered records and clears the buffers.
+///
+/// # Returns
+///
+/// * `anyhow::Result<u64>` - The number of warnings emitted for retried writes
+///   and skipped records.
 async fn persist_batches(
     pool: &sqlx::SqlitePool,
     id: Uuid,
     nodes: &mut Vec<NodeRecord>,
     files: &mut Vec<FileRecord>,
     batch_size: usize,
-) -> anyhow::Result<()> {
+    retry: &PersistRetry,
+    events: &tokio::sync::broadcast::Sender<ScanEvent>,
+) -> anyhow::Result<u64> {
     if nodes.is_empty() && files.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
     let sid = id.to_string();
-    
-    // Respect SQLite variable limit
+
+    // Respect SQLite variable limit; the ON CONFLICT clause adds no binds
     const SQLITE_MAX_VARS: usize = 999;
-    const NODE_BINDS_PER_ROW: usize = 11;
-    const FILE_BINDS_PER_ROW: usize = 7;
+    const NODE_BINDS_PER_ROW: usize = 14;
+    const FILE_BINDS_PER_ROW: usize = 12;
 
     // Ensure we never compute 0 rows per statement
     let max_node_rows_per_stmt = (SQLITE_MAX_VARS / NODE_BINDS_PER_ROW).max(1);
@@ -1025,87 +2562,1325 @@ async fn persist_batches(
     // chunk sizes for query construction
     let node_chunk_size = batch_size.max(1).min(max_node_rows_per_stmt.max(1));
     let file_chunk_size = batch_size.max(1).min(max_file_rows_per_stmt.max(1));
-    
+
     // FIX Bug #6: Commit intermediate transactions to avoid huge internal journals and locks
-    let mut txdb = pool.begin().await?;
-    let mut chunks_processed = 0;
+    // Files go first: a directory's node row is sent after its whole subtree, so once
+    // it is stored everything below it is too, which resumed scans rely on
+    let mut warnings = 0;
+    for group in files.chunks(file_chunk_size * CHUNKS_PER_TX) {
+        warnings += insert_isolating(pool, &sid, Records::Files(group), file_chunk_size, retry, events).await?;
+    }
+    for group in nodes.chunks(node_chunk_size * CHUNKS_PER_TX) {
+        warnings += insert_isolating(pool, &sid, Records::Nodes(group), node_chunk_size, retry, events).await?;
+    }
+
+    nodes.clear();
+    files.clear();
+    Ok(warnings)
+}
+
+/// Writes records that were not scanned by this server, such as an imported export.
+///
+/// Uses the batching and retries of a scan; records SQLite rejects are
+/// skipped and stored as `persist_failed` warnings of the scan.
+///
+/// # Returns
+///
+/// * `anyhow::Result<u64>` - The number of warnings, as [`persist_batches`] counts them.
+pub(crate) async fn persist_imported(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    nodes: &mut Vec<NodeRecord>,
+    files: &mut Vec<FileRecord>,
+    batch_size: usize,
+    retry: &PersistRetry,
+) -> anyhow::Result<u64> {
+    let (tx, mut rx) = tokio::sync::broadcast::channel(1024);
+    let n = persist_batches(pool, id, nodes, files, batch_size, retry, &tx).await?;
+    let mut warnings = WarningBuffer::default();
+    while let Ok(event) = rx.try_recv() {
+        warnings.push(event);
+    }
+    warnings.persist(pool, id).await;
+    Ok(n)
+}
+
+/// The stored totals of a directory finished by an earlier run of a resumed scan.
+///
+/// Node rows are only written once the directory's whole subtree has been
+/// traversed, so a directory with a row does not need to be scanned again.
+/// Hardlinks inside it are not known to the resumed run, which may then count
+/// a file linked from both sides twice.
+#[derive(Debug, Clone, Copy)]
+struct PersistedDir {
+    logical_size: u64,
+    allocated_size: u64,
+    file_count: u64,
+    dir_count: u64,
+    mtime: Option<i64>,
+    atime: Option<i64>,
+}
+
+/// The persisted directories of a resumed scan, keyed by path.
+type PersistedDirs = std::collections::HashMap<String, PersistedDir>;
+
+/// Loads the directories an earlier run of a scan has finished.
+///
+/// Roots are left out: their node rows are rebuilt from their direct files and
+/// the totals of their subdirectories, whether those are taken over or scanned.
+async fn load_persisted_dirs(pool: &sqlx::SqlitePool, id: Uuid, roots: &[String]) -> anyhow::Result<PersistedDirs> {
+    let rows = sqlx::query(
+        "SELECT path, logical_size, allocated_size, file_count, dir_count, mtime, atime \
+         FROM nodes WHERE scan_id=?1 AND is_dir=1",
+    )
+    .bind(id.to_string())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let count = |col: &str| r.get::<i64, _>(col).max(0) as u64;
+            let dir = PersistedDir {
+                logical_size: count("logical_size"),
+                allocated_size: count("allocated_size"),
+                file_count: count("file_count"),
+                dir_count: count("dir_count"),
+                mtime: r.get("mtime"),
+                atime: r.get("atime"),
+            };
+            (r.get::<String, _>("path"), dir)
+        })
+        .filter(|(path, _)| !roots.contains(path))
+        .collect())
+}
+
+/// A directory of the scan a rescan repeats, with the totals of its direct files.
+///
+/// A directory whose modification time is unchanged kept its entries, so its
+/// files are copied from the earlier scan instead of being enumerated. A file
+/// rewritten in place does not touch the directory, so its stored size is kept.
+#[derive(Debug, Clone, Default)]
+struct PreviousDir {
+    mtime: Option<i64>,
+    fingerprint: Option<i64>,
+    /// Whether the stored rows describe the whole listing: it was complete, no
+    /// link and no entry with a name that is not valid Unicode was met in it.
+    reusable: bool,
+    files: u64,
+    logical: u64,
+    allocated: u64,
+    empty_files: u64,
+    latest_mtime: Option<i64>,
+    latest_atime: Option<i64>,
+    /// The paths of the direct subdirectories, which are checked on their own.
+    subdirs: Vec<String>,
+}
+
+/// The directories of the scan a rescan repeats, keyed by path.
+type PreviousDirs = std::collections::HashMap<String, PreviousDir>;
+
+/// Loads the directories of the scan a rescan repeats.
+///
+/// Returns `None` if that scan ran with other options, which may have left out
+/// or added entries; concurrency and I/O priority do not change the result and
+/// are not compared.
+async fn load_previous_dirs(
+    pool: &sqlx::SqlitePool,
+    previous: Uuid,
+    options: &ScanOptions,
+) -> anyhow::Result<Option<PreviousDirs>> {
+    let comparable =
+        |o: &ScanOptions| ScanOptions { concurrency: None, io_priority: Default::default(), ..o.clone() };
+    let stored: Option<String> = sqlx::query_scalar("SELECT options FROM scans WHERE id=?1")
+        .bind(previous.to_string())
+        .fetch_optional(pool)
+        .await?;
+    match stored.and_then(|json| serde_json::from_str::<ScanOptions>(&json).ok()) {
+        Some(stored) if comparable(&stored) == comparable(options) => {}
+        _ => return Ok(None),
+    }
 
-    // nodes in chunks
-    for chunk in nodes.chunks(node_chunk_size) {
+    let sid = previous.to_string();
+    let rows = sqlx::query(
+        "SELECT path, parent_path, mtime, fingerprint, empty_file_count, path_raw IS NOT NULL AS lossy \
+         FROM nodes WHERE scan_id=?1 AND is_dir=1",
+    )
+    .bind(&sid)
+    .fetch_all(pool)
+    .await?;
+    let mut dirs = PreviousDirs::with_capacity(rows.len());
+    let mut children: Vec<(String, String, bool)> = Vec::new();
+    for r in &rows {
+        let path: String = r.get("path");
+        let fingerprint: Option<i64> = r.get("fingerprint");
+        let dir = PreviousDir {
+            mtime: r.get("mtime"),
+            fingerprint,
+            reusable: fingerprint.is_some(),
+            empty_files: r.get::<Option<i64>, _>("empty_file_count").unwrap_or(0).max(0) as u64,
+            ..Default::default()
+        };
+        if let Some(parent) = r.get::<Option<String>, _>("parent_path") {
+            children.push((parent, path.clone(), r.get("lossy")));
+        }
+        dirs.insert(path, dir);
+    }
+    for (parent, path, lossy) in children {
+        if let Some(dir) = dirs.get_mut(&parent) {
+            dir.subdirs.push(path);
+            dir.reusable &= !lossy;
+        }
+    }
+
+    let totals = sqlx::query(
+        "SELECT parent_path, COUNT(*) AS files, COALESCE(SUM(logical_size), 0) AS logical, \
+         COALESCE(SUM(allocated_size), 0) AS allocated, MAX(mtime) AS latest_mtime, \
+         MAX(atime) AS latest_atime, MAX(path_raw IS NOT NULL) AS lossy, MAX(logical_size) AS largest \
+         FROM files WHERE scan_id=?1 AND parent_path IS NOT NULL GROUP BY parent_path",
+    )
+    .bind(&sid)
+    .fetch_all(pool)
+    .await?;
+    for r in &totals {
+        let Some(dir) = dirs.get_mut(&r.get::<String, _>("parent_path")) else { continue };
+        let count = |col: &str| r.get::<i64, _>(col).max(0) as u64;
+        dir.files = count("files");
+        dir.logical = count("logical");
+        dir.allocated = count("allocated");
+        dir.latest_mtime = r.get("latest_mtime");
+        dir.latest_atime = r.get("latest_atime");
+        dir.reusable &= r.get::<i64, _>("lossy") == 0;
+        // Copied hashes would hide content that changed since, so hashed files are read again
+        dir.reusable &= options.hash_min_size.is_none_or(|min| (r.get::<i64, _>("largest") as u64) < min);
+    }
+
+    // Links are re-evaluated by the link tracker of each run, so their directories are listed
+    let linked: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT parent_path FROM scan_links WHERE scan_id=?1 AND parent_path IS NOT NULL",
+    )
+    .bind(&sid)
+    .fetch_all(pool)
+    .await?;
+    for parent in linked {
+        if let Some(dir) = dirs.get_mut(&parent) {
+            dir.reusable = false;
+        }
+    }
+    Ok(Some(dirs))
+}
+
+/// Copies the files of the directories a rescan took over from the earlier scan.
+///
+/// Runs before the node rows of the same batch are stored, so a stored
+/// directory row still implies that its files are stored.
+async fn copy_unchanged_files(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    from: Option<Uuid>,
+    dirs: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    // Two binds plus one per directory, well below SQLite's variable limit
+    const DIRS_PER_STMT: usize = 500;
+    let Some(from) = from.filter(|_| !dirs.is_empty()) else {
+        dirs.clear();
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    for chunk in dirs.chunks(DIRS_PER_STMT) {
         let mut qb = QueryBuilder::new(
-            "INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, file_count, dir_count, mtime, atime) "
+            "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size, mtime, atime, \
+             ads_count, ads_size, owner, category, path_raw) SELECT ",
         );
-        qb.push_values(chunk, |mut b, n| {
-            // Clamp u64 values to i64::MAX to prevent overflow when converting to i64 for SQLite
-            let logical_size_safe = n.logical_size.min(i64::MAX as u64) as i64;
-            let allocated_size_safe = n.allocated_size.min(i64::MAX as u64) as i64;
-            let file_count_safe = n.file_count.min(i64::MAX as u64) as i64;
-            let dir_count_safe = n.dir_count.min(i64::MAX as u64) as i64;
+        qb.push_bind(id.to_string());
+        qb.push(
+            ", path, parent_path, logical_size, allocated_size, mtime, atime, ads_count, ads_size, owner, \
+             category, path_raw FROM files WHERE scan_id=",
+        );
+        qb.push_bind(from.to_string());
+        qb.push(" AND parent_path IN (");
+        let mut list = qb.separated(", ");
+        for dir in chunk {
+            list.push_bind(dir);
+        }
+        qb.push(") ON CONFLICT(scan_id, path) DO NOTHING");
+        qb.build().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    dirs.clear();
+    Ok(())
+}
+
+/// Stores the subtotals of each root of a scan, replacing earlier ones.
+async fn persist_roots(pool: &sqlx::SqlitePool, id: Uuid, roots: &[RootSummary]) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM scan_roots WHERE scan_id=?1").bind(id.to_string()).execute(&mut *tx).await?;
+    for (index, root) in roots.iter().enumerate() {
+        sqlx::query(
+            r#"INSERT INTO scan_roots (scan_id, root_index, root_path, dir_count, file_count,
+                   logical_size, allocated_size, warning_count)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+        )
+        .bind(id.to_string())
+        .bind(index as i64)
+        .bind(&root.root)
+        .bind(root.dir_count.min(i64::MAX as u64) as i64)
+        .bind(root.file_count.min(i64::MAX as u64) as i64)
+        .bind(root.logical_size.min(i64::MAX as u64) as i64)
+        .bind(root.allocated_size.min(i64::MAX as u64) as i64)
+        .bind(root.warning_count.min(i64::MAX as u64) as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
 
+/// Stores the links met by a scan, replacing earlier ones.
+async fn persist_links(pool: &sqlx::SqlitePool, id: Uuid, links: &[LinkRecord]) -> anyhow::Result<()> {
+    // 7 binds per row, well below SQLite's variable limit
+    const ROWS_PER_STMT: usize = 100;
+    let sid = id.to_string();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM scan_links WHERE scan_id=?1").bind(&sid).execute(&mut *tx).await?;
+    for chunk in links.chunks(ROWS_PER_STMT) {
+        let mut qb =
+            QueryBuilder::new("INSERT INTO scan_links (scan_id, path, parent_path, kind, target, followed, outside_root) ");
+        qb.push_values(chunk, |mut b, l| {
             b.push_bind(&sid)
-                .push_bind(&n.path)
-                .push_bind(n.parent_path.as_deref())
-                .push_bind(n.depth as i64)
-                .push_bind(if n.is_dir { 1i64 } else { 0i64 })
-                .push_bind(logical_size_safe)
-                .push_bind(allocated_size_safe)
-                .push_bind(file_count_safe)
-                .push_bind(dir_count_safe)
-                .push_bind(n.mtime)
-                .push_bind(n.atime);
+                .push_bind(&l.path)
+                .push_bind(l.parent_path.as_deref())
+                .push_bind(l.kind)
+                .push_bind(l.target.as_deref())
+                .push_bind(l.followed)
+                .push_bind(l.outside_root);
         });
-        qb.build().execute(&mut *txdb).await?;
-        
-        chunks_processed += 1;
-        if chunks_processed >= 5 {
-            txdb.commit().await?;
-            txdb = pool.begin().await?;
-            chunks_processed = 0;
+        qb.build().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_scan, test_db_in};
+    use tokio::sync::broadcast;
+
+    const FLUSH: usize = 1_000;
+    const DIR_CONCURRENCY: usize = 4;
+
+    fn test_options() -> ScanOptions {
+        ScanOptions { measure_allocated: false, ..Default::default() }
+    }
+
+    async fn scan(
+        pool: &sqlx::SqlitePool,
+        id: Uuid,
+        root: &Path,
+        options: ScanOptions,
+        max_entries: Option<u64>,
+    ) -> ScanResultSummary {
+        scan_reusing(pool, id, root, options, max_entries, Reuse::Nothing).await.0
+    }
+
+    /// Runs a scan that takes over stored rows and returns its summary and warning codes.
+    async fn scan_reusing(
+        pool: &sqlx::SqlitePool,
+        id: Uuid,
+        root: &Path,
+        options: ScanOptions,
+        max_entries: Option<u64>,
+        reuse: Reuse,
+    ) -> (ScanResultSummary, Vec<String>) {
+        sqlx::query("INSERT INTO scans (id, status, root_paths, options) VALUES (?1, 'running', '[]', ?2)")
+            .bind(id.to_string())
+            .bind(serde_json::to_string(&options).unwrap())
+            .execute(pool)
+            .await
+            .unwrap();
+        let (tx, mut rx) = broadcast::channel(1024);
+        let summary = run_scan(
+            pool.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            options,
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            FLUSH,
+            64 << 20,
+            50,
+            None,
+            Some(DIR_CONCURRENCY),
+            max_entries,
+            Default::default(),
+            reuse,
+            &Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        let mut warnings = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let ScanEvent::Warning { code, .. } = event {
+                warnings.push(code);
+            }
         }
+        (summary, warnings)
     }
 
-    // files in chunks
-    for chunk in files.chunks(file_chunk_size) {
-        let mut qb = QueryBuilder::new(
-            "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size, mtime, atime) ",
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn huge_directory_keeps_buffers_bounded() {
+        const ROOT_FILES: usize = 200_000;
+        const SUBDIRS: usize = 500;
+        const NESTED_FILES: usize = 20_000;
+
+        let data = tempfile::tempdir().unwrap();
+        let root = data.path().join("root");
+        let nested = root.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        for i in 0..ROOT_FILES {
+            fs::File::create(root.join(format!("f{:06}.log", i))).unwrap();
+        }
+        for i in 0..NESTED_FILES {
+            fs::File::create(nested.join(format!("n{:05}.log", i))).unwrap();
+        }
+        for i in 0..SUBDIRS {
+            let d = root.join(format!("d{:04}", i));
+            fs::create_dir(&d).unwrap();
+            fs::write(d.join("x.bin"), b"abc").unwrap();
+        }
+
+        let pool = test_db_in(data.path()).await;
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &root, test_options(), None).await;
+
+        let expected_files = (ROOT_FILES + NESTED_FILES + SUBDIRS) as u64;
+        assert_eq!(summary.total_files, expected_files);
+        assert_eq!(summary.total_dirs, (1 + SUBDIRS + 1) as u64);
+        assert_eq!(summary.total_logical_size, (3 * SUBDIRS) as u64);
+        assert_eq!(summary.warnings, 0);
+
+        let root_row = sqlx::query("SELECT file_count, dir_count, logical_size FROM nodes WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(root.to_string_lossy().to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(sqlx::Row::get::<i64, _>(&root_row, "file_count"), expected_files as i64);
+        assert_eq!(sqlx::Row::get::<i64, _>(&root_row, "dir_count"), (SUBDIRS + 1) as i64);
+        assert_eq!(sqlx::Row::get::<i64, _>(&root_row, "logical_size"), (3 * SUBDIRS) as i64);
+        let stored_files: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored_files, expected_files as i64);
+
+        let peak_records = instrumentation::peak(id, Peak::BufferedRecords);
+        let peak_workers = instrumentation::peak(id, Peak::ActiveWorkers);
+        assert!(peak_records > 0 && peak_records <= FLUSH, "peak buffered records {}", peak_records);
+        assert!(peak_workers > 0 && peak_workers <= DIR_CONCURRENCY, "peak active workers {}", peak_workers);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn wide_fan_out_keeps_queued_and_spilled_dirs_bounded() {
+        // More subdirectories than the queue and one worker's spill hold together
+        const SUBDIRS: usize = MAX_QUEUED_DIRS + 2 * MAX_SPILLED_DIRS;
+
+        let data = tempfile::tempdir().unwrap();
+        let root = data.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        for i in 0..SUBDIRS {
+            let d = root.join(format!("d{:05}", i));
+            fs::create_dir(&d).unwrap();
+            fs::write(d.join("x.bin"), b"a").unwrap();
+        }
+
+        let pool = test_db_in(data.path()).await;
+        let id = Uuid::new_v4();
+        // A single worker takes nothing from the queue while it lists the root
+        let options = ScanOptions { concurrency: Some(1), ..test_options() };
+        let summary = scan(&pool, id, &root, options, None).await;
+
+        assert_eq!(summary.total_dirs, (SUBDIRS + 1) as u64);
+        assert_eq!(summary.total_files, SUBDIRS as u64);
+        assert_eq!(summary.total_logical_size, SUBDIRS as u64);
+        let root_counts: (i64, i64) =
+            sqlx::query_as("SELECT file_count, dir_count FROM nodes WHERE scan_id=?1 AND path=?2")
+                .bind(id.to_string())
+                .bind(root.to_string_lossy().to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(root_counts, (SUBDIRS as i64, SUBDIRS as i64));
+
+        assert_eq!(instrumentation::peak(id, Peak::QueuedDirs), MAX_QUEUED_DIRS);
+        assert_eq!(instrumentation::peak(id, Peak::SpilledDirs), MAX_SPILLED_DIRS);
+        assert_eq!(instrumentation::peak(id, Peak::ActiveWorkers), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn long_paths_are_flushed_by_the_byte_budget() {
+        const FILES: usize = 400;
+        const BUDGET: usize = 64 * 1024;
+
+        // Ten levels of 200 characters put every path at over 2000 bytes
+        let data = tempfile::tempdir().unwrap();
+        let mut deep = data.path().join("root");
+        for level in 0..10 {
+            deep.push(format!("{}{}", level, "d".repeat(199)));
+        }
+        fs::create_dir_all(&deep).unwrap();
+        for i in 0..FILES {
+            fs::File::create(deep.join(format!("{:04}{}", i, "f".repeat(196)))).unwrap();
+        }
+        let root = data.path().join("root");
+        let pool = test_db_in(data.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let (tx, _rx) = broadcast::channel(1024);
+        let metrics = Metrics::default();
+        let summary = run_scan(
+            pool.clone(),
+            id,
+            vec![root.to_string_lossy().to_string()],
+            test_options(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            FLUSH,
+            BUDGET,
+            50,
+            None,
+            Some(DIR_CONCURRENCY),
+            None,
+            Default::default(),
+            Reuse::Nothing,
+            &metrics,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.total_files, FILES as u64);
+
+        // Every file record holds its path and its parent's; a worker flushes at the budget
+        // and the aggregator holds at most the budget plus one batch
+        let record = deep.to_string_lossy().len() * 2 + 400;
+        let bound = (DIR_CONCURRENCY + 2) * (BUDGET + record);
+        assert!(bound < FILES * record / 4);
+        let peak = summary.buffer_peak_bytes as usize;
+        assert!(peak >= BUDGET && peak <= bound, "peak buffered bytes {}", peak);
+        assert_eq!(metrics.get_snapshot().scanner_buffered_bytes, 0);
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, FILES as i64);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shared_workers_keep_directory_totals_within_handle_limit() {
+        // (files, subdirectories, logical size) of the subtree, as the recursive traversal counted them
+        fn expected(dir: &Path, totals: &mut Vec<(String, (i64, i64, i64))>) -> (i64, i64, i64) {
+            let (mut files, mut dirs, mut size) = (0, 0, 0);
+            for entry in fs::read_dir(dir).unwrap().flatten() {
+                let md = entry.metadata().unwrap();
+                if md.is_dir() {
+                    let (f, d, s) = expected(&entry.path(), totals);
+                    (files, dirs, size) = (files + f, dirs + d + 1, size + s);
+                } else {
+                    (files, size) = (files + 1, size + md.len() as i64);
+                }
+            }
+            totals.push((dir.to_string_lossy().to_string(), (files, dirs, size)));
+            (files, dirs, size)
+        }
+
+        let data = tempfile::tempdir().unwrap();
+        let roots = [data.path().join("a"), data.path().join("b")];
+        for (r, root) in roots.iter().enumerate() {
+            for i in 0..12 {
+                let mut dir = root.join(format!("d{}", i));
+                for depth in 0..(i % 4) {
+                    dir = dir.join(format!("n{}", depth));
+                }
+                fs::create_dir_all(&dir).unwrap();
+                for f in 0..(i + r) {
+                    fs::write(dir.join(format!("f{}.bin", f)), vec![0u8; 10 * f + i]).unwrap();
+                }
+            }
+            fs::write(root.join("top.bin"), vec![0u8; 7]).unwrap();
+        }
+        let mut want = Vec::new();
+        for root in &roots {
+            expected(root, &mut want);
+        }
+        want.sort();
+
+        let pool = test_db_in(data.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let (tx, _rx) = broadcast::channel(1024);
+        // More workers are asked for than the handle limit allows
+        let options = ScanOptions { concurrency: Some(16), ..test_options() };
+        let summary = run_scan(
+            pool.clone(),
+            id,
+            roots.iter().map(|r| r.to_string_lossy().to_string()).collect(),
+            options,
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            FLUSH,
+            64 << 20,
+            50,
+            Some(DIR_CONCURRENCY),
+            None,
+            None,
+            Default::default(),
+            Reuse::Nothing,
+            &Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut got: Vec<(String, (i64, i64, i64))> =
+            sqlx::query_as("SELECT path, file_count, dir_count, logical_size FROM nodes WHERE scan_id=?1")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(path, files, dirs, size): (String, i64, i64, i64)| (path, (files, dirs, size)))
+                .collect();
+        got.sort();
+        assert_eq!(got, want);
+        let files: i64 = want
+            .iter()
+            .filter(|(p, _)| roots.iter().any(|r| r.to_string_lossy() == *p))
+            .map(|(_, t)| t.0)
+            .sum();
+        assert_eq!(summary.total_files, files as u64);
+        assert_eq!(summary.total_dirs, want.len() as u64);
+        assert_eq!(summary.roots.len(), 2);
+        for (root, stored) in roots.iter().zip(&summary.roots) {
+            let (_, (files, dirs, size)) = want.iter().find(|(p, _)| *p == root.to_string_lossy()).unwrap();
+            assert_eq!(
+                (stored.file_count, stored.dir_count, stored.logical_size),
+                (*files as u64, *dirs as u64 + 1, *size as u64)
+            );
+        }
+
+        let peak_workers = instrumentation::peak(id, Peak::ActiveWorkers);
+        assert!(peak_workers > 0 && peak_workers <= DIR_CONCURRENCY, "peak active workers {}", peak_workers);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn includes_record_only_matching_files() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("vol");
+        let deep = root.join("vms").join("deep");
+        let skipped = root.join("excluded");
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir_all(&skipped).unwrap();
+        fs::write(root.join("install.iso"), vec![0u8; 40]).unwrap();
+        fs::write(root.join("notes.txt"), b"x").unwrap();
+        fs::write(deep.join("disk.vhdx"), vec![0u8; 100]).unwrap();
+        fs::write(deep.join("disk.log"), b"xyz").unwrap();
+        fs::write(skipped.join("other.iso"), vec![0u8; 7]).unwrap();
+
+        let options = ScanOptions {
+            includes: vec!["**/*.vhdx".into(), "*.iso".into()],
+            excludes: vec!["**/excluded".into()],
+            ..test_options()
+        };
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &root, options, None).await;
+        assert_eq!(summary.total_files, 2);
+        assert_eq!(summary.total_logical_size, 140);
+
+        let mut files: Vec<String> = sqlx::query_scalar("SELECT path FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        files.sort();
+        let mut expected = vec![
+            root.join("install.iso").to_string_lossy().to_string(),
+            deep.join("disk.vhdx").to_string_lossy().to_string(),
+        ];
+        expected.sort();
+        assert_eq!(files, expected);
+
+        // Directories on the way to a match are still traversed and aggregated
+        let vms_size: i64 = sqlx::query_scalar("SELECT logical_size FROM nodes WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(root.join("vms").to_string_lossy().to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(vms_size, 100);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn fingerprints_are_stable_and_track_child_changes() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("vol");
+        let (docs, media) = (root.join("docs"), root.join("media"));
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&media).unwrap();
+        fs::write(docs.join("a.txt"), b"hello").unwrap();
+        fs::write(media.join("b.bin"), vec![0u8; 64]).unwrap();
+
+        async fn fingerprints(pool: &sqlx::SqlitePool, id: Uuid) -> Vec<(String, Option<i64>)> {
+            sqlx::query_as("SELECT path, fingerprint FROM nodes WHERE scan_id=?1 ORDER BY path")
+                .bind(id.to_string())
+                .fetch_all(pool)
+                .await
+                .unwrap()
+        }
+
+        let first = Uuid::new_v4();
+        scan(&pool, first, &root, test_options(), None).await;
+        let second = Uuid::new_v4();
+        scan(&pool, second, &root, test_options(), None).await;
+        let before = fingerprints(&pool, first).await;
+        assert_eq!(before.len(), 3);
+        assert!(before.iter().all(|(_, fp)| fp.is_some()));
+        assert_eq!(fingerprints(&pool, second).await, before);
+
+        fs::write(docs.join("a.txt"), b"hello, world").unwrap();
+        let third = Uuid::new_v4();
+        scan(&pool, third, &root, test_options(), None).await;
+        let after = fingerprints(&pool, third).await;
+        let get = |v: &[(String, Option<i64>)], p: &Path| {
+            v.iter().find(|(path, _)| path.as_str() == p.to_string_lossy()).unwrap().1
+        };
+        assert_ne!(get(&after, &docs), get(&before, &docs));
+        assert_eq!(get(&after, &media), get(&before, &media));
+
+        // The root lists two entries, so a limit of one leaves its listing incomplete
+        let limited = Uuid::new_v4();
+        scan(&pool, limited, &root, test_options(), Some(1)).await;
+        assert_eq!(get(&fingerprints(&pool, limited).await, &root), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn rescans_take_over_unchanged_directories() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("vol");
+        let (a, b) = (root.join("a"), root.join("b"));
+        let c = b.join("c");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&c).unwrap();
+        fs::write(a.join("x.txt"), b"x").unwrap();
+        fs::write(b.join("y.txt"), b"yy").unwrap();
+        fs::write(c.join("z.txt"), b"zzz").unwrap();
+        let first = Uuid::new_v4();
+        scan(&pool, first, &root, test_options(), None).await;
+
+        fs::write(a.join("new.txt"), b"new").unwrap();
+        fs::write(b.join("y.txt"), vec![b'y'; 100]).unwrap();
+        fs::write(c.join("new2.txt"), b"new2").unwrap();
+        // Modification times have a resolution of one second, so mark a and c as changed
+        for dir in [&a, &c] {
+            sqlx::query("UPDATE nodes SET mtime=mtime-100 WHERE scan_id=?1 AND path=?2")
+                .bind(first.to_string())
+                .bind(dir.to_string_lossy().to_string())
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        async fn file_sizes(pool: &sqlx::SqlitePool, id: Uuid) -> Vec<(String, i64)> {
+            let rows: Vec<(String, i64)> =
+                sqlx::query_as("SELECT path, logical_size FROM files WHERE scan_id=?1 ORDER BY path")
+                    .bind(id.to_string())
+                    .fetch_all(pool)
+                    .await
+                    .unwrap();
+            rows.into_iter()
+                .map(|(path, size)| (Path::new(&path).file_name().unwrap().to_string_lossy().into(), size))
+                .collect()
+        }
+        let rescan = Uuid::new_v4();
+        let (summary, warnings) =
+            scan_reusing(&pool, rescan, &root, test_options(), None, Reuse::Rescan(first)).await;
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let names = |v: Vec<(String, i64)>| v.into_iter().collect::<std::collections::BTreeMap<_, _>>();
+        let files = names(file_sizes(&pool, rescan).await);
+        assert_eq!(files.len(), 5);
+        assert_eq!(files["new.txt"], 3);
+        assert_eq!(files["new2.txt"], 4);
+        // b kept its modification time, so its files are copied with their stored sizes
+        assert_eq!(files["y.txt"], 2);
+
+        let (logical, count): (i64, i64) = sqlx::query_as(
+            "SELECT logical_size, file_count FROM nodes WHERE scan_id=?1 AND path=?2",
+        )
+        .bind(rescan.to_string())
+        .bind(root.to_string_lossy().to_string())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((logical, count), (files.values().sum::<i64>(), 5));
+        assert_eq!((summary.total_logical_size, summary.total_files, summary.total_dirs), (13, 5, 4));
+
+        // Other options may change what a directory holds, so everything is traversed
+        let changed = Uuid::new_v4();
+        let options = ScanOptions { include_hidden: !test_options().include_hidden, ..test_options() };
+        let (_, warnings) = scan_reusing(&pool, changed, &root, options, None, Reuse::Rescan(first)).await;
+        assert_eq!(warnings, ["rescan_options_changed"]);
+        assert_eq!(names(file_sizes(&pool, changed).await)["y.txt"], 100);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn backup_mode_without_privilege_scans_normally_with_warning() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("vol");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.txt"), b"hello").unwrap();
+
+        let id = Uuid::new_v4();
+        let options = ScanOptions { access_mode: AccessMode::Backup, ..test_options() };
+        let (summary, warnings) = scan_reusing(&pool, id, &root, options, None, Reuse::Nothing).await;
+        assert_eq!(warnings, ["backup_privilege_unavailable"]);
+        assert!(!summary.backup_mode);
+        assert_eq!((summary.total_files, summary.warnings), (1, 1));
+        let stored: bool = sqlx::query_scalar("SELECT backup_mode FROM scans WHERE id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(!stored);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn entries_beyond_limit_are_skipped_with_warning() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+
+        // Limit hit while enumerating a root
+        let flat = data.path().join("flat");
+        fs::create_dir(&flat).unwrap();
+        for i in 0..25 {
+            fs::write(flat.join(format!("f{}", i)), b"x").unwrap();
+        }
+        let summary = scan(&pool, Uuid::new_v4(), &flat, test_options(), Some(10)).await;
+        assert_eq!(summary.total_files, 10);
+        assert_eq!(summary.total_logical_size, 10);
+        assert_eq!(summary.warnings, 1);
+
+        // Limit hit inside a subdirectory; the parent's aggregates only include scanned entries
+        let tree = data.path().join("tree");
+        let sub = tree.join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        for i in 0..30 {
+            fs::write(sub.join(format!("f{}", i)), b"xy").unwrap();
+        }
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &tree, test_options(), Some(10)).await;
+        assert_eq!(summary.total_files, 10);
+        assert_eq!(summary.warnings, 1);
+        let root_files: i64 = sqlx::query_scalar("SELECT file_count FROM nodes WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(tree.to_string_lossy().to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(root_files, 10);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn hardlinked_files_are_counted_once() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("links");
+        let sub = root.join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(root.join("a.bin"), vec![0u8; 1000]).unwrap();
+        fs::hard_link(root.join("a.bin"), root.join("b.bin")).unwrap();
+        fs::hard_link(root.join("a.bin"), sub.join("c.bin")).unwrap();
+        fs::write(sub.join("d.bin"), vec![0u8; 10]).unwrap();
+
+        let plain = scan(&pool, Uuid::new_v4(), &root, test_options(), None).await;
+        assert_eq!(plain.total_allocated_size, 3010);
+        assert_eq!(plain.dedup_saved_bytes, 0);
+
+        let options = ScanOptions { measure_hardlinks: true, ..test_options() };
+        let id = Uuid::new_v4();
+        let dedup = scan(&pool, id, &root, options, None).await;
+        assert_eq!(dedup.total_files, 4);
+        assert_eq!(dedup.total_logical_size, 3010);
+        assert_eq!(dedup.total_allocated_size, 1010);
+        assert_eq!(dedup.dedup_saved_bytes, 2000);
+
+        // Exactly one of the three links keeps its allocated size
+        let counted: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM files WHERE scan_id=?1 AND logical_size=1000 AND allocated_size=1000",
+        )
+        .bind(id.to_string())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(counted, 1);
+        let root_alloc: i64 = sqlx::query_scalar("SELECT allocated_size FROM nodes WHERE scan_id=?1 AND path=?2")
+            .bind(id.to_string())
+            .bind(root.to_string_lossy().to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(root_alloc, 1010);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn non_utf8_names_are_recorded_with_their_exact_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("names");
+        let bad_dir = root.join(OsStr::from_bytes(b"bad\xffdir"));
+        let bad_file = bad_dir.join(OsStr::from_bytes(b"f\xfe.bin"));
+        fs::create_dir_all(&bad_dir).unwrap();
+        fs::write(&bad_file, b"abc").unwrap();
+        fs::write(bad_dir.join("plain.bin"), b"de").unwrap();
+        fs::write(root.join("skip.tmp"), b"x").unwrap();
+
+        // Excludes used to drop every path that was not valid UTF-8
+        let options = ScanOptions { excludes: vec!["*.tmp".into()], ..test_options() };
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &root, options, None).await;
+        assert_eq!((summary.total_files, summary.total_dirs, summary.total_logical_size), (2, 2, 5));
+        // One warning for each invalid name, none for the valid file below the invalid directory
+        assert_eq!(summary.warnings, 2);
+
+        let files: Vec<(String, Option<Vec<u8>>)> =
+            sqlx::query_as("SELECT path, path_raw FROM files WHERE scan_id=?1 ORDER BY logical_size DESC")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(files[0].0, bad_file.to_string_lossy());
+        assert!(files[0].0.contains('\u{FFFD}'));
+        assert_eq!(files[0].1.as_deref(), Some(bad_file.as_os_str().as_bytes()));
+        assert_eq!(files[1].1.as_deref(), Some(bad_dir.join("plain.bin").as_os_str().as_bytes()));
+        let dirs: Vec<(String, Option<Vec<u8>>)> =
+            sqlx::query_as("SELECT path, path_raw FROM nodes WHERE scan_id=?1 ORDER BY depth")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            dirs,
+            vec![
+                (root.to_string_lossy().to_string(), None),
+                (bad_dir.to_string_lossy().to_string(), Some(bad_dir.as_os_str().as_bytes().to_vec())),
+            ]
         );
-        qb.push_values(chunk, |mut b, f| {
-            // Clamp u64 values to i64::MAX to prevent overflow when converting to i64 for SQLite
-            let logical_size_safe = f.logical_size.min(i64::MAX as u64) as i64;
-            let allocated_size_safe = f.allocated_size.min(i64::MAX as u64) as i64;
+    }
 
-            b.push_bind(&sid)
-                .push_bind(&f.path)
-                .push_bind(f.parent_path.as_deref())
-                .push_bind(logical_size_safe)
-                .push_bind(allocated_size_safe)
-                .push_bind(f.mtime)
-                .push_bind(f.atime);
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn empty_files_are_counted_per_directory() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("empty");
+        let sub = root.join("sub");
+        fs::create_dir_all(sub.join("none")).unwrap();
+        fs::File::create(root.join("a.lock")).unwrap();
+        fs::write(root.join("b.txt"), b"x").unwrap();
+        for i in 0..3 {
+            fs::File::create(sub.join(format!("e{}", i))).unwrap();
+        }
+
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &root, test_options(), None).await;
+        assert_eq!(summary.empty_files, 4);
+        let counts: Vec<(String, Option<i64>)> =
+            sqlx::query_as("SELECT path, empty_file_count FROM nodes WHERE scan_id=?1 ORDER BY path")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let path = |p: &Path| p.to_string_lossy().to_string();
+        assert_eq!(
+            counts,
+            vec![(path(&root), Some(1)), (path(&sub), Some(3)), (path(&sub.join("none")), Some(0))]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn subtotals_are_tracked_per_root() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let (first, second) = (data.path().join("first"), data.path().join("second"));
+        fs::create_dir_all(first.join("sub")).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(first.join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::write(first.join("sub").join("b.bin"), vec![0u8; 200]).unwrap();
+        fs::write(second.join("c.bin"), vec![0u8; 50]).unwrap();
+        let missing = data.path().join("missing").to_string_lossy().to_string();
+
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let roots =
+            vec![first.to_string_lossy().to_string(), second.to_string_lossy().to_string(), missing.clone()];
+        let (tx, _rx) = broadcast::channel(1024);
+        let summary = run_scan(
+            pool.clone(),
+            id,
+            roots.clone(),
+            test_options(),
+            tx,
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            FLUSH,
+            64 << 20,
+            50,
+            None,
+            Some(DIR_CONCURRENCY),
+            None,
+            Default::default(),
+            Reuse::Nothing,
+            &Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let sub = |root: &str, dirs, files, logical, warnings| RootSummary {
+            root: root.to_string(),
+            dir_count: dirs,
+            file_count: files,
+            logical_size: logical,
+            allocated_size: logical,
+            warning_count: warnings,
+        };
+        let expected =
+            vec![sub(&roots[0], 2, 2, 300, 0), sub(&roots[1], 1, 1, 50, 0), sub(&missing, 0, 0, 0, 1)];
+        assert_eq!(summary.roots, expected);
+        assert_eq!(summary.total_logical_size, 350);
+
+        let stored: Vec<(String, i64, i64)> = sqlx::query_as(
+            "SELECT root_path, file_count, warning_count FROM scan_roots WHERE scan_id=?1 \
+             ORDER BY root_index",
+        )
+        .bind(id.to_string())
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored, vec![(roots[0].clone(), 2, 0), (roots[1].clone(), 1, 0), (missing, 0, 1)]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sparse_files_report_allocated_blocks() {
+        const LOGICAL: u64 = 64 * 1024 * 1024;
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("sparse");
+        fs::create_dir_all(&root).unwrap();
+        // Extending a file without writing leaves a hole on every common Unix filesystem
+        fs::File::create(root.join("hole.img")).unwrap().set_len(LOGICAL).unwrap();
+        fs::write(root.join("small.txt"), b"abc").unwrap();
+
+        let options = ScanOptions { measure_allocated: true, ..test_options() };
+        let id = Uuid::new_v4();
+        let summary = scan(&pool, id, &root, options, None).await;
+        assert_eq!(summary.total_logical_size, LOGICAL + 3);
+        assert!(summary.total_allocated_size < LOGICAL, "allocated: {}", summary.total_allocated_size);
+
+        let (logical, allocated): (i64, i64) =
+            sqlx::query_as("SELECT logical_size, allocated_size FROM nodes WHERE scan_id=?1 AND path=?2")
+                .bind(id.to_string())
+                .bind(root.to_string_lossy().to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(logical as u64, LOGICAL + 3);
+        assert!(allocated < logical);
+        let small: i64 =
+            sqlx::query_scalar("SELECT allocated_size FROM files WHERE scan_id=?1 AND path LIKE '%small.txt'")
+                .bind(id.to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        // A non-empty file occupies at least one block
+        assert!(small >= 512, "allocated: {}", small);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn alternate_streams_are_stored_only_when_measured() {
+        let data = tempfile::tempdir().unwrap();
+        let pool = test_db_in(data.path()).await;
+        let root = data.path().join("streams");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("plain.txt"), b"hello").unwrap();
+
+        for measure_ads in [false, true] {
+            let options = ScanOptions { measure_ads, ..test_options() };
+            let id = Uuid::new_v4();
+            let summary = scan(&pool, id, &root, options, None).await;
+            assert_eq!(summary.total_logical_size, 5);
+            let ads: (Option<i64>, Option<i64>) =
+                sqlx::query_as("SELECT ads_count, ads_size FROM files WHERE scan_id=?1")
+                    .bind(id.to_string())
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            // Streams can only be listed on Windows, and a fresh file has none
+            if measure_ads && cfg!(windows) {
+                assert_eq!(ads, (Some(0), Some(0)));
+            } else {
+                assert_eq!(ads, (None, None));
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn scanner_gauges_rise_while_scanning_and_return_to_zero() {
+        let data = tempfile::tempdir().unwrap();
+        let root = data.path().join("root");
+        for d in 0..40 {
+            let sub = root.join(format!("d{:02}", d)).join("deep");
+            fs::create_dir_all(&sub).unwrap();
+            for f in 0..150 {
+                fs::write(sub.join(format!("f{:03}.bin", f)), b"x").unwrap();
+            }
+        }
+        let db_dir = tempfile::tempdir().unwrap();
+        let pool = test_db_in(db_dir.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+
+        let metrics = Metrics::default();
+        let scan_metrics = metrics.clone();
+        let (tx, _rx) = broadcast::channel(1024);
+        let roots = vec![root.to_string_lossy().to_string()];
+        // A long flush interval keeps records buffered between the ticks
+        let handle = tokio::spawn(async move {
+            run_scan(
+                pool,
+                id,
+                roots,
+                test_options(),
+                tx,
+                CancellationToken::new(),
+                Default::default(),
+                500,
+                FLUSH,
+                64 << 20,
+                200,
+                None,
+                Some(DIR_CONCURRENCY),
+                None,
+                Default::default(),
+                Reuse::Nothing,
+                &scan_metrics,
+                &Default::default(),
+            )
+            .await
         });
-        qb.build().execute(&mut *txdb).await?;
-        
-        chunks_processed += 1;
-        if chunks_processed >= 5 {
-            txdb.commit().await?;
-            txdb = pool.begin().await?;
-            chunks_processed = 0;
+
+        let (mut running, mut workers, mut buffered) = (0, 0, 0);
+        while !handle.is_finished() {
+            let m = metrics.get_snapshot();
+            running = running.max(m.scans_running);
+            workers = workers.max(m.scanner_active_workers);
+            buffered = buffered.max(m.scanner_buffered_records + m.scanner_queue_depth);
+            tokio::time::sleep(Duration::from_millis(1)).await;
         }
+        let summary = handle.await.unwrap().unwrap();
+        assert_eq!(summary.total_files, 6_000);
+        assert_eq!(running, 1);
+        assert!(workers > 0 && workers <= DIR_CONCURRENCY, "workers peaked at {}", workers);
+        assert!(buffered > 0);
+
+        let m = metrics.get_snapshot();
+        assert_eq!(m.scans_running, 0);
+        assert_eq!(m.scanner_active_workers, 0);
+        assert_eq!(m.scanner_queue_depth, 0);
+        assert_eq!(m.scanner_buffered_records, 0);
+        assert_eq!(m.scanner_buffered_bytes, 0);
+        assert!(summary.buffer_peak_bytes > 0);
+        assert_eq!(m.scanner_buffer_peak_bytes as u64, summary.buffer_peak_bytes);
     }
 
-    txdb.commit().await?;
-    nodes.clear();
-    files.clear();
-    Ok(())
-}
+    fn file_records(paths: &[&str]) -> Vec<FileRecord> {
+        paths
+            .iter()
+            .map(|p| FileRecord {
+                path: p.to_string(),
+                parent_path: Some("/data".into()),
+                logical_size: 1,
+                allocated_size: 1,
+                mtime: None,
+                atime: None,
+                ads: None,
+                owner: None,
+                category: None,
+                hash: None,
+                path_raw: None,
+            })
+            .collect()
+    }
+
+    /// Opens a second pool on the same database that gives up on locks right away,
+    /// plus a connection holding the write lock, and adds a scan to write to.
+    async fn locked_pool(dir: &Path) -> (sqlx::SqlitePool, sqlx::pool::PoolConnection<sqlx::Sqlite>, Uuid) {
+        let pool = test_db_in(dir).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let opts = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(dir.join("test.db"))
+            .busy_timeout(Duration::from_millis(1));
+        let impatient = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect_with(opts).await.unwrap();
+        let mut blocker = pool.acquire().await.unwrap();
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *blocker).await.unwrap();
+        (impatient, blocker, id)
+    }
 
-fn diff_summary(current: &ScanResultSummary, previous: &ScanResultSummary) -> ScanResultSummary {
-    ScanResultSummary {
-        total_dirs: current.total_dirs.saturating_sub(previous.total_dirs),
-        total_files: current.total_files.saturating_sub(previous.total_files),
-        total_logical_size: current.total_logical_size.saturating_sub(previous.total_logical_size),
-        total_allocated_size: current.total_allocated_size.saturating_sub(previous.total_allocated_size),
-        warnings: current.warnings.saturating_sub(previous.warnings),
-        latest_mtime: current.latest_mtime,
-        latest_atime: current.latest_atime,
+    fn warning_codes(rx: &mut broadcast::Receiver<ScanEvent>) -> Vec<(String, String)> {
+        let mut codes = Vec::new();
+        while let Ok(ev) = rx.try_recv() {
+            if let ScanEvent::Warning { path, code, .. } = ev {
+                codes.push((code, path));
+            }
+        }
+        codes
+    }
+
+    #[tokio::test]
+    async fn locked_database_is_retried_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pool, mut blocker, id) = locked_pool(dir.path()).await;
+        let (tx, mut rx) = broadcast::channel(64);
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            sqlx::query("COMMIT").execute(&mut *blocker).await.unwrap();
+        });
+
+        let mut files = file_records(&["/data/a", "/data/b", "/data/c"]);
+        let retry = PersistRetry { max_retries: 20, budget: Duration::from_secs(20) };
+        let warnings = persist_batches(&pool, id, &mut Vec::new(), &mut files, 2, &retry, &tx).await.unwrap();
+        release.await.unwrap();
+
+        assert!(warnings > 0);
+        assert!(files.is_empty());
+        let codes = warning_codes(&mut rx);
+        assert_eq!(codes.len() as u64, warnings);
+        assert!(codes.iter().all(|(code, _)| code == "db_busy"), "{codes:?}");
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 3);
+    }
+
+    #[tokio::test]
+    async fn locked_database_fails_once_the_retry_budget_is_used_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pool, _blocker, id) = locked_pool(dir.path()).await;
+        let (tx, mut rx) = broadcast::channel(64);
+
+        let mut files = file_records(&["/data/a"]);
+        let retry = PersistRetry { max_retries: 3, budget: Duration::from_secs(10) };
+        let res = persist_batches(&pool, id, &mut Vec::new(), &mut files, 10, &retry, &tx).await;
+        assert!(res.is_err());
+        assert_eq!(files.len(), 1);
+        assert_eq!(warning_codes(&mut rx).len(), 3);
+
+        let retry = PersistRetry { max_retries: 100, budget: Duration::from_millis(120) };
+        let res = persist_batches(&pool, id, &mut Vec::new(), &mut files, 10, &retry, &tx).await;
+        assert!(res.is_err());
+        // 50 + 100 ms would exceed the budget after the first retry
+        assert_eq!(warning_codes(&mut rx).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejected_records_are_isolated_and_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_db_in(dir.path()).await;
+        sqlx::query(
+            "CREATE TRIGGER reject_bad BEFORE INSERT ON files WHEN NEW.path = '/data/bad' \
+             BEGIN SELECT RAISE(ABORT, 'bad record'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let (tx, mut rx) = broadcast::channel(64);
+
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let paths: Vec<String> = (0..9).map(|i| format!("/data/f{i}")).collect();
+        let mut all: Vec<&str> = paths.iter().map(String::as_str).collect();
+        all.insert(6, "/data/bad");
+        let mut files = file_records(&all);
+        let warnings =
+            persist_batches(&pool, id, &mut Vec::new(), &mut files, 4, &PersistRetry::default(), &tx).await.unwrap();
+
+        assert_eq!(warnings, 1);
+        assert_eq!(warning_codes(&mut rx), vec![("persist_failed".to_string(), "/data/bad".to_string())]);
+        let stored: Vec<String> = sqlx::query_scalar("SELECT path FROM files WHERE scan_id=?1 ORDER BY path")
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, paths);
+    }
+
+    #[tokio::test]
+    async fn writing_a_path_again_refreshes_its_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let (tx, _rx) = broadcast::channel(16);
+        let node = |size: u64, files: u64| NodeRecord {
+            path: "/data".into(),
+            parent_path: None,
+            depth: 1,
+            is_dir: true,
+            logical_size: size,
+            allocated_size: size,
+            file_count: files,
+            dir_count: 0,
+            mtime: Some(size as i64),
+            atime: None,
+            fingerprint: None,
+            empty_file_count: 0,
+            path_raw: None,
+        };
+
+        let retry = PersistRetry::default();
+        let mut files = file_records(&["/data/a"]);
+        persist_batches(&pool, id, &mut vec![node(100, 1)], &mut files, 10, &retry, &tx).await.unwrap();
+        let mut files = file_records(&["/data/a"]);
+        files[0].logical_size = 7;
+        files[0].mtime = Some(42);
+        persist_batches(&pool, id, &mut vec![node(250, 3)], &mut files, 10, &retry, &tx).await.unwrap();
+
+        let nodes: Vec<(i64, i64, Option<i64>)> =
+            sqlx::query_as("SELECT allocated_size, file_count, mtime FROM nodes WHERE scan_id=?1")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(nodes, vec![(250, 3, Some(250))]);
+        let files: Vec<(i64, Option<i64>)> = sqlx::query_as("SELECT logical_size, mtime FROM files WHERE scan_id=?1")
+            .bind(id.to_string())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(files, vec![(7, Some(42))]);
     }
 }
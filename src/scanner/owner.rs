@@ -0,0 +1,215 @@
+//! File owners for scans with `capture_owner`.
+//!
+//! On Windows the owner SID of every file is read with `GetNamedSecurityInfoW`
+//! and resolved to `DOMAIN\name` with `LookupAccountSidW`. Account lookups can
+//! hit a domain controller, so the names are cached per SID; SIDs that no
+//! longer resolve (deleted accounts) are recorded as the SID string. On Unix
+//! the numeric user ID is recorded. Files whose owner cannot be read are
+//! recorded as [`UNKNOWN_OWNER`] and counted, and the scan ends with a single
+//! `owner_unknown` warning instead of one per file.
+//!
+//! The owner is stored on each file row. The `owner_usage` table sums the
+//! files directly inside each directory per owner, which is what
+//! `GET /scans/{id}/owners` adds up for a subtree.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::routes::scans::subtree_like_pattern;
+
+/// The owner recorded for files whose owner could not be read.
+pub const UNKNOWN_OWNER: &str = "unknown";
+
+/// Resolves file owners during one scan and counts the failures.
+#[derive(Default)]
+pub(crate) struct OwnerLookup {
+    failures: AtomicU64,
+}
+
+impl OwnerLookup {
+    /// Returns the owner of a file, or [`UNKNOWN_OWNER`] if it cannot be read.
+    ///
+    /// Issues blocking system calls, so it must only run on the scanner's
+    /// worker threads or inside `spawn_blocking`.
+    pub fn owner_of(&self, path: &Path, md: &fs::Metadata) -> String {
+        file_owner(path, md).unwrap_or_else(|| {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            UNKNOWN_OWNER.to_string()
+        })
+    }
+
+    /// The number of files whose owner could not be read.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns the owner of a file, `None` if it cannot be read.
+#[cfg(windows)]
+pub(crate) fn file_owner(path: &Path, _md: &fs::Metadata) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, ERROR_SUCCESS, HLOCAL};
+    use windows::Win32::Security::Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT};
+    use windows::Win32::Security::{OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID};
+
+    let w: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut owner = PSID::default();
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    // SAFETY: `w` is NUL-terminated; the returned SID points into `descriptor`,
+    // which is freed below after the SID was last used.
+    let err = unsafe {
+        GetNamedSecurityInfoW(
+            PCWSTR(w.as_ptr()),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            Some(&mut owner),
+            None,
+            None,
+            None,
+            &mut descriptor,
+        )
+    };
+    if err != ERROR_SUCCESS {
+        tracing::debug!("GetNamedSecurityInfoW failed for {:?}: {:?}", path, err);
+        return None;
+    }
+    let name = windows_impl::account_name(owner);
+    // SAFETY: The descriptor was allocated by GetNamedSecurityInfoW.
+    unsafe {
+        let _ = LocalFree(Some(HLOCAL(descriptor.0)));
+    }
+    name
+}
+
+#[cfg(unix)]
+pub(crate) fn file_owner(_path: &Path, md: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    Some(md.uid().to_string())
+}
+
+#[cfg(not(any(windows, unix)))]
+pub(crate) fn file_owner(_path: &Path, _md: &fs::Metadata) -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+mod windows_impl {
+    use lru::LruCache;
+    use std::sync::Mutex;
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows::Win32::Security::{LookupAccountSidW, PSID, SID_NAME_USE};
+
+    lazy_static::lazy_static! {
+        // Account names per SID string; a scan usually meets only a handful of owners
+        static ref ACCOUNT_CACHE: Mutex<LruCache<String, String>> = {
+            let size = std::num::NonZeroUsize::new(super::super::get_cache_size())
+                .unwrap_or_else(|| std::num::NonZeroUsize::new(1000).unwrap());
+            Mutex::new(LruCache::new(size))
+        };
+    }
+
+    /// Returns `DOMAIN\name` for a SID, the SID string if it does not resolve.
+    pub fn account_name(sid: PSID) -> Option<String> {
+        let key = sid_string(sid)?;
+        if let Ok(mut cache) = ACCOUNT_CACHE.try_lock() {
+            if let Some(name) = cache.get(&key) {
+                return Some(name.clone());
+            }
+        }
+        let name = lookup(sid).unwrap_or_else(|| key.clone());
+        if let Ok(mut cache) = ACCOUNT_CACHE.try_lock() {
+            cache.put(key, name.clone());
+        }
+        Some(name)
+    }
+
+    fn sid_string(sid: PSID) -> Option<String> {
+        let mut raw = PWSTR::null();
+        // SAFETY: `sid` is valid while the caller holds its security descriptor.
+        unsafe { ConvertSidToStringSidW(sid, &mut raw) }.ok()?;
+        // SAFETY: On success `raw` is a NUL-terminated string allocated with LocalAlloc.
+        let s = unsafe { raw.to_string() }.ok();
+        // SAFETY: See above; the string is not used after this.
+        unsafe {
+            let _ = LocalFree(Some(HLOCAL(raw.0.cast())));
+        }
+        s
+    }
+
+    fn lookup(sid: PSID) -> Option<String> {
+        // Account and domain names are limited to 256 characters
+        let mut name = [0u16; 257];
+        let mut domain = [0u16; 257];
+        let (mut name_len, mut domain_len) = (name.len() as u32, domain.len() as u32);
+        let mut kind = SID_NAME_USE::default();
+        // SAFETY: The buffers outlive the call and their lengths are passed along.
+        unsafe {
+            LookupAccountSidW(
+                PCWSTR::null(),
+                sid,
+                Some(PWSTR(name.as_mut_ptr())),
+                &mut name_len,
+                Some(PWSTR(domain.as_mut_ptr())),
+                &mut domain_len,
+                &mut kind,
+            )
+        }
+        .ok()?;
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        Some(if domain.is_empty() { name } else { format!("{}\\{}", domain, name) })
+    }
+}
+
+/// Which rows of `owner_usage` [`refresh_usage`] rebuilds.
+pub(crate) enum UsageScope<'a> {
+    /// Every directory of the scan.
+    Scan,
+    /// One directory.
+    Dir(&'a str),
+    /// A directory and everything below it.
+    Subtree(&'a str),
+}
+
+/// Rebuilds the per-directory owner totals of a scan from its file rows.
+///
+/// Files without an owner, i.e. from scans without `capture_owner`, are left out.
+pub(crate) async fn refresh_usage(
+    conn: &mut sqlx::SqliteConnection,
+    sid: &str,
+    scope: UsageScope<'_>,
+) -> Result<(), sqlx::Error> {
+    // Directories are stored as `path` here and referenced as `parent_path` by the files
+    let filter = |qb: &mut QueryBuilder<'_, Sqlite>, column: &str| match &scope {
+        UsageScope::Scan => {}
+        UsageScope::Dir(dir) => {
+            qb.push(format!(" AND {} = ", column)).push_bind(dir.to_string());
+        }
+        UsageScope::Subtree(dir) => {
+            qb.push(format!(" AND ({} = ", column)).push_bind(dir.to_string());
+            qb.push(format!(" OR {} LIKE ", column)).push_bind(subtree_like_pattern(dir));
+            qb.push(" ESCAPE '!')");
+        }
+    };
+    let mut qb = QueryBuilder::new("DELETE FROM owner_usage WHERE scan_id = ");
+    qb.push_bind(sid);
+    filter(&mut qb, "path");
+    qb.build().execute(&mut *conn).await?;
+
+    let mut qb = QueryBuilder::new(
+        "INSERT INTO owner_usage (scan_id, path, owner, file_count, logical_size, allocated_size) \
+         SELECT scan_id, parent_path, owner, COUNT(*), SUM(logical_size), SUM(allocated_size) \
+         FROM files WHERE owner IS NOT NULL AND parent_path IS NOT NULL AND scan_id = ",
+    );
+    qb.push_bind(sid);
+    filter(&mut qb, "parent_path");
+    qb.push(" GROUP BY parent_path, owner");
+    qb.build().execute(&mut *conn).await?;
+    Ok(())
+}
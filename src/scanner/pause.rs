@@ -0,0 +1,108 @@
+//! Pausing of running scans.
+//!
+//! The scanner's workers are blocking threads, so the gate is a mutex and a
+//! condition variable rather than an async primitive. Workers check it before
+//! they start a directory, next to the cancellation token; a paused scan thus
+//! finishes the listings it is in and then waits, while the aggregator keeps
+//! flushing buffered records to SQLite.
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+/// How often a waiting worker checks whether the scan was cancelled.
+pub(crate) const CANCEL_POLL: Duration = Duration::from_millis(200);
+
+/// Holds the workers of a scan while it is paused.
+#[derive(Debug, Default)]
+pub struct PauseGate {
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl PauseGate {
+    /// Pauses the scan.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the scan was running, `false` if it was paused already.
+    pub fn pause(&self) -> bool {
+        let mut paused = self.lock();
+        !std::mem::replace(&mut *paused, true)
+    }
+
+    /// Resumes the scan and wakes all waiting workers.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the scan was paused, `false` if it was running already.
+    pub fn resume(&self) -> bool {
+        let was_paused = std::mem::replace(&mut *self.lock(), false);
+        self.resumed.notify_all();
+        was_paused
+    }
+
+    /// Returns whether the scan is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.lock()
+    }
+
+    /// Blocks the calling thread while the scan is paused and not cancelled.
+    ///
+    /// Must only be called from blocking threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - The cancellation token of the scan; cancelling ends the wait.
+    pub fn wait_while_paused(&self, cancel: &CancellationToken) {
+        let mut paused = self.lock();
+        while *paused && !cancel.is_cancelled() {
+            paused = match self.resumed.wait_timeout(paused, CANCEL_POLL) {
+                Ok((guard, _)) => guard,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, bool> {
+        // A worker panicking while holding the lock must not wedge the scan
+        self.paused.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn waiting_workers_continue_on_resume_and_cancel() {
+        let gate = Arc::new(PauseGate::default());
+        assert!(gate.pause());
+        assert!(!gate.pause());
+
+        let cancel = CancellationToken::new();
+        let waiter = {
+            let (gate, cancel) = (gate.clone(), cancel.clone());
+            std::thread::spawn(move || gate.wait_while_paused(&cancel))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        assert!(gate.resume());
+        waiter.join().unwrap();
+        assert!(!gate.resume());
+
+        gate.pause();
+        let waiter = {
+            let (gate, cancel) = (gate.clone(), cancel.clone());
+            std::thread::spawn(move || gate.wait_while_paused(&cancel))
+        };
+        let started = Instant::now();
+        cancel.cancel();
+        waiter.join().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(gate.is_paused());
+    }
+}
@@ -0,0 +1,135 @@
+//! Shallow previews of a directory that has not been scanned.
+//!
+//! Only the direct children are read, with one `stat` each; directories are
+//! not descended into. Children the scanner would leave out (excluded,
+//! hidden, links and reparse points that are not followed) are counted but
+//! not listed, using the same checks as [`scan_dir`](super::scan_dir).
+//! Listing stops at the entry limit or when the time budget runs out.
+//! Nothing is written to the database.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::{build_globset, check_entry, matches_excludes, system_time_to_secs};
+use crate::types::{DirPreview, PreviewEntry, ScanOptions};
+
+/// Lists the direct children of `dir`.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to preview.
+/// * `options` - The options a scan of the directory would run with.
+/// * `limit` - The most entries returned.
+/// * `budget` - How long the listing may take.
+///
+/// # Returns
+///
+/// The children, or the error of reading the directory itself. A path that
+/// is not a directory fails with [`ErrorKind::NotADirectory`].
+pub fn preview_dir(
+    dir: &Path,
+    options: &ScanOptions,
+    limit: usize,
+    budget: Duration,
+) -> io::Result<DirPreview> {
+    let started = Instant::now();
+    let globset = |patterns| build_globset(patterns).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e));
+    let excludes = globset(&options.excludes)?;
+    let includes = globset(&options.includes)?;
+    if !fs::metadata(dir)?.is_dir() {
+        return Err(io::Error::new(ErrorKind::NotADirectory, "not a directory"));
+    }
+
+    let mut entries = Vec::new();
+    let mut skipped = 0u64;
+    let mut truncated = false;
+    for entry in fs::read_dir(dir)? {
+        if entries.len() >= limit || started.elapsed() >= budget {
+            truncated = true;
+            break;
+        }
+        // Children that vanish or cannot be read are left out, as in a scan
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        let Ok(md) = entry.metadata() else { continue };
+        let md = if md.file_type().is_symlink() {
+            match options.follow_symlinks.then(|| fs::metadata(&path).ok()).flatten() {
+                Some(target) => target,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            }
+        } else {
+            md
+        };
+        let checks = check_entry(&path, &md, options, &includes, 0);
+        if matches_excludes(&path, &excludes) || checks.skipped() {
+            skipped += 1;
+            continue;
+        }
+        entries.push(PreviewEntry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_dir: md.is_dir(),
+            logical_size: (!md.is_dir()).then_some(md.len()),
+            mtime: system_time_to_secs(md.modified().ok()),
+        });
+    }
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| b.logical_size.cmp(&a.logical_size))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    Ok(DirPreview {
+        path: dir.to_string_lossy().to_string(),
+        entries,
+        skipped,
+        truncated,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_the_direct_children_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("root");
+        fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+        fs::write(dir.join("sub/deeper/big.bin"), vec![0u8; 4096]).unwrap();
+        fs::write(dir.join("small.txt"), b"abc").unwrap();
+        fs::write(dir.join("large.txt"), vec![0u8; 100]).unwrap();
+        fs::write(dir.join(".hidden"), b"x").unwrap();
+        fs::write(dir.join("skip.tmp"), b"x").unwrap();
+        std::os::unix::fs::symlink(dir.join("sub"), dir.join("link")).unwrap();
+        let options =
+            ScanOptions { include_hidden: false, excludes: vec!["*.tmp".into()], ..Default::default() };
+
+        let preview = preview_dir(&dir, &options, 100, Duration::from_secs(2)).unwrap();
+        let names: Vec<&str> = preview.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["sub", "large.txt", "small.txt"]);
+        assert_eq!(preview.entries[0].logical_size, None);
+        assert_eq!(preview.entries[1].logical_size, Some(100));
+        assert!(preview.entries[1].mtime.is_some());
+        assert_eq!(preview.skipped, 3);
+        assert!(!preview.truncated);
+
+        let preview = preview_dir(&dir, &options, 1, Duration::from_secs(2)).unwrap();
+        assert_eq!(preview.entries.len(), 1);
+        assert!(preview.truncated);
+        let preview = preview_dir(&dir, &options, 100, Duration::ZERO).unwrap();
+        assert!(preview.entries.is_empty());
+        assert!(preview.truncated);
+
+        let err = preview_dir(&dir.join("small.txt"), &options, 100, Duration::from_secs(2)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotADirectory);
+        let err = preview_dir(&dir.join("missing"), &options, 100, Duration::from_secs(2)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+}
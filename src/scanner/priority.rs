@@ -0,0 +1,202 @@
+//! OS-level I/O priority of the scanner threads.
+//!
+//! Scans of busy file servers can saturate the disks other services rely on.
+//! With `io_priority` set, every thread that walks directories lowers its own
+//! priority while it works: on Windows through `SetThreadPriority` (background
+//! mode also lowers the I/O and memory priority), on Linux through
+//! `ioprio_set`. Only the calling thread is affected, never the server process,
+//! and the previous priority is restored when the guard is dropped because the
+//! blocking threads of the tokio runtime are reused for other work.
+
+use crate::types::IoPriority;
+
+/// Restores the priority of the current thread when dropped.
+#[must_use = "the priority is restored as soon as the guard is dropped"]
+pub struct PriorityGuard {
+    #[cfg(windows)]
+    restore: Option<windows_impl::Restore>,
+    #[cfg(target_os = "linux")]
+    restore: Option<i32>,
+}
+
+/// Applies `priority` to the current thread until the returned guard is dropped.
+///
+/// Failures are logged and otherwise ignored; a scan never fails because its
+/// priority could not be changed.
+pub fn enter(priority: IoPriority) -> PriorityGuard {
+    if priority == IoPriority::Normal {
+        return PriorityGuard {
+            #[cfg(any(windows, target_os = "linux"))]
+            restore: None,
+        };
+    }
+    #[cfg(windows)]
+    {
+        PriorityGuard { restore: windows_impl::enter(priority) }
+    }
+    #[cfg(target_os = "linux")]
+    {
+        PriorityGuard { restore: linux_impl::enter(priority) }
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        tracing::debug!(?priority, "io_priority is not supported on this platform");
+        PriorityGuard {}
+    }
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        #[cfg(windows)]
+        if let Some(restore) = self.restore.take() {
+            windows_impl::restore(restore);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(previous) = self.restore.take() {
+            linux_impl::restore(previous);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows_impl {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, GetThreadPriority, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+        THREAD_MODE_BACKGROUND_END, THREAD_PRIORITY, THREAD_PRIORITY_BELOW_NORMAL,
+    };
+
+    use crate::types::IoPriority;
+
+    /// How to undo a priority change.
+    pub enum Restore {
+        /// Leave background mode.
+        Background,
+        /// Set the thread priority back to the given value.
+        Priority(THREAD_PRIORITY),
+    }
+
+    pub fn enter(priority: IoPriority) -> Option<Restore> {
+        // SAFETY: GetCurrentThread returns a pseudo handle that needs no closing.
+        let thread = unsafe { GetCurrentThread() };
+        match priority {
+            IoPriority::Normal => None,
+            IoPriority::Background => {
+                // SAFETY: Only the calling thread's own scheduling state is changed.
+                match unsafe { SetThreadPriority(thread, THREAD_MODE_BACKGROUND_BEGIN) } {
+                    Ok(()) => Some(Restore::Background),
+                    Err(e) => {
+                        tracing::debug!("THREAD_MODE_BACKGROUND_BEGIN failed: {e}");
+                        None
+                    }
+                }
+            }
+            IoPriority::Low => {
+                // SAFETY: See above.
+                let previous = unsafe { GetThreadPriority(thread) };
+                // THREAD_PRIORITY_ERROR_RETURN
+                if previous == i32::MAX {
+                    return None;
+                }
+                // SAFETY: See above.
+                match unsafe { SetThreadPriority(thread, THREAD_PRIORITY_BELOW_NORMAL) } {
+                    Ok(()) => Some(Restore::Priority(THREAD_PRIORITY(previous))),
+                    Err(e) => {
+                        tracing::debug!("THREAD_PRIORITY_BELOW_NORMAL failed: {e}");
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn restore(restore: Restore) {
+        // SAFETY: See `enter`.
+        let thread = unsafe { GetCurrentThread() };
+        let priority = match restore {
+            Restore::Background => THREAD_MODE_BACKGROUND_END,
+            Restore::Priority(previous) => previous,
+        };
+        // SAFETY: See `enter`.
+        if let Err(e) = unsafe { SetThreadPriority(thread, priority) } {
+            tracing::debug!("restoring the thread priority failed: {e}");
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux_impl {
+    use crate::types::IoPriority;
+
+    /// `IOPRIO_WHO_PROCESS`; with an ID of 0 it targets the calling thread.
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_SHIFT: i32 = 13;
+    const IOPRIO_CLASS_BE: i32 = 2;
+    const IOPRIO_CLASS_IDLE: i32 = 3;
+    /// The lowest level of the best-effort class.
+    const IOPRIO_BE_LOWEST: i32 = 7;
+
+    fn ioprio_get() -> Option<i32> {
+        // SAFETY: ioprio_get only reads the scheduling state of the calling thread.
+        let value = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0) };
+        i32::try_from(value).ok().filter(|v| *v >= 0)
+    }
+
+    fn ioprio_set(value: i32) -> bool {
+        // SAFETY: ioprio_set only changes the scheduling state of the calling thread.
+        unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, value) == 0 }
+    }
+
+    pub fn enter(priority: IoPriority) -> Option<i32> {
+        let value = match priority {
+            IoPriority::Normal => return None,
+            IoPriority::Low => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | IOPRIO_BE_LOWEST,
+            IoPriority::Background => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        };
+        let previous = ioprio_get()?;
+        if ioprio_set(value) {
+            Some(previous)
+        } else {
+            tracing::debug!(?priority, "ioprio_set failed: {}", std::io::Error::last_os_error());
+            None
+        }
+    }
+
+    pub fn restore(previous: i32) {
+        if !ioprio_set(previous) {
+            tracing::debug!("restoring the I/O priority failed: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(test)]
+    pub fn current() -> Option<i32> {
+        ioprio_get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn guard_lowers_and_restores_the_thread_priority() {
+        std::thread::spawn(|| {
+            let before = linux_impl::current().expect("ioprio_get");
+            {
+                let _guard = enter(IoPriority::Background);
+                assert_eq!(linux_impl::current(), Some(3 << 13));
+            }
+            assert_eq!(linux_impl::current(), Some(before));
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn normal_priority_changes_nothing() {
+        let guard = enter(IoPriority::Normal);
+        #[cfg(any(windows, target_os = "linux"))]
+        assert!(guard.restore.is_none());
+        drop(guard);
+    }
+}
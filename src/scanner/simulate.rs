@@ -0,0 +1,183 @@
+//! What-if simulation of exclude patterns against a stored scan.
+//!
+//! A scan skips a directory that matches an exclude pattern together with its
+//! whole subtree, and a file that matches on its own. The simulation applies
+//! the patterns the same way to the stored paths, with the same
+//! [`build_globset`] and [`matches_excludes`] the scanner uses, and sums up
+//! what the totals would have lost. Nothing is written.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use futures::stream::TryStreamExt;
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+use super::{build_globset, matches_excludes};
+use crate::types::{ExcludeSimulation, ExcludeSimulationDir};
+
+/// The number of contributing directories returned.
+pub const TOP_DIRS: usize = 20;
+
+/// Computes what a scan with the additional exclude `patterns` would not have recorded.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `id` - The ID of the scan.
+/// * `patterns` - The exclude patterns to simulate.
+///
+/// # Returns
+///
+/// * `anyhow::Result<ExcludeSimulation>` - The removed counts and sizes. Fails
+///   if a pattern is not a valid glob.
+pub async fn simulate_excludes(
+    pool: &SqlitePool,
+    id: Uuid,
+    patterns: &[String],
+) -> anyhow::Result<ExcludeSimulation> {
+    let set = build_globset(patterns)?;
+    let sid = id.to_string();
+    let mut excluded_dirs: HashSet<String> = HashSet::new();
+    let mut contributions: HashMap<String, ExcludeSimulationDir> = HashMap::new();
+    let mut result = ExcludeSimulation {
+        scan_id: id,
+        patterns: patterns.to_vec(),
+        excluded_files: 0,
+        excluded_dirs: 0,
+        logical_size: 0,
+        allocated_size: 0,
+        top_dirs: Vec::new(),
+    };
+
+    // Parents come first, so a directory below an excluded one is known to be excluded already
+    let mut dirs = sqlx::query(
+        "SELECT path, parent_path, logical_size, allocated_size, file_count, dir_count \
+         FROM nodes WHERE scan_id=?1 AND is_dir=1 ORDER BY depth",
+    )
+    .bind(&sid)
+    .fetch(pool);
+    while let Some(r) = dirs.try_next().await? {
+        let path: String = r.get("path");
+        let below_excluded =
+            r.get::<Option<String>, _>("parent_path").is_some_and(|parent| excluded_dirs.contains(&parent));
+        if below_excluded || matches_excludes(Path::new(&path), &set) {
+            if !below_excluded {
+                let dir = ExcludeSimulationDir {
+                    path: path.clone(),
+                    excluded: true,
+                    file_count: count(&r, "file_count"),
+                    dir_count: count(&r, "dir_count").saturating_add(1),
+                    logical_size: count(&r, "logical_size"),
+                    allocated_size: count(&r, "allocated_size"),
+                };
+                result.excluded_files = result.excluded_files.saturating_add(dir.file_count);
+                result.excluded_dirs = result.excluded_dirs.saturating_add(dir.dir_count);
+                result.logical_size = result.logical_size.saturating_add(dir.logical_size);
+                result.allocated_size = result.allocated_size.saturating_add(dir.allocated_size);
+                contributions.insert(path.clone(), dir);
+            }
+            excluded_dirs.insert(path);
+        }
+    }
+    drop(dirs);
+
+    let mut files =
+        sqlx::query("SELECT path, parent_path, logical_size, allocated_size FROM files WHERE scan_id=?1")
+            .bind(&sid)
+            .fetch(pool);
+    while let Some(r) = files.try_next().await? {
+        let parent: Option<String> = r.get("parent_path");
+        if parent.as_ref().is_some_and(|parent| excluded_dirs.contains(parent)) {
+            continue;
+        }
+        let path: String = r.get("path");
+        if !matches_excludes(Path::new(&path), &set) {
+            continue;
+        }
+        let (logical, allocated) = (count(&r, "logical_size"), count(&r, "allocated_size"));
+        result.excluded_files = result.excluded_files.saturating_add(1);
+        result.logical_size = result.logical_size.saturating_add(logical);
+        result.allocated_size = result.allocated_size.saturating_add(allocated);
+        let key = parent.unwrap_or(path);
+        let dir = contributions.entry(key.clone()).or_insert_with(|| ExcludeSimulationDir {
+            path: key,
+            excluded: false,
+            file_count: 0,
+            dir_count: 0,
+            logical_size: 0,
+            allocated_size: 0,
+        });
+        dir.file_count += 1;
+        dir.logical_size = dir.logical_size.saturating_add(logical);
+        dir.allocated_size = dir.allocated_size.saturating_add(allocated);
+    }
+
+    let mut top: Vec<ExcludeSimulationDir> = contributions.into_values().collect();
+    top.sort_by(|a, b| {
+        (b.allocated_size, b.logical_size)
+            .cmp(&(a.allocated_size, a.logical_size))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    top.truncate(TOP_DIRS);
+    result.top_dirs = top;
+    Ok(result)
+}
+
+fn count(r: &sqlx::sqlite::SqliteRow, col: &str) -> u64 {
+    r.get::<Option<i64>, _>(col).unwrap_or(0).max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_file, insert_node, insert_scan, test_db, FileRow, NodeRow};
+
+    /// `/r` with `/r/a` (1 file, 100 bytes), `/r/a/node_modules` (2 files, 300 bytes)
+    /// and `/r/b` (a 50 byte `.log` and a 10 byte `.txt`).
+    async fn fixture() -> (tempfile::TempDir, SqlitePool, Uuid) {
+        let (dir, pool) = test_db().await;
+        let id = insert_scan(&pool, "done", &["/r"], "default").await;
+        for (path, parent, depth, size, files, dirs) in [
+            ("/r", None, 0, 460, 5, 3),
+            ("/r/a", Some("/r"), 1, 400, 3, 1),
+            ("/r/a/node_modules", Some("/r/a"), 2, 300, 2, 0),
+            ("/r/b", Some("/r"), 1, 60, 2, 0),
+        ] {
+            insert_node(&pool, id, NodeRow { path, parent, depth, size, files, dirs, ..Default::default() })
+                .await;
+        }
+        for (path, parent, size) in [
+            ("/r/a/main.rs", "/r/a", 100),
+            ("/r/a/node_modules/x.js", "/r/a/node_modules", 100),
+            ("/r/a/node_modules/y.log", "/r/a/node_modules", 200),
+            ("/r/b/app.log", "/r/b", 50),
+            ("/r/b/notes.txt", "/r/b", 10),
+        ] {
+            insert_file(&pool, id, FileRow { path, parent: Some(parent), size, ..Default::default() }).await;
+        }
+        (dir, pool, id)
+    }
+
+    #[tokio::test]
+    async fn excluded_subtrees_and_files_are_summed_once() {
+        let (_dir, pool, id) = fixture().await;
+
+        let sim = simulate_excludes(&pool, id, &["**/node_modules".into(), "*.log".into()]).await.unwrap();
+        // y.log lies below the excluded directory and is only counted with it
+        assert_eq!((sim.excluded_files, sim.excluded_dirs), (3, 1));
+        assert_eq!((sim.logical_size, sim.allocated_size), (350, 350));
+        let top: Vec<(&str, bool, u64)> =
+            sim.top_dirs.iter().map(|d| (d.path.as_str(), d.excluded, d.allocated_size)).collect();
+        assert_eq!(top, [("/r/a/node_modules", true, 300), ("/r/b", false, 50)]);
+
+        // Backslashes are normalized like in a scan
+        let sim = simulate_excludes(&pool, id, &["**\\b".into()]).await.unwrap();
+        assert_eq!((sim.excluded_files, sim.excluded_dirs, sim.allocated_size), (2, 1, 60));
+
+        let sim = simulate_excludes(&pool, id, &[]).await.unwrap();
+        assert_eq!((sim.excluded_files, sim.excluded_dirs), (0, 0));
+        assert!(sim.top_dirs.is_empty());
+        assert!(simulate_excludes(&pool, id, &["a[".into()]).await.is_err());
+    }
+}
@@ -0,0 +1,640 @@
+//! Keeping a finished scan up to date from filesystem notifications.
+//!
+//! A watcher subscribes to recursive notifications for the roots of a scan and
+//! collects the changed paths for a debounce interval before applying them.
+//! Every changed path is looked up on disk again instead of trusting the event
+//! kind, so bursts of events for one file collapse into a single update and a
+//! rename is simply the removal of the old path plus the creation of the new one.
+//!
+//! Size changes are propagated to all ancestor directories and to the totals of
+//! the scan. Hardlinks are not deduplicated for files added by the watcher.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use globset::GlobSet;
+use notify::{EventKind, RecursiveMode, Watcher};
+use sqlx::Row;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{
+    build_globset, is_hidden_or_system, is_reparse_point, matches_excludes, matches_includes, measure_file,
+    category::CategoryMap,
+    owner::{self, UsageScope},
+    persist_batches, raw_path_bytes, scan_subtree, system_time_to_secs, PersistRetry, Subtree,
+};
+use crate::config::CategoriesConfig;
+use crate::routes::paths::remove_path_from_scan;
+use crate::types::{ScanEvent, ScanOptions};
+
+/// Watches the roots of a scan and applies filesystem changes to its stored rows.
+///
+/// Runs until `cancel` is triggered or the scan row is deleted. Each applied
+/// batch is announced with a `ScanEvent::Updated` on `tx`.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `id` - The ID of the scan.
+/// * `root_paths` - The root paths of the scan.
+/// * `options` - The options the scan was run with.
+/// * `tx` - A broadcast sender for update and warning events.
+/// * `cancel` - A cancellation token for stopping the watcher.
+/// * `batch_size` - The number of records to insert in a single statement when new directories are added.
+/// * `debounce` - How long changes are collected before they are applied.
+/// * `max_entries_per_dir` - The maximum number of entries read from a new directory.
+/// * `persist_retry` - How writes are retried while the database is locked.
+/// * `categories` - The categories added files are classified into.
+///
+/// # Returns
+///
+/// * `anyhow::Result<()>` - An error if none of the roots can be watched.
+#[allow(clippy::too_many_arguments)]
+pub async fn watch_scan(
+    pool: sqlx::SqlitePool,
+    id: Uuid,
+    root_paths: Vec<String>,
+    options: ScanOptions,
+    tx: broadcast::Sender<ScanEvent>,
+    cancel: CancellationToken,
+    batch_size: usize,
+    debounce: Duration,
+    max_entries_per_dir: Option<u64>,
+    persist_retry: PersistRetry,
+    categories: &CategoriesConfig,
+) -> anyhow::Result<()> {
+    let globset = build_globset(&options.excludes)?;
+    let includes = build_globset(&options.includes)?;
+    let (ev_tx, mut ev_rx) = mpsc::unbounded_channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = ev_tx.send(res);
+    })?;
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for root in &root_paths {
+        let path = PathBuf::from(root);
+        match watcher.watch(&path, RecursiveMode::Recursive) {
+            Ok(()) => roots.push(path),
+            Err(e) => {
+                tracing::warn!("Cannot watch {} for scan {}: {}", root, id, e);
+                let _ = tx.send(ScanEvent::Warning {
+                    path: root.clone(),
+                    code: "watch_failed".into(),
+                    message: format!("cannot watch root: {}", e),
+                });
+            }
+        }
+    }
+    if roots.is_empty() {
+        anyhow::bail!("none of the scan roots can be watched");
+    }
+
+    let ctx = ApplyCtx {
+        pool: pool.clone(),
+        id,
+        roots,
+        options,
+        globset,
+        includes,
+        tx: tx.clone(),
+        cancel: cancel.clone(),
+        batch_size,
+        max_entries_per_dir,
+        persist_retry,
+        categories: CategoryMap::for_scan(categories),
+    };
+    let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut ticker = interval(debounce);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            ev = ev_rx.recv() => match ev {
+                Some(Ok(event)) => {
+                    if event.need_rescan() {
+                        let _ = tx.send(ScanEvent::Warning {
+                            path: String::new(),
+                            code: "watch_overflow".into(),
+                            message: "filesystem notifications were lost; run a new scan for exact totals".into(),
+                        });
+                    }
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        pending.extend(event.paths);
+                    }
+                }
+                Some(Err(e)) => tracing::warn!("Watch error for scan {}: {}", id, e),
+                None => break,
+            },
+            _ = ticker.tick() => {
+                // Purging the scan stops its watcher
+                let exists = sqlx::query("SELECT 1 FROM scans WHERE id=?1")
+                    .bind(id.to_string())
+                    .fetch_optional(&pool)
+                    .await?
+                    .is_some();
+                if !exists {
+                    tracing::info!("Scan {} was deleted; stopping its watcher", id);
+                    break;
+                }
+                if pending.is_empty() {
+                    continue;
+                }
+                let paths = std::mem::take(&mut pending);
+                match apply_changes(&ctx, paths).await {
+                    Ok(0) => {}
+                    Ok(changed) => {
+                        if let Some(ev) = updated_event(&pool, id, changed).await? {
+                            let _ = tx.send(ev);
+                        }
+                    }
+                    Err(e) if cancel.is_cancelled() => tracing::debug!("Watch batch for scan {} interrupted: {}", id, e),
+                    Err(e) => {
+                        tracing::error!("Failed to apply watched changes for scan {}: {:?}", id, e);
+                        let _ = tx.send(ScanEvent::Warning {
+                            path: String::new(),
+                            code: "watch_apply_failed".into(),
+                            message: format!("failed to apply changes: {}", e),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    drop(watcher);
+    Ok(())
+}
+
+/// Everything needed to apply a batch of changed paths.
+struct ApplyCtx {
+    pool: sqlx::SqlitePool,
+    id: Uuid,
+    roots: Vec<PathBuf>,
+    options: ScanOptions,
+    globset: GlobSet,
+    includes: GlobSet,
+    tx: broadcast::Sender<ScanEvent>,
+    cancel: CancellationToken,
+    batch_size: usize,
+    max_entries_per_dir: Option<u64>,
+    persist_retry: PersistRetry,
+    categories: Option<Arc<CategoryMap>>,
+}
+
+async fn updated_event(pool: &sqlx::SqlitePool, id: Uuid, changed: u64) -> anyhow::Result<Option<ScanEvent>> {
+    let row = sqlx::query(
+        r#"SELECT COALESCE(total_logical_size,0) AS logical, COALESCE(total_allocated_size,0) AS allocated,
+                  COALESCE(dir_count,0) AS dirs, COALESCE(file_count,0) AS files
+           FROM scans WHERE id=?1"#,
+    )
+    .bind(id.to_string())
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| ScanEvent::Updated {
+        changed_paths: changed,
+        total_dirs: r.get::<i64, _>("dirs").max(0) as u64,
+        total_files: r.get::<i64, _>("files").max(0) as u64,
+        total_logical_size: r.get::<i64, _>("logical").max(0) as u64,
+        total_allocated_size: r.get::<i64, _>("allocated").max(0) as u64,
+    }))
+}
+
+/// Applies a batch of changed paths and returns how many of them changed the stored rows.
+async fn apply_changes(ctx: &ApplyCtx, paths: BTreeSet<PathBuf>) -> anyhow::Result<u64> {
+    let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+    // Parents first, so new directories are stored before their entries are looked at
+    paths.sort_by_key(|p| p.components().count());
+    let mut changed = 0;
+    for path in paths {
+        // Roots themselves are never added or removed by the watcher
+        let Some(root) = ctx.roots.iter().find(|r| path.starts_with(r) && path != **r) else { continue };
+        if apply_path(ctx, root, &path).await? {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Returns whether an existing entry would have been recorded by the scanner.
+fn is_included(
+    path: &Path,
+    md: &std::fs::Metadata,
+    options: &ScanOptions,
+    globset: &GlobSet,
+    includes: &GlobSet,
+) -> bool {
+    if matches_excludes(path, globset) {
+        return false;
+    }
+    if !options.include_hidden && is_hidden_or_system(path, md) {
+        return false;
+    }
+    if md.is_dir() {
+        return options.follow_symlinks || !is_reparse_point(md);
+    }
+    md.is_file() && matches_includes(path, includes)
+}
+
+async fn node_exists(pool: &sqlx::SqlitePool, id: Uuid, path: &str) -> anyhow::Result<bool> {
+    Ok(sqlx::query("SELECT 1 FROM nodes WHERE scan_id=?1 AND path=?2 AND is_dir=1")
+        .bind(id.to_string())
+        .bind(path)
+        .fetch_optional(pool)
+        .await?
+        .is_some())
+}
+
+/// Brings the rows of one path in line with the filesystem.
+async fn apply_path(ctx: &ApplyCtx, root: &Path, path: &Path) -> anyhow::Result<bool> {
+    let path_str = path.to_string_lossy().to_string();
+    let meta = match tokio::fs::metadata(path).await {
+        Ok(m) => Some(m),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::debug!("Skipping watched path {:?}: {}", path, e);
+            return Ok(false);
+        }
+    };
+    let Some(meta) = meta.filter(|m| is_included(path, m, &ctx.options, &ctx.globset, &ctx.includes)) else {
+        return Ok(remove_path_from_scan(&ctx.pool, ctx.id, &path_str).await?);
+    };
+
+    // Entries below excluded or unscanned directories stay out of the scan
+    let parent_known = match path.parent() {
+        Some(parent) => node_exists(&ctx.pool, ctx.id, &parent.to_string_lossy()).await?,
+        None => false,
+    };
+    if !parent_known {
+        return Ok(false);
+    }
+
+    if meta.is_file() {
+        if node_exists(&ctx.pool, ctx.id, &path_str).await? {
+            // A directory was replaced by a file of the same name
+            remove_path_from_scan(&ctx.pool, ctx.id, &path_str).await?;
+        }
+        return upsert_file(ctx, path, &path_str, &meta).await;
+    }
+
+    // Directories that are already stored get their contents reported by their own events
+    if node_exists(&ctx.pool, ctx.id, &path_str).await? {
+        return Ok(false);
+    }
+    let depth = path.components().count().saturating_sub(root.components().count());
+    if ctx.options.max_depth.is_some_and(|max| depth > max as usize) {
+        return Ok(false);
+    }
+    // A file may have been replaced by a directory of the same name
+    remove_path_from_scan(&ctx.pool, ctx.id, &path_str).await?;
+    add_directory(ctx, path, depth as u32).await?;
+    Ok(true)
+}
+
+/// Inserts or updates the row of a file and propagates the size change.
+async fn upsert_file(
+    ctx: &ApplyCtx,
+    path: &Path,
+    path_str: &str,
+    meta: &std::fs::Metadata,
+) -> anyhow::Result<bool> {
+    let (sizes, owner) = if ctx.options.measure_allocated || ctx.options.measure_ads || ctx.options.capture_owner {
+        let (options, p, md) = (ctx.options.clone(), path.to_path_buf(), meta.clone());
+        task::spawn_blocking(move || {
+            let owner = options
+                .capture_owner
+                .then(|| owner::file_owner(&p, &md).unwrap_or_else(|| owner::UNKNOWN_OWNER.to_string()));
+            (measure_file(&options, &p, &md), owner)
+        })
+        .await?
+    } else {
+        (measure_file(&ctx.options, path, meta), None)
+    };
+    let logical = sizes.logical.min(i64::MAX as u64) as i64;
+    let allocated = sizes.allocated.min(i64::MAX as u64) as i64;
+    let ads_count = sizes.ads.map(|(count, _)| count.min(i64::MAX as u64) as i64);
+    let ads_size = sizes.ads.map(|(_, bytes)| bytes.min(i64::MAX as u64) as i64);
+    // Alternate data streams do not make a file non-empty
+    let is_empty = meta.len() == 0;
+    let mtime = system_time_to_secs(meta.modified().ok());
+    let atime = system_time_to_secs(meta.accessed().ok());
+    let id = ctx.id.to_string();
+
+    let mut txdb = ctx.pool.begin().await?;
+    let old = sqlx::query(
+        "SELECT logical_size, allocated_size, mtime, ads_size, owner FROM files WHERE scan_id=?1 AND path=?2",
+    )
+    .bind(&id)
+    .bind(path_str)
+    .fetch_optional(&mut *txdb)
+    .await?;
+    let (d_logical, d_allocated, d_files, d_empty) = match old {
+        Some(r) => {
+            let (old_logical, old_allocated): (i64, i64) = (r.get("logical_size"), r.get("allocated_size"));
+            if old_logical == logical
+                && old_allocated == allocated
+                && r.get::<Option<i64>, _>("mtime") == mtime
+                && r.get::<Option<i64>, _>("ads_size") == ads_size
+                && r.get::<Option<String>, _>("owner") == owner
+            {
+                return Ok(false);
+            }
+            let was_empty = old_logical - r.get::<Option<i64>, _>("ads_size").unwrap_or(0) == 0;
+            sqlx::query(
+                // The stored hash no longer describes the content, and watching does not hash
+                r#"UPDATE files SET logical_size=?1, allocated_size=?2, mtime=?3, atime=?4,
+                                    ads_count=?5, ads_size=?6, owner=?7, hash=NULL
+                   WHERE scan_id=?8 AND path=?9"#,
+            )
+            .bind(logical)
+            .bind(allocated)
+            .bind(mtime)
+            .bind(atime)
+            .bind(ads_count)
+            .bind(ads_size)
+            .bind(owner.as_deref())
+            .bind(&id)
+            .bind(path_str)
+            .execute(&mut *txdb)
+            .await?;
+            let d_empty = is_empty as i64 - was_empty as i64;
+            (logical - old_logical, allocated - old_allocated, 0, d_empty)
+        }
+        None => {
+            sqlx::query(
+                r#"INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size, mtime, atime,
+                                     ads_count, ads_size, owner, category, path_raw)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
+            )
+            .bind(&id)
+            .bind(path_str)
+            .bind(path.parent().map(|p| p.to_string_lossy().to_string()))
+            .bind(logical)
+            .bind(allocated)
+            .bind(mtime)
+            .bind(atime)
+            .bind(ads_count)
+            .bind(ads_size)
+            .bind(owner.as_deref())
+            .bind(ctx.categories.as_deref().map(|c| c.classify(path)).as_deref())
+            .bind(raw_path_bytes(path))
+            .execute(&mut *txdb)
+            .await?;
+            (logical, allocated, 1, is_empty as i64)
+        }
+    };
+    if let (Some(parent), true) = (path.parent(), d_empty != 0) {
+        add_empty_files(&mut txdb, &id, &parent.to_string_lossy(), d_empty).await?;
+    }
+    if let (Some(parent), true) = (path.parent(), owner.is_some()) {
+        owner::refresh_usage(&mut txdb, &id, UsageScope::Dir(&parent.to_string_lossy())).await?;
+    }
+    // Directory aggregates only include logical sizes if the scan measured them
+    let d_logical = if ctx.options.measure_logical { d_logical } else { 0 };
+    add_to_ancestors(&mut txdb, &id, path, (d_logical, d_allocated, d_files, 0)).await?;
+    txdb.commit().await?;
+    Ok(true)
+}
+
+/// Scans a new directory and adds its subtree to the scan.
+async fn add_directory(ctx: &ApplyCtx, path: &Path, depth: u32) -> anyhow::Result<()> {
+    let (p, options, globset, includes, tx, cancel) = (
+        path.to_path_buf(),
+        ctx.options.clone(),
+        ctx.globset.clone(),
+        ctx.includes.clone(),
+        ctx.tx.clone(),
+        ctx.cancel.clone(),
+    );
+    let (max_entries, categories) = (ctx.max_entries_per_dir, ctx.categories.clone());
+    let (mut nodes, mut files, totals) = task::spawn_blocking(move || {
+        let _priority = super::priority::enter(options.io_priority);
+        scan_subtree(&p, depth, &options, &globset, &includes, &tx, &cancel, max_entries, categories)
+    })
+    .await??;
+
+    persist_batches(&ctx.pool, ctx.id, &mut nodes, &mut files, ctx.batch_size, &ctx.persist_retry, &ctx.tx)
+        .await?;
+    let Subtree { dirs, files: file_count, logical, allocated } = totals;
+    let clamp = |v: u64| v.min(i64::MAX as u64) as i64;
+    let id = ctx.id.to_string();
+    let mut txdb = ctx.pool.begin().await?;
+    add_to_ancestors(
+        &mut txdb,
+        &id,
+        path,
+        (clamp(logical), clamp(allocated), clamp(file_count), clamp(dirs)),
+    )
+    .await?;
+    if ctx.options.capture_owner {
+        owner::refresh_usage(&mut txdb, &id, UsageScope::Subtree(&path.to_string_lossy())).await?;
+    }
+    txdb.commit().await?;
+    Ok(())
+}
+
+/// Adds `(logical, allocated, files, dirs)` to every stored ancestor of `path` and to the scan totals.
+async fn add_to_ancestors(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+    path: &Path,
+    delta: (i64, i64, i64, i64),
+) -> anyhow::Result<()> {
+    // The children of the parent changed, so its stored fingerprint no longer describes them
+    if let Some(parent) = path.parent() {
+        clear_fingerprint(&mut *conn, id, &parent.to_string_lossy()).await?;
+    }
+    for ancestor in path.ancestors().skip(1) {
+        sqlx::query(
+            r#"UPDATE nodes SET logical_size = MAX(logical_size + ?1, 0), allocated_size = MAX(allocated_size + ?2, 0),
+                   file_count = MAX(file_count + ?3, 0), dir_count = MAX(dir_count + ?4, 0)
+               WHERE scan_id=?5 AND path=?6 AND is_dir=1"#,
+        )
+        .bind(delta.0)
+        .bind(delta.1)
+        .bind(delta.2)
+        .bind(delta.3)
+        .bind(id)
+        .bind(ancestor.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await?;
+    }
+    sqlx::query(
+        r#"UPDATE scans SET total_logical_size = MAX(COALESCE(total_logical_size, 0) + ?1, 0),
+               total_allocated_size = MAX(COALESCE(total_allocated_size, 0) + ?2, 0),
+               file_count = MAX(COALESCE(file_count, 0) + ?3, 0), dir_count = MAX(COALESCE(dir_count, 0) + ?4, 0)
+           WHERE id=?5"#,
+    )
+    .bind(delta.0)
+    .bind(delta.1)
+    .bind(delta.2)
+    .bind(delta.3)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Resets the fingerprint of a stored directory to unknown.
+pub(crate) async fn clear_fingerprint(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+    path: &str,
+) -> sqlx::Result<()> {
+    sqlx::query("UPDATE nodes SET fingerprint=NULL WHERE scan_id=?1 AND path=?2 AND is_dir=1")
+        .bind(id)
+        .bind(path)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Adds `delta` to the zero-byte file count of a stored directory.
+///
+/// Directories from scans that did not count empty files keep `NULL`.
+pub(crate) async fn add_empty_files(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+    path: &str,
+    delta: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"UPDATE nodes SET empty_file_count = MAX(empty_file_count + ?1, 0)
+           WHERE scan_id=?2 AND path=?3 AND is_dir=1"#,
+    )
+    .bind(delta)
+    .bind(id)
+    .bind(path)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_scan, test_db_in};
+
+    async fn totals(pool: &sqlx::SqlitePool, id: Uuid) -> (i64, i64) {
+        let r = sqlx::query("SELECT file_count, total_logical_size FROM scans WHERE id=?1")
+            .bind(id.to_string())
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (r.get("file_count"), r.get("total_logical_size"))
+    }
+
+    async fn node_size(pool: &sqlx::SqlitePool, id: Uuid, path: &Path) -> Option<(i64, i64)> {
+        sqlx::query("SELECT logical_size, file_count FROM nodes WHERE scan_id=?1 AND path=?2 AND is_dir=1")
+            .bind(id.to_string())
+            .bind(path.to_string_lossy().to_string())
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .map(|r| (r.get("logical_size"), r.get("file_count")))
+    }
+
+    #[tokio::test]
+    async fn watcher_applies_changes_and_stops_when_scan_is_purged() {
+        let dir = tempfile::tempdir().unwrap();
+        // The database lives outside the watched root
+        let root = dir.path().join("data");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        std::fs::write(root.join("a").join("one.txt"), vec![1u8; 100]).unwrap();
+        std::fs::write(root.join("b").join("two.txt"), vec![2u8; 200]).unwrap();
+
+        let pool = test_db_in(dir.path()).await;
+        let id = insert_scan(&pool, "running", &[], "default").await;
+        let options = ScanOptions { measure_allocated: false, ..Default::default() };
+        let (tx, _rx) = broadcast::channel(256);
+        let roots = vec![root.to_string_lossy().to_string()];
+        let summary = crate::scanner::run_scan(
+            pool.clone(),
+            id,
+            roots.clone(),
+            options.clone(),
+            tx.clone(),
+            CancellationToken::new(),
+            Default::default(),
+            500,
+            1_000,
+            64 << 20,
+            50,
+            None,
+            Some(2),
+            None,
+            Default::default(),
+            crate::scanner::Reuse::Nothing,
+            &crate::metrics::Metrics::default(),
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE scans SET status='done', file_count=?1, total_logical_size=?2 WHERE id=?3")
+            .bind(summary.total_files as i64)
+            .bind(summary.total_logical_size as i64)
+            .bind(id.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(totals(&pool, id).await, (2, 300));
+        let mut rx = tx.subscribe();
+
+        let categories = CategoriesConfig::default();
+        let watcher = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                watch_scan(
+                    pool,
+                    id,
+                    roots,
+                    options,
+                    tx,
+                    CancellationToken::new(),
+                    500,
+                    Duration::from_millis(50),
+                    None,
+                    Default::default(),
+                    &categories,
+                )
+                .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        std::fs::write(root.join("a").join("one.txt"), vec![1u8; 150]).unwrap();
+        std::fs::write(root.join("a").join("new.txt"), vec![3u8; 50]).unwrap();
+        std::fs::remove_file(root.join("b").join("two.txt")).unwrap();
+        std::fs::create_dir_all(root.join("c").join("deep")).unwrap();
+        std::fs::write(root.join("c").join("deep").join("three.txt"), vec![4u8; 70]).unwrap();
+
+        for _ in 0..200 {
+            if totals(&pool, id).await == (3, 270) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        assert_eq!(totals(&pool, id).await, (3, 270));
+        assert_eq!(node_size(&pool, id, &root).await, Some((270, 3)));
+        assert_eq!(node_size(&pool, id, &root.join("a")).await, Some((200, 2)));
+        assert_eq!(node_size(&pool, id, &root.join("b")).await, Some((0, 0)));
+        assert_eq!(node_size(&pool, id, &root.join("c")).await, Some((70, 1)));
+        assert_eq!(node_size(&pool, id, &root.join("c").join("deep")).await, Some((70, 1)));
+
+        let mut updated = false;
+        while let Ok(ev) = rx.try_recv() {
+            updated |= matches!(ev, ScanEvent::Updated { .. });
+        }
+        assert!(updated);
+
+        sqlx::query("DELETE FROM scans WHERE id=?1").bind(id.to_string()).execute(&pool).await.unwrap();
+        let stopped = tokio::time::timeout(Duration::from_secs(5), watcher).await;
+        assert!(stopped.expect("watcher did not stop after purge").unwrap().is_ok());
+    }
+}
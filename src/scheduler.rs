@@ -0,0 +1,319 @@
+//! Recurring scan scheduler.
+//!
+//! Schedules are stored in the `schedules` table, so they survive server
+//! restarts. A background task (spawned in `main.rs`) periodically calls
+//! [`run_due_schedules`], which starts every due schedule through the same
+//! code path as `POST /scans`. A schedule never fires while the scan it
+//! started last is still running; that occurrence is skipped instead.
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{middleware::namespace::Namespace, routes::scans::start_scan, state::AppState, types::CreateScanRequest};
+
+/// The largest accepted interval for interval schedules (one year).
+pub const MAX_INTERVAL_MINUTES: u32 = 366 * 24 * 60;
+
+/// A parsed five-field cron expression (`minute hour day-of-month month day-of-week`).
+///
+/// Supports `*`, single values, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/10`, `0-30/5`). Day-of-week accepts 0-7 where both 0 and 7 mean Sunday.
+/// As in classic cron, when both day fields are restricted a day matches if
+/// either of them matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    dom_any: bool,
+    dow_any: bool,
+}
+
+impl CronExpr {
+    /// Parses a five-field cron expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The cron expression, e.g. `"0 3 * * 1-5"`.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<CronExpr>` - The parsed expression, or an error describing the invalid field.
+    pub fn parse(expr: &str) -> anyhow::Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            anyhow::bail!("cron expression must have 5 fields, got {}", fields.len());
+        }
+        let (minutes, _) = parse_field(fields[0], 0, 59, "minute")?;
+        let (hours, _) = parse_field(fields[1], 0, 23, "hour")?;
+        let (days_of_month, dom_any) = parse_field(fields[2], 1, 31, "day-of-month")?;
+        let (months, _) = parse_field(fields[3], 1, 12, "month")?;
+        let (mut days_of_week, dow_any) = parse_field(fields[4], 0, 7, "day-of-week")?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+        Ok(Self { minutes, hours, days_of_month, months, days_of_week, dom_any, dow_any })
+    }
+
+    /// Returns the first matching minute strictly after `after`.
+    ///
+    /// Returns `None` if the expression does not match within the next five
+    /// years (e.g. `0 0 31 2 *`).
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = after + Duration::days(5 * 366);
+        while t <= limit {
+            if !has_bit(self.months, t.month()) {
+                let (y, m) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = Utc.with_ymd_and_hms(y, m, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.day_matches(&t) {
+                t = (t + Duration::days(1)).with_hour(0)?.with_minute(0)?;
+                continue;
+            }
+            if !has_bit(self.hours, t.hour()) {
+                t = (t + Duration::hours(1)).with_minute(0)?;
+                continue;
+            }
+            if !has_bit(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+        None
+    }
+
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        let dom = has_bit(self.days_of_month, t.day());
+        let dow = has_bit(self.days_of_week, t.weekday().num_days_from_sunday());
+        match (self.dom_any, self.dow_any) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+}
+
+fn has_bit(mask: u64, v: u32) -> bool {
+    v < 64 && mask & (1u64 << v) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> anyhow::Result<(u64, bool)> {
+    let parse_num = |s: &str| -> anyhow::Result<u32> {
+        s.parse::<u32>().map_err(|_| anyhow::anyhow!("invalid {} value '{}'", name, s))
+    };
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, parse_num(s)?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            anyhow::bail!("{} step must be > 0", name);
+        }
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_num(a)?, parse_num(b)?)
+        } else {
+            let v = parse_num(range)?;
+            // "5/15" means "starting at 5, every 15"
+            if part.contains('/') {
+                (v, max)
+            } else {
+                (v, v)
+            }
+        };
+        if lo < min || hi > max || lo > hi {
+            anyhow::bail!("{} value out of range {}-{}: '{}'", name, min, max, part);
+        }
+        let mut v = lo;
+        while v <= hi {
+            mask |= 1u64 << v;
+            v += step;
+        }
+    }
+    Ok((mask, field.starts_with('*')))
+}
+
+/// Computes the next run time of a schedule after `after`.
+///
+/// Exactly one of `interval_minutes` and `cron` is expected to be set.
+///
+/// # Arguments
+///
+/// * `interval_minutes` - The interval in minutes for interval schedules.
+/// * `cron` - The cron expression for cron schedules.
+/// * `after` - The reference time.
+///
+/// # Returns
+///
+/// * `anyhow::Result<DateTime<Utc>>` - The next due time.
+pub fn next_run_after(
+    interval_minutes: Option<u32>,
+    cron: Option<&str>,
+    after: DateTime<Utc>,
+) -> anyhow::Result<DateTime<Utc>> {
+    match (interval_minutes, cron) {
+        (Some(m), None) => {
+            if m == 0 || m > MAX_INTERVAL_MINUTES {
+                anyhow::bail!("interval_minutes must be in 1..={}", MAX_INTERVAL_MINUTES);
+            }
+            Ok(after + Duration::minutes(m as i64))
+        }
+        (None, Some(expr)) => CronExpr::parse(expr)?
+            .next_after(after)
+            .ok_or_else(|| anyhow::anyhow!("cron expression '{}' never fires", expr)),
+        _ => anyhow::bail!("exactly one of interval_minutes or cron must be set"),
+    }
+}
+
+/// Formats a timestamp the same way SQLite's `strftime('%Y-%m-%dT%H:%M:%SZ','now')` does.
+///
+/// Keeping the format identical lets due schedules be selected with a plain
+/// string comparison.
+pub fn format_timestamp(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Starts all schedules that are due.
+///
+/// For every schedule whose `next_run_at` has passed, this either starts a new
+/// scan or, if the scan it started last is still running, skips the
+/// occurrence. In both cases `next_run_at` is advanced.
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// * `anyhow::Result<()>` - An error only if the due schedules could not be loaded.
+pub async fn run_due_schedules(state: &AppState) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let now_str = format_timestamp(now);
+    let rows = sqlx::query(
+        r#"SELECT id, request, interval_minutes, cron, last_scan_id, namespace
+           FROM schedules WHERE next_run_at <= ?1 ORDER BY next_run_at ASC"#,
+    )
+    .bind(&now_str)
+    .fetch_all(&state.db)
+    .await?;
+
+    for r in rows {
+        let id: String = r.get("id");
+        let interval_minutes = r.get::<Option<i64>, _>("interval_minutes").and_then(|m| u32::try_from(m).ok());
+        let cron = r.get::<Option<String>, _>("cron");
+        let next = match next_run_after(interval_minutes, cron.as_deref(), now) {
+            Ok(n) => format_timestamp(n),
+            Err(e) => {
+                tracing::warn!("Schedule {} has an invalid specification: {}", id, e);
+                continue;
+            }
+        };
+
+        let last_scan_id = r.get::<Option<String>, _>("last_scan_id").and_then(|s| Uuid::parse_str(&s).ok());
+        let still_running = match last_scan_id {
+            Some(sid) => state.jobs.read().await.contains_key(&sid),
+            None => false,
+        };
+        if still_running {
+            tracing::info!("Schedule {} skipped: previous scan is still running", id);
+            if let Err(e) = sqlx::query("UPDATE schedules SET next_run_at=?1 WHERE id=?2")
+                .bind(&next)
+                .bind(&id)
+                .execute(&state.db)
+                .await
+            {
+                tracing::error!("Failed to advance schedule {}: {}", id, e);
+            }
+            continue;
+        }
+
+        let started = match serde_json::from_str::<CreateScanRequest>(&r.get::<String, _>("request")) {
+            Ok(req) => {
+                let ns = Namespace::parse(&r.get::<String, _>("namespace")).unwrap_or_default();
+                start_scan(state, req, &ns).await.map_err(|e| e.to_string())
+            }
+            Err(e) => Err(format!("stored request is invalid: {}", e)),
+        };
+        let res = match started {
+            Ok(resp) => {
+                tracing::info!("Schedule {} started scan {}", id, resp.id);
+                sqlx::query("UPDATE schedules SET last_run_at=?1, next_run_at=?2, last_scan_id=?3 WHERE id=?4")
+                    .bind(&now_str)
+                    .bind(&next)
+                    .bind(resp.id.to_string())
+                    .bind(&id)
+                    .execute(&state.db)
+                    .await
+            }
+            Err(e) => {
+                tracing::warn!("Schedule {} failed to start scan: {}", id, e);
+                sqlx::query("UPDATE schedules SET next_run_at=?1 WHERE id=?2")
+                    .bind(&next)
+                    .bind(&id)
+                    .execute(&state.db)
+                    .await
+            }
+        };
+        if let Err(e) = res {
+            tracing::error!("Failed to update schedule {}: {}", id, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).single().unwrap()
+    }
+
+    #[test]
+    fn test_cron_parse_rejects_invalid() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("5-1 * * * *").is_err());
+        assert!(CronExpr::parse("a * * * *").is_err());
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let every_15 = CronExpr::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_15.next_after(at(2024, 1, 1, 10, 7)), Some(at(2024, 1, 1, 10, 15)));
+        assert_eq!(every_15.next_after(at(2024, 1, 1, 10, 45)), Some(at(2024, 1, 1, 11, 0)));
+
+        let nightly = CronExpr::parse("30 2 * * *").unwrap();
+        assert_eq!(nightly.next_after(at(2024, 12, 31, 3, 0)), Some(at(2025, 1, 1, 2, 30)));
+
+        // 2024-01-06 is a Saturday; next weekday run is Monday 2024-01-08
+        let weekdays = CronExpr::parse("0 3 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(at(2024, 1, 6, 0, 0)), Some(at(2024, 1, 8, 3, 0)));
+
+        // 7 is Sunday
+        let sundays = CronExpr::parse("0 0 * * 7").unwrap();
+        assert_eq!(sundays.next_after(at(2024, 1, 1, 0, 0)), Some(at(2024, 1, 7, 0, 0)));
+
+        assert_eq!(CronExpr::parse("0 0 31 2 *").unwrap().next_after(at(2024, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_next_run_after_requires_exactly_one_spec() {
+        let now = at(2024, 1, 1, 0, 0);
+        assert_eq!(next_run_after(Some(90), None, now).unwrap(), at(2024, 1, 1, 1, 30));
+        assert!(next_run_after(Some(0), None, now).is_err());
+        assert!(next_run_after(None, None, now).is_err());
+        assert!(next_run_after(Some(5), Some("* * * * *"), now).is_err());
+    }
+}
@@ -1,14 +1,372 @@
 #![allow(dead_code)]
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use tokio::sync::{broadcast, RwLock};
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, SharedConfig};
 use crate::metrics::Metrics;
 use crate::middleware::EndpointRateLimiter;
-use crate::types::ScanEvent;
+use crate::scanner::pause::PauseGate;
+use crate::types::{ExpectedTotals, ScanEvent};
+
+/// The number of recent events a job keeps for replay.
+pub const EVENT_REPLAY_CAPACITY: usize = 500;
+/// The capacity of the channel carrying numbered events to live subscribers.
+const NUMBERED_CHANNEL_SIZE: usize = 4096;
+
+/// The recent events of a job, numbered so reconnecting clients can resume.
+///
+/// Events are recorded from the job's broadcast channel by a background task.
+/// Each gets an id one higher than the last; the newest `capacity` events are
+/// kept and replayed to new subscribers before they receive live events.
+///
+/// With [`EventLog::expect`], `progress` events get an estimated percentage
+/// of the expected totals. Workers report their own counts between the
+/// aggregator's totals, so the estimate only ever grows.
+pub struct EventLog {
+    inner: Mutex<EventLogInner>,
+}
+
+struct EventLogInner {
+    next_id: u64,
+    capacity: usize,
+    events: VecDeque<(u64, ScanEvent)>,
+    /// `None` once the job's channel closed; subscribers then only get the backlog.
+    live: Option<broadcast::Sender<(u64, ScanEvent)>>,
+    expected: Option<ExpectedTotals>,
+    /// The highest estimate sent so far.
+    percent: u8,
+}
+
+/// The kept events a subscriber starts with and the receiver for the ones that follow.
+pub type Subscription = (Vec<(u64, ScanEvent)>, broadcast::Receiver<(u64, ScanEvent)>);
+
+impl EventLog {
+    /// Creates an empty log keeping at most `capacity` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of events to keep.
+    pub fn new(capacity: usize) -> Self {
+        let (live, _) = broadcast::channel(NUMBERED_CHANNEL_SIZE);
+        Self {
+            inner: Mutex::new(EventLogInner {
+                next_id: 1,
+                capacity: capacity.max(1),
+                events: VecDeque::new(),
+                live: Some(live),
+                expected: None,
+                percent: 0,
+            }),
+        }
+    }
+
+    /// Numbers an event, keeps it and forwards it to live subscribers.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event to record.
+    ///
+    /// # Returns
+    ///
+    /// The id given to the event.
+    pub fn record(&self, mut event: ScanEvent) -> u64 {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let (ScanEvent::Progress { dirs_scanned, files_scanned, estimated_percent, .. }, Some(expected)) =
+            (&mut event, inner.expected)
+        {
+            let total = expected.dirs.saturating_add(expected.files).max(1) as u128;
+            let done = dirs_scanned.saturating_add(*files_scanned) as u128;
+            inner.percent = inner.percent.max((done * 100 / total).min(99) as u8);
+            *estimated_percent = Some(inner.percent);
+        }
+        let id = inner.next_id;
+        inner.next_id += 1;
+        if inner.events.len() >= inner.capacity {
+            inner.events.pop_front();
+        }
+        inner.events.push_back((id, event.clone()));
+        if let Some(live) = &inner.live {
+            let _ = live.send((id, event));
+        }
+        id
+    }
+
+    /// Estimates the progress of the `progress` events recorded from now on against `totals`.
+    ///
+    /// # Arguments
+    ///
+    /// * `totals` - The totals of an earlier scan of the same roots.
+    pub fn expect(&self, totals: ExpectedTotals) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).expected = Some(totals);
+    }
+
+    /// Ends the live stream; subscribers still get the kept events.
+    pub fn close(&self) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).live = None;
+    }
+
+    /// Returns the kept events after `last_id` and a receiver for the ones that follow.
+    ///
+    /// Both are taken under one lock, so no event is missed or delivered twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `last_id` - The id of the last event the client has seen, if any.
+    ///
+    /// # Returns
+    ///
+    /// The backlog in order and a receiver that is closed once the job's channel closed.
+    pub fn subscribe(&self, last_id: Option<u64>) -> Subscription {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let after = last_id.unwrap_or(0);
+        let backlog = inner.events.iter().filter(|(id, _)| *id > after).cloned().collect();
+        let rx = match &inner.live {
+            Some(live) => live.subscribe(),
+            // Dropping the only sender leaves a receiver that ends right away
+            None => broadcast::channel(1).1,
+        };
+        (backlog, rx)
+    }
+
+    /// Records every event sent on a job's channel until the channel closes.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `rx` - A receiver of the job's channel.
+    pub fn spawn_recorder(self: &Arc<Self>, mut rx: broadcast::Receiver<ScanEvent>) {
+        let log = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        log.record(event);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Event log lagged by {} messages", n);
+                        let message = format!("Stream lagged, missed {} events", n);
+                        log.record(ScanEvent::Failed { message });
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            log.close();
+        });
+    }
+}
+
+/// Event logs of finished scans, kept for `sse.replay_grace_secs`.
+pub type FinishedEvents = Arc<RwLock<HashMap<Uuid, Arc<EventLog>>>>;
+
+/// Keeps the event log of a finished scan for `grace`, then drops it.
+///
+/// A later log stored for the same scan is left alone when the grace period ends.
+///
+/// # Arguments
+///
+/// * `finished` - The event logs of finished scans.
+/// * `id` - The ID of the scan.
+/// * `log` - The event log of the scan.
+/// * `grace` - How long to keep the log.
+pub async fn retain_finished_events(
+    finished: &FinishedEvents,
+    id: Uuid,
+    log: Arc<EventLog>,
+    grace: Duration,
+) {
+    if grace.is_zero() {
+        return;
+    }
+    finished.write().await.insert(id, log.clone());
+    let finished = finished.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(grace).await;
+        let mut map = finished.write().await;
+        if map.get(&id).is_some_and(|kept| Arc::ptr_eq(kept, &log)) {
+            map.remove(&id);
+        }
+    });
+}
+
+/// Counts the requests currently reading each scan, such as diffs and exports.
+///
+/// The retention task never prunes a scan that is leased.
+#[derive(Default)]
+pub struct ScanLeases {
+    counts: Mutex<HashMap<Uuid, usize>>,
+}
+
+impl ScanLeases {
+    /// Marks a scan as in use until the returned lease is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the scan.
+    pub fn acquire(self: &Arc<Self>, id: Uuid) -> ScanLease {
+        *self.counts.lock().unwrap_or_else(|e| e.into_inner()).entry(id).or_insert(0) += 1;
+        ScanLease { leases: self.clone(), id }
+    }
+
+    /// Returns whether a scan is currently in use.
+    pub fn is_leased(&self, id: Uuid) -> bool {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&id)
+    }
+}
+
+/// Keeps a scan from being pruned while it is alive.
+pub struct ScanLease {
+    leases: Arc<ScanLeases>,
+    id: Uuid,
+}
+
+impl Drop for ScanLease {
+    fn drop(&mut self) {
+        let mut counts = self.leases.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = counts.get_mut(&self.id) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// Counts the open event streams per client and per scan.
+///
+/// Every stream holds an [`SseConnection`] for as long as it is alive, so the
+/// counts also drop for clients that vanish without closing the connection.
+#[derive(Default)]
+pub struct SseConnections {
+    counts: Mutex<SseCounts>,
+}
+
+#[derive(Default)]
+struct SseCounts {
+    per_client: HashMap<IpAddr, usize>,
+    per_scan: HashMap<Uuid, usize>,
+}
+
+impl SseConnections {
+    /// Registers a stream of scan `id` for `client` unless the client already has `max` open.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The IP address of the client.
+    /// * `id` - The ID of the streamed scan.
+    /// * `max` - The maximum number of open streams per client; 0 for no limit.
+    ///
+    /// # Returns
+    ///
+    /// The registration, which ends when it is dropped, or `None` if the client is at its limit.
+    pub fn try_open(self: &Arc<Self>, client: IpAddr, id: Uuid, max: usize) -> Option<SseConnection> {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let open = counts.per_client.entry(client).or_insert(0);
+        if max > 0 && *open >= max {
+            return None;
+        }
+        *open += 1;
+        *counts.per_scan.entry(id).or_insert(0) += 1;
+        Some(SseConnection { connections: self.clone(), client, id })
+    }
+
+    /// Returns the number of open streams of a client.
+    pub fn of_client(&self, client: IpAddr) -> usize {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).per_client.get(&client).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of open streams per scan, ordered by scan ID.
+    pub fn per_scan(&self) -> Vec<(Uuid, usize)> {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut per_scan: Vec<(Uuid, usize)> = counts.per_scan.iter().map(|(id, n)| (*id, *n)).collect();
+        per_scan.sort();
+        per_scan
+    }
+}
+
+/// Keeps one event stream counted while it is alive.
+pub struct SseConnection {
+    connections: Arc<SseConnections>,
+    client: IpAddr,
+    id: Uuid,
+}
+
+impl Drop for SseConnection {
+    fn drop(&mut self) {
+        let mut counts = self.connections.counts.lock().unwrap_or_else(|e| e.into_inner());
+        release(&mut counts.per_client, &self.client);
+        release(&mut counts.per_scan, &self.id);
+    }
+}
+
+/// Takes one off the count of `key`, removing it once it reaches zero.
+fn release<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// The root paths of read-only scans, loaded from the database on first use.
+///
+/// Path operations refuse to modify anything below these roots. The cache is
+/// cleared whenever scans are created or deleted.
+#[derive(Default)]
+pub struct ReadOnlyRoots {
+    roots: RwLock<Option<Arc<Vec<String>>>>,
+    /// Raised by every invalidation, so a load racing with one is not kept.
+    generation: AtomicU64,
+}
+
+impl ReadOnlyRoots {
+    /// Drops the cached roots; the next lookup reads them again.
+    pub async fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.roots.write().await = None;
+    }
+
+    /// Returns the root paths of all read-only scans.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The pool to read the scans from on a cache miss.
+    pub async fn get(&self, pool: &sqlx::SqlitePool) -> sqlx::Result<Arc<Vec<String>>> {
+        if let Some(roots) = self.roots.read().await.as_ref() {
+            return Ok(roots.clone());
+        }
+        let generation = self.generation.load(Ordering::SeqCst);
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT id, root_paths FROM scans WHERE read_only = 1")
+            .fetch_all(pool)
+            .await?;
+        let mut roots = Vec::new();
+        for (id, json) in rows {
+            match serde_json::from_str::<Vec<String>>(&json) {
+                Ok(paths) => roots.extend(paths),
+                Err(e) => tracing::warn!("Unreadable root paths of read-only scan {}: {}", id, e),
+            }
+        }
+        let roots = Arc::new(roots);
+        let mut cached = self.roots.write().await;
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *cached = Some(roots.clone());
+        }
+        Ok(roots)
+    }
+}
 
 /// A handle to a running scan job.
 ///
@@ -26,6 +384,58 @@ pub struct JobHandle {
     /// Used to emit real-time updates about scan progress, warnings, and completion
     /// to connected clients via Server-Sent Events (SSE).
     pub sender: broadcast::Sender<ScanEvent>,
+    /// Whether a cancelled scan should keep its persisted records as a `partial` scan.
+    ///
+    /// Set by `DELETE /scans/{id}?finalize=true` before the cancel token fires.
+    pub finalize: Arc<AtomicBool>,
+    /// Cancelled by the job task once it has written its final status.
+    pub finished: CancellationToken,
+    /// The recent events of the job, replayed to clients that subscribe late.
+    pub events: Arc<EventLog>,
+    /// Holds the scanner's workers while the scan is paused.
+    pub pause: Arc<PauseGate>,
+    /// The totals of the last finished scan of the same roots, if any.
+    pub expected_totals: Option<ExpectedTotals>,
+}
+
+impl JobHandle {
+    /// Creates a handle for a job that has not been cancelled or finalized.
+    ///
+    /// Starts recording the job's events, so it must be called from within a
+    /// Tokio runtime and before the first event is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - The cancellation token of the job.
+    /// * `sender` - The broadcast sender for the job's events.
+    pub fn new(cancel: CancellationToken, sender: broadcast::Sender<ScanEvent>) -> Self {
+        let events = Arc::new(EventLog::new(EVENT_REPLAY_CAPACITY));
+        events.spawn_recorder(sender.subscribe());
+        Self {
+            cancel,
+            sender,
+            finalize: Arc::new(AtomicBool::new(false)),
+            finished: CancellationToken::new(),
+            events,
+            pause: Arc::new(PauseGate::default()),
+            expected_totals: None,
+        }
+    }
+
+    /// Sets the totals the progress of the job is estimated against.
+    ///
+    /// Must be called before the first event is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `totals` - The totals of an earlier scan of the same roots, if any.
+    pub fn with_expected_totals(mut self, totals: Option<ExpectedTotals>) -> Self {
+        if let Some(totals) = totals {
+            self.events.expect(totals);
+        }
+        self.expected_totals = totals;
+        self
+    }
 }
 
 /// The shared application state.
@@ -41,16 +451,50 @@ pub struct AppState {
     /// Provides connections to the SQLite database for storing scan results,
     /// job metadata, and other persistent data.
     pub db: sqlx::SqlitePool,
+    /// The read-only database connection pool.
+    ///
+    /// Used by query endpoints so reads never queue behind scan inserts on the
+    /// write pool. Defaults to the write pool until [`AppState::with_read_pool`]
+    /// is called.
+    pub read_db: sqlx::SqlitePool,
     /// A map of running scan jobs.
     ///
     /// Maps scan UUIDs to their corresponding job handles, allowing for
     /// job management, cancellation, and event broadcasting.
     pub jobs: Arc<RwLock<HashMap<Uuid, JobHandle>>>,
+    /// A map of scans kept up to date by a filesystem watcher.
+    ///
+    /// Watchers outlive the scan job and are not counted as running jobs; their
+    /// handles carry the event channel used by `GET /scans/{id}/events`.
+    pub watchers: Arc<RwLock<HashMap<Uuid, JobHandle>>>,
+    /// The event logs of recently finished scans.
+    ///
+    /// Lets `GET /scans/{id}/events` replay a scan's last events after its job
+    /// handle is gone, for `sse.replay_grace_secs`.
+    pub finished_events: FinishedEvents,
+    /// Background path operations (moves and archives), keyed by operation ID.
+    ///
+    /// Read by `GET /paths/operations`; finished entries are removed after a
+    /// retention period.
+    pub operations: crate::routes::paths_operations::Operations,
+    /// The scans currently read by diffs and exports.
+    ///
+    /// Checked by the retention task so it never prunes a scan in use.
+    pub scan_leases: Arc<ScanLeases>,
+    /// The root paths of read-only scans, checked by path operations.
+    pub read_only_roots: Arc<ReadOnlyRoots>,
+    /// The open event streams, limited per client by `sse.max_connections_per_client`.
+    ///
+    /// Reported by `/metrics` per scan and in total.
+    pub sse_connections: Arc<SseConnections>,
+    /// The import sessions a request is currently uploading to, so chunks of a session never interleave.
+    pub import_uploads: Arc<crate::import::ImportUploads>,
     /// The application configuration.
     ///
     /// Contains server settings, database configuration, scan defaults,
-    /// and other runtime parameters.
-    pub config: Arc<AppConfig>,
+    /// and other runtime parameters. Replaced as a whole by
+    /// [`AppState::reload_config`]; take one snapshot with `get()` per use.
+    pub config: SharedConfig,
     /// The application metrics.
     ///
     /// Tracks performance counters and statistics about scans, files processed,
@@ -61,6 +505,10 @@ pub struct AppState {
     /// Provides rate limiting functionality for different API endpoints
     /// to prevent abuse and ensure fair usage.
     pub rate_limiter: EndpointRateLimiter,
+    /// Location of the discovery file written for local tools, if any.
+    ///
+    /// Reported by `GET /healthz?verbose=1`.
+    pub discovery_path: Option<Arc<PathBuf>>,
 }
 
 impl AppState {
@@ -78,28 +526,82 @@ impl AppState {
     ///
     /// A new `AppState` instance with:
     /// - Database connection pool
-    /// - Empty job registry HashMap
+    /// - Empty job and watcher registries
     /// - Wrapped configuration in Arc
     /// - Fresh metrics instance
-    /// - Rate limiter with default endpoint limits:
-    ///   - 60 scans per minute
-    ///   - 600 searches per minute  
-    ///   - 120 drive lists per minute
-    ///   - 30 move operations per minute
+    /// - Rate limiter with the endpoint limits from `config.rate_limits`
     pub fn new(db: sqlx::SqlitePool, config: AppConfig) -> Self {
-        let rate_limiter = EndpointRateLimiter::new().with_limits(vec![
-            ("/scans", 60, 60),             // 60 scans per minute
-            ("/scans/:id/search", 600, 60), // 600 searches per minute
-            ("/drives", 120, 60),           // 120 drive lists per minute
-            ("/paths/move", 30, 60),        // 30 move operations per minute
-        ]);
+        let rate_limiter = EndpointRateLimiter::from_config(&config.rate_limits);
 
         Self {
+            read_db: db.clone(),
             db,
             jobs: Arc::new(RwLock::new(HashMap::new())),
-            config: Arc::new(config),
+            watchers: Arc::new(RwLock::new(HashMap::new())),
+            finished_events: Arc::new(RwLock::new(HashMap::new())),
+            operations: Arc::new(RwLock::new(HashMap::new())),
+            scan_leases: Arc::new(ScanLeases::default()),
+            read_only_roots: Arc::new(ReadOnlyRoots::default()),
+            sse_connections: Arc::new(SseConnections::default()),
+            import_uploads: Arc::new(crate::import::ImportUploads::default()),
+            config: SharedConfig::new(config),
             metrics: Metrics::new(),
             rate_limiter,
+            discovery_path: None,
         }
     }
+
+    /// Replaces the pool used for read-only queries.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_db` - A pool whose connections are opened with `PRAGMA query_only=ON`
+    ///
+    /// # Returns
+    ///
+    /// The updated `AppState`
+    pub fn with_read_pool(mut self, read_db: sqlx::SqlitePool) -> Self {
+        self.read_db = read_db;
+        self
+    }
+
+    /// Sets the discovery file location reported by the health endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The discovery file path, or `None` if discovery is unavailable
+    ///
+    /// # Returns
+    ///
+    /// The updated `AppState`
+    pub fn with_discovery_path(mut self, path: Option<PathBuf>) -> Self {
+        self.discovery_path = path.map(Arc::new);
+        self
+    }
+
+    /// Applies a newly loaded configuration without a restart.
+    ///
+    /// Scan defaults, rate limits, security headers and the other settings
+    /// read per request take effect for later requests; running scans keep the
+    /// options they were started with. Settings only read at startup keep
+    /// their current value.
+    ///
+    /// # Arguments
+    ///
+    /// * `new` - The newly loaded and validated configuration
+    ///
+    /// # Returns
+    ///
+    /// The names of the settings that changed but require a restart
+    pub async fn reload_config(&self, new: AppConfig) -> Vec<String> {
+        let (cfg, requires_restart) = crate::config::merge_reload(&self.config.get(), new);
+        self.rate_limiter.reload(&cfg.rate_limits).await;
+        self.config.replace(cfg);
+        requires_restart
+    }
+
+    /// Returns the pool that read-only handlers should query.
+    pub fn read_pool(&self) -> &sqlx::SqlitePool {
+        &self.read_db
+    }
 }
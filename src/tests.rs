@@ -27,6 +27,7 @@ mod tests {
             excludes: vec!["**/.git".to_string(), "**/node_modules".to_string()],
             max_depth: Some(5),
             concurrency: Some(8),
+            measure_hardlinks: false,
         };
         assert_eq!(options.follow_symlinks, true);
         assert_eq!(options.include_hidden, false);
@@ -194,7 +195,7 @@ mod tests {
             .execute(&state.db).await.unwrap();
 
             let q = routes::scans::TreeQuery { path: Some(root_s.clone()), depth: Some(1), sort: Some("size".into()), limit: Some(100) };
-            let res = routes::scans::get_tree(State(state.clone()), Path(id), Query(q)).await.unwrap();
+            let res = routes::scans::get_tree(State(state.clone()), crate::middleware::namespace::Namespace::default(), Path(id), Query(q)).await.unwrap();
             let resp = res.into_response();
             assert!(resp.status().is_success());
         }
@@ -231,7 +232,7 @@ mod tests {
             }
 
             let q = routes::scans::TopQuery { scope: Some("dirs".into()), limit: Some(10) };
-            let res = routes::scans::get_top(State(state.clone()), Path(id), Query(q)).await.unwrap();
+            let res = routes::scans::get_top(State(state.clone()), crate::middleware::namespace::Namespace::default(), Path(id), Query(q)).await.unwrap();
             let resp = res.into_response();
             assert!(resp.status().is_success());
         }
@@ -277,7 +278,7 @@ mod list_endpoint_tests {
         .execute(&state.db).await.unwrap();
 
         // Call handler directly
-        let res = routes::scans::get_list(State(state.clone()), Path(id), Query(routes::scans::ListQuery::default())).await.unwrap();
+        let res = routes::scans::get_list(State(state.clone()), crate::middleware::namespace::Namespace::default(), Path(id), Query(routes::scans::ListQuery::default())).await.unwrap();
         let resp = res.into_response();
         assert!(resp.status().is_success());
         let body = body::to_bytes(resp.into_body(), 2 * 1024 * 1024).await.unwrap();
@@ -335,7 +336,7 @@ mod list_endpoint_tests {
 
         // Call handler directly for children listing
         let q = routes::scans::ListQuery { path: Some(root_s.clone()), sort: None, order: None, limit: None, offset: None };
-        let res = routes::scans::get_list(State(state.clone()), Path(id), Query(q)).await.unwrap();
+        let res = routes::scans::get_list(State(state.clone()), crate::middleware::namespace::Namespace::default(), Path(id), Query(q)).await.unwrap();
         let resp = res.into_response();
         assert!(resp.status().is_success());
         let body = body::to_bytes(resp.into_body(), 2 * 1024 * 1024).await.unwrap();
@@ -362,6 +363,7 @@ mod list_endpoint_tests {
             files_scanned: 100,
             logical_size: 1024,
             allocated_size: 2048,
+            estimated_percent: None,
         };
         
         let json = serde_json::to_string(&event).unwrap();
@@ -510,7 +512,6 @@ mod list_endpoint_tests {
             AppError::NotFound("Scan not found".to_string()),
             AppError::InvalidInput("Invalid path".to_string()),
             AppError::Database("Connection failed".to_string()),
-            AppError::Scanner("Access denied".to_string()),
             AppError::Internal(anyhow::anyhow!("Unexpected error")),
         ];
         
@@ -531,6 +532,7 @@ mod list_endpoint_tests {
             excludes: Some(vec![]),
             max_depth: None,
             concurrency: None,
+            measure_hardlinks: None,
         };
         assert!(!valid_req.root_paths.is_empty());
         
@@ -543,6 +545,7 @@ mod list_endpoint_tests {
             excludes: None,
             max_depth: None,
             concurrency: None,
+            measure_hardlinks: None,
         };
         assert!(invalid_req.root_paths.is_empty());
     }
@@ -37,6 +37,7 @@ mod tests {
             },
             database: crate::config::DatabaseConfig {
                 url: db_url,
+                analyze_after_scan: false,
             },
             scan_defaults: crate::config::ScanDefaultsConfig {
                 follow_symlinks: false,
@@ -22,7 +22,7 @@ mod tests {
         env::set_var("SPEICHERWALD__SERVER__PORT", "0");
         let result = config::load();
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("invalid server.port"));
+        assert!(result.unwrap_err().to_string().contains("server.port must not be 0"));
         env::remove_var("SPEICHERWALD__SERVER__PORT");
     }
 
@@ -183,7 +183,7 @@ port = 7000
         env::set_var("SPEICHERWALD__SCANNER__BATCH_SIZE", "0");
         let result = config::load();
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("scanner.batch_size must be > 0"));
+        assert!(result.unwrap_err().to_string().contains("scanner.batch_size must be in 1..=10000"));
         env::remove_var("SPEICHERWALD__SCANNER__BATCH_SIZE");
     }
 
@@ -202,7 +202,7 @@ port = 7000
         env::set_var("SPEICHERWALD__SCANNER__FLUSH_THRESHOLD", "99");
         let result = config::load();
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("scanner.flush_threshold must be >= batch_size"));
+        assert!(result.unwrap_err().to_string().contains("scanner.flush_threshold (99) must be >= batch_size (100)"));
         env::remove_var("SPEICHERWALD__SCANNER__BATCH_SIZE");
         env::remove_var("SPEICHERWALD__SCANNER__FLUSH_THRESHOLD");
     }
@@ -221,7 +221,7 @@ port = 7000
         env::set_var("SPEICHERWALD__SCANNER__DIR_CONCURRENCY", "0");
         let result = config::load();
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("scanner.dir_concurrency must be in 1..=1000"));
+        assert!(result.unwrap_err().to_string().contains("scanner.dir_concurrency must be in 1..=256"));
         env::remove_var("SPEICHERWALD__SCANNER__DIR_CONCURRENCY");
     }
 
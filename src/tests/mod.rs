@@ -12,6 +12,7 @@
 //! - **db_tests**: Database operations and migration tests
 //! - **health_api_tests**: Health check endpoint tests
 //! - **drives_api_tests**: Drive management API tests
+//! - **support**: Fixtures shared by the unit tests, compiled as `crate::test_support`
 //!
 //! ## Running Tests
 //!
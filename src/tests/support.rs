@@ -0,0 +1,133 @@
+//! Fixtures shared by the unit tests.
+//!
+//! Compiled into test builds as `crate::test_support`, so every module's tests
+//! set up their database the same way.
+
+use std::path::Path;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+/// Creates a database with the current schema in a new temporary directory.
+///
+/// # Returns
+///
+/// * `(TempDir, SqlitePool)` - The directory, which must outlive the pool, and the pool.
+pub async fn test_db() -> (TempDir, SqlitePool) {
+    let dir = tempfile::tempdir().unwrap();
+    let pool = test_db_in(dir.path()).await;
+    (dir, pool)
+}
+
+/// Creates a database with the current schema as `test.db` in `dir`.
+///
+/// For tests that keep the files they scan in the same temporary directory.
+pub async fn test_db_in(dir: &Path) -> SqlitePool {
+    let url = format!("sqlite://{}?mode=rwc", dir.join("test.db").display());
+    let pool = crate::db::connect_write_pool(&url, 4).await.unwrap();
+    crate::db::init_db(&pool).await.unwrap();
+    pool
+}
+
+/// Inserts a scan with the stored options `{}`.
+///
+/// # Arguments
+///
+/// * `pool` - The database.
+/// * `status` - The status of the scan, e.g. `done` or `running`.
+/// * `roots` - The root paths of the scan.
+/// * `ns` - The namespace of the scan, `default` for most tests.
+///
+/// # Returns
+///
+/// * `Uuid` - The ID of the new scan.
+pub async fn insert_scan(pool: &SqlitePool, status: &str, roots: &[&str], ns: &str) -> Uuid {
+    insert_scan_row(pool, status, roots, ns, "{}".into()).await
+}
+
+/// Inserts a scan like [`insert_scan`] that stores `options`, a `ScanOptions` or a part of one as JSON.
+pub async fn insert_scan_with_options(
+    pool: &SqlitePool,
+    status: &str,
+    roots: &[&str],
+    ns: &str,
+    options: &impl Serialize,
+) -> Uuid {
+    insert_scan_row(pool, status, roots, ns, serde_json::to_string(options).unwrap()).await
+}
+
+async fn insert_scan_row(pool: &SqlitePool, status: &str, roots: &[&str], ns: &str, options: String) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO scans (id, status, root_paths, options, namespace) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .bind(id.to_string())
+        .bind(status)
+        .bind(serde_json::to_string(roots).unwrap())
+        .bind(options)
+        .bind(ns)
+        .execute(pool)
+        .await
+        .unwrap();
+    id
+}
+
+/// A directory row for [`insert_node`]; `..Default::default()` leaves out what a test doesn't look at.
+#[derive(Default)]
+pub struct NodeRow<'a> {
+    pub path: &'a str,
+    pub parent: Option<&'a str>,
+    pub depth: i64,
+    /// Both the logical and the allocated size.
+    pub size: i64,
+    pub files: i64,
+    pub dirs: i64,
+    pub mtime: Option<i64>,
+}
+
+/// Inserts a directory node into the scan `id`.
+pub async fn insert_node(pool: &SqlitePool, id: Uuid, node: NodeRow<'_>) {
+    sqlx::query(
+        "INSERT INTO nodes (scan_id, path, parent_path, depth, is_dir, logical_size, allocated_size, \
+         file_count, dir_count, mtime) VALUES (?1, ?2, ?3, ?4, 1, ?5, ?5, ?6, ?7, ?8)",
+    )
+    .bind(id.to_string())
+    .bind(node.path)
+    .bind(node.parent)
+    .bind(node.depth)
+    .bind(node.size)
+    .bind(node.files)
+    .bind(node.dirs)
+    .bind(node.mtime)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// A file row for [`insert_file`]; `..Default::default()` leaves out what a test doesn't look at.
+#[derive(Default)]
+pub struct FileRow<'a> {
+    pub path: &'a str,
+    pub parent: Option<&'a str>,
+    /// Both the logical and the allocated size.
+    pub size: i64,
+    pub mtime: Option<i64>,
+    pub atime: Option<i64>,
+}
+
+/// Inserts a file into the scan `id`.
+pub async fn insert_file(pool: &SqlitePool, id: Uuid, file: FileRow<'_>) {
+    sqlx::query(
+        "INSERT INTO files (scan_id, path, parent_path, logical_size, allocated_size, mtime, atime) \
+         VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)",
+    )
+    .bind(id.to_string())
+    .bind(file.path)
+    .bind(file.parent)
+    .bind(file.size)
+    .bind(file.mtime)
+    .bind(file.atime)
+    .execute(pool)
+    .await
+    .unwrap();
+}
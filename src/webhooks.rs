@@ -0,0 +1,326 @@
+//! Notifications about finished scans.
+//!
+//! The receivers live in the `[webhooks]` config section. When a scan ends,
+//! its background task calls [`notify_scan_finished`], which posts a
+//! [`ScanWebhookPayload`] to every receiver that wants the outcome. Deliveries
+//! run in their own tasks: a failed attempt is retried with exponential
+//! backoff and finally logged, and never changes the status of the scan.
+//!
+//! With a `secret`, the request carries the hex HMAC-SHA256 of its body in the
+//! `X-Speicherwald-Signature` header as `sha256=<hex>`.
+
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::config::{WebhookEndpointConfig, WebhooksConfig};
+
+/// The header with the signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-Speicherwald-Signature";
+/// The header with the event of the notification.
+pub const EVENT_HEADER: &str = "X-Speicherwald-Event";
+
+/// The body posted to the receivers when a scan ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanWebhookPayload {
+    /// The outcome the receivers filter on: `done`, `failed` or `canceled`.
+    pub event: String,
+    /// The ID of the scan.
+    pub scan_id: Uuid,
+    /// The stored status of the scan; a cancelled scan that kept its results is `partial`.
+    pub status: String,
+    /// The root paths of the scan.
+    pub root_paths: Vec<String>,
+    /// The number of directories.
+    pub total_dirs: i64,
+    /// The number of files.
+    pub total_files: i64,
+    /// The logical size of the files, in bytes.
+    pub total_logical_size: i64,
+    /// The allocated size of the files, in bytes.
+    pub total_allocated_size: i64,
+    /// The number of warnings.
+    pub warning_count: i64,
+    /// How long the scan ran, in milliseconds.
+    pub duration_ms: Option<i64>,
+    /// When the scan started.
+    pub started_at: Option<String>,
+    /// When the scan ended.
+    pub finished_at: Option<String>,
+    /// The error of a failed scan.
+    pub error: Option<String>,
+}
+
+/// Returns the event a scan with the stored `status` ends with, if it has ended.
+pub fn event_for_status(status: &str) -> Option<&'static str> {
+    match status {
+        "done" => Some("done"),
+        "failed" => Some("failed"),
+        "canceled" | "partial" => Some("canceled"),
+        _ => None,
+    }
+}
+
+/// Posts the outcome of a finished scan to the configured receivers.
+///
+/// Reads the final status and totals of the scan, so it must run after they
+/// were written. Returns once the deliveries are spawned.
+///
+/// # Arguments
+///
+/// * `pool` - The database pool.
+/// * `id` - The ID of the scan.
+/// * `error` - The error of a failed scan.
+/// * `config` - The webhook configuration.
+pub async fn notify_scan_finished(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    error: Option<String>,
+    config: &WebhooksConfig,
+) {
+    if config.endpoints.is_empty() {
+        return;
+    }
+    let payload = match load_payload(pool, id, error).await {
+        Ok(Some(payload)) => payload,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to load scan {} for its webhooks: {}", id, e);
+            return;
+        }
+    };
+    let body = match serde_json::to_vec(&payload) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to serialize the webhook payload of scan {}: {}", id, e);
+            return;
+        }
+    };
+    let client = match reqwest::Client::builder().timeout(Duration::from_secs(config.timeout_secs)).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create the webhook client: {}", e);
+            return;
+        }
+    };
+    for endpoint in config.endpoints.iter().filter(|e| e.wants(&payload.event)) {
+        tokio::spawn(deliver(
+            client.clone(),
+            endpoint.clone(),
+            payload.event.clone(),
+            body.clone(),
+            config.max_retries,
+            Duration::from_millis(config.retry_backoff_ms),
+        ));
+    }
+}
+
+/// Loads the payload of a scan; `None` if the scan is gone or has not ended.
+async fn load_payload(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    error: Option<String>,
+) -> anyhow::Result<Option<ScanWebhookPayload>> {
+    let Some(r) = sqlx::query(
+        r#"SELECT status, root_paths, started_at, finished_at, duration_ms,
+                   COALESCE(dir_count,0) AS dir_count, COALESCE(file_count,0) AS file_count,
+                   COALESCE(total_logical_size,0) AS total_logical_size,
+                   COALESCE(total_allocated_size,0) AS total_allocated_size,
+                   COALESCE(warning_count,0) AS warning_count
+            FROM scans WHERE id=?1"#,
+    )
+    .bind(id.to_string())
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let status: String = r.get("status");
+    let Some(event) = event_for_status(&status) else {
+        return Ok(None);
+    };
+    Ok(Some(ScanWebhookPayload {
+        event: event.to_string(),
+        scan_id: id,
+        root_paths: serde_json::from_str(&r.get::<String, _>("root_paths"))?,
+        total_dirs: r.get("dir_count"),
+        total_files: r.get("file_count"),
+        total_logical_size: r.get("total_logical_size"),
+        total_allocated_size: r.get("total_allocated_size"),
+        warning_count: r.get("warning_count"),
+        duration_ms: r.get("duration_ms"),
+        started_at: r.get("started_at"),
+        finished_at: r.get("finished_at"),
+        error: if status == "failed" { error } else { None },
+        status,
+    }))
+}
+
+/// Returns the `sha256=<hex>` signature of `body` with `secret`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key of any length");
+    mac.update(body);
+    let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Posts `body` to one receiver until it answers with a success status or the
+/// retries are used up.
+async fn deliver(
+    client: reqwest::Client,
+    endpoint: WebhookEndpointConfig,
+    event: String,
+    body: Vec<u8>,
+    max_retries: u32,
+    backoff: Duration,
+) {
+    let signature = endpoint.secret.as_deref().map(|secret| signature(secret, &body));
+    for attempt in 0..=max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(backoff.saturating_mul(1 << (attempt - 1))).await;
+        }
+        let mut req = client
+            .post(&endpoint.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &event)
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            req = req.header(SIGNATURE_HEADER, signature);
+        }
+        let reason = match req.send().await {
+            Ok(res) if res.status().is_success() => return,
+            Ok(res) => format!("status {}", res.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt < max_retries {
+            tracing::warn!("Webhook {} failed ({}), retrying", endpoint.url, reason);
+        } else {
+            tracing::error!("Webhook {} failed after {} attempts: {}", endpoint.url, attempt + 1, reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+
+    use super::*;
+    use crate::test_support::test_db;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+    /// A receiver that fails the first `failures` requests with 500.
+    async fn receiver(failures: usize) -> (String, Received) {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(move |State(received): State<Received>, headers: HeaderMap, body: Bytes| async move {
+                    let mut received = received.lock().unwrap();
+                    received.push((headers, body));
+                    if received.len() <= failures {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    } else {
+                        StatusCode::NO_CONTENT
+                    }
+                }),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    async fn wait_for(received: &Received, count: usize) {
+        for _ in 0..200 {
+            if received.lock().unwrap().len() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("expected {} webhook requests, got {}", count, received.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn finished_scans_are_posted_to_the_receivers_with_retries() {
+        let (_dir, pool) = test_db().await;
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO scans (id, status, root_paths, options, dir_count, file_count, total_logical_size, \
+             total_allocated_size, warning_count, duration_ms, finished_at) \
+             VALUES (?1, 'done', '[\"/data\"]', '{}', 3, 7, 1000, 4096, 1, 250, '2026-01-02T03:04:05Z')",
+        )
+        .bind(id.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (flaky_url, flaky) = receiver(2).await;
+        let (failed_only_url, failed_only) = receiver(0).await;
+        let config = WebhooksConfig {
+            endpoints: vec![
+                WebhookEndpointConfig {
+                    url: flaky_url,
+                    events: vec!["done".into()],
+                    secret: Some("s3cret".into()),
+                },
+                WebhookEndpointConfig { url: failed_only_url, events: vec!["failed".into()], secret: None },
+            ],
+            timeout_secs: 5,
+            max_retries: 3,
+            retry_backoff_ms: 10,
+        };
+        notify_scan_finished(&pool, id, Some("ignored for done scans".into()), &config).await;
+
+        // Two failed attempts, then the delivery that succeeds; no further retries
+        wait_for(&flaky, 3).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let requests = flaky.lock().unwrap().clone();
+        assert_eq!(requests.len(), 3);
+        assert!(failed_only.lock().unwrap().is_empty());
+
+        let (headers, body) = &requests[2];
+        assert_eq!(headers[EVENT_HEADER], "done");
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers[SIGNATURE_HEADER], signature("s3cret", body).as_str());
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "event": "done",
+                "scan_id": id,
+                "status": "done",
+                "root_paths": ["/data"],
+                "total_dirs": 3,
+                "total_files": 7,
+                "total_logical_size": 1000,
+                "total_allocated_size": 4096,
+                "warning_count": 1,
+                "duration_ms": 250,
+                "started_at": payload["started_at"],
+                "finished_at": "2026-01-02T03:04:05Z",
+                "error": null,
+            })
+        );
+        assert!(requests.iter().all(|(_, b)| b == body));
+    }
+
+    #[test]
+    fn signatures_match_a_known_hmac() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(event_for_status("partial"), Some("canceled"));
+        assert_eq!(event_for_status("running"), None);
+    }
+}
@@ -7,15 +7,23 @@
 //! ## Features
 //!
 //! - **Type-safe API calls**: All functions return strongly-typed results
-//! - **Error handling**: Network errors are converted to user-friendly messages
-//! - **SSE support**: Real-time event streaming for scan progress
+//! - **Error handling**: Network errors are converted to user-friendly messages;
+//!   the read requests of the scan page fail with a typed [`ApiError`]
+//! - **Retries**: Those reads are repeated with jittered backoff when rate
+//!   limited or when the backend is briefly unreachable
+//! - **SSE support**: Real-time event streaming for scan progress, falling back
+//!   to a WebSocket when the EventSource keeps failing
 //! - **Query parameter handling**: Automatic URL encoding for complex queries
 
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value as JsonValue;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::closure::Closure;
-use web_sys::{EventSource, MessageEvent};
+use web_sys::{EventSource, MessageEvent, WebSocket};
 
 use crate::types::*;
 
@@ -36,6 +44,134 @@ pub const BASE: &str = "";
 /// The complete URL for the API request
 fn url(path: &str) -> String { format!("{}{}", BASE, path) }
 
+/// Attempts of a retried read request, the first one included.
+const MAX_ATTEMPTS: u32 = 3;
+/// Backoff before the second attempt; it doubles with every further attempt.
+const RETRY_BASE_MS: u32 = 400;
+/// Rate limits asking to wait longer than this are reported instead of waited out.
+const MAX_RETRY_AFTER_SECS: u64 = 5;
+
+/// A failed API request, parsed from the backend's error body
+/// (`{"error": {"code", "message", "details"}}`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// The rate limiter rejected the request (429); retry after that many seconds.
+    RateLimited { retry_after: u64 },
+    /// The scan or path does not exist (404), with the backend's message.
+    NotFound(String),
+    /// Any other error status, with the backend's message.
+    ServerError { status: u16, message: String },
+    /// The backend was not reachable or its answer could not be read.
+    Network(String),
+}
+
+impl ApiError {
+    /// Builds the error of a response with a non-success `status` from its body.
+    ///
+    /// Bodies that are not the backend's error JSON (e.g. from a proxy) become
+    /// the message as they are.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let json = serde_json::from_str::<JsonValue>(body).ok();
+        let error = json.as_ref().and_then(|v| v.get("error"));
+        let message = match error.and_then(|e| e.get("message")).and_then(JsonValue::as_str) {
+            Some(msg) => msg.to_string(),
+            None if body.trim().is_empty() => "Serverfehler".to_string(),
+            None => body.trim().to_string(),
+        };
+        match status {
+            429 => {
+                // The rate limiter puts the seconds next to `error`, handlers into `error.details`
+                let retry_after = json
+                    .as_ref()
+                    .and_then(|v| v.get("retry_after_seconds"))
+                    .or_else(|| error?.get("details")?.get("retry_after_seconds"))
+                    .and_then(JsonValue::as_u64)
+                    .unwrap_or(1);
+                ApiError::RateLimited { retry_after }
+            }
+            404 => ApiError::NotFound(message),
+            _ => ApiError::ServerError { status, message },
+        }
+    }
+
+    /// The seconds to wait before trying again, for rate limits only.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ApiError::RateLimited { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    fn network(e: reqwasm::Error) -> Self { ApiError::Network(e.to_string()) }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::RateLimited { retry_after } => {
+                write!(f, "Zu viele Anfragen (429). Bitte nach {} Sekunden erneut versuchen.", retry_after)
+            }
+            ApiError::NotFound(msg) => write!(f, "Nicht gefunden: {}", msg),
+            ApiError::ServerError { status: 403, message } => write!(f, "Schreibgeschützt: {}", message),
+            ApiError::ServerError { status, message } => write!(f, "{} (HTTP {})", message, status),
+            ApiError::Network(msg) => write!(f, "Netzwerkfehler: {}", msg),
+        }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(e: ApiError) -> Self { e.to_string() }
+}
+
+/// The wait in milliseconds before attempt `attempt + 1`, or `None` if `err`
+/// is not worth another attempt.
+///
+/// Only rate limits and network errors are retried. The exponential backoff
+/// gets up to 50 % jitter from `random` (in `[0, 1)`), so tabs that failed
+/// together do not retry in lockstep; a rate limit waits at least its `retry_after`.
+fn retry_delay_ms(err: &ApiError, attempt: u32, random: f64) -> Option<u32> {
+    if attempt >= MAX_ATTEMPTS {
+        return None;
+    }
+    let backoff = RETRY_BASE_MS << (attempt - 1);
+    let delay = backoff + (f64::from(backoff) * 0.5 * random) as u32;
+    match err {
+        ApiError::Network(_) => Some(delay),
+        ApiError::RateLimited { retry_after } if *retry_after <= MAX_RETRY_AFTER_SECS => {
+            Some(delay.max(*retry_after as u32 * 1000))
+        }
+        _ => None,
+    }
+}
+
+/// Sends a GET request and decodes the JSON answer, retrying as [`retry_delay_ms`] allows.
+///
+/// Only for reads, which are safe to repeat; mutations are sent once.
+async fn get_json<T: DeserializeOwned>(path: &str) -> Result<T, ApiError> {
+    let mut attempt = 1;
+    loop {
+        let err = match get_json_once(path).await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        match retry_delay_ms(&err, attempt, js_sys::Math::random()) {
+            Some(ms) => gloo_timers::future::TimeoutFuture::new(ms).await,
+            None => return Err(err),
+        }
+        attempt += 1;
+    }
+}
+
+async fn get_json_once<T: DeserializeOwned>(path: &str) -> Result<T, ApiError> {
+    let resp = reqwasm::http::Request::get(&url(path)).send().await.map_err(ApiError::network)?;
+    if !resp.ok() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(ApiError::from_response(status, &body));
+    }
+    resp.json().await.map_err(ApiError::network)
+}
+
 /// Retrieves a list of all scans from the backend.
 ///
 /// # Returns
@@ -49,15 +185,35 @@ pub async fn list_scans() -> Result<Vec<ScanSummary>, String> {
 
 /// Retrieves information about available storage drives.
 ///
+/// # Arguments
+///
+/// * `detailed` - Also fetch volume label, filesystem and BitLocker state (slower)
+///
 /// # Returns
 ///
 /// * `Result<DrivesResponse, String>` - Drive information or an error message
-pub async fn list_drives() -> Result<DrivesResponse, String> {
-    let resp = reqwasm::http::Request::get(&url("/drives")).send().await.map_err(map_net)?;
+pub async fn list_drives(detailed: bool) -> Result<DrivesResponse, String> {
+    let path = if detailed { "/drives?detailed=true" } else { "/drives" };
+    let resp = reqwasm::http::Request::get(&url(path)).send().await.map_err(map_net)?;
     if !resp.ok() { return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into())); }
     resp.json().await.map_err(map_net)
 }
 
+/// Lists the direct children of a directory without scanning it.
+///
+/// Not retried: the Home page asks again as soon as the typed path changes.
+///
+/// # Arguments
+///
+/// * `path` - The directory typed by the user
+///
+/// # Returns
+///
+/// * `Result<DirPreview, ApiError>` - The children, or the error of the directory (403 if access is denied)
+pub async fn preview_path(path: &str) -> Result<DirPreview, ApiError> {
+    get_json_once(&format!("/preview?path={}", urlencoding::encode(path))).await
+}
+
 /// Checks if the backend server is healthy and responding.
 ///
 /// # Returns
@@ -132,18 +288,16 @@ pub async fn create_scan(req: &CreateScanReq) -> Result<CreateScanResp, String>
 ///
 /// # Returns
 ///
-/// * `Result<ScanSummary, String>` - The scan summary containing detailed information
-///   about the scan, or an error message if the scan doesn't exist or the request failed
+/// * `Result<ScanSummary, ApiError>` - The scan summary containing detailed information
+///   about the scan, or `NotFound` if the scan doesn't exist
 ///
 /// # Notes
 ///
 /// - Can be used to check the current progress of an ongoing scan
 /// - Returns complete metadata for completed scans
-/// - Invalid scan IDs will result in an error
-pub async fn get_scan(id: &str) -> Result<ScanSummary, String> {
-    let resp = reqwasm::http::Request::get(&url(&format!("/scans/{}", id))).send().await.map_err(map_net)?;
-    if !resp.ok() { return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into())); }
-    resp.json().await.map_err(map_net)
+/// - Rate limits and network errors are retried
+pub async fn get_scan(id: &str) -> Result<ScanSummary, ApiError> {
+    get_json(&format!("/scans/{}", id)).await
 }
 
 /// Cancels an ongoing scan and optionally purges its data.
@@ -201,7 +355,7 @@ pub struct TreeQuery {
 ///
 /// # Returns
 ///
-/// * `Result<Vec<NodeDto>, String>` - A vector of directory tree nodes or an error message
+/// * `Result<Vec<NodeDto>, ApiError>` - A vector of directory tree nodes or the error
 ///
 /// # Notes
 ///
@@ -210,16 +364,15 @@ pub struct TreeQuery {
 /// - Use `depth` to limit how deep the tree traversal goes
 /// - Results can be sorted by various criteria using the `sort` parameter
 /// - The `limit` parameter helps control response size for large directories
-pub async fn get_tree(id: &str, q: &TreeQuery) -> Result<Vec<NodeDto>, String> {
+/// - Rate limits and network errors are retried
+pub async fn get_tree(id: &str, q: &TreeQuery) -> Result<Vec<NodeDto>, ApiError> {
     let mut qs = vec![];
     if let Some(p) = &q.path { qs.push(format!("path={}", urlencoding::encode(p))); }
     if let Some(d) = q.depth { qs.push(format!("depth={}", d)); }
     if let Some(s) = &q.sort { qs.push(format!("sort={}", urlencoding::encode(s))); }
     if let Some(l) = q.limit { qs.push(format!("limit={}", l)); }
     let qstr = if qs.is_empty() { String::new() } else { format!("?{}", qs.join("&")) };
-    let resp = reqwasm::http::Request::get(&url(&format!("/scans/{}/tree{}", id, qstr))).send().await.map_err(map_net)?;
-    if !resp.ok() { return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into())); }
-    resp.json().await.map_err(map_net)
+    get_json(&format!("/scans/{}/tree{}", id, qstr)).await
 }
 
 /// Query parameters for retrieving top items from a scan.
@@ -231,7 +384,13 @@ pub struct TopQuery {
     /// Scope to limit the search (path or directory, root if not specified)
     pub scope: Option<String>,
     /// Maximum number of top items to return
-    pub limit: Option<i64>
+    pub limit: Option<i64>,
+    /// Only rank items below this directory (whole scan if not specified)
+    pub path: Option<String>,
+    /// Levels below `path` to include, 1 for direct children only
+    pub max_depth_relative: Option<i64>,
+    /// Minimum allocated size in bytes
+    pub min_size: Option<i64>
 }
 
 /// Retrieves the largest items from a scan.
@@ -246,22 +405,24 @@ pub struct TopQuery {
 ///
 /// # Returns
 ///
-/// * `Result<Vec<TopItem>, String>` - A vector of top items sorted by size or an error message
+/// * `Result<Vec<TopItem>, ApiError>` - A vector of top items sorted by size or the error
 ///
 /// # Notes
 ///
 /// - Results are sorted by size in descending order (largest first)
-/// - Use the `scope` parameter to limit analysis to a specific directory
+/// - `scope` selects directories or files, `path` limits analysis to a specific directory
 /// - The `limit` parameter controls how many top items to return
 /// - Useful for identifying which files and directories consume the most space
-pub async fn get_top(id: &str, q: &TopQuery) -> Result<Vec<TopItem>, String> {
+/// - Rate limits and network errors are retried
+pub async fn get_top(id: &str, q: &TopQuery) -> Result<Vec<TopItem>, ApiError> {
     let mut qs = vec![];
     if let Some(s) = &q.scope { qs.push(format!("scope={}", urlencoding::encode(s))); }
     if let Some(l) = q.limit { qs.push(format!("limit={}", l)); }
+    if let Some(p) = &q.path { qs.push(format!("path={}", urlencoding::encode(p))); }
+    if let Some(d) = q.max_depth_relative { qs.push(format!("max_depth_relative={}", d)); }
+    if let Some(m) = q.min_size { qs.push(format!("min_size={}", m)); }
     let qstr = if qs.is_empty() { String::new() } else { format!("?{}", qs.join("&")) };
-    let resp = reqwasm::http::Request::get(&url(&format!("/scans/{}/top{}", id, qstr))).send().await.map_err(map_net)?;
-    if !resp.ok() { return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into())); }
-    resp.json().await.map_err(map_net)
+    get_json(&format!("/scans/{}/top{}", id, qstr)).await
 }
 
 /// Query parameters for retrieving a paginated list of items from a scan.
@@ -279,13 +440,23 @@ pub struct ListQuery {
     /// Maximum number of items to return per page
     pub limit: Option<i64>,
     /// Number of items to skip for pagination
-    pub offset: Option<i64>
+    pub offset: Option<i64>,
+    /// Minimum allocated size in bytes
+    pub min_size: Option<i64>,
+    /// Maximum allocated size in bytes
+    pub max_size: Option<i64>,
+    /// Only items modified at or after this Unix timestamp
+    pub modified_after: Option<i64>,
+    /// Only items modified at or before this Unix timestamp
+    pub modified_before: Option<i64>,
+    /// Item kind ("dirs", "files" or "all")
+    pub kind: Option<String>,
 }
 
 /// Retrieves a paginated list of items from a scan.
 ///
 /// Fetches a flat listing of files and directories with comprehensive filtering,
-/// sorting, and pagination options. Handles rate limiting with proper error messages.
+/// sorting, and pagination options. Rate limits and network errors are retried.
 ///
 /// # Arguments
 ///
@@ -294,7 +465,7 @@ pub struct ListQuery {
 ///
 /// # Returns
 ///
-/// * `Result<Vec<ListItem>, String>` - A vector of list items or an error message
+/// * `Result<Vec<ListItem>, ApiError>` - A vector of list items or the error
 ///
 /// # Notes
 ///
@@ -302,36 +473,99 @@ pub struct ListQuery {
 /// - Results can be sorted by various fields using the `sort` parameter
 /// - Sort order can be controlled with the `order` parameter ("asc" or "desc")
 /// - Use the `path` parameter to list items from a specific directory
-/// - Handles HTTP 429 rate limiting with informative error messages
+/// - A rate limit that asks to wait more than a few seconds ends as `RateLimited`
 /// - Returns flat listings, not hierarchical tree structures
-pub async fn get_list(id: &str, q: &ListQuery) -> Result<Vec<ListItem>, String> {
+pub async fn get_list(id: &str, q: &ListQuery) -> Result<Vec<ListItem>, ApiError> {
+    fetch_list(id, list_query_params(q)).await
+}
+
+/// Retrieves a page of items like [`get_list`] together with the number and
+/// sizes of all items matching the query.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier of the scan to query
+/// * `q` - A `ListQuery` containing path, sorting, and pagination parameters
+///
+/// # Returns
+///
+/// * `Result<ListResponse, ApiError>` - The page and totals or the error
+pub async fn get_list_with_totals(id: &str, q: &ListQuery) -> Result<ListResponse, ApiError> {
+    let mut qs = list_query_params(q);
+    qs.push("with_totals=true".to_string());
+    fetch_list(id, qs).await
+}
+
+fn list_query_params(q: &ListQuery) -> Vec<String> {
     let mut qs = vec![];
     if let Some(p) = &q.path { qs.push(format!("path={}", urlencoding::encode(p))); }
     if let Some(s) = &q.sort { qs.push(format!("sort={}", urlencoding::encode(s))); }
     if let Some(o) = &q.order { qs.push(format!("order={}", urlencoding::encode(o))); }
     if let Some(l) = q.limit { qs.push(format!("limit={}", l)); }
     if let Some(o) = q.offset { qs.push(format!("offset={}", o)); }
+    if let Some(s) = q.min_size { qs.push(format!("min_size={}", s)); }
+    if let Some(s) = q.max_size { qs.push(format!("max_size={}", s)); }
+    if let Some(t) = q.modified_after { qs.push(format!("modified_after={}", t)); }
+    if let Some(t) = q.modified_before { qs.push(format!("modified_before={}", t)); }
+    if let Some(k) = &q.kind { qs.push(format!("kind={}", urlencoding::encode(k))); }
+    qs
+}
+
+/// Query parameters for the server-side export of a scan.
+///
+/// The backend produces the file, so downloads use the same filtering,
+/// ordering and RFC 4180 quoting as every other export client.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportQuery {
+    /// Export format ("csv", "json" or "ndjson")
+    pub format: String,
+    /// Records to export ("nodes", "files" or "all")
+    pub scope: Option<String>,
+    /// Maximum number of records to export
+    pub limit: Option<i64>,
+    /// Comma-separated CSV columns (e.g. "path,allocated_size,mtime")
+    pub columns: Option<String>,
+    /// CSV separator ("comma" or "semicolon")
+    pub sep: Option<String>,
+    /// Subtree to export (whole scan if not specified)
+    pub path: Option<String>,
+    /// Minimum allocated size in bytes
+    pub min_size: Option<i64>,
+    /// Order of the directories ("path" or "size")
+    pub sort: Option<String>,
+    /// Unit of the CSV size columns ("bytes", "kib", "mib", "gib" or "auto")
+    pub units: Option<String>,
+    /// Decimal separator of the CSV size columns ("en" or "de")
+    pub locale: Option<String>,
+}
+
+/// Builds the URL of the export endpoint, suitable for a browser download.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier of the scan to export
+/// * `q` - The export parameters
+///
+/// # Returns
+///
+/// * `String` - The URL of `GET /scans/{id}/export` with the query string
+pub fn export_url(id: &str, q: &ExportQuery) -> String {
+    let mut qs = vec![format!("format={}", urlencoding::encode(&q.format))];
+    if let Some(s) = &q.scope { qs.push(format!("scope={}", urlencoding::encode(s))); }
+    if let Some(l) = q.limit { qs.push(format!("limit={}", l)); }
+    if let Some(c) = &q.columns { qs.push(format!("columns={}", urlencoding::encode(c))); }
+    if let Some(s) = &q.sep { qs.push(format!("sep={}", urlencoding::encode(s))); }
+    if let Some(p) = &q.path { qs.push(format!("path={}", urlencoding::encode(p))); }
+    if let Some(m) = q.min_size { qs.push(format!("min_size={}", m)); }
+    if let Some(s) = &q.sort { qs.push(format!("sort={}", urlencoding::encode(s))); }
+    if let Some(u) = &q.units { qs.push(format!("units={}", urlencoding::encode(u))); }
+    if let Some(l) = &q.locale { qs.push(format!("locale={}", urlencoding::encode(l))); }
+    url(&format!("/scans/{}/export?{}", id, qs.join("&")))
+}
+
+async fn fetch_list<T: DeserializeOwned>(id: &str, qs: Vec<String>) -> Result<T, ApiError> {
     let qstr = if qs.is_empty() { String::new() } else { format!("?{}", qs.join("&")) };
-    let resp = reqwasm::http::Request::get(&url(&format!("/scans/{}/list{}", id, qstr))).send().await.map_err(map_net)?;
-    if !resp.ok() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into());
-        if status == 429 {
-            // Try to parse retry_after_seconds from backend JSON
-            if let Ok(v) = serde_json::from_str::<JsonValue>(&text) {
-                if let Some(sec) = v.get("retry_after_seconds").and_then(|x| x.as_u64()) {
-                    return Err(format!("Zu viele Anfragen (429). Bitte nach {} Sekunden erneut versuchen.", sec));
-                }
-                if let Some(obj) = v.get("error").and_then(|e| e.as_object()) {
-                    if let Some(msg) = obj.get("message").and_then(|m| m.as_str()) {
-                        return Err(format!("Zu viele Anfragen (429). {}", msg));
-                    }
-                }
-            }
-        }
-        return Err(text);
-    }
-    resp.json().await.map_err(map_net)
+    get_json(&format!("/scans/{}/list{}", id, qstr)).await
 }
 
 /// Query parameters for searching items within a scan.
@@ -411,41 +645,112 @@ pub async fn search_scan(id: &str, q: &SearchQuery) -> Result<SearchResult, Stri
 /// A user-friendly error message string describing the network problem
 fn map_net(e: reqwasm::Error) -> String { format!("Netzwerkfehler: {}", e) }
 
+/// Extracts the message of a backend error body (`{"error":{"message":...}}`).
+///
+/// A rejection because the path belongs to a read-only scan (403) is marked as such.
+/// Bodies in another format are returned unchanged.
+fn error_message(status: u16, text: String) -> String {
+    let msg = serde_json::from_str::<JsonValue>(&text)
+        .ok()
+        .and_then(|v| v.get("error")?.get("message")?.as_str().map(str::to_string));
+    match (status, msg) {
+        (403, Some(msg)) => format!("Schreibgeschützt: {}", msg),
+        (_, Some(msg)) => msg,
+        (_, None) => text,
+    }
+}
+
 /// Moves a path to a new location within the file system.
 ///
-/// Performs a move operation to relocate files or directories to a new location.
-/// This operation updates the scan data to reflect the new path structure.
+/// Starts a background move operation and polls it once per second until it
+/// has finished. Closing the page does not stop the move on the server; it can
+/// be looked up again via `get_move_operation`.
 ///
 /// # Arguments
 ///
 /// * `req` - A `MovePathRequest` containing source path, destination path,
 ///   and other move operation parameters
+/// * `on_progress` - Called with every polled state of the running operation
 ///
 /// # Returns
 ///
-/// * `Result<MovePathResponse, String>` - The response containing operation results
-///   and updated path information, or an error message if the move failed
+/// * `Result<MovePathResponse, String>` - The final result of a completed or
+///   cancelled move, or an error message if the move failed
 ///
 /// # Notes
 ///
-/// - The move operation updates scan data to maintain consistency
-/// - Source and destination paths must be within scanned directories
-/// - Moving to an existing location may result in overwriting
 /// - Large directories may take time to process
-/// - The operation is atomic - either fully succeeds or fails completely
-pub async fn move_path(req: &MovePathRequest) -> Result<MovePathResponse, String> {
+/// - A cancelled move keeps already copied files and never deletes its source
+pub async fn move_path<F>(req: &MovePathRequest, mut on_progress: F) -> Result<MovePathResponse, String>
+where F: FnMut(&MoveOperation) {
     let resp = reqwasm::http::Request::post(&url("/paths/move"))
         .header("Content-Type", "application/json")
         .body(serde_json::to_string(req).unwrap())
         .send()
         .await
         .map_err(map_net)?;
+    if !resp.ok() {
+        let status = resp.status();
+        return Err(error_message(status, resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into())));
+    }
+    let mut op: MoveOperation = resp.json().await.map_err(map_net)?;
+    while op.finished_at.is_none() {
+        on_progress(&op);
+        gloo_timers::future::TimeoutFuture::new(1000).await;
+        op = get_move_operation(&op.op_id).await?;
+    }
+    match op.error.take() {
+        Some(err) => Err(err),
+        None => Ok(op.into_response()),
+    }
+}
+
+/// Retrieves the current state of a background move operation.
+///
+/// # Arguments
+///
+/// * `op_id` - The operation ID returned by `POST /paths/move`
+///
+/// # Returns
+///
+/// * `Result<MoveOperation, String>` - The operation, or an error message if it
+///   is unknown (finished operations are kept for an hour)
+pub async fn get_move_operation(op_id: &str) -> Result<MoveOperation, String> {
+    let resp = reqwasm::http::Request::get(&url(&format!("/paths/operations/{}", op_id)))
+        .send()
+        .await
+        .map_err(map_net)?;
     if !resp.ok() {
         return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into()));
     }
     resp.json().await.map_err(map_net)
 }
 
+/// Requests cancellation of a running move operation.
+///
+/// The server stops before the next file; already copied files stay in place.
+///
+/// # Arguments
+///
+/// * `op_id` - The operation ID returned by `POST /paths/move`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok if the request was accepted, or an error message
+pub async fn cancel_move_operation(op_id: &str) -> Result<(), String> {
+    let resp = reqwasm::http::Request::delete(&url(&format!("/paths/operations/{}", op_id)))
+        .send()
+        .await
+        .map_err(map_net)?;
+    if !resp.ok() {
+        return Err(resp.text().await.unwrap_or_else(|_| "HTTP Fehler".into()));
+    }
+    Ok(())
+}
+
+/// Consecutive EventSource errors after which `sse_attach` switches to the WebSocket.
+const SSE_ERRORS_BEFORE_WS: u32 = 2;
+
 /// Establishes a Server-Sent Events (SSE) connection for real-time scan updates.
 ///
 /// Creates an EventSource connection to receive real-time updates about scan progress,
@@ -469,18 +774,26 @@ pub async fn move_path(req: &MovePathRequest) -> Result<MovePathResponse, String
 ///
 /// - The returned EventSource must be kept alive to maintain the connection
 /// - The callback is invoked for each scan event (progress updates, completion, etc.)
-/// - Connection errors will be logged but won't automatically close the EventSource
+/// - If the EventSource errors out twice in a row without a message in between (e.g.
+///   behind a proxy that buffers SSE), it is closed and the events are received
+///   through [`ws_attach`] instead; the callback stays the same
 /// - The closure is intentionally leaked to keep it alive as long as the EventSource
 /// - Use EventSource.close() to clean up the connection when done
 /// - Events are automatically deserialized from JSON into ScanEvent structs
-pub fn sse_attach<F>(id: &str, mut on_message: F) -> Result<EventSource, String>
+pub fn sse_attach<F>(id: &str, on_message: F) -> Result<EventSource, String>
 where F: 'static + FnMut(ScanEvent) {
     let es = EventSource::new(&url(&format!("/scans/{}/events", id))).map_err(|e| format!("SSE Fehler: {:?}", e))?;
+    let on_message = Rc::new(RefCell::new(on_message));
+    let errors = Rc::new(Cell::new(0u32));
+
+    let deliver = on_message.clone();
+    let errors_msg = errors.clone();
     let closure = Closure::<dyn FnMut(web_sys::Event)>::new(move |ev: web_sys::Event| {
+        errors_msg.set(0);
         if let Ok(me) = ev.dyn_into::<MessageEvent>() {
             if let Some(text) = me.data().as_string() {
                 if let Ok(ev) = serde_json::from_str::<ScanEvent>(&text) {
-                    on_message(ev);
+                    (deliver.borrow_mut())(ev);
                 }
             }
         }
@@ -488,5 +801,93 @@ where F: 'static + FnMut(ScanEvent) {
     es.set_onmessage(Some(closure.as_ref().unchecked_ref()));
     // Leak the closure to keep it as long as the EventSource lives (we close ES on drop by the owner)
     closure.forget();
+
+    let es_err = es.clone();
+    let id = id.to_string();
+    let on_error = Closure::<dyn FnMut(web_sys::Event)>::new(move |_ev: web_sys::Event| {
+        errors.set(errors.get() + 1);
+        if errors.get() < SSE_ERRORS_BEFORE_WS {
+            return;
+        }
+        es_err.set_onerror(None);
+        es_err.close();
+        let deliver = on_message.clone();
+        // Nothing left to report to: the callback only takes scan events
+        let _ = ws_attach(&id, move |ev| (deliver.borrow_mut())(ev));
+    });
+    es.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    on_error.forget();
     Ok(es)
 }
+
+/// Receives the events of a scan through `GET /scans/{id}/ws`.
+///
+/// The fallback for proxies that buffer Server-Sent Events. The frames carry
+/// the same JSON events as the SSE stream; the server closes the socket with
+/// the final status of the scan once it ends.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier of the scan to monitor
+/// * `on_message` - A callback function that will be invoked for each `ScanEvent` received
+///
+/// # Returns
+///
+/// * `Result<WebSocket, String>` - The WebSocket, or an error message if it could not be opened
+pub fn ws_attach<F>(id: &str, mut on_message: F) -> Result<WebSocket, String>
+where F: 'static + FnMut(ScanEvent) {
+    let location = web_sys::window().ok_or_else(|| "WebSocket Fehler: kein Fenster".to_string())?.location();
+    let protocol = location.protocol().map_err(|e| format!("WebSocket Fehler: {:?}", e))?;
+    let host = location.host().map_err(|e| format!("WebSocket Fehler: {:?}", e))?;
+    let scheme = if protocol == "https:" { "wss" } else { "ws" };
+    let ws = WebSocket::new(&format!("{}://{}{}", scheme, host, url(&format!("/scans/{}/ws", id))))
+        .map_err(|e| format!("WebSocket Fehler: {:?}", e))?;
+    let closure = Closure::<dyn FnMut(MessageEvent)>::new(move |me: MessageEvent| {
+        if let Some(text) = me.data().as_string() {
+            if let Ok(ev) = serde_json::from_str::<ScanEvent>(&text) {
+                on_message(ev);
+            }
+        }
+    });
+    ws.set_onmessage(Some(closure.as_ref().unchecked_ref()));
+    // The socket stays open without a reference, so the closure has to outlive this function
+    closure.forget();
+    Ok(ws)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_parsed_from_the_backend_body() {
+        let limited = r#"{"error":{"code":"RATE_LIMITED","message":"slow down"},"retry_after_seconds":7,"status":429}"#;
+        assert_eq!(ApiError::from_response(429, limited), ApiError::RateLimited { retry_after: 7 });
+        let in_details = r#"{"error":{"code":"RATE_LIMITED","message":"m","details":{"retry_after_seconds":3}}}"#;
+        assert_eq!(ApiError::from_response(429, in_details).retry_after(), Some(3));
+        assert_eq!(ApiError::from_response(429, "").retry_after(), Some(1));
+
+        let missing = r#"{"error":{"code":"NOT_FOUND","message":"Scan not found"},"status":404}"#;
+        assert_eq!(ApiError::from_response(404, missing), ApiError::NotFound("Scan not found".into()));
+        assert_eq!(
+            ApiError::from_response(502, "Bad Gateway\n"),
+            ApiError::ServerError { status: 502, message: "Bad Gateway".into() }
+        );
+        assert_eq!(ApiError::from_response(500, "").to_string(), "Serverfehler (HTTP 500)");
+        let read_only = r#"{"error":{"code":"FORBIDDEN","message":"read-only scan"}}"#;
+        assert_eq!(ApiError::from_response(403, read_only).to_string(), "Schreibgeschützt: read-only scan");
+    }
+
+    #[test]
+    fn only_rate_limits_and_network_errors_are_retried() {
+        let network = ApiError::Network("offline".into());
+        assert_eq!(retry_delay_ms(&network, 1, 0.0), Some(400));
+        assert_eq!(retry_delay_ms(&network, 2, 0.5), Some(1000));
+        assert_eq!(retry_delay_ms(&network, 3, 0.0), None);
+
+        assert_eq!(retry_delay_ms(&ApiError::RateLimited { retry_after: 2 }, 1, 0.0), Some(2000));
+        assert_eq!(retry_delay_ms(&ApiError::RateLimited { retry_after: 60 }, 1, 0.0), None);
+        assert_eq!(retry_delay_ms(&ApiError::NotFound("x".into()), 1, 0.0), None);
+        assert_eq!(retry_delay_ms(&ApiError::ServerError { status: 500, message: "x".into() }, 1, 0.0), None);
+    }
+}
@@ -31,7 +31,7 @@ use std::rc::Rc;
 mod api;
 mod types;
 mod ui_utils;
-use ui_utils::{fmt_bytes, fmt_ago_short, copy_to_clipboard, download_csv, trigger_download, show_toast};
+use ui_utils::{fmt_bytes, fmt_ago_short, copy_to_clipboard, trigger_download, show_toast};
 
 /// State for the move/copy dialog functionality.
 ///
@@ -52,6 +52,7 @@ struct MoveDialogState {
     in_progress: bool,
     done: bool,
     result: Option<types::MovePathResponse>,
+    progress: Option<types::MoveOperation>,
     error: Option<String>,
 }
 
@@ -116,6 +117,9 @@ fn Home() -> Element {
     let err_scans = use_signal(|| None as Option<String>);
     let err_drives = use_signal(|| None as Option<String>);
     let err_health = use_signal(|| None as Option<String>);
+    let preview = use_signal(|| None as Option<types::DirPreview>);
+    let preview_err = use_signal(|| None as Option<String>);
+    let preview_generation = use_signal(|| 0u32);
 
     // initial laden
     {
@@ -146,7 +150,7 @@ fn Home() -> Element {
                 let mut e_health = e_health.clone();
                 let mut loading_done = loading_done.clone();
                 match api::list_scans().await { Ok(list) => { scans.set(list); e_scans.set(None); }, Err(e) => e_scans.set(Some(e)) }
-                match api::list_drives().await { Ok(dr) => { drives_state.set(dr.items); e_drives.set(None); }, Err(e) => e_drives.set(Some(e)) }
+                match api::list_drives(true).await { Ok(dr) => { drives_state.set(dr.items); e_drives.set(None); }, Err(e) => e_drives.set(Some(e)) }
                 match api::healthz().await { Ok(ok) => { server_state.set(Some(ok)); e_health.set(None); }, Err(e) => e_health.set(Some(e)) }
                 loading_done.set(false);
             });
@@ -178,7 +182,7 @@ fn Home() -> Element {
             let mut ed2 = e_drives.clone();
             let mut eh2 = e_health.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                match api::list_drives().await { Ok(dr) => { d2.set(dr.items); ed2.set(None); }, Err(e) => ed2.set(Some(e)) }
+                match api::list_drives(true).await { Ok(dr) => { d2.set(dr.items); ed2.set(None); }, Err(e) => ed2.set(Some(e)) }
                 match api::healthz().await { Ok(ok) => { h2.set(Some(ok)); eh2.set(None); }, Err(e) => eh2.set(Some(e)) }
             });
         }
@@ -251,10 +255,17 @@ fn Home() -> Element {
                         };
                         let percent = if d.total_bytes > 0 { (used as f64) / (d.total_bytes as f64) * 100.0 } else { 0.0 };
                         let bar_width = format!("width:{:.1}%;", percent);
+                        let title = match d.label.as_deref() {
+                            Some(label) => format!("{} ({})", label, path),
+                            None => path.clone(),
+                        };
+                        let mut type_txt = d.drive_type.clone();
+                        if let Some(fs) = d.filesystem.as_deref() { type_txt = format!("{} · {}", type_txt, fs); }
+                        if d.bitlocker == Some(true) { type_txt.push_str(" · BitLocker"); }
                         rsx!{ div { style: "border:1px solid #222533;background:#0f1117;border-radius:10px;padding:10px;display:flex;flex-direction:column;gap:8px;",
                             div { style: "display:flex;justify-content:space-between;gap:8px;",
-                                div { style: "color:#e5e7eb;", strong { "{path}" } }
-                                span { style: "color:#9aa0a6;", "{d.drive_type}" }
+                                div { style: "color:#e5e7eb;", strong { "{title}" } }
+                                span { style: "color:#9aa0a6;", "{type_txt}" }
                             }
                             div { style: "display:flex;gap:10px;align-items:center;",
                                 span { style: "min-width:80px;color:#a0aec0;", "{fmt_bytes(used as i64)} / {fmt_bytes(d.total_bytes as i64)}" }
@@ -296,31 +307,85 @@ fn Home() -> Element {
             }
             div { class: "input-group",
                 input { class: "form-control", value: "{new_root}", placeholder: "Root-Pfad (z. B. C:\\ oder \\\\server\\share)",
-                    oninput: move |e: Event<FormData>| { let mut new_root2 = new_root.clone(); new_root2.set(e.value().clone()); } }
+                    oninput: move |e: Event<FormData>| {
+                        let value = e.value().clone();
+                        let mut new_root2 = new_root.clone();
+                        new_root2.set(value.clone());
+                        // Vorschau erst nach einer Tipp-Pause laden; Antworten zu älteren Eingaben verwerfen
+                        let mut generation = preview_generation.clone();
+                        let current = generation.read().wrapping_add(1);
+                        generation.set(current);
+                        let mut preview = preview.clone();
+                        let mut preview_err = preview_err.clone();
+                        spawn(async move {
+                            gloo_timers::future::TimeoutFuture::new(400).await;
+                            if *generation.read() != current { return; }
+                            let path = value.trim().to_string();
+                            if path.is_empty() { preview.set(None); preview_err.set(None); return; }
+                            let res = api::preview_path(&path).await;
+                            if *generation.read() != current { return; }
+                            match res {
+                                Ok(p) => { preview.set(Some(p)); preview_err.set(None); }
+                                Err(e) => { preview.set(None); preview_err.set(Some(e.to_string())); }
+                            }
+                        });
+                    } }
                 div { class: "input-group-append",
                     button { class: "btn btn-primary", onclick: start_scan, "Scan starten" }
                     button { class: "btn", onclick: reload, "Aktualisieren" }
                 }
             }
+            { preview_err.read().as_ref().map(|e| rsx!(div { class: "alert alert-error", "Vorschau: {e}" })) }
+            { preview.read().as_ref().map(|p| {
+                let cut = if p.truncated { " (gekürzt)" } else { "" };
+                rsx!{ div { style: "border:1px solid #222533;background:#0f1117;border-radius:10px;padding:10px;margin:8px 0;",
+                    div { class: "text-muted", "Vorschau {p.path}: {p.entries.len()} Einträge, {p.skipped} ausgelassen{cut}" }
+                    ul { class: "list-unstyled", style: "max-height:240px;overflow:auto;margin-top:6px;",
+                        { p.entries.iter().map(|e| {
+                            let size = match e.logical_size { Some(s) => fmt_bytes(s as i64), None => "Ordner".to_string() };
+                            rsx!(li { key: "{e.name}", style: "display:flex;justify-content:space-between;gap:8px;",
+                                span { "{e.name}" }
+                                span { style: "color:#9aa0a6;", "{size}" }
+                            })
+                        }) }
+                    }
+                } }
+            }) }
             ul { class: "list-unstyled",
                 { (scans.read().is_empty() && !home_loading.read().to_owned()).then(|| rsx!(li { class: "text-muted", "Noch keine Scans." })) }
                 { scans.read().iter().map(|s| {
                     let id = s.id.clone();
+                    let mut label = if s.root_paths.is_empty() { id.clone() } else { s.root_paths.join(", ") };
+                    if let Some(origin) = &s.origin {
+                        label = format!("{} (Import von {})", label, origin);
+                    }
                     rsx!{ li { style: "margin:6px 0;",
                         Link { to: Route::Scan { id: id.clone() },
-                            "{id} – {s.status} – Ordner {s.dir_count} – Dateien {s.file_count} – Allokiert {fmt_bytes(s.total_allocated_size)}" }
+                            "{label} – {s.status} – Ordner {s.dir_count} – Dateien {s.file_count} – Allokiert {fmt_bytes(s.total_allocated_size)}" }
                     } }
                 }) }
             }
         }
     }
 }
+/// Fasst die Scan-Optionen zusammen, die Zahlen gegenüber dem Explorer verändern.
+fn describe_options(o: &types::ScanOptions) -> String {
+    let mut parts = vec![if o.include_hidden { "versteckte Dateien gezählt".to_string() } else { "versteckte Dateien ignoriert".to_string() }];
+    if o.follow_symlinks { parts.push("Symlinks verfolgt".into()); }
+    if !o.excludes.is_empty() { parts.push(format!("Ausschlüsse: {}", o.excludes.join(", "))); }
+    if !o.includes.is_empty() { parts.push(format!("nur: {}", o.includes.join(", "))); }
+    if let Some(d) = o.max_depth { parts.push(format!("max. Tiefe {}", d)); }
+    parts.join(" · ")
+}
+
 // ----- Scan-Detailseite mit Live-Log & Tabellen -----
 #[component]
 fn Scan(id: String) -> Element {
     // KPI/Meta und Log
     let kpi = use_signal(|| None as Option<types::ScanSummary>);
     let log = use_signal(|| String::new());
+    // Geschätzter Fortschritt in Prozent, sofern ein früherer Scan derselben Wurzeln existiert
+    let progress = use_signal(|| None as Option<u8>);
 
     // EventSource-Handle, damit die Verbindung lebt
     let es_ref = use_signal(|| None as Option<web_sys::EventSource>);
@@ -329,9 +394,9 @@ fn Scan(id: String) -> Element {
     let tree_items = use_signal(|| Vec::<types::NodeDto>::new());
     let top_items = use_signal(|| Vec::<types::TopItem>::new());
     let list_items = use_signal(|| Vec::<types::ListItem>::new());
-    let err_tree = use_signal(|| None as Option<String>);
-    let err_top = use_signal(|| None as Option<String>);
-    let err_list = use_signal(|| None as Option<String>);
+    let err_tree = use_signal(|| None as Option<api::ApiError>);
+    let err_top = use_signal(|| None as Option<api::ApiError>);
+    let err_list = use_signal(|| None as Option<api::ApiError>);
     let loading_tree = use_signal(|| false);
     let loading_list = use_signal(|| false);
 
@@ -447,7 +512,7 @@ fn Scan(id: String) -> Element {
             let mut drive_targets = drive_targets_state.clone();
             let mut drive_err = drive_err_state.clone();
             spawn(async move {
-                match api::list_drives().await {
+                match api::list_drives(false).await {
                     Ok(dr) => {
                         drive_targets.set(dr.items);
                         drive_err.set(None);
@@ -516,6 +581,7 @@ fn Scan(id: String) -> Element {
                 let qq = api::TopQuery {
                     scope: Some(top_scope),
                     limit: Some(100),
+                    ..Default::default()
                 };
 
                 match api::get_top(&id, &qq).await {
@@ -569,6 +635,7 @@ fn Scan(id: String) -> Element {
                     order: Some(order),
                     limit: Some(limit + 1),
                     offset: Some(offset),
+                    ..Default::default()
                 };
 
                 match api::get_list(&id, &lq).await {
@@ -641,6 +708,7 @@ fn Scan(id: String) -> Element {
                     order: Some(list_order_val),
                     limit: Some(list_limit_val + 1),
                     offset: Some(list_offset_val),
+                    ..Default::default()
                 };
 
                 match api::get_list(&id, &lq).await {
@@ -748,7 +816,7 @@ fn Scan(id: String) -> Element {
                 let mut has_more2 = has_more2.clone();
                 let mut e2 = e2.clone();
                 let mut l2 = l2.clone();
-                let q = api::ListQuery { path: q_path, sort: Some(q_sort), order: Some(q_order), limit: Some(q_limit + 1), offset: Some(q_offset) };
+                let q = api::ListQuery { path: q_path, sort: Some(q_sort), order: Some(q_order), limit: Some(q_limit + 1), offset: Some(q_offset), ..Default::default() };
                 match api::get_list(&id_c, &q).await {
                     Ok(list) => {
                         let has_more = (list.len() as i64) > q_limit;
@@ -776,6 +844,7 @@ fn Scan(id: String) -> Element {
         let id_for_cb = id.clone();
         let kpi = kpi.clone();
         let log_state = log.clone();
+        let progress_state = progress.clone();
         let es_ref_state = es_ref.clone();
         let tree_items_h = tree_items.clone();
         let tree_path_h = tree_path.clone();
@@ -798,6 +867,7 @@ fn Scan(id: String) -> Element {
 
         use_effect(move || {
             let mut log_state_in = log_state.clone();
+            let mut progress_in = progress_state.clone();
             let log_state_err = log_state.clone();
             let es_holder = es_ref_state.clone();
             let id_for_cb = id_for_cb.clone();
@@ -816,15 +886,27 @@ fn Scan(id: String) -> Element {
                 }
                 
                 match &ev {
-                    types::ScanEvent::Started { root_paths } => newlog.push_str(&format!("Started: {}\n", root_paths.join(", "))),
+                    types::ScanEvent::Started { root_paths, .. } => newlog.push_str(&format!("Started: {}\n", root_paths.join(", "))),
                     types::ScanEvent::Progress { current_path, dirs_scanned, files_scanned, allocated_size, .. } => newlog.push_str(&format!("Progress: {} | dirs={} files={} alloc={}\n", current_path, dirs_scanned, files_scanned, fmt_bytes(*allocated_size as i64))),
+                    types::ScanEvent::HashProgress { files_hashed, files_total, bytes_hashed, bytes_total, .. } => newlog.push_str(&format!("Hashing: {}/{} files | {} / {}\n", files_hashed, files_total, fmt_bytes(*bytes_hashed as i64), fmt_bytes(*bytes_total as i64))),
+                    types::ScanEvent::DuplicatesDone { groups, wasted_bytes } => newlog.push_str(&format!("Duplicates: {} groups, {} wasted\n", groups, fmt_bytes(*wasted_bytes as i64))),
+                    types::ScanEvent::Updated { changed_paths, total_files, total_allocated_size, .. } => newlog.push_str(&format!("Updated: {} changes | files={} alloc={}\n", changed_paths, total_files, fmt_bytes(*total_allocated_size as i64))),
                     types::ScanEvent::Warning { path, code, message } => newlog.push_str(&format!("Warning: {} ({}) : {}\n", path, code, message)),
+                    types::ScanEvent::Paused => newlog.push_str("Paused\n"),
+                    types::ScanEvent::Resumed => newlog.push_str("Resumed\n"),
                     types::ScanEvent::Done { .. } => newlog.push_str("Done\n"),
                     types::ScanEvent::Cancelled => newlog.push_str("Cancelled\n"),
+                    types::ScanEvent::Interrupted { resumable } => newlog.push_str(&format!("Interrupted by a backend restart{}\n", if *resumable { " (can be started again)" } else { "" })),
                     types::ScanEvent::Failed { message } => newlog.push_str(&format!("Failed: {}\n", message)),
+                    types::ScanEvent::Heartbeat { .. } => {}
                 }
                 // FIX Bug #2: Remove redundant clone
                 log_state_in.set(newlog);
+                match &ev {
+                    types::ScanEvent::Progress { estimated_percent: Some(p), .. } => progress_in.set(Some(*p)),
+                    types::ScanEvent::Done { .. } if progress_in.read().is_some() => progress_in.set(Some(100)),
+                    _ => {}
+                }
 
                 if let types::ScanEvent::Done { .. } = ev {
                     if list_path_h.read().is_none() {
@@ -845,6 +927,7 @@ fn Scan(id: String) -> Element {
                                 order: Some(order_state.read().clone()),
                                 limit: Some(*limit_state.read()),
                                 offset: Some(0),
+                                ..Default::default()
                             };
                             if let Ok(list) = api::get_list(&id_aut, &q_roots).await {
                                 list_items2.set(list.clone());
@@ -865,6 +948,7 @@ fn Scan(id: String) -> Element {
                                                 order: Some("desc".into()),
                                                 limit: Some(500),
                                                 offset: Some(0),
+                                                ..Default::default()
                                             };
                                             if let Ok(list2) = api::get_list(&id_list2, &q_child).await {
                                                 list_items3.set(list2);
@@ -901,9 +985,10 @@ fn Scan(id: String) -> Element {
                         let id_top = id_for_cb.clone();
                         let top_items2 = top_items_h.clone();
                         let scope = top_scope_h.read().clone();
+                        let top_path = list_path_h.read().clone();
                         wasm_bindgen_futures::spawn_local(async move {
                             let mut top_items2 = top_items2.clone();
-                            let q = api::TopQuery { scope: Some(scope), limit: Some(100) };
+                            let q = api::TopQuery { scope: Some(scope), limit: Some(100), path: top_path, ..Default::default() };
                             if let Ok(list) = api::get_top(&id_top, &q).await {
                                 top_items2.set(list);
                             }
@@ -946,6 +1031,7 @@ fn Scan(id: String) -> Element {
                                     order: Some(q_order_l),
                                     limit: Some(q_limit_l + 1),
                                     offset: Some(q_offset_l),
+                                    ..Default::default()
                                 };
                                 if let Ok(list) = api::get_list(&id_list, &q).await {
                                     let has_more = (list.len() as i64) > q_limit_l;
@@ -1146,6 +1232,15 @@ fn Scan(id: String) -> Element {
         section { class: "panel",
             h2 { "Scan {id}" }
             div { style: "color:#a0aec0;margin:4px 0 8px 0;", "Status: {kpi.read().as_ref().map(|s| s.status.clone()).unwrap_or_else(|| \"...\".into())}" }
+            { kpi.read().as_ref().filter(|s| s.read_only).map(|_| rsx!(
+                div { style: "color:#f6ad55;margin:0 0 8px 0;", "Schreibgeschützt: Verschieben und Löschen unterhalb der Roots ist gesperrt" }
+            )) }
+            { kpi.read().as_ref().filter(|s| s.backup_mode).map(|_| rsx!(
+                div { style: "color:#a0aec0;margin:0 0 8px 0;", "Backup-Modus: mit Sicherungsrechten gescannt, auch Verzeichnisse nur für Administratoren" }
+            )) }
+            { kpi.read().as_ref().and_then(|s| s.options.as_ref().map(|o| (s.root_paths.join(", "), describe_options(o)))).map(|(roots, opts)| rsx!(
+                div { style: "color:#a0aec0;margin:0 0 8px 0;", "Roots: {roots} – {opts}" }
+            )) }
             div { style: "display:flex;gap:12px;flex-wrap:wrap;",
                 button { class: "btn", onclick: cancel, "Abbrechen" }
                 button { class: "btn btn-danger", onclick: purge, "Purge" }
@@ -1231,6 +1326,7 @@ fn Scan(id: String) -> Element {
                             option { value: "allocated", "Allokiert" }
                             option { value: "logical", "Logisch" }
                             option { value: "name", "Name" }
+                            option { value: "name_natural", "Name (natürlich)" }
                             option { value: "type", "Typ" }
                             option { value: "modified", "Änderungsdatum" }
                         }
@@ -1455,39 +1551,28 @@ fn Scan(id: String) -> Element {
                         button { class: "btn btn-primary", r#type: "button", style: btn_primary_style(), disabled: *loading_list.read(), title: if *loading_list.read() { "Laden läuft…" } else { "Nächste Seite laden" }, onclick: next_page, "Nächste Seite" }
                         span { "Seite: {(*list_offset.read() / *list_limit.read()) + 1} (Offset: {*list_offset.read()})" }
                         span { "Einträge (Seite): {list_items.len()}" }
-                        button { class: "btn", onclick: {
-                                let list_items = list_items.clone();
-                                let search_query = search_query.clone();
+                        button { class: "btn", title: "Exportiert den aktuellen Ordner samt Unterordnern mit Größen- und Typfilter", onclick: {
+                                let id_csv = id.clone();
+                                let list_path = list_path.clone();
                                 let min_size_filter = min_size_filter.clone();
                                 let file_type_filter = file_type_filter.clone();
-                                let show_hidden = show_hidden.clone();
                                 move |_| {
-                                    let mut csv = String::from("type,name,path,allocated,logical,mtime\n");
-                                    let query_val = search_query.read().to_lowercase();
-                                    let min_size_val = min_size_filter.read().clone();
-                                    let type_filter_val = file_type_filter.read().clone();
-                                    let show_hidden_val = *show_hidden.read();
-                                    for it in list_items.read().iter().filter(|it| {
-                                        let name_match = if query_val.is_empty() { true } else { match it { types::ListItem::Dir { name, .. } => name.to_lowercase().contains(&query_val), types::ListItem::File { name, .. } => name.to_lowercase().contains(&query_val), } };
-                                        let size_match = match it { types::ListItem::Dir { allocated_size, .. } => *allocated_size >= min_size_val, types::ListItem::File { allocated_size, .. } => *allocated_size >= min_size_val, };
-                                        let type_match = match type_filter_val.as_str() { "dirs" => matches!(it, types::ListItem::Dir { .. }), "files" => matches!(it, types::ListItem::File { .. }), _ => true };
-                                        let hidden_match = if !show_hidden_val { match it { types::ListItem::Dir { name, .. } => !name.starts_with('.'), types::ListItem::File { name, .. } => !name.starts_with('.'), } } else { true };
-                                        name_match && size_match && type_match && hidden_match
-                                    }) {
-                                        match it {
-                                            types::ListItem::Dir { name, path, allocated_size, logical_size, mtime, .. } => {
-                                                csv.push_str(&format!("dir,\"{}\",\"{}\",{},{},{}\n", name.replace('"', ""), path.replace('"', ""), allocated_size, logical_size, mtime.unwrap_or(0)));
-                                            }
-                                            types::ListItem::File { name, path, allocated_size, logical_size, mtime, .. } => {
-                                                csv.push_str(&format!("file,\"{}\",\"{}\",{},{},{}\n", name.replace('"', ""), path.replace('"', ""), allocated_size, logical_size, mtime.unwrap_or(0)));
-                                            }
-                                        }
-                                    }
-                                    download_csv("speicherwald_list.csv", &csv);
+                                    let scope = match file_type_filter.read().as_str() { "dirs" => "nodes", "files" => "files", _ => "all" };
+                                    let min_size = *min_size_filter.read();
+                                    let q = api::ExportQuery {
+                                        format: "csv".into(),
+                                        scope: Some(scope.into()),
+                                        limit: Some(25_000),
+                                        columns: Some("type,path,allocated_size,logical_size,mtime".into()),
+                                        path: list_path.read().clone(),
+                                        min_size: (min_size > 0).then_some(min_size),
+                                        ..Default::default()
+                                    };
+                                    trigger_download(&api::export_url(&id_csv, &q), Some("speicherwald_list.csv"));
                                 }
                             }, "CSV export" }
                         { (*loading_list.read()).then(|| rsx!(span { class: "spinner", "" })) }
-                        { err_list.read().as_ref().map(|e| rsx!(span { class: "text-danger", " Fehler: {e}" })) }
+                        { err_list.read().clone().map(|e| rsx!(ErrorBanner { error: e })) }
                         
                         { (!selected_items.read().is_empty()).then(|| {
                             let count = selected_items.read().len();
@@ -1540,6 +1625,7 @@ fn Scan(id: String) -> Element {
                                             in_progress: false,
                                             done: false,
                                             result: None,
+                                            progress: None,
                                             error: None,
                                         }));
                                     },
@@ -1770,6 +1856,7 @@ fn Scan(id: String) -> Element {
                                                                 in_progress: false,
                                                                 done: false,
                                                                 result: None,
+                                                                progress: None,
                                                                 error: None,
                                                             }));
                                                         }
@@ -1876,6 +1963,7 @@ fn Scan(id: String) -> Element {
                                                                 in_progress: false,
                                                                 done: false,
                                                                 result: None,
+                                                                progress: None,
                                                                 error: None,
                                                             }));
                                                         }
@@ -1926,7 +2014,7 @@ fn Scan(id: String) -> Element {
                     label { style: "display:flex;gap:6px;align-items:center;", input { r#type: "checkbox", checked: *live_update.read(), oninput: move |_| { let current = *live_update.read(); let mut live_update = live_update.clone(); live_update.set(!current); } } " Live-Update Tabellen" }
                     span { "Einträge: {tree_items.len()}" }
                     { (*loading_tree.read()).then(|| rsx!(span { class: "spinner", "" })) }
-                    { err_tree.read().as_ref().map(|e| rsx!(span { class: "text-danger", " Fehler: {e}" })) }
+                    { err_tree.read().clone().map(|e| rsx!(ErrorBanner { error: e })) }
                 }
                 h3 { style: "margin-top:16px;", "Baum – Ergebnisse" }
 
@@ -1971,6 +2059,7 @@ fn Scan(id: String) -> Element {
                                         in_progress: false,
                                         done: false,
                                         result: None,
+                                        progress: None,
                                         error: None,
                                     }));
                                 },
@@ -2182,6 +2271,7 @@ fn Scan(id: String) -> Element {
                                                             in_progress: false,
                                                             done: false,
                                                             result: None,
+                                                            progress: None,
                                                             error: None,
                                                         }));
                                                     }
@@ -2204,11 +2294,32 @@ fn Scan(id: String) -> Element {
                     div { style: "display:flex;gap:12px;margin-top:8px;",
                          button { class: "btn", onclick: {
                                 let id_export = id.clone();
-                                move |_| { trigger_download(&id_export, Some("csv")); }
+                                move |_| {
+                                    let q = api::ExportQuery { format: "csv".into(), limit: Some(25_000), ..Default::default() };
+                                    trigger_download(&api::export_url(&id_export, &q), Some(&format!("scan_{}.csv", id_export)));
+                                }
                             }, "CSV Export (Gesamter Scan)" }
                          button { class: "btn", onclick: {
                                 let id_export = id.clone();
-                                move |_| { trigger_download(&id_export, Some("json")); }
+                                move |_| {
+                                    let q = api::ExportQuery {
+                                        format: "csv".into(),
+                                        limit: Some(25_000),
+                                        columns: Some("type,path,allocated_size,logical_size,allocated_bytes".into()),
+                                        sep: Some("semicolon".into()),
+                                        units: Some("auto".into()),
+                                        locale: Some("de".into()),
+                                        ..Default::default()
+                                    };
+                                    trigger_download(&api::export_url(&id_export, &q), Some(&format!("scan_{}_excel.csv", id_export)));
+                                }
+                            }, "CSV Export (Excel, lesbare Größen)" }
+                         button { class: "btn", onclick: {
+                                let id_export = id.clone();
+                                move |_| {
+                                    let q = api::ExportQuery { format: "json".into(), ..Default::default() };
+                                    trigger_download(&api::export_url(&id_export, &q), Some(&format!("scan_{}.json", id_export)));
+                                }
                             }, "JSON Export (Rohdaten)" }
                     }
                 }
@@ -2219,6 +2330,7 @@ fn Scan(id: String) -> Element {
                             let top_scope = top_scope.clone();
                             let top_show = top_show.clone();
                             let top_items = top_items.clone();
+                            let list_path = list_path.clone();
                             move |e: Event<FormData>| {
                             let value = e.value();
                             let mut top_scope = top_scope.clone();
@@ -2227,9 +2339,10 @@ fn Scan(id: String) -> Element {
                             top_show2.set(15);
                             let top_items2 = top_items.clone();
                             let id_top = id.clone();
+                            let top_path = list_path.read().clone();
                             wasm_bindgen_futures::spawn_local(async move {
                                 let mut top_items2 = top_items2.clone();
-                                let q = api::TopQuery { scope: Some(value), limit: Some(100) };
+                                let q = api::TopQuery { scope: Some(value), limit: Some(100), path: top_path, ..Default::default() };
                                 if let Ok(list) = api::get_top(&id_top, &q).await { top_items2.set(list); }
                             });
                         }
@@ -2240,29 +2353,23 @@ fn Scan(id: String) -> Element {
                     button { style: btn_style(), onclick: top_less, "Weniger" }
                     button { style: btn_style(), onclick: top_more, "Mehr" }
                     button { class: "btn", onclick: {
-                            let top_items = top_items.clone();
+                            let top_scope = top_scope.clone();
                             let top_show = top_show.clone();
                             let id_csv = id.clone();
                             move |_| {
-                                // FIX Bug #37: Note that this builds CSV in memory
-                                // For very large exports, consider using server-side streaming
-                                let mut csv = String::from("type,path,allocated,logical,depth,file_count,dir_count\n");
-                                let show_count = *top_show.read();
-                                for it in top_items.read().iter().take(show_count) {
-                                    match it {
-                                        types::TopItem::Dir { path, allocated_size, logical_size, depth, file_count, dir_count, .. } => {
-                                            csv.push_str(&format!("dir,\"{}\",{},{},{},{},{}\n", path.replace('"', ""), allocated_size, logical_size, depth, file_count, dir_count));
-                                        }
-                                        types::TopItem::File { path, allocated_size, logical_size, .. } => {
-                                            csv.push_str(&format!("file,\"{}\",{},{},,,\n", path.replace('"', ""), allocated_size, logical_size));
-                                        }
-                                    }
-                                }
-                                let fname = format!("speicherwald_top_{}.csv", id_csv);
-                                download_csv(&fname, &csv);
+                                let files = top_scope.read().as_str() == "files";
+                                let q = api::ExportQuery {
+                                    format: "csv".into(),
+                                    scope: Some(if files { "files" } else { "nodes" }.into()),
+                                    limit: Some(*top_show.read() as i64),
+                                    columns: Some("type,path,allocated_size,logical_size,depth,file_count,dir_count".into()),
+                                    sort: Some("size".into()),
+                                    ..Default::default()
+                                };
+                                trigger_download(&api::export_url(&id_csv, &q), Some(&format!("speicherwald_top_{}.csv", id_csv)));
                             }
                         }, "CSV export" }
-                    { err_top.read().as_ref().map(|e| rsx!(span { style: "color:#f87171;", " Fehler: {e}" })) }
+                    { err_top.read().clone().map(|e| rsx!(ErrorBanner { error: e })) }
                 }
                 // Visuelle Übersicht (Top-N Balken)
                 div { style: "margin-top:8px;",
@@ -2434,6 +2541,14 @@ fn Scan(id: String) -> Element {
                             "Live Updates" 
                          }
                      }
+                     { progress.read().map(|p| rsx! {
+                         div { style: "display:flex;gap:8px;align-items:center;margin:8px 0;",
+                             div { class: "progress-bar", style: "flex:1;", title: "Geschätzt anhand des letzten Scans derselben Pfade",
+                                 div { class: "progress-bar-fill", style: "width:{p}%;" }
+                             }
+                             span { style: "color:#9aa0a6;font-size:12px;min-width:40px;text-align:right;", "{p}%" }
+                         }
+                     }) }
                      pre { style: "background:#0b0c0f;border:1px solid #222533;border-radius:8px;padding:10px;max-height:600px;overflow:auto;white-space:pre-wrap;font-family:monospace;", "{log}" }
                  }
             }) }
@@ -2442,6 +2557,35 @@ fn Scan(id: String) -> Element {
     }
 }
 
+/// Error banner of a failed scan page request.
+///
+/// A rate limit counts its wait down, so the user knows when a retry will be accepted.
+#[component]
+fn ErrorBanner(error: api::ApiError) -> Element {
+    let mut remaining = use_signal(|| error.retry_after());
+    // Ein neuer Fehler startet den Countdown neu
+    use_effect(use_reactive!(|error| remaining.set(error.retry_after())));
+    use_future(move || async move {
+        loop {
+            gloo_timers::future::TimeoutFuture::new(1_000).await;
+            let left = *remaining.peek();
+            if let Some(secs) = left.filter(|s| *s > 0) {
+                remaining.set(Some(secs - 1));
+            }
+        }
+    });
+    let text = match (&error, remaining()) {
+        (api::ApiError::RateLimited { .. }, Some(0)) => {
+            "Zu viele Anfragen (429). Bitte jetzt erneut versuchen.".to_string()
+        }
+        (api::ApiError::RateLimited { .. }, Some(secs)) => {
+            format!("Zu viele Anfragen (429). Erneut versuchen in {} s …", secs)
+        }
+        _ => error.to_string(),
+    };
+    rsx!(span { class: "text-danger", " Fehler: {text}" })
+}
+
 fn move_dialog_view(
     dialog: &MoveDialogState,
     move_signal: Signal<Option<MoveDialogState>>,
@@ -2455,6 +2599,18 @@ fn move_dialog_view(
     let is_done = dialog.done;
     let is_running = dialog.in_progress;
     let destination_blank = dialog.destination.trim().is_empty();
+    let running_op_id = dialog.progress.as_ref().map(|op| op.op_id.clone());
+    let progress_txt = match dialog.progress.as_ref() {
+        Some(op) if op.status == "cancelling" => "Wird nach der aktuellen Datei abgebrochen ...".to_string(),
+        Some(op) => format!(
+            "Verschiebe Daten ... {} von {} ({} / {} Elemente)",
+            fmt_bytes(op.bytes_moved as i64),
+            fmt_bytes(op.bytes_to_transfer as i64),
+            op.items_done,
+            op.items_total
+        ),
+        None => "Verschiebe Daten ...".to_string(),
+    };
 
     let transfer_size_txt = fmt_bytes(dialog.allocated_size);
     let logical_size_txt = fmt_bytes(dialog.logical_size);
@@ -2686,7 +2842,7 @@ fn move_dialog_view(
                     Some(rsx!{
                         div { style: "display:flex;gap:10px;align-items:center;color:#60a5fa;font-size:13px;padding:10px 12px;border-radius:12px;background:rgba(37,99,235,0.12);border:1px solid rgba(96,165,250,0.35);",
                             span { class: "spinner" }
-                            span { "Verschiebe Daten ..." }
+                            span { "{progress_txt}" }
                         }
                     })
                 } else {
@@ -2728,10 +2884,22 @@ fn move_dialog_view(
                     button {
                         class: "btn",
                         style: "background:transparent;border:1px solid #2d3445;color:#cbd5f5;border-radius:10px;padding:8px 14px;font-size:13px;letter-spacing:0.04em;text-transform:uppercase;",
-                        disabled: is_running,
+                        disabled: is_running && running_op_id.is_none(),
                         onclick: {
                             let close_signal = move_signal.clone();
+                            let running_op_id = running_op_id.clone();
                             move |_| {
+                                // A running move keeps going on the server until it is cancelled there
+                                if is_running {
+                                    if let Some(op_id) = running_op_id.clone() {
+                                        wasm_bindgen_futures::spawn_local(async move {
+                                            if let Err(err) = api::cancel_move_operation(&op_id).await {
+                                                show_toast(&format!("Abbruch fehlgeschlagen: {}", err));
+                                            }
+                                        });
+                                    }
+                                    return;
+                                }
                                 let mut signal = close_signal.clone();
                                 signal.set(None);
                             }
@@ -2759,6 +2927,7 @@ fn move_dialog_view(
                                     inflight.error = None;
                                     inflight.done = false;
                                     inflight.result = None;
+                                    inflight.progress = None;
 
                                     let mut final_sources = Vec::new();
                                     let mut final_destinations = Vec::new();
@@ -2814,17 +2983,26 @@ fn move_dialog_view(
                                         let selected_items_async = selected_items.clone();
 
                                         async move {
-                                            match api::move_path(&request).await {
+                                            let progress_signal = move_signal_async.clone();
+                                            let progress_state = inflight_state.clone();
+                                            let on_progress = move |op: &types::MoveOperation| {
+                                                let mut updated = progress_state.clone();
+                                                updated.progress = Some(op.clone());
+                                                let mut progress_signal = progress_signal.clone();
+                                                progress_signal.set(Some(updated));
+                                            };
+                                            match api::move_path(&request, on_progress).await {
                                                 Ok(resp) => {
                                                     let mut updated = inflight_state.clone();
                                                     updated.in_progress = false;
                                                     updated.done = true;
+                                                    let cancelled = resp.status == "cancelled";
                                                     updated.result = Some(resp);
 
                                                     let mut move_signal_async = move_signal_async.clone();
                                                     move_signal_async.set(Some(updated));
 
-                                                    if request.remove_source {
+                                                    if request.remove_source && !cancelled {
                                                         let mut moved = moved_items.clone();
                                                         let mut current_moved = moved.read().clone();
                                                         let mut selected = selected_items_async.clone();
@@ -2837,9 +3015,13 @@ fn move_dialog_view(
                                                         selected.set(current_sel);
                                                     }
 
-                                                    show_toast("Pfad wurde verschoben");
+                                                    if cancelled {
+                                                        show_toast("Verschieben abgebrochen");
+                                                    } else {
+                                                        show_toast("Pfad wurde verschoben");
+                                                    }
 
-                                                    match api::list_drives().await {
+                                                    match api::list_drives(false).await {
                                                         Ok(dr) => {
                                                             let mut drives_signal_async = drives_signal_async.clone();
                                                             drives_signal_async.set(dr.items);
@@ -2898,5 +3080,5 @@ fn btn_primary_style() -> &'static str {
 //     "width:100%;border-collapse:collapse;margin-top:8px;background:#0f1117;border:1px solid #222533;border-radius:8px;"
 // }
 
-// helper functions (fmt_bytes, copy_to_clipboard, show_toast, download_csv)
+// helper functions (fmt_bytes, copy_to_clipboard, show_toast, trigger_download)
 // are imported from ui_utils module
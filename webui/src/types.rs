@@ -28,6 +28,37 @@ pub struct ScanSummary {
     pub dir_count: i64,
     pub file_count: i64,
     pub warning_count: i64,
+    #[serde(default)]
+    pub resumable: bool,
+    #[serde(default)]
+    pub root_paths: Vec<String>,
+    /// The server an imported scan came from
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// Only filled for a single scan; `None` if the server cannot read them
+    #[serde(default)]
+    pub options: Option<ScanOptions>,
+    /// Path operations refuse to modify anything below the scan's roots
+    #[serde(default)]
+    pub read_only: bool,
+    /// The scan ran with backup privileges (Windows)
+    #[serde(default)]
+    pub backup_mode: bool,
+}
+
+/// The options a scan ran with, as far as the UI shows them.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct ScanOptions {
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub include_hidden: bool,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+    #[serde(default)]
+    pub includes: Vec<String>,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
 }
 
 /// Response containing a list of available drives.
@@ -50,6 +81,15 @@ pub struct DriveInfo {
     pub drive_type: String,
     pub total_bytes: u64,
     pub free_bytes: u64,
+    /// Only filled when requested with `detailed`
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub filesystem: Option<String>,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    #[serde(default)]
+    pub bitlocker: Option<bool>,
 }
 
 /// Response when creating a new scan.
@@ -63,7 +103,10 @@ pub struct CreateScanResp {
     /// Initial status (typically "running")
     pub status: String,
     /// ISO timestamp when the scan was started
-    pub started_at: String
+    pub started_at: String,
+    /// Nested root paths that were dropped, and similar adjustments
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 /// A node in the file system tree structure.
@@ -106,6 +149,18 @@ pub enum ListItem {
     File { name: String, path: String, parent_path: Option<String>, logical_size: i64, allocated_size: i64, mtime: Option<i64>, atime: Option<i64> },
 }
 
+/// A page of a directory listing with totals over all matching items.
+///
+/// Returned by the list endpoint with `with_totals=true`, so the UI can show
+/// how many items and bytes a folder holds beyond the current page.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ListResponse {
+    pub items: Vec<ListItem>,
+    pub total_count: i64,
+    pub total_allocated_size: i64,
+    pub total_logical_size: i64,
+}
+
 /// Results from a file system search operation.
 ///
 /// Contains the items matching a search query along with metadata
@@ -142,20 +197,11 @@ pub enum SearchItem {
     },
 }
 
-/// Real-time events from a running scan.
-///
-/// These events are sent via Server-Sent Events (SSE) to provide
-/// live updates about scan progress, warnings, and completion status.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum ScanEvent {
-    Started { root_paths: Vec<String> },
-    Progress { current_path: String, dirs_scanned: u64, files_scanned: u64, logical_size: u64, allocated_size: u64 },
-    Warning { path: String, code: String, message: String },
-    Done { total_dirs: u64, total_files: u64, total_logical_size: u64, total_allocated_size: u64 },
-    Cancelled,
-    Failed { message: String },
-}
+/// Real-time events from a running scan, shared with the backend.
+pub use speicherwald_types::ScanEvent;
+
+/// The direct children of a directory before it is scanned, shared with the backend.
+pub use speicherwald_types::{DirPreview, PreviewEntry};
 
 /// Request to move or copy a file or directory.
 ///
@@ -188,3 +234,99 @@ pub struct MovePathResponse {
     pub finished_at: String,
     pub warnings: Vec<String>,
 }
+
+/// One source and its destination in a [`MoveBatchRequest`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MoveBatchItem {
+    pub source: String,
+    pub destination: String,
+}
+
+/// Request to move or copy several items via `POST /paths/move-batch`.
+///
+/// Items are given as `items`, or as `sources` moved into `destination_dir`
+/// under their names; both forms can be combined.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct MoveBatchRequest {
+    #[serde(default)]
+    pub items: Vec<MoveBatchItem>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    #[serde(default)]
+    pub remove_source: bool,
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Skip the remaining items after the first failed one
+    #[serde(default)]
+    pub stop_on_error: bool,
+}
+
+/// The state of one item of a move operation.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveItemStatus {
+    Pending,
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+/// The outcome of one item of a move operation, with the reason for
+/// skipped and failed items in `message`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MoveItemResult {
+    pub source: String,
+    pub destination: String,
+    pub status: MoveItemStatus,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Progress of a background move started via `POST /paths/move` or
+/// `POST /paths/move-batch`.
+///
+/// Polled from `GET /paths/operations/{op_id}` until `finished_at` is set;
+/// `status` ends as `completed`, `cancelled` or `failed`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MoveOperation {
+    pub op_id: String,
+    pub status: String,
+    pub sources: Vec<String>,
+    pub destinations: Vec<String>,
+    pub bytes_to_transfer: u64,
+    pub bytes_moved: u64,
+    pub freed_bytes: u64,
+    pub items_total: usize,
+    pub items_done: usize,
+    #[serde(default)]
+    pub current_source: Option<String>,
+    #[serde(default)]
+    pub items: Vec<MoveItemResult>,
+    pub duration_ms: u128,
+    pub started_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl MoveOperation {
+    /// Converts a finished operation into the final move result.
+    pub fn into_response(self) -> MovePathResponse {
+        MovePathResponse {
+            status: self.status,
+            sources: self.sources,
+            destinations: self.destinations,
+            bytes_to_transfer: self.bytes_to_transfer,
+            bytes_moved: self.bytes_moved,
+            freed_bytes: self.freed_bytes,
+            duration_ms: self.duration_ms,
+            started_at: self.started_at,
+            finished_at: self.finished_at.unwrap_or_default(),
+            warnings: self.warnings,
+        }
+    }
+}
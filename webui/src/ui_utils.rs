@@ -315,43 +315,3 @@ pub fn trigger_download(url: &str, suggested_filename: Option<&str>) {
         }
     }
 }
-
-/// Triggers a CSV file download using a data URI.
-///
-/// Creates a data URI for the provided CSV content and triggers a download
-/// with the specified filename. Useful for generating client-side CSV files
-/// without server involvement.
-///
-/// # Arguments
-///
-/// * `filename` - The filename to suggest for the downloaded file
-/// * `content` - The CSV content to download
-///
-/// # Notes
-///
-/// - Uses a data URI with `text/csv;charset=utf-8` MIME type
-/// - Content is URL-encoded to handle special characters and newlines
-/// - Filename should include the .csv extension for proper browser handling
-/// - Generated entirely client-side, no server round-trip required
-/// - Works for CSV content of any reasonable size (browser limitations may apply)
-pub fn download_csv(filename: &str, content: &str) {
-    if let Some(win) = web_sys::window() {
-        if let Some(doc) = win.document() {
-            if let Ok(a) = doc.create_element("a") {
-                let href = format!(
-                    "data:text/csv;charset=utf-8,{}",
-                    urlencoding::encode(content)
-                );
-                let _ = a.set_attribute("href", &href);
-                let _ = a.set_attribute("download", filename);
-                if let Some(body) = doc.body() {
-                    let _ = body.append_child(&a);
-                    if let Some(ae) = a.dyn_ref::<web_sys::HtmlElement>() {
-                        ae.click();
-                    }
-                    let _ = body.remove_child(&a);
-                }
-            }
-        }
-    }
-}